@@ -0,0 +1,219 @@
+//! Offline database maintenance subcommands
+
+use clap::Subcommand;
+use dex_dexvm::DexVmSnapshot;
+use dex_node::{verify::verify_chain, NodeConfig};
+use dex_storage::{
+    rebuild_indexes, BlockReader, DualvmStorage, StateReader, StateWriter, MDBX_DATA_FILE,
+};
+use std::path::{Path, PathBuf};
+
+/// Name of the scratch directory used while compacting
+const COMPACT_DIR: &str = "compact.tmp";
+
+/// `dex-reth db` subcommands
+#[derive(Debug, Subcommand)]
+pub enum DbCommand {
+    /// Print per-table entry counts, page usage and file size
+    Stats,
+    /// Copy-compact the MDBX environment (node must be stopped)
+    Compact,
+    /// Walk the chain from genesis and report the first inconsistent block
+    Verify,
+    /// Recompute the transaction hash index and counter ranking from blocks and counters
+    RebuildIndexes,
+    /// Write all DexVM counters as JSON (to stdout unless --output is given)
+    ExportDexvm {
+        /// Output file
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    /// Load DexVM counters from a JSON export into a fresh database
+    ImportDexvm {
+        /// File written by `export-dexvm`
+        input: PathBuf,
+    },
+}
+
+/// Run a database subcommand against the given datadir
+pub fn run(command: DbCommand, datadir: &Path) -> eyre::Result<()> {
+    match command {
+        DbCommand::Stats => stats(datadir),
+        DbCommand::Compact => compact(datadir),
+        DbCommand::Verify => verify(datadir),
+        DbCommand::RebuildIndexes => rebuild(datadir),
+        DbCommand::ExportDexvm { output } => export_dexvm(datadir, output.as_deref()),
+        DbCommand::ImportDexvm { input } => import_dexvm(datadir, &input),
+    }
+}
+
+fn stats(datadir: &Path) -> eyre::Result<()> {
+    let storage = DualvmStorage::new(datadir)?;
+    let stats = storage.stats()?;
+
+    println!("Database: {}", datadir.display());
+    println!("File size: {} bytes (page size {})", stats.file_size_bytes, stats.page_size);
+    println!("Freelist: {} pages", stats.freelist_pages);
+    println!("{:<22} {:>12} {:>10} {:>10} {:>10} {:>14}", "Table", "Entries", "Branch", "Leaf", "Overflow", "Size");
+    for table in &stats.tables {
+        println!(
+            "{:<22} {:>12} {:>10} {:>10} {:>10} {:>14}",
+            table.name,
+            table.entries,
+            table.branch_pages,
+            table.leaf_pages,
+            table.overflow_pages,
+            table.size_bytes
+        );
+    }
+
+    Ok(())
+}
+
+fn compact(datadir: &Path) -> eyre::Result<()> {
+    if !datadir.join(MDBX_DATA_FILE).exists() {
+        return Err(eyre::eyre!("No database found in {}", datadir.display()));
+    }
+
+    let scratch = datadir.join(COMPACT_DIR);
+    if scratch.exists() {
+        std::fs::remove_dir_all(&scratch)?;
+    }
+
+    let report = {
+        let storage = DualvmStorage::new(datadir)?;
+        storage.compact_into(&scratch)?
+    };
+
+    // Swap the compacted data file in and drop the stale lock file
+    std::fs::rename(scratch.join(MDBX_DATA_FILE), datadir.join(MDBX_DATA_FILE))?;
+    let _ = std::fs::remove_file(datadir.join("mdbx.lck"));
+    std::fs::remove_dir_all(&scratch)?;
+
+    println!(
+        "Compacted {}: {} entries, {} -> {} bytes",
+        datadir.display(),
+        report.entries_copied,
+        report.size_before,
+        report.size_after
+    );
+
+    Ok(())
+}
+
+fn verify(datadir: &Path) -> eyre::Result<()> {
+    let storage = DualvmStorage::new(datadir)?;
+    let report = verify_chain(storage.blocks.as_ref(), storage.state.as_ref());
+
+    match report.divergence {
+        None => {
+            println!(
+                "Verified {} blocks (0..={}) in {}",
+                report.blocks_verified,
+                report.latest_block,
+                datadir.display()
+            );
+            Ok(())
+        }
+        Some((number, divergence)) => {
+            println!(
+                "Verified {} of {} blocks in {}",
+                report.blocks_verified,
+                report.latest_block + 1,
+                datadir.display()
+            );
+            Err(eyre::eyre!("Block {} diverged: {}", number, divergence))
+        }
+    }
+}
+
+fn rebuild(datadir: &Path) -> eyre::Result<()> {
+    if !datadir.join(MDBX_DATA_FILE).exists() {
+        return Err(eyre::eyre!("No database found in {}", datadir.display()));
+    }
+    let storage = DualvmStorage::new(datadir)?;
+
+    for index in rebuild_indexes(&storage)? {
+        println!(
+            "{:<22} {:>12} entries, {} written, {} removed",
+            index.table, index.entries, index.written, index.removed
+        );
+    }
+    Ok(())
+}
+
+fn export_dexvm(datadir: &Path, output: Option<&Path>) -> eyre::Result<()> {
+    let storage = DualvmStorage::new(datadir)?;
+    let snapshot = DexVmSnapshot::from_counters(storage.state.all_counters())
+        .with_labeled_counters(storage.state.all_labeled_counters());
+    let json = serde_json::to_string_pretty(&snapshot)?;
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, json)?;
+            eprintln!("Exported {} DexVM counters to {}", snapshot.counters.len(), path.display());
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+fn import_dexvm(datadir: &Path, input: &Path) -> eyre::Result<()> {
+    let snapshot: DexVmSnapshot = serde_json::from_slice(&std::fs::read(input)?)?;
+    let state = snapshot.to_state().map_err(|e| eyre::eyre!(e))?;
+
+    let storage = DualvmStorage::new(datadir)?;
+    let head = storage.blocks.get_latest_block().map_or(0, |block| block.number);
+    let has_counters = !storage.state.all_counters().is_empty() ||
+        !storage.state.all_labeled_counters().is_empty();
+    if head > 0 || has_counters {
+        return Err(eyre::eyre!(
+            "DexVM state can only be imported into a fresh database ({} is at block {})",
+            datadir.display(),
+            head
+        ));
+    }
+
+    let counters: Vec<_> =
+        snapshot.counters.iter().map(|(address, value)| (*address, *value)).collect();
+    storage.state.set_counters(&counters)?;
+    storage.state.set_labeled_counters(&snapshot.labeled_entries())?;
+
+    println!(
+        "Imported {} DexVM counters into {}, state root {}",
+        snapshot.counters.len(),
+        datadir.display(),
+        state.state_root()
+    );
+
+    Ok(())
+}
+
+/// Replay blocks `from..=to` (up to the stored head without `to`) against a scratch state
+pub fn replay(datadir: &Path, config: NodeConfig, from: u64, to: Option<u64>) -> eyre::Result<()> {
+    let storage = DualvmStorage::open_read_only(datadir)?;
+    let to = to.unwrap_or_else(|| storage.blocks.latest_block_number());
+    let report = dex_node::replay(storage.blocks.as_ref(), config, from, to)?;
+
+    match report.mismatch {
+        None => {
+            println!(
+                "Replayed blocks {}..={} of {}: {} state roots match",
+                report.from,
+                report.to,
+                datadir.display(),
+                report.blocks_matched
+            );
+            Ok(())
+        }
+        Some(mismatch) => {
+            print!("{}", mismatch);
+            Err(eyre::eyre!(
+                "Block {} diverged after {} matching blocks",
+                mismatch.number,
+                report.blocks_matched
+            ))
+        }
+    }
+}
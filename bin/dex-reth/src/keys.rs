@@ -0,0 +1,55 @@
+//! Node identity subcommands
+//!
+//! `keygen` creates a validator key and the node's P2P key, and `enode` prints
+//! the enode URL of an existing P2P key, so multi-node networks can be wired
+//! together (`--bootnodes`, `--trusted-peers`) before any node has started.
+
+use alloy_primitives::hex;
+use dex_node::consensus::secret_key_to_address;
+use dex_p2p::{pk2id, P2pConfig};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::Path,
+};
+
+/// Generate a validator key and a P2P key, saving the P2P key to `key_path`
+///
+/// An existing P2P key is only replaced with `force`, as that changes the
+/// node's identity for every peer that trusts it.
+pub fn keygen(key_path: &Path, host: IpAddr, port: u16, force: bool) -> eyre::Result<()> {
+    if key_path.exists() && !force {
+        return Err(eyre::eyre!(
+            "P2P key {} already exists; pass --force to replace it, or run `dex-reth enode`",
+            key_path.display()
+        ));
+    }
+
+    let validator_key = P2pConfig::random_secret_key();
+    let p2p_key = P2pConfig::random_secret_key();
+    P2pConfig::save_secret_key(&p2p_key, key_path)?;
+
+    println!("Validator key: 0x{}", hex::encode(validator_key.secret_bytes()));
+    println!("Validator address: {}", secret_key_to_address(&validator_key));
+    println!("P2P key: {}", key_path.display());
+    println!("Enode: {}", enode_url(&p2p_key, host, port));
+    Ok(())
+}
+
+/// Print the enode URL of the P2P key at `key_path`
+pub fn enode(key_path: &Path, host: IpAddr, port: u16) -> eyre::Result<()> {
+    if !key_path.exists() {
+        return Err(eyre::eyre!(
+            "No P2P key at {}; run `dex-reth keygen` or start the node once",
+            key_path.display()
+        ));
+    }
+    let key = P2pConfig::load_or_create_secret_key(key_path)?;
+    println!("{}", enode_url(&key, host, port));
+    Ok(())
+}
+
+/// Enode URL peers reach a node with `secret_key` at; IPv6 hosts are bracketed
+pub fn enode_url(secret_key: &secp256k1::SecretKey, host: IpAddr, port: u16) -> String {
+    let peer_id = pk2id(&secret_key.public_key(secp256k1::SECP256K1));
+    dex_p2p::enode_url(peer_id, SocketAddr::new(host, port))
+}
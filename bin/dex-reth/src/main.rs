@@ -2,19 +2,31 @@
 //!
 //! A dual virtual machine blockchain node with EVM and DexVM support.
 
-use alloy_consensus::Header as ConsensusHeader;
-use alloy_primitives::{hex, keccak256, Address, Bloom, B256, B64, U256};
-use alloy_rlp::Decodable;
-use clap::Parser;
-use dex_node::{DualVmNode, PoaConfig};
-use dex_p2p::{P2pConfig, P2pEvent, P2pHandle, P2pService, HashOrNumber, PeerId, SessionCommand};
-use dex_rpc::EvmRpcServer;
-use dex_storage::{BlockStore, StoredBlock};
-use reth_ethereum_primitives::{BlockBody, TransactionSigned};
-use reth_network_peers::TrustedPeer;
+mod db;
+mod keys;
+mod network;
+mod replica;
+mod validate;
+
+use alloy_primitives::Address;
+use clap::{Parser, Subcommand};
+use dex_node::{
+    BlockLimits, FeePolicy, Genesis, InvariantMode, NodeConfig, NodeMode, PoaConfig,
+    StorageRetryPolicy, TraceWriter, WatchdogConfig,
+};
+use dex_p2p::{GenesisPolicy, P2pConfig};
+use network::Network;
 use serde::Deserialize;
-use std::{collections::{HashMap, HashSet}, path::PathBuf, sync::Arc, time::Duration};
-use tokio::sync::RwLock;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use validate::ConfigCheck;
+
+/// Genesis, fee policy and block limits of a genesis file
+type LoadedGenesis = (Genesis, FeePolicy, BlockLimits);
 
 /// dex-reth node command line arguments
 #[derive(Debug, Parser)]
@@ -32,6 +44,14 @@ struct Cli {
     #[clap(long, default_value = "30303")]
     p2p_port: u16,
 
+    /// P2P listen address; an IPv6 address such as `::` listens on IPv6
+    #[clap(long, default_value = "0.0.0.0")]
+    p2p_addr: IpAddr,
+
+    /// Also accept IPv4 peers when --p2p-addr is an IPv6 address
+    #[clap(long)]
+    p2p_dual_stack: bool,
+
     /// Disable P2P networking (P2P is enabled by default)
     #[clap(long, default_value = "false")]
     disable_p2p: bool,
@@ -52,6 +72,11 @@ struct Cli {
     #[clap(long)]
     enable_consensus: bool,
 
+    /// Node mode: "validator", "fullnode" or "rpc-only" (no block production or sync, no P2P).
+    /// Defaults to validator with --enable-consensus, rpc-only with --disable-p2p, else fullnode
+    #[clap(long)]
+    mode: Option<NodeMode>,
+
     /// Validator private key (hex string, with or without 0x prefix)
     /// Default is Hardhat's first test account key (0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266)
     #[clap(long, default_value = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")]
@@ -61,1018 +86,623 @@ struct Cli {
     #[clap(long, default_value = "500")]
     block_interval_ms: u64,
 
-    /// Data directory
+    /// Validator addresses taking turns proposing blocks, comma-separated, in the same order on
+    /// every node; synced headers must be signed by the validator in turn (default: the address
+    /// of --validator-key is the only validator)
+    #[clap(long, value_delimiter = ',')]
+    validators: Vec<String>,
+
+    /// Data directory root; each chain uses its own `<datadir>/<chain_id>` subdirectory
     #[clap(long, default_value = "./data")]
     datadir: PathBuf,
 
-    /// Maximum number of P2P peers
-    #[clap(long, default_value = "50")]
-    max_peers: usize,
-}
+    /// Keep the database in memory and use a throwaway P2P key; nothing is written to
+    /// --datadir and all chain data is lost on exit
+    #[clap(long)]
+    ephemeral: bool,
 
-/// Genesis file format
-#[derive(Debug, Deserialize)]
-struct GenesisFile {
-    config: GenesisConfig,
-    alloc: HashMap<Address, AccountAlloc>,
-}
+    /// Serve dev-only RPC: `dex_resetAccount` rewrites account state; never use on a shared chain
+    #[clap(long)]
+    dev: bool,
 
-#[derive(Debug, Deserialize)]
-struct GenesisConfig {
-    #[serde(rename = "chainId")]
-    chain_id: u64,
-}
+    /// Named network preset: "dev" (chain 1) or "local" (chain 13337 with the bundled genesis)
+    #[clap(long)]
+    network: Option<Network>,
 
-#[derive(Debug, Deserialize)]
-struct AccountAlloc {
-    balance: String,
-}
+    /// Retries for a failed block write before block production stops (0 stops immediately)
+    #[clap(long, default_value = "3")]
+    storage_retries: u32,
 
-/// Block sync manager for fullnode mode
-struct BlockSyncManager {
-    /// P2P handle for sending requests
-    p2p_handle: P2pHandle,
-    /// Block store for checking/storing blocks
-    block_store: Arc<BlockStore>,
-    /// Blocks we're currently requesting headers for
-    pending_header_requests: HashSet<u64>,
-    /// Headers received, waiting for bodies (block_number -> header)
-    pending_body_requests: HashMap<u64, ConsensusHeader>,
-    /// Track which peer we requested from (for bodies)
-    request_peer: Option<PeerId>,
-    /// Track known peer head heights for active sync
-    peer_heads: HashMap<PeerId, u64>,
-}
+    /// Delay between block write retries (milliseconds)
+    #[clap(long, default_value = "100")]
+    storage_retry_delay_ms: u64,
 
-impl BlockSyncManager {
-    fn new(p2p_handle: P2pHandle, block_store: Arc<BlockStore>) -> Self {
-        Self {
-            p2p_handle,
-            block_store,
-            pending_header_requests: HashSet::new(),
-            pending_body_requests: HashMap::new(),
-            request_peer: None,
-            peer_heads: HashMap::new(),
-        }
-    }
+    /// Per-transaction execution limit (milliseconds); slower transactions are dropped from
+    /// the block and quarantined (0 disables the limit)
+    #[clap(long, default_value = "250")]
+    tx_timeout_ms: u64,
 
-    /// Request initial sync from a peer when connected
-    async fn request_initial_sync(&mut self, peer_id: PeerId) {
-        let our_latest = self.block_store.latest_block_number();
+    /// Log the hash, sender and gas of included transactions that execute for longer than
+    /// this (milliseconds; 0 disables)
+    #[clap(long, default_value = "50")]
+    slow_tx_threshold_ms: u64,
 
-        // Request headers starting from our latest block + 1
-        // Use a larger batch size for initial sync (up to 512 headers)
-        let start_block = our_latest + 1;
-        let count = 512u64; // Request more headers at once
+    /// Give addresses an empty EVM account when a block first sets their DexVM counter; changes
+    /// the EVM state root, so replay a chain with the setting its validator used
+    #[clap(long)]
+    create_evm_accounts: bool,
 
-        // Only request if we don't have pending requests
-        if !self.pending_header_requests.is_empty() {
-            tracing::debug!("Skipping initial sync request, already have pending requests");
-            return;
-        }
+    /// Post-block invariant checks (balances vs. fees, nonces, counters, gas): "off", "log"
+    /// errors, or "halt" block production; defaults to "halt" in debug builds, "off" in release
+    #[clap(long, default_value_t = InvariantMode::default())]
+    check_invariants: InvariantMode,
 
-        tracing::info!(
-            "Requesting initial sync from peer {}: starting at block {}",
-            peer_id, start_block
-        );
+    /// Block intervals a validator may go without finalizing a block before it logs an error
+    /// and reports itself degraded on `/health` (0 disables the watchdog)
+    #[clap(long, default_value_t = dex_node::DEFAULT_STALL_INTERVALS)]
+    stall_intervals: u32,
 
-        // Mark blocks as pending
-        for block_num in start_block..start_block + count {
-            self.pending_header_requests.insert(block_num);
-        }
-        self.request_peer = Some(peer_id);
+    /// Restart the consensus task when block production stalls or the task exits
+    #[clap(long)]
+    restart_stalled_consensus: bool,
 
-        let cmd = SessionCommand::GetBlockHeaders {
-            peer_id,
-            start: start_block,
-            count,
-        };
-        if let Err(e) = self.p2p_handle.send_command(cmd).await {
-            tracing::warn!("Failed to send initial sync request: {}", e);
-            // Clear pending on error
-            self.pending_header_requests.clear();
-        }
-    }
+    /// Write a JSON trace of every produced block (per-transaction outcome, gas, balance and
+    /// nonce changes, counter changes, accessed state); slows block production down
+    #[clap(long)]
+    trace_blocks: bool,
 
-    /// Handle NewBlockHash event - request headers if we don't have the block
-    async fn handle_new_block_hash(&mut self, peer_id: PeerId, _hash: B256, number: u64) {
-        // Track the peer's head height
-        self.peer_heads.insert(peer_id, number);
+    /// Block trace directory; defaults to `<datadir>/<chain_id>/traces`
+    #[clap(long)]
+    trace_dir: Option<PathBuf>,
 
-        // Check if we already have this block
-        if self.block_store.get_block_by_number(number).is_some() {
-            tracing::debug!("Already have block {}, skipping sync", number);
-            return;
-        }
+    /// Newest block traces kept in --trace-dir, older ones are deleted (0 keeps all)
+    #[clap(long, default_value_t = dex_node::DEFAULT_TRACE_FILES)]
+    trace_keep: usize,
 
-        // Check if we're already requesting this block
-        if self.pending_header_requests.contains(&number) || self.pending_body_requests.contains_key(&number) {
-            tracing::debug!("Already requesting block {}, skipping", number);
-            return;
-        }
+    /// Back the database up into this directory, every --backup-interval-mins and on
+    /// `admin_backup`; the copy is consistent while the node runs
+    #[clap(long)]
+    backup_dir: Option<PathBuf>,
 
-        // Request headers for missing blocks (up to 512 at a time)
-        let our_latest = self.block_store.latest_block_number();
-        let start_block = our_latest + 1;
-        let mut count = number - our_latest;
+    /// Minutes between scheduled backups (0 only backs up on `admin_backup`)
+    #[clap(long, default_value = "0")]
+    backup_interval_mins: u64,
 
-        // Limit batch size
-        if count > 512 {
-            count = 512;
-        }
+    /// Newest backups kept in --backup-dir, older ones are deleted (0 keeps all)
+    #[clap(long, default_value_t = dex_storage::DEFAULT_BACKUPS_KEPT)]
+    backup_keep: usize,
 
-        if count > 0 {
-            tracing::info!(
-                "Requesting {} block headers from peer {} (blocks {} to {})",
-                count, peer_id, start_block, start_block + count - 1
-            );
+    /// GiB the database may grow to; `dex_storageInfo` and /status estimate the days left
+    /// at its current growth (0 sets no budget)
+    #[clap(long, default_value = "0")]
+    disk_budget_gb: u64,
 
-            // Track pending requests
-            for block_num in start_block..start_block + count {
-                self.pending_header_requests.insert(block_num);
-            }
-            self.request_peer = Some(peer_id);
+    /// EVM RPC state reads (eth_getBalance, eth_call, ...) while more than --sync-gate-lag
+    /// blocks behind peers: "serve" as usual, "warn" with an x-dex-syncing response header,
+    /// or "reject" with the eth_syncing progress as error data
+    #[clap(long, default_value_t = dex_rpc::SyncGateMode::default())]
+    sync_gate: dex_rpc::SyncGateMode,
 
-            // Send request
-            let cmd = SessionCommand::GetBlockHeaders {
-                peer_id,
-                start: start_block,
-                count,
-            };
-            if let Err(e) = self.p2p_handle.send_command(cmd).await {
-                tracing::warn!("Failed to send GetBlockHeaders: {}", e);
-                // Clear pending on error
-                for block_num in start_block..start_block + count {
-                    self.pending_header_requests.remove(&block_num);
-                }
-            }
-        }
-    }
+    /// Blocks the node may be behind the highest peer block before --sync-gate applies
+    #[clap(long, default_value_t = dex_rpc::DEFAULT_MAX_SYNC_LAG)]
+    sync_gate_lag: u64,
 
-    /// Handle BlockHeaders response - store headers and request bodies
-    async fn handle_block_headers(&mut self, peer_id: PeerId, headers: Vec<ConsensusHeader>) {
-        if headers.is_empty() {
-            tracing::debug!("Received empty headers response from {}", peer_id);
-            // Clear pending requests since we got an empty response
-            self.pending_header_requests.clear();
-            return;
-        }
+    /// Append a JSON line per audited EVM RPC call (method, params, sender, outcome, latency)
+    /// to this file
+    #[clap(long)]
+    rpc_audit_log: Option<PathBuf>,
 
-        tracing::info!("Received {} block headers from peer {}", headers.len(), peer_id);
+    /// Audited RPC methods, comma-separated; an entry ending in `_` covers the namespace.
+    /// Defaults to eth_sendRawTransaction, admin_ and dex_resetAccount
+    #[clap(long, value_delimiter = ',')]
+    rpc_audit_methods: Vec<String>,
 
-        // Collect hashes for body requests
-        let mut hashes_to_request: Vec<B256> = Vec::new();
+    /// Fraction of calls of each audited method that are logged (0.0 to 1.0)
+    #[clap(long, default_value = "1.0")]
+    rpc_audit_sample_rate: f64,
 
-        for header in headers {
-            let block_num = header.number;
+    /// Leave params and results out of the audit log
+    #[clap(long)]
+    rpc_audit_redact: bool,
 
-            // Remove from pending header requests
-            self.pending_header_requests.remove(&block_num);
+    /// Counter change webhook URLs (http:// only), comma-separated; more can be added via REST
+    #[clap(long, value_delimiter = ',')]
+    webhooks: Vec<String>,
 
-            // Compute header hash
-            let header_hash = keccak256(alloy_rlp::encode(&header));
+    /// Retries for a failed webhook delivery
+    #[clap(long, default_value_t = dex_rpc::DEFAULT_WEBHOOK_RETRIES)]
+    webhook_retries: u32,
 
-            tracing::debug!(
-                "Received header for block {}: hash={:?}, parent={:?}",
-                block_num, header_hash, header.parent_hash
-            );
+    /// Delay before the first webhook retry, doubled on each further retry (milliseconds)
+    #[clap(long, default_value = "500")]
+    webhook_backoff_ms: u64,
 
-            // Store header and add to body request queue
-            hashes_to_request.push(header_hash);
-            self.pending_body_requests.insert(block_num, header);
-        }
+    /// Maximum number of P2P peers
+    #[clap(long, default_value = "50")]
+    max_peers: usize,
 
-        // Clear any remaining pending header requests (for blocks we didn't receive)
-        self.pending_header_requests.clear();
+    /// Minimum gas price (wei) for mempool acceptance and relaying
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MIN_GAS_PRICE)]
+    min_gas_price: u128,
 
-        // Request bodies for all headers
-        if !hashes_to_request.is_empty() {
-            tracing::info!("Requesting {} block bodies from peer {}", hashes_to_request.len(), peer_id);
+    /// Minimum priority fee (wei) for mempool acceptance and relaying
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MIN_PRIORITY_FEE)]
+    min_priority_fee: u128,
 
-            let cmd = SessionCommand::GetBlockBodies {
-                peer_id,
-                hashes: hashes_to_request,
-            };
-            if let Err(e) = self.p2p_handle.send_command(cmd).await {
-                tracing::warn!("Failed to send GetBlockBodies: {}", e);
-            }
-        }
-    }
+    /// Maximum pending transactions per sender
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MAX_PENDING_PER_SENDER)]
+    max_pending_per_sender: usize,
 
-    /// Handle BlockBodies response - create and store complete blocks
-    async fn handle_block_bodies(&mut self, peer_id: PeerId, bodies: Vec<BlockBody>) {
-        if bodies.is_empty() {
-            tracing::debug!("Received empty bodies response");
-            return;
-        }
+    /// Fee increase (percent) for a transaction to replace a pending one with the same nonce
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_PRICE_BUMP_PERCENT)]
+    price_bump_percent: u64,
 
-        tracing::info!("Received {} block bodies", bodies.len());
+    /// Maximum number of pending transactions; a full pool evicts the lowest-paying ones
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MAX_POOL_COUNT)]
+    max_pool_txs: usize,
 
-        // Match bodies with pending headers
-        // Bodies come in the same order as requested hashes
-        let mut pending_numbers: Vec<u64> = self.pending_body_requests.keys().copied().collect();
-        pending_numbers.sort();
+    /// Maximum encoded size of all pending transactions (bytes)
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MAX_POOL_BYTES)]
+    max_pool_bytes: usize,
 
-        for (i, body) in bodies.into_iter().enumerate() {
-            if i >= pending_numbers.len() {
-                tracing::warn!("Received more bodies than pending headers");
-                break;
-            }
+    /// Maximum encoded size of one transaction (bytes); larger ones from RPC or peers are
+    /// refused before decoding
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MAX_TX_BYTES)]
+    max_tx_bytes: usize,
 
-            let block_num = pending_numbers[i];
+    /// Maximum input (calldata or init code) of one transaction (bytes)
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MAX_INPUT_BYTES)]
+    max_tx_input_bytes: usize,
 
-            if let Some(header) = self.pending_body_requests.remove(&block_num) {
-                // Create StoredBlock from header and body
-                let header_hash = keccak256(alloy_rlp::encode(&header));
+    /// Drop pending transactions older than this (minutes)
+    #[clap(long, default_value_t = dex_rpc::mempool::DEFAULT_MAX_TX_AGE_MINS)]
+    max_tx_age_mins: u64,
 
-                // Extract transaction hashes and prepare for storage
-                let tx_hashes: Vec<B256> = body.transactions.iter()
-                    .map(|tx| *tx.tx_hash())
-                    .collect();
+    /// Simulate incoming transactions and reject those that would revert or run out of gas
+    #[clap(long)]
+    reject_failing_txs: bool,
 
-                // Store full transactions
-                let tx_data: Vec<(B256, Vec<u8>)> = body.transactions.iter()
-                    .map(|tx| (*tx.tx_hash(), alloy_rlp::encode(tx)))
-                    .collect();
+    /// Transactions per second a validator takes from each peer's gossip; more are dropped, and
+    /// peers that keep exceeding it are disconnected
+    #[clap(long, default_value_t = dex_node::DEFAULT_PEER_TX_RATE)]
+    p2p_tx_rate: u32,
 
-                if !tx_data.is_empty() {
-                    if let Err(e) = self.block_store.store_transactions(&tx_data) {
-                        tracing::error!("Failed to store transactions for block {}: {}", block_num, e);
-                    }
-                }
+    /// Transactions a peer may gossip at once after a quiet spell
+    #[clap(long, default_value_t = dex_node::DEFAULT_PEER_TX_BURST)]
+    p2p_tx_burst: u32,
 
-                // Extract signature from extra_data if present (65 bytes)
-                let signature = if header.extra_data.len() >= 65 {
-                    let mut sig = [0u8; 65];
-                    sig.copy_from_slice(&header.extra_data[header.extra_data.len() - 65..]);
-                    sig
-                } else {
-                    [0u8; 65]
-                };
-
-                let stored_block = StoredBlock {
-                    number: header.number,
-                    hash: header_hash,
-                    parent_hash: header.parent_hash,
-                    timestamp: header.timestamp,
-                    gas_limit: header.gas_limit,
-                    gas_used: header.gas_used,
-                    miner: header.beneficiary,
-                    // For sync, use header's state_root as combined (we don't have separate roots)
-                    evm_state_root: header.state_root,
-                    dexvm_state_root: B256::ZERO,
-                    combined_state_root: header.state_root,
-                    transaction_hashes: tx_hashes.clone(),
-                    transaction_count: tx_data.len() as u64,
-                    signature,
-                };
-
-                // Store the block
-                match self.block_store.store_block(stored_block) {
-                    Ok(_) => {
-                        tracing::info!(
-                            "Synced block {}: hash={:?}, txs={}",
-                            block_num, header_hash, tx_hashes.len()
-                        );
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to store synced block {}: {}", block_num, e);
-                    }
-                }
-            } else {
-                tracing::warn!("Received body for unknown block {}", block_num);
-            }
-        }
+    /// Workers decoding gossiped transactions and recovering their signers
+    #[clap(long, default_value_t = dex_node::DEFAULT_TX_INGEST_WORKERS)]
+    p2p_tx_workers: usize,
 
-        // Log sync progress
-        let latest = self.block_store.latest_block_number();
-        tracing::info!("Sync progress: latest block = {}", latest);
-
-        // Continue sync if peer has more blocks
-        if let Some(&peer_head) = self.peer_heads.get(&peer_id) {
-            if latest < peer_head && self.pending_header_requests.is_empty() && self.pending_body_requests.is_empty() {
-                tracing::info!(
-                    "Continuing sync: our latest={}, peer head={}",
-                    latest, peer_head
-                );
-                self.handle_new_block_hash(peer_id, B256::ZERO, peer_head).await;
-            }
-        }
-    }
-}
+    /// Only allow P2P sessions with peers listed in --trusted-peers
+    #[clap(long)]
+    trusted_only: bool,
 
-/// Run fullnode sync loop
-async fn run_fullnode_sync(
-    p2p_handle: P2pHandle,
-    block_store: Arc<BlockStore>,
-) -> eyre::Result<()> {
-    let mut sync_manager = BlockSyncManager::new(p2p_handle.clone(), block_store);
-    let mut events = p2p_handle.subscribe();
-
-    tracing::info!("Starting fullnode sync handler");
-
-    loop {
-        match events.recv().await {
-            Ok(event) => match event {
-                P2pEvent::PeerConnected { peer_id, addr } => {
-                    tracing::info!("Peer connected: {} from {}", peer_id, addr);
-                    // Request initial sync from the connected peer
-                    sync_manager.request_initial_sync(peer_id).await;
-                }
-                P2pEvent::PeerDisconnected { peer_id } => {
-                    tracing::info!("Peer disconnected: {}", peer_id);
-                    sync_manager.peer_heads.remove(&peer_id);
-                }
-                P2pEvent::NewBlockHash { peer_id, hash, number } => {
-                    tracing::info!(
-                        "Received NewBlockHash from {}: block {} hash {:?}",
-                        peer_id, number, hash
-                    );
-                    sync_manager.handle_new_block_hash(peer_id, hash, number).await;
-                }
-                P2pEvent::BlockHeaders { peer_id, request_id: _, headers } => {
-                    sync_manager.handle_block_headers(peer_id, headers).await;
-                }
-                P2pEvent::BlockBodies { peer_id, request_id: _, bodies } => {
-                    sync_manager.handle_block_bodies(peer_id, bodies).await;
-                }
-                _ => {}
-            },
-            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                tracing::warn!("P2P event receiver lagged {} events", n);
-            }
-            Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                tracing::info!("P2P event channel closed");
-                break;
-            }
-        }
-    }
+    /// Trusted peers (enode URLs or hex peer IDs), comma-separated
+    #[clap(long, value_delimiter = ',')]
+    trusted_peers: Vec<String>,
 
-    Ok(())
-}
+    /// Gossip transactions encrypted to these peers only (enode URLs or hex peer IDs)
+    #[clap(long, value_delimiter = ',')]
+    private_tx_peers: Vec<String>,
 
-/// Run validator P2P event handler - responds to block header/body requests
-async fn run_validator_p2p_handler(
-    p2p_handle: P2pHandle,
-    block_store: Arc<BlockStore>,
-    evm_rpc_server: Option<Arc<EvmRpcServer>>,
-) -> eyre::Result<()> {
-    let mut events = p2p_handle.subscribe();
-
-    tracing::info!("Starting validator P2P event handler");
-
-    loop {
-        match events.recv().await {
-            Ok(event) => match event {
-                P2pEvent::PeerConnected { peer_id, addr } => {
-                    tracing::info!("Peer connected: {} from {}", peer_id, addr);
-                }
-                P2pEvent::PeerDisconnected { peer_id } => {
-                    tracing::info!("Peer disconnected: {}", peer_id);
-                }
-                P2pEvent::NewBlockHash { peer_id, hash, number } => {
-                    tracing::debug!(
-                        "Received NewBlockHash from {}: block {} hash {:?}",
-                        peer_id, number, hash
-                    );
-                    // Validator doesn't need to sync - it produces blocks
-                }
-                P2pEvent::GetBlockHeadersRequest { peer_id, request_id, start, limit } => {
-                    tracing::info!(
-                        "Peer {} requesting {} headers starting from {:?}",
-                        peer_id, limit, start
-                    );
-
-                    // Look up requested headers from our block store
-                    let mut headers: Vec<ConsensusHeader> = Vec::new();
-
-                    let start_num = match start {
-                        HashOrNumber::Number(n) => n,
-                        HashOrNumber::Hash(hash) => {
-                            // Try to find block by hash
-                            // For now, we don't support hash-based lookup
-                            tracing::warn!("Hash-based header lookup not implemented, hash={:?}", hash);
-                            continue;
-                        }
-                    };
-
-                    // Collect headers (going backwards from start, as per ETH protocol)
-                    for i in 0..limit {
-                        let block_num = if start_num >= i { start_num - i } else { break };
-
-                        if let Some(block) = block_store.get_block_by_number(block_num) {
-                            // Include signature in extra_data (65 bytes at the end)
-                            let extra_data = alloy_primitives::Bytes::copy_from_slice(&block.signature);
-
-                            // Convert StoredBlock to ConsensusHeader
-                            let header = ConsensusHeader {
-                                parent_hash: block.parent_hash,
-                                ommers_hash: keccak256([0x80]), // RLP empty list
-                                beneficiary: block.miner,
-                                state_root: block.combined_state_root,
-                                transactions_root: keccak256([0x80]), // Empty trie root
-                                receipts_root: keccak256([0x80]),
-                                logs_bloom: Bloom::ZERO,
-                                difficulty: U256::ZERO,
-                                number: block.number,
-                                gas_limit: block.gas_limit,
-                                gas_used: block.gas_used,
-                                timestamp: block.timestamp,
-                                extra_data,
-                                mix_hash: B256::ZERO,
-                                nonce: B64::ZERO,
-                                base_fee_per_gas: Some(0),
-                                withdrawals_root: None,
-                                blob_gas_used: None,
-                                excess_blob_gas: None,
-                                parent_beacon_block_root: None,
-                                requests_hash: None,
-                            };
-                            headers.push(header);
-                        } else {
-                            // No more blocks
-                            break;
-                        }
-                    }
-
-                    if !headers.is_empty() {
-                        tracing::info!("Sending {} headers to peer {}", headers.len(), peer_id);
-                        let cmd = SessionCommand::SendBlockHeaders {
-                            peer_id,
-                            request_id,
-                            headers,
-                        };
-                        if let Err(e) = p2p_handle.send_command(cmd).await {
-                            tracing::warn!("Failed to send headers to peer {}: {}", peer_id, e);
-                        }
-                    } else {
-                        tracing::debug!("No headers found for request from peer {}", peer_id);
-                    }
-                }
-                P2pEvent::GetBlockBodiesRequest { peer_id, request_id, hashes } => {
-                    tracing::info!(
-                        "Peer {} requesting {} block bodies",
-                        peer_id, hashes.len()
-                    );
-
-                    // Look up transactions for each requested block hash
-                    let mut bodies: Vec<BlockBody> = Vec::with_capacity(hashes.len());
-
-                    for block_hash in &hashes {
-                        // Find the block by hash
-                        if let Some(block) = block_store.get_block_by_hash(*block_hash) {
-                            // Get full transactions from storage
-                            let mut transactions = Vec::new();
-                            for tx_hash in &block.transaction_hashes {
-                                if let Some(tx_rlp) = block_store.get_transaction(*tx_hash) {
-                                    // Decode the transaction
-                                    if let Ok(tx) = TransactionSigned::decode(&mut tx_rlp.as_slice()) {
-                                        transactions.push(tx);
-                                    } else {
-                                        tracing::warn!("Failed to decode transaction {:?}", tx_hash);
-                                    }
-                                }
-                            }
-
-                            tracing::debug!(
-                                "Block {} has {} transactions",
-                                block.number, transactions.len()
-                            );
-
-                            bodies.push(BlockBody {
-                                transactions,
-                                ommers: vec![],
-                                withdrawals: None,
-                            });
-                        } else {
-                            // Block not found, send empty body
-                            tracing::debug!("Block {:?} not found", block_hash);
-                            bodies.push(BlockBody {
-                                transactions: vec![],
-                                ommers: vec![],
-                                withdrawals: None,
-                            });
-                        }
-                    }
-
-                    let total_txs: usize = bodies.iter().map(|b| b.transactions.len()).sum();
-                    tracing::info!(
-                        "Sending {} bodies with {} total transactions to peer {}",
-                        bodies.len(), total_txs, peer_id
-                    );
-                    let cmd = SessionCommand::SendBlockBodies {
-                        peer_id,
-                        request_id,
-                        bodies,
-                    };
-                    if let Err(e) = p2p_handle.send_command(cmd).await {
-                        tracing::warn!("Failed to send bodies to peer {}: {}", peer_id, e);
-                    }
-                }
-                P2pEvent::Transactions { peer_id, transactions } => {
-                    tracing::info!(
-                        "Received {} transactions from peer {}",
-                        transactions.len(), peer_id
-                    );
-
-                    // Add transactions to the pending pool
-                    if let Some(ref rpc_server) = evm_rpc_server {
-                        let mut added = 0;
-                        for tx_rlp in transactions {
-                            let decode_result: Result<TransactionSigned, _> = TransactionSigned::decode(&mut tx_rlp.as_slice());
-                            if let Ok(tx) = decode_result {
-                                if rpc_server.add_pending_transaction_from_p2p(tx) {
-                                    added += 1;
-                                }
-                            }
-                        }
-                        if added > 0 {
-                            tracing::info!("Added {} transactions to mempool from peer {}", added, peer_id);
-                        }
-                    }
-                }
-                _ => {}
-            },
-            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                tracing::warn!("P2P event receiver lagged {} events", n);
-            }
-            Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                tracing::info!("P2P event channel closed");
-                break;
-            }
-        }
-    }
+    /// Peer genesis/fork ID matching: "strict" refuses mismatched peers, "relaxed" only warns
+    #[clap(long, default_value = "strict")]
+    genesis_policy: GenesisPolicy,
 
-    Ok(())
+    /// Maintenance subcommand (runs instead of the node)
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
-/// Run consensus loop with P2P block broadcasting
-async fn run_consensus_loop_with_p2p(
-    mut node: DualVmNode,
-    p2p_handle: Option<P2pHandle>,
-    last_broadcast_block: Arc<RwLock<u64>>,
-) -> eyre::Result<()> {
-    // Verify consensus is configured
-    if node.consensus().is_none() {
-        return Err(eyre::eyre!("No consensus engine configured"));
-    }
+/// Maintenance subcommands
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Offline database maintenance
+    Db {
+        #[clap(subcommand)]
+        command: db::DbCommand,
+    },
+    /// Generate a validator key and this node's P2P key; prints the address and enode
+    Keygen {
+        /// Host the enode URL advertises
+        #[clap(long, default_value = "127.0.0.1")]
+        host: IpAddr,
+        /// Replace an existing P2P key (changes the node's identity)
+        #[clap(long)]
+        force: bool,
+    },
+    /// Print this node's enode URL without starting it
+    Enode {
+        /// Host the enode URL advertises
+        #[clap(long, default_value = "127.0.0.1")]
+        host: IpAddr,
+    },
+    /// Serve EVM JSON-RPC from the datadir of a node running in another process
+    RpcServe {
+        /// Open the database read-only (required; the node keeps writing it)
+        #[clap(long)]
+        readonly: bool,
+    },
+    /// Re-execute stored blocks on a scratch state and compare their state roots
+    Replay {
+        /// First block whose state root is compared
+        #[clap(long)]
+        from: u64,
+        /// Last block to replay (defaults to the stored head)
+        #[clap(long)]
+        to: Option<u64>,
+    },
+}
 
-    tracing::info!("Starting consensus loop with P2P integration");
-
-    loop {
-        // Get proposal from consensus (short borrow)
-        let proposal = node.consensus().and_then(|c| c.recv_proposal());
-
-        if let Some(proposal) = proposal {
-            tracing::info!(
-                "Received block proposal: block_number={}, tx_count={}",
-                proposal.number,
-                proposal.transactions.len()
-            );
-
-            let pending_txs = if let Some(rpc_server) = node.evm_rpc_server() {
-                let txs = rpc_server.get_pending_transactions();
-                rpc_server.clear_pending_transactions();
-                txs
-            } else {
-                vec![]
-            };
+/// Genesis file format
+/// Node-specific sections of a genesis file; the rest is read as a [`Genesis`]
+#[derive(Debug, Deserialize)]
+struct GenesisFile {
+    config: GenesisConfig,
+}
 
-            let mut all_transactions = proposal.transactions.clone();
-            for pending in &pending_txs {
-                all_transactions.push(pending.tx.clone());
-            }
+#[derive(Debug, Deserialize)]
+struct GenesisConfig {
+    #[serde(rename = "feePolicy")]
+    fee_policy: Option<GenesisFeePolicy>,
+    #[serde(rename = "blockLimits")]
+    block_limits: Option<GenesisBlockLimits>,
+}
 
-            if !all_transactions.is_empty() {
-                tracing::info!(
-                    "Processing block {} with {} transactions ({} from mempool)",
-                    proposal.number,
-                    all_transactions.len(),
-                    pending_txs.len()
-                );
-            }
+/// Fee policy section of the genesis config; absent, fees are destroyed
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenesisFeePolicy {
+    /// Account credited with base fees
+    #[serde(default)]
+    burn_address: Address,
+    /// Pay tips to the block's validator
+    #[serde(default = "default_tip_validator")]
+    tip_validator: bool,
+    /// Account taking a share of tips
+    treasury: Option<Address>,
+    /// Treasury share of tips in percent
+    #[serde(default)]
+    treasury_percent: u8,
+}
 
-            match node.executor_mut().execute_transactions(all_transactions.clone()) {
-                Ok(result) => {
-                    tracing::info!(
-                        "Block executed successfully: gas_used={}, state_root={:?}",
-                        result.total_gas_used,
-                        result.combined_state_root
-                    );
-
-                    // Build a proper Ethereum header for hashing
-                    let block_header = ConsensusHeader {
-                        parent_hash: proposal.parent_hash,
-                        ommers_hash: keccak256([0x80]), // RLP empty list
-                        beneficiary: proposal.proposer,
-                        state_root: result.combined_state_root,
-                        transactions_root: keccak256([0x80]), // Empty trie root
-                        receipts_root: keccak256([0x80]),
-                        logs_bloom: Bloom::ZERO,
-                        difficulty: U256::ZERO,
-                        number: proposal.number,
-                        gas_limit: 30_000_000,
-                        gas_used: result.total_gas_used,
-                        timestamp: proposal.timestamp,
-                        extra_data: alloy_primitives::Bytes::copy_from_slice(&proposal.signature.to_bytes()),
-                        mix_hash: B256::ZERO,
-                        nonce: B64::ZERO,
-                        base_fee_per_gas: Some(0),
-                        withdrawals_root: None,
-                        blob_gas_used: None,
-                        excess_blob_gas: None,
-                        parent_beacon_block_root: None,
-                        requests_hash: None,
-                    };
-                    let block_hash = keccak256(alloy_rlp::encode(&block_header));
-
-                    let tx_hashes: Vec<B256> =
-                        all_transactions.iter().map(|tx| *tx.tx_hash()).collect();
-
-                    // Store transaction receipts
-                    if let Some(rpc_server) = node.evm_rpc_server() {
-                        use alloy_consensus::transaction::SignerRecoverable;
-                        use alloy_consensus::Transaction;
-
-                        for (idx, (tx, receipt)) in all_transactions.iter().zip(result.evm_receipts.iter()).enumerate() {
-                            let tx_hash = *tx.tx_hash();
-                            let from = tx.recover_signer().unwrap_or_default();
-                            let to = tx.to();
-
-                            // Calculate contract address for contract creation txs
-                            let contract_address = if to.is_none() && receipt.status.coerce_status() {
-                                // Contract creation: address = keccak256(rlp([sender, nonce]))[12:]
-                                let nonce = tx.nonce();
-                                let mut data = Vec::new();
-                                data.extend_from_slice(from.as_slice());
-                                data.extend_from_slice(&nonce.to_be_bytes());
-                                Some(Address::from_slice(&keccak256(&data)[12..]))
-                            } else {
-                                None
-                            };
-
-                            let rpc_receipt = dex_rpc::TransactionReceipt {
-                                transaction_hash: tx_hash,
-                                transaction_index: alloy_primitives::U64::from(idx),
-                                block_hash,
-                                block_number: alloy_primitives::U64::from(proposal.number),
-                                from,
-                                to,
-                                cumulative_gas_used: alloy_primitives::U64::from(receipt.cumulative_gas_used),
-                                gas_used: alloy_primitives::U64::from(21000u64), // Base gas for now
-                                contract_address,
-                                logs: vec![],
-                                logs_bloom: alloy_primitives::Bytes::from(vec![0u8; 256]), // 256 bytes bloom filter
-                                status: alloy_primitives::U64::from(if receipt.status.coerce_status() { 1u64 } else { 0u64 }),
-                                tx_type: alloy_primitives::U64::from(0u64), // Legacy tx
-                            };
-
-                            rpc_server.add_receipt(tx_hash, rpc_receipt);
-                        }
-                    }
-
-                    let stored_block = dex_storage::StoredBlock {
-                        number: proposal.number,
-                        hash: block_hash,
-                        parent_hash: proposal.parent_hash,
-                        timestamp: proposal.timestamp,
-                        gas_limit: 30_000_000,
-                        gas_used: result.total_gas_used,
-                        miner: proposal.proposer,
-                        evm_state_root: result.evm_state_root,
-                        dexvm_state_root: result.dexvm_state_root,
-                        combined_state_root: result.combined_state_root,
-                        transaction_hashes: tx_hashes,
-                        transaction_count: all_transactions.len() as u64,
-                        signature: proposal.signature.to_bytes(),
-                    };
-
-                    if let Err(e) = node.block_store().store_block(stored_block) {
-                        tracing::error!("Failed to store block: {}", e);
-                    }
-
-                    // Store full transaction data for block body sync
-                    let tx_data: Vec<(B256, Vec<u8>)> = all_transactions.iter()
-                        .map(|tx| (*tx.tx_hash(), alloy_rlp::encode(tx)))
-                        .collect();
-                    if let Err(e) = node.block_store().store_transactions(&tx_data) {
-                        tracing::error!("Failed to store transactions: {}", e);
-                    }
-
-                    // Persist DexVM counter state to database
-                    if let Ok(dexvm_exec) = node.executor().dexvm_executor().read() {
-                        for (address, &value) in dexvm_exec.state().all_accounts() {
-                            if let Err(e) = node.state_store().set_counter(*address, value) {
-                                tracing::error!("Failed to persist DexVM counter for {}: {}", address, e);
-                            }
-                        }
-                    }
-
-                    // Finalize block (short borrow)
-                    if let Some(consensus) = node.consensus() {
-                        consensus.finalize_block(result.combined_state_root);
-                    }
-
-                    tracing::info!(
-                        "Block {} finalized and stored, hash={:?}",
-                        proposal.number,
-                        block_hash
-                    );
-
-                    // Broadcast new block to all connected peers via P2P
-                    if let Some(ref handle) = p2p_handle {
-                        let last_block = *last_broadcast_block.read().await;
-                        if proposal.number > last_block {
-                            let cmd = SessionCommand::BroadcastBlock {
-                                hash: block_hash,
-                                number: proposal.number,
-                            };
-                            if let Err(e) = handle.send_command(cmd).await {
-                                tracing::warn!("Failed to broadcast block via P2P: {}", e);
-                            } else {
-                                *last_broadcast_block.write().await = proposal.number;
-                                tracing::debug!(
-                                    "Broadcasted block {} to {} peers",
-                                    proposal.number,
-                                    handle.connected_count()
-                                );
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Block execution failed: {}", e);
-                }
-            }
+/// Block size section of the genesis config; absent fields keep the defaults
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenesisBlockLimits {
+    /// Encoded bytes of a block's signed transactions
+    max_bytes: Option<usize>,
+    /// Signed transactions per block
+    max_transactions: Option<usize>,
+}
+
+impl From<GenesisBlockLimits> for BlockLimits {
+    fn from(limits: GenesisBlockLimits) -> Self {
+        let defaults = BlockLimits::default();
+        Self {
+            max_bytes: limits.max_bytes.unwrap_or(defaults.max_bytes),
+            max_txs: limits.max_transactions.unwrap_or(defaults.max_txs),
         }
+    }
+}
 
-        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+fn default_tip_validator() -> bool {
+    true
+}
+
+impl From<GenesisFeePolicy> for FeePolicy {
+    fn from(policy: GenesisFeePolicy) -> Self {
+        Self {
+            burn_address: Some(policy.burn_address),
+            tip_validator: policy.tip_validator,
+            treasury: policy.treasury,
+            treasury_percent: policy.treasury_percent,
+        }
     }
 }
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
     init_tracing(&cli.log_level)?;
 
+    // Resolve the chain from --genesis, falling back to the --network preset
+    let network = cli.network.unwrap_or_default();
+    let mut check = ConfigCheck::default();
+    let (genesis, fee_policy, block_limits) = validate::genesis(&mut check, &cli, network);
+    let chain_id = genesis.config.chain_id;
+    let genesis_hash = genesis.hash();
+
+    let chain_dir = network::chain_dir(&cli.datadir, chain_id);
+    let db_dir = chain_dir.join(network::DB_DIR);
+
+    match cli.command.take() {
+        Some(Command::Db { command }) => {
+            check.finish()?;
+            return db::run(command, &db_dir);
+        }
+        Some(Command::Keygen { host, force }) => {
+            check.finish()?;
+            let key_path = chain_dir.join(network::P2P_KEY_FILE);
+            return keys::keygen(&key_path, host, cli.p2p_port, force);
+        }
+        Some(Command::Enode { host }) => {
+            check.finish()?;
+            return keys::enode(&chain_dir.join(network::P2P_KEY_FILE), host, cli.p2p_port);
+        }
+        Some(Command::RpcServe { readonly }) => {
+            check.finish()?;
+            return replica::run(chain_id, genesis_hash, &db_dir, readonly, cli.evm_rpc_port)
+                .await;
+        }
+        Some(Command::Replay { from, to }) => {
+            check.finish()?;
+            let config = NodeConfig {
+                chain_id,
+                fee_policy,
+                block_limits,
+                genesis: Some(genesis),
+                create_evm_accounts: cli.create_evm_accounts,
+                ..Default::default()
+            };
+            return db::replay(&db_dir, config, from, to);
+        }
+        None => {}
+    }
+
+    // Report every misconfiguration before touching the database or binding ports
+    let settings = validate::node(&mut check, &cli, &chain_dir);
+    check.finish()?;
+    let mode = settings.mode;
+
     tracing::info!("====================================");
     tracing::info!("  Starting dex-reth Node v0.1.0");
     tracing::info!("====================================");
     tracing::info!("EVM JSON-RPC port: {}", cli.evm_rpc_port);
     tracing::info!("DexVM REST API port: {}", cli.dexvm_port);
-    tracing::info!("Data directory: {}", cli.datadir.display());
-
-    // Load genesis file
-    let (chain_id, genesis_alloc, genesis_hash) = if let Some(genesis_path) = &cli.genesis {
-        tracing::info!("Loading genesis file from: {}", genesis_path.display());
-        let genesis_data = std::fs::read_to_string(genesis_path)?;
-        let genesis: GenesisFile = serde_json::from_str(&genesis_data)?;
-
-        let chain_id = genesis.config.chain_id;
-        tracing::info!("Chain ID: {}", chain_id);
-
-        let mut alloc = HashMap::new();
-        for (address, account) in genesis.alloc {
-            let balance = if account.balance.starts_with("0x") {
-                U256::from_str_radix(&account.balance[2..], 16)?
-            } else {
-                U256::from_str_radix(&account.balance, 10)?
-            };
-
-            tracing::info!("Genesis account: {} with balance {} wei", address, balance);
+    if cli.ephemeral {
+        tracing::info!("Data directory: none (ephemeral, chain data is kept in memory)");
+    } else {
+        tracing::info!("Data directory: {}", chain_dir.display());
+    }
 
-            alloc.insert(address, balance);
-        }
+    match &cli.genesis {
+        Some(genesis_path) => tracing::info!("Loaded genesis file from: {}", genesis_path.display()),
+        None => tracing::info!("Using network preset: {}", network),
+    }
+    tracing::info!("Chain ID: {}", chain_id);
+    tracing::info!("Genesis hash: {}", genesis_hash);
+    for (address, account) in &genesis.alloc {
+        tracing::info!("Genesis account: {} with balance {} wei", address, account.balance);
+    }
+    tracing::info!("Fee policy: {:?}", fee_policy);
+    tracing::info!(
+        "Block limits: {} bytes, {} transactions",
+        block_limits.max_bytes,
+        block_limits.max_txs
+    );
+    if cli.dev {
+        tracing::warn!("Dev mode: dex_resetAccount can rewrite any account's state");
+    }
 
-        // Compute genesis hash from genesis data
-        let genesis_hash = keccak256(genesis_data.as_bytes());
+    if !cli.ephemeral {
+        network::warn_legacy_layout(&cli.datadir, &chain_dir);
+    }
 
-        (chain_id, Some(alloc), genesis_hash)
-    } else {
-        tracing::info!("No genesis file specified, using default chain ID 1");
-        (1, None, B256::ZERO)
+    // Everything the node needs goes into its configuration; `launch` does the rest
+    let mut config = NodeConfig {
+        chain_id,
+        datadir: db_dir,
+        ephemeral: cli.ephemeral,
+        evm_rpc_port: cli.evm_rpc_port,
+        dexvm_rpc_port: cli.dexvm_port,
+        storage_retry: StorageRetryPolicy {
+            max_retries: cli.storage_retries,
+            retry_delay: Duration::from_millis(cli.storage_retry_delay_ms),
+        },
+        invariant_mode: cli.check_invariants,
+        fee_policy,
+        block_limits,
+        dev: cli.dev,
+        production_watchdog: (cli.stall_intervals > 0).then_some(WatchdogConfig {
+            stall_intervals: cli.stall_intervals,
+            restart_consensus: cli.restart_stalled_consensus,
+        }),
+        genesis: Some(genesis),
+        tx_timeout: (cli.tx_timeout_ms > 0).then(|| Duration::from_millis(cli.tx_timeout_ms)),
+        slow_tx_threshold: (cli.slow_tx_threshold_ms > 0)
+            .then(|| Duration::from_millis(cli.slow_tx_threshold_ms)),
+        mempool: dex_rpc::MempoolConfig {
+            min_gas_price: cli.min_gas_price,
+            min_priority_fee: cli.min_priority_fee,
+            max_pending_per_sender: cli.max_pending_per_sender,
+            reject_failing: cli.reject_failing_txs,
+            max_pool_count: cli.max_pool_txs,
+            max_pool_bytes: cli.max_pool_bytes,
+            max_tx_bytes: cli.max_tx_bytes,
+            max_input_bytes: cli.max_tx_input_bytes,
+            max_tx_age: Duration::from_secs(cli.max_tx_age_mins * 60),
+            price_bump_percent: cli.price_bump_percent,
+        },
+        webhooks: dex_rpc::WebhookConfig {
+            max_retries: cli.webhook_retries,
+            initial_backoff: Duration::from_millis(cli.webhook_backoff_ms),
+        },
+        webhook_urls: cli.webhooks.clone(),
+        shutdown_sentinel: Some(chain_dir.join(network::SHUTDOWN_SENTINEL_FILE)),
+        disk_budget: (cli.disk_budget_gb > 0).then(|| cli.disk_budget_gb.saturating_mul(1 << 30)),
+        sync_gate: dex_rpc::SyncGate { mode: cli.sync_gate, max_lag: cli.sync_gate_lag },
+        create_evm_accounts: cli.create_evm_accounts,
+        tx_ingest: dex_node::TxIngestConfig {
+            peer_rate: cli.p2p_tx_rate,
+            peer_burst: cli.p2p_tx_burst,
+            workers: cli.p2p_tx_workers,
+        },
+        validators: settings.validators.clone(),
+        ..Default::default()
     };
+    if let Some(trace_dir) = trace_dir(&cli, &chain_dir).filter(|_| cli.trace_blocks) {
+        let writer = TraceWriter::new(trace_dir, cli.trace_keep)?;
+        tracing::info!("Writing block traces to {}", writer.dir().display());
+        config.block_traces = Some(writer);
+    }
+    if let Some(dir) = &cli.backup_dir {
+        let backups = dex_storage::Backups::new(dir, cli.backup_keep)?;
+        tracing::info!("Database backups go to {}", backups.dir().display());
+        config.backups = Some(Arc::new(backups));
+        config.backup_interval = (cli.backup_interval_mins > 0)
+            .then(|| Duration::from_secs(cli.backup_interval_mins * 60));
+    }
+    if let Some(path) = &cli.rpc_audit_log {
+        let mut audit = dex_rpc::RpcAuditConfig::new(path);
+        if !cli.rpc_audit_methods.is_empty() {
+            audit.methods = cli.rpc_audit_methods.clone();
+        }
+        audit.sample_rate = cli.rpc_audit_sample_rate;
+        audit.redact = cli.rpc_audit_redact;
+        let log = dex_rpc::RpcAuditLog::open(audit)?;
+        tracing::info!("Auditing RPC calls to {}", log.path().display());
+        config.rpc_audit = Some(log);
+    }
 
-    // Create node
-    let mut node = DualVmNode::with_full_config(
-        chain_id,
-        genesis_alloc.clone().unwrap_or_default(),
-        cli.datadir.clone(),
-        None,
-    );
+    tracing::info!("Node mode: {}", mode);
 
-    // Start P2P service if enabled
-    let _p2p_handle = if !cli.disable_p2p {
-        tracing::info!("P2P networking enabled on port {}", cli.p2p_port);
+    // P2P runs unless disabled or serving RPC only
+    let p2p_config = if !cli.disable_p2p && mode != NodeMode::RpcOnly {
+        tracing::info!("P2P networking enabled on {}", SocketAddr::new(cli.p2p_addr, cli.p2p_port));
 
         // Load or create persistent P2P secret key
-        let key_path = cli.datadir.join("p2p_key");
-        let secret_key = match P2pConfig::load_or_create_secret_key(&key_path) {
-            Ok(key) => {
-                tracing::info!("P2P key loaded from: {}", key_path.display());
-                key
-            }
-            Err(e) => {
-                tracing::warn!("Failed to load P2P key: {}, generating new key", e);
-                P2pConfig::random_secret_key()
-            }
-        };
-        let mut p2p_config = P2pConfig::new(secret_key, chain_id, genesis_hash)
-            .with_port(cli.p2p_port)
-            .with_max_peers(cli.max_peers);
-
-        // Add boot nodes from CLI
-        for bootnode in &cli.bootnodes {
-            match bootnode.parse::<TrustedPeer>() {
-                Ok(peer) => {
-                    tracing::info!("Adding bootnode: {}", bootnode);
-                    p2p_config = p2p_config.with_boot_node(peer);
+        let key_path = chain_dir.join(network::P2P_KEY_FILE);
+        let secret_key = if cli.ephemeral {
+            P2pConfig::random_secret_key()
+        } else {
+            match P2pConfig::load_or_create_secret_key(&key_path) {
+                Ok(key) => {
+                    tracing::info!("P2P key loaded from: {}", key_path.display());
+                    key
                 }
                 Err(e) => {
-                    tracing::warn!("Invalid bootnode URL '{}': {}", bootnode, e);
+                    tracing::warn!("Failed to load P2P key: {}, generating new key", e);
+                    P2pConfig::random_secret_key()
                 }
             }
+        };
+        let mut p2p_config = P2pConfig::new(secret_key, chain_id, genesis_hash)
+            .with_listen_ip(cli.p2p_addr)
+            .with_port(cli.p2p_port)
+            .with_dual_stack(cli.p2p_dual_stack)
+            .with_max_peers(cli.max_peers)
+            .with_trusted_only(cli.trusted_only)
+            .with_genesis_policy(cli.genesis_policy);
+
+        // Add trusted peers and boot nodes from CLI
+        for peer_id in settings.trusted_peers {
+            p2p_config = p2p_config.with_trusted_peer(peer_id);
         }
-
-        let p2p_service = P2pService::new(p2p_config);
-        let handle = p2p_service.start().await?;
-
-        // Display enode URL for other nodes to connect
-        let local_id = handle.local_id();
-        tracing::info!("P2P service started");
-        tracing::info!("Local peer ID: {:?}", local_id);
-        tracing::info!(
-            "Enode URL: enode://{}@127.0.0.1:{}",
-            hex::encode(local_id.as_slice()),
-            cli.p2p_port
-        );
-
-        Some(handle)
+        for bootnode in settings.bootnodes {
+            tracing::info!("Adding bootnode: {}", bootnode);
+            p2p_config = p2p_config.with_boot_node(bootnode);
+        }
+        for peer_id in settings.private_tx_peers {
+            p2p_config = p2p_config.with_private_tx_peer(peer_id);
+        }
+        Some(p2p_config)
     } else {
         tracing::info!("P2P networking disabled");
         None
     };
 
     // Configure POA consensus
-    if cli.enable_consensus {
-        let mut poa_config = PoaConfig::from_hex_key(
+    let poa_config = if mode.produces_blocks() {
+        let poa_config = PoaConfig::from_hex_key(
             &cli.validator_key,
             Duration::from_millis(cli.block_interval_ms),
         )
         .map_err(|e| eyre::eyre!("Invalid validator key: {}", e))?;
-
-        let latest_block = node.block_store().latest_block_number();
-        let last_block_hash = node
-            .block_store()
-            .get_block_by_number(latest_block)
-            .map(|b| b.hash)
-            .unwrap_or_default();
-
-        poa_config.starting_block = latest_block;
-
+        tracing::info!("Validator set: {:?}", settings.validators);
+        let poa_config = poa_config.with_validators(settings.validators.clone());
         tracing::info!("POA consensus enabled");
-        tracing::info!("Validator address: {:?}", poa_config.validator);
         tracing::info!("Block interval: {}ms", cli.block_interval_ms);
-        tracing::info!("Continuing from block {} (hash: {:?})", latest_block, last_block_hash);
-
-        node.set_consensus(poa_config, last_block_hash);
+        Some(poa_config)
     } else {
-        tracing::info!("POA consensus not enabled (RPC-only mode)");
-    }
-
-    // Start EVM JSON-RPC service
-    let evm_rpc_handle = node.start_evm_rpc(cli.evm_rpc_port).await?;
-    tracing::info!("EVM JSON-RPC available at: http://127.0.0.1:{}", cli.evm_rpc_port);
+        tracing::info!("POA consensus not enabled");
+        None
+    };
 
-    // Start DexVM REST API service
-    let dexvm_rpc_handle = node.start_dexvm_rpc(cli.dexvm_port).await?;
-    tracing::info!("DexVM REST API available at: http://127.0.0.1:{}", cli.dexvm_port);
+    let mut node = dex_node::launch(config, p2p_config, poa_config).await?;
+    let (evm_rpc_port, dexvm_port) = (node.evm_rpc_addr().port(), node.dexvm_rpc_addr().port());
 
     tracing::info!("====================================");
     tracing::info!("  dex-reth Node started successfully");
     tracing::info!("====================================");
     tracing::info!("");
     tracing::info!("Endpoints:");
-    tracing::info!("  - EVM RPC:    http://127.0.0.1:{}", cli.evm_rpc_port);
-    tracing::info!("  - DexVM API:  http://127.0.0.1:{}", cli.dexvm_port);
-    tracing::info!("  - Health:     http://127.0.0.1:{}/health", cli.dexvm_port);
-    if !cli.disable_p2p {
-        tracing::info!("  - P2P:        0.0.0.0:{}", cli.p2p_port);
+    tracing::info!("  - EVM RPC:    http://127.0.0.1:{}", evm_rpc_port);
+    tracing::info!("  - DexVM API:  http://127.0.0.1:{}", dexvm_port);
+    tracing::info!("  - Health:     http://127.0.0.1:{}/health", dexvm_port);
+    tracing::info!("  - Status:     http://127.0.0.1:{}/status", dexvm_port);
+    tracing::info!("  - Webhooks:   http://127.0.0.1:{}/api/v1/webhooks", dexvm_port);
+    if node.p2p().is_some() {
+        tracing::info!("  - P2P:        {}", SocketAddr::new(cli.p2p_addr, cli.p2p_port));
     }
     tracing::info!("");
-    tracing::info!("Data stored in: {}", cli.datadir.display());
-
-    if cli.enable_consensus {
-        let consensus_handle =
-            node.start_consensus().ok_or_else(|| eyre::eyre!("Failed to start consensus"))?;
-
-        tracing::info!("POA consensus engine started, auto block production enabled");
-
-        // Clone P2P handle for block broadcasting
-        let p2p_for_broadcast = _p2p_handle.clone();
-
-        // Shared last block number for P2P broadcasting
-        let last_broadcast_block = Arc::new(RwLock::new(0u64));
-        let last_broadcast_block_for_loop = Arc::clone(&last_broadcast_block);
-
-        // Start P2P event handler if P2P is enabled (responds to block requests)
-        let p2p_event_handle = if let Some(p2p_handle) = _p2p_handle.clone() {
-            let block_store = Arc::clone(&node.storage().blocks);
-            let evm_rpc_server = node.evm_rpc_server().cloned();
-            Some(tokio::spawn(async move {
-                if let Err(e) = run_validator_p2p_handler(p2p_handle, block_store, evm_rpc_server).await {
-                    tracing::error!("Validator P2P handler error: {}", e);
-                }
-            }))
-        } else {
-            None
-        };
-
-        let consensus_loop = tokio::spawn(async move {
-            if let Err(e) = run_consensus_loop_with_p2p(
-                node,
-                p2p_for_broadcast,
-                last_broadcast_block_for_loop,
-            ).await {
-                tracing::error!("Consensus loop error: {}", e);
-            }
-        });
-
-        tracing::info!("");
-        tracing::info!("Press Ctrl+C to stop");
-
-        tokio::signal::ctrl_c().await?;
-
-        tracing::info!("");
-        tracing::info!("Shutting down dex-reth Node...");
+    if !cli.ephemeral {
+        tracing::info!("Data stored in: {}", chain_dir.display());
+    }
 
-        consensus_handle.abort();
-        consensus_loop.abort();
-        if let Some(h) = p2p_event_handle {
-            h.abort();
+    match mode {
+        NodeMode::Validator => {
+            tracing::info!("POA consensus engine started, auto block production enabled")
         }
-        dexvm_rpc_handle.abort();
-        evm_rpc_handle.stop()?;
-    } else {
-        // Full node mode with block sync
-        tracing::info!("Running in fullnode mode (sync only, no block production)");
-
-        // Create transaction broadcast channel for fullnode to forward transactions
-        let (tx_broadcast_tx, mut tx_broadcast_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(256);
-
-        // Set the broadcast channel on the RPC server if available
-        if let Some(rpc_server) = node.evm_rpc_server() {
-            rpc_server.set_tx_broadcast_sender(tx_broadcast_tx);
-            tracing::info!("Transaction forwarding enabled for fullnode");
+        NodeMode::FullNode => {
+            tracing::info!("Running in fullnode mode (sync only, no block production)")
         }
+        NodeMode::RpcOnly => {
+            tracing::info!("Running in RPC-only mode (no block production or sync)")
+        }
+    }
 
-        // Start fullnode sync handler if P2P is enabled
-        let sync_handle = if let Some(p2p_handle) = _p2p_handle.clone() {
-            let block_store = Arc::clone(&node.storage().blocks);
-            Some(tokio::spawn(async move {
-                if let Err(e) = run_fullnode_sync(p2p_handle, block_store).await {
-                    tracing::error!("Fullnode sync error: {}", e);
-                }
-            }))
-        } else {
-            None
-        };
-
-        // Start transaction broadcast handler if P2P is enabled
-        let tx_broadcast_handle = _p2p_handle.clone().map(|p2p_handle| tokio::spawn(async move {
-                tracing::info!("Starting transaction broadcast handler");
-                while let Some(tx_rlp) = tx_broadcast_rx.recv().await {
-                    tracing::debug!("Broadcasting transaction to peers");
-                    let cmd = SessionCommand::BroadcastTransactions {
-                        transactions: vec![tx_rlp],
-                    };
-                    if let Err(e) = p2p_handle.send_command(cmd).await {
-                        tracing::warn!("Failed to broadcast transaction: {}", e);
-                    }
-                }
-            }));
+    tracing::info!("");
+    tracing::info!("Press Ctrl+C to stop");
 
-        tracing::info!("");
-        tracing::info!("Press Ctrl+C to stop");
+    // A storage failure that outlasts its retries stops the whole node
+    let result = tokio::select! {
+        signal = tokio::signal::ctrl_c() => signal.map_err(Into::into),
+        exited = node.wait_for_exit() => exited,
+    };
 
-        tokio::signal::ctrl_c().await?;
+    tracing::info!("");
+    tracing::info!("Shutting down dex-reth Node...");
 
-        tracing::info!("");
-        tracing::info!("Shutting down dex-reth Node...");
+    // After a failure the node is dropped without a clean shutdown, keeping the sentinel
+    result?;
+    node.stop()?;
+    tracing::info!("dex-reth Node stopped.");
+    Ok(())
+}
 
-        if let Some(h) = sync_handle {
-            h.abort();
-        }
-        if let Some(h) = tx_broadcast_handle {
-            h.abort();
-        }
-        dexvm_rpc_handle.abort();
-        evm_rpc_handle.stop()?;
+/// Directory block traces are written to, if there is one
+fn trace_dir(cli: &Cli, chain_dir: &Path) -> Option<PathBuf> {
+    match (&cli.trace_dir, cli.ephemeral) {
+        (Some(dir), _) => Some(dir.clone()),
+        (None, false) => Some(chain_dir.join(network::TRACES_DIR)),
+        (None, true) => None,
     }
+}
 
-    tracing::info!("dex-reth Node stopped.");
-    Ok(())
+/// Parse a genesis file into its genesis, fee policy and block limits
+fn load_genesis(genesis_data: &str) -> eyre::Result<LoadedGenesis> {
+    let genesis = Genesis::from_json(genesis_data)?;
+    let sections: GenesisFile = serde_json::from_str(genesis_data)?;
+    let fee_policy = sections.config.fee_policy.map(FeePolicy::from).unwrap_or_default();
+    fee_policy.validate()?;
+    let block_limits = sections.config.block_limits.map(BlockLimits::from).unwrap_or_default();
+    block_limits.validate()?;
+
+    Ok((genesis, fee_policy, block_limits))
 }
 
 fn init_tracing(level: &str) -> eyre::Result<()> {
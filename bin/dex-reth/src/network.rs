@@ -0,0 +1,87 @@
+//! Named network presets and per-chain data directory layout
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Genesis shipped with the `local` preset
+const LOCAL_GENESIS: &str = include_str!("../../../genesis.json");
+
+/// Database directory inside a chain directory
+pub const DB_DIR: &str = "db";
+
+/// P2P secret key file inside a chain directory
+pub const P2P_KEY_FILE: &str = "p2p_key";
+
+/// Block trace directory inside a chain directory
+pub const TRACES_DIR: &str = "traces";
+
+/// Sentinel file present while a node runs on a chain directory
+pub const SHUTDOWN_SENTINEL_FILE: &str = "running";
+
+/// Built-in network presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Network {
+    /// Chain 1 with an empty genesis allocation
+    #[default]
+    Dev,
+    /// Chain 13337 with the repository's genesis.json
+    Local,
+}
+
+impl Network {
+    /// Chain id of the preset
+    pub fn chain_id(self) -> u64 {
+        match self {
+            Self::Dev => 1,
+            Self::Local => 13337,
+        }
+    }
+
+    /// Genesis file contents, if the preset has one
+    pub fn genesis(self) -> Option<&'static str> {
+        match self {
+            Self::Dev => None,
+            Self::Local => Some(LOCAL_GENESIS),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dev" => Ok(Self::Dev),
+            "local" => Ok(Self::Local),
+            other => Err(format!("unknown network '{}', expected 'dev' or 'local'", other)),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dev => write!(f, "dev"),
+            Self::Local => write!(f, "local"),
+        }
+    }
+}
+
+/// Per-chain directory under the datadir root: `<datadir>/<chain_id>`
+pub fn chain_dir(datadir: &Path, chain_id: u64) -> PathBuf {
+    datadir.join(chain_id.to_string())
+}
+
+/// Warn about a database left at the datadir root by the old flat layout
+pub fn warn_legacy_layout(datadir: &Path, chain_dir: &Path) {
+    if datadir.join(dex_storage::MDBX_DATA_FILE).exists() {
+        tracing::warn!(
+            "Found a database directly in {} (old layout); it is ignored. Move it to {} to keep using it",
+            datadir.display(),
+            chain_dir.join(DB_DIR).display()
+        );
+    }
+}
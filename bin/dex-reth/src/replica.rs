@@ -0,0 +1,69 @@
+//! Read-only RPC replica
+//!
+//! `dex-reth rpc-serve --readonly` serves EVM JSON-RPC from the datadir of a
+//! node running in another process, for analytics and indexing load that
+//! should not compete with block production. The database is opened
+//! read-only; new blocks are picked up by polling for the stored head.
+
+use alloy_primitives::B256;
+use dex_storage::{BlockReader, DualvmStorage};
+use std::{path::Path, sync::Arc, time::Duration};
+
+/// How often the replica looks for blocks written by the node
+const HEAD_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Serve EVM JSON-RPC from `db_dir` until interrupted
+pub async fn run(
+    chain_id: u64,
+    genesis_hash: B256,
+    db_dir: &Path,
+    readonly: bool,
+    port: u16,
+) -> eyre::Result<()> {
+    if !readonly {
+        return Err(eyre::eyre!(
+            "rpc-serve shares the datadir of a running node and needs --readonly"
+        ));
+    }
+
+    let storage = Arc::new(DualvmStorage::open_read_only(db_dir)?);
+    storage.blocks.check_genesis(chain_id, genesis_hash)?;
+    tracing::info!(
+        "Serving {} read-only at block {}",
+        db_dir.display(),
+        storage.blocks.latest_block_number()
+    );
+
+    let (handle, server, _) = dex_rpc::start_evm_rpc_server(
+        chain_id,
+        storage.state.clone(),
+        storage.blocks.clone(),
+        port,
+        None,
+    )
+    .await?;
+    server.set_storage(Arc::clone(&storage));
+    server.set_read_only(true);
+    server.set_client_features(dex_rpc::ClientFeatures {
+        receipts_persisted: true,
+        ..Default::default()
+    });
+    tracing::info!("Read-only EVM JSON-RPC available at: http://127.0.0.1:{}", port);
+
+    let blocks = Arc::clone(&storage.blocks);
+    let poll = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HEAD_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = blocks.refresh_latest_block_number() {
+                tracing::warn!("Failed to read the stored head: {}", e);
+            }
+        }
+    });
+
+    tokio::signal::ctrl_c().await?;
+    tracing::info!("Shutting down read-only replica");
+    poll.abort();
+    handle.stop()?;
+    Ok(())
+}
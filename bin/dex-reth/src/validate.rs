@@ -0,0 +1,393 @@
+//! Startup configuration checks
+//!
+//! Everything that can be checked before the node opens its database or binds
+//! a port is checked here: genesis, mode flags, ports, the validator key and
+//! set, data directories and peer lists. Every problem found is reported together, each
+//! with a suggested fix, instead of the node stopping at the first one or
+//! failing halfway through startup.
+
+use crate::{load_genesis, network::{self, Network}, trace_dir, Cli, LoadedGenesis};
+use alloy_primitives::Address;
+use dex_node::{BlockLimits, FeePolicy, Genesis, NodeMode, PoaConfig};
+use dex_p2p::PeerId;
+use reth_network_peers::TrustedPeer;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, Ipv4Addr, TcpListener},
+    path::Path,
+    time::Duration,
+};
+
+/// File written and removed to check that a directory is writable
+const WRITE_PROBE_FILE: &str = ".write-probe";
+
+/// A misconfiguration and how to fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    pub problem: String,
+    pub fix: String,
+}
+
+/// Problems found so far
+#[derive(Debug, Default)]
+pub struct ConfigCheck {
+    problems: Vec<ConfigProblem>,
+}
+
+impl ConfigCheck {
+    /// Record a problem and its suggested fix
+    pub fn report(&mut self, problem: impl Into<String>, fix: impl Into<String>) {
+        self.problems.push(ConfigProblem { problem: problem.into(), fix: fix.into() });
+    }
+
+    /// Fail with every recorded problem, if there is any
+    pub fn finish(self) -> eyre::Result<()> {
+        if self.problems.is_empty() {
+            return Ok(());
+        }
+        Err(eyre::eyre!("{}", self))
+    }
+}
+
+impl fmt::Display for ConfigCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration ({} problems):", self.problems.len())?;
+        for ConfigProblem { problem, fix } in &self.problems {
+            write!(f, "\n  - {}\n    fix: {}", problem, fix)?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings of the node derived while checking its flags
+#[derive(Debug)]
+pub struct NodeSettings {
+    pub mode: NodeMode,
+    pub trusted_peers: Vec<PeerId>,
+    pub bootnodes: Vec<TrustedPeer>,
+    pub private_tx_peers: Vec<PeerId>,
+    pub validators: Vec<Address>,
+}
+
+/// Load the genesis from `--genesis` or the `--network` preset
+///
+/// Falls back to the preset's chain with no allocations if there is no usable
+/// genesis, so the remaining checks still run.
+pub fn genesis(check: &mut ConfigCheck, cli: &Cli, network: Network) -> LoadedGenesis {
+    let fallback = (
+        Genesis::with_balances(network.chain_id(), HashMap::new()),
+        FeePolicy::default(),
+        BlockLimits::default(),
+    );
+    let data = match &cli.genesis {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) => {
+                check.report(
+                    format!("Cannot read genesis file {}: {}", path.display(), e),
+                    "pass the path of an existing genesis JSON file to --genesis",
+                );
+                return fallback;
+            }
+        },
+        None => match network.genesis() {
+            Some(data) => data.to_string(),
+            None => return fallback,
+        },
+    };
+
+    let genesis = match load_genesis(&data) {
+        Ok(genesis) => genesis,
+        Err(e) => {
+            check.report(
+                format!("Invalid genesis: {}", e),
+                "fix the genesis JSON (geth genesis fields, config.feePolicy, config.blockLimits)",
+            );
+            return fallback;
+        }
+    };
+
+    if let Some(network) = cli.network {
+        if network.chain_id() != genesis.0.config.chain_id {
+            check.report(
+                format!(
+                    "--network {} is chain {} but the genesis file is chain {}",
+                    network,
+                    network.chain_id(),
+                    genesis.0.config.chain_id
+                ),
+                "drop --network, or pass a genesis file for the preset's chain",
+            );
+        }
+    }
+    system_addresses(check, &genesis.0);
+    genesis
+}
+
+/// Check the system address registry, and that genesis puts no code at a reserved address
+fn system_addresses(check: &mut ConfigCheck, genesis: &Genesis) {
+    let registry = dex_dexvm::system_registry();
+    if let Err(e) = registry.validate() {
+        let fix = "move the handler to an address in its range";
+        check.report(format!("Invalid system addresses: {}", e), fix);
+    }
+    let reserved = genesis.alloc.iter().filter(|(address, account)| {
+        let has_code = account.code.as_ref().is_some_and(|code| !code.is_empty());
+        has_code && registry.is_reserved(**address)
+    });
+    for (address, _) in reserved {
+        check.report(
+            format!("Genesis puts code at reserved system address {}", address),
+            "deploy the contract elsewhere; 0x00-0x1ff and the DexVM router are reserved",
+        );
+    }
+}
+
+/// Check the flags of a node run
+pub fn node(check: &mut ConfigCheck, cli: &Cli, chain_dir: &Path) -> NodeSettings {
+    let mode = mode(check, cli);
+    let p2p_enabled = !cli.disable_p2p && mode != NodeMode::RpcOnly;
+
+    ports(check, cli, p2p_enabled);
+    validator_key(check, cli, mode);
+    let validators = validator_set(check, cli, mode);
+    data_dirs(check, cli, chain_dir);
+    rpc_audit(check, cli);
+    let (trusted_peers, bootnodes, private_tx_peers) =
+        if p2p_enabled { peers(check, cli) } else { Default::default() };
+
+    NodeSettings { mode, trusted_peers, bootnodes, private_tx_peers, validators }
+}
+
+/// Resolve the node mode, reporting conflicting flags
+fn mode(check: &mut ConfigCheck, cli: &Cli) -> NodeMode {
+    let mode = cli.mode.unwrap_or(NodeMode::from_flags(cli.enable_consensus, !cli.disable_p2p));
+    if cli.enable_consensus && mode != NodeMode::Validator {
+        check.report(
+            format!("--enable-consensus conflicts with --mode {}", mode),
+            "drop --enable-consensus, or use --mode validator",
+        );
+    }
+    if cli.disable_p2p && !mode.p2p_optional() {
+        check.report(
+            format!("--mode {} syncs from peers and needs P2P", mode),
+            "drop --disable-p2p, or use --mode rpc-only",
+        );
+    }
+    mode
+}
+
+/// Ports must differ from each other and be free
+fn ports(check: &mut ConfigCheck, cli: &Cli, p2p_enabled: bool) {
+    let any = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+    let mut ports =
+        vec![("--evm-rpc-port", any, cli.evm_rpc_port), ("--dexvm-port", any, cli.dexvm_port)];
+    if p2p_enabled {
+        ports.push(("--p2p-port", cli.p2p_addr, cli.p2p_port));
+        if cli.p2p_dual_stack && cli.p2p_addr.is_ipv4() {
+            check.report(
+                format!("--p2p-dual-stack needs an IPv6 --p2p-addr, not {}", cli.p2p_addr),
+                "pass --p2p-addr :: to listen on IPv6 and IPv4, or drop --p2p-dual-stack",
+            );
+        }
+    }
+
+    for (i, (flag, ip, port)) in ports.iter().enumerate() {
+        // Port 0 lets the OS pick a free port
+        if *port == 0 {
+            continue;
+        }
+        if let Some((other, _, _)) = ports[..i].iter().find(|(_, _, p)| p == port) {
+            check.report(
+                format!("{} {} is also used by {}", flag, port, other),
+                format!("pass a different {}", flag),
+            );
+            continue;
+        }
+        if let Err(e) = TcpListener::bind((*ip, *port)) {
+            check.report(
+                format!("{} {} is not available: {}", flag, port, e),
+                format!("stop the process listening on port {} or pass a different {}", port, flag),
+            );
+        }
+    }
+}
+
+/// A validator needs a usable key; a key given anyway must still be valid
+fn validator_key(check: &mut ConfigCheck, cli: &Cli, mode: NodeMode) {
+    let Err(e) = PoaConfig::from_hex_key(&cli.validator_key, Duration::ZERO) else {
+        return;
+    };
+    let fix = if mode.produces_blocks() {
+        "pass a 32-byte hex secp256k1 private key to --validator-key"
+    } else {
+        "fix --validator-key or drop it; it is only used with --enable-consensus"
+    };
+    check.report(format!("--validator-key is invalid: {}", e), fix);
+}
+
+/// Validator addresses must parse, and a validator must be among them to propose blocks
+///
+/// Without `--validators`, the address of `--validator-key` is the only validator,
+/// so a fullnode still checks who signed the blocks it syncs.
+fn validator_set(check: &mut ConfigCheck, cli: &Cli, mode: NodeMode) -> Vec<Address> {
+    let mut validators = Vec::new();
+    for validator in &cli.validators {
+        match validator.parse::<Address>() {
+            Ok(address) => validators.push(address),
+            Err(e) => check.report(
+                format!("Invalid validator address '{}': {}", validator, e),
+                "pass 20-byte hex addresses to --validators",
+            ),
+        }
+    }
+    let Ok(config) = PoaConfig::from_hex_key(&cli.validator_key, Duration::ZERO) else {
+        // Reported by the validator key check
+        return validators;
+    };
+    if cli.validators.is_empty() {
+        return vec![config.validator];
+    }
+    if mode.produces_blocks() && !validators.contains(&config.validator) {
+        check.report(
+            format!("--validators leaves out this validator ({})", config.validator),
+            "add the address of --validator-key to --validators, in the same order on every \
+             validator",
+        );
+    }
+    validators
+}
+
+/// The chain directory and trace directory must be writable
+fn data_dirs(check: &mut ConfigCheck, cli: &Cli, chain_dir: &Path) {
+    if !cli.ephemeral {
+        if let Err(e) = check_writable(chain_dir) {
+            check.report(
+                format!("Data directory {} is not writable: {}", chain_dir.display(), e),
+                "fix its permissions, pass a writable --datadir, or run with --ephemeral",
+            );
+        }
+    }
+
+    if cli.trace_blocks {
+        match trace_dir(cli, chain_dir) {
+            Some(dir) => {
+                if let Err(e) = check_writable(&dir) {
+                    check.report(
+                        format!("Trace directory {} is not writable: {}", dir.display(), e),
+                        "fix its permissions or pass a writable --trace-dir",
+                    );
+                }
+            }
+            None => check.report(
+                "--trace-blocks with --ephemeral has nowhere to write traces",
+                "pass a --trace-dir",
+            ),
+        }
+    }
+
+    if let Some(dir) = &cli.backup_dir {
+        if cli.ephemeral {
+            check.report(
+                "--backup-dir with --ephemeral has no database to back up",
+                "drop --backup-dir, or run without --ephemeral",
+            );
+        } else if dir.starts_with(chain_dir.join(network::DB_DIR)) {
+            check.report(
+                format!("Backup directory {} is inside the database", dir.display()),
+                "pass a --backup-dir outside <datadir>/<chain_id>/db",
+            );
+        } else if let Err(e) = check_writable(dir) {
+            check.report(
+                format!("Backup directory {} is not writable: {}", dir.display(), e),
+                "fix its permissions or pass a writable --backup-dir",
+            );
+        }
+    } else if cli.backup_interval_mins > 0 {
+        check.report(
+            "--backup-interval-mins without --backup-dir has nowhere to write backups",
+            "add --backup-dir",
+        );
+    }
+
+    if cli.disk_budget_gb > 0 && cli.ephemeral {
+        check.report(
+            "--disk-budget-gb with --ephemeral has no database file to budget",
+            "drop --disk-budget-gb, or run without --ephemeral",
+        );
+    }
+}
+
+/// The audit log must be writable and its sample rate a fraction
+fn rpc_audit(check: &mut ConfigCheck, cli: &Cli) {
+    if !(0.0..=1.0).contains(&cli.rpc_audit_sample_rate) {
+        check.report(
+            format!("--rpc-audit-sample-rate {} is not a fraction", cli.rpc_audit_sample_rate),
+            "pass a value between 0.0 and 1.0",
+        );
+    }
+    let Some(path) = &cli.rpc_audit_log else {
+        return;
+    };
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    if let Err(e) = check_writable(dir) {
+        check.report(
+            format!("Audit log directory {} is not writable: {}", dir.display(), e),
+            "fix its permissions or pass a writable --rpc-audit-log",
+        );
+    }
+}
+
+/// Create `dir` if needed and write a file into it
+fn check_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(WRITE_PROBE_FILE);
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
+
+/// Parse peer IDs given as enode URLs or hex peer IDs
+fn peer_ids(check: &mut ConfigCheck, kind: &str, values: &[String]) -> Vec<PeerId> {
+    let mut peer_ids = Vec::new();
+    for value in values {
+        let peer_id = value
+            .parse::<TrustedPeer>()
+            .map(|peer| peer.id)
+            .or_else(|_| value.parse::<PeerId>());
+        match peer_id {
+            Ok(peer_id) => peer_ids.push(peer_id),
+            Err(e) => check.report(
+                format!("Invalid {} '{}': {}", kind, value, e),
+                "pass enode://<id>@<host>:<port> URLs or 128-character hex peer IDs",
+            ),
+        }
+    }
+    peer_ids
+}
+
+/// Parse trusted peers, boot nodes and private transaction peers
+fn peers(check: &mut ConfigCheck, cli: &Cli) -> (Vec<PeerId>, Vec<TrustedPeer>, Vec<PeerId>) {
+    let trusted_peers = peer_ids(check, "trusted peer", &cli.trusted_peers);
+    if cli.trusted_only && cli.trusted_peers.is_empty() {
+        check.report(
+            "--trusted-only without --trusted-peers refuses every session",
+            "add --trusted-peers, or drop --trusted-only",
+        );
+    }
+
+    let mut bootnodes = Vec::new();
+    for bootnode in &cli.bootnodes {
+        match bootnode.parse::<TrustedPeer>() {
+            Ok(peer) => bootnodes.push(peer),
+            Err(e) => check.report(
+                format!("Invalid bootnode URL '{}': {}", bootnode, e),
+                "pass enode://<id>@<host>:<port> URLs to --bootnodes",
+            ),
+        }
+    }
+
+    let private_tx_peers = peer_ids(check, "private transaction peer", &cli.private_tx_peers);
+
+    (trusted_peers, bootnodes, private_tx_peers)
+}
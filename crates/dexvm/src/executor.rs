@@ -1,5 +1,6 @@
-use crate::state::DexVmState;
-use dex_primitives::{DexVmExecutionResult, DexVmOperation, DexVmTransaction};
+use crate::{precompiles::record_counter_access, state::DexVmState};
+use alloy_primitives::{Address, U256};
+use dex_primitives::{AccessSet, DexVmExecutionResult, DexVmOperation, DexVmTransaction};
 use reth_execution_errors::BlockExecutionError;
 
 /// Gas cost constants for DexVM operations
@@ -7,6 +8,40 @@ const BASE_GAS: u64 = 21000;
 const INCREMENT_GAS: u64 = 5000;
 const DECREMENT_GAS: u64 = 5000;
 const QUERY_GAS: u64 = 3000;
+/// Cold account and storage read costs, as in EIP-2929
+const EVM_BALANCE_GAS: u64 = 2600;
+const EVM_STORAGE_GAS: u64 = 2100;
+
+/// Read-only view of EVM state for DexVM operations
+///
+/// The reverse of the counter precompile: DexVM transactions can read EVM
+/// balances and storage but never change them.
+pub trait EvmStateReader {
+    /// Balance of an EVM account, zero if it does not exist
+    fn balance(&self, address: &Address) -> U256;
+
+    /// Value of an EVM storage slot, zero if unset
+    fn storage(&self, address: &Address, slot: U256) -> U256;
+}
+
+/// Record the state a DexVM operation accesses
+///
+/// Counter operations access the sender's counter, written only if
+/// `succeeded` and not a query; EVM reads only read the EVM account or slot.
+pub fn record_operation_access(
+    access: &mut AccessSet,
+    from: Address,
+    operation: DexVmOperation,
+    succeeded: bool,
+) {
+    match operation {
+        DexVmOperation::EvmBalance(address) => access.read_account(address),
+        DexVmOperation::EvmStorage(address, slot) => access.read_slot(address, slot.into()),
+        operation => {
+            record_counter_access(access, from, succeeded && operation != DexVmOperation::Query)
+        }
+    }
+}
 
 /// DexVM executor
 ///
@@ -31,8 +66,18 @@ impl DexVmExecutor {
     pub fn execute_transaction(
         &mut self,
         tx: &DexVmTransaction,
+    ) -> Result<DexVmExecutionResult, BlockExecutionError> {
+        self.execute_transaction_with_evm(tx, None)
+    }
+
+    /// Execute a transaction with EVM state for EVM reads
+    pub fn execute_transaction_with_evm(
+        &mut self,
+        tx: &DexVmTransaction,
+        evm_state: Option<&dyn EvmStateReader>,
     ) -> Result<DexVmExecutionResult, BlockExecutionError> {
         let old_counter = self.pending_state.get_counter(&tx.from);
+        let mut evm_value = None;
 
         let (success, new_counter, gas_used, error) = match tx.operation {
             DexVmOperation::Increment(amount) => {
@@ -46,11 +91,42 @@ impl DexVmExecutor {
                 }
             }
             DexVmOperation::Query => (true, old_counter, BASE_GAS + QUERY_GAS, None),
+            DexVmOperation::EvmBalance(address) => {
+                evm_value = Some(Self::require_evm(evm_state)?.balance(&address));
+                (true, old_counter, BASE_GAS + EVM_BALANCE_GAS, None)
+            }
+            DexVmOperation::EvmStorage(address, slot) => {
+                evm_value = Some(Self::require_evm(evm_state)?.storage(&address, slot));
+                (true, old_counter, BASE_GAS + EVM_STORAGE_GAS, None)
+            }
         };
 
         self.has_pending = true;
 
-        Ok(DexVmExecutionResult { success, old_counter, new_counter, gas_used, error })
+        Ok(DexVmExecutionResult { success, old_counter, new_counter, gas_used, error, evm_value })
+    }
+
+    fn require_evm(
+        evm_state: Option<&dyn EvmStateReader>,
+    ) -> Result<&dyn EvmStateReader, BlockExecutionError> {
+        evm_state.ok_or_else(|| BlockExecutionError::msg("EVM state required for EVM reads"))
+    }
+
+    /// Fail a transaction without executing it, leaving the counter unchanged
+    pub fn reject_transaction(
+        &self,
+        tx: &DexVmTransaction,
+        error: impl Into<String>,
+    ) -> DexVmExecutionResult {
+        let counter = self.pending_state.get_counter(&tx.from);
+        DexVmExecutionResult {
+            success: false,
+            old_counter: counter,
+            new_counter: counter,
+            gas_used: BASE_GAS,
+            error: Some(error.into()),
+            evm_value: None,
+        }
     }
 
     /// Commit pending state changes
@@ -85,6 +161,14 @@ impl DexVmExecutor {
         &mut self.pending_state
     }
 
+    /// Set a counter in both committed and pending state, outside block execution
+    ///
+    /// For dev resets; production changes go through transactions.
+    pub fn set_counter(&mut self, address: Address, value: u64) {
+        self.state.set_counter(address, value);
+        self.pending_state.set_counter(address, value);
+    }
+
     /// Get state root (from committed state)
     pub fn state_root(&self) -> alloy_primitives::B256 {
         self.state.state_root()
@@ -106,14 +190,36 @@ impl DexVmExecutor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy_primitives::address;
+    use alloy_primitives::{address, B256};
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MockEvm {
+        balances: HashMap<Address, U256>,
+        storage: HashMap<(Address, U256), U256>,
+    }
+
+    impl EvmStateReader for MockEvm {
+        fn balance(&self, address: &Address) -> U256 {
+            self.balances.get(address).copied().unwrap_or_default()
+        }
+
+        fn storage(&self, address: &Address, slot: U256) -> U256 {
+            self.storage.get(&(*address, slot)).copied().unwrap_or_default()
+        }
+    }
 
     #[test]
     fn test_increment_transaction() {
         let mut executor = DexVmExecutor::new(DexVmState::new());
         let from = address!("1111111111111111111111111111111111111111");
 
-        let tx = DexVmTransaction { from, operation: DexVmOperation::Increment(10), signature: vec![] };
+        let tx = DexVmTransaction {
+            from,
+            operation: DexVmOperation::Increment(10),
+            nonce: 0,
+            signature: vec![],
+        };
 
         let result = executor.execute_transaction(&tx).unwrap();
         assert!(result.success);
@@ -134,7 +240,12 @@ mod tests {
 
         let mut executor = DexVmExecutor::new(state);
 
-        let tx = DexVmTransaction { from, operation: DexVmOperation::Decrement(30), signature: vec![] };
+        let tx = DexVmTransaction {
+            from,
+            operation: DexVmOperation::Decrement(30),
+            nonce: 0,
+            signature: vec![],
+        };
 
         let result = executor.execute_transaction(&tx).unwrap();
         assert!(result.success);
@@ -153,7 +264,12 @@ mod tests {
 
         let mut executor = DexVmExecutor::new(state);
 
-        let tx = DexVmTransaction { from, operation: DexVmOperation::Decrement(100), signature: vec![] };
+        let tx = DexVmTransaction {
+            from,
+            operation: DexVmOperation::Decrement(100),
+            nonce: 0,
+            signature: vec![],
+        };
 
         let result = executor.execute_transaction(&tx).unwrap();
         assert!(!result.success);
@@ -167,7 +283,12 @@ mod tests {
         let mut executor = DexVmExecutor::new(DexVmState::new());
         let from = address!("4444444444444444444444444444444444444444");
 
-        let tx = DexVmTransaction { from, operation: DexVmOperation::Increment(50), signature: vec![] };
+        let tx = DexVmTransaction {
+            from,
+            operation: DexVmOperation::Increment(50),
+            nonce: 0,
+            signature: vec![],
+        };
 
         executor.execute_transaction(&tx).unwrap();
         assert!(executor.has_pending_changes());
@@ -186,7 +307,12 @@ mod tests {
 
         let mut executor = DexVmExecutor::new(state);
 
-        let tx = DexVmTransaction { from, operation: DexVmOperation::Query, signature: vec![] };
+        let tx = DexVmTransaction {
+            from,
+            operation: DexVmOperation::Query,
+            nonce: 0,
+            signature: vec![],
+        };
 
         let result = executor.execute_transaction(&tx).unwrap();
         assert!(result.success);
@@ -194,4 +320,46 @@ mod tests {
         assert_eq!(result.new_counter, 42); // Query doesn't change value
         assert_eq!(result.gas_used, BASE_GAS + QUERY_GAS);
     }
+
+    #[test]
+    fn test_evm_reads() {
+        let from = address!("6666666666666666666666666666666666666666");
+        let account = address!("7777777777777777777777777777777777777777");
+        let mut evm = MockEvm::default();
+        evm.balances.insert(account, U256::from(1_000));
+        evm.storage.insert((account, U256::from(3)), U256::from(42));
+
+        let mut state = DexVmState::new();
+        state.set_counter(from, 5);
+        let mut executor = DexVmExecutor::new(state);
+
+        let balance = DexVmTransaction {
+            from,
+            operation: DexVmOperation::EvmBalance(account),
+            nonce: 0,
+            signature: vec![],
+        };
+        let result = executor.execute_transaction_with_evm(&balance, Some(&evm)).unwrap();
+        assert!(result.success);
+        assert_eq!(result.evm_value, Some(U256::from(1_000)));
+        assert_eq!(result.gas_used, BASE_GAS + EVM_BALANCE_GAS);
+        assert_eq!((result.old_counter, result.new_counter), (5, 5));
+
+        let storage = DexVmTransaction {
+            from,
+            operation: DexVmOperation::EvmStorage(account, U256::from(3)),
+            nonce: 0,
+            signature: vec![],
+        };
+        let result = executor.execute_transaction_with_evm(&storage, Some(&evm)).unwrap();
+        assert_eq!(result.evm_value, Some(U256::from(42)));
+
+        // Without EVM state the read cannot run
+        assert!(executor.execute_transaction(&storage).is_err());
+
+        let mut access = AccessSet::new();
+        record_operation_access(&mut access, from, storage.operation, true);
+        assert!(access.slots_read.contains(&(account, B256::from(U256::from(3)))));
+        assert!(access.slots_written.is_empty());
+    }
 }
@@ -3,15 +3,23 @@
 //! A simple counter-based virtual machine for the dual VM system.
 
 pub mod executor;
+pub mod pool;
 pub mod precompiles;
+pub mod snapshot;
 pub mod state;
 
-pub use executor::DexVmExecutor;
+pub use executor::{record_operation_access, DexVmExecutor, EvmStateReader};
+pub use pool::{DexVmPool, DEFAULT_MAX_POOL_SIZE};
 pub use precompiles::{
+    record_counter_access, record_labeled_counter_access, sends_value_to_dexvm,
     PrecompileExecutor, PrecompileOperation, PrecompileResult, COUNTER_PRECOMPILE_ADDRESS,
-    OP_DECREMENT, OP_INCREMENT, OP_QUERY,
+    DEXVM_VALUE_ERROR, MAX_BATCH_OPERATIONS, MAX_TOP_COUNTERS, OP_BATCH, OP_DECREMENT,
+    OP_INCREMENT, OP_LABELED_DECREMENT, OP_LABELED_INCREMENT, OP_LABELED_QUERY, OP_QUERY, OP_TOP,
 };
-pub use state::DexVmState;
+pub use snapshot::{DexVmSnapshot, SNAPSHOT_VERSION};
+pub use state::{DexVmState, StateCheckpoint};
 
 // Re-export transaction types for convenience
-pub use dex_primitives::{DexVmOperation, DexVmTransaction};
+pub use dex_primitives::{
+    system_registry, DexVmOperation, DexVmTransaction, PrecompileRegistry, SystemHandler,
+};
@@ -0,0 +1,151 @@
+//! DexVM pending transaction queue
+//!
+//! Native DexVM transactions submitted through the API wait here until the
+//! block builder drains them. Transactions are drained in submission order.
+
+use alloy_primitives::{Address, B256};
+use dex_primitives::DexVmTransaction;
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard},
+};
+
+/// Default maximum number of queued DexVM transactions
+pub const DEFAULT_MAX_POOL_SIZE: usize = 10_000;
+
+/// FIFO queue of native DexVM transactions awaiting inclusion
+#[derive(Debug)]
+pub struct DexVmPool {
+    queue: Mutex<VecDeque<DexVmTransaction>>,
+    max_size: usize,
+}
+
+impl DexVmPool {
+    /// Create a pool holding at most `max_size` transactions
+    pub fn new(max_size: usize) -> Self {
+        Self { queue: Mutex::new(VecDeque::new()), max_size }
+    }
+
+    fn queue(&self) -> MutexGuard<'_, VecDeque<DexVmTransaction>> {
+        // The queue holds plain data, so a poisoned lock is still usable
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a transaction, returning its hash and queue position
+    pub fn submit(&self, tx: DexVmTransaction) -> Result<(B256, usize), String> {
+        let mut queue = self.queue();
+        if queue.len() >= self.max_size {
+            return Err(format!("DexVM pool is full ({} transactions)", self.max_size));
+        }
+
+        let hash = tx.hash();
+        queue.push_back(tx);
+        Ok((hash, queue.len() - 1))
+    }
+
+    /// Remove up to `max` transactions in submission order
+    pub fn drain(&self, max: usize) -> Vec<DexVmTransaction> {
+        let mut queue = self.queue();
+        let count = max.min(queue.len());
+        queue.drain(..count).collect()
+    }
+
+    /// Put transactions back at the front of the queue, keeping their order
+    ///
+    /// Used for transactions drained but not included in a block.
+    pub fn requeue(&self, txs: Vec<DexVmTransaction>) {
+        let mut queue = self.queue();
+        for tx in txs.into_iter().rev() {
+            queue.push_front(tx);
+        }
+    }
+
+    /// Drop every queued transaction from `sender`, returning how many were dropped
+    pub fn remove_sender(&self, sender: &Address) -> usize {
+        let mut queue = self.queue();
+        let before = queue.len();
+        queue.retain(|tx| tx.from != *sender);
+        before - queue.len()
+    }
+
+    /// Number of queued transactions
+    pub fn len(&self) -> usize {
+        self.queue().len()
+    }
+
+    /// Check if the pool is empty
+    pub fn is_empty(&self) -> bool {
+        self.queue().is_empty()
+    }
+}
+
+impl Default for DexVmPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_POOL_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_primitives::DexVmOperation;
+
+    fn tx(byte: u8, amount: u64) -> DexVmTransaction {
+        DexVmTransaction {
+            from: Address::repeat_byte(byte),
+            operation: DexVmOperation::Increment(amount),
+            nonce: 0,
+            signature: vec![],
+        }
+    }
+
+    #[test]
+    fn test_drain_in_submission_order() {
+        let pool = DexVmPool::new(10);
+        pool.submit(tx(2, 1)).unwrap();
+        pool.submit(tx(1, 2)).unwrap();
+        let (_, position) = pool.submit(tx(3, 3)).unwrap();
+        assert_eq!(position, 2);
+
+        let drained = pool.drain(2);
+        assert_eq!(drained[0].from, Address::repeat_byte(2));
+        assert_eq!(drained[1].from, Address::repeat_byte(1));
+        assert_eq!(pool.len(), 1);
+
+        assert_eq!(pool.drain(10).len(), 1);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_requeue_keeps_order() {
+        let pool = DexVmPool::new(10);
+        pool.submit(tx(3, 1)).unwrap();
+        pool.requeue(vec![tx(1, 1), tx(2, 1)]);
+
+        let drained = pool.drain(3);
+        let senders: Vec<_> = drained.iter().map(|tx| tx.from).collect();
+        assert_eq!(
+            senders,
+            vec![Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(3)]
+        );
+    }
+
+    #[test]
+    fn test_remove_sender() {
+        let pool = DexVmPool::new(10);
+        pool.submit(tx(1, 1)).unwrap();
+        pool.submit(tx(2, 1)).unwrap();
+        pool.submit(tx(1, 2)).unwrap();
+
+        assert_eq!(pool.remove_sender(&Address::repeat_byte(1)), 2);
+        assert_eq!(pool.remove_sender(&Address::repeat_byte(1)), 0);
+        assert_eq!(pool.drain(10)[0].from, Address::repeat_byte(2));
+    }
+
+    #[test]
+    fn test_pool_capacity() {
+        let pool = DexVmPool::new(1);
+        assert!(pool.submit(tx(1, 1)).is_ok());
+        assert!(pool.submit(tx(1, 1)).is_err());
+    }
+}
@@ -1,18 +1,60 @@
 use crate::state::DexVmState;
-use alloy_primitives::Address;
+use alloy_primitives::{Address, B256, U256};
+use dex_primitives::{labeled_counter_slot, system_handler, AccessSet};
 use reth_execution_errors::BlockExecutionError;
 
-/// Counter precompile address (for EVM → DexVM cross-VM calls)
-pub const COUNTER_PRECOMPILE_ADDRESS: Address =
-    alloy_primitives::address!("0000000000000000000000000000000000000100");
+pub use dex_primitives::COUNTER_PRECOMPILE_ADDRESS;
 
 /// Counter operation opcodes
 pub const OP_INCREMENT: u8 = 0x00;
 pub const OP_DECREMENT: u8 = 0x01;
 pub const OP_QUERY: u8 = 0x02;
+pub const OP_BATCH: u8 = 0x03;
+pub const OP_TOP: u8 = 0x04;
+pub const OP_LABELED_INCREMENT: u8 = 0x05;
+pub const OP_LABELED_DECREMENT: u8 = 0x06;
+pub const OP_LABELED_QUERY: u8 = 0x07;
+
+/// Maximum number of entries in a batch operation
+pub const MAX_BATCH_OPERATIONS: usize = 64;
+
+/// Maximum number of counters a top query returns
+pub const MAX_TOP_COUNTERS: usize = 16;
+
+/// Failure reason of calls that send ETH into DexVM
+pub const DEXVM_VALUE_ERROR: &str = "DexVM calls cannot carry value";
+
+/// Whether a call sends ETH to the counter precompile or the DexVM router
+///
+/// DexVM holds no ETH, so the value would be lost. Such calls fail without
+/// moving it, in both the EVM and the DexVM executor.
+pub fn sends_value_to_dexvm(to: Option<Address>, value: U256) -> bool {
+    system_handler(to).is_some() && !value.is_zero()
+}
+
+/// Record a DexVM counter access as a slot of the counter precompile account
+///
+/// The counter is always read; it is recorded as written only if `written` is set.
+pub fn record_counter_access(access: &mut AccessSet, owner: Address, written: bool) {
+    record_labeled_counter_access(access, owner, B256::ZERO, written);
+}
+
+/// Record an access to one of an owner's labeled counters, like [`record_counter_access`]
+pub fn record_labeled_counter_access(
+    access: &mut AccessSet,
+    owner: Address,
+    label: B256,
+    written: bool,
+) {
+    let slot = labeled_counter_slot(owner, label);
+    access.read_slot(COUNTER_PRECOMPILE_ADDRESS, slot);
+    if written {
+        access.write_slot(COUNTER_PRECOMPILE_ADDRESS, slot);
+    }
+}
 
 /// Precompile operation type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PrecompileOperation {
     /// Increment counter - calldata: [0x00][amount: 8 bytes]
     IncrementCounter(u64),
@@ -20,6 +62,19 @@ pub enum PrecompileOperation {
     DecrementCounter(u64),
     /// Query counter - calldata: [0x02][padding: 8 bytes]
     QueryCounter,
+    /// Apply increments/decrements atomically - calldata: [0x03]([op: 1 byte][amount: 8 bytes])*
+    BatchCounter(Vec<PrecompileOperation>),
+    /// Highest counters of all accounts - calldata: [0x04][count: 8 bytes]
+    ///
+    /// Returns `[address: 20 bytes][value: 8 bytes]` per entry, largest first.
+    /// Access sets only record the caller's counter, not the ones ranked.
+    TopCounters(usize),
+    /// Increment a labeled counter - calldata: [0x05][label: 32 bytes][amount: 8 bytes]
+    LabeledIncrement(B256, u64),
+    /// Decrement a labeled counter - calldata: [0x06][label: 32 bytes][amount: 8 bytes]
+    LabeledDecrement(B256, u64),
+    /// Query a labeled counter - calldata: [0x07][label: 32 bytes][padding: 8 bytes]
+    LabeledQuery(B256),
     /// Invalid operation
     Invalid,
 }
@@ -41,6 +96,11 @@ pub struct PrecompileResult {
 const COUNTER_INCREMENT_GAS: u64 = 26000;
 const COUNTER_DECREMENT_GAS: u64 = 26000;
 const COUNTER_QUERY_GAS: u64 = 24000;
+const COUNTER_BATCH_BASE_GAS: u64 = 21000;
+const COUNTER_BATCH_OP_GAS: u64 = 5000;
+const COUNTER_TOP_BASE_GAS: u64 = 24000;
+const COUNTER_TOP_ENTRY_GAS: u64 = 2100;
+const INVALID_OPERATION_GAS: u64 = 3000;
 
 /// Precompile executor for counter operations
 #[derive(Debug, Default)]
@@ -52,6 +112,50 @@ impl PrecompileExecutor {
         Self
     }
 
+    /// Gas a call with this calldata costs, known before it runs
+    pub fn required_gas(input: &[u8]) -> u64 {
+        match Self::parse_operation(input) {
+            PrecompileOperation::IncrementCounter(_) |
+            PrecompileOperation::LabeledIncrement(..) => COUNTER_INCREMENT_GAS,
+            PrecompileOperation::DecrementCounter(_) |
+            PrecompileOperation::LabeledDecrement(..) => COUNTER_DECREMENT_GAS,
+            PrecompileOperation::QueryCounter | PrecompileOperation::LabeledQuery(_) => {
+                COUNTER_QUERY_GAS
+            }
+            PrecompileOperation::BatchCounter(operations) => {
+                COUNTER_BATCH_BASE_GAS + COUNTER_BATCH_OP_GAS * operations.len() as u64
+            }
+            PrecompileOperation::TopCounters(count) => {
+                COUNTER_TOP_BASE_GAS + COUNTER_TOP_ENTRY_GAS * count as u64
+            }
+            PrecompileOperation::Invalid => INVALID_OPERATION_GAS,
+        }
+    }
+
+    /// Execute a call that may use at most `gas_limit` gas
+    ///
+    /// A call that needs more fails as out of gas before touching DexVM state
+    /// and consumes the whole limit.
+    pub fn execute_with_gas_limit(
+        &self,
+        caller: Address,
+        to: Address,
+        input: &[u8],
+        gas_limit: u64,
+        dexvm_state: Option<&mut DexVmState>,
+    ) -> Result<PrecompileResult, BlockExecutionError> {
+        let required = Self::required_gas(input);
+        if required > gas_limit {
+            return Ok(PrecompileResult {
+                success: false,
+                return_data: vec![],
+                gas_used: gas_limit,
+                error: Some(format!("out of gas: need {}, limit {}", required, gas_limit)),
+            });
+        }
+        self.execute_with_dexvm(caller, to, input, dexvm_state)
+    }
+
     /// Execute precompile call with DexVM state for counter operations
     pub fn execute_with_dexvm(
         &self,
@@ -68,68 +172,89 @@ impl PrecompileExecutor {
 
         match operation {
             PrecompileOperation::IncrementCounter(amount) => {
+                Ok(Self::increment(Self::require_state(dexvm_state)?, caller, B256::ZERO, amount))
+            }
+            PrecompileOperation::DecrementCounter(amount) => {
+                Ok(Self::decrement(Self::require_state(dexvm_state)?, caller, B256::ZERO, amount))
+            }
+            PrecompileOperation::QueryCounter => {
+                Ok(Self::query(Self::require_state(dexvm_state)?, caller, B256::ZERO))
+            }
+            PrecompileOperation::LabeledIncrement(label, amount) => {
+                Ok(Self::increment(Self::require_state(dexvm_state)?, caller, label, amount))
+            }
+            PrecompileOperation::LabeledDecrement(label, amount) => {
+                Ok(Self::decrement(Self::require_state(dexvm_state)?, caller, label, amount))
+            }
+            PrecompileOperation::LabeledQuery(label) => {
+                Ok(Self::query(Self::require_state(dexvm_state)?, caller, label))
+            }
+            PrecompileOperation::BatchCounter(operations) => {
                 let dexvm = dexvm_state.ok_or_else(|| {
                     BlockExecutionError::msg("DexVM state required for counter operations")
                 })?;
 
-                let new_value = dexvm.increment_counter(caller, amount);
+                let gas_used =
+                    COUNTER_BATCH_BASE_GAS + COUNTER_BATCH_OP_GAS * operations.len() as u64;
+
+                // Apply to a local value so a failing entry leaves state untouched
+                let mut value = dexvm.get_counter(&caller);
+                for (index, operation) in operations.iter().enumerate() {
+                    value = match *operation {
+                        PrecompileOperation::IncrementCounter(amount) => value.saturating_add(amount),
+                        PrecompileOperation::DecrementCounter(amount) if amount <= value => {
+                            value - amount
+                        }
+                        PrecompileOperation::DecrementCounter(amount) => {
+                            let err = format!(
+                                "Batch entry {} failed: counter underflow: have {}, want to decrement {}",
+                                index, value, amount
+                            );
+                            tracing::warn!("Counter batch failed: address={}, error={}", caller, err);
+                            return Ok(PrecompileResult {
+                                success: false,
+                                return_data: vec![],
+                                gas_used,
+                                error: Some(err),
+                            });
+                        }
+                        _ => unreachable!("batch entries are validated when parsing"),
+                    };
+                }
+
+                dexvm.set_counter(caller, value);
                 tracing::debug!(
-                    "Counter increment: address={}, amount={}, new_value={}",
+                    "Counter batch: address={}, operations={}, new_value={}",
                     caller,
-                    amount,
-                    new_value
+                    operations.len(),
+                    value
                 );
 
                 Ok(PrecompileResult {
                     success: true,
-                    return_data: new_value.to_be_bytes().to_vec(),
-                    gas_used: COUNTER_INCREMENT_GAS,
+                    return_data: value.to_be_bytes().to_vec(),
+                    gas_used,
                     error: None,
                 })
             }
-            PrecompileOperation::DecrementCounter(amount) => {
+            PrecompileOperation::TopCounters(count) => {
                 let dexvm = dexvm_state.ok_or_else(|| {
                     BlockExecutionError::msg("DexVM state required for counter operations")
                 })?;
 
-                match dexvm.decrement_counter(caller, amount) {
-                    Ok(new_value) => {
-                        tracing::debug!(
-                            "Counter decrement: address={}, amount={}, new_value={}",
-                            caller,
-                            amount,
-                            new_value
-                        );
-                        Ok(PrecompileResult {
-                            success: true,
-                            return_data: new_value.to_be_bytes().to_vec(),
-                            gas_used: COUNTER_DECREMENT_GAS,
-                            error: None,
-                        })
-                    }
-                    Err(err) => {
-                        tracing::warn!("Counter decrement failed: address={}, error={}", caller, err);
-                        Ok(PrecompileResult {
-                            success: false,
-                            return_data: vec![],
-                            gas_used: COUNTER_DECREMENT_GAS,
-                            error: Some(err),
-                        })
-                    }
-                }
-            }
-            PrecompileOperation::QueryCounter => {
-                let dexvm = dexvm_state.ok_or_else(|| {
-                    BlockExecutionError::msg("DexVM state required for counter operations")
-                })?;
+                let top = dexvm.top_counters(count);
+                tracing::debug!("Counter top: count={}, returned={}", count, top.len());
 
-                let value = dexvm.get_counter(&caller);
-                tracing::debug!("Counter query: address={}, value={}", caller, value);
+                let mut return_data = Vec::with_capacity(top.len() * 28);
+                for (address, value) in top {
+                    return_data.extend_from_slice(address.as_slice());
+                    return_data.extend_from_slice(&value.to_be_bytes());
+                }
 
                 Ok(PrecompileResult {
                     success: true,
-                    return_data: value.to_be_bytes().to_vec(),
-                    gas_used: COUNTER_QUERY_GAS,
+                    return_data,
+                    gas_used: COUNTER_TOP_BASE_GAS + COUNTER_TOP_ENTRY_GAS * count as u64,
                     error: None,
                 })
             }
@@ -137,20 +262,134 @@ impl PrecompileExecutor {
                 Ok(PrecompileResult {
                     success: false,
                     return_data: vec![],
-                    gas_used: 3000,
+                    gas_used: INVALID_OPERATION_GAS,
                     error: Some("Invalid counter operation".to_string()),
                 })
             }
         }
     }
 
+    fn require_state(
+        dexvm_state: Option<&mut DexVmState>,
+    ) -> Result<&mut DexVmState, BlockExecutionError> {
+        dexvm_state
+            .ok_or_else(|| BlockExecutionError::msg("DexVM state required for counter operations"))
+    }
+
+    /// Increment the caller's counter under `label`, zero for the main counter
+    fn increment(
+        dexvm: &mut DexVmState,
+        caller: Address,
+        label: B256,
+        amount: u64,
+    ) -> PrecompileResult {
+        let new_value = dexvm.increment_labeled_counter(caller, label, amount);
+        tracing::debug!(
+            "Counter increment: address={}, label={}, amount={}, new_value={}",
+            caller,
+            label,
+            amount,
+            new_value
+        );
+
+        PrecompileResult {
+            success: true,
+            return_data: new_value.to_be_bytes().to_vec(),
+            gas_used: COUNTER_INCREMENT_GAS,
+            error: None,
+        }
+    }
+
+    /// Decrement the caller's counter under `label`, failing on underflow
+    fn decrement(
+        dexvm: &mut DexVmState,
+        caller: Address,
+        label: B256,
+        amount: u64,
+    ) -> PrecompileResult {
+        match dexvm.decrement_labeled_counter(caller, label, amount) {
+            Ok(new_value) => {
+                tracing::debug!(
+                    "Counter decrement: address={}, label={}, amount={}, new_value={}",
+                    caller,
+                    label,
+                    amount,
+                    new_value
+                );
+                PrecompileResult {
+                    success: true,
+                    return_data: new_value.to_be_bytes().to_vec(),
+                    gas_used: COUNTER_DECREMENT_GAS,
+                    error: None,
+                }
+            }
+            Err(err) => {
+                tracing::warn!("Counter decrement failed: address={}, error={}", caller, err);
+                PrecompileResult {
+                    success: false,
+                    return_data: vec![],
+                    gas_used: COUNTER_DECREMENT_GAS,
+                    error: Some(err),
+                }
+            }
+        }
+    }
+
+    /// Read the caller's counter under `label`
+    fn query(dexvm: &DexVmState, caller: Address, label: B256) -> PrecompileResult {
+        let value = dexvm.get_labeled_counter(&caller, label);
+        tracing::debug!("Counter query: address={}, label={}, value={}", caller, label, value);
+
+        PrecompileResult {
+            success: true,
+            return_data: value.to_be_bytes().to_vec(),
+            gas_used: COUNTER_QUERY_GAS,
+            error: None,
+        }
+    }
+
+    /// Whether a successful call with this calldata changes the caller's counter
+    pub fn writes_counter(input: &[u8]) -> bool {
+        matches!(
+            Self::parse_operation(input),
+            PrecompileOperation::IncrementCounter(_) |
+                PrecompileOperation::DecrementCounter(_) |
+                PrecompileOperation::BatchCounter(_) |
+                PrecompileOperation::LabeledIncrement(..) |
+                PrecompileOperation::LabeledDecrement(..)
+        )
+    }
+
+    /// Label of the caller's counter a call with this calldata touches
+    ///
+    /// Zero, the main counter, for everything but labeled operations.
+    pub fn counter_label(input: &[u8]) -> B256 {
+        match Self::parse_operation(input) {
+            PrecompileOperation::LabeledIncrement(label, _) |
+            PrecompileOperation::LabeledDecrement(label, _) |
+            PrecompileOperation::LabeledQuery(label) => label,
+            _ => B256::ZERO,
+        }
+    }
+
     /// Parse calldata to determine operation type
     ///
     /// Counter operation format: [op: 1 byte][amount: 8 bytes big-endian]
     /// - op = 0x00 → Increment
     /// - op = 0x01 → Decrement
     /// - op = 0x02 → Query
+    /// - op = 0x03 → Batch of increment/decrement entries in the same format
+    /// - op = 0x04 → Top counters, with the count in place of the amount
+    /// - op = 0x05..=0x07 → Labeled increment, decrement and query, with a
+    ///   32-byte label between the op and the amount
     fn parse_operation(input: &[u8]) -> PrecompileOperation {
+        if input.first() == Some(&OP_BATCH) {
+            return Self::parse_batch(&input[1..]);
+        }
+        if matches!(input.first(), Some(&(OP_LABELED_INCREMENT..=OP_LABELED_QUERY))) {
+            return Self::parse_labeled(input);
+        }
+
         if input.len() != 9 {
             return PrecompileOperation::Invalid;
         }
@@ -162,9 +401,51 @@ impl PrecompileExecutor {
             OP_INCREMENT => PrecompileOperation::IncrementCounter(amount),
             OP_DECREMENT => PrecompileOperation::DecrementCounter(amount),
             OP_QUERY => PrecompileOperation::QueryCounter,
+            OP_TOP if (1..=MAX_TOP_COUNTERS as u64).contains(&amount) => {
+                PrecompileOperation::TopCounters(amount as usize)
+            }
             _ => PrecompileOperation::Invalid,
         }
     }
+
+    /// Parse a `[op][label: 32 bytes][amount: 8 bytes]` labeled operation
+    fn parse_labeled(input: &[u8]) -> PrecompileOperation {
+        if input.len() != 41 {
+            return PrecompileOperation::Invalid;
+        }
+
+        let label = B256::from_slice(&input[1..33]);
+        let amount = u64::from_be_bytes(input[33..41].try_into().unwrap());
+
+        match input[0] {
+            OP_LABELED_INCREMENT => PrecompileOperation::LabeledIncrement(label, amount),
+            OP_LABELED_DECREMENT => PrecompileOperation::LabeledDecrement(label, amount),
+            OP_LABELED_QUERY => PrecompileOperation::LabeledQuery(label),
+            _ => PrecompileOperation::Invalid,
+        }
+    }
+
+    /// Parse packed `[op][amount]` batch entries (increment and decrement only)
+    fn parse_batch(entries: &[u8]) -> PrecompileOperation {
+        let count = entries.len() / 9;
+        if count == 0 || entries.len() % 9 != 0 || count > MAX_BATCH_OPERATIONS {
+            return PrecompileOperation::Invalid;
+        }
+
+        let mut operations = Vec::with_capacity(count);
+        for entry in entries.chunks_exact(9) {
+            let operation = Self::parse_operation(entry);
+            if !matches!(
+                operation,
+                PrecompileOperation::IncrementCounter(_) | PrecompileOperation::DecrementCounter(_)
+            ) {
+                return PrecompileOperation::Invalid;
+            }
+            operations.push(operation);
+        }
+
+        PrecompileOperation::BatchCounter(operations)
+    }
 }
 
 #[cfg(test)]
@@ -257,6 +538,127 @@ mod tests {
         assert_eq!(value, 42);
     }
 
+    #[test]
+    fn test_counter_top() {
+        let executor = PrecompileExecutor::new();
+        let mut dexvm_state = DexVmState::new();
+        let caller = address!("9999999999999999999999999999999999999999");
+        let leader = address!("1111111111111111111111111111111111111111");
+
+        dexvm_state.set_counter(caller, 3);
+        dexvm_state.set_counter(leader, 8);
+
+        let calldata = make_counter_calldata(OP_TOP, 5);
+        let result = executor
+            .execute_with_dexvm(caller, COUNTER_PRECOMPILE_ADDRESS, &calldata, Some(&mut dexvm_state))
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.gas_used, COUNTER_TOP_BASE_GAS + 5 * COUNTER_TOP_ENTRY_GAS);
+        assert_eq!(result.return_data.len(), 56);
+        assert_eq!(&result.return_data[..20], leader.as_slice());
+        assert_eq!(&result.return_data[20..28], &8u64.to_be_bytes());
+        assert_eq!(&result.return_data[28..48], caller.as_slice());
+        assert!(!PrecompileExecutor::writes_counter(&calldata));
+
+        // Counts outside 1..=MAX_TOP_COUNTERS are rejected
+        for count in [0, MAX_TOP_COUNTERS as u64 + 1] {
+            let calldata = make_counter_calldata(OP_TOP, count);
+            assert_eq!(PrecompileExecutor::parse_operation(&calldata), PrecompileOperation::Invalid);
+        }
+    }
+
+    #[test]
+    fn test_counter_batch() {
+        let executor = PrecompileExecutor::new();
+        let mut dexvm_state = DexVmState::new();
+        let caller = address!("cccccccccccccccccccccccccccccccccccccccc");
+
+        let mut calldata = vec![OP_BATCH];
+        calldata.extend(make_counter_calldata(OP_INCREMENT, 10));
+        calldata.extend(make_counter_calldata(OP_DECREMENT, 3));
+        calldata.extend(make_counter_calldata(OP_INCREMENT, 5));
+        let result = executor
+            .execute_with_dexvm(caller, COUNTER_PRECOMPILE_ADDRESS, &calldata, Some(&mut dexvm_state))
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.gas_used, COUNTER_BATCH_BASE_GAS + 3 * COUNTER_BATCH_OP_GAS);
+        let value = u64::from_be_bytes(result.return_data.try_into().unwrap());
+        assert_eq!(value, 12);
+        assert_eq!(dexvm_state.get_counter(&caller), 12);
+    }
+
+    #[test]
+    fn test_counter_batch_is_atomic() {
+        let executor = PrecompileExecutor::new();
+        let mut dexvm_state = DexVmState::new();
+        let caller = address!("dddddddddddddddddddddddddddddddddddddddd");
+        dexvm_state.set_counter(caller, 5);
+
+        // Second entry underflows, so the first must not apply either
+        let mut calldata = vec![OP_BATCH];
+        calldata.extend(make_counter_calldata(OP_INCREMENT, 10));
+        calldata.extend(make_counter_calldata(OP_DECREMENT, 100));
+        let result = executor
+            .execute_with_dexvm(caller, COUNTER_PRECOMPILE_ADDRESS, &calldata, Some(&mut dexvm_state))
+            .unwrap();
+
+        assert!(!result.success);
+        assert_eq!(dexvm_state.get_counter(&caller), 5);
+
+        // Nested batches and queries are rejected
+        let mut calldata = vec![OP_BATCH];
+        calldata.extend(make_counter_calldata(OP_QUERY, 0));
+        let result = executor
+            .execute_with_dexvm(caller, COUNTER_PRECOMPILE_ADDRESS, &calldata, Some(&mut dexvm_state))
+            .unwrap();
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn test_labeled_counters() {
+        let executor = PrecompileExecutor::new();
+        let mut state = DexVmState::new();
+        let caller = address!("1212121212121212121212121212121212121212");
+        let label = B256::repeat_byte(7);
+        let labeled = |op: u8, amount: u64| {
+            let mut data = vec![op];
+            data.extend_from_slice(label.as_slice());
+            data.extend_from_slice(&amount.to_be_bytes());
+            data
+        };
+        let mut call = |calldata: &[u8]| {
+            executor
+                .execute_with_dexvm(caller, COUNTER_PRECOMPILE_ADDRESS, calldata, Some(&mut state))
+                .unwrap()
+        };
+
+        let result = call(&labeled(OP_LABELED_INCREMENT, 10));
+        assert!(result.success);
+        assert_eq!(result.gas_used, COUNTER_INCREMENT_GAS);
+        assert_eq!(result.return_data, 10u64.to_be_bytes());
+        assert!(call(&labeled(OP_LABELED_DECREMENT, 4)).success);
+        assert!(!call(&labeled(OP_LABELED_DECREMENT, 7)).success);
+        assert_eq!(call(&labeled(OP_LABELED_QUERY, 0)).return_data, 6u64.to_be_bytes());
+
+        // The main counter is a separate counter
+        assert_eq!(call(&make_counter_calldata(OP_QUERY, 0)).return_data, 0u64.to_be_bytes());
+        assert_eq!(state.get_labeled_counter(&caller, label), 6);
+
+        assert!(PrecompileExecutor::writes_counter(&labeled(OP_LABELED_INCREMENT, 1)));
+        assert!(!PrecompileExecutor::writes_counter(&labeled(OP_LABELED_QUERY, 0)));
+        assert_eq!(PrecompileExecutor::counter_label(&labeled(OP_LABELED_QUERY, 0)), label);
+        assert_eq!(
+            PrecompileExecutor::counter_label(&make_counter_calldata(OP_INCREMENT, 1)),
+            B256::ZERO
+        );
+
+        // Labeled operations need the full label and amount
+        let truncated = &labeled(OP_LABELED_INCREMENT, 1)[..40];
+        assert_eq!(PrecompileExecutor::parse_operation(truncated), PrecompileOperation::Invalid);
+    }
+
     #[test]
     fn test_invalid_operation() {
         let executor = PrecompileExecutor::new();
@@ -272,6 +674,49 @@ mod tests {
         assert!(result.error.is_some());
     }
 
+    #[test]
+    fn test_counter_out_of_gas() {
+        let executor = PrecompileExecutor::new();
+        let mut dexvm_state = DexVmState::new();
+        let caller = address!("eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee");
+
+        let calldata = make_counter_calldata(OP_INCREMENT, 10);
+        assert_eq!(PrecompileExecutor::required_gas(&calldata), COUNTER_INCREMENT_GAS);
+
+        let result = executor
+            .execute_with_gas_limit(
+                caller,
+                COUNTER_PRECOMPILE_ADDRESS,
+                &calldata,
+                COUNTER_INCREMENT_GAS - 1,
+                Some(&mut dexvm_state),
+            )
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.gas_used, COUNTER_INCREMENT_GAS - 1);
+        assert_eq!(dexvm_state.get_counter(&caller), 0);
+
+        let result = executor
+            .execute_with_gas_limit(
+                caller,
+                COUNTER_PRECOMPILE_ADDRESS,
+                &calldata,
+                COUNTER_INCREMENT_GAS,
+                Some(&mut dexvm_state),
+            )
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(dexvm_state.get_counter(&caller), 10);
+    }
+
+    #[test]
+    fn test_writes_counter() {
+        assert!(PrecompileExecutor::writes_counter(&make_counter_calldata(OP_INCREMENT, 1)));
+        assert!(PrecompileExecutor::writes_counter(&make_counter_calldata(OP_DECREMENT, 1)));
+        assert!(!PrecompileExecutor::writes_counter(&make_counter_calldata(OP_QUERY, 0)));
+        assert!(!PrecompileExecutor::writes_counter(&[0x00]));
+    }
+
     #[test]
     fn test_counter_operation_without_dexvm_state() {
         let executor = PrecompileExecutor::new();
@@ -0,0 +1,136 @@
+//! JSON export and import of DexVM state
+//!
+//! Snapshots carry a format version so later kinds of DexVM state can be
+//! added without breaking existing fixtures.
+
+use crate::state::DexVmState;
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Current snapshot format version
+///
+/// Version 2 added labeled counters; version 1 snapshots still import.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// Complete DexVM state in a stable, human-readable form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DexVmSnapshot {
+    /// Format version, see [`SNAPSHOT_VERSION`]
+    pub version: u32,
+    /// Non-zero counters by owner, sorted by address
+    pub counters: BTreeMap<Address, u64>,
+    /// Non-zero labeled counters by owner and label
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labeled: BTreeMap<Address, BTreeMap<B256, u64>>,
+}
+
+impl DexVmSnapshot {
+    /// Snapshot a set of counters, dropping zero values
+    pub fn from_counters(counters: impl IntoIterator<Item = (Address, u64)>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            counters: counters.into_iter().filter(|(_, value)| *value != 0).collect(),
+            labeled: BTreeMap::new(),
+        }
+    }
+
+    /// Add labeled counters, dropping zero values
+    pub fn with_labeled_counters(
+        mut self,
+        counters: impl IntoIterator<Item = ((Address, B256), u64)>,
+    ) -> Self {
+        for ((address, label), value) in counters {
+            if value != 0 && !label.is_zero() {
+                self.labeled.entry(address).or_default().insert(label, value);
+            }
+        }
+        self
+    }
+
+    /// Snapshot a DexVM state
+    pub fn from_state(state: &DexVmState) -> Self {
+        Self::from_counters(state.all_accounts().iter().map(|(address, value)| (*address, *value)))
+            .with_labeled_counters(state.all_labeled_counters().iter().map(|(k, v)| (*k, *v)))
+    }
+
+    /// Labeled counters as `(owner, label, value)` entries
+    pub fn labeled_entries(&self) -> Vec<(Address, B256, u64)> {
+        self.labeled
+            .iter()
+            .flat_map(|(address, labels)| {
+                labels.iter().map(move |(label, value)| (*address, *label, *value))
+            })
+            .collect()
+    }
+
+    /// Rebuild the DexVM state, rejecting unknown format versions
+    pub fn to_state(&self) -> Result<DexVmState, String> {
+        if !(1..=SNAPSHOT_VERSION).contains(&self.version) {
+            return Err(format!(
+                "Unsupported DexVM snapshot version {} (expected {})",
+                self.version, SNAPSHOT_VERSION
+            ));
+        }
+
+        let mut state = DexVmState::new();
+        for (address, value) in &self.counters {
+            state.set_counter(*address, *value);
+        }
+        for (address, label, value) in self.labeled_entries() {
+            state.set_labeled_counter(address, label, value);
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut state = DexVmState::new();
+        state.set_counter(address!("2222222222222222222222222222222222222222"), 7);
+        state.set_counter(address!("1111111111111111111111111111111111111111"), 3);
+
+        let snapshot = DexVmSnapshot::from_state(&state);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let expected = concat!(
+            r#"{"version":2,"counters":{"#,
+            r#""0x1111111111111111111111111111111111111111":3,"#,
+            r#""0x2222222222222222222222222222222222222222":7}}"#
+        );
+        assert_eq!(json, expected);
+
+        let restored: DexVmSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.to_state().unwrap().state_root(), state.state_root());
+    }
+
+    #[test]
+    fn test_labeled_counters_roundtrip() {
+        let mut state = DexVmState::new();
+        let owner = address!("1111111111111111111111111111111111111111");
+        state.set_counter(owner, 3);
+        state.set_labeled_counter(owner, B256::repeat_byte(1), 9);
+
+        let snapshot = DexVmSnapshot::from_state(&state);
+        assert_eq!(snapshot.labeled_entries(), vec![(owner, B256::repeat_byte(1), 9)]);
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: DexVmSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.to_state().unwrap().state_root(), state.state_root());
+
+        // Version 1 snapshots have no labeled counters and still import
+        let legacy = r#"{"version":1,"counters":{"0x1111111111111111111111111111111111111111":3}}"#;
+        let legacy: DexVmSnapshot = serde_json::from_str(legacy).unwrap();
+        assert_eq!(legacy.to_state().unwrap().get_counter(&owner), 3);
+    }
+
+    #[test]
+    fn test_unknown_version_rejected() {
+        let snapshot =
+            DexVmSnapshot { version: 3, counters: BTreeMap::new(), labeled: BTreeMap::new() };
+        assert!(snapshot.to_state().is_err());
+    }
+}
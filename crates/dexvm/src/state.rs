@@ -1,19 +1,83 @@
 use alloy_primitives::{keccak256, Address, B256};
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
+
+/// Position in the state journal to revert to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateCheckpoint(usize, usize);
 
 /// DexVM state
 ///
-/// Manages account counter state for the DexVM
+/// Manages account counter state for the DexVM. Besides its main counter an
+/// address can own any number of labeled counters, keyed by a 32-byte label;
+/// label zero is the main counter.
+///
+/// It also tracks the nonce of each sender's last native transaction. Nonces
+/// are not part of the state root.
 #[derive(Debug, Clone, Default)]
 pub struct DexVmState {
     /// Account counters: address -> counter value
     counters: HashMap<Address, u64>,
+    /// Counters ordered from highest value, ties by address
+    ranks: BTreeSet<(Reverse<u64>, Address)>,
+    /// Non-zero labeled counters: (address, label) -> counter value
+    labeled: BTreeMap<(Address, B256), u64>,
+    /// Nonce of the last executed native transaction of each sender
+    nonces: HashMap<Address, u64>,
+    /// Previous counter values, recorded while a checkpoint is open
+    journal: Vec<(Address, B256, u64)>,
+    /// Previous nonces, recorded while a checkpoint is open
+    nonce_journal: Vec<(Address, u64)>,
+    /// Number of open checkpoints
+    checkpoint_depth: usize,
 }
 
 impl DexVmState {
     /// Create new empty state
     pub fn new() -> Self {
-        Self { counters: HashMap::new() }
+        Self {
+            counters: HashMap::new(),
+            ranks: BTreeSet::new(),
+            labeled: BTreeMap::new(),
+            nonces: HashMap::new(),
+            journal: Vec::new(),
+            nonce_journal: Vec::new(),
+            checkpoint_depth: 0,
+        }
+    }
+
+    /// Open a checkpoint; mutations after this point can be reverted
+    pub fn checkpoint(&mut self) -> StateCheckpoint {
+        self.checkpoint_depth += 1;
+        StateCheckpoint(self.journal.len(), self.nonce_journal.len())
+    }
+
+    /// Undo all mutations made since the checkpoint and close it
+    pub fn revert_to_checkpoint(&mut self, checkpoint: StateCheckpoint) {
+        while self.journal.len() > checkpoint.0 {
+            let (address, label, previous) = self.journal.pop().expect("journal length checked");
+            self.write_labeled_counter(address, label, previous);
+        }
+        while self.nonce_journal.len() > checkpoint.1 {
+            let (address, previous) = self.nonce_journal.pop().expect("journal length checked");
+            self.write_nonce(address, previous);
+        }
+        self.close_checkpoint();
+    }
+
+    /// Keep mutations made since the checkpoint and close it
+    pub fn commit_checkpoint(&mut self, _checkpoint: StateCheckpoint) {
+        self.close_checkpoint();
+    }
+
+    fn close_checkpoint(&mut self) {
+        self.checkpoint_depth = self.checkpoint_depth.saturating_sub(1);
+        if self.checkpoint_depth == 0 {
+            self.journal.clear();
+            self.nonce_journal.clear();
+        }
     }
 
     /// Get counter value for address
@@ -23,24 +87,101 @@ impl DexVmState {
 
     /// Set counter value for address
     pub fn set_counter(&mut self, address: Address, value: u64) {
-        if value == 0 {
-            self.counters.remove(&address);
+        self.set_labeled_counter(address, B256::ZERO, value);
+    }
+
+    /// Get a labeled counter value for address
+    pub fn get_labeled_counter(&self, address: &Address, label: B256) -> u64 {
+        if label.is_zero() {
+            return self.get_counter(address);
+        }
+        self.labeled.get(&(*address, label)).copied().unwrap_or(0)
+    }
+
+    /// Set a labeled counter value for address
+    pub fn set_labeled_counter(&mut self, address: Address, label: B256, value: u64) {
+        if self.checkpoint_depth > 0 {
+            self.journal.push((address, label, self.get_labeled_counter(&address, label)));
+        }
+        self.write_labeled_counter(address, label, value);
+    }
+
+    fn write_labeled_counter(&mut self, address: Address, label: B256, value: u64) {
+        if label.is_zero() {
+            self.write_counter(address, value);
+        } else if value == 0 {
+            self.labeled.remove(&(address, label));
         } else {
-            self.counters.insert(address, value);
+            self.labeled.insert((address, label), value);
         }
     }
 
+    fn write_counter(&mut self, address: Address, value: u64) {
+        let previous = if value == 0 {
+            self.counters.remove(&address)
+        } else {
+            self.counters.insert(address, value)
+        };
+        if let Some(previous) = previous {
+            self.ranks.remove(&(Reverse(previous), address));
+        }
+        if value != 0 {
+            self.ranks.insert((Reverse(value), address));
+        }
+    }
+
+    /// Nonce of the sender's last executed native transaction, zero if none
+    pub fn get_nonce(&self, address: &Address) -> u64 {
+        self.nonces.get(address).copied().unwrap_or(0)
+    }
+
+    /// Record the nonce of the sender's last executed native transaction
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) {
+        if self.checkpoint_depth > 0 {
+            self.nonce_journal.push((address, self.get_nonce(&address)));
+        }
+        self.write_nonce(address, nonce);
+    }
+
+    fn write_nonce(&mut self, address: Address, nonce: u64) {
+        if nonce == 0 {
+            self.nonces.remove(&address);
+        } else {
+            self.nonces.insert(address, nonce);
+        }
+    }
+
+    /// Get all native transaction nonces
+    pub fn all_nonces(&self) -> &HashMap<Address, u64> {
+        &self.nonces
+    }
+
     /// Increment counter and return new value
     pub fn increment_counter(&mut self, address: Address, amount: u64) -> u64 {
-        let current = self.get_counter(&address);
-        let new_value = current.saturating_add(amount);
-        self.set_counter(address, new_value);
-        new_value
+        self.increment_labeled_counter(address, B256::ZERO, amount)
     }
 
     /// Decrement counter and return (success, new_value)
     pub fn decrement_counter(&mut self, address: Address, amount: u64) -> Result<u64, String> {
-        let current = self.get_counter(&address);
+        self.decrement_labeled_counter(address, B256::ZERO, amount)
+    }
+
+    /// Increment a labeled counter and return new value
+    pub fn increment_labeled_counter(&mut self, address: Address, label: B256, amount: u64) -> u64 {
+        let current = self.get_labeled_counter(&address, label);
+        let new_value = current.saturating_add(amount);
+        self.set_labeled_counter(address, label, new_value);
+        new_value
+    }
+
+    /// Decrement a labeled counter and return new value, failing on underflow
+    pub fn decrement_labeled_counter(
+        &mut self,
+        address: Address,
+        label: B256,
+        amount: u64,
+    ) -> Result<u64, String> {
+        let current = self.get_labeled_counter(&address, label);
         if amount > current {
             return Err(format!(
                 "Counter underflow: have {}, want to decrement {}",
@@ -48,15 +189,17 @@ impl DexVmState {
             ));
         }
         let new_value = current - amount;
-        self.set_counter(address, new_value);
+        self.set_labeled_counter(address, label, new_value);
         Ok(new_value)
     }
 
     /// Calculate state root
     ///
-    /// Simple implementation: keccak256(sorted_account_data)
+    /// Simple implementation: keccak256(sorted_account_data), followed by the
+    /// hash of the sorted labeled counters if there are any, so state without
+    /// labels keeps its root.
     pub fn state_root(&self) -> B256 {
-        if self.counters.is_empty() {
+        if self.counters.is_empty() && self.labeled.is_empty() {
             return B256::ZERO;
         }
 
@@ -71,6 +214,16 @@ impl DexVmState {
             data.extend_from_slice(&counter.to_be_bytes());
         }
 
+        if !self.labeled.is_empty() {
+            let mut labeled = Vec::with_capacity(self.labeled.len() * 60);
+            for ((addr, label), counter) in &self.labeled {
+                labeled.extend_from_slice(addr.as_slice());
+                labeled.extend_from_slice(label.as_slice());
+                labeled.extend_from_slice(&counter.to_be_bytes());
+            }
+            data.extend_from_slice(keccak256(&labeled).as_slice());
+        }
+
         keccak256(&data)
     }
 
@@ -83,6 +236,28 @@ impl DexVmState {
     pub fn account_count(&self) -> usize {
         self.counters.len()
     }
+
+    /// Non-zero labeled counters of an address, by label
+    pub fn counter_labels(&self, address: &Address) -> Vec<(B256, u64)> {
+        let first = (*address, B256::ZERO);
+        let last = (*address, B256::repeat_byte(0xff));
+        self.labeled.range(first..=last).map(|(&(_, label), &value)| (label, value)).collect()
+    }
+
+    /// Get all non-zero labeled counters
+    pub fn all_labeled_counters(&self) -> &BTreeMap<(Address, B256), u64> {
+        &self.labeled
+    }
+
+    /// Whether no address has any counter
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty() && self.labeled.is_empty()
+    }
+
+    /// Highest `limit` counters, largest first and ties by address
+    pub fn top_counters(&self, limit: usize) -> Vec<(Address, u64)> {
+        self.ranks.iter().take(limit).map(|&(Reverse(value), address)| (address, value)).collect()
+    }
 }
 
 #[cfg(test)]
@@ -142,6 +317,38 @@ mod tests {
         assert_ne!(root, root3);
     }
 
+    #[test]
+    fn test_checkpoint_revert() {
+        let mut state = DexVmState::new();
+        let addr1 = address!("1111111111111111111111111111111111111111");
+        let addr2 = address!("2222222222222222222222222222222222222222");
+        state.set_counter(addr1, 5);
+        let root = state.state_root();
+
+        let outer = state.checkpoint();
+        state.increment_counter(addr1, 10);
+        state.set_nonce(addr1, 3);
+
+        // Nested checkpoint committed into the outer one
+        let inner = state.checkpoint();
+        state.set_counter(addr2, 7);
+        state.commit_checkpoint(inner);
+        assert_eq!(state.get_counter(&addr2), 7);
+
+        state.revert_to_checkpoint(outer);
+        assert_eq!(state.get_counter(&addr1), 5);
+        assert_eq!(state.get_counter(&addr2), 0);
+        assert_eq!(state.account_count(), 1);
+        assert_eq!(state.get_nonce(&addr1), 0);
+        assert_eq!(state.state_root(), root);
+
+        // Committed checkpoints keep their changes
+        let checkpoint = state.checkpoint();
+        state.set_counter(addr1, 0);
+        state.commit_checkpoint(checkpoint);
+        assert_eq!(state.account_count(), 0);
+    }
+
     #[test]
     fn test_zero_counter_removal() {
         let mut state = DexVmState::new();
@@ -155,4 +362,60 @@ mod tests {
         assert_eq!(state.account_count(), 0);
         assert_eq!(state.get_counter(&addr), 0);
     }
+
+    #[test]
+    fn test_top_counters() {
+        let mut state = DexVmState::new();
+        let addr1 = address!("1111111111111111111111111111111111111111");
+        let addr2 = address!("2222222222222222222222222222222222222222");
+        let addr3 = address!("3333333333333333333333333333333333333333");
+
+        state.set_counter(addr3, 5);
+        state.set_counter(addr2, 9);
+        state.set_counter(addr1, 5);
+        assert_eq!(state.top_counters(10), vec![(addr2, 9), (addr1, 5), (addr3, 5)]);
+        assert_eq!(state.top_counters(2), vec![(addr2, 9), (addr1, 5)]);
+
+        // Reverted writes restore the previous ranking
+        let checkpoint = state.checkpoint();
+        state.increment_counter(addr3, 10);
+        state.set_counter(addr2, 0);
+        assert_eq!(state.top_counters(10), vec![(addr3, 15), (addr1, 5)]);
+        state.revert_to_checkpoint(checkpoint);
+        assert_eq!(state.top_counters(10), vec![(addr2, 9), (addr1, 5), (addr3, 5)]);
+    }
+
+    #[test]
+    fn test_labeled_counters() {
+        let mut state = DexVmState::new();
+        let addr = address!("1111111111111111111111111111111111111111");
+        let (score, visits) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        state.set_counter(addr, 5);
+        let root = state.state_root();
+
+        // Labels are independent of each other and of the main counter
+        assert_eq!(state.increment_labeled_counter(addr, visits, 3), 3);
+        assert_eq!(state.increment_labeled_counter(addr, score, 10), 10);
+        assert_eq!(state.decrement_labeled_counter(addr, score, 4), Ok(6));
+        assert!(state.decrement_labeled_counter(addr, visits, 4).is_err());
+        assert_eq!(state.get_counter(&addr), 5);
+        assert_eq!(state.get_labeled_counter(&addr, B256::ZERO), 5);
+        assert_eq!(state.counter_labels(&addr), vec![(score, 6), (visits, 3)]);
+        assert_eq!(state.top_counters(10), vec![(addr, 5)]);
+        assert_ne!(state.state_root(), root);
+
+        // Labeled writes are journaled like main counter writes
+        let checkpoint = state.checkpoint();
+        state.set_labeled_counter(addr, score, 0);
+        state.increment_labeled_counter(addr, visits, 1);
+        assert_eq!(state.counter_labels(&addr), vec![(visits, 4)]);
+        state.revert_to_checkpoint(checkpoint);
+        assert_eq!(state.counter_labels(&addr), vec![(score, 6), (visits, 3)]);
+
+        // Clearing every label restores the unlabeled root
+        state.set_labeled_counter(addr, score, 0);
+        state.set_labeled_counter(addr, visits, 0);
+        assert!(state.all_labeled_counters().is_empty());
+        assert_eq!(state.state_root(), root);
+    }
 }
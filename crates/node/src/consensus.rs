@@ -1,14 +1,39 @@
 //! POA consensus engine with block signing
-
-use alloy_primitives::{keccak256, Address, B256};
+//!
+//! Validators take turns in a fixed round-robin order: block `n` is proposed
+//! by `validators[n % len]`. A validator only proposes in its own slots and
+//! imports the blocks of the others, which are checked against the proposer
+//! whose turn it was. A validator that stops proposing stalls the chain at its
+//! slot.
+//!
+//! Block contents can be shaped by a [`ProposalHandler`], in the manner of
+//! ABCI's PrepareProposal and ProcessProposal: an external component such as a
+//! DEX matching engine injects, drops or reorders the transactions of the
+//! block being built. The default [`PassthroughProposals`] keeps the canonical
+//! order.
+
+use alloy_primitives::{b256, keccak256, Address, B256, U256};
+use dex_rpc::{public_key_to_address, PendingTransaction};
 use reth_ethereum_primitives::TransactionSigned;
 use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
 use std::{
+    fmt::Debug,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 use tokio::{sync::mpsc, time::sleep};
 
+/// Share of the block interval spent executing transactions
+pub const BUILD_BUDGET_PERCENT: u32 = 80;
+
+/// Order of the secp256k1 curve group
+pub const SECP256K1N: B256 =
+    b256!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+
+/// Half the secp256k1 group order, the largest canonical `s` value
+pub const SECP256K1N_HALF: B256 =
+    b256!("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0");
+
 /// POA consensus configuration
 #[derive(Debug, Clone)]
 pub struct PoaConfig {
@@ -16,10 +41,14 @@ pub struct PoaConfig {
     pub secret_key: SecretKey,
     /// Validator address (derived from secret key)
     pub validator: Address,
+    /// Validators taking turns proposing blocks, in round-robin order
+    pub validators: Vec<Address>,
     /// Block interval
     pub block_interval: Duration,
     /// Starting block number
     pub starting_block: u64,
+    /// Hook choosing the transactions of produced blocks
+    pub proposal_handler: Arc<dyn ProposalHandler>,
 }
 
 impl PoaConfig {
@@ -29,11 +58,39 @@ impl PoaConfig {
         Self {
             secret_key,
             validator,
+            validators: vec![validator],
             block_interval,
             starting_block: 0,
+            proposal_handler: Arc::new(PassthroughProposals),
         }
     }
 
+    /// Set the hook choosing the transactions of produced blocks
+    pub fn with_proposal_handler(mut self, handler: Arc<dyn ProposalHandler>) -> Self {
+        self.proposal_handler = handler;
+        self
+    }
+
+    /// Set the validators taking turns proposing blocks
+    ///
+    /// The validator itself must be among them to propose any block.
+    pub fn with_validators(mut self, validators: Vec<Address>) -> Self {
+        self.validators = validators;
+        self
+    }
+
+    /// Validator whose turn it is to propose block `number`
+    pub fn proposer_for(&self, number: u64) -> Address {
+        round_robin_proposer(&self.validators, number).unwrap_or(self.validator)
+    }
+
+    /// Time budget for executing a block's transactions
+    ///
+    /// Leaves the rest of the interval for sealing, storing and broadcasting.
+    pub fn build_budget(&self) -> Duration {
+        self.block_interval * BUILD_BUDGET_PERCENT / 100
+    }
+
     /// Create from hex private key string
     pub fn from_hex_key(hex_key: &str, block_interval: Duration) -> Result<Self, String> {
         let hex_key = hex_key.strip_prefix("0x").unwrap_or(hex_key);
@@ -44,14 +101,58 @@ impl PoaConfig {
     }
 }
 
+/// Validator of `validators` whose turn it is to propose block `number`
+///
+/// Returns `None` for an empty set.
+pub fn round_robin_proposer(validators: &[Address], number: u64) -> Option<Address> {
+    if validators.is_empty() {
+        return None;
+    }
+    Some(validators[(number % validators.len() as u64) as usize])
+}
+
+/// Application hook into block building
+///
+/// Both calls run on the block producer, between ordering the candidate
+/// transactions and executing the block. They must be fast: the time they
+/// take comes out of the block's build budget.
+pub trait ProposalHandler: Debug + Send + Sync {
+    /// Choose the transactions of the block being built, in execution order
+    ///
+    /// `txs` holds the proposal's and the mempool's transactions in canonical
+    /// order. Transactions left out go back to the mempool; injected ones are
+    /// executed like the rest and skipped if invalid.
+    fn prepare_proposal(
+        &self,
+        proposal: &BlockProposal,
+        txs: Vec<PendingTransaction>,
+    ) -> Vec<PendingTransaction> {
+        let _ = proposal;
+        txs
+    }
+
+    /// Accept the transactions `prepare_proposal` chose, or refuse them with a
+    /// reason, in which case the block keeps the canonical order
+    fn process_proposal(
+        &self,
+        proposal: &BlockProposal,
+        txs: &[PendingTransaction],
+    ) -> Result<(), String> {
+        let _ = (proposal, txs);
+        Ok(())
+    }
+}
+
+/// Proposal handler keeping the canonical transaction order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PassthroughProposals;
+
+impl ProposalHandler for PassthroughProposals {}
+
 /// Derive address from secret key
 pub fn secret_key_to_address(secret_key: &SecretKey) -> Address {
     let secp = Secp256k1::new();
-    let public_key = PublicKey::from_secret_key(&secp, secret_key);
-    let public_key_bytes = public_key.serialize_uncompressed();
-    // Skip the first byte (0x04 prefix) and hash the rest
-    let hash = keccak256(&public_key_bytes[1..]);
-    Address::from_slice(&hash[12..])
+    public_key_to_address(&PublicKey::from_secret_key(&secp, secret_key))
 }
 
 /// Block signature (65 bytes: r[32] + s[32] + v[1])
@@ -88,6 +189,24 @@ impl BlockSignature {
     pub fn is_empty(&self) -> bool {
         self.r == B256::ZERO && self.s == B256::ZERO && self.v == 0
     }
+
+    /// Check the signature is in canonical form: `s` in the lower half of
+    /// the curve order and `v` in {0, 1}
+    ///
+    /// Every signature has a high-s twin `(r, n - s, v ^ 1)` recovering the
+    /// same signer; only the low-s form is accepted so a block has one encoding.
+    pub fn is_canonical(&self) -> bool {
+        self.v <= 1 && self.s != B256::ZERO && self.s <= SECP256K1N_HALF
+    }
+
+    /// Convert a high-s signature into its equivalent low-s form
+    pub fn normalize_s(&mut self) {
+        if self.s > SECP256K1N_HALF {
+            let s = U256::from_be_bytes(SECP256K1N.0) - U256::from_be_bytes(self.s.0);
+            self.s = B256::from(s);
+            self.v ^= 1;
+        }
+    }
 }
 
 /// Block proposal with signature
@@ -132,11 +251,14 @@ impl BlockProposal {
             s: B256::from_slice(&signature[32..64]),
             v: i32::from(recovery_id) as u8,
         };
+        self.signature.normalize_s();
     }
 
     /// Verify the block signature and return the signer address
+    ///
+    /// Returns `None` for empty or non-canonical (high-s) signatures.
     pub fn recover_signer(&self) -> Option<Address> {
-        if self.signature.is_empty() {
+        if !self.signature.is_canonical() {
             return None;
         }
 
@@ -155,9 +277,7 @@ impl BlockProposal {
             secp256k1::ecdsa::RecoverableSignature::from_compact(&sig_bytes, recovery_id).ok()?;
 
         let public_key = secp.recover_ecdsa(&message, &recoverable_sig).ok()?;
-        let public_key_bytes = public_key.serialize_uncompressed();
-        let hash = keccak256(&public_key_bytes[1..]);
-        Some(Address::from_slice(&hash[12..]))
+        Some(public_key_to_address(&public_key))
     }
 
     /// Verify the block was signed by the expected proposer
@@ -170,6 +290,9 @@ impl BlockProposal {
 }
 
 /// POA consensus engine
+///
+/// Clones share the block counter and proposal channel.
+#[derive(Clone)]
 pub struct PoaConsensus {
     config: PoaConfig,
     current_block: Arc<Mutex<u64>>,
@@ -201,8 +324,9 @@ impl PoaConsensus {
 
         tokio::spawn(async move {
             tracing::info!(
-                "POA consensus started, validator: {:?}, block_interval: {:?}",
+                "POA consensus started, validator: {:?}, validators: {}, block_interval: {:?}",
                 config.validator,
+                config.validators.len(),
                 config.block_interval
             );
 
@@ -218,8 +342,13 @@ impl PoaConsensus {
 
                 let block_number = {
                     let mut block = current_block.lock().unwrap();
-                    *block += 1;
-                    *block
+                    let next = *block + 1;
+                    // The slots of other validators are filled by importing their blocks
+                    if config.proposer_for(next) != config.validator {
+                        continue;
+                    }
+                    *block = next;
+                    next
                 };
 
                 let parent_hash = *last_block_hash.lock().unwrap();
@@ -264,6 +393,14 @@ impl PoaConsensus {
         tracing::debug!("Block finalized, hash={:?}", block_hash);
     }
 
+    /// Move past a block of another validator once it is imported
+    pub fn import_block(&self, number: u64, block_hash: B256) {
+        let mut current = self.current_block.lock().unwrap();
+        *current = (*current).max(number);
+        *self.last_block_hash.lock().unwrap() = block_hash;
+        tracing::debug!("Block {} imported, hash={:?}", number, block_hash);
+    }
+
     /// Get current block number
     pub fn current_block_number(&self) -> u64 {
         *self.current_block.lock().unwrap()
@@ -274,6 +411,11 @@ impl PoaConsensus {
         &self.config
     }
 
+    /// Hook choosing the transactions of produced blocks
+    pub fn proposal_handler(&self) -> &Arc<dyn ProposalHandler> {
+        &self.config.proposal_handler
+    }
+
     /// Set last block hash (for recovery from storage)
     pub fn set_last_block_hash(&mut self, hash: B256) {
         *self.last_block_hash.lock().unwrap() = hash;
@@ -309,13 +451,14 @@ impl PoaConsensus {
             .map_err(|e| format!("Failed to submit transaction: {}", e))
     }
 
-    /// Verify a block was signed by the expected validator
+    /// Verify a block was signed by the validator whose turn it was
     pub fn verify_block(&self, proposal: &BlockProposal) -> bool {
-        // Check if proposer matches our expected validator
-        if proposal.proposer != self.config.validator {
+        let expected = self.config.proposer_for(proposal.number);
+        if proposal.proposer != expected {
             tracing::warn!(
-                "Block proposer mismatch: expected {:?}, got {:?}",
-                self.config.validator,
+                "Block {} proposer mismatch: expected {:?}, got {:?}",
+                proposal.number,
+                expected,
                 proposal.proposer
             );
             return false;
@@ -374,11 +517,32 @@ mod tests {
     const TEST_PRIVATE_KEY: &str =
         "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
 
+    // Address: 0x70997970C51812dc3A010C7d01b50e0d17dc79C8
+    const OTHER_PRIVATE_KEY: &str =
+        "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
     fn test_secret_key() -> SecretKey {
         let key_bytes = hex::decode(TEST_PRIVATE_KEY).unwrap();
         SecretKey::from_slice(&key_bytes).unwrap()
     }
 
+    fn other_secret_key() -> SecretKey {
+        SecretKey::from_slice(&hex::decode(OTHER_PRIVATE_KEY).unwrap()).unwrap()
+    }
+
+    fn signed_proposal(number: u64, secret_key: &SecretKey) -> BlockProposal {
+        let mut proposal = BlockProposal {
+            number,
+            parent_hash: B256::ZERO,
+            timestamp: 1234567890,
+            transactions: vec![],
+            proposer: secret_key_to_address(secret_key),
+            signature: BlockSignature::default(),
+        };
+        proposal.sign(secret_key);
+        proposal
+    }
+
     #[test]
     fn test_secret_key_to_address() {
         let secret_key = test_secret_key();
@@ -514,6 +678,53 @@ mod tests {
         handle.abort();
     }
 
+    #[test]
+    fn test_round_robin_proposer() {
+        let ours = secret_key_to_address(&test_secret_key());
+        let other = secret_key_to_address(&other_secret_key());
+        let config = PoaConfig::new(test_secret_key(), Duration::from_secs(1));
+        assert_eq!(config.proposer_for(7), ours);
+
+        let config = config.with_validators(vec![ours, other]);
+        assert_eq!(config.proposer_for(2), ours);
+        assert_eq!(config.proposer_for(3), other);
+        assert_eq!(round_robin_proposer(&[], 3), None);
+
+        // Blocks are accepted from the validator whose turn it was, signed by it
+        let consensus = PoaConsensus::new(config);
+        assert!(consensus.verify_block(&signed_proposal(3, &other_secret_key())));
+        assert!(!consensus.verify_block(&signed_proposal(4, &other_secret_key())));
+        let mut forged = signed_proposal(3, &test_secret_key());
+        forged.proposer = other;
+        assert!(!consensus.verify_block(&forged));
+    }
+
+    #[tokio::test]
+    async fn test_production_waits_for_other_validators() {
+        let other = secret_key_to_address(&other_secret_key());
+        let config = PoaConfig::new(test_secret_key(), Duration::from_millis(50));
+        let ours = config.validator;
+        let consensus = PoaConsensus::new(config.with_validators(vec![other, ours]));
+        let handle = consensus.start();
+
+        // Block 1 is ours, block 2 belongs to the other validator
+        tokio::time::sleep(Duration::from_millis(220)).await;
+        let first = consensus.recv_proposal().unwrap();
+        assert_eq!((first.number, first.proposer), (1, ours));
+        assert!(consensus.recv_proposal().is_none());
+        assert_eq!(consensus.current_block_number(), 1);
+
+        // Importing it lets production continue on top of it
+        let imported = B256::repeat_byte(2);
+        consensus.import_block(2, imported);
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        let next = consensus.recv_proposal().unwrap();
+        assert_eq!((next.number, next.parent_hash), (3, imported));
+        assert!(next.verify_signature());
+
+        handle.abort();
+    }
+
     #[test]
     fn test_finalize_block() {
         let config = PoaConfig::new(test_secret_key(), Duration::from_millis(500));
@@ -525,6 +736,40 @@ mod tests {
         assert_eq!(*consensus.last_block_hash.lock().unwrap(), block_hash);
     }
 
+    #[test]
+    fn test_high_s_signature_rejected() {
+        let secret_key = test_secret_key();
+        let validator = secret_key_to_address(&secret_key);
+
+        let mut proposal = BlockProposal {
+            number: 1,
+            parent_hash: B256::ZERO,
+            timestamp: 1234567890,
+            transactions: vec![],
+            proposer: validator,
+            signature: BlockSignature::default(),
+        };
+        proposal.sign(&secret_key);
+        assert!(proposal.signature.is_canonical());
+
+        // Malleated twin (r, n - s, v ^ 1) recovers the same key but is rejected
+        let low_s = proposal.signature;
+        let s = U256::from_be_bytes(SECP256K1N.0) - U256::from_be_bytes(low_s.s.0);
+        proposal.signature = BlockSignature { r: low_s.r, s: B256::from(s), v: low_s.v ^ 1 };
+        assert!(!proposal.signature.is_canonical());
+        assert_eq!(proposal.recover_signer(), None);
+        assert!(!verify_block_signature(&proposal, &[validator]));
+
+        // Normalizing restores the original signature
+        proposal.signature.normalize_s();
+        assert_eq!(proposal.signature, low_s);
+        assert_eq!(proposal.recover_signer(), Some(validator));
+
+        // Recovery ids 2 and 3 are not canonical either
+        proposal.signature.v += 2;
+        assert_eq!(proposal.recover_signer(), None);
+    }
+
     #[test]
     fn test_signature_bytes_roundtrip() {
         let sig = BlockSignature {
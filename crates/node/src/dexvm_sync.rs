@@ -0,0 +1,532 @@
+//! DexVM state sync over the `dexvm/1` subprotocol
+//!
+//! Fullnodes store synced blocks without executing them, so their DexVM
+//! counters stay at whatever they loaded on startup. Every node answers
+//! `dexvm/1` requests from its committed DexVM state, and a fullnode
+//! periodically asks a peer for its state root: when the root differs from the
+//! local one, the fullnode pages through the peer's counters, checks the
+//! assembled state against the root and installs it.
+//!
+//! A page taken under a different root than the first one restarts the
+//! download, so a state is only installed when all of it came from one root.
+//! The root itself is trusted only at our own head, where together with the
+//! peer's EVM root it must reproduce the state root of the block we stored
+//! there; a peer whose root contradicts that block is banned.
+
+use crate::{
+    executor::combine_state_roots, producer::labeled_counter_writes, sync::INVALID_BLOCK_BAN,
+};
+use alloy_primitives::{Address, B256};
+use dex_dexvm::{DexVmExecutor, DexVmState};
+use dex_p2p::{
+    CounterEntry, CounterKey, DexVmMessage, P2pHandle, PeerId, SessionCommand,
+    MAX_COUNTERS_PER_RESPONSE,
+};
+use dex_storage::{BlockReader, StateReader, StateStore, StateWriter};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Time between DexVM state root checks against a peer
+pub const DEXVM_SYNC_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Time a `dexvm/1` request may go unanswered before it is abandoned
+const DEXVM_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Up to `limit` non-zero counters of `state` with keys after `after`, and
+/// whether they are the last ones
+pub fn counters_page(
+    state: &DexVmState,
+    after: Option<CounterKey>,
+    limit: u64,
+) -> (Vec<CounterEntry>, bool) {
+    let counters: BTreeMap<CounterKey, u64> = state
+        .all_accounts()
+        .iter()
+        .map(|(&address, &value)| (CounterKey { address, label: B256::ZERO }, value))
+        .chain(
+            state
+                .all_labeled_counters()
+                .iter()
+                .map(|(&(address, label), &value)| (CounterKey { address, label }, value)),
+        )
+        .collect();
+    let mut remaining = counters
+        .into_iter()
+        .filter(|(key, _)| after.is_none_or(|after| *key > after))
+        .map(|(key, value)| CounterEntry { key, value });
+    let limit = limit.min(MAX_COUNTERS_PER_RESPONSE) as usize;
+    let entries: Vec<_> = remaining.by_ref().take(limit).collect();
+    let complete = remaining.next().is_none();
+    (entries, complete)
+}
+
+/// Check a peer's DexVM state root at `head` against the block we stored there
+///
+/// Returns false if `head` is not our latest block, so there is nothing to
+/// check against, and an error if our block commits to other roots. Genesis
+/// state comes from the genesis file and is never synced.
+fn verify_peer_root(
+    blocks: &dyn BlockReader,
+    head: u64,
+    evm_root: B256,
+    root: B256,
+) -> eyre::Result<bool> {
+    if head == 0 || head != blocks.latest_block_number() {
+        return Ok(false);
+    }
+    let Some(block) = blocks.get_block_by_number(head) else {
+        return Ok(false);
+    };
+    if combine_state_roots(evm_root, root) != block.combined_state_root {
+        return Err(eyre::eyre!(
+            "DexVM root {} does not match the state root of block {}",
+            root,
+            head
+        ));
+    }
+    Ok(true)
+}
+
+/// A peer's DexVM state, assembled page by page
+#[derive(Debug)]
+struct Download {
+    head: u64,
+    evm_root: B256,
+    root: B256,
+    state: DexVmState,
+    last: Option<CounterKey>,
+}
+
+impl Download {
+    fn new(head: u64, evm_root: B256, root: B256) -> Self {
+        Self { head, evm_root, root, state: DexVmState::new(), last: None }
+    }
+
+    /// Add a page of counters, which must continue in key order after the previous page
+    fn apply(&mut self, entries: &[CounterEntry]) -> eyre::Result<()> {
+        if entries.len() as u64 > MAX_COUNTERS_PER_RESPONSE {
+            return Err(eyre::eyre!("{} counters in one page", entries.len()));
+        }
+        for entry in entries {
+            if self.last.is_some_and(|last| entry.key <= last) {
+                return Err(eyre::eyre!("Counter {:?} out of order", entry.key));
+            }
+            if entry.value == 0 {
+                return Err(eyre::eyre!("Zero counter {:?}", entry.key));
+            }
+            self.state.set_labeled_counter(entry.key.address, entry.key.label, entry.value);
+            self.last = Some(entry.key);
+        }
+        Ok(())
+    }
+}
+
+/// What the request in flight asks for
+#[derive(Debug)]
+enum Request {
+    StateRoot,
+    Counters(Download),
+}
+
+#[derive(Debug)]
+struct Pending {
+    peer_id: PeerId,
+    request_id: u64,
+    sent_at: Instant,
+    request: Request,
+}
+
+/// Serves the committed DexVM state to peers and copies a peer's state on fullnodes
+pub struct DexVmStateSync {
+    p2p: P2pHandle,
+    executor: Arc<RwLock<DexVmExecutor>>,
+    state_store: Arc<StateStore>,
+    block_store: Arc<dyn BlockReader>,
+    pending: Option<Pending>,
+    next_request_id: u64,
+    /// Block and EVM root of the state last installed or confirmed from a peer
+    installed: Option<(u64, B256)>,
+}
+
+impl DexVmStateSync {
+    pub fn new(
+        p2p: P2pHandle,
+        executor: Arc<RwLock<DexVmExecutor>>,
+        state_store: Arc<StateStore>,
+        block_store: Arc<dyn BlockReader>,
+    ) -> Self {
+        Self {
+            p2p,
+            executor,
+            state_store,
+            block_store,
+            pending: None,
+            next_request_id: 0,
+            installed: None,
+        }
+    }
+
+    /// Answer a request from a peer, or take in a response to our own request
+    pub async fn handle_message(&mut self, peer_id: PeerId, message: DexVmMessage) {
+        match message {
+            DexVmMessage::GetStateRoot { request_id } => {
+                let Some((head, evm_root, root, ())) = self.committed(|_| ()) else {
+                    tracing::debug!("No checkable DexVM state to send to peer {}", peer_id);
+                    return;
+                };
+                let message = DexVmMessage::StateRoot { request_id, head, evm_root, root };
+                self.send(peer_id, message).await;
+            }
+            DexVmMessage::GetCounters { request_id, after, limit } => {
+                let Some((head, evm_root, root, (entries, complete))) =
+                    self.committed(|state| counters_page(state, after, limit))
+                else {
+                    tracing::debug!("No checkable DexVM state to send to peer {}", peer_id);
+                    return;
+                };
+                tracing::debug!("Sending {} DexVM counters to peer {}", entries.len(), peer_id);
+                let page =
+                    DexVmMessage::Counters { request_id, head, evm_root, root, entries, complete };
+                self.send(peer_id, page).await;
+            }
+            response => self.handle_response(peer_id, response).await,
+        }
+    }
+
+    /// Ask a peer serving DexVM state for its root, unless a request is in flight
+    ///
+    /// Peers take turns, so one with no state to serve does not hold up the sync.
+    pub async fn check_peers(&mut self) {
+        if let Some(pending) = &self.pending {
+            if pending.sent_at.elapsed() < DEXVM_REQUEST_TIMEOUT {
+                return;
+            }
+            tracing::warn!("DexVM state request to peer {} timed out", pending.peer_id);
+            self.pending = None;
+        }
+        let peers = self.p2p.dexvm_peers();
+        if let Some(&peer_id) = peers.get(self.next_request_id as usize % peers.len().max(1)) {
+            self.request(peer_id, Request::StateRoot).await;
+        }
+    }
+
+    /// Abandon the request in flight to a peer that went away
+    pub fn handle_peer_disconnected(&mut self, peer_id: PeerId) {
+        if self.pending.as_ref().is_some_and(|pending| pending.peer_id == peer_id) {
+            self.pending = None;
+        }
+    }
+
+    async fn handle_response(&mut self, peer_id: PeerId, message: DexVmMessage) {
+        let Some(pending) = self.pending.take_if(|pending| {
+            pending.peer_id == peer_id && pending.request_id == message.request_id()
+        }) else {
+            tracing::debug!("Ignoring unrequested DexVM message from peer {}", peer_id);
+            return;
+        };
+
+        match (pending.request, message) {
+            (Request::StateRoot, DexVmMessage::StateRoot { head, evm_root, root, .. }) => {
+                if !self.check_root(peer_id, head, evm_root, root).await {
+                    return;
+                }
+                let local = self.executor.read().unwrap_or_else(|e| e.into_inner()).state_root();
+                if root == local {
+                    self.installed = Some((head, evm_root));
+                    return;
+                }
+                tracing::info!(
+                    "DexVM state root {} of peer {} at block {} differs from ours ({})",
+                    root, peer_id, head, local
+                );
+                self.request_counters(peer_id, Download::new(head, evm_root, root)).await;
+            }
+            (
+                Request::Counters(mut download),
+                DexVmMessage::Counters { head, evm_root, root, entries, complete, .. },
+            ) => {
+                if root != download.root {
+                    // The peer committed a block since the first page
+                    tracing::debug!(
+                        "DexVM state of peer {} moved to block {}, restarting",
+                        peer_id, head
+                    );
+                    if self.check_root(peer_id, head, evm_root, root).await {
+                        self.request_counters(peer_id, Download::new(head, evm_root, root)).await;
+                    }
+                    return;
+                }
+                if let Err(e) = download.apply(&entries) {
+                    tracing::warn!("Bad DexVM counters from peer {}: {}", peer_id, e);
+                    self.ban(peer_id).await;
+                    return;
+                }
+                if !complete {
+                    self.request_counters(peer_id, download).await;
+                    return;
+                }
+                if download.state.state_root() != download.root {
+                    tracing::warn!("DexVM state from peer {} does not match its root", peer_id);
+                    self.ban(peer_id).await;
+                    return;
+                }
+                // Our head may have moved on while the pages came in
+                if !self.check_root(peer_id, download.head, download.evm_root, download.root).await
+                {
+                    return;
+                }
+                let (head, evm_root, root, counters) = (
+                    download.head,
+                    download.evm_root,
+                    download.root,
+                    download.state.account_count(),
+                );
+                match install_dexvm_state(&self.state_store, &self.executor, download.state) {
+                    Ok(()) => {
+                        self.installed = Some((head, evm_root));
+                        tracing::info!(
+                            "Synced DexVM state of block {} from peer {}: {} counters, root {}",
+                            head, peer_id, counters, root
+                        );
+                    }
+                    Err(e) => tracing::error!("Failed to install synced DexVM state: {}", e),
+                }
+            }
+            (_, message) => {
+                tracing::warn!("Unexpected DexVM response {:?} from peer {}", message, peer_id);
+            }
+        }
+    }
+
+    /// Whether a peer's root at `head` checks out against our stored block
+    ///
+    /// A root that contradicts our block gets the peer banned.
+    async fn check_root(&mut self, peer_id: PeerId, head: u64, evm_root: B256, root: B256) -> bool {
+        match verify_peer_root(self.block_store.as_ref(), head, evm_root, root) {
+            Ok(true) => true,
+            Ok(false) => {
+                tracing::debug!("DexVM state of peer {} is not at our head ({})", peer_id, head);
+                false
+            }
+            Err(e) => {
+                tracing::warn!("Bad DexVM state root from peer {}: {}", peer_id, e);
+                self.ban(peer_id).await;
+                false
+            }
+        }
+    }
+
+    async fn ban(&self, peer_id: PeerId) {
+        let cmd = SessionCommand::BanPeer { peer_id, duration: INVALID_BLOCK_BAN };
+        if let Err(e) = self.p2p.send_command(cmd).await {
+            tracing::warn!("Failed to ban peer {}: {}", peer_id, e);
+        }
+    }
+
+    async fn request_counters(&mut self, peer_id: PeerId, download: Download) {
+        self.request(peer_id, Request::Counters(download)).await;
+    }
+
+    async fn request(&mut self, peer_id: PeerId, request: Request) {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let message = match &request {
+            Request::StateRoot => DexVmMessage::GetStateRoot { request_id },
+            Request::Counters(download) => DexVmMessage::GetCounters {
+                request_id,
+                after: download.last,
+                limit: MAX_COUNTERS_PER_RESPONSE,
+            },
+        };
+        self.pending = Some(Pending { peer_id, request_id, sent_at: Instant::now(), request });
+        self.send(peer_id, message).await;
+    }
+
+    async fn send(&self, peer_id: PeerId, message: DexVmMessage) {
+        let cmd = SessionCommand::SendDexVm { peer_id, message };
+        if let Err(e) = self.p2p.send_command(cmd).await {
+            tracing::warn!("Failed to send DexVM message to peer {}: {}", peer_id, e);
+        }
+    }
+
+    /// Latest stored block, its EVM and DexVM roots and `f` of the committed DexVM state
+    ///
+    /// `None` unless the committed state is the one the latest block commits
+    /// to, which peers check it against: a fullnode that has not installed the
+    /// state of its latest block has nothing to serve.
+    fn committed<T>(&self, f: impl FnOnce(&DexVmState) -> T) -> Option<(u64, B256, B256, T)> {
+        let head = self.block_store.latest_block_number();
+        let executor = self.executor.read().unwrap_or_else(|e| e.into_inner());
+        let root = executor.state_root();
+        let evm_root = match self.installed {
+            Some((installed, evm_root)) if installed == head => evm_root,
+            _ => {
+                // Synced blocks only carry the combined root
+                let block = self.block_store.get_block_by_number(head)?;
+                let separate = block.evm_state_root != block.combined_state_root;
+                if !separate || block.dexvm_state_root != root {
+                    return None;
+                }
+                block.evm_state_root
+            }
+        };
+        Some((head, evm_root, root, f(executor.state())))
+    }
+}
+
+/// Replace the stored and in-memory DexVM state with `state`
+///
+/// Stored counters missing from `state` are written as zero, which deletes them.
+/// Native transaction nonces are not part of the synced state; the stored ones
+/// are kept.
+pub(crate) fn install_dexvm_state(
+    state_store: &StateStore,
+    executor: &RwLock<DexVmExecutor>,
+    mut state: DexVmState,
+) -> eyre::Result<()> {
+    let mut writes: Vec<(Address, B256, u64)> = state
+        .all_accounts()
+        .iter()
+        .map(|(&address, &value)| (address, B256::ZERO, value))
+        .collect();
+    writes.extend(
+        state_store
+            .all_counters()
+            .into_keys()
+            .filter(|address| !state.all_accounts().contains_key(address))
+            .map(|address| (address, B256::ZERO, 0)),
+    );
+    writes.extend(labeled_counter_writes(&state, state_store));
+    state_store.set_labeled_counters(&writes)?;
+    for (address, nonce) in state_store.all_dexvm_nonces() {
+        state.set_nonce(address, nonce);
+    }
+
+    let mut executor = executor.write().map_err(|e| eyre::eyre!("{}", e))?;
+    *executor = DexVmExecutor::new(state);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_storage::DualvmStorage;
+
+    #[test]
+    fn test_paged_state_reassembles_and_installs() {
+        let mut state = DexVmState::new();
+        for byte in 1..=5u8 {
+            state.set_counter(Address::repeat_byte(byte), byte as u64);
+        }
+        state.set_labeled_counter(Address::repeat_byte(2), B256::repeat_byte(9), 7);
+        state.set_labeled_counter(Address::repeat_byte(6), B256::repeat_byte(9), 8);
+
+        let mut download = Download::new(10, B256::ZERO, state.state_root());
+        let mut pages = 0;
+        loop {
+            let (entries, complete) = counters_page(&state, download.last, 3);
+            download.apply(&entries).unwrap();
+            pages += 1;
+            if complete {
+                break;
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(download.state.state_root(), state.state_root());
+
+        // A page that goes back in key order is refused
+        let (first, _) = counters_page(&state, None, 1);
+        assert!(download.apply(&first).is_err());
+
+        // Installing replaces counters the node had stored before
+        let storage = DualvmStorage::in_memory().unwrap();
+        let stale = Address::repeat_byte(0xee);
+        storage.state.set_counter(stale, 4).unwrap();
+        storage.state.set_labeled_counters(&[(stale, B256::repeat_byte(1), 2)]).unwrap();
+        let executor = RwLock::new(DexVmExecutor::new(DexVmState::new()));
+        install_dexvm_state(&storage.state, &executor, download.state).unwrap();
+
+        assert_eq!(executor.read().unwrap().state_root(), state.state_root());
+        assert_eq!(storage.state.get_counter(stale), 0);
+        let labeled = storage.state.all_labeled_counters();
+        assert!(!labeled.contains_key(&(stale, B256::repeat_byte(1))));
+        let reloaded = crate::node::load_dexvm_state(storage.state.as_ref());
+        assert_eq!(reloaded.state_root(), state.state_root());
+    }
+
+    #[tokio::test]
+    async fn test_forged_state_is_not_installed() {
+        use dex_storage::{BlockWriter, StoredBlock};
+
+        let mut honest = DexVmState::new();
+        honest.set_counter(Address::repeat_byte(1), 5);
+        let mut forged = honest.clone();
+        forged.set_counter(Address::repeat_byte(2), 1_000);
+
+        // Our head, block 1, was synced and commits to the honest state
+        let storage = DualvmStorage::in_memory().unwrap();
+        let genesis = StoredBlock::genesis(1);
+        storage.blocks.store_block(genesis.clone()).unwrap();
+        let evm_root = B256::repeat_byte(0xe0);
+        let combined = combine_state_roots(evm_root, honest.state_root());
+        let block = StoredBlock {
+            number: 1,
+            parent_hash: genesis.hash,
+            evm_state_root: combined,
+            combined_state_root: combined,
+            ..genesis
+        };
+        storage.blocks.store_block(block).unwrap();
+
+        let service = dex_p2p::P2pService::new(dex_p2p::P2pConfig::default().with_port(0));
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::new())));
+        let mut sync = DexVmStateSync::new(
+            service.handle(),
+            Arc::clone(&executor),
+            Arc::clone(&storage.state),
+            storage.blocks.clone(),
+        );
+        let peer = PeerId::repeat_byte(7);
+        let state_root = |request_id, head, root| DexVmMessage::StateRoot {
+            request_id,
+            head,
+            evm_root,
+            root,
+        };
+        let counters = |request_id, state: &DexVmState, root| {
+            let (entries, complete) = counters_page(state, None, MAX_COUNTERS_PER_RESPONSE);
+            DexVmMessage::Counters { request_id, head: 1, evm_root, root, entries, complete }
+        };
+        let (root, forged_root) = (honest.state_root(), forged.state_root());
+
+        // A root our block does not commit to is refused before anything is downloaded
+        assert!(verify_peer_root(storage.blocks.as_ref(), 1, evm_root, forged_root).is_err());
+        sync.request(peer, Request::StateRoot).await;
+        sync.handle_message(peer, state_root(0, 1, forged_root)).await;
+        assert!(sync.pending.is_none());
+
+        // Roots at another block cannot be checked, so nothing is downloaded either
+        sync.request(peer, Request::StateRoot).await;
+        sync.handle_message(peer, state_root(1, 2, root)).await;
+        assert!(sync.pending.is_none());
+
+        // Forged counters sent under the honest root are not installed
+        sync.request(peer, Request::StateRoot).await;
+        sync.handle_message(peer, state_root(2, 1, root)).await;
+        assert!(matches!(sync.pending, Some(Pending { request: Request::Counters(_), .. })));
+        sync.handle_message(peer, counters(3, &forged, root)).await;
+        assert_eq!(executor.read().unwrap().state_root(), DexVmState::new().state_root());
+        assert!(sync.committed(|_| ()).is_none());
+
+        // The honest state is installed, and served on from then on
+        sync.request(peer, Request::StateRoot).await;
+        sync.handle_message(peer, state_root(4, 1, root)).await;
+        sync.handle_message(peer, counters(5, &honest, root)).await;
+        assert_eq!(executor.read().unwrap().state_root(), root);
+        let served = sync.committed(|_| ()).map(|(head, _, root, ())| (head, root));
+        assert_eq!(served, Some((1, root)));
+    }
+}
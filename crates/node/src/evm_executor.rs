@@ -1,28 +1,173 @@
 //! Simple EVM executor
 
+use crate::inspector::ExecutionInspector;
 use alloy_consensus::{transaction::SignerRecoverable, Receipt, Transaction};
 use alloy_primitives::{Address, B256, U256};
-use dex_dexvm::{DexVmState, PrecompileExecutor, COUNTER_PRECOMPILE_ADDRESS};
-use dex_storage::StateStore;
+use dex_dexvm::{
+    sends_value_to_dexvm, DexVmState, EvmStateReader, PrecompileExecutor,
+    COUNTER_PRECOMPILE_ADDRESS, DEXVM_VALUE_ERROR,
+};
+use dex_primitives::{system_handler, SystemHandler};
+use dex_storage::{AccountState, StateWriter};
 use reth_ethereum_primitives::TransactionSigned;
 use reth_execution_errors::BlockExecutionError;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
+
+/// Gas every transaction pays before execution
+const TX_BASE_GAS: u64 = 21_000;
+/// Extra base gas of a contract creation
+const TX_CREATE_GAS: u64 = 32_000;
+/// Gas per zero and non-zero calldata byte
+const TX_DATA_ZERO_GAS: u64 = 4;
+const TX_DATA_NON_ZERO_GAS: u64 = 16;
+/// Gas per access list address and storage key
+const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+
+/// Intrinsic gas of a transaction: the base cost plus calldata and access list
+///
+/// Plain transfers run no code, so this is all the gas they use.
+pub fn intrinsic_gas(tx: &TransactionSigned) -> u64 {
+    let base = if tx.is_create() { TX_BASE_GAS + TX_CREATE_GAS } else { TX_BASE_GAS };
+    let data: u64 = tx
+        .input()
+        .iter()
+        .map(|&byte| if byte == 0 { TX_DATA_ZERO_GAS } else { TX_DATA_NON_ZERO_GAS })
+        .sum();
+    let access_list = tx.access_list().map_or(0, |list| {
+        list.iter()
+            .map(|item| {
+                ACCESS_LIST_ADDRESS_GAS +
+                    item.storage_keys.len() as u64 * ACCESS_LIST_STORAGE_KEY_GAS
+            })
+            .sum()
+    });
+    base + data + access_list
+}
+
+/// Receipt of a transaction that failed its checks; nothing is charged, so no gas is used
+fn failed_receipt() -> Receipt {
+    Receipt { status: false.into(), cumulative_gas_used: 0, logs: vec![] }
+}
+
+/// The accounts a transaction can write, with their code and storage, taken before it runs
+///
+/// `None` marks an account that did not exist yet.
+#[derive(Debug, Clone, Default)]
+pub struct AccountSnapshot(Vec<(Address, Option<AccountState>)>);
+
+impl AccountSnapshot {
+    /// Captured accounts with their balance and nonce
+    pub fn accounts(&self) -> impl Iterator<Item = (Address, Option<(U256, u64)>)> + '_ {
+        self.0
+            .iter()
+            .map(|(address, account)| (*address, account.as_ref().map(|a| (a.balance, a.nonce))))
+    }
+}
+
+/// Every account a block may have written, with its code and storage, as it
+/// was before the block
+///
+/// Restoring it (as an [`AccountSnapshot`]) undoes a block that failed part of
+/// the way through, since the executor writes through to the state store.
+/// Each write commits on its own, so a crash in the middle of a block still
+/// leaves its earlier writes on disk; only errors are undone.
+#[derive(Debug, Clone, Default)]
+pub struct BlockSnapshot(HashMap<Address, Option<AccountState>>);
+
+impl BlockSnapshot {
+    /// Record the accounts of `snapshot` the block has not touched before
+    pub fn record(&mut self, snapshot: &AccountSnapshot) {
+        for (address, account) in &snapshot.0 {
+            self.0.entry(*address).or_insert_with(|| account.clone());
+        }
+    }
+}
 
-/// Simple EVM executor backed by persistent StateStore
+impl From<BlockSnapshot> for AccountSnapshot {
+    fn from(snapshot: BlockSnapshot) -> Self {
+        Self(snapshot.0.into_iter().collect())
+    }
+}
+
+/// The state store as a transaction sees it, reporting every access to the inspector
+///
+/// Transactions read and write accounts only through it, so no execution
+/// path can leave an access out of the transaction's access set.
+struct InspectedState<'a> {
+    store: Arc<dyn StateWriter>,
+    inspector: &'a mut ExecutionInspector,
+}
+
+impl InspectedState<'_> {
+    fn balance(&mut self, address: Address) -> U256 {
+        self.inspector.read_account(address);
+        self.store.get_balance(&address)
+    }
+
+    fn nonce(&mut self, address: Address) -> u64 {
+        self.inspector.read_account(address);
+        self.store.get_nonce(&address)
+    }
+
+    fn set_balance(&mut self, address: Address, balance: U256) {
+        self.inspector.write_account(address);
+        let _ = self.store.set_balance(address, balance);
+    }
+
+    fn increment_nonce(&mut self, address: Address) -> eyre::Result<u64> {
+        self.inspector.write_account(address);
+        self.store.increment_nonce(address)
+    }
+}
+
+/// Simple EVM executor backed by a shared state store
 pub struct SimpleEvmExecutor {
-    /// Shared state store (MDBX-backed)
-    state_store: Arc<StateStore>,
+    /// Shared state store
+    state_store: Arc<dyn StateWriter>,
     /// Precompile executor
     precompile_executor: PrecompileExecutor,
     /// Chain ID
-    #[allow(dead_code)]
     chain_id: u64,
 }
 
 impl SimpleEvmExecutor {
     /// Create new EVM executor with state store
-    pub fn new(chain_id: u64, state_store: Arc<StateStore>) -> Self {
-        Self { state_store, precompile_executor: PrecompileExecutor::new(), chain_id }
+    pub fn new(chain_id: u64, state_store: Arc<dyn StateWriter>) -> Self {
+        Self {
+            state_store,
+            precompile_executor: PrecompileExecutor::new(),
+            chain_id,
+        }
+    }
+
+    /// Chain id transactions must be signed for
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Snapshot the accounts `tx` can write: its sender and recipient
+    pub fn snapshot(&self, tx: &TransactionSigned) -> AccountSnapshot {
+        self.snapshot_accounts(tx.recover_signer().ok().into_iter().chain(tx.to()))
+    }
+
+    /// Snapshot `addresses` with their code and storage
+    pub fn snapshot_accounts(
+        &self,
+        addresses: impl IntoIterator<Item = Address>,
+    ) -> AccountSnapshot {
+        let accounts = addresses
+            .into_iter()
+            .map(|address| (address, self.state_store.get_account(&address)))
+            .collect();
+        AccountSnapshot(accounts)
+    }
+
+    /// Undo a transaction by restoring the accounts it could write
+    pub fn restore(&mut self, snapshot: AccountSnapshot) -> Result<(), BlockExecutionError> {
+        self.state_store.restore_accounts(&snapshot.0).map_err(|e| {
+            BlockExecutionError::msg(format!("Failed to restore accounts: {}", e))
+        })
     }
 
     /// Set account balance
@@ -30,11 +175,31 @@ impl SimpleEvmExecutor {
         let _ = self.state_store.set_balance(address, balance);
     }
 
+    /// Whether `address` has an EVM account, even an empty one
+    pub fn account_exists(&self, address: &Address) -> bool {
+        self.state_store.get_account(address).is_some()
+    }
+
+    /// Create an empty account at `address`, leaving an existing one unchanged
+    pub fn create_account(&mut self, address: Address) -> Result<(), BlockExecutionError> {
+        if self.account_exists(&address) {
+            return Ok(());
+        }
+        self.state_store.set_balance(address, U256::ZERO).map_err(|e| {
+            BlockExecutionError::msg(format!("Failed to create account {}: {}", address, e))
+        })
+    }
+
     /// Get account balance
     pub fn get_balance(&self, address: &Address) -> U256 {
         self.state_store.get_balance(address)
     }
 
+    /// Get account nonce
+    pub fn get_nonce(&self, address: &Address) -> u64 {
+        self.state_store.get_nonce(address)
+    }
+
     /// Get account count
     pub fn account_count(&self) -> usize {
         self.state_store.all_accounts().len()
@@ -47,32 +212,47 @@ impl SimpleEvmExecutor {
         _block_number: u64,
         _timestamp: u64,
     ) -> Result<Receipt, BlockExecutionError> {
-        self.execute_transaction_with_dexvm(tx, _block_number, _timestamp, None)
+        let mut inspector = ExecutionInspector::default();
+        self.execute_transaction_with_dexvm(tx, _block_number, _timestamp, None, &mut inspector)
     }
 
     /// Execute single transaction with DexVM state for cross-VM calls
+    ///
+    /// Every account and counter the transaction reads or writes is reported
+    /// to `inspector`.
     pub fn execute_transaction_with_dexvm(
         &mut self,
         tx: &TransactionSigned,
         _block_number: u64,
         _timestamp: u64,
         dexvm_state: Option<&mut DexVmState>,
+        inspector: &mut ExecutionInspector,
     ) -> Result<Receipt, BlockExecutionError> {
         let caller = tx
             .recover_signer()
             .map_err(|_| BlockExecutionError::msg("Failed to recover transaction signer"))?;
+        let mut state = InspectedState { store: Arc::clone(&self.state_store), inspector };
+
+        if sends_value_to_dexvm(tx.to(), tx.value()) {
+            return Ok(Self::reject_dexvm_value(tx, caller, &mut state));
+        }
 
         // Check if it's a precompile call
-        if let Some(to) = tx.to() {
-            if to == COUNTER_PRECOMPILE_ADDRESS {
-                return self.execute_precompile_transaction_with_dexvm(tx, caller, dexvm_state);
-            }
+        if system_handler(tx.to()) == Some(SystemHandler::CounterPrecompile) {
+            return self.execute_precompile_transaction_with_dexvm(
+                tx,
+                caller,
+                dexvm_state,
+                &mut state,
+            );
         }
 
-        let caller_balance = self.get_balance(&caller);
-        let caller_nonce = self.state_store.get_nonce(&caller);
+        let caller_balance = state.balance(caller);
+        let caller_nonce = state.nonce(caller);
         let tx_value = tx.value();
-        let tx_cost = tx_value + U256::from(tx.gas_limit() as u128 * tx.effective_gas_price(None));
+        let gas_price = tx.effective_gas_price(None);
+        let tx_cost = tx_value + U256::from(tx.gas_limit() as u128 * gas_price);
+        let gas_used = intrinsic_gas(tx);
 
         // Check nonce
         if tx.nonce() != caller_nonce {
@@ -80,7 +260,16 @@ impl SimpleEvmExecutor {
                 "Nonce mismatch for {}: expected {}, got {}",
                 caller, caller_nonce, tx.nonce()
             );
-            return Ok(Receipt { status: false.into(), cumulative_gas_used: 21000, logs: vec![] });
+            return Ok(failed_receipt());
+        }
+
+        // Check gas limit
+        if tx.gas_limit() < gas_used {
+            tracing::warn!(
+                "Gas limit below intrinsic gas for {}: limit {}, need {}",
+                caller, tx.gas_limit(), gas_used
+            );
+            return Ok(failed_receipt());
         }
 
         // Check balance
@@ -89,20 +278,20 @@ impl SimpleEvmExecutor {
                 "Insufficient balance for {}: have {}, need {}",
                 caller, caller_balance, tx_cost
             );
-            return Ok(Receipt { status: false.into(), cumulative_gas_used: 21000, logs: vec![] });
+            return Ok(failed_receipt());
         }
 
-        // Deduct balance and increment nonce
-        let new_balance = caller_balance - tx_cost;
-        self.set_balance(caller, new_balance);
-        let new_nonce = self.state_store.increment_nonce(caller).unwrap_or(caller_nonce + 1);
+        // Deduct value and the gas used, and increment nonce
+        let new_balance = caller_balance - tx_value - U256::from(gas_used as u128 * gas_price);
+        state.set_balance(caller, new_balance);
+        let new_nonce = state.increment_nonce(caller).unwrap_or(caller_nonce + 1);
 
         tracing::info!(
             "TX executed: from={}, to={:?}, value={}, gas_cost={}, balance: {} -> {}, nonce: {} -> {}",
             caller,
             tx.to(),
             tx_value,
-            tx_cost - tx_value,
+            caller_balance - tx_value - new_balance,
             caller_balance,
             new_balance,
             caller_nonce,
@@ -111,13 +300,36 @@ impl SimpleEvmExecutor {
 
         // Transfer value to recipient
         if let Some(to) = tx.to() {
-            let to_balance = self.get_balance(&to);
+            let to_balance = state.balance(to);
             let to_new_balance = to_balance + tx_value;
-            self.set_balance(to, to_new_balance);
+            state.set_balance(to, to_new_balance);
             tracing::debug!("Recipient {} balance: {} -> {}", to, to_balance, to_new_balance);
         }
 
-        Ok(Receipt { status: true.into(), cumulative_gas_used: 21000, logs: vec![] })
+        Ok(Receipt { status: true.into(), cumulative_gas_used: gas_used, logs: vec![] })
+    }
+
+    /// Fail a call that sends ETH into DexVM, moving no value and charging no gas
+    ///
+    /// The nonce is still consumed, as for a failed counter operation.
+    fn reject_dexvm_value(
+        tx: &TransactionSigned,
+        caller: Address,
+        state: &mut InspectedState<'_>,
+    ) -> Receipt {
+        let caller_nonce = state.nonce(caller);
+        if tx.nonce() != caller_nonce {
+            tracing::warn!(
+                "Nonce mismatch for {}: expected {}, got {}",
+                caller, caller_nonce, tx.nonce()
+            );
+            return failed_receipt();
+        }
+
+        tracing::warn!("Rejecting {} from {}: {}", tx.tx_hash(), caller, DEXVM_VALUE_ERROR);
+        let _ = state.increment_nonce(caller);
+
+        failed_receipt()
     }
 
     fn execute_precompile_transaction_with_dexvm(
@@ -125,12 +337,13 @@ impl SimpleEvmExecutor {
         tx: &TransactionSigned,
         caller: Address,
         dexvm_state: Option<&mut DexVmState>,
+        state: &mut InspectedState<'_>,
     ) -> Result<Receipt, BlockExecutionError> {
-        let caller_balance = self.get_balance(&caller);
-        let caller_nonce = self.state_store.get_nonce(&caller);
+        let caller_balance = state.balance(caller);
+        let caller_nonce = state.nonce(caller);
         let tx_value = tx.value();
-        let gas_cost = U256::from(tx.gas_limit() as u128 * tx.effective_gas_price(None));
-        let tx_cost = tx_value + gas_cost;
+        let gas_price = tx.effective_gas_price(None);
+        let tx_cost = tx_value + U256::from(tx.gas_limit() as u128 * gas_price);
 
         // Check nonce
         if tx.nonce() != caller_nonce {
@@ -138,40 +351,49 @@ impl SimpleEvmExecutor {
                 "Nonce mismatch for {}: expected {}, got {}",
                 caller, caller_nonce, tx.nonce()
             );
-            return Ok(Receipt { status: false.into(), cumulative_gas_used: 21000, logs: vec![] });
+            return Ok(failed_receipt());
         }
 
         // Check balance
         if caller_balance < tx_cost {
             tracing::error!("Insufficient balance: have {}, need {}", caller_balance, tx_cost);
-            return Ok(Receipt { status: false.into(), cumulative_gas_used: 21000, logs: vec![] });
+            return Ok(failed_receipt());
         }
 
-        // Save original balance for potential rollback
-        let original_balance = caller_balance;
-        self.set_balance(caller, caller_balance - tx_cost);
+        // Hold the full cost while the precompile runs
+        state.set_balance(caller, caller_balance - tx_cost);
 
-        let result = self.precompile_executor.execute_with_dexvm(
+        // The precompile's gas is charged against the transaction's gas limit
+        let result = self.precompile_executor.execute_with_gas_limit(
             caller,
             COUNTER_PRECOMPILE_ADDRESS,
             tx.input(),
+            tx.gas_limit(),
             dexvm_state,
         )?;
 
+        let written = result.success && PrecompileExecutor::writes_counter(tx.input());
+        let label = PrecompileExecutor::counter_label(tx.input());
+        state.inspector.access_counter(caller, label, written);
+
         tracing::debug!(
             "Precompile execution: success={}, gas_used={}",
             result.success,
             result.gas_used,
         );
 
-        // If counter operation failed, rollback EVM state changes (but still increment nonce)
-        if !result.success {
-            tracing::warn!("Counter operation failed, rolling back EVM state: {:?}", result.error);
-            self.set_balance(caller, original_balance);
-        }
+        // The gas used is charged either way; a failed operation also returns the value
+        let gas_fee = U256::from(result.gas_used.min(tx.gas_limit()) as u128 * gas_price);
+        let charged = if result.success {
+            tx_value + gas_fee
+        } else {
+            tracing::warn!("Counter operation failed, refunding the value: {:?}", result.error);
+            gas_fee
+        };
+        state.set_balance(caller, caller_balance - charged);
 
         // Increment nonce regardless of success (gas is still consumed)
-        let _ = self.state_store.increment_nonce(caller);
+        let _ = state.increment_nonce(caller);
 
         Ok(Receipt { status: result.success.into(), cumulative_gas_used: result.gas_used, logs: vec![] })
     }
@@ -182,30 +404,38 @@ impl SimpleEvmExecutor {
     }
 }
 
+impl EvmStateReader for SimpleEvmExecutor {
+    fn balance(&self, address: &Address) -> U256 {
+        self.state_store.get_balance(address)
+    }
+
+    fn storage(&self, address: &Address, slot: U256) -> U256 {
+        self.state_store.get_storage(address, slot)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use alloy_consensus::TxLegacy;
     use alloy_primitives::{address, Signature, TxKind};
-    use dex_storage::DualvmStorage;
-    use tempfile::tempdir;
+    use dex_storage::{DualvmStorage, StateReader, StateStore};
 
-    fn create_test_state_store() -> (Arc<StateStore>, tempfile::TempDir) {
-        let dir = tempdir().unwrap();
-        let storage = DualvmStorage::new(dir.path()).unwrap();
-        (Arc::clone(&storage.state), dir)
+    fn create_test_state_store() -> Arc<StateStore> {
+        let storage = DualvmStorage::in_memory().unwrap();
+        Arc::clone(&storage.state)
     }
 
     #[test]
     fn test_create_executor() {
-        let (state_store, _dir) = create_test_state_store();
+        let state_store = create_test_state_store();
         let executor = SimpleEvmExecutor::new(1, state_store);
         assert_eq!(executor.chain_id, 1);
     }
 
     #[test]
     fn test_set_get_balance() {
-        let (state_store, _dir) = create_test_state_store();
+        let state_store = create_test_state_store();
         let mut executor = SimpleEvmExecutor::new(1, state_store);
         let addr = address!("1111111111111111111111111111111111111111");
 
@@ -217,7 +447,7 @@ mod tests {
     fn test_precompile_counter_increment() {
         use dex_dexvm::OP_INCREMENT;
 
-        let (state_store, _dir) = create_test_state_store();
+        let state_store = create_test_state_store();
         let mut executor = SimpleEvmExecutor::new(1, state_store);
         let mut dexvm_state = DexVmState::new();
 
@@ -242,17 +472,30 @@ mod tests {
         let caller = tx.recover_signer().unwrap();
         executor.set_balance(caller, U256::from(1_000_000u64));
 
-        let receipt = executor.execute_transaction_with_dexvm(&tx, 1, 0, Some(&mut dexvm_state)).unwrap();
+        let mut inspector = ExecutionInspector::default();
+        let receipt = executor
+            .execute_transaction_with_dexvm(&tx, 1, 0, Some(&mut dexvm_state), &mut inspector)
+            .unwrap();
 
         assert_eq!(receipt.status, true.into());
         assert_eq!(dexvm_state.get_counter(&caller), 10);
+
+        // The precompile's gas is charged and the rest of the limit refunded
+        let charged = U256::from(receipt.cumulative_gas_used);
+        assert_eq!(executor.get_balance(&caller), U256::from(1_000_000u64) - charged);
+
+        let access_set = inspector.take_access_set();
+        assert!(access_set.accounts_written.contains(&caller));
+        assert!(access_set
+            .slots_written
+            .contains(&(COUNTER_PRECOMPILE_ADDRESS, dex_primitives::counter_slot(caller))));
     }
 
     #[test]
     fn test_precompile_counter_decrement_rollback() {
         use dex_dexvm::OP_DECREMENT;
 
-        let (state_store, _dir) = create_test_state_store();
+        let state_store = create_test_state_store();
         let mut executor = SimpleEvmExecutor::new(1, state_store);
         let mut dexvm_state = DexVmState::new();
 
@@ -284,12 +527,158 @@ mod tests {
         executor.set_balance(recovered_caller, U256::from(1_000_000u64));
 
         let original_balance = executor.get_balance(&recovered_caller);
-        let receipt = executor.execute_transaction_with_dexvm(&tx, 1, 0, Some(&mut dexvm_state)).unwrap();
+        let mut inspector = ExecutionInspector::default();
+        let receipt = executor
+            .execute_transaction_with_dexvm(&tx, 1, 0, Some(&mut dexvm_state), &mut inspector)
+            .unwrap();
 
         // Transaction should fail (status false)
         assert_eq!(receipt.status, false.into());
 
-        // EVM balance should be restored (rollback)
-        assert_eq!(executor.get_balance(&recovered_caller), original_balance);
+        // Only the gas used is charged
+        let charged = U256::from(receipt.cumulative_gas_used);
+        assert!(!charged.is_zero());
+        assert_eq!(executor.get_balance(&recovered_caller), original_balance - charged);
+    }
+
+    #[test]
+    fn test_precompile_out_of_gas() {
+        use dex_dexvm::OP_INCREMENT;
+
+        let state_store = create_test_state_store();
+        let mut executor = SimpleEvmExecutor::new(1, state_store);
+        let mut dexvm_state = DexVmState::new();
+
+        let mut calldata = vec![OP_INCREMENT];
+        calldata.extend_from_slice(&10u64.to_be_bytes());
+        let gas_limit = PrecompileExecutor::required_gas(&calldata) - 1;
+
+        let tx = TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
+                value: U256::ZERO,
+                input: calldata.into(),
+                nonce: 0,
+                gas_price: 1,
+                gas_limit,
+                chain_id: Some(1),
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+
+        let caller = tx.recover_signer().unwrap();
+        executor.set_balance(caller, U256::from(1_000_000u64));
+
+        let mut inspector = ExecutionInspector::default();
+        let receipt = executor
+            .execute_transaction_with_dexvm(&tx, 1, 0, Some(&mut dexvm_state), &mut inspector)
+            .unwrap();
+
+        // The whole limit is used and charged, and the counter is untouched
+        assert_eq!(receipt.status, false.into());
+        assert_eq!(receipt.cumulative_gas_used, gas_limit);
+        assert_eq!(dexvm_state.get_counter(&caller), 0);
+        assert_eq!(executor.get_balance(&caller), U256::from(1_000_000u64 - gas_limit));
+    }
+
+    #[test]
+    fn test_precompile_rejects_value() {
+        use dex_dexvm::OP_INCREMENT;
+
+        let state_store = create_test_state_store();
+        let mut executor = SimpleEvmExecutor::new(1, state_store.clone());
+        let mut dexvm_state = DexVmState::new();
+
+        let mut calldata = vec![OP_INCREMENT];
+        calldata.extend_from_slice(&10u64.to_be_bytes());
+
+        let tx = TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
+                value: U256::from(500),
+                input: calldata.into(),
+                nonce: 0,
+                gas_price: 1,
+                gas_limit: 100000,
+                chain_id: Some(1),
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+
+        let caller = tx.recover_signer().unwrap();
+        executor.set_balance(caller, U256::from(1_000_000u64));
+
+        let mut inspector = ExecutionInspector::default();
+        let receipt = executor
+            .execute_transaction_with_dexvm(&tx, 1, 0, Some(&mut dexvm_state), &mut inspector)
+            .unwrap();
+
+        // The call fails, the value stays with the sender and only the nonce is used
+        assert_eq!(receipt.status, false.into());
+        assert_eq!(dexvm_state.get_counter(&caller), 0);
+        assert_eq!(executor.get_balance(&caller), U256::from(1_000_000u64));
+        assert_eq!(executor.get_balance(&COUNTER_PRECOMPILE_ADDRESS), U256::ZERO);
+        assert_eq!(state_store.get_nonce(&caller), 1);
+    }
+
+    #[test]
+    fn test_transfer_reports_intrinsic_gas() {
+        let state_store = create_test_state_store();
+        let mut executor = SimpleEvmExecutor::new(1, state_store);
+        let to = address!("2222222222222222222222222222222222222222");
+        let transfer = |nonce, input: Vec<u8>, gas_limit| {
+            TransactionSigned::new_unhashed(
+                TxLegacy {
+                    to: TxKind::Call(to),
+                    value: U256::from(1),
+                    input: input.into(),
+                    nonce,
+                    gas_price: 1,
+                    gas_limit,
+                    chain_id: Some(1),
+                }
+                .into(),
+                Signature::test_signature(),
+            )
+        };
+        // The test signature recovers to a different sender for every transaction
+        let funded = |executor: &mut SimpleEvmExecutor, tx: &TransactionSigned| {
+            let caller = tx.recover_signer().unwrap();
+            executor.set_balance(caller, U256::from(1_000_000u64));
+            caller
+        };
+
+        let tx = transfer(0, vec![], 50_000);
+        let caller = funded(&mut executor, &tx);
+        let receipt = executor.execute_transaction(&tx, 1, 0).unwrap();
+        assert_eq!(receipt.cumulative_gas_used, 21_000);
+
+        // Only the gas used is charged; the rest of the limit is refunded
+        assert_eq!(executor.get_balance(&caller), U256::from(1_000_000u64 - 1 - 21_000));
+
+        // Calldata costs 4 gas per zero byte and 16 per other byte
+        let tx = transfer(0, vec![0, 0, 1], 50_000);
+        funded(&mut executor, &tx);
+        let receipt = executor.execute_transaction(&tx, 1, 0).unwrap();
+        assert_eq!(receipt.status, true.into());
+        assert_eq!(receipt.cumulative_gas_used, 21_000 + 2 * 4 + 16);
+
+        // A transaction failing its nonce check is not charged and uses no gas
+        let tx = transfer(5, vec![], 50_000);
+        funded(&mut executor, &tx);
+        let receipt = executor.execute_transaction(&tx, 1, 0).unwrap();
+        assert_eq!(receipt.status, false.into());
+        assert_eq!(receipt.cumulative_gas_used, 0);
+
+        // A limit below the intrinsic gas fails the transaction without charging it
+        let tx = transfer(0, vec![], 20_999);
+        let caller = funded(&mut executor, &tx);
+        let receipt = executor.execute_transaction(&tx, 1, 0).unwrap();
+        assert_eq!(receipt.status, false.into());
+        assert_eq!(receipt.cumulative_gas_used, 0);
+        assert_eq!(executor.get_balance(&caller), U256::from(1_000_000u64));
+        assert_eq!(executor.get_nonce(&caller), 0);
     }
 }
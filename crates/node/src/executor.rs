@@ -1,13 +1,29 @@
 //! Dual VM executor
 
-use crate::evm_executor::SimpleEvmExecutor;
-use alloy_consensus::Transaction;
-use alloy_primitives::B256;
-use dex_dexvm::{DexVmExecutor, COUNTER_PRECOMPILE_ADDRESS};
-use dex_primitives::{DexVmReceipt, DualVmTransaction};
+use crate::{
+    evm_executor::{AccountSnapshot, BlockSnapshot, SimpleEvmExecutor},
+    fees::FeePolicy,
+    inspector::{AccountChange, ExecutionInspector, TracedTxKind, TxTrace},
+};
+use alloy_consensus::{transaction::SignerRecoverable, Transaction};
+use alloy_primitives::{Address, B256, U256};
+use dex_dexvm::{sends_value_to_dexvm, DexVmExecutor, StateCheckpoint, DEXVM_VALUE_ERROR};
+use dex_primitives::{
+    system_handler, AccessSet, CounterChange, DexVmReceipt, DexVmTransaction, DualVmTransaction,
+    SystemHandler,
+};
+use dex_rpc::{recover_operation_signer, TxTimeHistogram};
 use reth_ethereum_primitives::TransactionSigned;
 use reth_execution_errors::BlockExecutionError;
-use std::sync::{Arc, RwLock};
+use dex_storage::StoredBlockStats;
+use std::{
+    any::Any,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::{Duration, Instant},
+};
 
 /// Dual VM execution result
 #[derive(Debug, Clone)]
@@ -16,6 +32,8 @@ pub struct DualVmExecutionResult {
     pub evm_receipts: Vec<alloy_consensus::Receipt>,
     /// DexVM receipts
     pub dexvm_receipts: Vec<DexVmReceipt>,
+    /// Receipts for every signed transaction in block order, with block-cumulative gas
+    pub block_receipts: Vec<alloy_consensus::Receipt>,
     /// Total gas used
     pub total_gas_used: u64,
     /// EVM state root
@@ -24,6 +42,230 @@ pub struct DualVmExecutionResult {
     pub dexvm_state_root: B256,
     /// Combined state root
     pub combined_state_root: B256,
+    /// Number of signed transactions executed
+    ///
+    /// Together with `skipped_transactions` they form a prefix of the input.
+    pub included_transactions: usize,
+    /// Number of native DexVM transactions executed
+    ///
+    /// Together with the native ones in `skipped_transactions` they form a
+    /// prefix of the input.
+    pub included_dexvm_transactions: usize,
+    /// Number of included signed transactions that called the counter precompile
+    pub cross_vm_transactions: usize,
+    /// Wall-clock time spent executing the block
+    pub execution_time: Duration,
+    /// State accessed by each included signed transaction, by transaction hash
+    pub access_sets: Vec<(B256, AccessSet)>,
+    /// Counter values changed by included transactions, in execution order
+    pub counter_changes: Vec<CounterChange>,
+    /// Transactions dropped from the block, with their effects undone
+    ///
+    /// Native DexVM transactions are dropped without running when their
+    /// signature or nonce is invalid.
+    pub skipped_transactions: Vec<(B256, SkipReason)>,
+    /// Addresses given their first counter in this block that had no EVM account
+    ///
+    /// They now have an empty EVM account if the executor creates accounts
+    /// (see [`DualVmExecutor::set_create_evm_accounts`]).
+    pub counter_only_accounts: Vec<Address>,
+    /// Gas fees charged to senders of executed EVM transactions
+    pub fees_paid: U256,
+    /// Part of `fees_paid` the fee policy credited to accounts; the rest was destroyed
+    pub fees_credited: U256,
+    /// Per-transaction traces, recorded only while tracing is enabled
+    pub traces: Vec<TxTrace>,
+}
+
+impl DualVmExecutionResult {
+    /// Fill in the state roots of a block executed with deferred roots
+    pub fn set_state_roots(&mut self, evm_state_root: B256, dexvm_state_root: B256) {
+        self.evm_state_root = evm_state_root;
+        self.dexvm_state_root = dexvm_state_root;
+        self.combined_state_root = combine_state_roots(evm_state_root, dexvm_state_root);
+    }
+}
+
+/// Combine the EVM and DexVM state roots into the block's state root
+pub fn combine_state_roots(evm_root: B256, dexvm_root: B256) -> B256 {
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(evm_root.as_slice());
+    data.extend_from_slice(dexvm_root.as_slice());
+    alloy_primitives::keccak256(&data)
+}
+
+/// Why a transaction was dropped from a block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Execution took longer than the per-transaction timeout
+    TimedOut(Duration),
+    /// The executor panicked
+    Panicked(String),
+    /// A native DexVM transaction not signed by its sender
+    InvalidSignature(String),
+    /// A native DexVM transaction whose nonce does not exceed the sender's last one
+    StaleNonce { nonce: u64, last: u64 },
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TimedOut(elapsed) => write!(f, "execution took {:?}", elapsed),
+            Self::Panicked(message) => write!(f, "executor panicked: {}", message),
+            Self::InvalidSignature(message) => write!(f, "invalid signature: {}", message),
+            Self::StaleNonce { nonce, last } => {
+                write!(f, "nonce {} does not exceed the last nonce {}", nonce, last)
+            }
+        }
+    }
+}
+
+/// Text of a caught panic
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Per-transaction outputs accumulated while executing a block
+#[derive(Default)]
+struct BlockOutputs {
+    evm_receipts: Vec<alloy_consensus::Receipt>,
+    dexvm_receipts: Vec<DexVmReceipt>,
+    block_receipts: Vec<alloy_consensus::Receipt>,
+    cross_vm_transactions: usize,
+    access_sets: Vec<(B256, AccessSet)>,
+    counter_changes: Vec<CounterChange>,
+    skipped_transactions: Vec<(B256, SkipReason)>,
+    fees_paid: U256,
+    fee_credits: BTreeMap<Address, U256>,
+}
+
+impl BlockOutputs {
+    /// Add the outputs of a transaction executed on its own
+    fn append(&mut self, other: Self) {
+        self.evm_receipts.extend(other.evm_receipts);
+        self.dexvm_receipts.extend(other.dexvm_receipts);
+        self.block_receipts.extend(other.block_receipts);
+        self.cross_vm_transactions += other.cross_vm_transactions;
+        self.access_sets.extend(other.access_sets);
+        self.counter_changes.extend(other.counter_changes);
+        self.skipped_transactions.extend(other.skipped_transactions);
+        self.fees_paid += other.fees_paid;
+        for (address, amount) in other.fee_credits {
+            *self.fee_credits.entry(address).or_default() += amount;
+        }
+    }
+
+    /// Record the fee of an executed EVM transaction, charged on the gas it used
+    ///
+    /// Failed precompile calls still pay for their gas; transactions failing their
+    /// checks use none. `receipt` still reports the transaction's own gas, not the
+    /// block's running total.
+    fn record_fee(
+        &mut self,
+        tx: &TransactionSigned,
+        receipt: &alloy_consensus::Receipt,
+        policy: &FeePolicy,
+        validator: Option<Address>,
+    ) {
+        let gas_used = receipt.cumulative_gas_used;
+        let gas_price = tx.effective_gas_price(None);
+        self.fees_paid += U256::from(gas_used as u128 * gas_price);
+        for (address, amount) in policy.credits(gas_used, gas_price, validator) {
+            *self.fee_credits.entry(address).or_default() += amount;
+        }
+    }
+
+    /// Record a counter change, skipping operations that left the value unchanged
+    fn record_counter_change(&mut self, address: Address, old: u64, new: u64, tx_hash: B256) {
+        if old != new {
+            self.counter_changes.push(CounterChange {
+                address,
+                old_value: old,
+                new_value: new,
+                tx_hash,
+            });
+        }
+    }
+}
+
+impl DualVmExecutionResult {
+    /// Summarize the block for the block stats table
+    pub fn block_stats(&self) -> StoredBlockStats {
+        let cross_vm = self.cross_vm_transactions;
+        let dexvm_routed = self.block_receipts.len() - self.evm_receipts.len();
+        let native = self.included_dexvm_transactions;
+
+        let failed_signed =
+            self.block_receipts.iter().filter(|r| !r.status.coerce_status()).count();
+        let native_receipts = &self.dexvm_receipts[self.dexvm_receipts.len() - native..];
+        let failed_native = native_receipts.iter().filter(|r| !r.success).count();
+
+        StoredBlockStats {
+            evm_tx_count: (self.evm_receipts.len() - cross_vm) as u64,
+            cross_vm_tx_count: cross_vm as u64,
+            dexvm_tx_count: dexvm_routed as u64,
+            native_dexvm_tx_count: native as u64,
+            failed_tx_count: (failed_signed + failed_native) as u64,
+            dexvm_ops_count: (self.dexvm_receipts.len() + cross_vm) as u64,
+            gas_used: self.total_gas_used,
+            execution_micros: self.execution_time.as_micros() as u64,
+        }
+    }
+}
+
+/// Check whether the block building deadline has passed
+///
+/// Callers only check after one transaction has run, so every block makes progress.
+fn deadline_passed(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Check that a native DexVM transaction is signed by its sender with a fresh nonce
+fn check_native_transaction(
+    tx: &DexVmTransaction,
+    chain_id: u64,
+    last_nonce: u64,
+) -> Result<(), SkipReason> {
+    let signer = recover_operation_signer(tx.operation, tx.nonce, chain_id, &tx.signature)
+        .map_err(SkipReason::InvalidSignature)?;
+    if signer != tx.from {
+        return Err(SkipReason::InvalidSignature(format!("signed by {}", signer)));
+    }
+    if tx.nonce <= last_nonce {
+        return Err(SkipReason::StaleNonce { nonce: tx.nonce, last: last_nonce });
+    }
+    Ok(())
+}
+
+fn log_slow_tx(hash: B256, sender: Address, gas_used: u64, elapsed: Duration) {
+    tracing::warn!(
+        "Slow transaction {} from {}: {:?} for {} gas",
+        hash, sender, elapsed, gas_used
+    );
+}
+
+/// An account as the next transaction executed would see it, in both VMs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountView {
+    pub balance: U256,
+    pub nonce: u64,
+    /// DexVM counter, including changes of the block being executed
+    pub counter: u64,
+}
+
+/// How to undo a block executed by [`DualVmExecutor::execute_imported_block`]
+///
+/// Holds the EVM accounts the block wrote as they were before it and the
+/// DexVM checkpoint opened before it, which stays open until the block is
+/// kept or undone.
+#[derive(Debug)]
+pub struct BlockUndo {
+    evm: BlockSnapshot,
+    dexvm: StateCheckpoint,
 }
 
 /// Dual VM executor
@@ -32,6 +274,18 @@ pub struct DualVmExecutor {
     dexvm_executor: Arc<RwLock<DexVmExecutor>>,
     current_block: u64,
     current_timestamp: u64,
+    /// Where the fees of executed transactions go
+    fee_policy: FeePolicy,
+    /// Validator producing the current block, credited with tips
+    beneficiary: Option<Address>,
+    /// Collects each transaction's state accesses, and traces while tracing is enabled
+    inspector: ExecutionInspector,
+    /// Execution time of every transaction run
+    tx_times: Option<Arc<TxTimeHistogram>>,
+    /// Included transactions running longer than this are logged
+    slow_tx_threshold: Option<Duration>,
+    /// Give addresses an empty EVM account when their counter is first set
+    create_evm_accounts: bool,
 }
 
 impl DualVmExecutor {
@@ -48,9 +302,56 @@ impl DualVmExecutor {
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
+            fee_policy: FeePolicy::default(),
+            beneficiary: None,
+            inspector: ExecutionInspector::default(),
+            tx_times: None,
+            slow_tx_threshold: None,
+            create_evm_accounts: false,
         }
     }
 
+    /// Record the execution time of every transaction in `histogram`
+    pub fn set_tx_times(&mut self, histogram: Arc<TxTimeHistogram>) {
+        self.tx_times = Some(histogram);
+    }
+
+    /// Log the hash, sender and gas of included transactions slower than `threshold`
+    pub fn set_slow_tx_threshold(&mut self, threshold: Option<Duration>) {
+        self.slow_tx_threshold = threshold;
+    }
+
+    /// Create an empty EVM account for addresses whose counter is first set in a block
+    ///
+    /// Changes the EVM state root of such blocks, so every node replaying a
+    /// chain must use the setting its validator used.
+    pub fn set_create_evm_accounts(&mut self, enabled: bool) {
+        self.create_evm_accounts = enabled;
+    }
+
+    /// Distribute transaction fees according to `policy`
+    pub fn set_fee_policy(&mut self, policy: FeePolicy) {
+        self.fee_policy = policy;
+    }
+
+    /// Set the validator credited with tips of the blocks executed next
+    pub fn set_beneficiary(&mut self, beneficiary: Option<Address>) {
+        self.beneficiary = beneficiary;
+    }
+
+    /// Record a trace of every included transaction in the execution result
+    ///
+    /// Reads the sender and recipient accounts around each transaction, so
+    /// blocks execute more slowly while enabled.
+    pub fn set_tracing(&mut self, enabled: bool) {
+        self.inspector = ExecutionInspector::new(enabled);
+    }
+
+    /// Whether transactions are traced
+    pub fn is_tracing(&self) -> bool {
+        self.inspector.is_tracing()
+    }
+
     /// Advance to next block
     pub fn advance_block(&mut self) {
         self.current_block += 1;
@@ -63,94 +364,490 @@ impl DualVmExecutor {
         &mut self,
         transactions: Vec<TransactionSigned>,
     ) -> Result<DualVmExecutionResult, BlockExecutionError> {
-        let mut evm_receipts = Vec::new();
-        let mut dexvm_receipts = Vec::new();
+        self.execute_block(transactions, Vec::new(), None, None)
+    }
+
+    /// Execute a block of signed transactions followed by native DexVM transactions
+    ///
+    /// Ordering rule: signed transactions run first in the given order, then
+    /// native DexVM transactions in queue order. DexVM changes are journaled
+    /// for the whole block; if any transaction errors, they are reverted.
+    ///
+    /// Visibility rule: every transaction sees the effects of all earlier
+    /// transactions of the block in both VMs, e.g. a cross-VM call can spend
+    /// ETH transferred to its sender one transaction before, and a native
+    /// DexVM transaction sees counters changed by signed ones. A failed
+    /// transaction (status 0) changes no balance and no counter, so later
+    /// transactions see the state before it; a failed cross-VM call still
+    /// consumes its sender's nonce.
+    ///
+    /// Once `deadline` passes, no further transactions are started; the
+    /// result reports how many of each kind were included.
+    ///
+    /// Signed transactions running longer than `tx_timeout` are dropped.
+    /// Execution cannot be interrupted, so an overrunning transaction is
+    /// undone once it returns. Transactions that panic are always dropped.
+    /// Only proposals we build set a timeout: imported and replayed blocks
+    /// must run every transaction their proposer included.
+    pub fn execute_block(
+        &mut self,
+        transactions: Vec<TransactionSigned>,
+        dexvm_transactions: Vec<DexVmTransaction>,
+        deadline: Option<Instant>,
+        tx_timeout: Option<Duration>,
+    ) -> Result<DualVmExecutionResult, BlockExecutionError> {
+        let mut result = self.execute_block_deferred(
+            transactions,
+            dexvm_transactions,
+            deadline,
+            tx_timeout,
+        )?;
+        let evm_state_root = self.evm_read()?.state_root();
+        let dexvm_state_root = self.dexvm_read()?.state_root();
+        result.set_state_roots(evm_state_root, dexvm_state_root);
+        Ok(result)
+    }
+
+    /// Execute a block like [`Self::execute_block`], without computing its state roots
+    ///
+    /// The roots are left zero for the caller to compute, e.g. in the
+    /// background, and fill in with [`DualVmExecutionResult::set_state_roots`].
+    pub fn execute_block_deferred(
+        &mut self,
+        transactions: Vec<TransactionSigned>,
+        dexvm_transactions: Vec<DexVmTransaction>,
+        deadline: Option<Instant>,
+        tx_timeout: Option<Duration>,
+    ) -> Result<DualVmExecutionResult, BlockExecutionError> {
+        self.run_block(transactions, dexvm_transactions, deadline, tx_timeout, false)
+            .map(|(result, _)| result)
+    }
+
+    /// Execute a block another validator proposed, without computing its state roots
+    ///
+    /// Every transaction must run: a skipped one fails the block, which is
+    /// undone. The block stays undoable until [`Self::keep_block`] or
+    /// [`Self::undo_block`] is called with the returned [`BlockUndo`], e.g.
+    /// once its hash is checked against the one its proposer sealed. Until
+    /// then its DexVM changes are only in the pending state.
+    pub fn execute_imported_block(
+        &mut self,
+        transactions: Vec<TransactionSigned>,
+        dexvm_transactions: Vec<DexVmTransaction>,
+    ) -> Result<(DualVmExecutionResult, BlockUndo), BlockExecutionError> {
+        let (result, undo) = self.run_block(transactions, dexvm_transactions, None, None, true)?;
+        Ok((result, undo.expect("imported blocks keep their undo")))
+    }
+
+    /// Keep a block executed by [`Self::execute_imported_block`]
+    pub fn keep_block(&mut self, undo: BlockUndo) -> Result<(), BlockExecutionError> {
+        let mut dexvm = self.dexvm_write()?;
+        dexvm.pending_state_mut().commit_checkpoint(undo.dexvm);
+        dexvm.sync_pending_to_state();
+        Ok(())
+    }
+
+    /// Undo a block executed by [`Self::execute_imported_block`]
+    ///
+    /// Restores the EVM accounts it wrote and reverts its DexVM changes.
+    pub fn undo_block(&mut self, undo: BlockUndo) -> Result<(), BlockExecutionError> {
+        self.dexvm_write()?.pending_state_mut().revert_to_checkpoint(undo.dexvm);
+        self.evm_write()?.restore(undo.evm.into())
+    }
+
+    /// Execute a block, returning its undo instead of keeping it if `imported`
+    fn run_block(
+        &mut self,
+        transactions: Vec<TransactionSigned>,
+        dexvm_transactions: Vec<DexVmTransaction>,
+        deadline: Option<Instant>,
+        tx_timeout: Option<Duration>,
+        imported: bool,
+    ) -> Result<(DualVmExecutionResult, Option<BlockUndo>), BlockExecutionError> {
+        let started = Instant::now();
+        let mut outputs = BlockOutputs::default();
+        self.inspector.take();
+
+        let block_checkpoint = self.dexvm_write()?.pending_state_mut().checkpoint();
+        let mut block_snapshot = BlockSnapshot::default();
+
+        let executed = self
+            .execute_block_transactions(
+                transactions,
+                deadline,
+                tx_timeout,
+                &mut outputs,
+                &mut block_snapshot,
+            )
+            .and_then(|gas_used| {
+                self.execute_native_dexvm_transactions(
+                    dexvm_transactions,
+                    deadline,
+                    gas_used,
+                    &mut outputs,
+                )
+            })
+            .and_then(|outcome| {
+                let fees_credited = self.credit_fees(&outputs.fee_credits, &mut block_snapshot)?;
+                let counter_only_accounts =
+                    self.counter_only_accounts(&outputs.counter_changes, &mut block_snapshot)?;
+                Ok((outcome, fees_credited, counter_only_accounts))
+            })
+            .and_then(|executed| match outputs.skipped_transactions.first() {
+                Some((hash, reason)) if imported => Err(BlockExecutionError::msg(format!(
+                    "Included transaction {} did not run: {}",
+                    hash, reason
+                ))),
+                _ => Ok(executed),
+            });
+        let ((total_gas_used, included_dexvm_transactions), fees_credited, counter_only_accounts) =
+            match executed {
+                Ok(executed) => executed,
+                Err(e) => {
+                    // EVM writes go straight to the state store, so they are undone by
+                    // restoring the accounts as they were before the block
+                    tracing::warn!(
+                        "Block execution failed, reverting EVM and DexVM changes: {}",
+                        e
+                    );
+                    self.evm_executor.clear_poison();
+                    self.dexvm_executor.clear_poison();
+                    self.dexvm_write()?.pending_state_mut().revert_to_checkpoint(block_checkpoint);
+                    self.evm_write()?.restore(block_snapshot.into())?;
+                    return Err(e);
+                }
+            };
+
+        let undo = if imported {
+            Some(BlockUndo { evm: block_snapshot, dexvm: block_checkpoint })
+        } else {
+            // Sync DexVM pending state to committed state before roots are computed
+            let mut dexvm = self.dexvm_write()?;
+            dexvm.pending_state_mut().commit_checkpoint(block_checkpoint);
+            dexvm.sync_pending_to_state();
+            None
+        };
+
+        let included_transactions = outputs.block_receipts.len();
+
+        let result = DualVmExecutionResult {
+            evm_receipts: outputs.evm_receipts,
+            dexvm_receipts: outputs.dexvm_receipts,
+            block_receipts: outputs.block_receipts,
+            total_gas_used,
+            evm_state_root: B256::ZERO,
+            dexvm_state_root: B256::ZERO,
+            combined_state_root: B256::ZERO,
+            included_transactions,
+            included_dexvm_transactions,
+            cross_vm_transactions: outputs.cross_vm_transactions,
+            execution_time: started.elapsed(),
+            access_sets: outputs.access_sets,
+            counter_changes: outputs.counter_changes,
+            skipped_transactions: outputs.skipped_transactions,
+            counter_only_accounts,
+            fees_paid: outputs.fees_paid,
+            fees_credited,
+            traces: self.inspector.take(),
+        };
+        Ok((result, undo))
+    }
+
+    /// Execute each transaction in order, returning total gas used
+    ///
+    /// Records the accounts each transaction can write in `block_snapshot` before it runs.
+    fn execute_block_transactions(
+        &mut self,
+        transactions: Vec<TransactionSigned>,
+        deadline: Option<Instant>,
+        tx_timeout: Option<Duration>,
+        outputs: &mut BlockOutputs,
+        block_snapshot: &mut BlockSnapshot,
+    ) -> Result<u64, BlockExecutionError> {
         let mut total_gas_used = 0u64;
 
         for tx in transactions {
-            let dual_tx = DualVmTransaction::from_ethereum_tx(tx.clone());
-
-            match dual_tx {
-                DualVmTransaction::Evm(_evm_tx) => {
-                    // Check if this EVM tx is calling the counter precompile
-                    let is_precompile_call = tx.to() == Some(COUNTER_PRECOMPILE_ADDRESS);
-
-                    if is_precompile_call {
-                        // Cross-VM call: EVM → DexVM via precompile
-                        // Need write access to both executors
-                        let receipt = self.execute_cross_vm_transaction(&tx)?;
-                        total_gas_used += receipt.cumulative_gas_used;
-                        evm_receipts.push(receipt);
-                    } else {
-                        // Regular EVM transaction
-                        let mut executor = self
-                            .evm_executor
-                            .write()
-                            .map_err(|e| BlockExecutionError::msg(format!("Lock error: {}", e)))?;
-
-                        let receipt = executor.execute_transaction(
-                            &tx,
-                            self.current_block,
-                            self.current_timestamp,
-                        )?;
-
-                        total_gas_used += receipt.cumulative_gas_used;
-                        evm_receipts.push(receipt);
+            if total_gas_used > 0 && deadline_passed(deadline) {
+                break;
+            }
+
+            let hash = *tx.tx_hash();
+            let snapshot = self.evm_read()?.snapshot(&tx);
+            block_snapshot.record(&snapshot);
+            let checkpoint = self.dexvm_write()?.pending_state_mut().checkpoint();
+
+            // Outputs are buffered so a skipped transaction leaves no trace
+            let mut tx_outputs = BlockOutputs::default();
+            let started = Instant::now();
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.execute_signed_transaction(&tx, total_gas_used, &mut tx_outputs)
+            }));
+            let elapsed = started.elapsed();
+            self.record_tx_time(elapsed);
+
+            let skip_reason = match outcome {
+                Ok(Ok(_)) if tx_timeout.is_some_and(|timeout| elapsed > timeout) => {
+                    SkipReason::TimedOut(elapsed)
+                }
+                Ok(Ok(gas_used)) => {
+                    self.dexvm_write()?.pending_state_mut().commit_checkpoint(checkpoint);
+                    if self.is_slow(elapsed) {
+                        let sender = tx.recover_signer().unwrap_or_default();
+                        log_slow_tx(hash, sender, gas_used - total_gas_used, elapsed);
+                    }
+                    if self.inspector.is_tracing() {
+                        let gas = gas_used - total_gas_used;
+                        let trace =
+                            self.trace_transaction(&tx, &snapshot, &tx_outputs, gas, elapsed)?;
+                        self.inspector.record(trace);
                     }
+                    outputs.append(tx_outputs);
+                    total_gas_used = gas_used;
+                    continue;
                 }
-                DualVmTransaction::DexVm(dexvm_tx) => {
+                Ok(Err(e)) => {
+                    self.dexvm_write()?.pending_state_mut().revert_to_checkpoint(checkpoint);
+                    return Err(e);
+                }
+                Err(payload) => {
+                    // The panic poisoned whichever lock it held; the state is restored below
+                    self.evm_executor.clear_poison();
+                    self.dexvm_executor.clear_poison();
+                    SkipReason::Panicked(panic_message(payload.as_ref()))
+                }
+            };
+
+            tracing::warn!("Skipping transaction {}: {}", hash, skip_reason);
+            self.evm_write()?.restore(snapshot)?;
+            self.dexvm_write()?.pending_state_mut().revert_to_checkpoint(checkpoint);
+            outputs.skipped_transactions.push((hash, skip_reason));
+        }
+
+        Ok(total_gas_used)
+    }
+
+    /// Execute one signed transaction, returning the new block gas total
+    ///
+    /// Its access set is whatever the executors reported to the inspector while it ran.
+    fn execute_signed_transaction(
+        &mut self,
+        tx: &TransactionSigned,
+        mut total_gas_used: u64,
+        outputs: &mut BlockOutputs,
+    ) -> Result<u64, BlockExecutionError> {
+        let dual_tx = DualVmTransaction::from_ethereum_tx(tx.clone());
+        self.inspector.start_transaction();
+
+        match dual_tx {
+            DualVmTransaction::Evm(_evm_tx) => {
+                // Check if this EVM tx is calling the counter precompile
+                let is_precompile_call =
+                    system_handler(tx.to()) == Some(SystemHandler::CounterPrecompile);
+
+                if is_precompile_call {
+                    // Cross-VM call: EVM → DexVM via precompile
+                    // Need write access to both executors
+                    let owner = tx.recover_signer().unwrap_or_default();
+                    let old = self.dexvm_read()?.pending_state().get_counter(&owner);
+                    let mut receipt = self.execute_cross_vm_transaction(tx)?;
+                    let new = self.dexvm_read()?.pending_state().get_counter(&owner);
+                    outputs.record_counter_change(owner, old, new, *tx.tx_hash());
+                    outputs.record_fee(tx, &receipt, &self.fee_policy, self.beneficiary);
+                    total_gas_used += receipt.cumulative_gas_used;
+                    receipt.cumulative_gas_used = total_gas_used;
+                    outputs.block_receipts.push(receipt.clone());
+                    outputs.evm_receipts.push(receipt);
+                    outputs.cross_vm_transactions += 1;
+                } else {
+                    // Regular EVM transaction
                     let mut executor = self
-                        .dexvm_executor
+                        .evm_executor
                         .write()
                         .map_err(|e| BlockExecutionError::msg(format!("Lock error: {}", e)))?;
 
-                    let result = executor.execute_transaction(&dexvm_tx)?;
-                    total_gas_used += result.gas_used;
+                    let mut receipt = executor.execute_transaction_with_dexvm(
+                        tx,
+                        self.current_block,
+                        self.current_timestamp,
+                        None,
+                        &mut self.inspector,
+                    )?;
+                    outputs.record_fee(tx, &receipt, &self.fee_policy, self.beneficiary);
 
-                    let receipt = DexVmReceipt::from_result(result, dexvm_tx.from);
-                    dexvm_receipts.push(receipt);
-
-                    executor.commit();
+                    // Executors report per-tx gas; receipts carry the running block total
+                    total_gas_used += receipt.cumulative_gas_used;
+                    receipt.cumulative_gas_used = total_gas_used;
+                    outputs.block_receipts.push(receipt.clone());
+                    outputs.evm_receipts.push(receipt);
                 }
             }
+            DualVmTransaction::DexVm(dexvm_tx) => {
+                let result = {
+                    // EVM before DexVM, the order cross-VM transactions lock in
+                    let evm = self.evm_read()?;
+                    let mut executor = self.dexvm_write()?;
+
+                    if sends_value_to_dexvm(tx.to(), tx.value()) {
+                        tracing::warn!("Rejecting {}: {}", tx.tx_hash(), DEXVM_VALUE_ERROR);
+                        executor.reject_transaction(&dexvm_tx, DEXVM_VALUE_ERROR)
+                    } else {
+                        executor.execute_transaction_with_evm(&dexvm_tx, Some(&*evm))?
+                    }
+                };
+                total_gas_used += result.gas_used;
+
+                self.inspector.access_operation(dexvm_tx.from, dexvm_tx.operation, result.success);
+                outputs.record_counter_change(
+                    dexvm_tx.from,
+                    result.old_counter,
+                    result.new_counter,
+                    *tx.tx_hash(),
+                );
+
+                outputs.block_receipts.push(alloy_consensus::Receipt {
+                    status: result.success.into(),
+                    cumulative_gas_used: total_gas_used,
+                    logs: vec![],
+                });
+
+                let receipt = DexVmReceipt::from_result(result, dexvm_tx.from)
+                    .with_transaction(*tx.tx_hash(), dexvm_tx.operation);
+                outputs.dexvm_receipts.push(receipt);
+            }
         }
 
-        // Sync DexVM pending state to committed state before computing roots
-        {
-            let mut dexvm_executor = self
-                .dexvm_executor
-                .write()
-                .map_err(|e| BlockExecutionError::msg(format!("DexVM lock error: {}", e)))?;
-            dexvm_executor.sync_pending_to_state();
+        outputs.access_sets.push((*tx.tx_hash(), self.inspector.take_access_set()));
+        Ok(total_gas_used)
+    }
+
+    /// Execute queued native DexVM transactions
+    ///
+    /// Returns the new total gas used and the number of transactions included.
+    fn execute_native_dexvm_transactions(
+        &mut self,
+        transactions: Vec<DexVmTransaction>,
+        deadline: Option<Instant>,
+        mut total_gas_used: u64,
+        outputs: &mut BlockOutputs,
+    ) -> Result<(u64, usize), BlockExecutionError> {
+        if transactions.is_empty() {
+            return Ok((total_gas_used, 0));
         }
 
-        let evm_executor = self
-            .evm_executor
-            .read()
-            .map_err(|e| BlockExecutionError::msg(format!("Lock error: {}", e)))?;
-        let dexvm_executor = self
-            .dexvm_executor
-            .read()
-            .map_err(|e| BlockExecutionError::msg(format!("Lock error: {}", e)))?;
+        let evm = self.evm_read()?;
+        let mut executor = self.dexvm_write()?;
+        let mut included = 0;
+        let mut traces = Vec::new();
+        for tx in transactions {
+            if total_gas_used > 0 && deadline_passed(deadline) {
+                break;
+            }
 
-        let evm_state_root = evm_executor.state_root();
-        let dexvm_state_root = dexvm_executor.state_root();
-        let combined_state_root = self.combine_state_roots(evm_state_root, dexvm_state_root);
+            let last_nonce = executor.pending_state().get_nonce(&tx.from);
+            if let Err(reason) = check_native_transaction(&tx, evm.chain_id(), last_nonce) {
+                outputs.skipped_transactions.push((tx.hash(), reason));
+                continue;
+            }
+            executor.pending_state_mut().set_nonce(tx.from, tx.nonce);
 
-        Ok(DualVmExecutionResult {
-            evm_receipts,
-            dexvm_receipts,
-            total_gas_used,
-            evm_state_root,
-            dexvm_state_root,
-            combined_state_root,
+            let started = Instant::now();
+            let result = executor.execute_transaction_with_evm(&tx, Some(&*evm))?;
+            let elapsed = started.elapsed();
+            self.record_tx_time(elapsed);
+            if self.is_slow(elapsed) {
+                log_slow_tx(tx.hash(), tx.from, result.gas_used, elapsed);
+            }
+            total_gas_used += result.gas_used;
+            if self.inspector.is_tracing() {
+                traces.push(TxTrace {
+                    hash: tx.hash(),
+                    kind: TracedTxKind::NativeDexVm,
+                    from: tx.from,
+                    to: None,
+                    success: result.success,
+                    gas_used: result.gas_used,
+                    counter: (result.old_counter != result.new_counter)
+                        .then_some((result.old_counter, result.new_counter)),
+                    accounts: vec![],
+                    access_set: None,
+                    execution_micros: elapsed.as_micros() as u64,
+                });
+            }
+            outputs.record_counter_change(
+                tx.from,
+                result.old_counter,
+                result.new_counter,
+                tx.hash(),
+            );
+            let receipt = DexVmReceipt::from_result(result, tx.from)
+                .with_transaction(tx.hash(), tx.operation);
+            outputs.dexvm_receipts.push(receipt);
+            included += 1;
+        }
+        drop(executor);
+        drop(evm);
+
+        traces.into_iter().for_each(|trace| self.inspector.record(trace));
+        Ok((total_gas_used, included))
+    }
+
+    fn record_tx_time(&self, elapsed: Duration) {
+        if let Some(histogram) = &self.tx_times {
+            histogram.record(elapsed);
+        }
+    }
+
+    fn is_slow(&self, elapsed: Duration) -> bool {
+        self.slow_tx_threshold.is_some_and(|threshold| elapsed > threshold)
+    }
+
+    /// Trace of a signed transaction that just ran, from its buffered outputs
+    fn trace_transaction(
+        &self,
+        tx: &TransactionSigned,
+        before: &AccountSnapshot,
+        outputs: &BlockOutputs,
+        gas_used: u64,
+        elapsed: Duration,
+    ) -> Result<TxTrace, BlockExecutionError> {
+        let after = self.evm_read()?.snapshot(tx);
+        let accounts = before
+            .accounts()
+            .zip(after.accounts())
+            .filter(|((_, before), (_, after))| before != after)
+            .map(|((address, before), (_, after))| {
+                let (balance_before, nonce_before) = before.unwrap_or_default();
+                let (balance_after, nonce_after) = after.unwrap_or_default();
+                AccountChange { address, balance_before, balance_after, nonce_before, nonce_after }
+            })
+            .collect();
+
+        let kind = if outputs.cross_vm_transactions > 0 {
+            TracedTxKind::CrossVm
+        } else if !outputs.dexvm_receipts.is_empty() {
+            TracedTxKind::DexVm
+        } else {
+            TracedTxKind::Evm
+        };
+
+        Ok(TxTrace {
+            hash: *tx.tx_hash(),
+            kind,
+            from: tx.recover_signer().unwrap_or_default(),
+            to: tx.to(),
+            success: outputs.block_receipts.first().is_some_and(|r| r.status.coerce_status()),
+            gas_used,
+            counter: outputs.counter_changes.first().map(|c| (c.old_value, c.new_value)),
+            accounts,
+            access_set: outputs.access_sets.first().map(|(_, access_set)| access_set.clone()),
+            execution_micros: elapsed.as_micros() as u64,
         })
     }
 
     /// Execute a cross-VM transaction (EVM → DexVM via precompile)
     ///
-    /// This handles atomic execution: if the DexVM operation fails,
-    /// both EVM and DexVM state changes are rolled back.
+    /// This handles atomic execution: DexVM changes made by the transaction
+    /// are reverted unless the EVM side succeeds.
     fn execute_cross_vm_transaction(
         &mut self,
         tx: &TransactionSigned,
@@ -166,39 +863,118 @@ impl DualVmExecutor {
             .write()
             .map_err(|e| BlockExecutionError::msg(format!("DexVM lock error: {}", e)))?;
 
-        // Get mutable reference to DexVM pending state
-        // The pending_state is used for atomic operations
+        // Journal DexVM pending state for this transaction
         let dexvm_state = dexvm_executor.pending_state_mut();
+        let checkpoint = dexvm_state.checkpoint();
 
         // Execute the EVM transaction with DexVM state access
-        let receipt = evm_executor.execute_transaction_with_dexvm(
+        let result = evm_executor.execute_transaction_with_dexvm(
             tx,
             self.current_block,
             self.current_timestamp,
-            Some(dexvm_state),
-        )?;
+            Some(&mut *dexvm_state),
+            &mut self.inspector,
+        );
 
-        // If the transaction succeeded, commit DexVM state
-        // If failed, DexVM state is already unchanged (operation was rejected)
-        if receipt.status.coerce_status() {
-            tracing::debug!("Cross-VM transaction succeeded, committing DexVM state");
-            // Note: We don't call commit() here because we modified pending_state directly
-            // The state will be committed when computing state roots
-        } else {
-            tracing::debug!("Cross-VM transaction failed, DexVM state unchanged");
+        match result {
+            Ok(receipt) if receipt.status.coerce_status() => {
+                tracing::debug!("Cross-VM transaction succeeded, keeping DexVM changes");
+                dexvm_state.commit_checkpoint(checkpoint);
+                Ok(receipt)
+            }
+            Ok(receipt) => {
+                tracing::debug!("Cross-VM transaction failed, reverting DexVM changes");
+                dexvm_state.revert_to_checkpoint(checkpoint);
+                Ok(receipt)
+            }
+            Err(e) => {
+                dexvm_state.revert_to_checkpoint(checkpoint);
+                Err(e)
+            }
+        }
+    }
+
+    /// Credit the fees collected in a block, returning the total credited
+    fn credit_fees(
+        &self,
+        credits: &BTreeMap<Address, U256>,
+        block_snapshot: &mut BlockSnapshot,
+    ) -> Result<U256, BlockExecutionError> {
+        let mut evm_executor = self.evm_write()?;
+        block_snapshot.record(&evm_executor.snapshot_accounts(credits.keys().copied()));
+        let mut total = U256::ZERO;
+        for (address, amount) in credits {
+            let balance = evm_executor.get_balance(address);
+            evm_executor.set_balance(*address, balance + amount);
+            total += amount;
+        }
+        Ok(total)
+    }
+
+    /// Addresses whose counter was first set by `changes` but that have no EVM account
+    ///
+    /// Creates their accounts if enabled, otherwise logs them.
+    fn counter_only_accounts(
+        &self,
+        changes: &[CounterChange],
+        block_snapshot: &mut BlockSnapshot,
+    ) -> Result<Vec<Address>, BlockExecutionError> {
+        let mut evm_executor = self.evm_write()?;
+        let addresses: BTreeSet<Address> = changes
+            .iter()
+            .filter(|change| change.old_value == 0)
+            .map(|change| change.address)
+            .filter(|address| !evm_executor.account_exists(address))
+            .collect();
+        block_snapshot.record(&evm_executor.snapshot_accounts(addresses.iter().copied()));
+
+        for address in &addresses {
+            if self.create_evm_accounts {
+                evm_executor.create_account(*address)?;
+                tracing::debug!("Created EVM account for DexVM counter owner {}", address);
+            } else {
+                tracing::warn!("DexVM counter set for {} which has no EVM account", address);
+            }
         }
+        Ok(addresses.into_iter().collect())
+    }
+
+    fn evm_read(&self) -> Result<RwLockReadGuard<'_, SimpleEvmExecutor>, BlockExecutionError> {
+        self.evm_executor
+            .read()
+            .map_err(|e| BlockExecutionError::msg(format!("EVM lock error: {}", e)))
+    }
+
+    fn evm_write(&self) -> Result<RwLockWriteGuard<'_, SimpleEvmExecutor>, BlockExecutionError> {
+        self.evm_executor
+            .write()
+            .map_err(|e| BlockExecutionError::msg(format!("EVM lock error: {}", e)))
+    }
 
-        Ok(receipt)
+    fn dexvm_read(&self) -> Result<RwLockReadGuard<'_, DexVmExecutor>, BlockExecutionError> {
+        self.dexvm_executor
+            .read()
+            .map_err(|e| BlockExecutionError::msg(format!("DexVM lock error: {}", e)))
     }
 
-    /// Combine two state roots
-    fn combine_state_roots(&self, evm_root: B256, dexvm_root: B256) -> B256 {
-        use alloy_primitives::keccak256;
+    fn dexvm_write(&self) -> Result<RwLockWriteGuard<'_, DexVmExecutor>, BlockExecutionError> {
+        self.dexvm_executor
+            .write()
+            .map_err(|e| BlockExecutionError::msg(format!("DexVM lock error: {}", e)))
+    }
 
-        let mut data = Vec::with_capacity(64);
-        data.extend_from_slice(evm_root.as_slice());
-        data.extend_from_slice(dexvm_root.as_slice());
-        keccak256(&data)
+    /// `address` as the next transaction executed would see it
+    ///
+    /// Reads the state transactions execute against: the EVM state store and
+    /// the pending DexVM state.
+    pub fn account_view(&self, address: Address) -> Result<AccountView, BlockExecutionError> {
+        let evm = self.evm_read()?;
+        let counter = self.dexvm_read()?.pending_state().get_counter(&address);
+        Ok(AccountView {
+            balance: evm.get_balance(&address),
+            nonce: evm.get_nonce(&address),
+            counter,
+        })
     }
 
     /// Get DexVM executor reference
@@ -210,42 +986,157 @@ impl DualVmExecutor {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy_consensus::transaction::SignerRecoverable;
     use alloy_consensus::TxLegacy;
-    use alloy_primitives::{Signature, TxKind, U256};
-    use dex_dexvm::{DexVmState, OP_INCREMENT, OP_QUERY};
+    use alloy_primitives::{Address, Signature, TxKind, U256};
+    use dex_dexvm::{
+        DexVmState, COUNTER_PRECOMPILE_ADDRESS, OP_BATCH, OP_DECREMENT, OP_INCREMENT, OP_QUERY,
+    };
     use dex_primitives::DEXVM_ROUTER_ADDRESS;
-    use dex_storage::{DualvmStorage, StateStore};
-    use tempfile::tempdir;
+    use crate::invariants::{check_block, StateSummary};
+    use dex_storage::{DualvmStorage, StateReader, StateStore, StateWriter};
+    use alloy_consensus::SignableTransaction;
+    use dex_primitives::DexVmOperation;
+    use secp256k1::{Message, SecretKey, SECP256K1};
 
-    fn create_test_state_store() -> (Arc<StateStore>, tempfile::TempDir) {
-        let dir = tempdir().unwrap();
-        let storage = DualvmStorage::new(dir.path()).unwrap();
-        (Arc::clone(&storage.state), dir)
+    fn create_test_state_store() -> Arc<StateStore> {
+        let storage = DualvmStorage::in_memory().unwrap();
+        Arc::clone(&storage.state)
+    }
+
+    /// Sign `tx` with `key`, for blocks with more than one sender
+    fn sign(tx: TxLegacy, key: &SecretKey) -> TransactionSigned {
+        let message = Message::from_digest(tx.signature_hash().0);
+        let (recovery_id, signature) =
+            SECP256K1.sign_ecdsa_recoverable(&message, key).serialize_compact();
+        let signature = Signature::new(
+            U256::from_be_slice(&signature[..32]),
+            U256::from_be_slice(&signature[32..]),
+            i32::from(recovery_id) != 0,
+        );
+        TransactionSigned::new_unhashed(tx.into(), signature)
+    }
+
+    /// Sign `tx` with the fixed test signature
+    fn test_signed(tx: TxLegacy) -> TransactionSigned {
+        TransactionSigned::new_unhashed(tx.into(), Signature::test_signature())
+    }
+
+    /// Router call incrementing the sender's counter by `amount`
+    fn router_increment(amount: u64) -> TransactionSigned {
+        test_signed(router_call(amount))
+    }
+
+    fn router_call(amount: u64) -> TxLegacy {
+        let mut calldata = vec![0u8];
+        calldata.extend_from_slice(&amount.to_be_bytes());
+        TxLegacy {
+            to: TxKind::Call(DEXVM_ROUTER_ADDRESS),
+            input: calldata.into(),
+            gas_price: 1,
+            gas_limit: 100000,
+            chain_id: Some(1),
+            ..Default::default()
+        }
+    }
+
+    /// Native DexVM transaction signed by `key` for chain 1
+    fn native(key: &SecretKey, operation: DexVmOperation, nonce: u64) -> DexVmTransaction {
+        let signature = dex_rpc::sign_operation(key, operation, nonce, 1);
+        DexVmTransaction {
+            from: crate::consensus::secret_key_to_address(key),
+            operation,
+            nonce,
+            signature: signature.to_vec(),
+        }
+    }
+
+    fn counter_call(nonce: u64, calldata: Vec<u8>) -> TxLegacy {
+        TxLegacy {
+            to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
+            input: calldata.into(),
+            nonce,
+            gas_price: 1,
+            gas_limit: 100_000,
+            chain_id: Some(1),
+            ..Default::default()
+        }
+    }
+
+    fn transfer(nonce: u64, to: Address, value: u64) -> TxLegacy {
+        TxLegacy {
+            to: TxKind::Call(to),
+            nonce,
+            gas_price: 1,
+            gas_limit: 21_000,
+            value: U256::from(value),
+            chain_id: Some(1),
+            ..Default::default()
+        }
     }
 
     #[test]
     fn test_combine_state_roots() {
-        let (state_store, _dir) = create_test_state_store();
-        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
-        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
-        let executor = DualVmExecutor::new(evm_executor, dexvm_executor);
-
         let evm_root = B256::from([1u8; 32]);
         let dexvm_root = B256::from([2u8; 32]);
 
-        let combined1 = executor.combine_state_roots(evm_root, dexvm_root);
-        let combined2 = executor.combine_state_roots(evm_root, dexvm_root);
+        let combined1 = combine_state_roots(evm_root, dexvm_root);
+        let combined2 = combine_state_roots(evm_root, dexvm_root);
 
         assert_eq!(combined1, combined2);
 
-        let different_root = executor.combine_state_roots(B256::from([3u8; 32]), dexvm_root);
+        let different_root = combine_state_roots(B256::from([3u8; 32]), dexvm_root);
         assert_ne!(combined1, different_root);
     }
 
     #[test]
     fn test_execute_dexvm_transaction() {
-        let (state_store, _dir) = create_test_state_store();
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
+
+        let tx = router_increment(10);
+
+        let result = executor.execute_transactions(vec![tx]).unwrap();
+
+        assert_eq!(result.dexvm_receipts.len(), 1);
+        assert!(result.total_gas_used > 0);
+        assert_ne!(result.dexvm_state_root, B256::ZERO);
+    }
+
+    #[test]
+    fn test_dexvm_transaction_reads_evm_balance() {
+        let state_store = create_test_state_store();
+        let account = Address::repeat_byte(0x77);
+        state_store.set_balance(account, U256::from(500)).unwrap();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
+
+        let mut calldata = vec![3u8];
+        calldata.extend_from_slice(account.as_slice());
+        let tx = test_signed(TxLegacy {
+            to: TxKind::Call(DEXVM_ROUTER_ADDRESS),
+            input: calldata.into(),
+            gas_limit: 100000,
+            chain_id: Some(1),
+            ..Default::default()
+        });
+
+        let result = executor.execute_transactions(vec![tx]).unwrap();
+        let receipt = &result.dexvm_receipts[0];
+        assert!(receipt.success);
+        assert_eq!(receipt.evm_value, Some(U256::from(500)));
+
+        // The read touches the EVM account only, never a counter
+        let (_, access_set) = &result.access_sets[0];
+        assert!(access_set.accounts_read.contains(&account));
+        assert!(access_set.slots_read.is_empty() && access_set.slots_written.is_empty());
+    }
+
+    #[test]
+    fn test_dexvm_transaction_rejects_value() {
+        let state_store = create_test_state_store();
         let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
         let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
         let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
@@ -253,25 +1144,334 @@ mod tests {
         let mut calldata = vec![0u8];
         calldata.extend_from_slice(&10u64.to_be_bytes());
 
-        let tx = TransactionSigned::new_unhashed(
+        let tx = test_signed(TxLegacy {
+            to: TxKind::Call(DEXVM_ROUTER_ADDRESS),
+            input: calldata.into(),
+            nonce: 0,
+            gas_price: 1,
+            gas_limit: 100000,
+            value: U256::from(500),
+            chain_id: Some(1),
+        });
+
+        let result = executor.execute_transactions(vec![tx]).unwrap();
+
+        // Included as a failed transaction that leaves the counter alone
+        assert_eq!(result.dexvm_receipts.len(), 1);
+        assert!(!result.dexvm_receipts[0].success);
+        assert_eq!(result.block_receipts[0].status, false.into());
+        assert_eq!(result.dexvm_state_root, B256::ZERO);
+    }
+
+    #[test]
+    fn test_block_receipts_are_cumulative() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
+
+        let txs = vec![router_increment(1), router_increment(2)];
+        let result = executor.execute_transactions(txs).unwrap();
+
+        assert_eq!(result.block_receipts.len(), 2);
+        let first = result.block_receipts[0].cumulative_gas_used;
+        let second = result.block_receipts[1].cumulative_gas_used;
+        assert!(second > first);
+        assert_eq!(second, result.total_gas_used);
+    }
+
+    #[test]
+    fn test_native_dexvm_transactions_run_after_signed() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor.clone());
+
+        let key = SecretKey::from_slice(&[0x33; 32]).unwrap();
+        let signed = sign(router_call(5), &key);
+        let sender = signed.recover_signer().unwrap();
+
+        // Decrement only succeeds if it runs after the signed increment
+        let native = native(&key, DexVmOperation::Decrement(3), 1);
+
+        let result = executor.execute_block(vec![signed], vec![native], None, None).unwrap();
+
+        assert_eq!(result.block_receipts.len(), 1);
+        assert_eq!(result.dexvm_receipts.len(), 2);
+        assert!(result.dexvm_receipts[1].success);
+        assert!(result.total_gas_used > result.block_receipts[0].cumulative_gas_used);
+        assert_eq!(dexvm_executor.read().unwrap().state().get_counter(&sender), 2);
+
+        let stats = result.block_stats();
+        assert_eq!(stats.dexvm_tx_count, 1);
+        assert_eq!(stats.native_dexvm_tx_count, 1);
+        assert_eq!(stats.evm_tx_count, 0);
+        assert_eq!(stats.failed_tx_count, 0);
+        assert_eq!(stats.dexvm_ops_count, 2);
+        assert_eq!(stats.gas_used, result.total_gas_used);
+
+        // Only signed transactions get access sets; the router call writes the sender's counter
+        assert_eq!(result.access_sets.len(), 1);
+        let slot = (COUNTER_PRECOMPILE_ADDRESS, dex_primitives::counter_slot(sender));
+        assert!(result.access_sets[0].1.slots_written.contains(&slot));
+
+        // Increment by 5, then decrement by 3
+        let changes: Vec<_> =
+            result.counter_changes.iter().map(|c| (c.old_value, c.new_value)).collect();
+        assert_eq!(changes, vec![(0, 5), (5, 2)]);
+        assert!(result.counter_changes.iter().all(|c| c.address == sender));
+    }
+
+    #[test]
+    fn test_native_transactions_need_signature_and_fresh_nonce() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor.clone());
+        let key = SecretKey::from_slice(&[0x44; 32]).unwrap();
+
+        let valid = native(&key, DexVmOperation::Increment(5), 1);
+        let unsigned =
+            DexVmTransaction { signature: vec![], ..native(&key, DexVmOperation::Increment(1), 2) };
+        let mut forged = native(&key, DexVmOperation::Increment(7), 3);
+        forged.from = Address::repeat_byte(0x55);
+        let transactions = vec![valid.clone(), unsigned, forged, valid.clone()];
+        let result = executor.execute_block(vec![], transactions, None, None).unwrap();
+
+        assert_eq!(result.included_dexvm_transactions, 1);
+        assert_eq!(result.dexvm_receipts.len(), 1);
+        let reasons: Vec<_> =
+            result.skipped_transactions.iter().map(|(_, reason)| reason.clone()).collect();
+        assert!(matches!(reasons[0], SkipReason::InvalidSignature(_)));
+        assert!(matches!(reasons[1], SkipReason::InvalidSignature(_)));
+        assert_eq!(reasons[2], SkipReason::StaleNonce { nonce: 1, last: 1 });
+
+        let dexvm = dexvm_executor.read().unwrap();
+        assert_eq!(dexvm.state().get_counter(&valid.from), 5);
+        assert_eq!(dexvm.state().get_nonce(&valid.from), 1);
+        assert_eq!(dexvm.state().get_nonce(&Address::repeat_byte(0x55)), 0);
+    }
+
+    #[test]
+    fn test_imported_blocks_run_every_transaction_and_can_be_undone() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor.clone());
+        let key = SecretKey::from_slice(&[0x66; 32]).unwrap();
+        let signed = sign(router_call(5), &key);
+        let sender = signed.recover_signer().unwrap();
+
+        // A transaction the proposer included but that does not run here fails the block
+        let stale = native(&key, DexVmOperation::Increment(1), 0);
+        assert!(executor.execute_imported_block(vec![signed.clone()], vec![stale]).is_err());
+        let view = executor.account_view(sender).unwrap();
+        assert_eq!((view.nonce, view.counter), (0, 0));
+
+        // A block that ran is undone as a whole
+        let natives = vec![native(&key, DexVmOperation::Increment(2), 1)];
+        let (_, undo) =
+            executor.execute_imported_block(vec![signed.clone()], natives.clone()).unwrap();
+        let view = executor.account_view(sender).unwrap();
+        assert_eq!((view.nonce, view.counter), (1, 7));
+        assert_eq!(dexvm_executor.read().unwrap().state().get_counter(&sender), 0);
+        executor.undo_block(undo).unwrap();
+        let view = executor.account_view(sender).unwrap();
+        assert_eq!((view.nonce, view.counter), (0, 0));
+        assert_eq!(dexvm_executor.read().unwrap().pending_state().get_nonce(&sender), 0);
+
+        // or kept, which commits its DexVM changes
+        let (_, undo) = executor.execute_imported_block(vec![signed], natives).unwrap();
+        executor.keep_block(undo).unwrap();
+        let dexvm = dexvm_executor.read().unwrap();
+        assert_eq!(dexvm.state().get_counter(&sender), 7);
+        assert_eq!(dexvm.state().get_nonce(&sender), 1);
+    }
+
+    #[test]
+    fn test_tracing_records_included_transactions() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
+
+        let key = SecretKey::from_slice(&[0x33; 32]).unwrap();
+        let signed = sign(router_call(5), &key);
+        let sender = signed.recover_signer().unwrap();
+        let native = native(&key, DexVmOperation::Decrement(3), 1);
+
+        // Nothing is recorded until tracing is enabled
+        let result =
+            executor.execute_block(vec![signed.clone()], vec![native.clone()], None, None).unwrap();
+        assert!(result.traces.is_empty());
+
+        executor.set_tracing(true);
+        assert!(executor.is_tracing());
+        let result = executor.execute_block(vec![signed], vec![native], None, None).unwrap();
+
+        assert_eq!(result.traces.len(), 2);
+        let router = &result.traces[0];
+        assert_eq!(router.kind, TracedTxKind::DexVm);
+        assert_eq!(router.from, sender);
+        assert_eq!(router.to, Some(DEXVM_ROUTER_ADDRESS));
+        assert!(router.success);
+        assert_eq!(router.counter, Some((2, 7)));
+        assert!(router.access_set.is_some());
+
+        let native = &result.traces[1];
+        assert_eq!(native.kind, TracedTxKind::NativeDexVm);
+        assert_eq!(native.counter, Some((7, 4)));
+        assert!(native.access_set.is_none());
+
+        let gas: u64 = result.traces.iter().map(|trace| trace.gas_used).sum();
+        assert_eq!(gas, result.total_gas_used);
+    }
+
+    #[test]
+    fn test_timed_out_transaction_is_skipped_and_undone() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store.clone())));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(Arc::clone(&evm_executor), dexvm_executor);
+
+        let recipient = Address::repeat_byte(0x77);
+        let tx = test_signed(TxLegacy {
+            to: TxKind::Call(recipient),
+            value: U256::from(100),
+            nonce: 0,
+            gas_price: 1,
+            gas_limit: 21000,
+            chain_id: Some(1),
+            ..Default::default()
+        });
+        let sender = tx.recover_signer().unwrap();
+        evm_executor.write().unwrap().set_balance(sender, U256::from(1_000_000u64));
+
+        // Skipped transactions still count toward execution times
+        let times = Arc::new(TxTimeHistogram::default());
+        executor.set_tx_times(Arc::clone(&times));
+        executor.set_slow_tx_threshold(Some(Duration::ZERO));
+
+        // Every transaction overruns a zero timeout
+        let timeout = Some(Duration::ZERO);
+        let result = executor.execute_block(vec![tx.clone()], vec![], None, timeout).unwrap();
+        assert_eq!(result.included_transactions, 0);
+        assert!(result.block_receipts.is_empty());
+        assert_eq!(result.skipped_transactions.len(), 1);
+        assert_eq!(result.skipped_transactions[0].0, *tx.tx_hash());
+        assert!(matches!(result.skipped_transactions[0].1, SkipReason::TimedOut(_)));
+        assert_eq!(state_store.get_balance(&sender), U256::from(1_000_000u64));
+        assert_eq!(state_store.get_nonce(&sender), 0);
+        assert!(state_store.get_account(&recipient).is_none());
+
+        // Imported blocks run without a timeout
+        let result = executor.execute_block(vec![tx], vec![], None, None).unwrap();
+        assert_eq!(result.included_transactions, 1);
+        assert_eq!(state_store.get_balance(&recipient), U256::from(100));
+        assert_eq!(times.summary().count, 2);
+    }
+
+    #[test]
+    fn test_fee_policy_credits_fees() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store.clone())));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(Arc::clone(&evm_executor), dexvm_executor);
+
+        let validator = Address::repeat_byte(0x55);
+        let gas_price = dex_rpc::BASE_FEE_PER_GAS as u128 + 10;
+        let tx = test_signed(TxLegacy {
+            to: TxKind::Call(Address::repeat_byte(0x77)),
+            nonce: 0,
+            gas_price,
+            gas_limit: 21000,
+            chain_id: Some(1),
+            ..Default::default()
+        });
+        let sender = tx.recover_signer().unwrap();
+        evm_executor.write().unwrap().set_balance(sender, U256::from(10u128.pow(18)));
+
+        executor.set_fee_policy(FeePolicy::burn_and_tip());
+        executor.set_beneficiary(Some(validator));
+        let before = StateSummary::capture(state_store.as_ref());
+        let result = executor.execute_transactions(vec![tx]).unwrap();
+
+        let base_fee = U256::from(21_000u64 * dex_rpc::BASE_FEE_PER_GAS);
+        assert_eq!(result.fees_paid, U256::from(21_000 * gas_price));
+        assert_eq!(result.fees_credited, result.fees_paid);
+        assert_eq!(state_store.get_balance(&Address::ZERO), base_fee);
+        assert_eq!(state_store.get_balance(&validator), U256::from(21_000 * 10));
+
+        // Credited fees stay in the total balance
+        let after = StateSummary::capture(state_store.as_ref());
+        assert_eq!(check_block(&before, &after, &result, |_| 0), vec![]);
+    }
+
+    #[test]
+    fn test_expired_deadline_includes_one_transaction() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store)));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
+
+        let key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let native = native(&key, DexVmOperation::Increment(1), 1);
+
+        // Deadline already passed: only the first transaction is started
+        let txs = vec![router_increment(1), router_increment(2)];
+        let result = executor
+            .execute_block(txs, vec![native], Some(Instant::now()), None)
+            .unwrap();
+
+        assert_eq!(result.included_transactions, 1);
+        assert_eq!(result.included_dexvm_transactions, 0);
+        assert_eq!(result.block_receipts.len(), 1);
+    }
+
+    #[test]
+    fn test_failed_block_reverts_evm_and_dexvm_changes() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store.clone())));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor.clone(), dexvm_executor.clone());
+
+        let dexvm_tx = router_increment(10);
+        let sender = dexvm_tx.recover_signer().unwrap();
+        let recipient = Address::repeat_byte(0x24);
+        let transfer = test_signed(TxLegacy {
+            to: TxKind::Call(recipient),
+            nonce: 0,
+            gas_price: 1,
+            gas_limit: 21000,
+            value: U256::from(100),
+            chain_id: Some(1),
+            ..Default::default()
+        });
+        evm_executor.write().unwrap().set_balance(sender, U256::from(1_000_000u64));
+
+        // EVM transfer with an unrecoverable signature errors the whole block
+        let bad_tx = TransactionSigned::new_unhashed(
             TxLegacy {
-                to: TxKind::Call(DEXVM_ROUTER_ADDRESS),
-                input: calldata.into(),
+                to: TxKind::Call(Address::repeat_byte(0x42)),
                 nonce: 0,
                 gas_price: 1,
-                gas_limit: 100000,
-                value: U256::ZERO,
+                gas_limit: 21000,
+                value: U256::from(1),
                 chain_id: Some(1),
+                ..Default::default()
             }
             .into(),
-            Signature::test_signature(),
+            Signature::new(U256::ZERO, U256::ZERO, false),
         );
 
-        let result = executor.execute_transactions(vec![tx]).unwrap();
+        assert!(executor.execute_transactions(vec![dexvm_tx, transfer, bad_tx]).is_err());
 
-        assert_eq!(result.dexvm_receipts.len(), 1);
-        assert!(result.total_gas_used > 0);
-        assert_ne!(result.dexvm_state_root, B256::ZERO);
+        assert_eq!(state_store.get_balance(&sender), U256::from(1_000_000u64));
+        assert_eq!(state_store.get_nonce(&sender), 0);
+        assert!(state_store.get_account(&recipient).is_none());
+        let dexvm = dexvm_executor.read().unwrap();
+        assert_eq!(dexvm.pending_state().get_counter(&sender), 0);
+        assert_eq!(dexvm.state().get_counter(&sender), 0);
     }
 
     #[test]
@@ -281,41 +1481,45 @@ mod tests {
         calldata.extend_from_slice(&25u64.to_be_bytes());
 
         // Create the transaction first, then get the caller address from it
-        let tx = TransactionSigned::new_unhashed(
-            TxLegacy {
-                to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
-                input: calldata.into(),
-                nonce: 0,
-                gas_price: 1,
-                gas_limit: 100000,
-                value: U256::ZERO,
-                chain_id: Some(1),
-            }
-            .into(),
-            Signature::test_signature(),
-        );
+        let tx = test_signed(TxLegacy {
+            to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
+            input: calldata.into(),
+            nonce: 0,
+            gas_price: 1,
+            gas_limit: 100000,
+            value: U256::ZERO,
+            chain_id: Some(1),
+        });
 
         // Get the actual caller address from the transaction we'll execute
         let caller = tx.recover_signer().unwrap();
 
         // Setup EVM executor with funded account
-        let (state_store, _dir) = create_test_state_store();
-        let mut evm_exec = SimpleEvmExecutor::new(1, state_store);
+        let state_store = create_test_state_store();
+        let mut evm_exec = SimpleEvmExecutor::new(1, state_store.clone());
         evm_exec.set_balance(caller, U256::from(1_000_000_000u64));
 
         let evm_executor = Arc::new(RwLock::new(evm_exec));
         let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
         let mut executor = DualVmExecutor::new(evm_executor.clone(), dexvm_executor.clone());
 
+        let before = StateSummary::capture(state_store.as_ref());
         let result = executor.execute_transactions(vec![tx]).unwrap();
 
         // Should have one EVM receipt (the precompile call)
         assert_eq!(result.evm_receipts.len(), 1);
         assert!(result.evm_receipts[0].status.coerce_status());
+        assert_eq!(result.fees_paid, U256::from(26_000));
 
         // DexVM state should be updated
         let dexvm = dexvm_executor.read().unwrap();
         assert_eq!(dexvm.state().get_counter(&caller), 25);
+
+        // The block breaks no invariants
+        let after = StateSummary::capture(state_store.as_ref());
+        let violations =
+            check_block(&before, &after, &result, |address| dexvm.state().get_counter(address));
+        assert_eq!(violations, vec![]);
     }
 
     #[test]
@@ -325,24 +1529,20 @@ mod tests {
         calldata.extend_from_slice(&0u64.to_be_bytes());
 
         // Create the transaction first to get the correct caller
-        let tx = TransactionSigned::new_unhashed(
-            TxLegacy {
-                to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
-                input: calldata.into(),
-                nonce: 0,
-                gas_price: 1,
-                gas_limit: 100000,
-                value: U256::ZERO,
-                chain_id: Some(1),
-            }
-            .into(),
-            Signature::test_signature(),
-        );
+        let tx = test_signed(TxLegacy {
+            to: TxKind::Call(COUNTER_PRECOMPILE_ADDRESS),
+            input: calldata.into(),
+            nonce: 0,
+            gas_price: 1,
+            gas_limit: 100000,
+            value: U256::ZERO,
+            chain_id: Some(1),
+        });
 
         let caller = tx.recover_signer().unwrap();
 
         // Setup EVM executor with funded account
-        let (state_store, _dir) = create_test_state_store();
+        let state_store = create_test_state_store();
         let mut evm_exec = SimpleEvmExecutor::new(1, state_store);
         evm_exec.set_balance(caller, U256::from(1_000_000_000u64));
         let evm_executor = Arc::new(RwLock::new(evm_exec));
@@ -363,4 +1563,157 @@ mod tests {
         let dexvm = dexvm_executor.read().unwrap();
         assert_eq!(dexvm.state().get_counter(&caller), 100);
     }
+
+    #[test]
+    fn test_block_sees_earlier_transactions_in_both_vms() {
+        let funder_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let account_key = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let funder = crate::consensus::secret_key_to_address(&funder_key);
+        let account = crate::consensus::secret_key_to_address(&account_key);
+
+        let state_store = create_test_state_store();
+        state_store.set_balance(funder, U256::from(10_000_000)).unwrap();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store.clone())));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor.clone());
+
+        // The account holds nothing until the transfer before its cross-VM call
+        let mut increment = vec![OP_INCREMENT];
+        increment.extend_from_slice(&25u64.to_be_bytes());
+        let mut balance_read = vec![3u8];
+        balance_read.extend_from_slice(account.as_slice());
+        let transactions = vec![
+            sign(transfer(0, account, 1_000_000), &funder_key),
+            sign(counter_call(0, increment), &account_key),
+            sign(
+                TxLegacy {
+                    to: TxKind::Call(DEXVM_ROUTER_ADDRESS),
+                    input: balance_read.into(),
+                    nonce: 1,
+                    gas_limit: 100_000,
+                    chain_id: Some(1),
+                    ..Default::default()
+                },
+                &account_key,
+            ),
+        ];
+        // Only succeeds if it sees the counter the cross-VM call set
+        let native = native(&account_key, DexVmOperation::Decrement(10), 1);
+
+        let before = StateSummary::capture(state_store.as_ref());
+        let result = executor.execute_block(transactions, vec![native], None, None).unwrap();
+
+        assert!(result.block_receipts.iter().all(|receipt| receipt.status.coerce_status()));
+        assert_eq!(result.cross_vm_transactions, 1);
+        // The router call saw the balance left after the cross-VM call's gas
+        assert_eq!(result.dexvm_receipts[0].evm_value, Some(U256::from(900_000)));
+        assert!(result.dexvm_receipts[1].success);
+        let changes: Vec<_> =
+            result.counter_changes.iter().map(|c| (c.old_value, c.new_value)).collect();
+        assert_eq!(changes, vec![(0, 25), (25, 15)]);
+
+        let view = executor.account_view(account).unwrap();
+        assert_eq!(view, AccountView { balance: U256::from(900_000), nonce: 1, counter: 15 });
+        assert_eq!(executor.account_view(funder).unwrap().nonce, 1);
+
+        let after = StateSummary::capture(state_store.as_ref());
+        let dexvm = dexvm_executor.read().unwrap();
+        let violations =
+            check_block(&before, &after, &result, |address| dexvm.state().get_counter(address));
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    fn test_failed_cross_vm_transaction_leaves_both_vms_intact() {
+        let funder_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let account_key = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let funder = crate::consensus::secret_key_to_address(&funder_key);
+        let account = crate::consensus::secret_key_to_address(&account_key);
+
+        let state_store = create_test_state_store();
+        state_store.set_balance(funder, U256::from(10_000_000)).unwrap();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store.clone())));
+        let mut dexvm_state = DexVmState::new();
+        dexvm_state.set_counter(account, 3);
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(dexvm_state)));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor.clone());
+
+        // Increment by 5, then underflow: the whole batch fails
+        let mut batch = vec![OP_BATCH, OP_INCREMENT];
+        batch.extend_from_slice(&5u64.to_be_bytes());
+        batch.push(OP_DECREMENT);
+        batch.extend_from_slice(&10u64.to_be_bytes());
+        let transactions = vec![
+            sign(transfer(0, account, 1_000_000), &funder_key),
+            sign(counter_call(0, batch), &account_key),
+        ];
+        // Would succeed had the batch's increment been kept
+        let native = native(&account_key, DexVmOperation::Decrement(5), 1);
+
+        let before = StateSummary::capture(state_store.as_ref());
+        let result = executor.execute_block(transactions, vec![native], None, None).unwrap();
+
+        assert!(result.block_receipts[0].status.coerce_status());
+        assert!(!result.block_receipts[1].status.coerce_status());
+        // Cross-VM calls get no DexVM receipt; the native decrement saw the counter at 3
+        assert_eq!(result.dexvm_receipts.len(), 1);
+        assert!(!result.dexvm_receipts[0].success);
+        assert!(result.counter_changes.is_empty());
+        // Only the transfer paid a fee
+        assert_eq!(result.fees_paid, U256::from(21_000));
+
+        // The failed call kept its nonce increment and nothing else
+        let view = executor.account_view(account).unwrap();
+        assert_eq!(view, AccountView { balance: U256::from(1_000_000), nonce: 1, counter: 3 });
+
+        let after = StateSummary::capture(state_store.as_ref());
+        {
+            let dexvm = dexvm_executor.read().unwrap();
+            assert_eq!(dexvm.state().get_counter(&account), 3);
+            let violations = check_block(&before, &after, &result, |address| {
+                dexvm.state().get_counter(address)
+            });
+            assert_eq!(violations, vec![]);
+        }
+
+        // The next block builds on the intact state
+        let mut increment = vec![OP_INCREMENT];
+        increment.extend_from_slice(&4u64.to_be_bytes());
+        executor.advance_block();
+        let result =
+            executor.execute_transactions(vec![sign(counter_call(1, increment), &account_key)]);
+        assert!(result.unwrap().block_receipts[0].status.coerce_status());
+        let view = executor.account_view(account).unwrap();
+        assert_eq!(view, AccountView { balance: U256::from(900_000), nonce: 2, counter: 7 });
+    }
+
+    #[test]
+    fn test_counter_only_accounts() {
+        let state_store = create_test_state_store();
+        let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(1, state_store.clone())));
+        let dexvm_executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::new())));
+        let mut executor = DualVmExecutor::new(evm_executor, dexvm_executor);
+        let (first_key, second_key) =
+            (SecretKey::from_slice(&[1; 32]).unwrap(), SecretKey::from_slice(&[2; 32]).unwrap());
+        let increment = |key, nonce| native(key, DexVmOperation::Increment(1), nonce);
+        let first = crate::consensus::secret_key_to_address(&first_key);
+        let second = crate::consensus::secret_key_to_address(&second_key);
+
+        let result =
+            executor.execute_block(vec![], vec![increment(&first_key, 1)], None, None).unwrap();
+        assert_eq!(result.counter_only_accounts, vec![first]);
+        assert!(state_store.get_account(&first).is_none());
+        // Only the first counter change is reported
+        let result =
+            executor.execute_block(vec![], vec![increment(&first_key, 2)], None, None).unwrap();
+        assert!(result.counter_only_accounts.is_empty());
+
+        executor.set_create_evm_accounts(true);
+        let root_before = state_store.state_root();
+        let result =
+            executor.execute_block(vec![], vec![increment(&second_key, 1)], None, None).unwrap();
+        assert_eq!(result.counter_only_accounts, vec![second]);
+        assert_eq!(state_store.get_account(&second).unwrap().balance, U256::ZERO);
+        assert_ne!(result.evm_state_root, root_before);
+    }
 }
@@ -0,0 +1,121 @@
+//! Transaction fee distribution
+//!
+//! Senders of executed EVM transactions are charged `gas_used * gas_price`.
+//! The fee policy, set in the chain spec, decides where that goes: the part
+//! up to the base fee is burned, the tip above it goes to the block's
+//! validator, and an optional treasury takes a share of the tip. Whatever no
+//! account is credited with is destroyed; the default policy destroys it all.
+
+use alloy_primitives::{Address, U256};
+use dex_rpc::BASE_FEE_PER_GAS;
+
+/// Where the gas fees charged to senders go
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeePolicy {
+    /// Account credited with the base-fee part of each fee; `None` destroys it
+    pub burn_address: Option<Address>,
+    /// Credit tips to the block's validator
+    pub tip_validator: bool,
+    /// Account credited with `treasury_percent` of each tip
+    pub treasury: Option<Address>,
+    /// Share of each tip sent to the treasury, from 0 to 100
+    pub treasury_percent: u8,
+}
+
+impl FeePolicy {
+    /// Burn base fees to the zero address and pay tips to the validator
+    pub fn burn_and_tip() -> Self {
+        Self { burn_address: Some(Address::ZERO), tip_validator: true, ..Default::default() }
+    }
+
+    /// Refuse a treasury share above 100%
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.treasury_percent > 100 {
+            return Err(eyre::eyre!(
+                "treasury share is {}% but cannot exceed 100%",
+                self.treasury_percent
+            ));
+        }
+        Ok(())
+    }
+
+    /// Accounts credited for a fee of `gas_used * gas_price`, with their amounts
+    ///
+    /// Tips go to `validator` only if the block has one. Zero credits are left out.
+    pub fn credits(
+        &self,
+        gas_used: u64,
+        gas_price: u128,
+        validator: Option<Address>,
+    ) -> Vec<(Address, U256)> {
+        let gas = U256::from(gas_used);
+        let base_fee = gas * U256::from(gas_price.min(BASE_FEE_PER_GAS as u128));
+        let tip = gas * U256::from(gas_price) - base_fee;
+
+        let treasury_share = match self.treasury {
+            Some(_) => tip * U256::from(self.treasury_percent.min(100)) / U256::from(100),
+            None => U256::ZERO,
+        };
+
+        let credits = [
+            (self.burn_address, base_fee),
+            (self.treasury, treasury_share),
+            (validator.filter(|_| self.tip_validator), tip - treasury_share),
+        ];
+        credits
+            .into_iter()
+            .filter_map(|(address, amount)| Some((address?, amount)))
+            .filter(|(_, amount)| !amount.is_zero())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    const VALIDATOR: Address = address!("1111111111111111111111111111111111111111");
+    const TREASURY: Address = address!("2222222222222222222222222222222222222222");
+
+    #[test]
+    fn test_default_policy_destroys_fees() {
+        let policy = FeePolicy::default();
+        assert_eq!(policy.credits(21_000, 2 * BASE_FEE_PER_GAS as u128, Some(VALIDATOR)), vec![]);
+    }
+
+    #[test]
+    fn test_fee_split() {
+        let policy = FeePolicy {
+            treasury: Some(TREASURY),
+            treasury_percent: 25,
+            ..FeePolicy::burn_and_tip()
+        };
+        let base_fee = U256::from(21_000u64 * BASE_FEE_PER_GAS);
+        let tip = U256::from(21_000u64 * 400);
+
+        let credits = policy.credits(21_000, BASE_FEE_PER_GAS as u128 + 400, Some(VALIDATOR));
+        assert_eq!(
+            credits,
+            vec![
+                (Address::ZERO, base_fee),
+                (TREASURY, tip / U256::from(4)),
+                (VALIDATOR, tip * U256::from(3) / U256::from(4)),
+            ]
+        );
+
+        // Without a validator its share of the tip is destroyed
+        let credits = policy.credits(21_000, BASE_FEE_PER_GAS as u128 + 400, None);
+        assert_eq!(credits, vec![(Address::ZERO, base_fee), (TREASURY, tip / U256::from(4))]);
+
+        // A price below the base fee is burned entirely
+        let credits = policy.credits(21_000, 7, Some(VALIDATOR));
+        assert_eq!(credits, vec![(Address::ZERO, U256::from(21_000u64 * 7))]);
+    }
+
+    #[test]
+    fn test_validate_treasury_percent() {
+        assert!(FeePolicy { treasury_percent: 100, ..Default::default() }.validate().is_ok());
+        assert!(FeePolicy { treasury_percent: 101, ..Default::default() }.validate().is_err());
+    }
+}
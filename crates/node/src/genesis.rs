@@ -0,0 +1,432 @@
+//! Genesis files in the geth format
+//!
+//! The genesis block's header is derived from the genesis file the way geth
+//! derives it: nonce, timestamp, extra data, gas limit, difficulty, mix hash
+//! and coinbase come from the file, the state root is the Ethereum trie root
+//! of the allocations, and fork-dependent fields (base fee, withdrawals root,
+//! blob gas, requests hash) follow the forks active at genesis. The genesis
+//! hash is the hash of that header, so it matches what other tooling computes
+//! for the same file.
+
+use crate::header::header_hash;
+use alloy_consensus::{Header as ConsensusHeader, EMPTY_OMMER_ROOT_HASH};
+use alloy_primitives::{b256, keccak256, Address, Bloom, Bytes, B256, B64, U256};
+use alloy_trie::{
+    root::{state_root_unhashed, storage_root_unhashed},
+    TrieAccount, EMPTY_ROOT_HASH, KECCAK_EMPTY,
+};
+use dex_storage::{AccountState, StateWriter, StoredBlock};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+
+/// Gas limit of a genesis file without `gasLimit`, as in geth
+pub const GENESIS_GAS_LIMIT: u64 = 4_712_388;
+
+/// Difficulty of a genesis file without `difficulty` and `mixHash`, as in geth
+pub const GENESIS_DIFFICULTY: u64 = 131_072;
+
+/// Base fee of a genesis block with London active and no `baseFeePerGas`
+pub const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+
+/// Requests hash of a block without execution layer requests (EIP-7685)
+const EMPTY_REQUESTS_HASH: B256 =
+    b256!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+
+/// Gas limit of genesis blocks built from bare allocations
+const DEV_GAS_LIMIT: u64 = 30_000_000;
+
+/// Contents of a geth genesis file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    /// Chain id and fork activations
+    pub config: ChainConfig,
+    /// Header nonce
+    #[serde(default, deserialize_with = "quantity_u64")]
+    pub nonce: u64,
+    /// Header timestamp in seconds
+    #[serde(default, deserialize_with = "quantity_u64")]
+    pub timestamp: u64,
+    /// Header extra data
+    #[serde(default)]
+    pub extra_data: Bytes,
+    /// Header gas limit; 0 takes [`GENESIS_GAS_LIMIT`]
+    #[serde(default, deserialize_with = "quantity_u64")]
+    pub gas_limit: u64,
+    /// Header difficulty
+    #[serde(default, deserialize_with = "optional_quantity_u256")]
+    pub difficulty: Option<U256>,
+    /// Header mix hash
+    #[serde(default)]
+    pub mix_hash: B256,
+    /// Header beneficiary
+    #[serde(default)]
+    pub coinbase: Address,
+    /// Header base fee, if London is active at genesis
+    #[serde(default, deserialize_with = "optional_quantity_u64")]
+    pub base_fee_per_gas: Option<u64>,
+    /// Header number; the chain must start at 0
+    #[serde(default, deserialize_with = "quantity_u64")]
+    pub number: u64,
+    /// Header gas used
+    #[serde(default, deserialize_with = "quantity_u64")]
+    pub gas_used: u64,
+    /// Header parent hash
+    #[serde(default)]
+    pub parent_hash: B256,
+    /// Header excess blob gas, if Cancun is active at genesis
+    #[serde(default, deserialize_with = "optional_quantity_u64")]
+    pub excess_blob_gas: Option<u64>,
+    /// Header blob gas used, if Cancun is active at genesis
+    #[serde(default, deserialize_with = "optional_quantity_u64")]
+    pub blob_gas_used: Option<u64>,
+    /// Initial accounts
+    #[serde(default)]
+    pub alloc: BTreeMap<Address, GenesisAccount>,
+}
+
+/// Chain id and the forks that shape the genesis header
+///
+/// Other fields of the geth chain config are accepted and ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainConfig {
+    /// Chain id
+    pub chain_id: u64,
+    /// Block London activates at
+    pub london_block: Option<u64>,
+    /// Timestamp Shanghai activates at
+    pub shanghai_time: Option<u64>,
+    /// Timestamp Cancun activates at
+    pub cancun_time: Option<u64>,
+    /// Timestamp Prague activates at
+    pub prague_time: Option<u64>,
+}
+
+impl ChainConfig {
+    fn is_london(&self, number: u64) -> bool {
+        self.london_block.is_some_and(|block| block <= number)
+    }
+
+    fn is_active(fork_time: Option<u64>, timestamp: u64) -> bool {
+        fork_time.is_some_and(|time| time <= timestamp)
+    }
+}
+
+/// Account allocated at genesis
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct GenesisAccount {
+    /// Balance in wei, as a hex or decimal string
+    #[serde(deserialize_with = "quantity_u256")]
+    pub balance: U256,
+    /// Account nonce
+    #[serde(default, deserialize_with = "quantity_u64")]
+    pub nonce: u64,
+    /// Contract code
+    #[serde(default)]
+    pub code: Option<Bytes>,
+    /// Contract storage
+    #[serde(default)]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+impl GenesisAccount {
+    /// Storage slots with a non-zero value
+    fn storage_slots(&self) -> impl Iterator<Item = (B256, U256)> + '_ {
+        self.storage
+            .iter()
+            .map(|(slot, value)| (*slot, U256::from_be_bytes(value.0)))
+            .filter(|(_, value)| !value.is_zero())
+    }
+
+    fn trie_account(&self) -> TrieAccount {
+        TrieAccount {
+            nonce: self.nonce,
+            balance: self.balance,
+            storage_root: storage_root_unhashed(self.storage_slots()),
+            code_hash: self.code.as_ref().map(keccak256).unwrap_or(KECCAK_EMPTY),
+        }
+    }
+}
+
+impl Genesis {
+    /// Parse and check a genesis file
+    pub fn from_json(data: &str) -> eyre::Result<Self> {
+        let genesis: Self = serde_json::from_str(data)?;
+        genesis.validate()?;
+        Ok(genesis)
+    }
+
+    /// Genesis of `chain_id` holding only `balances`, for development chains
+    pub fn with_balances(chain_id: u64, balances: HashMap<Address, U256>) -> Self {
+        let alloc = balances
+            .into_iter()
+            .map(|(address, balance)| (address, GenesisAccount { balance, ..Default::default() }))
+            .collect();
+        Self {
+            config: ChainConfig { chain_id, ..Default::default() },
+            nonce: 0,
+            timestamp: 0,
+            extra_data: Bytes::new(),
+            gas_limit: DEV_GAS_LIMIT,
+            difficulty: Some(U256::from(1)),
+            mix_hash: B256::ZERO,
+            coinbase: Address::ZERO,
+            base_fee_per_gas: None,
+            number: 0,
+            gas_used: 0,
+            parent_hash: B256::ZERO,
+            excess_blob_gas: None,
+            blob_gas_used: None,
+            alloc,
+        }
+    }
+
+    /// Refuse genesis blocks the chain cannot start from
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.number != 0 {
+            return Err(eyre::eyre!("genesis number is {} but chains start at 0", self.number));
+        }
+        Ok(())
+    }
+
+    /// Ethereum state trie root of the allocations
+    pub fn state_root(&self) -> B256 {
+        if self.alloc.is_empty() {
+            return EMPTY_ROOT_HASH;
+        }
+        state_root_unhashed(
+            self.alloc.iter().map(|(address, account)| (*address, account.trie_account())),
+        )
+    }
+
+    /// Genesis block header, derived as geth derives it
+    pub fn header(&self) -> ConsensusHeader {
+        let config = &self.config;
+        let difficulty = match self.difficulty {
+            Some(difficulty) => difficulty,
+            None if self.mix_hash.is_zero() => U256::from(GENESIS_DIFFICULTY),
+            None => U256::ZERO,
+        };
+        let gas_limit = if self.gas_limit == 0 { GENESIS_GAS_LIMIT } else { self.gas_limit };
+        let base_fee_per_gas = config
+            .is_london(0)
+            .then(|| self.base_fee_per_gas.unwrap_or(INITIAL_BASE_FEE));
+
+        let shanghai = ChainConfig::is_active(config.shanghai_time, self.timestamp);
+        let cancun = ChainConfig::is_active(config.cancun_time, self.timestamp);
+        let prague = ChainConfig::is_active(config.prague_time, self.timestamp);
+
+        ConsensusHeader {
+            parent_hash: self.parent_hash,
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            beneficiary: self.coinbase,
+            state_root: self.state_root(),
+            transactions_root: EMPTY_ROOT_HASH,
+            receipts_root: EMPTY_ROOT_HASH,
+            logs_bloom: Bloom::ZERO,
+            difficulty,
+            number: self.number,
+            gas_limit,
+            gas_used: self.gas_used,
+            timestamp: self.timestamp,
+            extra_data: self.extra_data.clone(),
+            mix_hash: self.mix_hash,
+            nonce: B64::from(self.nonce.to_be_bytes()),
+            base_fee_per_gas,
+            withdrawals_root: shanghai.then_some(EMPTY_ROOT_HASH),
+            blob_gas_used: cancun.then(|| self.blob_gas_used.unwrap_or_default()),
+            excess_blob_gas: cancun.then(|| self.excess_blob_gas.unwrap_or_default()),
+            parent_beacon_block_root: cancun.then_some(B256::ZERO),
+            requests_hash: prague.then_some(EMPTY_REQUESTS_HASH),
+        }
+    }
+
+    /// Genesis block hash
+    pub fn hash(&self) -> B256 {
+        header_hash(&self.header())
+    }
+
+    /// Stored genesis block, without the node's own state roots
+    pub fn block(&self) -> StoredBlock {
+        let header = self.header();
+        StoredBlock {
+            hash: header_hash(&header),
+            parent_hash: header.parent_hash,
+            timestamp: header.timestamp,
+            gas_limit: header.gas_limit,
+            gas_used: header.gas_used,
+            miner: header.beneficiary,
+            ..StoredBlock::genesis(self.config.chain_id)
+        }
+    }
+
+    /// Write the allocations into `state`
+    pub fn apply(&self, state: &impl StateWriter) -> eyre::Result<()> {
+        for (address, account) in &self.alloc {
+            let code_hash = account.code.as_ref().map(keccak256).unwrap_or_default();
+            let storage = account
+                .storage_slots()
+                .map(|(slot, value)| (U256::from_be_bytes(slot.0), value))
+                .collect();
+            let state_account = AccountState {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash,
+                code: account.code.clone(),
+                storage,
+            };
+            state.set_account(*address, state_account)?;
+        }
+        Ok(())
+    }
+}
+
+/// Geth quantity: a JSON number, or a hex (`0x`) or decimal string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Quantity {
+    Number(u64),
+    Text(String),
+}
+
+impl Quantity {
+    fn into_u256(self) -> Result<U256, String> {
+        match self {
+            Self::Number(n) => Ok(U256::from(n)),
+            Self::Text(text) => match text.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16),
+                None => U256::from_str_radix(&text, 10),
+            }
+            .map_err(|e| format!("invalid quantity {:?}: {}", text, e)),
+        }
+    }
+
+    fn into_u64(self) -> Result<u64, String> {
+        let value = self.into_u256()?;
+        u64::try_from(value).map_err(|_| format!("quantity {} does not fit in 64 bits", value))
+    }
+}
+
+fn quantity_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Quantity::deserialize(deserializer)?.into_u64().map_err(serde::de::Error::custom)
+}
+
+fn quantity_u256<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    Quantity::deserialize(deserializer)?.into_u256().map_err(serde::de::Error::custom)
+}
+
+fn optional_quantity_u64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    Option::<Quantity>::deserialize(deserializer)?
+        .map(Quantity::into_u64)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+fn optional_quantity_u256<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<U256>, D::Error> {
+    Option::<Quantity>::deserialize(deserializer)?
+        .map(Quantity::into_u256)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+    use dex_storage::{DualvmStorage, StateReader};
+
+    const GENESIS: &str = r#"{
+        "config": { "chainId": 13337, "londonBlock": 0, "shanghaiTime": 0, "cancunTime": 0 },
+        "nonce": "0x42",
+        "timestamp": "0x10",
+        "extraData": "0x1234",
+        "gasLimit": "0x1c9c380",
+        "difficulty": "0x1",
+        "coinbase": "0x2222222222222222222222222222222222222222",
+        "alloc": {
+            "0x1111111111111111111111111111111111111111": { "balance": "1000" },
+            "0x3333333333333333333333333333333333333333": {
+                "balance": "0x10",
+                "nonce": "0x1",
+                "code": "0x6000",
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001":
+                    "0x0000000000000000000000000000000000000000000000000000000000000007"
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_header_fields_come_from_the_file() {
+        let genesis = Genesis::from_json(GENESIS).unwrap();
+        let header = genesis.header();
+
+        assert_eq!(header.nonce, B64::from(0x42u64.to_be_bytes()));
+        assert_eq!(header.timestamp, 0x10);
+        assert_eq!(header.extra_data, Bytes::from(vec![0x12, 0x34]));
+        assert_eq!(header.gas_limit, 30_000_000);
+        assert_eq!(header.difficulty, U256::from(1));
+        assert_eq!(header.beneficiary, address!("2222222222222222222222222222222222222222"));
+        assert_eq!(header.state_root, genesis.state_root());
+        assert_ne!(header.state_root, EMPTY_ROOT_HASH);
+
+        // Forks active at genesis shape the header
+        assert_eq!(header.base_fee_per_gas, Some(INITIAL_BASE_FEE));
+        assert_eq!(header.withdrawals_root, Some(EMPTY_ROOT_HASH));
+        assert_eq!(header.blob_gas_used, Some(0));
+        assert_eq!(header.parent_beacon_block_root, Some(B256::ZERO));
+        assert_eq!(header.requests_hash, None);
+
+        let block = genesis.block();
+        assert_eq!(block.hash, genesis.hash());
+        assert_eq!(block.timestamp, 0x10);
+        assert_eq!(block.miner, header.beneficiary);
+
+        // Any header field changes the hash
+        let other = GENESIS.replace("\"0x42\"", "\"0x43\"");
+        assert_ne!(Genesis::from_json(&other).unwrap().hash(), genesis.hash());
+    }
+
+    #[test]
+    fn test_geth_defaults() {
+        let genesis = Genesis::from_json(r#"{ "config": { "chainId": 1 } }"#).unwrap();
+        let header = genesis.header();
+
+        assert_eq!(header.gas_limit, GENESIS_GAS_LIMIT);
+        assert_eq!(header.difficulty, U256::from(GENESIS_DIFFICULTY));
+        assert_eq!(header.state_root, EMPTY_ROOT_HASH);
+        assert_eq!(header.base_fee_per_gas, None);
+        assert_eq!(header.withdrawals_root, None);
+
+        assert!(Genesis::from_json(r#"{ "config": { "chainId": 1 }, "number": "0x1" }"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_writes_accounts() {
+        let genesis = Genesis::from_json(GENESIS).unwrap();
+        let storage = DualvmStorage::in_memory().unwrap();
+        genesis.apply(storage.state.as_ref()).unwrap();
+
+        let eoa = address!("1111111111111111111111111111111111111111");
+        let contract = address!("3333333333333333333333333333333333333333");
+        assert_eq!(storage.state.get_balance(&eoa), U256::from(1000));
+        assert_eq!(storage.state.get_balance(&contract), U256::from(16));
+        assert_eq!(storage.state.get_nonce(&contract), 1);
+        assert_eq!(storage.state.get_code(&contract), Some(Bytes::from(vec![0x60, 0x00])));
+        assert_eq!(storage.state.get_storage(&contract, U256::from(1)), U256::from(7));
+    }
+
+    #[test]
+    fn test_repository_genesis_parses() {
+        let genesis = Genesis::from_json(include_str!("../../../genesis.json")).unwrap();
+        assert_eq!(genesis.config.chain_id, 13337);
+        assert_eq!(genesis.alloc.len(), 10);
+        assert_eq!(genesis.block().gas_limit, 30_000_000);
+    }
+}
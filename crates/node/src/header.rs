@@ -0,0 +1,48 @@
+//! Ethereum header representation of stored blocks
+
+use alloy_consensus::{proofs::ordered_trie_root_with_encoder, Header as ConsensusHeader};
+use alloy_primitives::{keccak256, Bloom, Bytes, B256, B64, U256};
+use alloy_rlp::Encodable;
+use dex_storage::StoredBlock;
+
+/// Build the header a stored block was sealed with
+///
+/// The block signature travels in `extra_data` and `transactions_root` commits
+/// to the transaction hashes; the other roots are fixed empty-trie values.
+pub fn block_header(block: &StoredBlock) -> ConsensusHeader {
+    ConsensusHeader {
+        parent_hash: block.parent_hash,
+        ommers_hash: keccak256([0x80]), // RLP empty list
+        beneficiary: block.miner,
+        state_root: block.combined_state_root,
+        transactions_root: transactions_root(&block.transaction_hashes),
+        receipts_root: keccak256([0x80]),
+        logs_bloom: Bloom::ZERO,
+        difficulty: U256::ZERO,
+        number: block.number,
+        gas_limit: block.gas_limit,
+        gas_used: block.gas_used,
+        timestamp: block.timestamp,
+        extra_data: Bytes::copy_from_slice(&block.signature),
+        mix_hash: B256::ZERO,
+        nonce: B64::ZERO,
+        base_fee_per_gas: Some(0),
+        withdrawals_root: None,
+        blob_gas_used: None,
+        excess_blob_gas: None,
+        parent_beacon_block_root: None,
+        requests_hash: None,
+    }
+}
+
+/// Ordered trie root over a block's transaction hashes
+///
+/// Blocks without transactions get the empty trie root.
+pub fn transactions_root(tx_hashes: &[B256]) -> B256 {
+    ordered_trie_root_with_encoder(tx_hashes, |hash, buf| hash.encode(buf))
+}
+
+/// Hash of a header, as used for block hashes
+pub fn header_hash(header: &ConsensusHeader) -> B256 {
+    keccak256(alloy_rlp::encode(header))
+}
@@ -0,0 +1,286 @@
+//! Per-transaction state access and execution traces
+//!
+//! The executors report every account, slot and counter a transaction reads
+//! or writes to the [`ExecutionInspector`] of the [`DualVmExecutor`], which
+//! collects them into the transaction's access set. With tracing enabled, it
+//! also records what each transaction of a block did: its outcome and gas,
+//! the sender and recipient balances and nonces before and after, its counter
+//! change and the state it accessed. Reading accounts around every
+//! transaction slows block production down, so tracing is meant for
+//! debugging (`--trace-blocks`). [`TraceWriter`] dumps one JSON file per
+//! block and keeps only the newest.
+//!
+//! [`DualVmExecutor`]: crate::DualVmExecutor
+
+use crate::executor::DualVmExecutionResult;
+use alloy_primitives::{Address, B256, U256};
+use dex_dexvm::{record_labeled_counter_access, record_operation_access};
+use dex_primitives::{AccessSet, DexVmOperation};
+use dex_storage::StoredBlock;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Block trace files kept by default
+pub const DEFAULT_TRACE_FILES: usize = 1_000;
+
+/// How a traced transaction was executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TracedTxKind {
+    /// Plain EVM transaction
+    Evm,
+    /// EVM transaction calling the counter precompile
+    CrossVm,
+    /// Signed DexVM router transaction
+    DexVm,
+    /// Native DexVM transaction from the DexVM pool
+    NativeDexVm,
+}
+
+/// Balance and nonce of an account before and after a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountChange {
+    pub address: Address,
+    pub balance_before: U256,
+    pub balance_after: U256,
+    pub nonce_before: u64,
+    pub nonce_after: u64,
+}
+
+/// What one transaction did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTrace {
+    pub hash: B256,
+    pub kind: TracedTxKind,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub success: bool,
+    pub gas_used: u64,
+    /// Counter value before and after, if the transaction changed it
+    pub counter: Option<(u64, u64)>,
+    /// Sender and recipient accounts whose balance or nonce changed
+    pub accounts: Vec<AccountChange>,
+    /// State the transaction read and wrote; not recorded for native DexVM transactions
+    pub access_set: Option<AccessSet>,
+    pub execution_micros: u64,
+}
+
+/// Collects the state accesses of each transaction and, while tracing, the
+/// traces of the transactions executed in a block
+#[derive(Debug, Default)]
+pub struct ExecutionInspector {
+    /// Whether traces are recorded
+    tracing: bool,
+    traces: Vec<TxTrace>,
+    /// State accessed by the transaction being executed
+    access_set: AccessSet,
+}
+
+impl ExecutionInspector {
+    /// Inspector that also records traces if `tracing` is set
+    pub fn new(tracing: bool) -> Self {
+        Self { tracing, ..Default::default() }
+    }
+
+    /// Whether traces are recorded
+    pub fn is_tracing(&self) -> bool {
+        self.tracing
+    }
+
+    /// Start collecting the accesses of the next transaction
+    pub fn start_transaction(&mut self) {
+        self.access_set = AccessSet::new();
+    }
+
+    /// Take the access set collected since the transaction started
+    pub fn take_access_set(&mut self) -> AccessSet {
+        std::mem::take(&mut self.access_set)
+    }
+
+    /// An EVM account was read
+    pub fn read_account(&mut self, address: Address) {
+        self.access_set.read_account(address);
+    }
+
+    /// An EVM account was written
+    pub fn write_account(&mut self, address: Address) {
+        self.access_set.write_account(address);
+    }
+
+    /// A labeled counter was read, and written if `written` is set
+    pub fn access_counter(&mut self, owner: Address, label: B256, written: bool) {
+        record_labeled_counter_access(&mut self.access_set, owner, label, written);
+    }
+
+    /// A DexVM operation ran for `from`
+    pub fn access_operation(&mut self, from: Address, operation: DexVmOperation, succeeded: bool) {
+        record_operation_access(&mut self.access_set, from, operation, succeeded);
+    }
+
+    /// Record a transaction that was included in the block, if tracing
+    pub fn record(&mut self, trace: TxTrace) {
+        if self.tracing {
+            self.traces.push(trace);
+        }
+    }
+
+    /// Take the traces recorded so far
+    pub fn take(&mut self) -> Vec<TxTrace> {
+        std::mem::take(&mut self.traces)
+    }
+}
+
+/// A transaction dropped from the block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SkippedTrace {
+    pub hash: B256,
+    pub reason: String,
+}
+
+/// Trace of a produced block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    pub proposer: Address,
+    pub gas_used: u64,
+    pub evm_state_root: B256,
+    pub dexvm_state_root: B256,
+    pub combined_state_root: B256,
+    pub execution_micros: u64,
+    pub transactions: Vec<TxTrace>,
+    pub skipped: Vec<SkippedTrace>,
+}
+
+impl BlockTrace {
+    /// Trace of a block from its stored header and execution result
+    pub fn new(block: &StoredBlock, result: &DualVmExecutionResult) -> Self {
+        Self {
+            number: block.number,
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            proposer: block.miner,
+            gas_used: result.total_gas_used,
+            evm_state_root: result.evm_state_root,
+            dexvm_state_root: result.dexvm_state_root,
+            combined_state_root: result.combined_state_root,
+            execution_micros: result.execution_time.as_micros() as u64,
+            transactions: result.traces.clone(),
+            skipped: result
+                .skipped_transactions
+                .iter()
+                .map(|(hash, reason)| SkippedTrace { hash: *hash, reason: reason.to_string() })
+                .collect(),
+        }
+    }
+}
+
+/// Writes block traces as `block-<number>.json` files into a directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceWriter {
+    dir: PathBuf,
+    /// Newest files kept; 0 keeps every file
+    keep: usize,
+}
+
+impl TraceWriter {
+    /// Write into `dir`, creating it if needed, keeping the newest `keep` files
+    pub fn new(dir: impl Into<PathBuf>, keep: usize) -> eyre::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| {
+            eyre::eyre!("Failed to create trace directory {}: {}", dir.display(), e)
+        })?;
+        Ok(Self { dir, keep })
+    }
+
+    /// Directory the traces are written to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write a block's trace, then remove the oldest files beyond the limit
+    pub fn write(&self, trace: &BlockTrace) -> eyre::Result<PathBuf> {
+        let path = self.dir.join(format!("block-{:012}.json", trace.number));
+        fs::write(&path, serde_json::to_vec_pretty(trace)?)?;
+        self.rotate()?;
+        Ok(path)
+    }
+
+    fn rotate(&self) -> eyre::Result<()> {
+        if self.keep == 0 {
+            return Ok(());
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_trace_file(path))
+            .collect();
+        if files.len() <= self.keep {
+            return Ok(());
+        }
+
+        // Block numbers are zero-padded, so names sort oldest first
+        files.sort();
+        for path in &files[..files.len() - self.keep] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_trace_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("block-") && name.ends_with(".json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn block_trace(number: u64) -> BlockTrace {
+        BlockTrace {
+            number,
+            hash: B256::repeat_byte(number as u8),
+            parent_hash: B256::ZERO,
+            proposer: Address::ZERO,
+            gas_used: 0,
+            evm_state_root: B256::ZERO,
+            dexvm_state_root: B256::ZERO,
+            combined_state_root: B256::ZERO,
+            execution_micros: 0,
+            transactions: vec![],
+            skipped: vec![],
+        }
+    }
+
+    #[test]
+    fn test_trace_writer_rotates() {
+        let dir = tempdir().unwrap();
+        let writer = TraceWriter::new(dir.path().join("traces"), 2).unwrap();
+
+        for number in [9, 10, 11] {
+            writer.write(&block_trace(number)).unwrap();
+        }
+
+        let mut names: Vec<_> = fs::read_dir(writer.dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["block-000000000010.json", "block-000000000011.json"]);
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(writer.dir().join(&names[1])).unwrap()).unwrap();
+        assert_eq!(json["number"], 11);
+        assert!(json["transactions"].as_array().unwrap().is_empty());
+    }
+}
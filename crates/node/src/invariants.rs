@@ -0,0 +1,316 @@
+//! Post-block state invariants
+//!
+//! After a produced block executes, its effects are checked against rules
+//! the executor must never break:
+//! - There are no block rewards, so the total EVM balance drops by exactly the
+//!   fees the block's successful transactions paid, less the part the fee
+//!   policy credited back to accounts
+//! - Account nonces never decrease
+//! - Counter changes chain together (each starts where the previous change to
+//!   that counter ended) and the final counter equals the last change.
+//!   Counters are unsigned, so an underflow shows up as a value above
+//!   `i64::MAX` and is reported as a negative counter
+//! - Receipt gas totals never decrease and stay within the block's gas used
+//!
+//! Checking walks every account twice per block, so it is meant for
+//! development builds, where it is on by default.
+
+use crate::executor::DualVmExecutionResult;
+use alloy_primitives::{Address, U256};
+use dex_storage::StateReader;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+/// What block production does when a block breaks an invariant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantMode {
+    /// Do not check
+    Off,
+    /// Log every violation as an error and keep going
+    Log,
+    /// Log and stop block production before the block is stored
+    Halt,
+}
+
+impl Default for InvariantMode {
+    /// `halt` in debug builds, `off` in release builds
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Halt
+        } else {
+            Self::Off
+        }
+    }
+}
+
+impl FromStr for InvariantMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "log" => Ok(Self::Log),
+            "halt" => Ok(Self::Halt),
+            _ => Err(format!("unknown invariant mode '{}' (expected off, log or halt)", s)),
+        }
+    }
+}
+
+impl fmt::Display for InvariantMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Log => write!(f, "log"),
+            Self::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+/// EVM account totals taken before or after a block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSummary {
+    /// Sum of all account balances
+    pub total_balance: U256,
+    /// Nonce of every account
+    pub nonces: HashMap<Address, u64>,
+}
+
+impl StateSummary {
+    /// Summarize every account in the state store
+    pub fn capture<S: StateReader + ?Sized>(state: &S) -> Self {
+        let mut summary = Self::default();
+        for (address, account) in state.all_accounts() {
+            summary.total_balance += account.balance;
+            summary.nonces.insert(address, account.nonce);
+        }
+        summary
+    }
+}
+
+/// A broken post-block invariant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// Total balance did not change by exactly the fees paid less those credited
+    BalanceNotConserved { before: U256, after: U256, fees_paid: U256, fees_credited: U256 },
+    /// An account nonce went down
+    NonceDecreased { address: Address, before: u64, after: u64 },
+    /// A counter wrapped below zero
+    NegativeCounter { address: Address, value: u64 },
+    /// A counter change did not start from the counter's previous value
+    CounterChangeGap { address: Address, expected: u64, old_value: u64 },
+    /// The counter state differs from the block's last change to it
+    CounterMismatch { address: Address, expected: u64, actual: u64 },
+    /// A receipt's cumulative gas is below the previous receipt's
+    GasDecreased { index: usize, previous: u64, cumulative: u64 },
+    /// Receipts account for more gas than the block used
+    GasExceedsTotal { cumulative: u64, total: u64 },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BalanceNotConserved { before, after, fees_paid, fees_credited } => write!(
+                f,
+                "total balance went from {} to {} but the block paid {} in fees and credited {}",
+                before, after, fees_paid, fees_credited
+            ),
+            Self::NonceDecreased { address, before, after } => {
+                write!(f, "nonce of {} went down from {} to {}", address, before, after)
+            }
+            Self::NegativeCounter { address, value } => {
+                write!(f, "counter of {} wrapped below zero ({})", address, value)
+            }
+            Self::CounterChangeGap { address, expected, old_value } => write!(
+                f,
+                "counter change for {} starts at {} but the counter was {}",
+                address, old_value, expected
+            ),
+            Self::CounterMismatch { address, expected, actual } => write!(
+                f,
+                "counter of {} is {} but the block's last change set it to {}",
+                address, actual, expected
+            ),
+            Self::GasDecreased { index, previous, cumulative } => write!(
+                f,
+                "receipt {} has cumulative gas {} below the previous {}",
+                index, cumulative, previous
+            ),
+            Self::GasExceedsTotal { cumulative, total } => {
+                write!(f, "receipts account for {} gas but the block used {}", cumulative, total)
+            }
+        }
+    }
+}
+
+/// Check a block's execution result against the state before and after it
+///
+/// `counter` reads a DexVM counter after the block.
+pub fn check_block(
+    before: &StateSummary,
+    after: &StateSummary,
+    result: &DualVmExecutionResult,
+    counter: impl Fn(&Address) -> u64,
+) -> Vec<InvariantViolation> {
+    let mut violations = Vec::new();
+
+    let expected_balance =
+        before.total_balance.checked_sub(result.fees_paid).map(|b| b + result.fees_credited);
+    if expected_balance != Some(after.total_balance) {
+        violations.push(InvariantViolation::BalanceNotConserved {
+            before: before.total_balance,
+            after: after.total_balance,
+            fees_paid: result.fees_paid,
+            fees_credited: result.fees_credited,
+        });
+    }
+
+    for (address, &before_nonce) in &before.nonces {
+        let after_nonce = after.nonces.get(address).copied().unwrap_or_default();
+        if after_nonce < before_nonce {
+            violations.push(InvariantViolation::NonceDecreased {
+                address: *address,
+                before: before_nonce,
+                after: after_nonce,
+            });
+        }
+    }
+
+    let mut last_values: BTreeMap<Address, u64> = BTreeMap::new();
+    for change in &result.counter_changes {
+        if let Some(&expected) = last_values.get(&change.address) {
+            if change.old_value != expected {
+                violations.push(InvariantViolation::CounterChangeGap {
+                    address: change.address,
+                    expected,
+                    old_value: change.old_value,
+                });
+            }
+        }
+        last_values.insert(change.address, change.new_value);
+    }
+    for (address, expected) in last_values {
+        let actual = counter(&address);
+        if actual > i64::MAX as u64 {
+            violations.push(InvariantViolation::NegativeCounter { address, value: actual });
+        }
+        if actual != expected {
+            violations.push(InvariantViolation::CounterMismatch { address, expected, actual });
+        }
+    }
+
+    let mut previous = 0;
+    for (index, receipt) in result.block_receipts.iter().enumerate() {
+        if receipt.cumulative_gas_used < previous {
+            violations.push(InvariantViolation::GasDecreased {
+                index,
+                previous,
+                cumulative: receipt.cumulative_gas_used,
+            });
+        }
+        previous = previous.max(receipt.cumulative_gas_used);
+    }
+    if previous > result.total_gas_used {
+        violations.push(InvariantViolation::GasExceedsTotal {
+            cumulative: previous,
+            total: result.total_gas_used,
+        });
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::Receipt;
+    use alloy_primitives::{address, B256};
+    use dex_primitives::CounterChange;
+    use std::time::Duration;
+
+    const ALICE: Address = address!("1111111111111111111111111111111111111111");
+
+    fn result(fees_paid: u64, counter_changes: Vec<CounterChange>) -> DualVmExecutionResult {
+        let receipt = |cumulative_gas_used| Receipt {
+            status: true.into(),
+            cumulative_gas_used,
+            logs: vec![],
+        };
+        DualVmExecutionResult {
+            evm_receipts: vec![],
+            dexvm_receipts: vec![],
+            block_receipts: vec![receipt(21_000), receipt(42_000)],
+            total_gas_used: 42_000,
+            evm_state_root: B256::ZERO,
+            dexvm_state_root: B256::ZERO,
+            combined_state_root: B256::ZERO,
+            included_transactions: 2,
+            included_dexvm_transactions: 0,
+            cross_vm_transactions: 0,
+            execution_time: Duration::ZERO,
+            access_sets: vec![],
+            counter_changes,
+            skipped_transactions: vec![],
+            counter_only_accounts: vec![],
+            fees_paid: U256::from(fees_paid),
+            fees_credited: U256::ZERO,
+            traces: vec![],
+        }
+    }
+
+    fn summary(total_balance: u64, nonce: u64) -> StateSummary {
+        StateSummary {
+            total_balance: U256::from(total_balance),
+            nonces: HashMap::from([(ALICE, nonce)]),
+        }
+    }
+
+    fn change(old_value: u64, new_value: u64) -> CounterChange {
+        CounterChange { address: ALICE, old_value, new_value, tx_hash: B256::ZERO }
+    }
+
+    #[test]
+    fn test_consistent_block_passes() {
+        let result = result(100, vec![change(0, 5), change(5, 3)]);
+        let violations = check_block(&summary(1_000, 0), &summary(900, 2), &result, |_| 3);
+        assert_eq!(violations, vec![]);
+    }
+
+    #[test]
+    fn test_violations_are_reported() {
+        let mut result = result(100, vec![change(0, 5), change(4, 3)]);
+        result.total_gas_used = 30_000;
+
+        let violations =
+            check_block(&summary(1_000, 2), &summary(950, 1), &result, |_| u64::MAX);
+        assert_eq!(
+            violations,
+            vec![
+                InvariantViolation::BalanceNotConserved {
+                    before: U256::from(1_000),
+                    after: U256::from(950),
+                    fees_paid: U256::from(100),
+                    fees_credited: U256::ZERO,
+                },
+                InvariantViolation::NonceDecreased { address: ALICE, before: 2, after: 1 },
+                InvariantViolation::CounterChangeGap { address: ALICE, expected: 5, old_value: 4 },
+                InvariantViolation::NegativeCounter { address: ALICE, value: u64::MAX },
+                InvariantViolation::CounterMismatch {
+                    address: ALICE,
+                    expected: 3,
+                    actual: u64::MAX,
+                },
+                InvariantViolation::GasExceedsTotal { cumulative: 42_000, total: 30_000 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("halt".parse::<InvariantMode>().unwrap(), InvariantMode::Halt);
+        assert_eq!(InvariantMode::Log.to_string(), "log");
+        assert!("loud".parse::<InvariantMode>().is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Starting a complete node from its configuration
+//!
+//! [`launch`] does what the `dex-reth` binary does once its flags are parsed:
+//! it opens and checks the database, starts P2P, configures consensus, serves
+//! both RPC APIs and spawns the tasks of the node's mode. Other binaries, such
+//! as test orchestration tools, embed a node with it and stop it through the
+//! returned [`NodeHandle`].
+
+use crate::{
+    consensus::PoaConfig,
+    node::{DualVmNode, NodeConfig},
+    orchestrator::{NodeMode, NodeTasks},
+    shutdown::{startup_check, ShutdownSentinel, STARTUP_CHECK_DEPTH},
+};
+use dex_p2p::{enode_url, P2pConfig, P2pHandle, P2pService};
+use dex_rpc::{ChainEvent, EvmRpcServer, NodeStatus};
+use dex_storage::DualvmStorage;
+use jsonrpsee::server::ServerHandle;
+use std::{net::SocketAddr, sync::Arc};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+/// Start a node, returning once it serves RPC
+///
+/// The mode follows from the arguments: a validator with `poa`, a fullnode
+/// with only `p2p`, an RPC-only node with neither. A validator continues
+/// from the stored head, whatever `poa.starting_block` says.
+pub async fn launch(
+    config: NodeConfig,
+    p2p: Option<P2pConfig>,
+    poa: Option<PoaConfig>,
+) -> eyre::Result<NodeHandle> {
+    let mode = NodeMode::from_flags(poa.is_some(), p2p.is_some());
+    let chain_id = config.chain_id;
+    let genesis_hash = config.genesis.as_ref().map(|genesis| genesis.hash());
+    let datadir = config.datadir.clone();
+    let sentinel_path = config.shutdown_sentinel.clone().filter(|_| !config.ephemeral);
+    let (evm_rpc_port, dexvm_rpc_port) = (config.evm_rpc_port, config.dexvm_rpc_port);
+    let webhook_urls = config.webhook_urls.clone();
+
+    let mut node = DualVmNode::with_config(config);
+    if let Some(genesis_hash) = genesis_hash {
+        node.block_store().check_genesis(chain_id, genesis_hash)?;
+    }
+    node.block_store().check_contiguous().map_err(|e| {
+        e.wrap_err(format!(
+            "Database {} has gaps; remove it and resync from peers",
+            datadir.display()
+        ))
+    })?;
+    let recovery = node.block_store().recover()?;
+    if recovery.found_partial_block() {
+        tracing::warn!(
+            "Removed data of a block that was not fully stored after block {}: \
+             {} stats, {} transactions, {} receipts, {} access sets, {} DexVM receipts",
+            recovery.head,
+            recovery.removed_stats,
+            recovery.removed_transactions,
+            recovery.removed_receipts,
+            recovery.removed_access_sets,
+            recovery.removed_dexvm_receipts
+        );
+    }
+
+    // Check the stored head before serving RPC if the previous run did not stop cleanly
+    let sentinel = sentinel_path.map(ShutdownSentinel::create).transpose()?;
+    if let Some(sentinel) = &sentinel {
+        if !sentinel.previous_clean() {
+            tracing::warn!(
+                "Previous run did not shut down cleanly, checking the last {} blocks",
+                STARTUP_CHECK_DEPTH
+            );
+        }
+        let check = startup_check(node.storage().blocks.as_ref(), sentinel.previous_clean());
+        match &check.error {
+            None if !check.clean_shutdown => {
+                tracing::info!("Startup check passed ({} blocks)", check.blocks_checked)
+            }
+            None => {}
+            Some(error) => tracing::error!(
+                "Startup check failed after {} blocks: {}; /health reports degraded, \
+                 run `db verify` for details",
+                check.blocks_checked,
+                error
+            ),
+        }
+        node.status().set_startup_check(check);
+    }
+
+    for url in &webhook_urls {
+        let webhook = node.webhooks().register(url).map_err(|e| eyre::eyre!(e))?;
+        tracing::info!("Registered webhook {}: {}", webhook.id, webhook.url);
+    }
+
+    let p2p = match p2p {
+        Some(config) => {
+            let advertised = config.advertised_addr();
+            let handle = P2pService::new(config).start().await?;
+            tracing::info!("P2P service started");
+            tracing::info!("Local peer ID: {:?}", handle.local_id());
+            tracing::info!("Enode URL: {}", enode_url(handle.local_id(), advertised));
+            Some(handle)
+        }
+        None => None,
+    };
+
+    if let Some(mut poa) = poa {
+        poa.starting_block = recovery.head;
+        tracing::info!("Validator address: {:?}", poa.validator);
+        tracing::info!("Continuing from block {} (hash: {:?})", recovery.head, recovery.head_hash);
+        node.set_consensus(poa, recovery.head_hash);
+    }
+
+    let (evm_rpc_handle, evm_rpc_addr) = node.start_evm_rpc(evm_rpc_port).await?;
+    let rpc_server = node.evm_rpc_server().cloned().expect("EVM RPC server just started");
+    let (dexvm_rpc_handle, dexvm_rpc_addr) = match node.start_dexvm_rpc(dexvm_rpc_port).await {
+        Ok(started) => started,
+        Err(e) => {
+            let _ = evm_rpc_handle.stop();
+            return Err(e);
+        }
+    };
+
+    let storage = Arc::clone(node.storage());
+    let status = Arc::clone(node.status());
+    let tasks = NodeTasks::spawn(node, mode, p2p.clone())?;
+
+    Ok(NodeHandle {
+        mode,
+        storage,
+        status,
+        rpc_server,
+        p2p,
+        evm_rpc_addr,
+        dexvm_rpc_addr,
+        evm_rpc_handle,
+        dexvm_rpc_handle,
+        tasks,
+        sentinel,
+    })
+}
+
+/// A node started by [`launch`]
+///
+/// Dropping the handle stops the node like [`NodeHandle::stop`], but leaves
+/// the shutdown sentinel behind, so the next start checks the stored head.
+pub struct NodeHandle {
+    mode: NodeMode,
+    storage: Arc<DualvmStorage>,
+    status: Arc<NodeStatus>,
+    rpc_server: Arc<EvmRpcServer>,
+    p2p: Option<P2pHandle>,
+    evm_rpc_addr: SocketAddr,
+    dexvm_rpc_addr: SocketAddr,
+    evm_rpc_handle: ServerHandle,
+    dexvm_rpc_handle: JoinHandle<()>,
+    tasks: NodeTasks,
+    sentinel: Option<ShutdownSentinel>,
+}
+
+impl NodeHandle {
+    /// What the node does besides serving RPC
+    pub fn mode(&self) -> NodeMode {
+        self.mode
+    }
+
+    /// Address of the EVM JSON-RPC server, with the port picked if 0 was configured
+    pub fn evm_rpc_addr(&self) -> SocketAddr {
+        self.evm_rpc_addr
+    }
+
+    /// Address of the DexVM REST API, with the port picked if 0 was configured
+    pub fn dexvm_rpc_addr(&self) -> SocketAddr {
+        self.dexvm_rpc_addr
+    }
+
+    /// Node database
+    pub fn storage(&self) -> &Arc<DualvmStorage> {
+        &self.storage
+    }
+
+    /// Status served by `/status` and `/health`
+    pub fn status(&self) -> &Arc<NodeStatus> {
+        &self.status
+    }
+
+    /// EVM JSON-RPC implementation, for calls without going over HTTP
+    pub fn rpc_server(&self) -> &Arc<EvmRpcServer> {
+        &self.rpc_server
+    }
+
+    /// P2P handle, unless P2P is disabled
+    pub fn p2p(&self) -> Option<&P2pHandle> {
+        self.p2p.as_ref()
+    }
+
+    /// Receive blocks committed and reverted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.rpc_server.subscribe_chain_events()
+    }
+
+    /// Wait until block production stops, which only happens on a storage failure
+    ///
+    /// Never returns for modes that do not produce blocks.
+    pub async fn wait_for_exit(&mut self) -> eyre::Result<()> {
+        self.tasks.wait_for_exit().await
+    }
+
+    /// Stop every task and server, then mark the shutdown as clean
+    pub fn stop(mut self) -> eyre::Result<()> {
+        self.shutdown();
+        self.evm_rpc_handle.stop()?;
+        match self.sentinel.take() {
+            Some(sentinel) => sentinel.release(),
+            None => Ok(()),
+        }
+    }
+
+    fn shutdown(&mut self) {
+        self.tasks.shutdown();
+        self.dexvm_rpc_handle.abort();
+    }
+}
+
+impl Drop for NodeHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+        let _ = self.evm_rpc_handle.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    const DEV_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn config() -> NodeConfig {
+        NodeConfig { ephemeral: true, evm_rpc_port: 0, dexvm_rpc_port: 0, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_launch_validator_without_p2p() {
+        let poa = PoaConfig::from_hex_key(DEV_KEY, Duration::from_millis(20)).unwrap();
+        let handle = launch(config(), None, Some(poa)).await.unwrap();
+        assert_eq!(handle.mode(), NodeMode::Validator);
+        assert!(handle.p2p().is_none());
+        assert_ne!(handle.evm_rpc_addr().port(), 0);
+        assert_ne!(handle.dexvm_rpc_addr().port(), 0);
+
+        let mut events = handle.subscribe();
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap();
+        match event.unwrap() {
+            ChainEvent::Committed { block, .. } => assert!(block.number.to::<u64>() >= 1),
+            ChainEvent::Reverted { .. } => panic!("nothing to revert"),
+        }
+        assert!(handle.storage().blocks.latest_block_number() >= 1);
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_only_stop_releases_the_sentinel() {
+        let on_disk = |dir: &std::path::Path| NodeConfig {
+            ephemeral: false,
+            datadir: dir.join("db"),
+            shutdown_sentinel: Some(dir.join("running")),
+            ..config()
+        };
+
+        let dir = tempdir().unwrap();
+        let handle = launch(on_disk(dir.path()), None, None).await.unwrap();
+        assert_eq!(handle.mode(), NodeMode::RpcOnly);
+        assert!(dir.path().join("running").exists());
+        assert!(handle.status().startup_check().is_some_and(|check| check.clean_shutdown));
+        handle.stop().unwrap();
+        assert!(!dir.path().join("running").exists());
+
+        // Dropped without stopping, e.g. on an error: the next start checks the stored head
+        let dir = tempdir().unwrap();
+        drop(launch(on_disk(dir.path()), None, None).await.unwrap());
+        assert!(dir.path().join("running").exists());
+    }
+}
@@ -2,16 +2,69 @@
 //!
 //! This crate provides the complete dual VM node:
 //! - Dual VM executor: coordinates EVM and DexVM execution
+//! - Genesis: geth genesis files and the genesis header derived from them
+//! - Fee policy: burns, validator tips and treasury share of transaction fees
+//! - Block size limits: encoded bytes and transaction count per block
+//! - Execution inspector: optional per-transaction traces of produced blocks
 //! - Node type: integrates all components
 //! - RPC services: DexVM REST API (9845) + EVM JSON-RPC (8545)
-//! - POA consensus: simple single-validator consensus
+//! - POA consensus: simple single-validator consensus, with a hook for
+//!   application-defined block contents
+//! - Block production and P2P block sync loops, with state roots computed in the background
+//! - DexVM state sync: serving and copying counters over the `dexvm/1` subprotocol
+//! - Rate-limited ingestion of transactions gossiped by peers, on a worker pool
+//! - Node modes (validator, fullnode, RPC-only) and the tasks each one runs
+//! - `launch`: a complete node from its configuration, for embedding in other binaries
+//! - Offline chain verification
+//! - Deterministic replay of stored blocks
+//! - Unclean shutdown detection and startup consistency checks
+//! - Post-block invariant checks for development builds
+//! - Block production watchdog
 
 pub mod consensus;
+pub mod dexvm_sync;
 pub mod evm_executor;
 pub mod executor;
+pub mod fees;
+pub mod genesis;
+pub mod header;
+pub mod inspector;
+pub mod invariants;
+pub mod launch;
+pub mod limits;
 pub mod node;
+pub mod orchestrator;
+pub mod ordering;
+pub mod producer;
+pub mod replay;
+pub mod roots;
+pub mod shutdown;
+pub mod sync;
+pub mod tx_ingest;
+pub mod verify;
+pub mod watchdog;
 
-pub use consensus::{BlockProposal, PoaConfig, PoaConsensus};
-pub use evm_executor::SimpleEvmExecutor;
-pub use executor::{DualVmExecutionResult, DualVmExecutor};
+pub use consensus::{
+    round_robin_proposer, BlockProposal, PassthroughProposals, PoaConfig, PoaConsensus,
+    ProposalHandler,
+};
+pub use dexvm_sync::{DexVmStateSync, DEXVM_SYNC_INTERVAL};
+pub use evm_executor::{AccountSnapshot, BlockSnapshot, SimpleEvmExecutor};
+pub use executor::{AccountView, BlockUndo, DualVmExecutionResult, DualVmExecutor, SkipReason};
+pub use fees::FeePolicy;
+pub use genesis::{ChainConfig, Genesis, GenesisAccount};
+pub use inspector::{BlockTrace, ExecutionInspector, TraceWriter, TxTrace, DEFAULT_TRACE_FILES};
+pub use invariants::{InvariantMode, InvariantViolation, StateSummary};
+pub use launch::{launch, NodeHandle};
+pub use limits::{BlockLimits, DEFAULT_MAX_BLOCK_BYTES, DEFAULT_MAX_BLOCK_TXS};
 pub use node::{DualVmNode, NodeConfig};
+pub use orchestrator::{NodeMode, NodeTasks};
+pub use producer::StorageRetryPolicy;
+pub use replay::{replay, ReplayMismatch, ReplayReport};
+pub use roots::PendingRoots;
+pub use shutdown::{startup_check, ShutdownSentinel, STARTUP_CHECK_DEPTH};
+pub use tx_ingest::{
+    PeerVerdict, TxIngest, TxIngestConfig, DEFAULT_PEER_TX_BURST, DEFAULT_PEER_TX_RATE,
+    DEFAULT_TX_INGEST_WORKERS,
+};
+pub use watchdog::{WatchdogConfig, DEFAULT_STALL_INTERVALS};
@@ -0,0 +1,102 @@
+//! Block size limits
+//!
+//! Gas bounds how long a block takes to execute, not how large it is: a few
+//! transactions with big calldata make blocks that are slow to send to peers.
+//! The chain spec also caps the encoded bytes and the number of signed
+//! transactions in a block. Producers carry whatever does not fit into the
+//! next block, and synced blocks over either cap are rejected.
+
+/// Default cap on the encoded signed transactions of a block
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 2 * 1024 * 1024;
+
+/// Default cap on the number of signed transactions in a block
+pub const DEFAULT_MAX_BLOCK_TXS: usize = 10_000;
+
+/// Size caps every block of the chain keeps to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLimits {
+    /// Total RLP-encoded size of a block's signed transactions
+    pub max_bytes: usize,
+    /// Number of signed transactions in a block
+    pub max_txs: usize,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        Self { max_bytes: DEFAULT_MAX_BLOCK_BYTES, max_txs: DEFAULT_MAX_BLOCK_TXS }
+    }
+}
+
+impl BlockLimits {
+    /// Refuse caps that would keep every transaction out of blocks
+    pub fn validate(&self) -> eyre::Result<()> {
+        if self.max_bytes == 0 || self.max_txs == 0 {
+            return Err(eyre::eyre!(
+                "block limits must allow at least one transaction (max bytes {}, max txs {})",
+                self.max_bytes,
+                self.max_txs
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of leading transactions, given their encoded sizes, that fit in one block
+    pub fn fitting(&self, sizes: impl IntoIterator<Item = usize>) -> usize {
+        let mut bytes = 0usize;
+        let mut count = 0;
+        for size in sizes {
+            bytes = bytes.saturating_add(size);
+            if count == self.max_txs || bytes > self.max_bytes {
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Refuse a block of `tx_count` signed transactions taking `bytes` encoded bytes
+    pub fn check(&self, tx_count: usize, bytes: usize) -> eyre::Result<()> {
+        if tx_count > self.max_txs {
+            return Err(eyre::eyre!(
+                "{} transactions exceed the limit of {}",
+                tx_count,
+                self.max_txs
+            ));
+        }
+        if bytes > self.max_bytes {
+            return Err(eyre::eyre!(
+                "{} bytes of transactions exceed the limit of {}",
+                bytes,
+                self.max_bytes
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_cap_bytes_and_count() {
+        let limits = BlockLimits { max_bytes: 1_000, max_txs: 3 };
+
+        // Byte cap: the third transaction would overflow it
+        assert_eq!(limits.fitting([400, 500, 200, 10]), 2);
+        // Count cap
+        assert_eq!(limits.fitting([10; 5]), 3);
+        // Exactly at both caps
+        assert_eq!(limits.fitting([300, 300, 400]), 3);
+        // A single transaction larger than a block never fits
+        assert_eq!(limits.fitting([1_001]), 0);
+
+        assert!(limits.check(3, 1_000).is_ok());
+        assert!(limits.check(4, 100).is_err());
+        assert!(limits.check(1, 1_001).is_err());
+
+        assert!(limits.validate().is_ok());
+        assert!(BlockLimits { max_bytes: 0, max_txs: 3 }.validate().is_err());
+        assert!(BlockLimits { max_bytes: 1_000, max_txs: 0 }.validate().is_err());
+    }
+}
@@ -4,16 +4,32 @@ use crate::{
     consensus::{PoaConfig, PoaConsensus},
     evm_executor::SimpleEvmExecutor,
     executor::DualVmExecutor,
+    fees::FeePolicy,
+    genesis::Genesis,
+    inspector::TraceWriter,
+    invariants::InvariantMode,
+    limits::BlockLimits,
+    producer::{labeled_counter_writes, StorageRetryPolicy},
+    tx_ingest::TxIngestConfig,
+    watchdog::WatchdogConfig,
 };
 use alloy_primitives::{keccak256, Address, B256, U256};
-use dex_dexvm::{DexVmExecutor as DexExecutor, DexVmState};
-use dex_rpc::{start_evm_rpc_server, DexVmApi, EvmRpcServer};
-use dex_storage::{BlockStore, DualvmStorage, StateStore, StoredBlock};
+use dex_dexvm::{DexVmExecutor as DexExecutor, DexVmPool, DexVmState};
+use dex_rpc::{
+    start_evm_rpc_server, ClientFeatures, DexVmApi, EvmRpcServer, MempoolConfig, NodeStatus,
+    RpcAuditLog, SyncGate, WebhookConfig, WebhookDispatcher,
+};
+use dex_storage::{
+    Backups, BlockReader, BlockWriter, DualvmStorage, StateReader, StateWriter, StoredBlock,
+    UnwoundBlock,
+};
 use jsonrpsee::server::ServerHandle;
 use std::{
     collections::HashMap,
+    net::SocketAddr,
     path::PathBuf,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 use tokio::task::JoinHandle;
 
@@ -24,10 +40,57 @@ pub struct NodeConfig {
     pub chain_id: u64,
     /// Data directory
     pub datadir: PathBuf,
+    /// Keep the database in memory instead of `datadir`, losing it on exit
+    pub ephemeral: bool,
     /// EVM RPC port
     pub evm_rpc_port: u16,
     /// DexVM RPC port
     pub dexvm_rpc_port: u16,
+    /// Retry policy for persisting produced blocks
+    pub storage_retry: StorageRetryPolicy,
+    /// Post-block invariant checking
+    pub invariant_mode: InvariantMode,
+    /// Where transaction fees go, from the chain spec
+    pub fee_policy: FeePolicy,
+    /// Size caps of every block, from the chain spec
+    pub block_limits: BlockLimits,
+    /// Where produced blocks are traced to; `None` disables tracing
+    pub block_traces: Option<TraceWriter>,
+    /// Serve dev-only RPC such as `dex_resetAccount`
+    pub dev: bool,
+    /// Where audited EVM RPC calls are logged; `None` disables auditing
+    pub rpc_audit: Option<RpcAuditLog>,
+    /// Block production watchdog of validators; `None` disables it
+    pub production_watchdog: Option<WatchdogConfig>,
+    /// Where database backups are written; `None` disables backups
+    pub backups: Option<Arc<Backups>>,
+    /// How often a backup is taken; `None` only backs up on `admin_backup`
+    pub backup_interval: Option<Duration>,
+    /// Genesis applied to a new database; `None` starts from an empty allocation
+    pub genesis: Option<Genesis>,
+    /// Longest a signed transaction may run before it is dropped from a block we build
+    pub tx_timeout: Option<Duration>,
+    /// Included transactions running longer than this are logged
+    pub slow_tx_threshold: Option<Duration>,
+    /// Mempool admission limits of the EVM RPC server
+    pub mempool: MempoolConfig,
+    /// Webhook delivery settings
+    pub webhooks: WebhookConfig,
+    /// Webhook URLs registered by [`crate::launch`]
+    pub webhook_urls: Vec<String>,
+    /// File marking a running node, checked by [`crate::launch`] for an unclean
+    /// shutdown; `None` skips the check
+    pub shutdown_sentinel: Option<PathBuf>,
+    /// Bytes the database may grow to, for the `dex_storageInfo` estimate; `None` sets no budget
+    pub disk_budget: Option<u64>,
+    /// How EVM RPC state reads are answered while the node is behind its peers
+    pub sync_gate: SyncGate,
+    /// Give addresses an empty EVM account when their DexVM counter is first set
+    pub create_evm_accounts: bool,
+    /// Per-peer limits and workers for transactions gossiped to a validator
+    pub tx_ingest: TxIngestConfig,
+    /// Validators whose turns synced headers are checked against; a fullnode needs at least one
+    pub validators: Vec<Address>,
 }
 
 impl Default for NodeConfig {
@@ -35,20 +98,58 @@ impl Default for NodeConfig {
         Self {
             chain_id: 1,
             datadir: PathBuf::from("./data"),
+            ephemeral: false,
             evm_rpc_port: 8545,
             dexvm_rpc_port: 9845,
+            storage_retry: StorageRetryPolicy::default(),
+            invariant_mode: InvariantMode::default(),
+            fee_policy: FeePolicy::default(),
+            block_limits: BlockLimits::default(),
+            block_traces: None,
+            dev: false,
+            rpc_audit: None,
+            production_watchdog: Some(WatchdogConfig::default()),
+            backups: None,
+            backup_interval: None,
+            genesis: None,
+            tx_timeout: None,
+            slow_tx_threshold: None,
+            mempool: MempoolConfig::default(),
+            webhooks: WebhookConfig::default(),
+            webhook_urls: Vec::new(),
+            shutdown_sentinel: None,
+            disk_budget: None,
+            sync_gate: SyncGate::default(),
+            create_evm_accounts: false,
+            tx_ingest: TxIngestConfig::default(),
+            validators: Vec::new(),
         }
     }
 }
 
+impl NodeConfig {
+    /// Open the database this configuration points at
+    fn open_storage(&self) -> Arc<DualvmStorage> {
+        let storage = if self.ephemeral {
+            DualvmStorage::in_memory().expect("Failed to initialize in-memory database")
+        } else {
+            DualvmStorage::new(&self.datadir).expect("Failed to initialize MDBX database")
+        };
+        Arc::new(storage)
+    }
+}
+
 /// Dual VM node
 pub struct DualVmNode {
     config: NodeConfig,
     executor: DualVmExecutor,
     dexvm_executor: Arc<RwLock<DexExecutor>>,
+    dexvm_pool: Arc<DexVmPool>,
     consensus: Option<PoaConsensus>,
     storage: Arc<DualvmStorage>,
     evm_rpc_server: Option<Arc<EvmRpcServer>>,
+    status: Arc<NodeStatus>,
+    webhooks: Arc<WebhookDispatcher>,
 }
 
 impl DualVmNode {
@@ -58,18 +159,29 @@ impl DualVmNode {
     }
 
     /// Create node with configuration
+    ///
+    /// Applies `config.genesis` if set, like [`Self::with_config_and_chain_genesis`].
     pub fn with_config(config: NodeConfig) -> Self {
-        let storage = Arc::new(
-            DualvmStorage::new(&config.datadir).expect("Failed to initialize MDBX database"),
-        );
+        if let Some(genesis) = config.genesis.clone() {
+            return Self::with_config_and_chain_genesis(config, &genesis);
+        }
+        let storage = config.open_storage();
 
         // Create EVM executor backed by the shared StateStore
         let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(
             config.chain_id,
-            Arc::clone(&storage.state),
+            storage.state.clone(),
         )));
         let dexvm_executor = Arc::new(RwLock::new(DexExecutor::new(DexVmState::default())));
-        let executor = DualVmExecutor::new(evm_executor, Arc::clone(&dexvm_executor));
+        let mut executor = DualVmExecutor::new(evm_executor, Arc::clone(&dexvm_executor));
+        executor.set_fee_policy(config.fee_policy.clone());
+        executor.set_tracing(config.block_traces.is_some());
+        executor.set_slow_tx_threshold(config.slow_tx_threshold);
+        executor.set_create_evm_accounts(config.create_evm_accounts);
+        let status = Arc::new(NodeStatus::new());
+        executor.set_tx_times(Arc::clone(status.tx_execution()));
+        status.storage_growth().set_budget(config.disk_budget);
+        let webhooks = Arc::new(WebhookDispatcher::new(config.webhooks));
 
         if storage.blocks.block_count() == 0 {
             let genesis = StoredBlock::genesis(config.chain_id);
@@ -77,7 +189,17 @@ impl DualVmNode {
             tracing::info!("Created genesis block");
         }
 
-        Self { config, executor, dexvm_executor, consensus: None, storage, evm_rpc_server: None }
+        Self {
+            config,
+            executor,
+            dexvm_executor,
+            dexvm_pool: Arc::new(DexVmPool::default()),
+            consensus: None,
+            storage,
+            evm_rpc_server: None,
+            status,
+            webhooks,
+        }
     }
 
     /// Create dual VM node with genesis allocation
@@ -92,23 +214,37 @@ impl DualVmNode {
         datadir: PathBuf,
     ) -> Self {
         let config = NodeConfig { chain_id, datadir, ..Default::default() };
+        Self::with_config_and_genesis(config, genesis_alloc)
+    }
 
-        let storage = Arc::new(
-            DualvmStorage::new(&config.datadir).expect("Failed to initialize MDBX database"),
-        );
+    /// Create node with configuration and genesis allocation
+    ///
+    /// The allocation is only applied when the database is new.
+    pub fn with_config_and_genesis(
+        config: NodeConfig,
+        genesis_alloc: HashMap<Address, U256>,
+    ) -> Self {
+        let genesis = Genesis::with_balances(config.chain_id, genesis_alloc);
+        Self::with_config_and_chain_genesis(config, &genesis)
+    }
+
+    /// Create node with configuration and a genesis file
+    ///
+    /// The genesis block and allocations are only written when the database is new.
+    pub fn with_config_and_chain_genesis(config: NodeConfig, genesis: &Genesis) -> Self {
+        let chain_id = config.chain_id;
+        let storage = config.open_storage();
 
         if storage.is_new_database() {
             tracing::info!("New database detected, initializing genesis state");
-            storage
-                .state
-                .init_genesis(genesis_alloc)
-                .expect("Failed to init genesis state");
-
-            let mut genesis = StoredBlock::genesis(chain_id);
-            genesis.evm_state_root = storage.state.state_root();
-            genesis.combined_state_root = genesis.evm_state_root;
-            storage.blocks.store_block(genesis).expect("Failed to store genesis block");
-            tracing::info!("Created genesis block with initial allocations");
+            genesis.apply(storage.state.as_ref()).expect("Failed to init genesis state");
+
+            let mut block = genesis.block();
+            block.evm_state_root = storage.state.state_root();
+            block.combined_state_root = block.evm_state_root;
+            let hash = block.hash;
+            storage.blocks.store_block(block).expect("Failed to store genesis block");
+            tracing::info!("Created genesis block {} with initial allocations", hash);
         } else {
             tracing::info!(
                 "Existing database detected, loading state. Latest block: {}",
@@ -120,7 +256,7 @@ impl DualVmNode {
         // No need to manually load accounts - StateStore handles persistence
         let evm_executor = Arc::new(RwLock::new(SimpleEvmExecutor::new(
             chain_id,
-            Arc::clone(&storage.state),
+            storage.state.clone(),
         )));
         tracing::info!("EVM executor initialized with {} accounts",
             storage.state.all_accounts().len());
@@ -129,17 +265,35 @@ impl DualVmNode {
         let dexvm_executor = if storage.is_new_database() {
             Arc::new(RwLock::new(DexExecutor::new(DexVmState::default())))
         } else {
-            let mut dexvm_state = DexVmState::new();
-            let counters = storage.state.all_counters();
-            for (address, value) in counters {
-                dexvm_state.set_counter(address, value);
-            }
-            tracing::info!("Loaded {} DexVM counters from storage", dexvm_state.account_count());
+            let dexvm_state = load_dexvm_state(storage.state.as_ref());
+            tracing::info!(
+                "Loaded {} DexVM counters and {} labeled counters from storage",
+                dexvm_state.account_count(),
+                dexvm_state.all_labeled_counters().len()
+            );
             Arc::new(RwLock::new(DexExecutor::new(dexvm_state)))
         };
-        let executor = DualVmExecutor::new(evm_executor, Arc::clone(&dexvm_executor));
+        let mut executor = DualVmExecutor::new(evm_executor, Arc::clone(&dexvm_executor));
+        executor.set_fee_policy(config.fee_policy.clone());
+        executor.set_tracing(config.block_traces.is_some());
+        executor.set_slow_tx_threshold(config.slow_tx_threshold);
+        executor.set_create_evm_accounts(config.create_evm_accounts);
+        let status = Arc::new(NodeStatus::new());
+        executor.set_tx_times(Arc::clone(status.tx_execution()));
+        status.storage_growth().set_budget(config.disk_budget);
+        let webhooks = Arc::new(WebhookDispatcher::new(config.webhooks));
 
-        Self { config, executor, dexvm_executor, consensus: None, storage, evm_rpc_server: None }
+        Self {
+            config,
+            executor,
+            dexvm_executor,
+            dexvm_pool: Arc::new(DexVmPool::default()),
+            consensus: None,
+            storage,
+            evm_rpc_server: None,
+            status,
+            webhooks,
+        }
     }
 
     /// Create node with full configuration
@@ -151,6 +305,7 @@ impl DualVmNode {
     ) -> Self {
         let mut node = Self::with_genesis_and_datadir(chain_id, genesis_alloc, datadir);
         if let Some(config) = poa_config {
+            node.status.set_validator(config.validator);
             node.consensus = Some(PoaConsensus::new(config));
         }
         node
@@ -158,11 +313,102 @@ impl DualVmNode {
 
     /// Set POA consensus configuration
     pub fn set_consensus(&mut self, config: PoaConfig, last_block_hash: B256) {
+        self.status.set_validator(config.validator);
+        if let Some(server) = &self.evm_rpc_server {
+            server.set_validator(config.validator);
+        }
         let mut consensus = PoaConsensus::new(config);
         consensus.set_last_block_hash(last_block_hash);
         self.consensus = Some(consensus);
     }
 
+    /// Set how block production retries failed storage writes
+    pub fn set_storage_retry_policy(&mut self, policy: StorageRetryPolicy) {
+        self.config.storage_retry = policy;
+    }
+
+    /// Retry policy for persisting produced blocks
+    pub fn storage_retry_policy(&self) -> StorageRetryPolicy {
+        self.config.storage_retry
+    }
+
+    /// Set how produced blocks are checked against state invariants
+    pub fn set_invariant_mode(&mut self, mode: InvariantMode) {
+        self.config.invariant_mode = mode;
+    }
+
+    /// Post-block invariant checking mode
+    pub fn invariant_mode(&self) -> InvariantMode {
+        self.config.invariant_mode
+    }
+
+    /// Set the size caps of produced and synced blocks
+    pub fn set_block_limits(&mut self, limits: BlockLimits) {
+        self.config.block_limits = limits;
+    }
+
+    /// Size caps of produced and synced blocks
+    pub fn block_limits(&self) -> BlockLimits {
+        self.config.block_limits
+    }
+
+    /// Longest a transaction of a block we build may run before it is dropped
+    pub fn tx_timeout(&self) -> Option<Duration> {
+        self.config.tx_timeout
+    }
+
+    /// Per-peer limits and workers for gossiped transactions
+    pub fn tx_ingest(&self) -> TxIngestConfig {
+        self.config.tx_ingest
+    }
+
+    /// Validators whose turns synced headers are checked against
+    pub fn validators(&self) -> &[Address] {
+        &self.config.validators
+    }
+
+    /// Set how a validator watches its block production, or disable the watchdog with `None`
+    pub fn set_production_watchdog(&mut self, watchdog: Option<WatchdogConfig>) {
+        self.config.production_watchdog = watchdog;
+    }
+
+    /// Block production watchdog settings, if enabled
+    pub fn production_watchdog(&self) -> Option<WatchdogConfig> {
+        self.config.production_watchdog
+    }
+
+    /// Trace every produced block into `writer`, or stop tracing with `None`
+    pub fn set_block_traces(&mut self, writer: Option<TraceWriter>) {
+        self.executor.set_tracing(writer.is_some());
+        self.config.block_traces = writer;
+    }
+
+    /// Where produced blocks are traced to, if tracing is enabled
+    pub fn block_traces(&self) -> Option<&TraceWriter> {
+        self.config.block_traces.as_ref()
+    }
+
+    /// Back the database up into `backups` every `interval`, and on `admin_backup`
+    pub fn set_backups(&mut self, backups: Option<Backups>, interval: Option<Duration>) {
+        self.config.backups = backups.map(Arc::new);
+        self.config.backup_interval = interval;
+    }
+
+    /// Where database backups are written, if enabled
+    pub fn backups(&self) -> Option<&Arc<Backups>> {
+        self.config.backups.as_ref()
+    }
+
+    /// How often a backup is taken, if scheduled
+    pub fn backup_interval(&self) -> Option<Duration> {
+        self.config.backup_interval
+    }
+
+    /// Audit EVM RPC calls into `log` once the server starts, or stop with `None`
+    pub fn set_rpc_audit(&mut self, log: Option<RpcAuditLog>) {
+        self.config.rpc_audit = log;
+    }
+
     /// Get executor reference
     pub fn executor(&self) -> &DualVmExecutor {
         &self.executor
@@ -174,13 +420,13 @@ impl DualVmNode {
     }
 
     /// Get block store reference
-    pub fn block_store(&self) -> &BlockStore {
-        &self.storage.blocks
+    pub fn block_store(&self) -> &dyn BlockWriter {
+        self.storage.blocks.as_ref()
     }
 
     /// Get state store reference
-    pub fn state_store(&self) -> &StateStore {
-        &self.storage.state
+    pub fn state_store(&self) -> &dyn StateWriter {
+        self.storage.state.as_ref()
     }
 
     /// Get storage reference
@@ -188,13 +434,58 @@ impl DualVmNode {
         &self.storage
     }
 
-    /// Start DexVM REST API service
-    pub async fn start_dexvm_rpc(&self, port: u16) -> eyre::Result<JoinHandle<()>> {
-        let api = DexVmApi::new(Arc::clone(&self.dexvm_executor));
+    /// Remove every block above `number` and notify RPC subscriptions
+    ///
+    /// Only block data is removed; account and counter state must be rolled
+    /// back by the caller before blocks are executed on top again.
+    pub fn unwind_to(&self, number: u64) -> eyre::Result<Vec<UnwoundBlock>> {
+        let unwound = self.storage.blocks.unwind_to(number)?;
+        if let Some(server) = &self.evm_rpc_server {
+            server.notify_unwind(&unwound);
+        }
+        Ok(unwound)
+    }
+
+    /// Get native DexVM transaction queue
+    pub fn dexvm_pool(&self) -> &Arc<DexVmPool> {
+        &self.dexvm_pool
+    }
+
+    /// Get node status shared with the REST API
+    pub fn status(&self) -> &Arc<NodeStatus> {
+        &self.status
+    }
+
+    /// Get the dispatcher that delivers counter changes to webhooks
+    pub fn webhooks(&self) -> &Arc<WebhookDispatcher> {
+        &self.webhooks
+    }
+
+    /// Set webhook delivery retries and backoff
+    ///
+    /// Replaces the dispatcher, so call it before registering webhooks or
+    /// starting the REST API.
+    pub fn set_webhook_config(&mut self, config: WebhookConfig) {
+        self.config.webhooks = config;
+        self.webhooks = Arc::new(WebhookDispatcher::new(config));
+    }
+
+    /// Start DexVM REST API service, returning the address it listens on
+    pub async fn start_dexvm_rpc(&self, port: u16) -> eyre::Result<(JoinHandle<()>, SocketAddr)> {
+        let api = DexVmApi::new(Arc::clone(&self.dexvm_executor))
+            .with_pool(Arc::clone(&self.dexvm_pool))
+            .with_chain_id(self.config.chain_id)
+            .with_status(Arc::clone(&self.status))
+            .with_storage(Arc::clone(&self.storage))
+            .with_webhooks(Arc::clone(&self.webhooks));
+        let api = match &self.evm_rpc_server {
+            Some(server) => api.with_evm_rpc(Arc::clone(server)),
+            None => api,
+        };
         let app = api.routes();
 
-        let addr = format!("0.0.0.0:{}", port);
-        let listener = tokio::net::TcpListener::bind(&addr).await?;
+        let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+        let addr = listener.local_addr()?;
 
         tracing::info!("DexVM REST API listening on {}", addr);
 
@@ -204,21 +495,45 @@ impl DualVmNode {
             }
         });
 
-        Ok(handle)
+        Ok((handle, addr))
     }
 
-    /// Start EVM JSON-RPC service
-    pub async fn start_evm_rpc(&mut self, port: u16) -> eyre::Result<ServerHandle> {
+    /// Start EVM JSON-RPC service, returning the address it listens on
+    pub async fn start_evm_rpc(&mut self, port: u16) -> eyre::Result<(ServerHandle, SocketAddr)> {
         // Use the shared block_store and state_store from storage
         let state_store = Arc::clone(&self.storage.state);
         let block_store = Arc::clone(&self.storage.blocks);
 
-        let (handle, server) =
-            start_evm_rpc_server(self.config.chain_id, state_store, block_store, port).await?;
+        let (handle, server, addr) = start_evm_rpc_server(
+            self.config.chain_id,
+            state_store,
+            block_store,
+            port,
+            self.config.rpc_audit.clone(),
+        )
+        .await?;
+        server.set_storage(Arc::clone(&self.storage));
+        server.set_dexvm_executor(Arc::clone(&self.dexvm_executor));
+        server.set_dexvm_pool(Arc::clone(&self.dexvm_pool));
+        server.set_dev_mode(self.config.dev);
+        server.set_node_status(Arc::clone(&self.status));
+        server.set_mempool_config(self.config.mempool);
+        server.set_sync_gate(self.config.sync_gate);
+        if let Some(backups) = &self.config.backups {
+            server.set_backups(Arc::clone(backups));
+        }
+        server.set_client_features(ClientFeatures {
+            receipts_persisted: !self.config.ephemeral,
+            traces_available: self.config.block_traces.is_some(),
+            ..Default::default()
+        });
+        if let Some(validator) = self.status.validator() {
+            server.set_validator(validator);
+        }
 
         self.evm_rpc_server = Some(server);
 
-        Ok(handle)
+        Ok((handle, addr))
     }
 
     /// Get EVM RPC server reference
@@ -307,9 +622,16 @@ impl DualVmNode {
                                     tracing::error!("Failed to persist DexVM counter for {}: {}", address, e);
                                 }
                             }
+                            let labeled = labeled_counter_writes(
+                                dexvm_exec.state(),
+                                self.storage.state.as_ref(),
+                            );
+                            if let Err(e) = self.storage.state.set_labeled_counters(&labeled) {
+                                tracing::error!("Failed to persist labeled DexVM counters: {}", e);
+                            }
                         }
 
-                        consensus.finalize_block(result.combined_state_root);
+                        consensus.finalize_block(block_hash);
 
                         tracing::info!(
                             "Block {} finalized and stored, hash={:?}",
@@ -334,6 +656,21 @@ impl Default for DualVmNode {
     }
 }
 
+/// Rebuild the DexVM state from stored main and labeled counters and nonces
+pub(crate) fn load_dexvm_state(state: &dyn StateReader) -> DexVmState {
+    let mut dexvm_state = DexVmState::new();
+    for (address, value) in state.all_counters() {
+        dexvm_state.set_counter(address, value);
+    }
+    for ((address, label), value) in state.all_labeled_counters() {
+        dexvm_state.set_labeled_counter(address, label, value);
+    }
+    for (address, nonce) in state.all_dexvm_nonces() {
+        dexvm_state.set_nonce(address, nonce);
+    }
+    dexvm_state
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -341,9 +678,7 @@ mod tests {
 
     #[test]
     fn test_node_creation() {
-        let dir = tempdir().unwrap();
-        let config =
-            NodeConfig { chain_id: 1, datadir: dir.path().to_path_buf(), ..Default::default() };
+        let config = NodeConfig { chain_id: 1, ephemeral: true, ..Default::default() };
         let node = DualVmNode::with_config(config);
         assert!(node.executor.dexvm_executor().read().is_ok());
     }
@@ -380,11 +715,45 @@ mod tests {
         assert_eq!(balance, U256::from(1000));
     }
 
+    #[test]
+    fn test_ephemeral_node() {
+        use alloy_primitives::address;
+
+        let dir = tempdir().unwrap();
+        let datadir = dir.path().join("db");
+        let alice = address!("1111111111111111111111111111111111111111");
+        let config = NodeConfig {
+            chain_id: 13337,
+            datadir: datadir.clone(),
+            ephemeral: true,
+            ..Default::default()
+        };
+        let alloc = HashMap::from([(alice, U256::from(1000))]);
+        let node = DualVmNode::with_config_and_genesis(config, alloc);
+
+        assert_eq!(node.state_store().get_balance(&alice), U256::from(1000));
+        assert_eq!(node.block_store().block_count(), 1);
+        assert!(!datadir.exists());
+    }
+
+    #[test]
+    fn test_genesis_block_from_genesis_file() {
+        let genesis = Genesis::from_json(include_str!("../../../genesis.json")).unwrap();
+        let config = NodeConfig { chain_id: 13337, ephemeral: true, ..Default::default() };
+        let node = DualVmNode::with_config_and_chain_genesis(config, &genesis);
+
+        let block = node.block_store().get_block_by_number(0).unwrap();
+        assert_eq!(block.hash, genesis.hash());
+        assert_eq!(block.evm_state_root, node.state_store().state_root());
+        assert!(node.block_store().check_genesis(13337, genesis.hash()).is_ok());
+
+        let (address, account) = genesis.alloc.iter().next().unwrap();
+        assert_eq!(node.state_store().get_balance(address), account.balance);
+    }
+
     #[tokio::test]
     async fn test_start_rpc() {
-        let dir = tempdir().unwrap();
-        let config =
-            NodeConfig { chain_id: 1, datadir: dir.path().to_path_buf(), ..Default::default() };
+        let config = NodeConfig { chain_id: 1, ephemeral: true, ..Default::default() };
         let node = DualVmNode::with_config(config);
 
         let port = 0;
@@ -392,7 +761,8 @@ mod tests {
 
         assert!(handle.is_ok());
 
-        if let Ok(h) = handle {
+        if let Ok((h, addr)) = handle {
+            assert_ne!(addr.port(), 0);
             h.abort();
         }
     }
@@ -0,0 +1,376 @@
+//! Node modes and the background tasks each one runs
+//!
+//! RPC servers are started by the caller; [`NodeTasks::spawn`] wires the rest
+//! for a [`NodeMode`]: block production, its watchdog and the block-serving P2P
+//! handler for a validator, block and DexVM state sync and transaction forwarding for a
+//! fullnode, nothing for an RPC-only node. A validator sharing the chain with
+//! others also fetches their blocks for its consensus loop to import.
+//! Validators and fullnodes both serve their DexVM state over `dexvm/1`. Every
+//! mode with P2P tracks peers for `/status`, and every mode with the EVM RPC
+//! server expires old pending transactions and, when it forwards transactions,
+//! re-broadcasts those still pending. Scheduled database backups and sampling
+//! of the database size run in every mode.
+
+use crate::{
+    dexvm_sync::DexVmStateSync,
+    node::DualVmNode,
+    producer::run_consensus_loop_with_p2p,
+    sync::{
+        run_fullnode_sync, run_status_tracker, run_transaction_forwarder,
+        run_validator_p2p_handler, BlockImport,
+    },
+    tx_ingest::TxIngest,
+    watchdog::run_production_watchdog,
+};
+use dex_p2p::P2pHandle;
+use dex_rpc::{EvmRpcServer, NodeStatus};
+use dex_storage::{Backups, DualvmStorage};
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
+use tokio::{
+    sync::{mpsc, RwLock},
+    task::JoinHandle,
+};
+
+/// Transactions queued for forwarding to peers before submissions wait
+const TX_FORWARD_CAPACITY: usize = 256;
+
+/// How often expired pending transactions are dropped and the rest re-broadcast
+const POOL_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often the database size is sampled for its growth estimate
+const STORAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// What a node does besides serving RPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeMode {
+    /// Produces blocks and serves them to peers
+    Validator,
+    /// Syncs blocks from peers and forwards submitted transactions to them
+    FullNode,
+    /// Serves RPC from its own database without producing or syncing blocks
+    RpcOnly,
+}
+
+impl NodeMode {
+    /// Mode implied by `--enable-consensus` and whether P2P is enabled
+    pub fn from_flags(enable_consensus: bool, p2p_enabled: bool) -> Self {
+        match (enable_consensus, p2p_enabled) {
+            (true, _) => Self::Validator,
+            (false, true) => Self::FullNode,
+            (false, false) => Self::RpcOnly,
+        }
+    }
+
+    /// Whether the node produces blocks and needs consensus configured
+    pub fn produces_blocks(self) -> bool {
+        self == Self::Validator
+    }
+
+    /// Whether the node can run without P2P
+    pub fn p2p_optional(self) -> bool {
+        self != Self::FullNode
+    }
+}
+
+impl FromStr for NodeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "validator" => Ok(Self::Validator),
+            "fullnode" => Ok(Self::FullNode),
+            "rpc-only" => Ok(Self::RpcOnly),
+            other => Err(format!(
+                "unknown mode '{}', expected 'validator', 'fullnode' or 'rpc-only'",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for NodeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Validator => write!(f, "validator"),
+            Self::FullNode => write!(f, "fullnode"),
+            Self::RpcOnly => write!(f, "rpc-only"),
+        }
+    }
+}
+
+/// Background tasks of a running node, aborted together on shutdown or drop
+pub struct NodeTasks {
+    mode: NodeMode,
+    /// Kept alive for modes whose tasks do not own it
+    node: Option<DualVmNode>,
+    block_production: Option<JoinHandle<eyre::Result<()>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl NodeTasks {
+    /// Spawn the tasks of `mode`
+    ///
+    /// A validator needs consensus configured with [`DualVmNode::set_consensus`];
+    /// a fullnode needs `p2p` and the validator set to check synced blocks
+    /// against. Transactions are forwarded and sync notifies subscribers only if
+    /// the EVM RPC server was started first.
+    pub fn spawn(node: DualVmNode, mode: NodeMode, p2p: Option<P2pHandle>) -> eyre::Result<Self> {
+        if !mode.p2p_optional() && p2p.is_none() {
+            return Err(eyre::eyre!("A {} node needs P2P", mode));
+        }
+        if mode == NodeMode::FullNode && node.validators().is_empty() {
+            return Err(eyre::eyre!("A fullnode needs the validator set to check synced blocks"));
+        }
+
+        let mut tasks = Vec::new();
+        if let Some(p2p) = &p2p {
+            tasks.push(tokio::spawn(run_status_tracker(p2p.clone(), Arc::clone(node.status()))));
+        }
+        if let Some(rpc_server) = node.evm_rpc_server() {
+            tasks.push(tokio::spawn(run_pool_maintenance(Arc::clone(rpc_server))));
+        }
+        if let (Some(backups), Some(interval)) = (node.backups(), node.backup_interval()) {
+            tasks.push(tokio::spawn(run_scheduled_backups(
+                Arc::clone(backups),
+                Arc::clone(node.storage()),
+                interval,
+            )));
+        }
+        if !node.storage().is_in_memory() {
+            tasks.push(tokio::spawn(run_storage_sampler(
+                Arc::clone(node.status()),
+                Arc::clone(node.storage()),
+            )));
+        }
+
+        match mode {
+            NodeMode::Validator => {
+                let consensus = node
+                    .consensus()
+                    .cloned()
+                    .ok_or_else(|| eyre::eyre!("A validator needs consensus configured"))?;
+                let validators = consensus.config().validators.clone();
+                match node.production_watchdog() {
+                    Some(watchdog) => tasks.push(tokio::spawn(run_production_watchdog(
+                        consensus,
+                        Arc::clone(node.status()),
+                        watchdog,
+                    ))),
+                    None => tasks.push(consensus.start()),
+                }
+
+                // Serve stored blocks and DexVM state to syncing peers, and fetch the
+                // blocks of the other validators
+                let mut imports = None;
+                if let Some(p2p) = p2p.clone() {
+                    let block_store = Arc::clone(&node.storage().blocks);
+                    let tx_ingest = node
+                        .evm_rpc_server()
+                        .map(|server| TxIngest::spawn(node.tx_ingest(), Arc::clone(server)));
+                    let dexvm = dexvm_state_sync(&node, p2p.clone());
+                    let import = (validators.len() > 1).then(|| {
+                        let (blocks, received) = mpsc::unbounded_channel();
+                        imports = Some(received);
+                        BlockImport { validators, limits: node.block_limits(), blocks }
+                    });
+                    tasks.push(tokio::spawn(async move {
+                        let handler =
+                            run_validator_p2p_handler(p2p, block_store, tx_ingest, dexvm, import);
+                        if let Err(e) = handler.await {
+                            tracing::error!("Validator P2P handler error: {}", e);
+                        }
+                    }));
+                }
+
+                let last_broadcast_block = Arc::new(RwLock::new(0u64));
+                let block_production = tokio::spawn(run_consensus_loop_with_p2p(
+                    node,
+                    p2p,
+                    last_broadcast_block,
+                    imports,
+                ));
+                Ok(Self { mode, node: None, block_production: Some(block_production), tasks })
+            }
+            NodeMode::FullNode => {
+                let p2p = p2p.expect("checked above");
+
+                // Forward transactions submitted here to peers
+                let (tx_sender, tx_receiver) = mpsc::channel(TX_FORWARD_CAPACITY);
+                if let Some(rpc_server) = node.evm_rpc_server() {
+                    rpc_server.set_tx_broadcast_sender(tx_sender);
+                    tracing::info!("Transaction forwarding enabled for fullnode");
+                }
+                tasks.push(tokio::spawn(run_transaction_forwarder(p2p.clone(), tx_receiver)));
+
+                let block_store = Arc::clone(&node.storage().blocks);
+                let evm_rpc_server = node.evm_rpc_server().cloned();
+                let limits = node.block_limits();
+                let validators = node.validators().to_vec();
+                let dexvm = dexvm_state_sync(&node, p2p.clone());
+                tasks.push(tokio::spawn(async move {
+                    let sync = run_fullnode_sync(
+                        p2p,
+                        block_store,
+                        evm_rpc_server,
+                        limits,
+                        validators,
+                        dexvm,
+                    );
+                    if let Err(e) = sync.await {
+                        tracing::error!("Fullnode sync error: {}", e);
+                    }
+                }));
+                Ok(Self { mode, node: Some(node), block_production: None, tasks })
+            }
+            NodeMode::RpcOnly => {
+                Ok(Self { mode, node: Some(node), block_production: None, tasks })
+            }
+        }
+    }
+
+    /// Mode the tasks were spawned for
+    pub fn mode(&self) -> NodeMode {
+        self.mode
+    }
+
+    /// The node, unless block production owns it
+    pub fn node(&self) -> Option<&DualVmNode> {
+        self.node.as_ref()
+    }
+
+    /// Wait until block production stops, which only happens on a storage failure
+    ///
+    /// Never returns for modes that do not produce blocks.
+    pub async fn wait_for_exit(&mut self) -> eyre::Result<()> {
+        let Some(block_production) = &mut self.block_production else {
+            return std::future::pending().await;
+        };
+        let joined = block_production.await;
+        self.block_production = None;
+        match joined {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(e.wrap_err("Block production stopped")),
+            Err(e) => Err(eyre::eyre!("Consensus loop panicked: {}", e)),
+        }
+    }
+
+    /// Abort every task
+    pub fn shutdown(&mut self) {
+        if let Some(block_production) = self.block_production.take() {
+            block_production.abort();
+        }
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+/// DexVM state sync of `node` over `p2p`
+fn dexvm_state_sync(node: &DualVmNode, p2p: P2pHandle) -> DexVmStateSync {
+    DexVmStateSync::new(
+        p2p,
+        node.executor().dexvm_executor(),
+        Arc::clone(&node.storage().state),
+        Arc::clone(&node.storage().blocks),
+    )
+}
+
+/// Drop expired pending transactions, which no block drains while production is stalled,
+/// and re-broadcast the rest, so peers that connected since their first broadcast get them
+async fn run_pool_maintenance(rpc_server: Arc<EvmRpcServer>) {
+    let mut interval = tokio::time::interval(POOL_MAINTENANCE_INTERVAL);
+    loop {
+        interval.tick().await;
+        rpc_server.prune_pending_transactions();
+        let queued = rpc_server.rebroadcast_pending_transactions();
+        if queued > 0 {
+            tracing::debug!("Re-broadcasting {} pending transactions", queued);
+        }
+    }
+}
+
+/// Sample the database size every [`STORAGE_SAMPLE_INTERVAL`], starting now
+async fn run_storage_sampler(status: Arc<NodeStatus>, storage: Arc<DualvmStorage>) {
+    let mut interval = tokio::time::interval(STORAGE_SAMPLE_INTERVAL);
+    loop {
+        interval.tick().await;
+        status.storage_growth().sample(&storage);
+    }
+}
+
+/// Back the database up every `interval`, starting one interval after startup
+async fn run_scheduled_backups(
+    backups: Arc<Backups>,
+    storage: Arc<DualvmStorage>,
+    interval: Duration,
+) {
+    tracing::info!(
+        "Backing the database up to {} every {}s",
+        backups.dir().display(),
+        interval.as_secs()
+    );
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        ticks.tick().await;
+        let (backups, storage) = (Arc::clone(&backups), Arc::clone(&storage));
+        match tokio::task::spawn_blocking(move || backups.backup(&storage)).await {
+            Ok(Ok(report)) => tracing::info!(
+                "Backed up block {} to {} ({} bytes in {}ms, {} old backups removed)",
+                report.block_number,
+                report.path.display(),
+                report.size_bytes,
+                report.duration_ms,
+                report.removed
+            ),
+            Ok(Err(e)) => tracing::warn!("Scheduled backup failed: {:#}", e),
+            Err(e) => tracing::warn!("Scheduled backup task failed: {}", e),
+        }
+    }
+}
+
+impl Drop for NodeTasks {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::NodeConfig;
+
+    #[test]
+    fn test_mode_from_flags_and_str() {
+        assert_eq!(NodeMode::from_flags(true, false), NodeMode::Validator);
+        assert_eq!(NodeMode::from_flags(false, true), NodeMode::FullNode);
+        assert_eq!(NodeMode::from_flags(false, false), NodeMode::RpcOnly);
+
+        for mode in [NodeMode::Validator, NodeMode::FullNode, NodeMode::RpcOnly] {
+            assert_eq!(mode.to_string().parse::<NodeMode>(), Ok(mode));
+        }
+        assert!("archive".parse::<NodeMode>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spawn_checks_requirements() {
+        let config = || NodeConfig { ephemeral: true, ..Default::default() };
+
+        // A fullnode cannot sync without P2P, a validator cannot produce without consensus
+        let fullnode =
+            NodeTasks::spawn(DualVmNode::with_config(config()), NodeMode::FullNode, None);
+        assert!(fullnode.is_err());
+        let validator =
+            NodeTasks::spawn(DualVmNode::with_config(config()), NodeMode::Validator, None);
+        assert!(validator.is_err());
+
+        // Nor would it know whose blocks to accept without a validator set
+        let p2p = dex_p2p::P2pService::new(dex_p2p::P2pConfig::default().with_port(0)).handle();
+        let node = DualVmNode::with_config(config());
+        assert!(NodeTasks::spawn(node, NodeMode::FullNode, Some(p2p)).is_err());
+
+        let rpc_only =
+            NodeTasks::spawn(DualVmNode::with_config(config()), NodeMode::RpcOnly, None).unwrap();
+        assert_eq!(rpc_only.mode(), NodeMode::RpcOnly);
+        assert!(rpc_only.node().is_some());
+    }
+}
@@ -0,0 +1,125 @@
+//! Canonical transaction ordering for block building
+//!
+//! Each sender's transactions stay in nonce order. Across senders, the next
+//! transaction with the highest effective priority fee goes first; ties go to
+//! the lower sender address. Arrival order never matters, so validators with
+//! the same mempool build the same block.
+
+use alloy_consensus::Transaction;
+use alloy_primitives::Address;
+use dex_rpc::{PendingTransaction, BASE_FEE_PER_GAS};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, VecDeque},
+};
+
+/// Priority fee per gas paid to the block producer at the chain's base fee
+pub fn effective_tip(tx: &PendingTransaction) -> u128 {
+    tx.tx.effective_tip_per_gas(BASE_FEE_PER_GAS).unwrap_or(0)
+}
+
+/// Sort transactions into canonical block order
+pub fn canonical_order(txs: Vec<PendingTransaction>) -> Vec<PendingTransaction> {
+    let total = txs.len();
+
+    let mut by_sender: BTreeMap<Address, Vec<PendingTransaction>> = BTreeMap::new();
+    for tx in txs {
+        by_sender.entry(tx.from).or_default().push(tx);
+    }
+
+    let mut queues: BTreeMap<Address, VecDeque<PendingTransaction>> = by_sender
+        .into_iter()
+        .map(|(sender, mut txs)| {
+            txs.sort_by_key(|tx| (tx.tx.nonce(), tx.hash));
+            (sender, txs.into())
+        })
+        .collect();
+
+    // Heads of each sender queue, highest tip first, then lowest sender
+    let mut heads: BinaryHeap<(u128, Reverse<Address>)> = queues
+        .iter()
+        .filter_map(|(sender, queue)| {
+            queue.front().map(|tx| (effective_tip(tx), Reverse(*sender)))
+        })
+        .collect();
+
+    let mut ordered = Vec::with_capacity(total);
+    while let Some((_, Reverse(sender))) = heads.pop() {
+        let queue = queues.get_mut(&sender).expect("sender queue exists");
+        if let Some(tx) = queue.pop_front() {
+            ordered.push(tx);
+        }
+        if let Some(next) = queue.front() {
+            heads.push((effective_tip(next), Reverse(sender)));
+        }
+    }
+
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::TxLegacy;
+    use alloy_primitives::{address, Signature, TxKind, U256};
+    use reth_ethereum_primitives::TransactionSigned;
+
+    fn pending(from: Address, nonce: u64, gas_price: u128) -> PendingTransaction {
+        let tx = TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(Address::ZERO),
+                nonce,
+                gas_price,
+                gas_limit: 21_000,
+                value: U256::ZERO,
+                chain_id: Some(1),
+                ..Default::default()
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+        PendingTransaction::new(tx, from)
+    }
+
+    fn order_of(txs: &[PendingTransaction]) -> Vec<(Address, u64)> {
+        txs.iter().map(|tx| (tx.from, tx.tx.nonce())).collect()
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let alice = address!("1111111111111111111111111111111111111111");
+        let bob = address!("2222222222222222222222222222222222222222");
+        let base_fee = BASE_FEE_PER_GAS as u128;
+
+        let txs = vec![
+            pending(alice, 1, base_fee + 50),
+            pending(bob, 0, base_fee + 10),
+            pending(alice, 0, base_fee + 5),
+            pending(bob, 1, base_fee + 10),
+        ];
+
+        // Alice's high-fee nonce 1 must wait behind her low-fee nonce 0
+        assert_eq!(
+            order_of(&canonical_order(txs.clone())),
+            vec![(bob, 0), (bob, 1), (alice, 0), (alice, 1)]
+        );
+
+        // Arrival order does not change the result
+        let reversed: Vec<_> = txs.into_iter().rev().collect();
+        assert_eq!(
+            order_of(&canonical_order(reversed)),
+            vec![(bob, 0), (bob, 1), (alice, 0), (alice, 1)]
+        );
+    }
+
+    #[test]
+    fn test_equal_tips_order_by_sender() {
+        let alice = address!("1111111111111111111111111111111111111111");
+        let bob = address!("2222222222222222222222222222222222222222");
+        let gas_price = BASE_FEE_PER_GAS as u128 + 1;
+
+        let ordered =
+            canonical_order(vec![pending(bob, 0, gas_price), pending(alice, 0, gas_price)]);
+        assert_eq!(order_of(&ordered), vec![(alice, 0), (bob, 0)]);
+    }
+}
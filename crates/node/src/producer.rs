@@ -0,0 +1,1026 @@
+//! Block production loop
+//!
+//! Turns consensus proposals into executed, stored and broadcast blocks.
+//! Blocks of other validators, fetched by [`crate::sync`], are executed and
+//! committed the same way between our own; they must seal to the hash their
+//! proposer gave them, or the chains diverged and production stops.
+
+use crate::{
+    consensus::{BlockProposal, ProposalHandler},
+    executor::DualVmExecutionResult,
+    header::{block_header, header_hash},
+    inspector::BlockTrace,
+    invariants::{check_block, InvariantMode, StateSummary},
+    node::DualVmNode,
+    ordering::canonical_order,
+    roots::PendingRoots,
+    sync::{header_proposal, ImportedBlock, INVALID_BLOCK_BAN},
+};
+use alloy_consensus::{transaction::SignerRecoverable, Receipt};
+use alloy_primitives::{Address, B256};
+use dex_dexvm::DexVmState;
+use dex_p2p::{P2pHandle, PeerId, SessionCommand};
+use dex_rpc::{split_by_nonce, PendingTransaction};
+use dex_primitives::{
+    split_body_transactions, AccessSet, CounterChange, DexVmOperation, DexVmReceipt,
+    DexVmTransaction,
+};
+use dex_storage::{
+    BlockReader, StateReader, StoredAccessSet, StoredBlock, StoredDexVmOperation,
+    StoredDexVmReceipts, StoredLog, StoredReceipt,
+};
+use reth_ethereum_primitives::TransactionSigned;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::sync::{mpsc, RwLock};
+
+/// Maximum native DexVM transactions drained into a single block
+pub const MAX_NATIVE_DEXVM_TXS_PER_BLOCK: usize = 1_000;
+
+/// How block production reacts when persisting a finalized block fails
+///
+/// Writes are retried `max_retries` times; if they still fail the consensus
+/// loop stops instead of advancing past a block that was never stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageRetryPolicy {
+    /// Retries after the first failed attempt; 0 stops on the first failure
+    pub max_retries: u32,
+    /// Delay between attempts
+    pub retry_delay: Duration,
+}
+
+impl Default for StorageRetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 3, retry_delay: Duration::from_millis(100) }
+    }
+}
+
+/// Run a storage write, retrying failures according to the policy
+async fn retry_storage<T>(
+    policy: StorageRetryPolicy,
+    what: &str,
+    mut write: impl FnMut() -> eyre::Result<T>,
+) -> eyre::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match write() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_retries => {
+                attempt += 1;
+                tracing::warn!(
+                    "Failed to {} (attempt {}/{}): {}",
+                    what,
+                    attempt,
+                    policy.max_retries + 1,
+                    e
+                );
+                tokio::time::sleep(policy.retry_delay).await;
+            }
+            Err(e) => {
+                return Err(e.wrap_err(format!("Failed to {} after {} attempts", what, attempt + 1)))
+            }
+        }
+    }
+}
+
+/// Convert a recorded access set for storage
+fn stored_access_set(access_set: &AccessSet) -> StoredAccessSet {
+    StoredAccessSet {
+        accounts_read: access_set.accounts_read.iter().copied().collect(),
+        accounts_written: access_set.accounts_written.iter().copied().collect(),
+        slots_read: access_set.slots_read.iter().copied().collect(),
+        slots_written: access_set.slots_written.iter().copied().collect(),
+    }
+}
+
+/// Convert a block's execution receipts for storage, numbering logs across the block
+fn stored_receipts(
+    transactions: &[TransactionSigned],
+    receipts: &[Receipt],
+) -> Vec<(B256, StoredReceipt)> {
+    let mut next_log_index = 0;
+    transactions
+        .iter()
+        .zip(receipts)
+        .enumerate()
+        .map(|(index, (tx, receipt))| {
+            let stored = StoredReceipt {
+                success: receipt.status.coerce_status(),
+                cumulative_gas_used: receipt.cumulative_gas_used,
+                transaction_index: index as u64,
+                first_log_index: next_log_index,
+                logs: receipt
+                    .logs
+                    .iter()
+                    .map(|log| StoredLog {
+                        address: log.address,
+                        topics: log.data.topics().to_vec(),
+                        data: log.data.data.to_vec(),
+                    })
+                    .collect(),
+            };
+            next_log_index = stored.next_log_index();
+            (*tx.tx_hash(), stored)
+        })
+        .collect()
+}
+
+/// Convert a block's DexVM receipts for storage
+///
+/// Counter precompile calls have no DexVM receipt; the changes they made are
+/// stored as increments or decrements, in execution order with the receipts,
+/// so the stored operations account for every counter change of the block.
+fn stored_dexvm_receipts(
+    receipts: &[DexVmReceipt],
+    counter_changes: &[CounterChange],
+) -> StoredDexVmReceipts {
+    let stored = |receipt: &DexVmReceipt| {
+        let operation = receipt.operation?;
+        Some(StoredDexVmOperation {
+            tx_hash: receipt.tx_hash,
+            address: receipt.from,
+            op: operation.code(),
+            amount: operation.amount(),
+            success: receipt.success,
+            new_value: receipt.new_counter,
+        })
+    };
+    let receipt_hashes: HashSet<B256> = receipts.iter().map(|receipt| receipt.tx_hash).collect();
+    let mut receipts = receipts.iter();
+    let mut operations = Vec::new();
+    // Both lists are in execution order; a change with a receipt marks how far the receipts got
+    for change in counter_changes {
+        if receipt_hashes.contains(&change.tx_hash) {
+            for receipt in receipts.by_ref() {
+                operations.extend(stored(receipt));
+                if receipt.tx_hash == change.tx_hash {
+                    break;
+                }
+            }
+            continue;
+        }
+        let operation = if change.new_value >= change.old_value {
+            DexVmOperation::Increment(change.new_value - change.old_value)
+        } else {
+            DexVmOperation::Decrement(change.old_value - change.new_value)
+        };
+        operations.push(StoredDexVmOperation {
+            tx_hash: change.tx_hash,
+            address: change.address,
+            op: operation.code(),
+            amount: operation.amount(),
+            success: true,
+            new_value: change.new_value,
+        });
+    }
+    operations.extend(receipts.filter_map(stored));
+    StoredDexVmReceipts { operations }
+}
+
+/// Write a finalized block with its transactions, receipts, access sets, stats and counters
+///
+/// The block itself is written last so a stored block implies its data is
+/// stored too. Every write is idempotent, so a failed attempt can be repeated.
+/// Counters and native transaction nonces come from `dexvm_state`, the DexVM
+/// state at the end of the block, as the executor may already be running the
+/// next one.
+fn persist_block(
+    node: &DualVmNode,
+    block: &StoredBlock,
+    result: &DualVmExecutionResult,
+    dexvm_state: &DexVmState,
+    tx_data: &[(B256, Vec<u8>)],
+    receipts: &[(B256, StoredReceipt)],
+    access_sets: &[(B256, StoredAccessSet)],
+) -> eyre::Result<()> {
+    node.block_store().store_transactions(tx_data)?;
+    node.block_store().store_receipts(receipts)?;
+    node.block_store().store_access_sets(access_sets)?;
+    let dexvm_receipts = stored_dexvm_receipts(&result.dexvm_receipts, &result.counter_changes);
+    node.block_store().store_dexvm_receipts(block.number, dexvm_receipts)?;
+
+    for (address, &value) in dexvm_state.all_accounts() {
+        node.state_store().set_counter(*address, value)?;
+    }
+    let labeled = labeled_counter_writes(dexvm_state, node.storage().state.as_ref());
+    node.state_store().set_labeled_counters(&labeled)?;
+    let nonces: Vec<_> = dexvm_state.all_nonces().iter().map(|(&a, &n)| (a, n)).collect();
+    node.state_store().set_dexvm_nonces(&nonces)?;
+
+    node.block_store().store_block_stats(block.number, result.block_stats())?;
+    node.block_store().store_block(block.clone())
+}
+
+/// Labeled counter writes that bring storage in line with `state`
+///
+/// Stored labels that are no longer in `state` were cleared and are written as
+/// zero, which deletes them.
+pub(crate) fn labeled_counter_writes(
+    state: &DexVmState,
+    stored: &dyn StateReader,
+) -> Vec<(Address, B256, u64)> {
+    let current = state.all_labeled_counters();
+    let cleared = stored
+        .all_labeled_counters()
+        .into_keys()
+        .filter(|key| !current.contains_key(key))
+        .map(|(address, label)| (address, label, 0));
+    current
+        .iter()
+        .map(|(&(address, label), &value)| (address, label, value))
+        .chain(cleared)
+        .collect()
+}
+
+/// Announce a stored block to peers and log how many received it
+///
+/// A block no peer received is logged as a warning by the P2P service, which
+/// also emits `P2pEvent::AnnouncementUndelivered`.
+async fn announce_block(
+    handle: P2pHandle,
+    hash: B256,
+    number: u64,
+    last_broadcast_block: Arc<RwLock<u64>>,
+) {
+    match handle.broadcast_block(hash, number).await {
+        Ok(report) => {
+            let mut last = last_broadcast_block.write().await;
+            *last = (*last).max(number);
+            tracing::debug!(
+                "Announced block {} to {}/{} peers ({} queued)",
+                number,
+                report.delivered,
+                report.peers,
+                report.queued
+            );
+        }
+        Err(e) => tracing::warn!("Failed to broadcast block via P2P: {}", e),
+    }
+}
+
+/// Check an executed block against the state captured before it ran
+///
+/// Violations are logged as errors; in [`InvariantMode::Halt`] they also stop
+/// block production before the block is stored.
+fn check_invariants(
+    node: &DualVmNode,
+    number: u64,
+    before: &StateSummary,
+    result: &DualVmExecutionResult,
+) -> eyre::Result<()> {
+    let after = StateSummary::capture(node.state_store());
+    let dexvm_executor = node.executor().dexvm_executor();
+    let dexvm_exec = dexvm_executor.read().map_err(|e| eyre::eyre!(e.to_string()))?;
+    let violations =
+        check_block(before, &after, result, |address| dexvm_exec.state().get_counter(address));
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    for violation in &violations {
+        tracing::error!("Block {} breaks a state invariant: {}", number, violation);
+    }
+    if node.invariant_mode() == InvariantMode::Halt {
+        return Err(eyre::eyre!(
+            "Block {} breaks {} state invariants; stopping block production",
+            number,
+            violations.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Dump the trace of a stored block if `--trace-blocks` is on
+///
+/// Traces are a debugging aid, so a failed write is logged and production goes on.
+fn write_block_trace(node: &DualVmNode, block: &StoredBlock, result: &DualVmExecutionResult) {
+    let Some(writer) = node.block_traces() else { return };
+    match writer.write(&BlockTrace::new(block, result)) {
+        Ok(path) => tracing::debug!("Block {} trace written to {}", block.number, path.display()),
+        Err(e) => tracing::warn!("Failed to write trace of block {}: {}", block.number, e),
+    }
+}
+
+/// A block whose transactions ran, waiting for its state roots
+struct ExecutedBlock {
+    proposal: BlockProposal,
+    transactions: Vec<TransactionSigned>,
+    /// Native DexVM transactions that ran after the signed ones
+    native_transactions: Vec<DexVmTransaction>,
+    result: DualVmExecutionResult,
+    /// DexVM state at the end of the block, stored with it
+    dexvm_state: DexVmState,
+}
+
+/// An executed block with its state roots, sealed but not stored yet
+struct SealedBlock {
+    /// The block to store, with the hash of its Ethereum header
+    stored_block: StoredBlock,
+    transactions: Vec<TransactionSigned>,
+    /// Signed transactions followed by the native DexVM ones, as in the body
+    body_transactions: Vec<TransactionSigned>,
+    result: DualVmExecutionResult,
+    dexvm_state: DexVmState,
+}
+
+/// An executed block and its state roots being computed
+struct PendingBlock {
+    block: ExecutedBlock,
+    roots: PendingRoots,
+}
+
+/// Let `handler` choose a block's transactions from the canonical candidates
+///
+/// Returns the chosen transactions and the candidates left out. If the handler
+/// refuses its own choice, every candidate is kept in canonical order.
+fn prepare_transactions(
+    handler: &dyn ProposalHandler,
+    proposal: &BlockProposal,
+    candidates: Vec<PendingTransaction>,
+) -> (Vec<PendingTransaction>, Vec<PendingTransaction>) {
+    let chosen = handler.prepare_proposal(proposal, candidates.clone());
+    if let Err(reason) = handler.process_proposal(proposal, &chosen) {
+        tracing::warn!(
+            "Proposal handler refused its transactions for block {}: {}; using canonical order",
+            proposal.number,
+            reason
+        );
+        return (candidates, vec![]);
+    }
+    let kept: HashSet<B256> = chosen.iter().map(|tx| tx.hash).collect();
+    let left_out = candidates.into_iter().filter(|tx| !kept.contains(&tx.hash)).collect();
+    (chosen, left_out)
+}
+
+/// Execute a proposal with the mempool and native DexVM transactions
+///
+/// Its state roots are left to be computed in the background. Returns `None`
+/// if execution failed.
+fn execute_proposal(
+    node: &mut DualVmNode,
+    proposal: BlockProposal,
+) -> eyre::Result<Option<ExecutedBlock>> {
+    tracing::info!(
+        "Received block proposal: block_number={}, tx_count={}",
+        proposal.number,
+        proposal.transactions.len()
+    );
+
+    let pending_txs = if let Some(rpc_server) = node.evm_rpc_server() {
+        let txs = rpc_server.get_pending_transactions();
+        rpc_server.clear_pending_transactions();
+
+        // Transactions behind a nonce gap wait in the pool; used nonces can never run
+        let split = split_by_nonce(txs, node.storage().state.as_ref());
+        for tx in &split.stale {
+            tracing::debug!("Dropping pending transaction {} with a used nonce", tx.hash);
+        }
+        if !split.queued.is_empty() {
+            tracing::debug!("Keeping {} transactions behind nonce gaps", split.queued.len());
+            rpc_server.requeue_pending_transactions(split.queued);
+        }
+        split.ready
+    } else {
+        vec![]
+    };
+
+    // Proposal and mempool transactions share one canonical order
+    let proposal_txs = proposal.transactions.iter().map(|tx| {
+        PendingTransaction::new(tx.clone(), tx.recover_signer().unwrap_or_default())
+    });
+    let mut ordered_txs = canonical_order(proposal_txs.chain(pending_txs).collect());
+
+    // The proposal handler may inject, drop or reorder transactions
+    if let Some(consensus) = node.consensus() {
+        let handler = Arc::clone(consensus.proposal_handler());
+        let (chosen, left_out) = prepare_transactions(handler.as_ref(), &proposal, ordered_txs);
+        ordered_txs = chosen;
+        if let Some(rpc_server) = node.evm_rpc_server().filter(|_| !left_out.is_empty()) {
+            rpc_server.requeue_pending_transactions(left_out);
+        }
+    }
+
+    // Transactions larger than a whole block can never be included
+    let limits = node.block_limits();
+    ordered_txs.retain(|tx| {
+        if tx.size <= limits.max_bytes {
+            return true;
+        }
+        tracing::warn!(
+            "Quarantining transaction {} from block {}: {} bytes exceed the block limit of {}",
+            tx.hash,
+            proposal.number,
+            tx.size,
+            limits.max_bytes
+        );
+        if let Some(rpc_server) = node.evm_rpc_server() {
+            rpc_server.quarantine_transaction(tx.hash);
+        }
+        false
+    });
+
+    // Carry transactions over the block's byte and count caps into the next block
+    let fitting = limits.fitting(ordered_txs.iter().map(|tx| tx.size));
+    if fitting < ordered_txs.len() {
+        let leftover = ordered_txs.split_off(fitting);
+        tracing::info!(
+            "Block {} size limits reached, carrying {} transactions",
+            proposal.number,
+            leftover.len()
+        );
+        if let Some(rpc_server) = node.evm_rpc_server() {
+            rpc_server.requeue_pending_transactions(leftover);
+        }
+    }
+    let mut all_transactions: Vec<_> = ordered_txs.iter().map(|p| p.tx.clone()).collect();
+
+    // Native DexVM transactions run after all signed transactions
+    let mut dexvm_txs = node.dexvm_pool().drain(MAX_NATIVE_DEXVM_TXS_PER_BLOCK);
+
+    // Stop starting new transactions once the build budget is spent
+    let deadline = node.consensus().map(|c| std::time::Instant::now() + c.config().build_budget());
+
+    if !all_transactions.is_empty() || !dexvm_txs.is_empty() {
+        tracing::info!(
+            "Processing block {} with {} transactions ({} from mempool, {} native DexVM)",
+            proposal.number,
+            all_transactions.len() + dexvm_txs.len(),
+            all_transactions.len().saturating_sub(proposal.transactions.len()),
+            dexvm_txs.len()
+        );
+    }
+
+    // Invariants compare the state before and after the block
+    let state_before = (node.invariant_mode() != InvariantMode::Off)
+        .then(|| StateSummary::capture(node.state_store()));
+
+    // Tips of this block go to its proposer
+    node.executor_mut().set_beneficiary(Some(proposal.proposer));
+    let tx_timeout = node.tx_timeout();
+    let execution = node.executor_mut().execute_block_deferred(
+        all_transactions.clone(),
+        dexvm_txs.clone(),
+        deadline,
+        tx_timeout,
+    );
+    let result = match execution {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::error!("Block execution failed: {}", e);
+            return Ok(None);
+        }
+    };
+
+    // Dropped transactions are not retried; their effects are already undone
+    if !result.skipped_transactions.is_empty() {
+        let mut skipped = HashSet::new();
+        for (hash, reason) in &result.skipped_transactions {
+            tracing::warn!(
+                "Quarantining transaction {} from block {}: {}",
+                hash,
+                proposal.number,
+                reason
+            );
+            if let Some(rpc_server) = node.evm_rpc_server() {
+                rpc_server.quarantine_transaction(*hash);
+            }
+            skipped.insert(*hash);
+        }
+        ordered_txs.retain(|tx| !skipped.contains(&tx.hash));
+        all_transactions.retain(|tx| !skipped.contains(tx.tx_hash()));
+        dexvm_txs.retain(|tx| !skipped.contains(&tx.hash()));
+    }
+
+    // Carry transactions that did not fit into the next block
+    if result.included_transactions < all_transactions.len() ||
+        result.included_dexvm_transactions < dexvm_txs.len()
+    {
+        let leftover_pending = ordered_txs.split_off(result.included_transactions);
+        let leftover_dexvm = dexvm_txs.split_off(result.included_dexvm_transactions);
+        tracing::info!(
+            "Block {} build budget exceeded, carrying {} transactions and {} native DexVM \
+             transactions",
+            proposal.number,
+            leftover_pending.len(),
+            leftover_dexvm.len()
+        );
+        if let Some(rpc_server) = node.evm_rpc_server() {
+            rpc_server.requeue_pending_transactions(leftover_pending);
+        }
+        node.dexvm_pool().requeue(leftover_dexvm);
+        all_transactions.truncate(result.included_transactions);
+    }
+
+    if let Some(before) = &state_before {
+        check_invariants(node, proposal.number, before, &result)?;
+    }
+
+    let dexvm_state = node
+        .executor()
+        .dexvm_executor()
+        .read()
+        .map_err(|e| eyre::eyre!(e.to_string()))?
+        .state()
+        .clone();
+
+    Ok(Some(ExecutedBlock {
+        proposal,
+        transactions: all_transactions,
+        native_transactions: dexvm_txs,
+        result,
+        dexvm_state,
+    }))
+}
+
+/// Execute a block of another validator and commit it on top of the chain
+///
+/// Blocks that do not extend the chain are dropped. Blocks that do not
+/// decode, were not proposed in turn, skip or fail a transaction, or do not
+/// reproduce the hash their proposer sealed are undone and dropped, and the
+/// peer that sent them is banned. Returns the hash of the committed block.
+async fn import_block(
+    node: &mut DualVmNode,
+    imported: ImportedBlock,
+    p2p_handle: Option<&P2pHandle>,
+    last_broadcast_block: &Arc<RwLock<u64>>,
+) -> eyre::Result<Option<B256>> {
+    let ImportedBlock { peer_id, header, transactions } = imported;
+    let head = node.storage().blocks.latest_block_number();
+    let head_hash =
+        node.storage().blocks.get_block_by_number(head).map(|block| block.hash).unwrap_or_default();
+    if header.number != head + 1 || header.parent_hash != head_hash {
+        tracing::debug!("Dropping block {}, which does not extend block {}", header.number, head);
+        return Ok(None);
+    }
+    let (transactions, native_transactions) = match split_body_transactions(transactions) {
+        Ok(split) => split,
+        Err(e) => {
+            tracing::warn!("Dropping block {}, whose body does not decode: {}", header.number, e);
+            ban_block_sender(p2p_handle, peer_id).await;
+            return Ok(None);
+        }
+    };
+    let proposal = header_proposal(&header, transactions.clone());
+    if !node.consensus().is_some_and(|consensus| consensus.verify_block(&proposal)) {
+        tracing::warn!("Dropping block {}, not signed by the proposer in turn", header.number);
+        ban_block_sender(p2p_handle, peer_id).await;
+        return Ok(None);
+    }
+    tracing::info!(
+        "Importing block {} from validator {}: tx_count={}, native_dexvm_tx_count={}",
+        proposal.number,
+        proposal.proposer,
+        transactions.len(),
+        native_transactions.len()
+    );
+
+    node.executor_mut().set_beneficiary(Some(proposal.proposer));
+    let execution = node
+        .executor_mut()
+        .execute_imported_block(transactions.clone(), native_transactions.clone());
+    let (result, undo) = match execution {
+        Ok(executed) => executed,
+        Err(e) => {
+            tracing::warn!("Dropping block {}, which failed to execute: {}", proposal.number, e);
+            ban_block_sender(p2p_handle, peer_id).await;
+            return Ok(None);
+        }
+    };
+
+    // The block's DexVM changes stay pending until it is kept
+    let dexvm_state = node
+        .executor()
+        .dexvm_executor()
+        .read()
+        .map_err(|e| eyre::eyre!(e.to_string()))?
+        .pending_state()
+        .clone();
+    let roots = PendingRoots::spawn(&node.storage().state, dexvm_state.clone())?;
+    let (number, proposer) = (proposal.number, proposal.proposer);
+    let block = ExecutedBlock { proposal, transactions, native_transactions, result, dexvm_state };
+    let sealed = seal_block(node, PendingBlock { block, roots }, None).await?;
+    let sealed_hash = header_hash(&header);
+    if sealed.stored_block.hash != sealed_hash {
+        tracing::warn!(
+            "Dropping block {} of validator {}, which executed to hash {} but was sealed {}",
+            number,
+            proposer,
+            sealed.stored_block.hash,
+            sealed_hash
+        );
+        node.executor_mut().undo_block(undo).map_err(|e| eyre::eyre!(e.to_string()))?;
+        ban_block_sender(p2p_handle, peer_id).await;
+        return Ok(None);
+    }
+    node.executor_mut().keep_block(undo).map_err(|e| eyre::eyre!(e.to_string()))?;
+
+    // Its transactions leave our mempool
+    if let Some(rpc_server) = node.evm_rpc_server() {
+        let included: HashSet<B256> = sealed.transactions.iter().map(|tx| *tx.tx_hash()).collect();
+        let pending = rpc_server.get_pending_transactions();
+        rpc_server.clear_pending_transactions();
+        rpc_server.requeue_pending_transactions(
+            pending.into_iter().filter(|tx| !included.contains(&tx.hash)).collect(),
+        );
+    }
+
+    let hash = store_block(node, sealed, p2p_handle, last_broadcast_block).await?;
+    if let Some(consensus) = node.consensus() {
+        consensus.import_block(number, hash);
+    }
+    Ok(Some(hash))
+}
+
+/// Ban the peer that sent a block we dropped as invalid
+async fn ban_block_sender(p2p_handle: Option<&P2pHandle>, peer_id: PeerId) {
+    let Some(handle) = p2p_handle else { return };
+    let cmd = SessionCommand::BanPeer { peer_id, duration: INVALID_BLOCK_BAN };
+    if let Err(e) = handle.send_command(cmd).await {
+        tracing::warn!("Failed to ban peer {}: {}", peer_id, e);
+    }
+}
+
+/// Seal, store, finalize and announce a block once its state roots are ready
+///
+/// A proposal made while its parent was still pending points at an older
+/// block; it is re-signed on top of `parent_hash`. Returns the block hash.
+async fn commit_block(
+    node: &DualVmNode,
+    pending: PendingBlock,
+    parent_hash: Option<B256>,
+    p2p_handle: Option<&P2pHandle>,
+    last_broadcast_block: &Arc<RwLock<u64>>,
+) -> eyre::Result<B256> {
+    let sealed = seal_block(node, pending, parent_hash).await?;
+    store_block(node, sealed, p2p_handle, last_broadcast_block).await
+}
+
+/// Wait for a block's state roots and build the block to store
+///
+/// Re-signs the proposal on top of `parent_hash` if it points at an older block.
+async fn seal_block(
+    node: &DualVmNode,
+    pending: PendingBlock,
+    parent_hash: Option<B256>,
+) -> eyre::Result<SealedBlock> {
+    let PendingBlock { block, roots } = pending;
+    let ExecutedBlock { mut proposal, transactions, native_transactions, mut result, dexvm_state } =
+        block;
+
+    let (evm_state_root, dexvm_state_root) = roots.wait().await?;
+    result.set_state_roots(evm_state_root, dexvm_state_root);
+
+    if let Some(parent_hash) = parent_hash.filter(|hash| *hash != proposal.parent_hash) {
+        if let Some(consensus) = node.consensus() {
+            proposal.parent_hash = parent_hash;
+            proposal.sign(&consensus.config().secret_key);
+        }
+    }
+
+    tracing::info!(
+        "Block executed successfully: gas_used={}, state_root={:?}",
+        result.total_gas_used,
+        result.combined_state_root
+    );
+
+    // Native DexVM transactions follow the signed ones in the block body
+    let body_transactions: Vec<TransactionSigned> = transactions
+        .iter()
+        .cloned()
+        .chain(native_transactions.iter().map(DexVmTransaction::to_body_transaction))
+        .collect();
+    let tx_hashes: Vec<B256> = body_transactions.iter().map(|tx| *tx.tx_hash()).collect();
+    let mut stored_block = StoredBlock {
+        number: proposal.number,
+        hash: B256::ZERO,
+        parent_hash: proposal.parent_hash,
+        timestamp: proposal.timestamp,
+        gas_limit: 30_000_000,
+        gas_used: result.total_gas_used,
+        miner: proposal.proposer,
+        evm_state_root: result.evm_state_root,
+        dexvm_state_root: result.dexvm_state_root,
+        combined_state_root: result.combined_state_root,
+        transaction_count: tx_hashes.len() as u64,
+        transaction_hashes: tx_hashes,
+        signature: proposal.signature.to_bytes(),
+    };
+    // The hash of the Ethereum header peers receive for this block
+    stored_block.hash = header_hash(&block_header(&stored_block));
+    Ok(SealedBlock { stored_block, transactions, body_transactions, result, dexvm_state })
+}
+
+/// Store, finalize and announce a sealed block, returning its hash
+async fn store_block(
+    node: &DualVmNode,
+    sealed: SealedBlock,
+    p2p_handle: Option<&P2pHandle>,
+    last_broadcast_block: &Arc<RwLock<u64>>,
+) -> eyre::Result<B256> {
+    let SealedBlock {
+        stored_block,
+        transactions: all_transactions,
+        body_transactions,
+        result,
+        dexvm_state,
+    } = sealed;
+    let (number, block_hash) = (stored_block.number, stored_block.hash);
+
+    // Store transaction receipts
+    if let Some(rpc_server) = node.evm_rpc_server() {
+        let receipts = dex_rpc::build_block_receipts(
+            block_hash,
+            number,
+            dex_rpc::BASE_FEE_PER_GAS,
+            &all_transactions,
+            &result.block_receipts,
+        );
+        for receipt in receipts {
+            rpc_server.add_receipt(receipt.transaction_hash, receipt);
+        }
+    }
+
+    // Store full transaction data for block body sync
+    let tx_data: Vec<(B256, Vec<u8>)> =
+        body_transactions.iter().map(|tx| (*tx.tx_hash(), alloy_rlp::encode(tx))).collect();
+
+    let receipts = stored_receipts(&all_transactions, &result.block_receipts);
+
+    let access_sets: Vec<(B256, StoredAccessSet)> = result
+        .access_sets
+        .iter()
+        .map(|(hash, access_set)| (*hash, stored_access_set(access_set)))
+        .collect();
+
+    // Never finalize a block that is not on disk
+    let what = format!("store block {}", number);
+    retry_storage(node.storage_retry_policy(), &what, || {
+        persist_block(node, &stored_block, &result, &dexvm_state, &tx_data, &receipts, &access_sets)
+    })
+    .await?;
+
+    // Finalize block (short borrow)
+    if let Some(consensus) = node.consensus() {
+        consensus.finalize_block(block_hash);
+    }
+    node.status().record_block_produced();
+
+    tracing::info!("Block {} finalized and stored, hash={:?}", number, block_hash);
+
+    node.webhooks().dispatch(number, &result.counter_changes);
+    if let Some(rpc_server) = node.evm_rpc_server() {
+        rpc_server.notify_block(number);
+    }
+    write_block_trace(node, &stored_block, &result);
+
+    // Broadcast new block to all connected peers via P2P
+    if let Some(handle) = p2p_handle {
+        let last_block = *last_broadcast_block.read().await;
+        if number > last_block {
+            // Delivery acks can take a while; don't hold up the next block
+            tokio::spawn(announce_block(
+                handle.clone(),
+                block_hash,
+                number,
+                Arc::clone(last_broadcast_block),
+            ));
+        }
+    }
+
+    Ok(block_hash)
+}
+
+/// Run consensus loop with P2P block broadcasting
+///
+/// State roots are pipelined: a block's roots are computed in the background
+/// while the next proposal, if one is already waiting, executes. The block is
+/// committed once its roots are ready. Blocks of other validators arrive on
+/// `imports` and are executed after the pending block is committed.
+pub async fn run_consensus_loop_with_p2p(
+    mut node: DualVmNode,
+    p2p_handle: Option<P2pHandle>,
+    last_broadcast_block: Arc<RwLock<u64>>,
+    mut imports: Option<mpsc::UnboundedReceiver<ImportedBlock>>,
+) -> eyre::Result<()> {
+    // Verify consensus is configured
+    if node.consensus().is_none() {
+        return Err(eyre::eyre!("No consensus engine configured"));
+    }
+
+    tracing::info!("Starting consensus loop with P2P integration");
+
+    let mut pending: Option<PendingBlock> = None;
+    let mut parent_hash = None;
+
+    loop {
+        if let Some(imported) = imports.as_mut().and_then(|imports| imports.try_recv().ok()) {
+            if let Some(block) = pending.take() {
+                let hash = commit_block(
+                    &node,
+                    block,
+                    parent_hash,
+                    p2p_handle.as_ref(),
+                    &last_broadcast_block,
+                )
+                .await?;
+                parent_hash = Some(hash);
+            }
+            let hash =
+                import_block(&mut node, imported, p2p_handle.as_ref(), &last_broadcast_block)
+                    .await?;
+            parent_hash = hash.or(parent_hash);
+            continue;
+        }
+
+        // Get proposal from consensus (short borrow)
+        let proposal = node.consensus().and_then(|c| c.recv_proposal());
+
+        let executed = match proposal {
+            Some(proposal) => execute_proposal(&mut node, proposal)?,
+            None => None,
+        };
+
+        // Commit the pending block once its roots are ready, or before the
+        // roots of the block that just ran can be computed
+        if executed.is_some() || pending.as_ref().is_some_and(|p| p.roots.is_ready()) {
+            if let Some(block) = pending.take() {
+                let hash = commit_block(
+                    &node,
+                    block,
+                    parent_hash,
+                    p2p_handle.as_ref(),
+                    &last_broadcast_block,
+                )
+                .await?;
+                parent_hash = Some(hash);
+            }
+        }
+
+        if let Some(block) = executed {
+            let roots = PendingRoots::spawn(&node.storage().state, block.dexvm_state.clone())?;
+            pending = Some(PendingBlock { block, roots });
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_storage_recovers() {
+        let policy = StorageRetryPolicy { max_retries: 2, retry_delay: Duration::ZERO };
+        let mut calls = 0;
+        let result = retry_storage(policy, "write", || {
+            calls += 1;
+            if calls < 3 {
+                Err(eyre::eyre!("busy"))
+            } else {
+                Ok(calls)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_storage_gives_up() {
+        let policy = StorageRetryPolicy { max_retries: 0, retry_delay: Duration::ZERO };
+        let mut calls = 0;
+        let result: eyre::Result<()> = retry_storage(policy, "write", || {
+            calls += 1;
+            Err(eyre::eyre!("disk full"))
+        })
+        .await;
+        assert_eq!(calls, 1);
+        assert!(result.unwrap_err().to_string().contains("after 1 attempts"));
+    }
+
+    #[test]
+    fn test_labeled_counter_writes_clear_stale_labels() {
+        use dex_storage::{DualvmStorage, StateWriter};
+
+        let storage = DualvmStorage::in_memory().unwrap();
+        let owner = Address::repeat_byte(0x11);
+        let (kept, cleared) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        storage.state.set_labeled_counters(&[(owner, kept, 1), (owner, cleared, 4)]).unwrap();
+
+        let mut state = DexVmState::new();
+        state.set_labeled_counter(owner, kept, 3);
+        let writes = labeled_counter_writes(&state, storage.state.as_ref());
+        assert_eq!(writes, vec![(owner, kept, 3), (owner, cleared, 0)]);
+
+        storage.state.set_labeled_counters(&writes).unwrap();
+        assert_eq!(storage.state.counter_labels(&owner), vec![(kept, 3)]);
+    }
+
+    #[test]
+    fn test_stored_receipts_number_logs_across_block() {
+        use alloy_consensus::TxLegacy;
+        use alloy_primitives::{Address, Log, Signature};
+
+        let tx = |nonce| {
+            TransactionSigned::new_unhashed(
+                TxLegacy { nonce, ..Default::default() }.into(),
+                Signature::test_signature(),
+            )
+        };
+        let log = Log::new_unchecked(Address::repeat_byte(0x01), vec![B256::ZERO], vec![1].into());
+        let receipt = |cumulative_gas_used, logs: Vec<Log>| Receipt {
+            status: true.into(),
+            cumulative_gas_used,
+            logs,
+        };
+
+        let transactions = vec![tx(0), tx(1), tx(2)];
+        let receipts = vec![
+            receipt(21_000, vec![log.clone(), log.clone()]),
+            receipt(42_000, vec![]),
+            receipt(63_000, vec![log]),
+        ];
+        let stored = stored_receipts(&transactions, &receipts);
+
+        let positions: Vec<_> = stored
+            .iter()
+            .map(|(_, r)| (r.transaction_index, r.first_log_index, r.logs.len()))
+            .collect();
+        assert_eq!(positions, vec![(0, 0, 2), (1, 2, 0), (2, 2, 1)]);
+        assert_eq!(stored[0].0, *transactions[0].tx_hash());
+        assert_eq!(stored[2].1.log_index(0), 2);
+        assert_eq!(stored[2].1.logs[0].topics, vec![B256::ZERO]);
+    }
+
+    #[test]
+    fn test_proposal_handler_chooses_transactions() {
+        use alloy_consensus::TxLegacy;
+        use alloy_primitives::Signature;
+
+        /// Puts the last candidate first and drops the second
+        #[derive(Debug)]
+        struct Matcher {
+            refuse: bool,
+        }
+
+        impl ProposalHandler for Matcher {
+            fn prepare_proposal(
+                &self,
+                _proposal: &BlockProposal,
+                mut txs: Vec<PendingTransaction>,
+            ) -> Vec<PendingTransaction> {
+                txs.remove(1);
+                txs.rotate_right(1);
+                txs
+            }
+
+            fn process_proposal(
+                &self,
+                _proposal: &BlockProposal,
+                _txs: &[PendingTransaction],
+            ) -> Result<(), String> {
+                if self.refuse {
+                    Err("no match".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        let candidates: Vec<_> = (0..3)
+            .map(|nonce| {
+                let tx = TransactionSigned::new_unhashed(
+                    TxLegacy { nonce, ..Default::default() }.into(),
+                    Signature::test_signature(),
+                );
+                PendingTransaction::new(tx, Address::repeat_byte(1))
+            })
+            .collect();
+        let hashes: Vec<B256> = candidates.iter().map(|tx| tx.hash).collect();
+        let proposal = BlockProposal {
+            number: 1,
+            parent_hash: B256::ZERO,
+            timestamp: 0,
+            transactions: vec![],
+            proposer: Address::ZERO,
+            signature: Default::default(),
+        };
+        let hashes_of =
+            |txs: &[PendingTransaction]| -> Vec<B256> { txs.iter().map(|tx| tx.hash).collect() };
+
+        let (chosen, left_out) =
+            prepare_transactions(&crate::PassthroughProposals, &proposal, candidates.clone());
+        assert_eq!(hashes_of(&chosen), hashes);
+        assert!(left_out.is_empty());
+
+        let matcher = Matcher { refuse: false };
+        let (chosen, left_out) = prepare_transactions(&matcher, &proposal, candidates.clone());
+        assert_eq!(hashes_of(&chosen), vec![hashes[2], hashes[0]]);
+        assert_eq!(hashes_of(&left_out), vec![hashes[1]]);
+
+        // A refused choice falls back to the canonical order
+        let matcher = Matcher { refuse: true };
+        let (chosen, left_out) = prepare_transactions(&matcher, &proposal, candidates);
+        assert_eq!(hashes_of(&chosen), hashes);
+        assert!(left_out.is_empty());
+    }
+}
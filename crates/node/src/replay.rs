@@ -0,0 +1,407 @@
+//! Deterministic block replay
+//!
+//! [`replay`] executes stored blocks again on a scratch in-memory node that
+//! starts from the chain's genesis, and compares the combined state root each
+//! block produces with the stored one, so executor changes that alter results
+//! show up as the first block they affect. Only the head state is stored, so
+//! the blocks before the compared range are executed too, without computing
+//! their roots. Native DexVM transactions are replayed from the block body,
+//! where they follow the signed ones.
+
+use crate::{
+    executor::AccountView,
+    node::{DualVmNode, NodeConfig},
+};
+use alloy_consensus::{transaction::SignerRecoverable, Transaction};
+use alloy_primitives::{Address, B256};
+use alloy_rlp::Decodable;
+use dex_primitives::{split_body_transactions, DexVmTransaction};
+use dex_storage::{BlockReader, StoredBlock};
+use reth_ethereum_primitives::TransactionSigned;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+};
+
+/// Outcome of replaying a block range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// First block whose root was compared
+    pub from: u64,
+    /// Last block whose root was compared
+    pub to: u64,
+    /// Compared blocks whose replayed root matched the stored one
+    pub blocks_matched: u64,
+    /// First block whose root differed; replay stops there
+    pub mismatch: Option<ReplayMismatch>,
+}
+
+impl ReplayReport {
+    /// Whether every compared block matched
+    pub fn is_ok(&self) -> bool {
+        self.mismatch.is_none()
+    }
+}
+
+/// EVM, DexVM and combined state roots of a block
+///
+/// Blocks stored by sync only carry the combined root; their other roots are zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateRoots {
+    pub evm: B256,
+    pub dexvm: B256,
+    pub combined: B256,
+}
+
+/// A block whose replayed state root differs from the stored one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayMismatch {
+    pub number: u64,
+    pub stored: StateRoots,
+    pub replayed: StateRoots,
+    /// Transactions whose replayed outcome differs from their stored receipt
+    pub receipts: Vec<ReceiptDiff>,
+    /// Senders, recipients and the miner, where the replayed block changed them
+    pub accounts: Vec<AccountDiff>,
+}
+
+/// Outcome of one transaction: success and the block's gas used up to it
+pub type TxOutcome = (bool, u64);
+
+/// Stored and replayed outcome of a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceiptDiff {
+    pub tx_hash: B256,
+    /// `None` if no receipt is stored
+    pub stored: Option<TxOutcome>,
+    /// `None` if replay dropped the transaction from the block
+    pub replayed: Option<TxOutcome>,
+}
+
+/// An account before and after the replayed block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub address: Address,
+    pub before: AccountView,
+    pub after: AccountView,
+}
+
+impl fmt::Display for ReplayMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Block {} state root mismatch", self.number)?;
+        for (name, stored, replayed) in [
+            ("combined", self.stored.combined, self.replayed.combined),
+            ("evm", self.stored.evm, self.replayed.evm),
+            ("dexvm", self.stored.dexvm, self.replayed.dexvm),
+        ] {
+            writeln!(f, "  {:<8} stored {} replayed {}", name, stored, replayed)?;
+        }
+        for diff in &self.receipts {
+            writeln!(
+                f,
+                "  tx {}: stored {}, replayed {}",
+                diff.tx_hash,
+                outcome(diff.stored, "no receipt"),
+                outcome(diff.replayed, "dropped")
+            )?;
+        }
+        for diff in &self.accounts {
+            let (before, after) = (&diff.before, &diff.after);
+            writeln!(
+                f,
+                "  account {}: balance {} -> {}, nonce {} -> {}, counter {} -> {}",
+                diff.address,
+                before.balance,
+                after.balance,
+                before.nonce,
+                after.nonce,
+                before.counter,
+                after.counter
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn outcome(outcome: Option<TxOutcome>, missing: &str) -> String {
+    match outcome {
+        Some((success, gas)) => {
+            format!("{} at {} gas", if success { "success" } else { "failed" }, gas)
+        }
+        None => missing.to_string(),
+    }
+}
+
+/// Replay the stored blocks up to `to` on a scratch node, comparing roots from `from` on
+///
+/// The scratch node is built from `config`, which must carry the chain's
+/// genesis and fee policy; its storage and ephemerality settings are ignored.
+pub fn replay(
+    blocks: &dyn BlockReader,
+    config: NodeConfig,
+    from: u64,
+    to: u64,
+) -> eyre::Result<ReplayReport> {
+    let head = blocks.latest_block_number();
+    if from == 0 || from > to || to > head {
+        return Err(eyre::eyre!("Cannot replay {}..={}: blocks 1..={} are stored", from, to, head));
+    }
+    if let Some(genesis) = &config.genesis {
+        blocks.check_genesis(config.chain_id, genesis.hash())?;
+    }
+
+    let mut node = DualVmNode::with_config(NodeConfig {
+        ephemeral: true,
+        block_traces: None,
+        ..config
+    });
+    let mut report = ReplayReport { from, to, blocks_matched: 0, mismatch: None };
+    for number in 1..=to {
+        let block = blocks
+            .get_block_by_number(number)
+            .ok_or_else(|| eyre::eyre!("Block {} is missing", number))?;
+        let (transactions, native_transactions) = block_transactions(blocks, &block)?;
+        let executor = node.executor_mut();
+        executor.set_beneficiary(Some(block.miner));
+
+        if number < from {
+            executor
+                .execute_block_deferred(transactions, native_transactions, None, None)
+                .map_err(|e| eyre::eyre!("Block {} failed to execute: {}", number, e))?;
+            continue;
+        }
+
+        let addresses = touched_accounts(&block, &transactions, &native_transactions);
+        let before = account_views(&node, &addresses)?;
+        let result = node
+            .executor_mut()
+            .execute_block(transactions.clone(), native_transactions, None, None)
+            .map_err(|e| eyre::eyre!("Block {} failed to execute: {}", number, e))?;
+        if result.combined_state_root == block.combined_state_root {
+            report.blocks_matched += 1;
+            continue;
+        }
+
+        let after = account_views(&node, &addresses)?;
+        let skipped: HashSet<B256> =
+            result.skipped_transactions.iter().map(|(hash, _)| *hash).collect();
+        let mut replayed = result.block_receipts.iter();
+        let receipts = transactions
+            .iter()
+            .map(|tx| {
+                let tx_hash = *tx.tx_hash();
+                let stored = blocks
+                    .get_receipt(tx_hash)
+                    .map(|receipt| (receipt.success, receipt.cumulative_gas_used));
+                let replayed = if skipped.contains(&tx_hash) {
+                    None
+                } else {
+                    replayed.next().map(|receipt| {
+                        (receipt.status.coerce_status(), receipt.cumulative_gas_used)
+                    })
+                };
+                ReceiptDiff { tx_hash, stored, replayed }
+            })
+            .filter(|diff| diff.stored != diff.replayed)
+            .collect();
+        let accounts = addresses
+            .into_iter()
+            .zip(before.into_iter().zip(after))
+            .filter(|(_, (before, after))| before != after)
+            .map(|(address, (before, after))| AccountDiff { address, before, after })
+            .collect();
+
+        report.mismatch = Some(ReplayMismatch {
+            number,
+            stored: StateRoots {
+                evm: block.evm_state_root,
+                dexvm: block.dexvm_state_root,
+                combined: block.combined_state_root,
+            },
+            replayed: StateRoots {
+                evm: result.evm_state_root,
+                dexvm: result.dexvm_state_root,
+                combined: result.combined_state_root,
+            },
+            receipts,
+            accounts,
+        });
+        break;
+    }
+
+    Ok(report)
+}
+
+/// Signed and native DexVM transactions of a stored block
+fn block_transactions(
+    blocks: &dyn BlockReader,
+    block: &StoredBlock,
+) -> eyre::Result<(Vec<TransactionSigned>, Vec<DexVmTransaction>)> {
+    let transactions = block
+        .transaction_hashes
+        .iter()
+        .map(|hash| {
+            let rlp = blocks
+                .get_transaction(*hash)
+                .ok_or_else(|| eyre::eyre!("Transaction {} is missing", hash))?;
+            TransactionSigned::decode(&mut rlp.as_slice())
+                .map_err(|e| eyre::eyre!("Transaction {} does not decode: {}", hash, e))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+    split_body_transactions(transactions)
+        .map_err(|e| eyre::eyre!("Block {} body is invalid: {}", block.number, e))
+}
+
+/// The miner, every sender and recipient of `transactions` and every native sender
+fn touched_accounts(
+    block: &StoredBlock,
+    transactions: &[TransactionSigned],
+    native_transactions: &[DexVmTransaction],
+) -> Vec<Address> {
+    let mut accounts = BTreeSet::from([block.miner]);
+    for tx in transactions {
+        accounts.extend(tx.recover_signer().ok());
+        accounts.extend(tx.to());
+    }
+    accounts.extend(native_transactions.iter().map(|tx| tx.from));
+    accounts.into_iter().collect()
+}
+
+fn account_views(node: &DualVmNode, addresses: &[Address]) -> eyre::Result<Vec<AccountView>> {
+    addresses
+        .iter()
+        .map(|address| node.executor().account_view(*address).map_err(|e| eyre::eyre!("{}", e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consensus::secret_key_to_address, genesis::Genesis};
+    use alloy_consensus::{SignableTransaction, TxLegacy};
+    use alloy_primitives::{Signature, TxKind, U256};
+    use dex_primitives::DexVmOperation;
+    use dex_storage::{BlockWriter, StoredReceipt};
+    use secp256k1::{Message, SecretKey, SECP256K1};
+    use std::collections::HashMap;
+
+    fn transfer(key: &SecretKey, nonce: u64, to: Address) -> TransactionSigned {
+        let tx = TxLegacy {
+            to: TxKind::Call(to),
+            nonce,
+            gas_price: 1,
+            gas_limit: 21_000,
+            value: U256::from(1_000),
+            chain_id: Some(1),
+            ..Default::default()
+        };
+        let message = Message::from_digest(tx.signature_hash().0);
+        let (recovery_id, signature) =
+            SECP256K1.sign_ecdsa_recoverable(&message, key).serialize_compact();
+        let signature = Signature::new(
+            U256::from_be_slice(&signature[..32]),
+            U256::from_be_slice(&signature[32..]),
+            i32::from(recovery_id) != 0,
+        );
+        TransactionSigned::new_unhashed(tx.into(), signature)
+    }
+
+    /// Execute `transactions` on `node` and store them, returning block `number` to store
+    fn produce(
+        node: &mut DualVmNode,
+        number: u64,
+        transactions: Vec<TransactionSigned>,
+        native_transactions: Vec<DexVmTransaction>,
+    ) -> StoredBlock {
+        let body: Vec<_> = transactions
+            .iter()
+            .cloned()
+            .chain(native_transactions.iter().map(DexVmTransaction::to_body_transaction))
+            .collect();
+        let executor = node.executor_mut();
+        let result = executor.execute_block(transactions.clone(), native_transactions, None, None);
+        let result = result.unwrap();
+        let blocks = &node.storage().blocks;
+        let parent = blocks.get_block_by_number(number - 1).unwrap();
+        let receipts: Vec<_> = transactions
+            .iter()
+            .zip(&result.block_receipts)
+            .enumerate()
+            .map(|(index, (tx, receipt))| {
+                let stored = StoredReceipt {
+                    success: receipt.status.coerce_status(),
+                    cumulative_gas_used: receipt.cumulative_gas_used,
+                    transaction_index: index as u64,
+                    first_log_index: 0,
+                    logs: Vec::new(),
+                };
+                (*tx.tx_hash(), stored)
+            })
+            .collect();
+        let tx_data: Vec<_> =
+            body.iter().map(|tx| (*tx.tx_hash(), alloy_rlp::encode(tx))).collect();
+        blocks.store_transactions(&tx_data).unwrap();
+        blocks.store_receipts(&receipts).unwrap();
+        StoredBlock {
+            number,
+            hash: B256::with_last_byte(number as u8),
+            parent_hash: parent.hash,
+            evm_state_root: result.evm_state_root,
+            dexvm_state_root: result.dexvm_state_root,
+            combined_state_root: result.combined_state_root,
+            transaction_hashes: body.iter().map(|tx| *tx.tx_hash()).collect(),
+            transaction_count: body.len() as u64,
+            ..StoredBlock::genesis(1)
+        }
+    }
+
+    #[test]
+    fn test_replay_finds_first_diverging_block() {
+        let key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let sender = secret_key_to_address(&key);
+        let recipient = Address::repeat_byte(0x42);
+        let genesis = Genesis::with_balances(1, HashMap::from([(sender, U256::from(1_000_000))]));
+        let config = || NodeConfig {
+            ephemeral: true,
+            genesis: Some(genesis.clone()),
+            ..Default::default()
+        };
+
+        // Block 2 also runs a native DexVM transaction, replayed from its body
+        let operation = DexVmOperation::Increment(4);
+        let native = DexVmTransaction {
+            from: sender,
+            operation,
+            nonce: 1,
+            signature: dex_rpc::sign_operation(&key, operation, 1, 1).to_vec(),
+        };
+        let mut node = DualVmNode::with_config(config());
+        for (number, nonce, natives) in [(1, 0, vec![]), (2, 1, vec![native])] {
+            let block =
+                produce(&mut node, number, vec![transfer(&key, nonce, recipient)], natives);
+            node.storage().blocks.store_block(block).unwrap();
+        }
+
+        let report = replay(node.storage().blocks.as_ref(), config(), 1, 2).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.blocks_matched, 2);
+
+        // Block 3 was stored with a root its transactions do not produce
+        let mut tampered = produce(&mut node, 3, vec![transfer(&key, 2, recipient)], vec![]);
+        tampered.combined_state_root = B256::repeat_byte(0xee);
+        let blocks = &node.storage().blocks;
+        blocks.store_block(tampered).unwrap();
+
+        let report = replay(blocks.as_ref(), config(), 2, 3).unwrap();
+        assert_eq!(report.blocks_matched, 1);
+        let mismatch = report.mismatch.unwrap();
+        assert_eq!(mismatch.number, 3);
+        assert_eq!(mismatch.stored.combined, B256::repeat_byte(0xee));
+        assert!(mismatch.receipts.is_empty());
+        let sender_diff = mismatch.accounts.iter().find(|diff| diff.address == sender).unwrap();
+        assert_eq!((sender_diff.before.nonce, sender_diff.after.nonce), (2, 3));
+
+        assert!(replay(blocks.as_ref(), config(), 0, 3).is_err());
+        assert!(replay(blocks.as_ref(), config(), 1, 4).is_err());
+    }
+}
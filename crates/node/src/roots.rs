@@ -0,0 +1,70 @@
+//! Background state root computation
+//!
+//! Block production computes a block's state roots on a blocking worker while
+//! it executes the next block. The EVM root comes from the account state
+//! frozen when the block finished (see [`StateStore::freeze`]), the DexVM root
+//! from a copy of the block's DexVM state, so writes of the next block do not
+//! leak into them.
+
+use alloy_primitives::B256;
+use dex_dexvm::DexVmState;
+use dex_storage::StateStore;
+use std::{sync::Arc, time::Instant};
+use tokio::task::JoinHandle;
+
+/// EVM and DexVM state roots of a block, being computed on a worker
+pub struct PendingRoots {
+    task: JoinHandle<(B256, B256)>,
+}
+
+impl PendingRoots {
+    /// Start computing the roots of the current account state and `dexvm_state`
+    ///
+    /// Fails if the roots of another block are still being computed.
+    pub fn spawn(state_store: &Arc<StateStore>, dexvm_state: DexVmState) -> eyre::Result<Self> {
+        let frozen = state_store.freeze()?;
+        let task = tokio::task::spawn_blocking(move || {
+            let started = Instant::now();
+            let roots = (frozen.state_root(), dexvm_state.state_root());
+            tracing::debug!("State roots computed in {:?}", started.elapsed());
+            roots
+        });
+        Ok(Self { task })
+    }
+
+    /// Whether the roots are computed
+    pub fn is_ready(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the EVM and DexVM state roots
+    pub async fn wait(self) -> eyre::Result<(B256, B256)> {
+        self.task.await.map_err(|e| eyre::eyre!("State root worker failed: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, U256};
+    use dex_storage::{DualvmStorage, StateReader, StateWriter};
+
+    #[tokio::test]
+    async fn test_roots_ignore_later_writes() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let account = Address::repeat_byte(0x11);
+        storage.state.set_balance(account, U256::from(10)).unwrap();
+
+        let mut dexvm_state = DexVmState::new();
+        dexvm_state.set_counter(account, 5);
+        let expected = (storage.state.state_root(), dexvm_state.state_root());
+
+        let roots = PendingRoots::spawn(&storage.state, dexvm_state.clone()).unwrap();
+        // The next block runs meanwhile
+        storage.state.set_balance(account, U256::from(20)).unwrap();
+        dexvm_state.set_counter(account, 6);
+
+        assert_eq!(roots.wait().await.unwrap(), expected);
+        assert_ne!(storage.state.state_root(), expected.0);
+    }
+}
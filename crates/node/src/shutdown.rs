@@ -0,0 +1,109 @@
+//! Unclean shutdown detection
+//!
+//! A sentinel file is created when the node starts and removed only after a
+//! clean shutdown. Finding it on startup means the previous run crashed, was
+//! killed or lost power, so the stored head is checked with
+//! [`crate::verify::verify_head`] before RPC traffic is accepted.
+
+use crate::verify::verify_head;
+use dex_rpc::StartupCheck;
+use dex_storage::BlockReader;
+use eyre::{Result, WrapErr};
+use std::path::{Path, PathBuf};
+
+/// Blocks below the head checked after an unclean shutdown
+pub const STARTUP_CHECK_DEPTH: u64 = 64;
+
+/// Marks a running node in its data directory
+#[derive(Debug)]
+pub struct ShutdownSentinel {
+    path: PathBuf,
+    previous_clean: bool,
+}
+
+impl ShutdownSentinel {
+    /// Create the sentinel, noting whether the previous run left one behind
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let previous_clean = !path.exists();
+        std::fs::write(&path, std::process::id().to_string())
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self { path, previous_clean })
+    }
+
+    /// Sentinel file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the previous run shut down cleanly (or there was none)
+    pub fn previous_clean(&self) -> bool {
+        self.previous_clean
+    }
+
+    /// Remove the sentinel once the node has stopped cleanly
+    pub fn release(self) -> Result<()> {
+        std::fs::remove_file(&self.path)
+            .wrap_err_with(|| format!("Failed to remove {}", self.path.display()))
+    }
+}
+
+/// Check the stored head if the previous run did not shut down cleanly
+pub fn startup_check(blocks: &dyn BlockReader, previous_clean: bool) -> StartupCheck {
+    if previous_clean {
+        return StartupCheck { clean_shutdown: true, blocks_checked: 0, error: None };
+    }
+
+    let report = verify_head(blocks, STARTUP_CHECK_DEPTH);
+    StartupCheck {
+        clean_shutdown: false,
+        blocks_checked: report.blocks_verified,
+        error: report
+            .divergence
+            .map(|(number, divergence)| format!("block {}: {}", number, divergence)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_storage::{BlockWriter, DualvmStorage, StoredBlock};
+
+    #[test]
+    fn test_sentinel_detects_unclean_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("running");
+
+        let sentinel = ShutdownSentinel::create(&path).unwrap();
+        assert!(sentinel.previous_clean());
+        sentinel.release().unwrap();
+
+        // A run that never released its sentinel
+        let crashed = ShutdownSentinel::create(&path).unwrap();
+        drop(crashed);
+        let sentinel = ShutdownSentinel::create(&path).unwrap();
+        assert!(!sentinel.previous_clean());
+        sentinel.release().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_startup_check() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+
+        let check = startup_check(storage.blocks.as_ref(), true);
+        assert!(check.is_ok());
+        assert_eq!(check.blocks_checked, 0);
+
+        let check = startup_check(storage.blocks.as_ref(), false);
+        assert!(check.is_ok(), "{:?}", check.error);
+        assert_eq!(check.blocks_checked, 1);
+
+        // A head that does not link to the block below it
+        storage.blocks.store_block(StoredBlock { number: 1, ..StoredBlock::genesis(1) }).unwrap();
+        let check = startup_check(storage.blocks.as_ref(), false);
+        assert!(!check.is_ok());
+        assert!(check.error.unwrap().starts_with("block 1:"));
+    }
+}
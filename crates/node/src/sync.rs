@@ -0,0 +1,1677 @@
+//! Block sync over P2P
+//!
+//! Fullnodes request headers and bodies from peers; validators answer those
+//! requests and pass relayed transactions to [`crate::tx_ingest`] for the mempool.
+//! With a validator set, headers must be signed by the validator whose turn it
+//! was, and validators sharing the chain fetch each other's blocks the same way
+//! a fullnode does, handing them to the consensus loop as [`ImportedBlock`]s.
+
+use crate::{
+    consensus::{round_robin_proposer, BlockProposal, BlockSignature},
+    dexvm_sync::{DexVmStateSync, DEXVM_SYNC_INTERVAL},
+    header::{block_header, header_hash, transactions_root},
+    limits::BlockLimits,
+    tx_ingest::{PeerVerdict, TxIngest},
+    verify::verify_synced_block,
+};
+use alloy_consensus::Header as ConsensusHeader;
+use alloy_primitives::{keccak256, Address, B256};
+use alloy_rlp::Decodable;
+use dex_p2p::{
+    DisconnectReason, HashOrNumber, HeadersDirection, P2pEvent, P2pHandle, PeerId, SessionCommand,
+};
+use dex_primitives::DexVmTransaction;
+use dex_rpc::{EvmRpcServer, NodeStatus};
+use dex_storage::{BlockWriter, RangeDirection, StoredBlock, StoredSiblingBlock};
+use reth_ethereum_primitives::{BlockBody, TransactionSigned};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+
+/// Maximum number of headers sent in answer to one GetBlockHeaders request
+pub const MAX_HEADERS_PER_RESPONSE: u64 = 1_024;
+
+/// Maximum number of headers requested in one GetBlockHeaders message
+pub const MAX_HEADERS_PER_REQUEST: u64 = 512;
+
+/// Weight of the newest response in a peer's moving average response time
+const LATENCY_EWMA_WEIGHT: f64 = 0.3;
+
+/// Maximum number of block bodies requested in one GetBlockBodies message
+pub const MAX_BODIES_PER_REQUEST: usize = 64;
+
+/// Soft limit on the estimated size of the bodies in one request
+pub const MAX_BODY_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// Maximum number of body requests in flight to a peer
+pub const MAX_INFLIGHT_BODY_REQUESTS: usize = 4;
+
+/// Number of announced block hashes remembered to deduplicate announcements
+pub const RECENT_ANNOUNCEMENTS_CAPACITY: usize = 1_024;
+
+/// How long a peer that sent an invalid block is refused
+pub const INVALID_BLOCK_BAN: Duration = Duration::from_secs(30 * 60);
+
+/// Measured responsiveness of a peer
+#[derive(Debug, Clone, Copy)]
+struct PeerLatency {
+    /// Moving average of response time per delivered header or body (seconds)
+    secs_per_item: f64,
+}
+
+/// Response times of sync requests, per peer
+///
+/// Time per delivered item covers both round-trip latency and throughput:
+/// a slow peer and a peer answering with few items both score badly.
+#[derive(Debug, Default)]
+struct PeerLatencies {
+    peers: HashMap<PeerId, PeerLatency>,
+}
+
+impl PeerLatencies {
+    /// Record a response of `items` headers or bodies that took `elapsed`
+    fn record(&mut self, peer_id: PeerId, elapsed: Duration, items: usize) {
+        let sample = elapsed.as_secs_f64() / items.max(1) as f64;
+        self.peers
+            .entry(peer_id)
+            .and_modify(|l| l.secs_per_item += LATENCY_EWMA_WEIGHT * (sample - l.secs_per_item))
+            .or_insert(PeerLatency { secs_per_item: sample });
+    }
+
+    fn forget(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+    }
+
+    /// Expected time per item; unmeasured peers count as instant, so they get tried
+    fn cost(&self, peer_id: &PeerId) -> f64 {
+        self.peers.get(peer_id).map_or(0.0, |latency| latency.secs_per_item)
+    }
+}
+
+/// Peer to request the next blocks from, with its head
+///
+/// Candidates are peers whose head covers the next batch after `latest`, or
+/// has the best head when none does; the fastest of them wins, then the
+/// higher head, then the lower peer ID.
+fn select_sync_peer(
+    heads: &[(PeerId, u64)],
+    latest: u64,
+    latencies: &PeerLatencies,
+) -> Option<(PeerId, u64)> {
+    let best = heads.iter().map(|(_, head)| *head).max()?;
+    let needed = best.min(latest.saturating_add(MAX_HEADERS_PER_REQUEST));
+    heads
+        .iter()
+        .filter(|(_, head)| *head >= needed)
+        .min_by(|a, b| {
+            latencies
+                .cost(&a.0)
+                .total_cmp(&latencies.cost(&b.0))
+                .then(b.1.cmp(&a.1))
+                .then(a.0.cmp(&b.0))
+        })
+        .copied()
+}
+
+/// Block hashes announced recently, by any peer
+///
+/// Bounded; the oldest hash is forgotten first.
+#[derive(Debug)]
+struct RecentAnnouncements {
+    hashes: HashSet<B256>,
+    order: VecDeque<B256>,
+}
+
+impl RecentAnnouncements {
+    fn new() -> Self {
+        Self { hashes: HashSet::new(), order: VecDeque::new() }
+    }
+
+    /// Remember a hash; returns false if it was already announced
+    fn insert(&mut self, hash: B256) -> bool {
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > RECENT_ANNOUNCEMENTS_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Forget a hash, so its next announcement is acted on again
+    fn remove(&mut self, hash: &B256) {
+        if self.hashes.remove(hash) {
+            self.order.retain(|h| h != hash);
+        }
+    }
+}
+
+/// Estimate a block body's encoded size from its header
+///
+/// Calldata costs at least 4 gas per byte, so gas used bounds the body size.
+fn estimated_body_size(header: &ConsensusHeader) -> usize {
+    512 + (header.gas_used / 4) as usize
+}
+
+/// Take the next chunk of block numbers to request bodies for
+///
+/// A chunk holds at most [`MAX_BODIES_PER_REQUEST`] blocks and stays under
+/// [`MAX_BODY_REQUEST_BYTES`] unless a single body is larger on its own.
+fn next_body_chunk(
+    queue: &mut VecDeque<u64>,
+    headers: &HashMap<u64, ConsensusHeader>,
+) -> Vec<u64> {
+    let mut chunk = Vec::new();
+    let mut size = 0;
+
+    while let Some(&number) = queue.front() {
+        let body_size = headers.get(&number).map_or(0, estimated_body_size);
+        if chunk.len() == MAX_BODIES_PER_REQUEST ||
+            (!chunk.is_empty() && size + body_size > MAX_BODY_REQUEST_BYTES)
+        {
+            break;
+        }
+        queue.pop_front();
+        chunk.push(number);
+        size += body_size;
+    }
+
+    chunk
+}
+
+/// Block signature carried in the last 65 bytes of a header's `extra_data`
+///
+/// Headers without a signature yield an empty signature.
+fn header_signature(header: &ConsensusHeader) -> BlockSignature {
+    let extra_data = &header.extra_data;
+    if extra_data.len() >= 65 {
+        BlockSignature::from_bytes(&extra_data[extra_data.len() - 65..]).unwrap_or_default()
+    } else {
+        BlockSignature::default()
+    }
+}
+
+/// Reject headers signed with a non-canonical (high-s) signature
+fn has_canonical_signature(header: &ConsensusHeader) -> bool {
+    let signature = header_signature(header);
+    signature.is_empty() || signature.is_canonical()
+}
+
+/// The signed proposal a header was sealed from, with `transactions`
+pub(crate) fn header_proposal(
+    header: &ConsensusHeader,
+    transactions: Vec<TransactionSigned>,
+) -> BlockProposal {
+    BlockProposal {
+        number: header.number,
+        parent_hash: header.parent_hash,
+        timestamp: header.timestamp,
+        transactions,
+        proposer: header.beneficiary,
+        signature: header_signature(header),
+    }
+}
+
+/// Recover the signer of a header's block signature
+fn header_signer(header: &ConsensusHeader) -> Option<Address> {
+    header_proposal(header, vec![]).recover_signer()
+}
+
+/// Refuse a header not proposed and signed by the validator whose turn it was
+///
+/// Without a validator set no header is accepted, as nothing says who may sign it.
+fn check_proposer(validators: &[Address], header: &ConsensusHeader) -> eyre::Result<()> {
+    let Some(expected) = round_robin_proposer(validators, header.number) else {
+        return Err(eyre::eyre!("no validator set to check the proposer against"));
+    };
+    if header.beneficiary != expected {
+        return Err(eyre::eyre!("proposed by {} instead of {}", header.beneficiary, expected));
+    }
+    match header_signer(header) {
+        Some(signer) if signer == expected => Ok(()),
+        signer => Err(eyre::eyre!("signed by {:?} instead of {}", signer, expected)),
+    }
+}
+
+/// Record a block seen at a height the local chain already has, if it differs
+///
+/// Returns whether the sibling was not known yet.
+fn record_sibling(
+    block_store: &dyn BlockWriter,
+    peer_id: PeerId,
+    number: u64,
+    hash: B256,
+    signer: Option<Address>,
+) -> bool {
+    let received_at_ms =
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+    let sibling = StoredSiblingBlock { hash, signer, peer: peer_id, received_at_ms };
+    match block_store.record_sibling(number, sibling) {
+        Ok(recorded) => {
+            if recorded {
+                tracing::warn!(
+                    "Peer {} has block {} at height {}, which differs from ours (signer {:?})",
+                    peer_id,
+                    hash,
+                    number,
+                    signer
+                );
+            }
+            recorded
+        }
+        Err(e) => {
+            tracing::warn!("Failed to record sibling block {} at {}: {}", hash, number, e);
+            false
+        }
+    }
+}
+
+/// Record an announced block that competes with a stored one
+///
+/// The first time a sibling shows up, its header is requested from the peer
+/// so the signer can be recorded once it arrives.
+async fn note_announced_block(
+    p2p_handle: &P2pHandle,
+    block_store: &dyn BlockWriter,
+    peer_id: PeerId,
+    hash: B256,
+    number: u64,
+) {
+    let Some(local) = block_store.get_block_by_number(number) else {
+        return;
+    };
+    if hash == B256::ZERO || hash == local.hash {
+        return;
+    }
+    if record_sibling(block_store, peer_id, number, hash, None) {
+        let cmd = SessionCommand::GetBlockHeaders {
+            peer_id,
+            start: number,
+            count: 1,
+            skip: 0,
+            direction: HeadersDirection::Rising,
+        };
+        if let Err(e) = p2p_handle.send_command(cmd).await {
+            tracing::debug!("Failed to request sibling header {} from {}: {}", number, peer_id, e);
+        }
+    }
+}
+
+/// Record a received header for a height the local chain already has
+///
+/// Returns whether the height was already stored; such headers are not synced.
+fn record_sibling_header(
+    block_store: &dyn BlockWriter,
+    peer_id: PeerId,
+    header: &ConsensusHeader,
+) -> bool {
+    let Some(local) = block_store.get_block_by_number(header.number) else {
+        return false;
+    };
+    let hash = header_hash(header);
+    if hash != local.hash {
+        record_sibling(block_store, peer_id, header.number, hash, header_signer(header));
+    }
+    true
+}
+
+/// Answer a GetBlockHeaders request
+///
+/// Returns up to `limit` headers (capped at [`MAX_HEADERS_PER_RESPONSE`]),
+/// starting at `start` and moving `skip + 1` blocks at a time in `direction`.
+/// The response stops at the first block that is not stored, and is empty if
+/// `start` is an unknown hash.
+fn serve_block_headers(
+    block_store: &dyn BlockWriter,
+    start: HashOrNumber,
+    limit: u64,
+    skip: u64,
+    direction: HeadersDirection,
+) -> Vec<ConsensusHeader> {
+    let start = match start {
+        HashOrNumber::Number(number) => number,
+        HashOrNumber::Hash(hash) => match block_store.get_block_by_hash(hash) {
+            Some(block) => block.number,
+            None => return vec![],
+        },
+    };
+    let count = limit.min(MAX_HEADERS_PER_RESPONSE);
+
+    // Consecutive headers come from one range read
+    if skip == 0 {
+        let direction = match direction {
+            HeadersDirection::Rising => RangeDirection::Ascending,
+            HeadersDirection::Falling => RangeDirection::Descending,
+        };
+        let blocks = block_store.get_headers_range(start, count, direction);
+        return blocks.iter().map(block_header).collect();
+    }
+
+    let step = skip.saturating_add(1);
+    (0..count)
+        .map_while(|i| {
+            let offset = i.checked_mul(step)?;
+            match direction {
+                HeadersDirection::Rising => start.checked_add(offset),
+                HeadersDirection::Falling => start.checked_sub(offset),
+            }
+        })
+        .map_while(|number| block_store.get_block_by_number(number))
+        .map(|block| block_header(&block))
+        .collect()
+}
+
+/// Whether a body holds exactly the transactions its header commits to
+///
+/// Hashes are computed locally, so a peer cannot slip unrelated transactions
+/// into a block it answers for.
+fn body_matches_header(header: &ConsensusHeader, body: &BlockBody) -> bool {
+    let tx_hashes: Vec<B256> = body.transactions.iter().map(|tx| *tx.tx_hash()).collect();
+    transactions_root(&tx_hashes) == header.transactions_root
+}
+
+/// Refuse a body with more transactions or encoded bytes than a block may hold
+///
+/// Native DexVM transactions are not counted, as the producer does not count them.
+fn check_body_limits(limits: &BlockLimits, body: &BlockBody) -> eyre::Result<()> {
+    let signed: Vec<_> = body
+        .transactions
+        .iter()
+        .filter(|tx| DexVmTransaction::from_body_transaction(tx).is_none())
+        .collect();
+    let bytes = signed.iter().map(|tx| alloy_rlp::Encodable::length(*tx)).sum();
+    limits.check(signed.len(), bytes)
+}
+
+/// Build the stored block and transaction entries for a synced header and body
+fn synced_block(
+    header: &ConsensusHeader,
+    body: &BlockBody,
+) -> (StoredBlock, Vec<(B256, Vec<u8>)>) {
+    let header_hash = keccak256(alloy_rlp::encode(header));
+
+    // Extract transaction hashes and prepare for storage
+    let tx_hashes: Vec<B256> = body.transactions.iter().map(|tx| *tx.tx_hash()).collect();
+    let tx_data: Vec<(B256, Vec<u8>)> = body
+        .transactions
+        .iter()
+        .map(|tx| (*tx.tx_hash(), alloy_rlp::encode(tx)))
+        .collect();
+
+    let signature = header_signature(header).to_bytes();
+
+    let block = StoredBlock {
+        number: header.number,
+        hash: header_hash,
+        parent_hash: header.parent_hash,
+        timestamp: header.timestamp,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        miner: header.beneficiary,
+        // For sync, use header's state_root as combined (we don't have separate roots)
+        evm_state_root: header.state_root,
+        dexvm_state_root: B256::ZERO,
+        combined_state_root: header.state_root,
+        transaction_count: tx_data.len() as u64,
+        transaction_hashes: tx_hashes,
+        signature,
+    };
+
+    (block, tx_data)
+}
+
+/// A block of another validator, fetched for the consensus loop to execute
+#[derive(Debug, Clone)]
+pub struct ImportedBlock {
+    /// Peer that sent the block, banned if it turns out invalid
+    pub peer_id: PeerId,
+    pub header: ConsensusHeader,
+    pub transactions: Vec<TransactionSigned>,
+}
+
+/// How a validator fetches the blocks of the other validators
+#[derive(Debug, Clone)]
+pub struct BlockImport {
+    /// Validators taking turns proposing blocks
+    pub validators: Vec<Address>,
+    /// Size caps fetched blocks must keep to
+    pub limits: BlockLimits,
+    /// Where fetched blocks go, in block order
+    pub blocks: mpsc::UnboundedSender<ImportedBlock>,
+}
+
+/// Block sync manager for fullnode mode, also used by validators to import blocks
+struct BlockSyncManager {
+    /// P2P handle for sending requests
+    p2p_handle: P2pHandle,
+    /// Block store for checking/storing blocks
+    block_store: Arc<dyn BlockWriter>,
+    /// RPC server whose subscriptions are notified of synced blocks
+    evm_rpc_server: Option<Arc<EvmRpcServer>>,
+    /// Size caps synced blocks must keep to
+    limits: BlockLimits,
+    /// Validators whose turns headers are checked against; empty accepts any signer
+    validators: Vec<Address>,
+    /// Where complete blocks go to be executed, instead of being stored
+    import: Option<mpsc::UnboundedSender<ImportedBlock>>,
+    /// Blocks we're currently requesting headers for
+    pending_header_requests: HashSet<u64>,
+    /// Headers received, waiting for bodies (block_number -> header)
+    pending_body_requests: HashMap<u64, ConsensusHeader>,
+    /// Block numbers whose bodies have not been requested yet, in order
+    body_queue: VecDeque<u64>,
+    /// Block numbers of each in-flight body request and when it was sent, by peer and request ID
+    inflight_body_chunks: HashMap<(PeerId, u64), (Vec<u64>, Instant)>,
+    /// ID of the next body request
+    next_request_id: u64,
+    /// Completed blocks waiting for their predecessors to be stored, with the peer they came from
+    ready_blocks: BTreeMap<u64, (PeerId, ConsensusHeader, BlockBody)>,
+    /// Track which peer we requested from (for bodies)
+    request_peer: Option<PeerId>,
+    /// Peer and send time of the header request in flight
+    header_request_sent: Option<(PeerId, Instant)>,
+    /// Response times of peers, for choosing whom to sync from
+    latencies: PeerLatencies,
+    /// Announced hashes already acted on; peer heads are tracked by the P2P service
+    recent_announcements: RecentAnnouncements,
+}
+
+impl BlockSyncManager {
+    fn new(
+        p2p_handle: P2pHandle,
+        block_store: Arc<dyn BlockWriter>,
+        evm_rpc_server: Option<Arc<EvmRpcServer>>,
+        limits: BlockLimits,
+        validators: Vec<Address>,
+    ) -> Self {
+        Self {
+            p2p_handle,
+            block_store,
+            evm_rpc_server,
+            limits,
+            validators,
+            import: None,
+            pending_header_requests: HashSet::new(),
+            pending_body_requests: HashMap::new(),
+            body_queue: VecDeque::new(),
+            inflight_body_chunks: HashMap::new(),
+            next_request_id: 0,
+            ready_blocks: BTreeMap::new(),
+            request_peer: None,
+            header_request_sent: None,
+            latencies: PeerLatencies::default(),
+            recent_announcements: RecentAnnouncements::new(),
+        }
+    }
+
+    /// Fetch the blocks of other validators for a validator
+    fn importing(
+        p2p_handle: P2pHandle,
+        block_store: Arc<dyn BlockWriter>,
+        import: BlockImport,
+    ) -> Self {
+        let mut manager =
+            Self::new(p2p_handle, block_store, None, import.limits, import.validators);
+        manager.import = Some(import.blocks);
+        manager
+    }
+
+    /// Request initial sync from a peer when connected
+    async fn request_initial_sync(&mut self, peer_id: PeerId) {
+        let our_latest = self.block_store.latest_block_number();
+
+        // Request headers starting from our latest block + 1
+        // Use a larger batch size for initial sync
+        let start_block = our_latest + 1;
+        let count = MAX_HEADERS_PER_REQUEST;
+
+        // Only request if we don't have pending requests
+        if !self.pending_header_requests.is_empty() {
+            tracing::debug!("Skipping initial sync request, already have pending requests");
+            return;
+        }
+
+        tracing::info!(
+            "Requesting initial sync from peer {}: starting at block {}",
+            peer_id, start_block
+        );
+
+        // Mark blocks as pending
+        for block_num in start_block..start_block + count {
+            self.pending_header_requests.insert(block_num);
+        }
+        self.request_peer = Some(peer_id);
+        self.header_request_sent = Some((peer_id, Instant::now()));
+
+        let cmd = SessionCommand::GetBlockHeaders {
+            peer_id,
+            start: start_block,
+            count,
+            skip: 0,
+            direction: HeadersDirection::Rising,
+        };
+        if let Err(e) = self.p2p_handle.send_command(cmd).await {
+            tracing::warn!("Failed to send initial sync request: {}", e);
+            // Clear pending on error
+            self.pending_header_requests.clear();
+        }
+    }
+
+    /// Handle NewBlockHash event - request headers if we don't have the block
+    ///
+    /// Only the first announcement of a hash is acted on. Headers are requested
+    /// from the fastest peer whose announced head covers the next batch; the P2P
+    /// service has already recorded this announcement.
+    async fn handle_new_block_hash(&mut self, peer_id: PeerId, hash: B256, number: u64) {
+        if !self.recent_announcements.insert(hash) {
+            tracing::trace!("Block {} from peer {} already announced", number, peer_id);
+            return;
+        }
+        tracing::info!("Peer {} announced new block {} ({:?})", peer_id, number, hash);
+
+        // Check if we already have this block
+        if self.block_store.get_block_by_number(number).is_some() {
+            tracing::debug!("Already have block {}, skipping sync", number);
+            note_announced_block(&self.p2p_handle, &self.block_store, peer_id, hash, number)
+                .await;
+            return;
+        }
+
+        let (target, head) =
+            self.sync_target().filter(|(_, head)| *head >= number).unwrap_or((peer_id, number));
+        if !self.request_headers(target, head).await {
+            // Forget the announcement, so a later one retries
+            self.recent_announcements.remove(&hash);
+        }
+    }
+
+    /// Fastest connected peer that has the next blocks
+    fn sync_target(&self) -> Option<(PeerId, u64)> {
+        let latest = self.block_store.latest_block_number();
+        select_sync_peer(&self.p2p_handle.peer_heads(), latest, &self.latencies)
+    }
+
+    /// Request the headers after our latest block, up to `head`, from `peer_id`
+    ///
+    /// Returns false if the request could not be sent.
+    async fn request_headers(&mut self, peer_id: PeerId, head: u64) -> bool {
+        // Check if we're already requesting this block
+        if self.pending_header_requests.contains(&head)
+            || self.pending_body_requests.contains_key(&head)
+        {
+            tracing::debug!("Already requesting block {}, skipping", head);
+            // Bodies no peer has delivered yet are tried again from this one
+            if self.inflight_body_chunks.is_empty() {
+                self.request_bodies(peer_id).await;
+            }
+            return true;
+        }
+
+        // Request headers for missing blocks, a batch at a time
+        let our_latest = self.block_store.latest_block_number();
+        let start_block = our_latest + 1;
+        let count = head.saturating_sub(our_latest).min(MAX_HEADERS_PER_REQUEST);
+        if count == 0 {
+            return true;
+        }
+
+        tracing::info!(
+            "Requesting {} block headers from peer {} (blocks {} to {})",
+            count, peer_id, start_block, start_block + count - 1
+        );
+
+        // Track pending requests
+        for block_num in start_block..start_block + count {
+            self.pending_header_requests.insert(block_num);
+        }
+        self.request_peer = Some(peer_id);
+        self.header_request_sent = Some((peer_id, Instant::now()));
+
+        // Send request
+        let cmd = SessionCommand::GetBlockHeaders {
+            peer_id,
+            start: start_block,
+            count,
+            skip: 0,
+            direction: HeadersDirection::Rising,
+        };
+        if let Err(e) = self.p2p_handle.send_command(cmd).await {
+            tracing::warn!("Failed to send GetBlockHeaders: {}", e);
+            // Clear pending on error
+            for block_num in start_block..start_block + count {
+                self.pending_header_requests.remove(&block_num);
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Handle BlockHeaders response - store headers and request bodies
+    async fn handle_block_headers(&mut self, peer_id: PeerId, headers: Vec<ConsensusHeader>) {
+        if let Some((requested, sent)) = self.header_request_sent {
+            if requested == peer_id {
+                self.latencies.record(peer_id, sent.elapsed(), headers.len());
+                self.header_request_sent = None;
+            }
+        }
+
+        if headers.is_empty() {
+            tracing::debug!("Received empty headers response from {}", peer_id);
+            // Clear pending requests since we got an empty response
+            self.pending_header_requests.clear();
+            return;
+        }
+
+        tracing::info!("Received {} block headers from peer {}", headers.len(), peer_id);
+
+        for header in headers {
+            let block_num = header.number;
+
+            // Remove from pending header requests
+            self.pending_header_requests.remove(&block_num);
+
+            if !has_canonical_signature(&header) {
+                tracing::warn!(
+                    "Rejecting header for block {} from {}: non-canonical signature",
+                    block_num, peer_id
+                );
+                self.pending_header_requests.clear();
+                self.ban_peer(peer_id).await;
+                return;
+            }
+
+            if record_sibling_header(&self.block_store, peer_id, &header) {
+                continue;
+            }
+
+            if let Err(e) = check_proposer(&self.validators, &header) {
+                tracing::warn!("Rejecting header for block {} from {}: {}", block_num, peer_id, e);
+                self.pending_header_requests.clear();
+                self.ban_peer(peer_id).await;
+                return;
+            }
+
+            tracing::debug!(
+                "Received header for block {}: parent={:?}",
+                block_num, header.parent_hash
+            );
+
+            // Store header and add to body request queue
+            if self.pending_body_requests.insert(block_num, header).is_none() {
+                self.body_queue.push_back(block_num);
+            }
+        }
+
+        // Clear any remaining pending header requests (for blocks we didn't receive)
+        self.pending_header_requests.clear();
+
+        self.request_bodies(peer_id).await;
+    }
+
+    /// Issue body requests to `peer_id` in size-limited chunks, up to the in-flight limit
+    async fn request_bodies(&mut self, peer_id: PeerId) {
+        while self.inflight_body_chunks.keys().filter(|(peer, _)| *peer == peer_id).count() <
+            MAX_INFLIGHT_BODY_REQUESTS
+        {
+            let chunk = next_body_chunk(&mut self.body_queue, &self.pending_body_requests);
+            if chunk.is_empty() {
+                break;
+            }
+
+            let hashes: Vec<B256> = chunk
+                .iter()
+                .filter_map(|number| self.pending_body_requests.get(number))
+                .map(|header| keccak256(alloy_rlp::encode(header)))
+                .collect();
+
+            tracing::info!(
+                "Requesting {} block bodies from peer {} (blocks {} to {})",
+                hashes.len(),
+                peer_id,
+                chunk[0],
+                chunk[chunk.len() - 1]
+            );
+
+            let request_id = self.next_request_id;
+            self.next_request_id += 1;
+            let cmd = SessionCommand::GetBlockBodies { peer_id, request_id, hashes };
+            if let Err(e) = self.p2p_handle.send_command(cmd).await {
+                tracing::warn!("Failed to send GetBlockBodies: {}", e);
+                self.requeue_bodies(chunk);
+                break;
+            }
+            self.inflight_body_chunks.insert((peer_id, request_id), (chunk, Instant::now()));
+        }
+    }
+
+    /// Queue blocks whose bodies are still wanted to be requested again, in block order
+    fn requeue_bodies(&mut self, numbers: Vec<u64>) {
+        let wanted = numbers.into_iter().filter(|n| self.pending_body_requests.contains_key(n));
+        self.body_queue.extend(wanted);
+        self.body_queue.make_contiguous().sort_unstable();
+    }
+
+    /// Abandon requests made to a disconnected peer and resume from the best remaining one
+    ///
+    /// Bodies the peer was asked for are queued again.
+    async fn handle_peer_disconnected(&mut self, peer_id: PeerId) {
+        self.latencies.forget(&peer_id);
+        let lost: Vec<_> = self
+            .inflight_body_chunks
+            .keys()
+            .filter(|(peer, _)| *peer == peer_id)
+            .copied()
+            .collect();
+        for key in lost {
+            if let Some((chunk, _)) = self.inflight_body_chunks.remove(&key) {
+                self.requeue_bodies(chunk);
+            }
+        }
+        if self.request_peer != Some(peer_id) {
+            if let Some((target, _)) = self.sync_target() {
+                self.request_bodies(target).await;
+            }
+            return;
+        }
+        self.header_request_sent = None;
+        self.pending_header_requests.clear();
+        self.pending_body_requests.clear();
+        self.body_queue.clear();
+        self.inflight_body_chunks.clear();
+        self.request_peer = None;
+
+        if let Some((target, head)) = self.sync_target() {
+            if head > self.block_store.latest_block_number() {
+                tracing::info!(
+                    "Sync peer {} left, continuing from {} (head {})",
+                    peer_id, target, head
+                );
+                self.request_headers(target, head).await;
+            }
+        }
+    }
+
+    /// Ban a peer that sent an invalid block and drop the blocks it completed
+    ///
+    /// Its requests in flight are abandoned once the P2P service reports the
+    /// disconnect.
+    async fn ban_peer(&mut self, peer_id: PeerId) {
+        self.ready_blocks.retain(|_, (from, _, _)| *from != peer_id);
+        let cmd = SessionCommand::BanPeer { peer_id, duration: INVALID_BLOCK_BAN };
+        if let Err(e) = self.p2p_handle.send_command(cmd).await {
+            tracing::warn!("Failed to ban peer {}: {}", peer_id, e);
+        }
+    }
+
+    /// Verify and store ready blocks that extend the local chain, in order
+    ///
+    /// A block that fails verification gets the peer that sent it banned.
+    /// When importing, every ready block goes to the consensus loop instead,
+    /// which executes it and drops those that do not extend its chain.
+    async fn flush_ready_blocks(&mut self) {
+        if let Some(import) = &self.import {
+            for (number, (peer_id, header, body)) in std::mem::take(&mut self.ready_blocks) {
+                let block = ImportedBlock { peer_id, header, transactions: body.transactions };
+                if import.send(block).is_err() {
+                    tracing::warn!("Block import stopped, dropping block {}", number);
+                }
+            }
+            return;
+        }
+
+        let mut next = self.block_store.latest_block_number() + 1;
+
+        // Blocks that fail to persist stay queued and are retried on the next flush
+        while let Some((peer_id, header, body)) = self.ready_blocks.remove(&next) {
+            let Some(parent) = self.block_store.get_block_by_number(next - 1) else {
+                tracing::error!("Parent of synced block {} is missing", next);
+                self.ready_blocks.insert(next, (peer_id, header, body));
+                break;
+            };
+            let (block, tx_data) = synced_block(&header, &body);
+            if let Err(e) = verify_synced_block(&block, &parent, &self.validators) {
+                tracing::warn!("Rejecting block {} from {}: {}", next, peer_id, e);
+                self.ban_peer(peer_id).await;
+                break;
+            }
+
+            if !tx_data.is_empty() {
+                if let Err(e) = self.block_store.store_transactions(&tx_data) {
+                    tracing::error!("Failed to store transactions for block {}: {}", next, e);
+                    self.ready_blocks.insert(next, (peer_id, header, body));
+                    break;
+                }
+            }
+
+            let (hash, tx_count) = (block.hash, block.transaction_hashes.len());
+            match self.block_store.store_block(block.clone()) {
+                Ok(_) => {
+                    tracing::info!("Synced block {}: hash={:?}, txs={}", next, hash, tx_count);
+                    if let Some(rpc_server) = &self.evm_rpc_server {
+                        rpc_server.notify_block(next);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Failed to store synced block {}: {}", next, e);
+                    self.ready_blocks.insert(next, (peer_id, header, body));
+                    break;
+                }
+            }
+            next += 1;
+        }
+    }
+
+    /// Handle BlockBodies response - create and store complete blocks
+    ///
+    /// A response answers the request the peer sent it for, matched by request
+    /// ID. Blocks the peer left out are queued again; after an empty response
+    /// they are requested from another peer, if there is one.
+    async fn handle_block_bodies(
+        &mut self,
+        peer_id: PeerId,
+        request_id: u64,
+        bodies: Vec<BlockBody>,
+    ) {
+        let Some((chunk, sent)) = self.inflight_body_chunks.remove(&(peer_id, request_id)) else {
+            tracing::warn!(
+                "Received {} block bodies from {} for unknown request {}",
+                bodies.len(), peer_id, request_id
+            );
+            return;
+        };
+        self.latencies.record(peer_id, sent.elapsed(), bodies.len());
+
+        let retry_peer = if bodies.is_empty() {
+            tracing::debug!("Peer {} has none of the {} requested bodies", peer_id, chunk.len());
+            self.sync_target().map(|(target, _)| target).filter(|target| *target != peer_id)
+        } else {
+            Some(peer_id)
+        };
+
+        tracing::info!("Received {} of {} requested block bodies", bodies.len(), chunk.len());
+
+        if bodies.len() > chunk.len() {
+            tracing::warn!("Received more bodies than requested");
+        }
+
+        let answered = bodies.len().min(chunk.len());
+        let mut invalid = false;
+        for (&block_num, body) in chunk.iter().zip(bodies) {
+            if let Some(header) = self.pending_body_requests.remove(&block_num) {
+                if !body_matches_header(&header, &body) {
+                    // The header is dropped too; the block is synced again from headers
+                    tracing::warn!(
+                        "Rejecting body of block {} from {}: transactions do not match header",
+                        block_num, peer_id
+                    );
+                    invalid = true;
+                    continue;
+                }
+                if let Err(e) = check_body_limits(&self.limits, &body) {
+                    tracing::warn!(
+                        "Rejecting body of block {} from {}: {}",
+                        block_num, peer_id, e
+                    );
+                    invalid = true;
+                    continue;
+                }
+                self.ready_blocks.insert(block_num, (peer_id, header, body));
+            } else {
+                tracing::warn!("Received body for unknown block {}", block_num);
+            }
+        }
+        self.requeue_bodies(chunk[answered..].to_vec());
+
+        if invalid {
+            self.ban_peer(peer_id).await;
+        }
+        self.flush_ready_blocks().await;
+        if let Some(retry_peer) = retry_peer {
+            self.request_bodies(retry_peer).await;
+        }
+
+        // Log sync progress
+        let latest = self.block_store.latest_block_number();
+        tracing::info!("Sync progress: latest block = {}", latest);
+
+        // Continue sync if some peer has more blocks
+        if let Some((target, head)) = self.sync_target() {
+            let idle =
+                self.pending_header_requests.is_empty() && self.pending_body_requests.is_empty();
+            if latest < head && idle {
+                tracing::info!(
+                    "Continuing sync from {}: our latest={}, peer head={}",
+                    target, latest, head
+                );
+                self.request_headers(target, head).await;
+            }
+        }
+    }
+}
+
+/// Run fullnode sync loop
+///
+/// Besides blocks, the DexVM state is copied from peers serving `dexvm/1`,
+/// checked every [`DEXVM_SYNC_INTERVAL`]. Headers must be signed by the
+/// validator in turn among `validators`; with none, every header is refused.
+pub async fn run_fullnode_sync(
+    p2p_handle: P2pHandle,
+    block_store: Arc<dyn BlockWriter>,
+    evm_rpc_server: Option<Arc<EvmRpcServer>>,
+    limits: BlockLimits,
+    validators: Vec<Address>,
+    mut dexvm: DexVmStateSync,
+) -> eyre::Result<()> {
+    let mut sync_manager = BlockSyncManager::new(
+        p2p_handle.clone(),
+        block_store,
+        evm_rpc_server,
+        limits,
+        validators,
+    );
+    let mut events = p2p_handle.subscribe();
+    let mut dexvm_interval = tokio::time::interval(DEXVM_SYNC_INTERVAL);
+
+    tracing::info!("Starting fullnode sync handler");
+
+    loop {
+        let received = tokio::select! {
+            received = events.recv() => received,
+            _ = dexvm_interval.tick() => {
+                dexvm.check_peers().await;
+                continue;
+            }
+        };
+        match received {
+            Ok(event) => match event {
+                P2pEvent::PeerConnected { peer_id, addr } => {
+                    tracing::info!("Peer connected: {} from {}", peer_id, addr);
+                    // Request initial sync from the connected peer
+                    sync_manager.request_initial_sync(peer_id).await;
+                }
+                P2pEvent::PeerDisconnected { peer_id } => {
+                    tracing::info!("Peer disconnected: {}", peer_id);
+                    sync_manager.handle_peer_disconnected(peer_id).await;
+                    dexvm.handle_peer_disconnected(peer_id);
+                }
+                P2pEvent::NewBlockHash { peer_id, hash, number } => {
+                    sync_manager.handle_new_block_hash(peer_id, hash, number).await;
+                }
+                P2pEvent::BlockHeaders { peer_id, request_id: _, headers } => {
+                    sync_manager.handle_block_headers(peer_id, headers).await;
+                }
+                P2pEvent::BlockBodies { peer_id, request_id, bodies } => {
+                    sync_manager.handle_block_bodies(peer_id, request_id, bodies).await;
+                }
+                P2pEvent::DexVm { peer_id, message } => {
+                    dexvm.handle_message(peer_id, message).await;
+                }
+                _ => {}
+            },
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                tracing::warn!("P2P event receiver lagged {} events", n);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                tracing::info!("P2P event channel closed");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Run validator P2P event handler - responds to block header/body and DexVM state requests
+///
+/// Gossiped transactions are handed to `tx_ingest`, or ignored without it.
+/// With `import`, the blocks of the other validators are fetched for the
+/// consensus loop.
+pub async fn run_validator_p2p_handler(
+    p2p_handle: P2pHandle,
+    block_store: Arc<dyn BlockWriter>,
+    mut tx_ingest: Option<TxIngest>,
+    mut dexvm: DexVmStateSync,
+    import: Option<BlockImport>,
+) -> eyre::Result<()> {
+    let mut events = p2p_handle.subscribe();
+    let mut sync = import.map(|import| {
+        BlockSyncManager::importing(p2p_handle.clone(), Arc::clone(&block_store), import)
+    });
+
+    tracing::info!("Starting validator P2P event handler");
+
+    loop {
+        match events.recv().await {
+            Ok(event) => match event {
+                P2pEvent::PeerConnected { peer_id, addr } => {
+                    tracing::info!("Peer connected: {} from {}", peer_id, addr);
+                    if let Some(sync) = &mut sync {
+                        sync.request_initial_sync(peer_id).await;
+                    }
+                }
+                P2pEvent::PeerDisconnected { peer_id } => {
+                    tracing::info!("Peer disconnected: {}", peer_id);
+                    if let Some(ingest) = &mut tx_ingest {
+                        ingest.remove_peer(&peer_id);
+                    }
+                    if let Some(sync) = &mut sync {
+                        sync.handle_peer_disconnected(peer_id).await;
+                    }
+                }
+                P2pEvent::NewBlockHash { peer_id, hash, number } => {
+                    tracing::debug!(
+                        "Received NewBlockHash from {}: block {} hash {:?}",
+                        peer_id, number, hash
+                    );
+                    match &mut sync {
+                        Some(sync) => sync.handle_new_block_hash(peer_id, hash, number).await,
+                        // A lone validator doesn't need to sync - it produces blocks - but
+                        // a competing block points at a second validator or a fork
+                        None => {
+                            note_announced_block(&p2p_handle, &block_store, peer_id, hash, number)
+                                .await
+                        }
+                    }
+                }
+                P2pEvent::BlockHeaders { peer_id, request_id: _, headers } => match &mut sync {
+                    Some(sync) => sync.handle_block_headers(peer_id, headers).await,
+                    // Only requested for sibling blocks
+                    None => {
+                        let signed = headers.iter().filter(|h| has_canonical_signature(h));
+                        for header in signed {
+                            record_sibling_header(&block_store, peer_id, header);
+                        }
+                    }
+                },
+                P2pEvent::BlockBodies { peer_id, request_id, bodies } => {
+                    if let Some(sync) = &mut sync {
+                        sync.handle_block_bodies(peer_id, request_id, bodies).await;
+                    }
+                }
+                P2pEvent::GetBlockHeadersRequest {
+                    peer_id,
+                    request_id,
+                    start,
+                    limit,
+                    skip,
+                    direction,
+                } => {
+                    tracing::info!(
+                        "Peer {} requesting {} headers from {:?}, skip {}, {:?}",
+                        peer_id, limit, start, skip, direction
+                    );
+
+                    let headers =
+                        serve_block_headers(&block_store, start, limit, skip, direction);
+
+                    if !headers.is_empty() {
+                        tracing::info!("Sending {} headers to peer {}", headers.len(), peer_id);
+                        let cmd = SessionCommand::SendBlockHeaders {
+                            peer_id,
+                            request_id,
+                            headers,
+                        };
+                        if let Err(e) = p2p_handle.send_command(cmd).await {
+                            tracing::warn!("Failed to send headers to peer {}: {}", peer_id, e);
+                        }
+                    } else {
+                        tracing::debug!("No headers found for request from peer {}", peer_id);
+                    }
+                }
+                P2pEvent::GetBlockBodiesRequest { peer_id, request_id, hashes } => {
+                    tracing::info!(
+                        "Peer {} requesting {} block bodies",
+                        peer_id, hashes.len()
+                    );
+
+                    // Look up transactions for each requested block hash
+                    let mut bodies: Vec<BlockBody> = Vec::with_capacity(hashes.len());
+
+                    for block_hash in &hashes {
+                        // Find the block by hash
+                        if let Some(block) = block_store.get_block_by_hash(*block_hash) {
+                            // Get full transactions from storage
+                            let mut transactions = Vec::new();
+                            for tx_hash in &block.transaction_hashes {
+                                if let Some(tx_rlp) = block_store.get_transaction(*tx_hash) {
+                                    // Decode the transaction
+                                    if let Ok(tx) = TransactionSigned::decode(&mut tx_rlp.as_slice()) {
+                                        transactions.push(tx);
+                                    } else {
+                                        tracing::warn!("Failed to decode transaction {:?}", tx_hash);
+                                    }
+                                }
+                            }
+
+                            tracing::debug!(
+                                "Block {} has {} transactions",
+                                block.number, transactions.len()
+                            );
+
+                            bodies.push(BlockBody {
+                                transactions,
+                                ommers: vec![],
+                                withdrawals: None,
+                            });
+                        } else {
+                            // Block not found, send empty body
+                            tracing::debug!("Block {:?} not found", block_hash);
+                            bodies.push(BlockBody {
+                                transactions: vec![],
+                                ommers: vec![],
+                                withdrawals: None,
+                            });
+                        }
+                    }
+
+                    let total_txs: usize = bodies.iter().map(|b| b.transactions.len()).sum();
+                    tracing::info!(
+                        "Sending {} bodies with {} total transactions to peer {}",
+                        bodies.len(), total_txs, peer_id
+                    );
+                    let cmd = SessionCommand::SendBlockBodies {
+                        peer_id,
+                        request_id,
+                        bodies,
+                    };
+                    if let Err(e) = p2p_handle.send_command(cmd).await {
+                        tracing::warn!("Failed to send bodies to peer {}: {}", peer_id, e);
+                    }
+                }
+                P2pEvent::Transactions { peer_id, transactions } => {
+                    tracing::debug!(
+                        "Received {} transactions from peer {}",
+                        transactions.len(), peer_id
+                    );
+                    let Some(ingest) = &mut tx_ingest else { continue };
+                    if ingest.submit(peer_id, transactions) == PeerVerdict::Disconnect {
+                        tracing::warn!(
+                            "Peer {} keeps sending transactions over its budget, disconnecting",
+                            peer_id
+                        );
+                        let reason = DisconnectReason::UselessPeer;
+                        let cmd = SessionCommand::DisconnectPeer { peer_id, reason };
+                        if let Err(e) = p2p_handle.send_command(cmd).await {
+                            tracing::warn!("Failed to disconnect peer {}: {}", peer_id, e);
+                        }
+                    }
+                }
+                P2pEvent::DexVm { peer_id, message } => {
+                    dexvm.handle_message(peer_id, message).await;
+                }
+                _ => {}
+            },
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                tracing::warn!("P2P event receiver lagged {} events", n);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => {
+                tracing::info!("P2P event channel closed");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Forward locally submitted transactions to connected peers
+pub async fn run_transaction_forwarder(p2p_handle: P2pHandle, mut rx: mpsc::Receiver<Vec<u8>>) {
+    tracing::info!("Starting transaction broadcast handler");
+    while let Some(tx_rlp) = rx.recv().await {
+        tracing::debug!("Broadcasting transaction to peers");
+        let cmd = SessionCommand::BroadcastTransactions { transactions: vec![tx_rlp] };
+        if let Err(e) = p2p_handle.send_command(cmd).await {
+            tracing::warn!("Failed to broadcast transaction: {}", e);
+        }
+    }
+}
+
+/// Keep peer count and highest announced peer block up to date for `/status`
+///
+/// The highest block is the best head among the peers connected now, so it
+/// falls back when the peer that announced it disconnects.
+pub async fn run_status_tracker(p2p_handle: P2pHandle, status: Arc<NodeStatus>) {
+    let mut events = p2p_handle.subscribe();
+    let update = |status: &NodeStatus| {
+        status.set_peer_count(p2p_handle.connected_count());
+        status.set_highest_peer_block(p2p_handle.best_peer().map_or(0, |(_, head)| head));
+    };
+    update(&status);
+
+    loop {
+        match events.recv().await {
+            Ok(
+                P2pEvent::PeerConnected { .. } |
+                P2pEvent::PeerDisconnected { .. } |
+                P2pEvent::NewBlockHash { .. } |
+                P2pEvent::NewBlock { .. },
+            ) |
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => update(&status),
+            Ok(_) => {}
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_storage::BlockReader;
+
+    fn header(number: u64, gas_used: u64) -> ConsensusHeader {
+        ConsensusHeader { number, gas_used, ..Default::default() }
+    }
+
+    #[test]
+    fn test_header_signature_must_be_canonical() {
+        let mut signature = BlockSignature {
+            r: B256::repeat_byte(0x11),
+            s: B256::repeat_byte(0x22),
+            v: 1,
+        };
+        let mut synced = header(1, 0);
+        assert!(has_canonical_signature(&synced));
+
+        synced.extra_data = signature.to_bytes().to_vec().into();
+        assert!(has_canonical_signature(&synced));
+        assert_eq!(header_signature(&synced), signature);
+
+        signature.s = B256::repeat_byte(0xee);
+        synced.extra_data = signature.to_bytes().to_vec().into();
+        assert!(!has_canonical_signature(&synced));
+    }
+
+    #[test]
+    fn test_body_must_match_header() {
+        use alloy_consensus::TxLegacy;
+        use alloy_primitives::Signature;
+
+        let tx = |nonce| {
+            TransactionSigned::new_unhashed(
+                TxLegacy { nonce, gas_limit: 21000, ..Default::default() }.into(),
+                Signature::test_signature(),
+            )
+        };
+        let body = |txs: Vec<TransactionSigned>| BlockBody { transactions: txs, ..Default::default() };
+
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.transaction_hashes = vec![*tx(0).tx_hash(), *tx(1).tx_hash()];
+        let header = block_header(&block);
+
+        assert!(body_matches_header(&header, &body(vec![tx(0), tx(1)])));
+        // Injected, missing and reordered transactions are all caught
+        assert!(!body_matches_header(&header, &body(vec![tx(0), tx(1), tx(2)])));
+        assert!(!body_matches_header(&header, &body(vec![tx(0)])));
+        assert!(!body_matches_header(&header, &body(vec![tx(1), tx(0)])));
+
+        // Empty blocks keep the empty trie root
+        let empty = block_header(&StoredBlock::genesis(1));
+        assert_eq!(empty.transactions_root, keccak256([0x80]));
+        assert!(body_matches_header(&empty, &body(vec![])));
+        assert!(!body_matches_header(&empty, &body(vec![tx(0)])));
+    }
+
+    #[test]
+    fn test_body_over_block_limits_is_rejected() {
+        use alloy_consensus::TxLegacy;
+        use alloy_primitives::{Bytes, Signature};
+
+        let tx = |input_len| {
+            let input = Bytes::from(vec![0xab; input_len]);
+            TransactionSigned::new_unhashed(
+                TxLegacy { gas_limit: 21000, input, ..Default::default() }.into(),
+                Signature::test_signature(),
+            )
+        };
+        let body = |txs: Vec<TransactionSigned>| BlockBody { transactions: txs, ..Default::default() };
+        let limits = BlockLimits { max_bytes: 4_096, max_txs: 2 };
+
+        assert!(check_body_limits(&limits, &body(vec![tx(100), tx(100)])).is_ok());
+        assert!(check_body_limits(&limits, &body(vec![tx(100), tx(100), tx(100)])).is_err());
+        assert!(check_body_limits(&limits, &body(vec![tx(4_096)])).is_err());
+    }
+
+    #[test]
+    fn test_body_chunks_limit_count() {
+        let headers: HashMap<u64, ConsensusHeader> = (1..=150).map(|n| (n, header(n, 0))).collect();
+        let mut queue: VecDeque<u64> = (1..=150).collect();
+
+        assert_eq!(next_body_chunk(&mut queue, &headers), (1..=64).collect::<Vec<_>>());
+        assert_eq!(next_body_chunk(&mut queue, &headers), (65..=128).collect::<Vec<_>>());
+        assert_eq!(next_body_chunk(&mut queue, &headers), (129..=150).collect::<Vec<_>>());
+        assert!(next_body_chunk(&mut queue, &headers).is_empty());
+    }
+
+    #[test]
+    fn test_body_chunks_limit_size() {
+        // Each body is estimated at just over half the byte limit
+        let gas_used = (MAX_BODY_REQUEST_BYTES as u64 / 2) * 4;
+        let headers: HashMap<u64, ConsensusHeader> =
+            (1..=3).map(|n| (n, header(n, gas_used))).collect();
+        let mut queue: VecDeque<u64> = (1..=3).collect();
+
+        assert_eq!(next_body_chunk(&mut queue, &headers), vec![1]);
+        assert_eq!(next_body_chunk(&mut queue, &headers), vec![2]);
+        assert_eq!(next_body_chunk(&mut queue, &headers), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_body_over_block_limits_bans_its_peer() {
+        use alloy_consensus::TxLegacy;
+        use alloy_primitives::Signature;
+
+        let config = dex_p2p::P2pConfig::default().with_port(0);
+        let handle = dex_p2p::P2pService::new(config).start().await.unwrap();
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        let limits = BlockLimits { max_bytes: 4_096, max_txs: 1 };
+        let mut manager =
+            BlockSyncManager::new(handle.clone(), storage.blocks.clone(), None, limits, vec![]);
+
+        let txs: Vec<TransactionSigned> = (0..2)
+            .map(|nonce| {
+                TransactionSigned::new_unhashed(
+                    TxLegacy { nonce, gas_limit: 21000, ..Default::default() }.into(),
+                    Signature::test_signature(),
+                )
+            })
+            .collect();
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.transaction_hashes = txs.iter().map(|tx| *tx.tx_hash()).collect();
+        manager.pending_body_requests.insert(1, block_header(&block));
+        manager.body_queue.push_back(1);
+
+        let peer = PeerId::repeat_byte(1);
+        manager.request_bodies(peer).await;
+        let body = BlockBody { transactions: txs, ..Default::default() };
+        manager.handle_block_bodies(peer, 0, vec![body]).await;
+        assert!(manager.ready_blocks.is_empty());
+
+        for _ in 0..50 {
+            if handle.is_banned(&peer) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("peer sending an over-limit body was not banned");
+    }
+
+    #[tokio::test]
+    async fn test_body_responses_match_their_request() {
+        let service = dex_p2p::P2pService::new(dex_p2p::P2pConfig::default().with_port(0));
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        let mut manager = BlockSyncManager::new(
+            service.handle(),
+            storage.blocks.clone(),
+            None,
+            BlockLimits::default(),
+            vec![],
+        );
+        let empty_root = keccak256([0x80]);
+        for number in 1..=3 {
+            let header = ConsensusHeader { transactions_root: empty_root, ..header(number, 0) };
+            manager.pending_body_requests.insert(number, header);
+            manager.body_queue.push_back(number);
+        }
+
+        let (peer, other) = (PeerId::repeat_byte(1), PeerId::repeat_byte(2));
+        manager.request_bodies(peer).await;
+        assert_eq!(manager.inflight_body_chunks[&(peer, 0)].0, vec![1, 2, 3]);
+
+        // Bodies from another peer, or for another request, are not taken as the answer
+        manager.handle_block_bodies(other, 0, vec![BlockBody::default()]).await;
+        manager.handle_block_bodies(peer, 7, vec![BlockBody::default()]).await;
+        assert!(manager.pending_body_requests.contains_key(&1));
+
+        // A short response gets the rest requested again
+        manager.handle_block_bodies(peer, 0, vec![BlockBody::default()]).await;
+        assert!(!manager.pending_body_requests.contains_key(&1));
+        assert_eq!(manager.inflight_body_chunks[&(peer, 1)].0, vec![2, 3]);
+
+        // After an empty response the blocks stay queued for another peer
+        manager.handle_block_bodies(peer, 1, vec![]).await;
+        assert!(manager.inflight_body_chunks.is_empty());
+        assert_eq!(manager.body_queue, [2, 3]);
+        assert!(manager.pending_body_requests.contains_key(&2));
+
+        // So do the bodies asked of a peer that leaves
+        manager.request_bodies(other).await;
+        assert!(manager.body_queue.is_empty());
+        manager.handle_peer_disconnected(other).await;
+        assert!(manager.inflight_body_chunks.is_empty());
+        assert_eq!(manager.body_queue, [2, 3]);
+    }
+
+    #[test]
+    fn test_recent_announcements() {
+        let mut recent = RecentAnnouncements::new();
+        assert!(recent.insert(B256::repeat_byte(1)));
+        assert!(!recent.insert(B256::repeat_byte(1)));
+        recent.remove(&B256::repeat_byte(1));
+        assert!(recent.insert(B256::repeat_byte(1)));
+
+        for n in 0..RECENT_ANNOUNCEMENTS_CAPACITY as u64 {
+            recent.insert(B256::left_padding_from(&n.to_be_bytes()));
+        }
+        assert_eq!(recent.order.len(), RECENT_ANNOUNCEMENTS_CAPACITY);
+        assert!(recent.insert(B256::repeat_byte(1)));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_announcement_is_ignored() {
+        let service = dex_p2p::P2pService::new(dex_p2p::P2pConfig::default().with_port(0));
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        let mut manager = BlockSyncManager::new(
+            service.handle(),
+            storage.blocks.clone(),
+            None,
+            BlockLimits::default(),
+            vec![],
+        );
+
+        let (first, second) = (PeerId::repeat_byte(1), PeerId::repeat_byte(2));
+        let hash = B256::repeat_byte(0xbb);
+        manager.handle_new_block_hash(first, hash, 1).await;
+        assert_eq!(manager.request_peer, Some(first));
+        assert!(manager.pending_header_requests.contains(&1));
+
+        // The headers arrive, then the same block is announced by another peer
+        manager.pending_header_requests.clear();
+        manager.handle_new_block_hash(second, hash, 1).await;
+        assert!(manager.pending_header_requests.is_empty());
+        assert_eq!(manager.request_peer, Some(first));
+
+        // A new block is requested again
+        manager.handle_new_block_hash(second, B256::repeat_byte(0xcc), 2).await;
+        assert_eq!(manager.request_peer, Some(second));
+    }
+
+    #[test]
+    fn test_serve_block_headers() {
+        use HeadersDirection::{Falling, Rising};
+
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        let mut parent = StoredBlock::genesis(1);
+        storage.blocks.store_block(parent.clone()).unwrap();
+        for number in 1..=10 {
+            let (block, _) = synced_block(
+                &ConsensusHeader { number, parent_hash: parent.hash, ..Default::default() },
+                &BlockBody::default(),
+            );
+            storage.blocks.store_block(block.clone()).unwrap();
+            parent = block;
+        }
+
+        let numbers = |start, limit, skip, direction| -> Vec<u64> {
+            serve_block_headers(storage.blocks.as_ref(), start, limit, skip, direction)
+                .iter()
+                .map(|header| header.number)
+                .collect()
+        };
+
+        assert_eq!(numbers(HashOrNumber::Number(2), 3, 0, Rising), vec![2, 3, 4]);
+        assert_eq!(numbers(HashOrNumber::Number(2), 3, 0, Falling), vec![2, 1, 0]);
+        assert_eq!(numbers(HashOrNumber::Number(1), 4, 2, Rising), vec![1, 4, 7, 10]);
+        assert_eq!(numbers(HashOrNumber::Number(9), 5, 3, Falling), vec![9, 5, 1]);
+        // Stops at the head, or below genesis
+        assert_eq!(numbers(HashOrNumber::Number(6), 5, 1, Rising), vec![6, 8, 10]);
+        assert_eq!(numbers(HashOrNumber::Number(1), 5, 0, Falling), vec![1, 0]);
+        assert!(numbers(HashOrNumber::Number(11), 5, 0, Rising).is_empty());
+        assert!(numbers(HashOrNumber::Number(5), 0, 1, Rising).is_empty());
+        // Huge skips do not overflow
+        assert_eq!(numbers(HashOrNumber::Number(3), 3, u64::MAX, Rising), vec![3]);
+
+        // Hash starts resolve to the block's number
+        assert_eq!(numbers(HashOrNumber::Hash(parent.hash), 2, 4, Falling), vec![10, 5]);
+        assert!(numbers(HashOrNumber::Hash(B256::repeat_byte(0xee)), 2, 0, Rising).is_empty());
+    }
+
+    #[test]
+    fn test_record_sibling_header() {
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        let key = secp256k1::SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let validator = crate::consensus::secret_key_to_address(&key);
+
+        let signed_header = |timestamp| {
+            let mut proposal = BlockProposal {
+                number: 1,
+                parent_hash: B256::ZERO,
+                timestamp,
+                transactions: vec![],
+                proposer: validator,
+                signature: BlockSignature::default(),
+            };
+            proposal.sign(&key);
+            ConsensusHeader {
+                number: 1,
+                timestamp,
+                beneficiary: validator,
+                extra_data: proposal.signature.to_bytes().to_vec().into(),
+                ..Default::default()
+            }
+        };
+        let local = signed_header(1);
+        let (block, _) = synced_block(&local, &BlockBody::default());
+        storage.blocks.store_block(block).unwrap();
+        let peer = PeerId::repeat_byte(0x07);
+
+        // The stored block itself and unknown heights are not siblings
+        assert!(record_sibling_header(storage.blocks.as_ref(), peer, &local));
+        assert!(!record_sibling_header(storage.blocks.as_ref(), peer, &header(2, 0)));
+        assert!(storage.blocks.get_siblings_range(0, 2).is_empty());
+
+        let competing = signed_header(2);
+        assert!(record_sibling_header(storage.blocks.as_ref(), peer, &competing));
+        let siblings = storage.blocks.get_siblings_range(1, 1);
+        let recorded = &siblings[0].1.blocks[0];
+        assert_eq!(recorded.hash, header_hash(&competing));
+        assert_eq!(recorded.signer, Some(validator));
+        assert_eq!(recorded.peer, peer);
+    }
+
+    #[test]
+    fn test_header_must_come_from_the_proposer_in_turn() {
+        let keys = [0x42, 0x43].map(|byte| secp256k1::SecretKey::from_slice(&[byte; 32]).unwrap());
+        let validators = keys.map(|key| crate::consensus::secret_key_to_address(&key)).to_vec();
+        let signed_header = |number, key: &secp256k1::SecretKey| {
+            let mut proposal = header_proposal(&header(number, 0), vec![]);
+            proposal.proposer = crate::consensus::secret_key_to_address(key);
+            proposal.sign(key);
+            ConsensusHeader {
+                number,
+                beneficiary: proposal.proposer,
+                extra_data: proposal.signature.to_bytes().to_vec().into(),
+                ..Default::default()
+            }
+        };
+
+        // Block 3 is the second validator's turn
+        assert!(check_proposer(&validators, &signed_header(3, &keys[1])).is_ok());
+        assert!(check_proposer(&validators, &signed_header(3, &keys[0])).is_err());
+        assert!(check_proposer(&validators, &signed_header(4, &keys[0])).is_ok());
+
+        // The beneficiary alone does not make a proposer
+        let mut forged = signed_header(3, &keys[0]);
+        forged.beneficiary = validators[1];
+        assert!(check_proposer(&validators, &forged).is_err());
+
+        // Without a validator set no signer is accepted
+        assert!(check_proposer(&[], &signed_header(3, &keys[0])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_synced_block_is_not_stored() {
+        let service = dex_p2p::P2pService::new(dex_p2p::P2pConfig::default().with_port(0));
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        let genesis = StoredBlock::genesis(1);
+        storage.blocks.store_block(genesis.clone()).unwrap();
+        let key = secp256k1::SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let validators = vec![crate::consensus::secret_key_to_address(&key)];
+        let mut manager = BlockSyncManager::new(
+            service.handle(),
+            storage.blocks.clone(),
+            None,
+            BlockLimits::default(),
+            validators.clone(),
+        );
+
+        let mut block = StoredBlock { number: 1, parent_hash: genesis.hash, ..genesis };
+        block.miner = validators[0];
+        block.timestamp = 12;
+        let mut proposal = header_proposal(&block_header(&block), vec![]);
+        proposal.sign(&key);
+        block.signature = proposal.signature.to_bytes();
+        let valid = block_header(&block);
+
+        // A header whose signature does not cover it gets its peer's blocks dropped
+        let (bad, good) = (PeerId::repeat_byte(1), PeerId::repeat_byte(2));
+        let forged = ConsensusHeader { timestamp: 13, ..valid.clone() };
+        manager.ready_blocks.insert(1, (bad, forged, BlockBody::default()));
+        manager.ready_blocks.insert(2, (bad, header(2, 0), BlockBody::default()));
+        manager.flush_ready_blocks().await;
+        assert_eq!(storage.blocks.latest_block_number(), 0);
+        assert!(manager.ready_blocks.is_empty());
+
+        manager.ready_blocks.insert(1, (good, valid, BlockBody::default()));
+        manager.flush_ready_blocks().await;
+        assert_eq!(storage.blocks.latest_block_number(), 1);
+        assert_eq!(storage.blocks.get_block_by_number(1).unwrap().miner, validators[0]);
+    }
+
+    #[test]
+    fn test_sync_peer_prefers_fast_peers_that_have_the_blocks() {
+        let slow = PeerId::repeat_byte(1);
+        let fast = PeerId::repeat_byte(2);
+        let short = PeerId::repeat_byte(3);
+        let mut latencies = PeerLatencies::default();
+        assert_eq!(select_sync_peer(&[], 0, &latencies), None);
+
+        // Unmeasured peers tie, so the higher head wins
+        let heads = [(slow, 1000), (fast, 900), (short, 100)];
+        assert_eq!(select_sync_peer(&heads, 0, &latencies), Some((slow, 1000)));
+
+        latencies.record(slow, Duration::from_millis(500), 10);
+        latencies.record(fast, Duration::from_millis(100), 10);
+        latencies.record(short, Duration::from_millis(1), 10);
+        assert_eq!(select_sync_peer(&heads, 0, &latencies), Some((fast, 900)));
+
+        // Near the tip only peers with all missing blocks qualify
+        assert_eq!(select_sync_peer(&heads, 950, &latencies), Some((slow, 1000)));
+
+        // A slow response raises the average; a disconnect forgets it
+        latencies.record(fast, Duration::from_secs(10), 10);
+        assert_eq!(select_sync_peer(&heads, 0, &latencies), Some((slow, 1000)));
+        latencies.forget(&fast);
+        assert_eq!(latencies.cost(&fast), 0.0);
+    }
+}
@@ -0,0 +1,259 @@
+//! Ingestion of transactions gossiped by peers
+//!
+//! Decoding a gossiped transaction and recovering its signer is work any peer
+//! can make the node do for free, and the validator's P2P event loop also
+//! answers header, body and DexVM state requests. [`TxIngest`] keeps that work
+//! off the loop: each peer has a token bucket of transactions per second, the
+//! transactions within it queue in batches for a fixed pool of workers, and
+//! the rest are dropped, as are batches that find the queue full. Going over
+//! the budget is a strike against the peer; a peer with [`MAX_STRIKES`] recent
+//! strikes is disconnected.
+
+use alloy_consensus::transaction::SignerRecoverable;
+use alloy_rlp::Decodable;
+use dex_p2p::PeerId;
+use dex_rpc::{EvmRpcServer, MempoolConfig, PendingTransaction};
+use reth_ethereum_primitives::TransactionSigned;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::{
+    mpsc::{self, error::TrySendError},
+    Mutex,
+};
+
+/// Default transactions per second a peer may gossip
+pub const DEFAULT_PEER_TX_RATE: u32 = 200;
+
+/// Default transactions a peer may gossip at once after a quiet spell
+pub const DEFAULT_PEER_TX_BURST: u32 = 2_000;
+
+/// Default number of ingestion workers
+pub const DEFAULT_TX_INGEST_WORKERS: usize = 2;
+
+/// Strikes within [`STRIKE_WINDOW`] of each other that get a peer disconnected
+pub const MAX_STRIKES: u32 = 5;
+
+/// A peer's strikes are forgotten after this long without a new one
+const STRIKE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Batches that may wait for a worker, per worker
+const QUEUED_BATCHES_PER_WORKER: usize = 32;
+
+/// Limits on transactions gossiped by peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxIngestConfig {
+    /// Transactions per second each peer may gossip
+    pub peer_rate: u32,
+    /// Transactions a peer may gossip at once after a quiet spell
+    pub peer_burst: u32,
+    /// Tasks decoding transactions and recovering their signers
+    pub workers: usize,
+}
+
+impl Default for TxIngestConfig {
+    fn default() -> Self {
+        Self {
+            peer_rate: DEFAULT_PEER_TX_RATE,
+            peer_burst: DEFAULT_PEER_TX_BURST,
+            workers: DEFAULT_TX_INGEST_WORKERS,
+        }
+    }
+}
+
+/// What to do with a peer after taking in its transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerVerdict {
+    Keep,
+    /// The peer kept going over its budget
+    Disconnect,
+}
+
+/// A peer's token bucket and strikes
+#[derive(Debug)]
+struct PeerBudget {
+    tokens: f64,
+    refilled: Instant,
+    strikes: u32,
+    last_strike: Option<Instant>,
+}
+
+impl PeerBudget {
+    fn new(config: &TxIngestConfig, now: Instant) -> Self {
+        Self { tokens: config.peer_burst as f64, refilled: now, strikes: 0, last_strike: None }
+    }
+
+    /// Take up to `count` transactions out of the bucket, returning how many fit
+    fn take(&mut self, config: &TxIngestConfig, count: usize, now: Instant) -> usize {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * config.peer_rate as f64).min(config.peer_burst as f64);
+        self.refilled = now;
+        let taken = (self.tokens as usize).min(count);
+        self.tokens -= taken as f64;
+        taken
+    }
+
+    /// Record a strike, returning the number of recent ones
+    fn strike(&mut self, now: Instant) -> u32 {
+        if self.last_strike.is_some_and(|last| now.saturating_duration_since(last) > STRIKE_WINDOW)
+        {
+            self.strikes = 0;
+        }
+        self.strikes += 1;
+        self.last_strike = Some(now);
+        self.strikes
+    }
+}
+
+/// Rate limits gossiped transactions and hands them to the ingestion workers
+#[derive(Debug)]
+pub struct TxIngest {
+    config: TxIngestConfig,
+    budgets: HashMap<PeerId, PeerBudget>,
+    queue: mpsc::Sender<(PeerId, Vec<Vec<u8>>)>,
+}
+
+impl TxIngest {
+    /// Start the workers, which add the transactions they recover to `rpc_server`'s mempool
+    ///
+    /// The workers stop once the returned value is dropped.
+    pub fn spawn(config: TxIngestConfig, rpc_server: Arc<EvmRpcServer>) -> Self {
+        let workers = config.workers.max(1);
+        let (queue, batches) = mpsc::channel(workers * QUEUED_BATCHES_PER_WORKER);
+        let batches = Arc::new(Mutex::new(batches));
+        for _ in 0..workers {
+            tokio::spawn(run_worker(Arc::clone(&batches), Arc::clone(&rpc_server)));
+        }
+        Self { config, budgets: HashMap::new(), queue }
+    }
+
+    /// Queue the transactions a peer gossiped, up to its budget
+    pub fn submit(&mut self, peer_id: PeerId, transactions: Vec<Vec<u8>>) -> PeerVerdict {
+        self.submit_at(peer_id, transactions, Instant::now())
+    }
+
+    /// Forget a disconnected peer
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.budgets.remove(peer_id);
+    }
+
+    fn submit_at(
+        &mut self,
+        peer_id: PeerId,
+        mut transactions: Vec<Vec<u8>>,
+        now: Instant,
+    ) -> PeerVerdict {
+        let config = self.config;
+        let budget = self.budgets.entry(peer_id).or_insert_with(|| PeerBudget::new(&config, now));
+        let admitted = budget.take(&config, transactions.len(), now);
+        let mut verdict = PeerVerdict::Keep;
+        if admitted < transactions.len() {
+            let strikes = budget.strike(now);
+            tracing::debug!(
+                "Dropping {} transactions over the budget of peer {} (strike {})",
+                transactions.len() - admitted,
+                peer_id,
+                strikes
+            );
+            transactions.truncate(admitted);
+            if strikes >= MAX_STRIKES {
+                verdict = PeerVerdict::Disconnect;
+            }
+        }
+
+        if !transactions.is_empty() {
+            match self.queue.try_send((peer_id, transactions)) {
+                Ok(()) => {}
+                Err(TrySendError::Full((_, dropped))) => tracing::debug!(
+                    "Transaction ingestion queue full, dropping {} transactions from peer {}",
+                    dropped.len(),
+                    peer_id
+                ),
+                Err(TrySendError::Closed(_)) => {
+                    tracing::warn!("Transaction ingestion workers stopped")
+                }
+            }
+        }
+        verdict
+    }
+}
+
+/// Take batches off the queue until it closes, adding their transactions to the mempool
+async fn run_worker(
+    batches: Arc<Mutex<mpsc::Receiver<(PeerId, Vec<Vec<u8>>)>>>,
+    rpc_server: Arc<EvmRpcServer>,
+) {
+    loop {
+        let Some((peer_id, batch)) = batches.lock().await.recv().await else {
+            break;
+        };
+        let mempool = rpc_server.mempool_config();
+        let recovered = tokio::task::spawn_blocking(move || recover_batch(&mempool, batch)).await;
+        match recovered {
+            Ok(transactions) => {
+                let added = rpc_server.add_recovered_transactions_from_p2p(transactions);
+                if added > 0 {
+                    tracing::info!("Added {} transactions to mempool from peer {}", added, peer_id);
+                }
+            }
+            Err(e) => tracing::error!("Transaction ingestion worker failed: {}", e),
+        }
+    }
+}
+
+/// Decode a batch of gossiped transactions and recover their signers, dropping any that fail
+fn recover_batch(mempool: &MempoolConfig, batch: Vec<Vec<u8>>) -> Vec<PendingTransaction> {
+    batch
+        .into_iter()
+        .filter_map(|tx_rlp| {
+            // Oversized transactions are dropped undecoded
+            mempool.check_encoded_size(tx_rlp.len()).ok()?;
+            let tx = TransactionSigned::decode(&mut tx_rlp.as_slice()).ok()?;
+            let from = tx.recover_signer().ok()?;
+            Some(PendingTransaction::new(tx, from))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ingest(config: TxIngestConfig) -> (TxIngest, mpsc::Receiver<(PeerId, Vec<Vec<u8>>)>) {
+        let (queue, batches) = mpsc::channel(4);
+        (TxIngest { config, budgets: HashMap::new(), queue }, batches)
+    }
+
+    #[test]
+    fn test_peer_budget_limits_and_disconnects_flooders() {
+        let config = TxIngestConfig { peer_rate: 10, peer_burst: 20, workers: 1 };
+        let (mut ingest, mut batches) = ingest(config);
+        let (flooder, quiet) = (PeerId::repeat_byte(1), PeerId::repeat_byte(2));
+        let start = Instant::now();
+
+        // The burst is admitted, the rest of the message dropped
+        let verdict = ingest.submit_at(flooder, vec![vec![0]; 25], start);
+        assert_eq!(verdict, PeerVerdict::Keep);
+        assert_eq!(batches.try_recv().unwrap().1.len(), 20);
+
+        // Half a second refills five transactions
+        ingest.submit_at(flooder, vec![vec![0]; 25], start + Duration::from_millis(500));
+        assert_eq!(batches.try_recv().unwrap().1.len(), 5);
+
+        // Other peers have budgets of their own
+        ingest.submit_at(quiet, vec![vec![0]; 3], start);
+        assert_eq!(batches.try_recv().unwrap(), (quiet, vec![vec![0]; 3]));
+
+        let verdicts: Vec<_> =
+            (0..3).map(|_| ingest.submit_at(flooder, vec![vec![0]; 25], start)).collect();
+        assert_eq!(verdicts.last(), Some(&PeerVerdict::Disconnect));
+
+        // Strikes are forgotten after a quiet minute
+        let later = start + STRIKE_WINDOW + Duration::from_secs(1);
+        ingest.submit_at(flooder, vec![vec![0]; 30], later);
+        assert_eq!(ingest.budgets[&flooder].strikes, 1);
+    }
+}
@@ -0,0 +1,429 @@
+//! Offline chain consistency verification
+//!
+//! Walks stored blocks from genesis and re-derives what can be recomputed:
+//! header hashes, parent links, proposer signatures, transaction indexes and
+//! state roots. Stops at the first divergent block. [`verify_head`] runs the
+//! block checks on the most recent blocks only, for startup after an unclean
+//! shutdown, and [`verify_synced_block`] runs the header checks on a block
+//! received from a peer before sync stores it.
+
+use crate::{
+    consensus::{round_robin_proposer, BlockProposal, BlockSignature},
+    header::{block_header, header_hash},
+    node::load_dexvm_state,
+};
+use alloy_primitives::{keccak256, Address, B256};
+use alloy_rlp::Decodable;
+use dex_storage::{BlockReader, StateReader, StoredBlock};
+use reth_ethereum_primitives::TransactionSigned;
+use std::fmt;
+
+/// Why a block failed verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// Block is missing from the blocks table
+    MissingBlock,
+    /// Block number does not follow the previous block's
+    NumberGap { parent: u64, number: u64 },
+    /// Parent hash does not match the previous block's hash
+    ParentHashMismatch { expected: B256, stored: B256 },
+    /// Stored hash does not match the recomputed header hash
+    HeaderHashMismatch { stored: B256, computed: B256 },
+    /// Signature is missing or malformed
+    InvalidSignature,
+    /// Signature was not made by the block's miner
+    SignerMismatch { miner: Address, signer: Address },
+    /// Block was signed by a validator whose turn it was not
+    UnexpectedProposer { expected: Address, signer: Address },
+    /// Transaction body is missing
+    MissingTransaction(B256),
+    /// Transaction hash index points elsewhere
+    TxIndexMismatch { tx_hash: B256, indexed: Option<u64> },
+    /// Stored transaction body hashes to a different value
+    TxHashMismatch { stored: B256, computed: B256 },
+    /// Combined root is not keccak256(evm_root || dexvm_root)
+    CombinedRootMismatch { stored: B256, computed: B256 },
+    /// EVM state does not match the latest block's EVM root
+    EvmStateRootMismatch { stored: B256, computed: B256 },
+    /// DexVM counters do not match the latest block's DexVM root
+    DexVmStateRootMismatch { stored: B256, computed: B256 },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBlock => write!(f, "block missing"),
+            Self::NumberGap { parent, number } => {
+                write!(f, "block {} does not follow previous block {}", number, parent)
+            }
+            Self::ParentHashMismatch { expected, stored } => {
+                write!(f, "parent hash {} does not match previous block {}", stored, expected)
+            }
+            Self::HeaderHashMismatch { stored, computed } => {
+                write!(f, "stored hash {} but header hashes to {}", stored, computed)
+            }
+            Self::InvalidSignature => write!(f, "missing or malformed block signature"),
+            Self::SignerMismatch { miner, signer } => {
+                write!(f, "signed by {} but miner is {}", signer, miner)
+            }
+            Self::UnexpectedProposer { expected, signer } => {
+                write!(f, "signed by {} but it was {}'s turn", signer, expected)
+            }
+            Self::MissingTransaction(hash) => write!(f, "transaction {} body missing", hash),
+            Self::TxIndexMismatch { tx_hash, indexed } => {
+                write!(f, "transaction {} indexed to block {:?}", tx_hash, indexed)
+            }
+            Self::TxHashMismatch { stored, computed } => {
+                write!(f, "transaction {} body hashes to {}", stored, computed)
+            }
+            Self::CombinedRootMismatch { stored, computed } => {
+                write!(f, "combined root {} but roots combine to {}", stored, computed)
+            }
+            Self::EvmStateRootMismatch { stored, computed } => {
+                write!(f, "EVM root {} but state recomputes to {}", stored, computed)
+            }
+            Self::DexVmStateRootMismatch { stored, computed } => {
+                write!(f, "DexVM root {} but counters recompute to {}", stored, computed)
+            }
+        }
+    }
+}
+
+/// Outcome of a chain verification
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    /// Latest block number in the database
+    pub latest_block: u64,
+    /// Number of blocks that passed verification
+    pub blocks_verified: u64,
+    /// First divergent block and the reason, if any
+    pub divergence: Option<(u64, Divergence)>,
+}
+
+impl VerifyReport {
+    /// Whether the whole chain verified
+    pub fn is_ok(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+/// Verify every stored block from genesis to the latest block
+pub fn verify_chain(blocks: &dyn BlockReader, state: &dyn StateReader) -> VerifyReport {
+    verify_from(blocks, Some(state), 0)
+}
+
+/// Verify the latest `depth` blocks without recomputing state roots
+///
+/// Checks that they decode, link to their parents, are signed by their miner
+/// and that their transactions are stored and indexed. `blocks_verified`
+/// counts only the blocks checked.
+pub fn verify_head(blocks: &dyn BlockReader, depth: u64) -> VerifyReport {
+    let latest_block = blocks.latest_block_number();
+    verify_from(blocks, None, (latest_block + 1).saturating_sub(depth))
+}
+
+/// Verify blocks `from..=latest`, and the latest state if `state` is given
+fn verify_from(
+    blocks: &dyn BlockReader,
+    state: Option<&dyn StateReader>,
+    from: u64,
+) -> VerifyReport {
+    let latest_block = blocks.latest_block_number();
+    let diverged = |number: u64, divergence| VerifyReport {
+        latest_block,
+        blocks_verified: number.saturating_sub(from),
+        divergence: Some((number, divergence)),
+    };
+
+    // The first block's parent link is checked against the block below it
+    let mut parent = match from.checked_sub(1) {
+        Some(number) => match blocks.get_block_by_number(number) {
+            Some(block) => Some(block),
+            None => return diverged(number, Divergence::MissingBlock),
+        },
+        None => None,
+    };
+
+    for number in from..=latest_block {
+        let Some(block) = blocks.get_block_by_number(number) else {
+            return diverged(number, Divergence::MissingBlock);
+        };
+
+        if let Err(divergence) = verify_block(blocks, &block, parent.as_ref()) {
+            return diverged(number, divergence);
+        }
+
+        if let Some(state) = state.filter(|_| number == latest_block) {
+            if let Err(divergence) = verify_latest_state(state, &block) {
+                return diverged(number, divergence);
+            }
+        }
+
+        parent = Some(block);
+    }
+
+    VerifyReport { latest_block, blocks_verified: latest_block + 1 - from, divergence: None }
+}
+
+/// Whether the block recorded separate EVM and DexVM roots
+///
+/// Blocks imported by sync only carry the header's combined root.
+fn has_separate_roots(block: &StoredBlock) -> bool {
+    block.evm_state_root != block.combined_state_root
+}
+
+fn combine_roots(evm_root: B256, dexvm_root: B256) -> B256 {
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(evm_root.as_slice());
+    data.extend_from_slice(dexvm_root.as_slice());
+    keccak256(&data)
+}
+
+/// Check a block received from a peer before it is stored on top of `parent`
+///
+/// The block must follow `parent`, reproduce its hash from the header the node
+/// serves for it and be signed by its miner; with `validators`, the miner must
+/// be the validator whose turn it was. Its transactions are not stored yet.
+pub fn verify_synced_block(
+    block: &StoredBlock,
+    parent: &StoredBlock,
+    validators: &[Address],
+) -> Result<(), Divergence> {
+    let signer = verify_header(block, parent)?;
+    match round_robin_proposer(validators, block.number) {
+        Some(expected) if expected != signer => {
+            Err(Divergence::UnexpectedProposer { expected, signer })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn verify_block(
+    blocks: &dyn BlockReader,
+    block: &StoredBlock,
+    parent: Option<&StoredBlock>,
+) -> Result<(), Divergence> {
+    // Genesis has a synthetic hash and no signature
+    let Some(parent) = parent else {
+        return Ok(());
+    };
+    verify_header(block, parent)?;
+
+    for tx_hash in &block.transaction_hashes {
+        let indexed = blocks.get_tx_block_number(*tx_hash);
+        if indexed != Some(block.number) {
+            return Err(Divergence::TxIndexMismatch { tx_hash: *tx_hash, indexed });
+        }
+
+        let rlp =
+            blocks.get_transaction(*tx_hash).ok_or(Divergence::MissingTransaction(*tx_hash))?;
+        let computed = TransactionSigned::decode(&mut rlp.as_slice())
+            .map(|tx| *tx.tx_hash())
+            .unwrap_or_else(|_| keccak256(&rlp));
+        if computed != *tx_hash {
+            return Err(Divergence::TxHashMismatch { stored: *tx_hash, computed });
+        }
+    }
+
+    if has_separate_roots(block) {
+        let computed = combine_roots(block.evm_state_root, block.dexvm_state_root);
+        if computed != block.combined_state_root {
+            return Err(Divergence::CombinedRootMismatch {
+                stored: block.combined_state_root,
+                computed,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a block's number, parent link, hash and signature, returning its signer
+fn verify_header(block: &StoredBlock, parent: &StoredBlock) -> Result<Address, Divergence> {
+    if parent.number.checked_add(1) != Some(block.number) {
+        return Err(Divergence::NumberGap { parent: parent.number, number: block.number });
+    }
+
+    if block.parent_hash != parent.hash {
+        return Err(Divergence::ParentHashMismatch {
+            expected: parent.hash,
+            stored: block.parent_hash,
+        });
+    }
+
+    let computed = header_hash(&block_header(block));
+    if computed != block.hash {
+        return Err(Divergence::HeaderHashMismatch { stored: block.hash, computed });
+    }
+
+    let signature =
+        BlockSignature::from_bytes(&block.signature).ok_or(Divergence::InvalidSignature)?;
+    let proposal = BlockProposal {
+        number: block.number,
+        parent_hash: block.parent_hash,
+        timestamp: block.timestamp,
+        transactions: vec![],
+        proposer: block.miner,
+        signature,
+    };
+    let signer = proposal.recover_signer().ok_or(Divergence::InvalidSignature)?;
+    if signer != block.miner {
+        return Err(Divergence::SignerMismatch { miner: block.miner, signer });
+    }
+    Ok(signer)
+}
+
+/// Current state only reflects the latest block, so roots are recomputed there
+fn verify_latest_state(state: &dyn StateReader, block: &StoredBlock) -> Result<(), Divergence> {
+    if block.number == 0 || !has_separate_roots(block) {
+        return Ok(());
+    }
+
+    let computed = state.state_root();
+    if computed != block.evm_state_root {
+        return Err(Divergence::EvmStateRootMismatch { stored: block.evm_state_root, computed });
+    }
+
+    let computed = load_dexvm_state(state).state_root();
+    if computed != block.dexvm_state_root {
+        return Err(Divergence::DexVmStateRootMismatch { stored: block.dexvm_state_root, computed });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dex_storage::{BlockWriter, DualvmStorage};
+    use secp256k1::SecretKey;
+
+    fn signed_block(parent: &StoredBlock, key: &SecretKey) -> StoredBlock {
+        let miner = crate::consensus::secret_key_to_address(key);
+        let mut proposal = BlockProposal {
+            number: parent.number + 1,
+            parent_hash: parent.hash,
+            timestamp: parent.timestamp + 1,
+            transactions: vec![],
+            proposer: miner,
+            signature: BlockSignature::default(),
+        };
+        proposal.sign(key);
+
+        let mut block = StoredBlock {
+            number: proposal.number,
+            hash: B256::ZERO,
+            parent_hash: parent.hash,
+            timestamp: proposal.timestamp,
+            gas_limit: 30_000_000,
+            gas_used: 0,
+            miner,
+            evm_state_root: parent.evm_state_root,
+            dexvm_state_root: parent.dexvm_state_root,
+            combined_state_root: parent.combined_state_root,
+            transaction_hashes: vec![],
+            transaction_count: 0,
+            signature: proposal.signature.to_bytes(),
+        };
+        block.hash = header_hash(&block_header(&block));
+        block
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+
+        let genesis = StoredBlock::genesis(1);
+        storage.blocks.store_block(genesis.clone()).unwrap();
+        let block1 = signed_block(&genesis, &key);
+        storage.blocks.store_block(block1.clone()).unwrap();
+
+        let report = verify_chain(storage.blocks.as_ref(), storage.state.as_ref());
+        assert!(report.is_ok(), "{:?}", report.divergence);
+        assert_eq!(report.blocks_verified, 2);
+
+        // A block whose stored hash does not match its header
+        let mut block2 = signed_block(&block1, &key);
+        block2.gas_used = 21_000;
+        storage.blocks.store_block(block2.clone()).unwrap();
+
+        let report = verify_chain(storage.blocks.as_ref(), storage.state.as_ref());
+        assert_eq!(
+            report.divergence,
+            Some((
+                2,
+                Divergence::HeaderHashMismatch {
+                    stored: block2.hash,
+                    computed: header_hash(&block_header(&block2)),
+                }
+            ))
+        );
+        assert_eq!(report.blocks_verified, 2);
+
+        // Checking only the head still catches it, counting just the blocks checked
+        let report = verify_head(storage.blocks.as_ref(), 1);
+        assert_eq!(report.divergence.map(|(number, _)| number), Some(2));
+        assert_eq!(report.blocks_verified, 0);
+
+        // A block whose transaction body was never stored
+        let tx_hash = B256::repeat_byte(0xaa);
+        let mut block3 = signed_block(&block2, &key);
+        block3.transaction_hashes = vec![tx_hash];
+        block3.transaction_count = 1;
+        block3.hash = header_hash(&block_header(&block3));
+        storage.blocks.store_block(block3).unwrap();
+        let report = verify_head(storage.blocks.as_ref(), 1);
+        assert_eq!(report.divergence, Some((3, Divergence::MissingTransaction(tx_hash))));
+    }
+    #[test]
+    fn test_verify_synced_block() {
+        let key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let other = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let validators = [&other, &key].map(crate::consensus::secret_key_to_address);
+        let genesis = StoredBlock::genesis(1);
+        let block1 = signed_block(&genesis, &key);
+        assert_eq!(verify_synced_block(&block1, &genesis, &[]), Ok(()));
+
+        // Block 1 is the turn of the second validator
+        assert_eq!(verify_synced_block(&block1, &genesis, &validators), Ok(()));
+        let reversed = [validators[1], validators[0]];
+        assert_eq!(
+            verify_synced_block(&block1, &genesis, &reversed),
+            Err(Divergence::UnexpectedProposer { expected: validators[0], signer: validators[1] })
+        );
+
+        // A block signed by a key outside the set is refused, even if it names itself as miner
+        let stranger = SecretKey::from_slice(&[0x33; 32]).unwrap();
+        let forged = signed_block(&genesis, &stranger);
+        assert_eq!(
+            verify_synced_block(&forged, &genesis, &validators),
+            Err(Divergence::UnexpectedProposer {
+                expected: validators[1],
+                signer: crate::consensus::secret_key_to_address(&stranger),
+            })
+        );
+
+        // It must follow the stored head, by number and by hash
+        let block2 = signed_block(&block1, &other);
+        assert_eq!(
+            verify_synced_block(&block2, &genesis, &[]),
+            Err(Divergence::NumberGap { parent: 0, number: 2 })
+        );
+        let mut fork = genesis.clone();
+        fork.hash = B256::repeat_byte(0xf0);
+        assert!(matches!(
+            verify_synced_block(&block1, &fork, &[]),
+            Err(Divergence::ParentHashMismatch { .. })
+        ));
+
+        // Unsigned blocks are refused
+        let mut unsigned = block1.clone();
+        unsigned.signature = [0; 65];
+        unsigned.hash = header_hash(&block_header(&unsigned));
+        assert_eq!(
+            verify_synced_block(&unsigned, &genesis, &[]),
+            Err(Divergence::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Block production watchdog
+//!
+//! A validator whose consensus task exited, or whose proposals stopped turning
+//! into blocks, keeps serving RPC as if nothing happened. The watchdog owns the
+//! consensus task and checks how long ago the last block was finalized: after
+//! a configured number of missed block intervals it logs an error, marks
+//! `/health` degraded, counts the stall in `/status`, and optionally restarts
+//! the consensus task.
+
+use crate::consensus::PoaConsensus;
+use dex_rpc::NodeStatus;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+/// Default number of block intervals without a block before production counts as stalled
+pub const DEFAULT_STALL_INTERVALS: u32 = 5;
+
+/// When the watchdog alerts and what it does about a stall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    /// Block intervals without a finalized block before alerting
+    pub stall_intervals: u32,
+    /// Restart the consensus task when it exits or production stalls
+    pub restart_consensus: bool,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self { stall_intervals: DEFAULT_STALL_INTERVALS, restart_consensus: false }
+    }
+}
+
+impl WatchdogConfig {
+    /// Time without a finalized block after which production counts as stalled
+    pub fn stall_after(&self, block_interval: Duration) -> Duration {
+        block_interval * self.stall_intervals
+    }
+}
+
+/// Consensus task, aborted when dropped so it stops with the watchdog
+struct ConsensusTask(JoinHandle<()>);
+
+impl Drop for ConsensusTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Start `consensus` and watch the blocks it leads to until aborted
+pub async fn run_production_watchdog(
+    consensus: PoaConsensus,
+    status: Arc<NodeStatus>,
+    config: WatchdogConfig,
+) {
+    let block_interval = consensus.config().block_interval;
+    let stall_after = config.stall_after(block_interval);
+    let mut task = ConsensusTask(consensus.start());
+    let started = Instant::now();
+    let mut restarted_at = started;
+    let mut interval = tokio::time::interval(block_interval);
+
+    loop {
+        interval.tick().await;
+        // Startup work before the watchdog ran does not count against production
+        let since_last_block = status.since_last_block().min(started.elapsed());
+        let exited = task.0.is_finished();
+
+        if !exited && since_last_block <= stall_after {
+            continue;
+        }
+        if status.set_production_stalled() {
+            if exited {
+                tracing::error!("Consensus task exited; no more blocks will be proposed");
+            } else {
+                tracing::error!(
+                    "Block production stalled: no block finalized for {:?} ({} intervals)",
+                    since_last_block,
+                    config.stall_intervals
+                );
+            }
+        }
+
+        if config.restart_consensus && (exited || restarted_at.elapsed() > stall_after) {
+            tracing::warn!("Restarting the consensus task");
+            task = ConsensusTask(consensus.start());
+            restarted_at = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::PoaConfig;
+
+    const DEV_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[tokio::test]
+    async fn test_watchdog_alerts_without_blocks() {
+        let config = PoaConfig::from_hex_key(DEV_KEY, Duration::from_millis(10)).unwrap();
+        let consensus = PoaConsensus::new(config);
+        let status = Arc::new(NodeStatus::new());
+        let watchdog = WatchdogConfig { stall_intervals: 2, restart_consensus: false };
+        let task = tokio::spawn(run_production_watchdog(
+            consensus.clone(),
+            Arc::clone(&status),
+            watchdog,
+        ));
+
+        // Proposals keep coming, but nothing finalizes them into blocks
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(status.production_stalled());
+        assert!(consensus.recv_proposal().is_some());
+
+        status.record_block_produced();
+        assert!(!status.production_stalled());
+        task.abort();
+    }
+}
@@ -1,13 +1,19 @@
 //! P2P configuration
+//!
+//! The node listens on IPv4 by default. An IPv6 listen address takes IPv6
+//! peers only, unless `dual_stack` is set, in which case the same socket also
+//! accepts IPv4 peers. Enode URLs put IPv6 hosts in brackets
+//! (`enode://<id>@[::1]:30303`), both when parsed and when advertised.
 
-use alloy_primitives::B256;
-use reth_network_peers::TrustedPeer;
+use crate::session::GenesisPolicy;
+use alloy_primitives::{hex, B256};
+use reth_network_peers::{PeerId, TrustedPeer};
 use secp256k1::SecretKey;
 use std::{
     collections::HashSet,
     fs,
     io::{self, Write},
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::Path,
 };
 
@@ -21,6 +27,8 @@ pub struct P2pConfig {
     pub secret_key: SecretKey,
     /// Address to listen on
     pub listen_addr: SocketAddr,
+    /// Also accept IPv4 peers when `listen_addr` is an IPv6 address
+    pub dual_stack: bool,
     /// Chain ID
     pub chain_id: u64,
     /// Genesis block hash
@@ -31,6 +39,14 @@ pub struct P2pConfig {
     pub max_peers: usize,
     /// Network ID (same as chain ID for custom networks)
     pub network_id: u64,
+    /// Only allow sessions with peers in `trusted_peers`
+    pub trusted_only: bool,
+    /// Peer IDs allowed to connect in trusted-only mode
+    pub trusted_peers: HashSet<PeerId>,
+    /// Genesis hash and fork ID matching policy for peer handshakes
+    pub genesis_policy: GenesisPolicy,
+    /// Peers transactions are gossiped to, encrypted; empty floods them in the clear
+    pub private_tx_peers: HashSet<PeerId>,
 }
 
 impl P2pConfig {
@@ -39,11 +55,16 @@ impl P2pConfig {
         Self {
             secret_key,
             listen_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), DEFAULT_P2P_PORT),
+            dual_stack: false,
             chain_id,
             genesis_hash,
             boot_nodes: HashSet::new(),
             max_peers: 50,
             network_id: chain_id,
+            trusted_only: false,
+            trusted_peers: HashSet::new(),
+            genesis_policy: GenesisPolicy::Strict,
+            private_tx_peers: HashSet::new(),
         }
     }
 
@@ -106,6 +127,41 @@ impl P2pConfig {
         self
     }
 
+    /// Set the IP address to listen on, keeping the port
+    pub fn with_listen_ip(mut self, ip: IpAddr) -> Self {
+        self.listen_addr.set_ip(ip);
+        self
+    }
+
+    /// Also accept IPv4 peers on an IPv6 listen address
+    pub fn with_dual_stack(mut self, dual_stack: bool) -> Self {
+        self.dual_stack = dual_stack;
+        self
+    }
+
+    /// Check that dual-stack listening is only asked of an IPv6 address
+    pub fn validate_listen_addr(&self) -> Result<(), String> {
+        if self.dual_stack && self.listen_addr.is_ipv4() {
+            return Err(format!(
+                "dual-stack listening needs an IPv6 address, not {}",
+                self.listen_addr.ip()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Address to advertise in the enode URL
+    ///
+    /// An unspecified listen address advertises the loopback address of its family.
+    pub fn advertised_addr(&self) -> SocketAddr {
+        let ip = match self.listen_addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+        SocketAddr::new(ip, self.listen_addr.port())
+    }
+
     /// Add boot node
     pub fn with_boot_node(mut self, node: TrustedPeer) -> Self {
         self.boot_nodes.insert(node);
@@ -117,6 +173,47 @@ impl P2pConfig {
         self.max_peers = max;
         self
     }
+
+    /// Add trusted peer ID
+    pub fn with_trusted_peer(mut self, peer_id: PeerId) -> Self {
+        self.trusted_peers.insert(peer_id);
+        self
+    }
+
+    /// Restrict sessions to trusted peers only
+    pub fn with_trusted_only(mut self, trusted_only: bool) -> Self {
+        self.trusted_only = trusted_only;
+        self
+    }
+
+    /// Set the genesis matching policy
+    pub fn with_genesis_policy(mut self, policy: GenesisPolicy) -> Self {
+        self.genesis_policy = policy;
+        self
+    }
+
+    /// Gossip transactions encrypted to this peer instead of flooding them
+    pub fn with_private_tx_peer(mut self, peer_id: PeerId) -> Self {
+        self.private_tx_peers.insert(peer_id);
+        self
+    }
+
+    /// Whether transactions are gossiped only to private peers
+    pub fn private_tx_gossip(&self) -> bool {
+        !self.private_tx_peers.is_empty()
+    }
+
+    /// Peer allowlist to enforce, if trusted-only mode is enabled
+    pub fn peer_allowlist(&self) -> Option<HashSet<PeerId>> {
+        self.trusted_only.then(|| self.trusted_peers.clone())
+    }
+}
+
+/// Enode URL of `peer_id` at `addr`, with IPv6 hosts in brackets
+pub fn enode_url(peer_id: PeerId, addr: SocketAddr) -> String {
+    // IPv4-mapped IPv6 addresses are advertised as plain IPv4
+    let addr = SocketAddr::new(addr.ip().to_canonical(), addr.port());
+    format!("enode://{}@{}", hex::encode(peer_id.as_slice()), addr)
 }
 
 impl Default for P2pConfig {
@@ -149,4 +246,48 @@ mod tests {
         assert_eq!(config.listen_addr.port(), 30304);
         assert_eq!(config.max_peers, 100);
     }
+
+    #[test]
+    fn test_ipv6_listen_and_enode() {
+        let config = P2pConfig::default().with_listen_ip(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert!(config.clone().with_dual_stack(true).validate_listen_addr().is_err());
+        assert_eq!(config.advertised_addr().to_string(), "127.0.0.1:30303");
+
+        let config = config.with_listen_ip(IpAddr::V6(Ipv6Addr::UNSPECIFIED)).with_dual_stack(true);
+        assert!(config.validate_listen_addr().is_ok());
+        assert_eq!(config.listen_addr.port(), DEFAULT_P2P_PORT);
+        assert_eq!(config.advertised_addr().to_string(), "[::1]:30303");
+
+        // Bracketed IPv6 enodes parse back to the same peer
+        let peer_id = PeerId::repeat_byte(0x33);
+        let enode = enode_url(peer_id, "[fd00::7]:30304".parse().unwrap());
+        assert!(enode.ends_with("@[fd00::7]:30304"));
+        let peer: TrustedPeer = enode.parse().unwrap();
+        assert_eq!(peer.id, peer_id);
+        assert_eq!(peer.tcp_port, 30304);
+
+        let mapped = enode_url(peer_id, "[::ffff:10.0.0.1]:30303".parse().unwrap());
+        assert!(mapped.ends_with("@10.0.0.1:30303"));
+    }
+
+    #[test]
+    fn test_peer_allowlist() {
+        let peer_id = PeerId::repeat_byte(0x11);
+
+        let config = P2pConfig::default().with_trusted_peer(peer_id);
+        assert!(config.peer_allowlist().is_none());
+
+        let config = config.with_trusted_only(true);
+        assert_eq!(config.peer_allowlist(), Some(HashSet::from([peer_id])));
+    }
+
+    #[test]
+    fn test_private_tx_peers() {
+        let config = P2pConfig::default();
+        assert!(!config.private_tx_gossip());
+
+        let config = config.with_private_tx_peer(PeerId::repeat_byte(0x22));
+        assert!(config.private_tx_gossip());
+        assert!(config.private_tx_peers.contains(&PeerId::repeat_byte(0x22)));
+    }
 }
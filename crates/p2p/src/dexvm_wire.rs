@@ -0,0 +1,297 @@
+//! `dexvm/1` subprotocol for DexVM state sync
+//!
+//! Fullnodes do not execute blocks, so they cannot rebuild DexVM counters from
+//! the chain. Nodes advertise `dexvm/1` next to eth/68 in their hello message
+//! and answer requests for their committed DexVM state root and for pages of
+//! counters, letting a fullnode copy the state of a peer and check it against
+//! the root.
+//!
+//! Messages use the ids right after eth68's 17 messages. Each is the id byte
+//! followed by an RLP list of its fields. Counters are keyed by owner and
+//! label, the main counter of an owner being the one with the zero label, and
+//! are paged in key order.
+
+use alloy_primitives::{Address, B256};
+use alloy_rlp::{Decodable, Encodable, Header};
+use reth_eth_wire::Capability;
+
+/// First message id of `dexvm/1`, following eth68's messages
+pub const DEXVM_MESSAGE_OFFSET: u8 = 0x11;
+
+/// Number of message ids `dexvm/1` uses
+pub const DEXVM_MESSAGE_COUNT: u8 = 4;
+
+/// Most counters a peer sends in one [`DexVmMessage::Counters`] page
+pub const MAX_COUNTERS_PER_RESPONSE: u64 = 1024;
+
+const GET_STATE_ROOT_ID: u8 = DEXVM_MESSAGE_OFFSET;
+const STATE_ROOT_ID: u8 = DEXVM_MESSAGE_OFFSET + 1;
+const GET_COUNTERS_ID: u8 = DEXVM_MESSAGE_OFFSET + 2;
+const COUNTERS_ID: u8 = DEXVM_MESSAGE_OFFSET + 3;
+
+/// The `dexvm/1` capability advertised in the hello message
+pub fn dexvm_capability() -> Capability {
+    Capability::new_static("dexvm", 1)
+}
+
+/// Whether a message id belongs to `dexvm/1`
+pub fn is_dexvm_message(id: u8) -> bool {
+    (DEXVM_MESSAGE_OFFSET..DEXVM_MESSAGE_OFFSET + DEXVM_MESSAGE_COUNT).contains(&id)
+}
+
+/// Owner and label of a counter; the zero label is the owner's main counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CounterKey {
+    pub address: Address,
+    pub label: B256,
+}
+
+/// One counter in a [`DexVmMessage::Counters`] page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CounterEntry {
+    pub key: CounterKey,
+    pub value: u64,
+}
+
+/// A `dexvm/1` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DexVmMessage {
+    /// Ask for the committed DexVM state root
+    GetStateRoot { request_id: u64 },
+    /// DexVM state root after block `head`, with the EVM state root it combines with
+    StateRoot { request_id: u64, head: u64, evm_root: B256, root: B256 },
+    /// Ask for up to `limit` counters with keys after `after`, from the first without it
+    GetCounters { request_id: u64, after: Option<CounterKey>, limit: u64 },
+    /// A page of non-zero counters in key order, taken from the state with `root` at `head`
+    Counters {
+        request_id: u64,
+        head: u64,
+        evm_root: B256,
+        root: B256,
+        entries: Vec<CounterEntry>,
+        /// No counters follow this page
+        complete: bool,
+    },
+}
+
+impl DexVmMessage {
+    /// Request id the message carries
+    pub fn request_id(&self) -> u64 {
+        match self {
+            Self::GetStateRoot { request_id } |
+            Self::StateRoot { request_id, .. } |
+            Self::GetCounters { request_id, .. } |
+            Self::Counters { request_id, .. } => *request_id,
+        }
+    }
+
+    /// Encode as a message id followed by the RLP list of fields
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::GetStateRoot { request_id } => encode_message(GET_STATE_ROOT_ID, &[request_id]),
+            Self::StateRoot { request_id, head, evm_root, root } => {
+                encode_message(STATE_ROOT_ID, &[request_id, head, evm_root, root])
+            }
+            Self::GetCounters { request_id, after, limit } => {
+                let after: Vec<CounterKey> = after.iter().copied().collect();
+                encode_message(GET_COUNTERS_ID, &[request_id, &after, limit])
+            }
+            Self::Counters { request_id, head, evm_root, root, entries, complete } => {
+                encode_message(COUNTERS_ID, &[request_id, head, evm_root, root, entries, complete])
+            }
+        }
+    }
+
+    /// Decode a message produced by [`DexVmMessage::encode`]
+    pub fn decode(bytes: &[u8]) -> eyre::Result<Self> {
+        let (&id, mut buf) =
+            bytes.split_first().ok_or_else(|| eyre::eyre!("Empty dexvm message"))?;
+        let message = decode_fields(id, &mut buf)
+            .map_err(|e| eyre::eyre!("Malformed dexvm message {:#x}: {}", id, e))?;
+        if !buf.is_empty() {
+            return Err(eyre::eyre!("{} trailing bytes after dexvm message", buf.len()));
+        }
+        Ok(message)
+    }
+}
+
+fn encode_message(id: u8, fields: &[&dyn Encodable]) -> Vec<u8> {
+    let payload_length = fields.iter().map(|field| field.length()).sum();
+    let mut out = vec![id];
+    Header { list: true, payload_length }.encode(&mut out);
+    for field in fields {
+        field.encode(&mut out);
+    }
+    out
+}
+
+fn decode_fields(id: u8, buf: &mut &[u8]) -> alloy_rlp::Result<DexVmMessage> {
+    let mut payload = list_payload(buf)?;
+    let payload = &mut payload;
+    let message = match id {
+        GET_STATE_ROOT_ID => DexVmMessage::GetStateRoot { request_id: u64::decode(payload)? },
+        STATE_ROOT_ID => DexVmMessage::StateRoot {
+            request_id: u64::decode(payload)?,
+            head: u64::decode(payload)?,
+            evm_root: B256::decode(payload)?,
+            root: B256::decode(payload)?,
+        },
+        GET_COUNTERS_ID => {
+            let request_id = u64::decode(payload)?;
+            let after = Vec::<CounterKey>::decode(payload)?;
+            if after.len() > 1 {
+                return Err(alloy_rlp::Error::Custom("more than one start key"));
+            }
+            DexVmMessage::GetCounters {
+                request_id,
+                after: after.first().copied(),
+                limit: u64::decode(payload)?,
+            }
+        }
+        COUNTERS_ID => DexVmMessage::Counters {
+            request_id: u64::decode(payload)?,
+            head: u64::decode(payload)?,
+            evm_root: B256::decode(payload)?,
+            root: B256::decode(payload)?,
+            entries: Vec::decode(payload)?,
+            complete: bool::decode(payload)?,
+        },
+        _ => return Err(alloy_rlp::Error::Custom("unknown message id")),
+    };
+    if !payload.is_empty() {
+        return Err(alloy_rlp::Error::UnexpectedLength);
+    }
+    Ok(message)
+}
+
+/// Split the payload of the RLP list at the front of `buf` off it
+fn list_payload<'a>(buf: &mut &'a [u8]) -> alloy_rlp::Result<&'a [u8]> {
+    let header = Header::decode(buf)?;
+    if !header.list {
+        return Err(alloy_rlp::Error::UnexpectedString);
+    }
+    if buf.len() < header.payload_length {
+        return Err(alloy_rlp::Error::InputTooShort);
+    }
+    let (payload, rest) = buf.split_at(header.payload_length);
+    *buf = rest;
+    Ok(payload)
+}
+
+impl Encodable for CounterKey {
+    fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        Header { list: true, payload_length: self.address.length() + self.label.length() }
+            .encode(out);
+        self.address.encode(out);
+        self.label.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.address.length() + self.label.length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl Decodable for CounterKey {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = list_payload(buf)?;
+        let key =
+            Self { address: Address::decode(&mut payload)?, label: B256::decode(&mut payload)? };
+        if !payload.is_empty() {
+            return Err(alloy_rlp::Error::UnexpectedLength);
+        }
+        Ok(key)
+    }
+}
+
+impl Encodable for CounterEntry {
+    fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        Header { list: true, payload_length: self.key.length() + self.value.length() }.encode(out);
+        self.key.encode(out);
+        self.value.encode(out);
+    }
+
+    fn length(&self) -> usize {
+        let payload_length = self.key.length() + self.value.length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl Decodable for CounterEntry {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let mut payload = list_payload(buf)?;
+        let entry =
+            Self { key: CounterKey::decode(&mut payload)?, value: u64::decode(&mut payload)? };
+        if !payload.is_empty() {
+            return Err(alloy_rlp::Error::UnexpectedLength);
+        }
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages() -> Vec<DexVmMessage> {
+        let key = CounterKey { address: Address::repeat_byte(1), label: B256::ZERO };
+        let labeled = CounterKey { address: Address::repeat_byte(1), label: B256::repeat_byte(7) };
+        vec![
+            DexVmMessage::GetStateRoot { request_id: 1 },
+            DexVmMessage::StateRoot {
+                request_id: 2,
+                head: 40,
+                evm_root: B256::repeat_byte(0xbb),
+                root: B256::repeat_byte(0xaa),
+            },
+            DexVmMessage::GetCounters { request_id: 3, after: None, limit: 100 },
+            DexVmMessage::GetCounters { request_id: 4, after: Some(labeled), limit: 1 },
+            DexVmMessage::Counters {
+                request_id: 5,
+                head: 40,
+                evm_root: B256::repeat_byte(0xbb),
+                root: B256::repeat_byte(0xaa),
+                entries: vec![
+                    CounterEntry { key, value: 3 },
+                    CounterEntry { key: labeled, value: u64::MAX },
+                ],
+                complete: true,
+            },
+            DexVmMessage::Counters {
+                request_id: 6,
+                head: 0,
+                evm_root: B256::ZERO,
+                root: B256::ZERO,
+                entries: vec![],
+                complete: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        for message in messages() {
+            let encoded = message.encode();
+            assert!(is_dexvm_message(encoded[0]));
+            assert_eq!(DexVmMessage::decode(&encoded).unwrap(), message);
+        }
+        assert!(!is_dexvm_message(crate::PRIVATE_TRANSACTIONS_ID));
+        assert!(!is_dexvm_message(DEXVM_MESSAGE_OFFSET - 1));
+    }
+
+    #[test]
+    fn test_malformed_messages_rejected() {
+        for message in messages() {
+            let encoded = message.encode();
+            for len in 0..encoded.len() {
+                assert!(DexVmMessage::decode(&encoded[..len]).is_err());
+            }
+            let mut trailing = encoded.clone();
+            trailing.push(0x80);
+            assert!(DexVmMessage::decode(&trailing).is_err());
+            let mut unknown = encoded;
+            unknown[0] = DEXVM_MESSAGE_OFFSET + DEXVM_MESSAGE_COUNT;
+            assert!(DexVmMessage::decode(&unknown).is_err());
+        }
+    }
+}
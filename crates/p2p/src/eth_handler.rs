@@ -4,17 +4,19 @@ use alloy_consensus::Header as ConsensusHeader;
 use alloy_primitives::B256;
 use futures::{SinkExt, StreamExt};
 use reth_ecies::stream::ECIESStream;
-use reth_eth_wire::{EthVersion, P2PStream};
+use reth_eth_wire::{CanDisconnect, DisconnectReason, EthVersion, P2PStream};
 use reth_eth_wire_types::{
     BlockHashNumber, EthMessage, EthNetworkPrimitives, GetBlockBodies, GetBlockHeaders,
     HashOrNumber, HeadersDirection, NewBlockHashes, ProtocolMessage,
 };
 use reth_eth_wire::message::RequestPair;
-use reth_network_peers::PeerId;
-use tokio::{
-    net::TcpStream,
-    sync::mpsc,
+use crate::{
+    dexvm_wire::{is_dexvm_message, DexVmMessage},
+    private::{LinkKey, PRIVATE_TRANSACTIONS_ID},
+    transport::Transport,
 };
+use reth_network_peers::PeerId;
+use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, info, trace, warn};
 
 /// Events emitted by the ETH message handler
@@ -45,6 +47,9 @@ pub enum EthHandlerEvent {
         request_id: u64,
         start: HashOrNumber,
         limit: u64,
+        /// Blocks skipped between consecutive headers
+        skip: u64,
+        direction: HeadersDirection,
     },
     /// Received request for block bodies (validator should respond)
     GetBlockBodiesRequest {
@@ -57,6 +62,8 @@ pub enum EthHandlerEvent {
         peer_id: PeerId,
         transactions: Vec<Vec<u8>>, // RLP-encoded transactions
     },
+    /// Received a `dexvm/1` request or response
+    DexVm { peer_id: PeerId, message: DexVmMessage },
 }
 
 /// Commands that can be sent to the ETH handler
@@ -66,6 +73,9 @@ pub enum EthHandlerCommand {
     GetBlockHeaders {
         start: BlockHashOrNumber,
         limit: u64,
+        /// Blocks skipped between consecutive headers
+        skip: u64,
+        direction: HeadersDirection,
         request_id: u64,
     },
     /// Request block bodies from peer
@@ -76,6 +86,8 @@ pub enum EthHandlerCommand {
     /// Announce new block hashes to peer
     AnnounceBlocks {
         blocks: Vec<(B256, u64)>, // (hash, number)
+        /// Signalled once the announcement is written to the peer
+        ack: Option<oneshot::Sender<()>>,
     },
     /// Send block headers response
     SendBlockHeaders {
@@ -91,6 +103,14 @@ pub enum EthHandlerCommand {
     BroadcastTransactions {
         transactions: Vec<Vec<u8>>, // RLP-encoded transactions
     },
+    /// Send transactions to a private peer, encrypted under the link key
+    BroadcastPrivateTransactions {
+        transactions: Vec<Vec<u8>>, // RLP-encoded transactions
+    },
+    /// Send a `dexvm/1` request or response
+    SendDexVm { message: DexVmMessage },
+    /// Disconnect the peer and end the session
+    Disconnect { reason: DisconnectReason },
 }
 
 /// Block hash or number for header requests
@@ -101,9 +121,10 @@ pub enum BlockHashOrNumber {
 }
 
 /// Run the ETH message handler for a peer session
-pub async fn run_eth_handler(
+pub async fn run_eth_handler<Io: Transport>(
     peer_id: PeerId,
-    mut stream: P2PStream<ECIESStream<TcpStream>>,
+    mut stream: P2PStream<ECIESStream<Io>>,
+    link_key: LinkKey,
     mut command_rx: mpsc::Receiver<EthHandlerCommand>,
     event_tx: mpsc::Sender<EthHandlerEvent>,
 ) {
@@ -115,12 +136,22 @@ pub async fn run_eth_handler(
             msg_result = stream.next() => {
                 match msg_result {
                     Some(Ok(bytes)) => {
-                        if let Err(e) = handle_incoming_message(
-                            peer_id,
-                            &bytes,
-                            &event_tx,
-                        ).await {
-                            warn!("Error handling message from peer {}: {}", peer_id, e);
+                        let result = match bytes.first() {
+                            Some(&PRIVATE_TRANSACTIONS_ID) => {
+                                handle_private_transactions(peer_id, &link_key, &bytes, &event_tx)
+                                    .await
+                            }
+                            Some(&id) if is_dexvm_message(id) => {
+                                handle_dexvm_message(peer_id, &bytes, &event_tx).await
+                            }
+                            _ => handle_incoming_message(peer_id, &bytes, &event_tx).await,
+                        };
+                        if let Err(e) = result {
+                            // Malformed or out-of-place messages end the session
+                            warn!("Disconnecting peer {}: {}", peer_id, e);
+                            let _ = stream.disconnect(DisconnectReason::ProtocolBreach).await;
+                            let _ = event_tx.send(EthHandlerEvent::Disconnected { peer_id }).await;
+                            break;
                         }
                     }
                     Some(Err(e)) => {
@@ -138,8 +169,15 @@ pub async fn run_eth_handler(
 
             // Handle outgoing commands
             Some(cmd) = command_rx.recv() => {
+                if let EthHandlerCommand::Disconnect { reason } = cmd {
+                    warn!("Disconnecting peer {}: {}", peer_id, reason);
+                    let _ = stream.disconnect(reason).await;
+                    let _ = event_tx.send(EthHandlerEvent::Disconnected { peer_id }).await;
+                    break;
+                }
                 if let Err(e) = handle_command(
                     &mut stream,
+                    &link_key,
                     cmd,
                 ).await {
                     warn!("Error sending command to peer {}: {}", peer_id, e);
@@ -153,17 +191,56 @@ pub async fn run_eth_handler(
     info!("ETH handler stopped for peer {}", peer_id);
 }
 
+/// Open a private transactions message and forward its transactions
+///
+/// A message that fails authentication was not sealed by the peer on the
+/// other end of this link; the caller disconnects the peer.
+async fn handle_private_transactions(
+    peer_id: PeerId,
+    link_key: &LinkKey,
+    bytes: &[u8],
+    event_tx: &mpsc::Sender<EthHandlerEvent>,
+) -> eyre::Result<()> {
+    let transactions = link_key.open(bytes)?;
+    debug!("Received {} private transactions from peer {}", transactions.len(), peer_id);
+    event_tx.send(EthHandlerEvent::Transactions { peer_id, transactions }).await?;
+    Ok(())
+}
+
+/// Decode a `dexvm/1` message and forward it
+async fn handle_dexvm_message(
+    peer_id: PeerId,
+    bytes: &[u8],
+    event_tx: &mpsc::Sender<EthHandlerEvent>,
+) -> eyre::Result<()> {
+    let message = DexVmMessage::decode(bytes)?;
+    trace!("Received dexvm message {:?} from peer {}", message, peer_id);
+    event_tx.send(EthHandlerEvent::DexVm { peer_id, message }).await?;
+    Ok(())
+}
+
+/// Decode a message from the peer and forward it to the service
+///
+/// Fails on anything the peer should not have sent: bytes that do not decode
+/// as an eth68 message, or a second Status after the handshake. The caller
+/// disconnects the peer on error.
 async fn handle_incoming_message(
     peer_id: PeerId,
     bytes: &[u8],
     event_tx: &mpsc::Sender<EthHandlerEvent>,
 ) -> eyre::Result<()> {
-    let msg = ProtocolMessage::<EthNetworkPrimitives>::decode_message(
-        EthVersion::Eth68,
-        &mut &bytes[..],
-    )?;
+    let mut buf = bytes;
+    let msg = ProtocolMessage::<EthNetworkPrimitives>::decode_message(EthVersion::Eth68, &mut buf)
+        .map_err(|e| eyre::eyre!("Malformed {} byte message: {}", bytes.len(), e))?;
+    if !buf.is_empty() {
+        return Err(eyre::eyre!("{} trailing bytes after {:?}", buf.len(), msg.message_type));
+    }
 
     match msg.message {
+        EthMessage::Status(_) => {
+            return Err(eyre::eyre!("Unexpected Status message after handshake"));
+        }
+
         EthMessage::NewBlockHashes(hashes) => {
             trace!("Received NewBlockHashes from peer {}: {} hashes", peer_id, hashes.0.len());
             let blocks: Vec<_> = hashes.0.iter()
@@ -198,14 +275,22 @@ async fn handle_incoming_message(
 
         EthMessage::GetBlockHeaders(request) => {
             debug!(
-                "Received GetBlockHeaders from peer {}: request_id={}, start={:?}, limit={}",
-                peer_id, request.request_id, request.message.start_block, request.message.limit
+                "Received GetBlockHeaders from peer {}: request_id={}, start={:?}, limit={}, \
+                 skip={}, direction={:?}",
+                peer_id,
+                request.request_id,
+                request.message.start_block,
+                request.message.limit,
+                request.message.skip,
+                request.message.direction
             );
             event_tx.send(EthHandlerEvent::GetBlockHeadersRequest {
                 peer_id,
                 request_id: request.request_id,
                 start: request.message.start_block,
                 limit: request.message.limit,
+                skip: request.message.skip,
+                direction: request.message.direction,
             }).await?;
         }
 
@@ -248,23 +333,19 @@ async fn handle_incoming_message(
     Ok(())
 }
 
-async fn handle_command(
-    stream: &mut P2PStream<ECIESStream<TcpStream>>,
+async fn handle_command<Io: Transport>(
+    stream: &mut P2PStream<ECIESStream<Io>>,
+    link_key: &LinkKey,
     cmd: EthHandlerCommand,
 ) -> eyre::Result<()> {
     match cmd {
-        EthHandlerCommand::GetBlockHeaders { start, limit, request_id } => {
+        EthHandlerCommand::GetBlockHeaders { start, limit, skip, direction, request_id } => {
             let start_block = match start {
                 BlockHashOrNumber::Hash(hash) => HashOrNumber::Hash(hash),
                 BlockHashOrNumber::Number(num) => HashOrNumber::Number(num),
             };
 
-            let request = GetBlockHeaders {
-                start_block,
-                limit,
-                skip: 0,
-                direction: HeadersDirection::Falling,
-            };
+            let request = GetBlockHeaders { start_block, limit, skip, direction };
 
             let msg = ProtocolMessage::<EthNetworkPrimitives>::from(
                 EthMessage::GetBlockHeaders(RequestPair {
@@ -291,7 +372,7 @@ async fn handle_command(
             trace!("Sent GetBlockBodies request_id={}", request_id);
         }
 
-        EthHandlerCommand::AnnounceBlocks { blocks } => {
+        EthHandlerCommand::AnnounceBlocks { blocks, ack } => {
             let hashes: Vec<_> = blocks.into_iter()
                 .map(|(hash, number)| BlockHashNumber { hash, number })
                 .collect();
@@ -303,6 +384,9 @@ async fn handle_command(
             let encoded = alloy_rlp::encode(&msg);
             stream.send(encoded.into()).await?;
             trace!("Sent NewBlockHashes announcement");
+            if let Some(ack) = ack {
+                let _ = ack.send(());
+            }
         }
 
         EthHandlerCommand::SendBlockHeaders { request_id, headers } => {
@@ -355,6 +439,21 @@ async fn handle_command(
                 trace!("Broadcasted {} transactions", transactions.len());
             }
         }
+
+        EthHandlerCommand::BroadcastPrivateTransactions { transactions } => {
+            stream.send(link_key.seal(&transactions).into()).await?;
+            trace!("Sent {} private transactions", transactions.len());
+        }
+
+        EthHandlerCommand::SendDexVm { message } => {
+            stream.send(message.encode().into()).await?;
+            trace!("Sent dexvm message request_id={}", message.request_id());
+        }
+
+        EthHandlerCommand::Disconnect { reason } => {
+            // The session loop ends the session first; this only covers direct callers
+            stream.disconnect(reason).await?;
+        }
     }
 
     Ok(())
@@ -363,6 +462,85 @@ async fn handle_command(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        session::{accept_inbound, connect_outbound, SessionConfig},
+        transport::TcpDialer,
+    };
+    use alloy_chains::Chain;
+    use alloy_hardforks::{ForkHash, ForkId};
+    use alloy_primitives::U256;
+    use reth_eth_wire_types::{BlockBodies, BlockHeaders, Status, StatusMessage, Transactions};
+    use secp256k1::{SecretKey, SECP256K1};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    /// Deterministic xorshift generator for reproducible inputs
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> usize {
+            (self.next() % bound) as usize
+        }
+
+        fn bytes(&mut self, max_len: u64) -> Vec<u8> {
+            (0..self.below(max_len)).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    fn encode(message: EthMessage<EthNetworkPrimitives>) -> Vec<u8> {
+        alloy_rlp::encode(ProtocolMessage::<EthNetworkPrimitives>::from(message))
+    }
+
+    /// One valid encoding of every message the handler forwards
+    fn corpus() -> Vec<Vec<u8>> {
+        let hashes = vec![B256::repeat_byte(1), B256::repeat_byte(2)];
+        vec![
+            encode(EthMessage::NewBlockHashes(NewBlockHashes(vec![
+                BlockHashNumber { hash: hashes[0], number: 1 },
+                BlockHashNumber { hash: hashes[1], number: 2 },
+            ]))),
+            encode(EthMessage::GetBlockHeaders(RequestPair {
+                request_id: 7,
+                message: GetBlockHeaders {
+                    start_block: HashOrNumber::Number(1),
+                    limit: 16,
+                    skip: 0,
+                    direction: HeadersDirection::Rising,
+                },
+            })),
+            encode(EthMessage::GetBlockBodies(RequestPair {
+                request_id: 8,
+                message: GetBlockBodies(hashes),
+            })),
+            encode(EthMessage::BlockHeaders(RequestPair {
+                request_id: 7,
+                message: BlockHeaders(vec![ConsensusHeader::default(); 2]),
+            })),
+            encode(EthMessage::BlockBodies(RequestPair {
+                request_id: 8,
+                message: BlockBodies(vec![Default::default()]),
+            })),
+            encode(EthMessage::Transactions(Transactions(vec![]))),
+        ]
+    }
+
+    fn status() -> Status {
+        Status {
+            version: EthVersion::Eth68,
+            chain: Chain::from_id(1),
+            total_difficulty: U256::ZERO,
+            blockhash: B256::ZERO,
+            genesis: B256::ZERO,
+            forkid: ForkId { hash: ForkHash::from(B256::ZERO), next: 0 },
+        }
+    }
 
     #[test]
     fn test_block_hash_or_number() {
@@ -379,4 +557,236 @@ mod tests {
             _ => panic!("Expected Number variant"),
         }
     }
+
+    #[tokio::test]
+    async fn test_get_block_headers_fields_forwarded() {
+        let (event_tx, mut event_rx) = mpsc::channel(1);
+        let request = encode(EthMessage::GetBlockHeaders(RequestPair {
+            request_id: 3,
+            message: GetBlockHeaders {
+                start_block: HashOrNumber::Hash(B256::repeat_byte(9)),
+                limit: 5,
+                skip: 2,
+                direction: HeadersDirection::Falling,
+            },
+        }));
+        handle_incoming_message(PeerId::repeat_byte(1), &request, &event_tx).await.unwrap();
+
+        match event_rx.try_recv().unwrap() {
+            EthHandlerEvent::GetBlockHeadersRequest { request_id, limit, skip, direction, .. } => {
+                assert_eq!((request_id, limit, skip), (3, 5, 2));
+                assert_eq!(direction, HeadersDirection::Falling);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncated_messages_rejected() {
+        let peer_id = PeerId::repeat_byte(1);
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+
+        for message in corpus() {
+            handle_incoming_message(peer_id, &message, &event_tx).await.unwrap();
+            assert!(event_rx.try_recv().is_ok());
+
+            for len in 0..message.len() {
+                let result = handle_incoming_message(peer_id, &message[..len], &event_tx).await;
+                assert!(result.is_err(), "{} of {} bytes accepted", len, message.len());
+            }
+            assert!(event_rx.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_messages_rejected() {
+        let peer_id = PeerId::repeat_byte(1);
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+
+        // A second Status after the handshake
+        let status = encode(EthMessage::Status(StatusMessage::Legacy(status())));
+        assert!(handle_incoming_message(peer_id, &status, &event_tx).await.is_err());
+
+        // Unknown message ID and trailing garbage after a valid message
+        let mut unknown = corpus()[0].clone();
+        unknown[0] = 0x7f;
+        assert!(handle_incoming_message(peer_id, &unknown, &event_tx).await.is_err());
+        let mut trailing = corpus()[0].clone();
+        trailing.extend_from_slice(&[0xc0, 0x01]);
+        assert!(handle_incoming_message(peer_id, &trailing, &event_tx).await.is_err());
+
+        assert!(event_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_arbitrary_messages() {
+        let peer_id = PeerId::repeat_byte(1);
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let corpus = corpus();
+        let mut rng = Rng(0x5eed);
+
+        // Random bytes, half of them behind a known message ID
+        for _ in 0..20_000 {
+            let mut bytes = rng.bytes(300);
+            if rng.next() % 2 == 0 {
+                if let Some(first) = bytes.first_mut() {
+                    *first = rng.below(0x11) as u8;
+                }
+            }
+            let _ = handle_incoming_message(peer_id, &bytes, &event_tx).await;
+            while event_rx.try_recv().is_ok() {}
+        }
+
+        // Valid messages with one byte flipped
+        for _ in 0..5_000 {
+            let mut bytes = corpus[rng.below(corpus.len() as u64)].clone();
+            let index = rng.below(bytes.len() as u64);
+            bytes[index] ^= rng.next() as u8 | 1;
+            let _ = handle_incoming_message(peer_id, &bytes, &event_tx).await;
+            while event_rx.try_recv().is_ok() {}
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_message_disconnects_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_key = SecretKey::new(&mut rand::thread_rng());
+        let server_id = reth_network_peers::pk2id(&server_key.public_key(SECP256K1));
+        let server_config = SessionConfig::new(server_key, 1, B256::ZERO);
+        let client_config =
+            SessionConfig::new(SecretKey::new(&mut rand::thread_rng()), 1, B256::ZERO);
+
+        let server_handle = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            accept_inbound(stream, peer_addr, &server_config).await
+        });
+        let mut client = connect_outbound(&TcpDialer, addr, server_id, &client_config).await.unwrap();
+        let server = server_handle.await.unwrap().unwrap();
+
+        let (_command_tx, command_rx) = mpsc::channel(1);
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        tokio::spawn(run_eth_handler(
+            server.peer_id,
+            server.stream,
+            server.link_key,
+            command_rx,
+            event_tx,
+        ));
+
+        // A valid announcement is forwarded, a truncated one ends the session
+        let announcement = corpus().remove(0);
+        client.stream.send(announcement.clone().into()).await.unwrap();
+        let truncated = announcement[..announcement.len() / 2].to_vec();
+        client.stream.send(truncated.into()).await.unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let event = tokio::time::timeout(timeout, event_rx.recv()).await.unwrap();
+        assert!(matches!(event, Some(EthHandlerEvent::NewBlockHashes { .. })));
+        let event = tokio::time::timeout(timeout, event_rx.recv()).await.unwrap();
+        assert!(matches!(event, Some(EthHandlerEvent::Disconnected { .. })));
+
+        // The handler has stopped and the client sees the session end
+        assert!(tokio::time::timeout(timeout, event_rx.recv()).await.unwrap().is_none());
+        let closed = tokio::time::timeout(timeout, client.stream.next()).await;
+        assert!(!matches!(closed, Ok(Some(Ok(_)))));
+    }
+
+    #[tokio::test]
+    async fn test_private_transactions_need_the_link_key() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_key = SecretKey::new(&mut rand::thread_rng());
+        let server_id = reth_network_peers::pk2id(&server_key.public_key(SECP256K1));
+        let server_config = SessionConfig::new(server_key, 1, B256::ZERO);
+        let client_config =
+            SessionConfig::new(SecretKey::new(&mut rand::thread_rng()), 1, B256::ZERO);
+
+        let server_handle = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            accept_inbound(stream, peer_addr, &server_config).await
+        });
+        let mut client = connect_outbound(&TcpDialer, addr, server_id, &client_config).await.unwrap();
+        let server = server_handle.await.unwrap().unwrap();
+        assert_eq!(client.link_key, server.link_key);
+
+        let (_command_tx, command_rx) = mpsc::channel(1);
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        tokio::spawn(run_eth_handler(
+            server.peer_id,
+            server.stream,
+            server.link_key,
+            command_rx,
+            event_tx,
+        ));
+
+        // Sealed under the link key the transactions come through
+        let transactions = vec![vec![0x01; 40], vec![0x02; 80]];
+        client.stream.send(client.link_key.seal(&transactions).into()).await.unwrap();
+        let timeout = Duration::from_secs(5);
+        match tokio::time::timeout(timeout, event_rx.recv()).await.unwrap() {
+            Some(EthHandlerEvent::Transactions { transactions: received, .. }) => {
+                assert_eq!(received, transactions);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // Sealed under any other key the message ends the session
+        let other_key = SecretKey::new(&mut rand::thread_rng());
+        let forged = LinkKey::derive(&other_key, &server_id).unwrap().seal(&transactions);
+        client.stream.send(forged.into()).await.unwrap();
+        let event = tokio::time::timeout(timeout, event_rx.recv()).await.unwrap();
+        assert!(matches!(event, Some(EthHandlerEvent::Disconnected { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dexvm_messages_exchanged() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_key = SecretKey::new(&mut rand::thread_rng());
+        let server_id = reth_network_peers::pk2id(&server_key.public_key(SECP256K1));
+        let server_config = SessionConfig::new(server_key, 1, B256::ZERO);
+        let client_config =
+            SessionConfig::new(SecretKey::new(&mut rand::thread_rng()), 1, B256::ZERO);
+
+        let server_handle = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            accept_inbound(stream, peer_addr, &server_config).await
+        });
+        let mut client = connect_outbound(&TcpDialer, addr, server_id, &client_config).await.unwrap();
+        let server = server_handle.await.unwrap().unwrap();
+        assert!(server.capabilities.contains(&crate::dexvm_wire::dexvm_capability()));
+        assert!(client.capabilities.contains(&crate::dexvm_wire::dexvm_capability()));
+
+        let (command_tx, command_rx) = mpsc::channel(1);
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        tokio::spawn(run_eth_handler(
+            server.peer_id,
+            server.stream,
+            server.link_key,
+            command_rx,
+            event_tx,
+        ));
+
+        let request = DexVmMessage::GetStateRoot { request_id: 9 };
+        client.stream.send(request.encode().into()).await.unwrap();
+        let timeout = Duration::from_secs(5);
+        match tokio::time::timeout(timeout, event_rx.recv()).await.unwrap() {
+            Some(EthHandlerEvent::DexVm { message, .. }) => assert_eq!(message, request),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let response = DexVmMessage::StateRoot {
+            request_id: 9,
+            head: 3,
+            evm_root: B256::repeat_byte(2),
+            root: B256::repeat_byte(1),
+        };
+        command_tx.send(EthHandlerCommand::SendDexVm { message: response.clone() }).await.unwrap();
+        let bytes = tokio::time::timeout(timeout, client.stream.next()).await.unwrap().unwrap();
+        assert_eq!(DexVmMessage::decode(&bytes.unwrap()).unwrap(), response);
+    }
 }
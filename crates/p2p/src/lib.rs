@@ -6,19 +6,20 @@
 //!
 //! - Peer discovery and management
 //! - Eth protocol message handling
-//! - Transaction propagation
+//! - Transaction propagation, optionally encrypted to a set of private peers
 //! - Block announcement
+//! - DexVM state sync over the `dexvm/1` subprotocol
+//! - Pluggable transports: TCP, or an in-memory network for tests
 //!
 //! # Example
 //!
 //! ```ignore
-//! use dex_p2p::{P2pConfig, P2pService};
+//! use dex_p2p::{P2pConfig, P2pServiceBuilder};
 //!
-//! let config = P2pConfig::default()
-//!     .with_port(30303)
-//!     .with_max_peers(50);
-//!
-//! let service = P2pService::new(config);
+//! let service = P2pServiceBuilder::new(P2pConfig::default())
+//!     .port(30303)
+//!     .max_peers(50)
+//!     .build();
 //! let handle = service.start().await?;
 //!
 //! // Subscribe to events
@@ -29,18 +30,34 @@
 //! ```
 
 pub mod config;
+pub mod dexvm_wire;
 pub mod eth_handler;
 pub mod peer;
+pub mod private;
 pub mod service;
 pub mod session;
+pub mod transport;
 
-pub use config::{P2pConfig, DEFAULT_P2P_PORT};
+pub use config::{enode_url, P2pConfig, DEFAULT_P2P_PORT};
+pub use dexvm_wire::{
+    dexvm_capability, CounterEntry, CounterKey, DexVmMessage, MAX_COUNTERS_PER_RESPONSE,
+};
 pub use eth_handler::{BlockHashOrNumber, EthHandlerCommand, EthHandlerEvent};
 pub use peer::{PeerInfo, PeerManager, PeerState, SharedPeerManager};
-pub use service::{P2pEvent, P2pHandle, P2pService, P2pServiceBuilder, SessionCommand};
+pub use private::{LinkKey, PRIVATE_TRANSACTIONS_ID};
+pub use service::{
+    BroadcastReport, P2pEvent, P2pHandle, P2pService, P2pServiceBuilder, SessionCommand,
+    ANNOUNCE_ACK_TIMEOUT,
+};
+pub use session::GenesisPolicy;
+pub use transport::{
+    bind_tcp, BoxedTransport, Dialer, Listener, MemoryDialer, MemoryListener, MemoryNetwork,
+    TcpDialer, Transport,
+};
 
 /// Re-export reth network peer types
 pub use reth_network_peers::{pk2id, PeerId, TrustedPeer};
 
 /// Re-export ETH wire types
-pub use reth_eth_wire_types::HashOrNumber;
+pub use reth_eth_wire::DisconnectReason;
+pub use reth_eth_wire_types::{HashOrNumber, HeadersDirection};
@@ -7,7 +7,7 @@ use std::{
     collections::HashMap,
     net::SocketAddr,
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 /// Peer connection state
@@ -36,8 +36,12 @@ pub struct PeerInfo {
     pub client_version: Option<String>,
     /// Peer's chain head
     pub head_hash: Option<B256>,
+    /// Highest block number the peer announced
+    pub head_number: Option<u64>,
     /// Peer's total difficulty
     pub total_difficulty: Option<u128>,
+    /// Peer advertised `dexvm/1` and serves DexVM state
+    pub dexvm: bool,
     /// Time of last message
     pub last_seen: Instant,
     /// Connected at
@@ -54,7 +58,9 @@ impl PeerInfo {
             state: PeerState::Connecting,
             client_version: None,
             head_hash: None,
+            head_number: None,
             total_difficulty: None,
+            dexvm: false,
             last_seen: now,
             connected_at: now,
         }
@@ -78,6 +84,8 @@ pub struct PeerManager {
     peers: RwLock<HashMap<PeerId, PeerInfo>>,
     /// Maximum number of peers
     max_peers: usize,
+    /// Peers refused until the given time
+    banned: RwLock<HashMap<PeerId, Instant>>,
 }
 
 impl PeerManager {
@@ -86,11 +94,15 @@ impl PeerManager {
         Self {
             peers: RwLock::new(HashMap::new()),
             max_peers,
+            banned: RwLock::new(HashMap::new()),
         }
     }
 
-    /// Add a new peer
+    /// Add a new peer, unless it is banned or the peer limit is reached
     pub fn add_peer(&self, id: PeerId, addr: SocketAddr) -> bool {
+        if self.is_banned(&id) {
+            return false;
+        }
         let mut peers = self.peers.write();
         if peers.len() >= self.max_peers {
             return false;
@@ -104,6 +116,24 @@ impl PeerManager {
         self.peers.write().remove(id)
     }
 
+    /// Refuse sessions with a peer for `duration`
+    pub fn ban_peer(&self, id: PeerId, duration: Duration) {
+        self.banned.write().insert(id, Instant::now() + duration);
+    }
+
+    /// Whether sessions with a peer are refused, forgetting expired bans
+    pub fn is_banned(&self, id: &PeerId) -> bool {
+        let mut banned = self.banned.write();
+        match banned.get(id) {
+            Some(until) if *until > Instant::now() => true,
+            Some(_) => {
+                banned.remove(id);
+                false
+            }
+            None => false,
+        }
+    }
+
     /// Get peer info
     pub fn get_peer(&self, id: &PeerId) -> Option<PeerInfo> {
         self.peers.read().get(id).cloned()
@@ -126,6 +156,62 @@ impl PeerManager {
         }
     }
 
+    /// Record a block the peer announced, advancing its head if the block is newer
+    pub fn record_announced_head(&self, id: &PeerId, hash: B256, number: u64) {
+        if let Some(peer) = self.peers.write().get_mut(id) {
+            if peer.head_number.is_none_or(|head| number > head) {
+                peer.head_hash = Some(hash);
+                peer.head_number = Some(number);
+            }
+            peer.touch();
+        }
+    }
+
+    /// Highest block number a peer announced
+    pub fn peer_head(&self, id: &PeerId) -> Option<u64> {
+        self.peers.read().get(id).and_then(|peer| peer.head_number)
+    }
+
+    /// Announced heads of connected peers that announced any block
+    pub fn peer_heads(&self) -> Vec<(PeerId, u64)> {
+        self.peers
+            .read()
+            .values()
+            .filter(|p| p.is_connected())
+            .filter_map(|p| Some((p.id, p.head_number?)))
+            .collect()
+    }
+
+    /// Connected peer with the highest announced head, ties going to the lower peer ID
+    pub fn best_peer(&self) -> Option<(PeerId, u64)> {
+        self.peers
+            .read()
+            .values()
+            .filter(|p| p.is_connected())
+            .filter_map(|p| Some((p.id, p.head_number?)))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+    }
+
+    /// Record whether the peer serves DexVM state
+    pub fn set_dexvm(&self, id: &PeerId, dexvm: bool) {
+        if let Some(peer) = self.peers.write().get_mut(id) {
+            peer.dexvm = dexvm;
+        }
+    }
+
+    /// Connected peers serving DexVM state, highest announced head first
+    pub fn dexvm_peers(&self) -> Vec<PeerId> {
+        let mut peers: Vec<_> = self
+            .peers
+            .read()
+            .values()
+            .filter(|p| p.is_connected() && p.dexvm)
+            .map(|p| (p.id, p.head_number))
+            .collect();
+        peers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        peers.into_iter().map(|(id, _)| id).collect()
+    }
+
     /// Set peer client version
     pub fn set_client_version(&self, id: &PeerId, version: String) {
         if let Some(peer) = self.peers.write().get_mut(id) {
@@ -215,4 +301,66 @@ mod tests {
         assert!(manager.add_peer(id2, addr));
         assert!(!manager.add_peer(id3, addr)); // Should fail - max reached
     }
+
+    #[test]
+    fn test_banned_peer_refused() {
+        let manager = PeerManager::new(10);
+        let addr: SocketAddr = "127.0.0.1:30303".parse().unwrap();
+        let (banned, other) = (PeerId::repeat_byte(1), PeerId::repeat_byte(2));
+
+        manager.ban_peer(banned, Duration::from_secs(60));
+        assert!(manager.is_banned(&banned));
+        assert!(!manager.add_peer(banned, addr));
+        assert!(manager.add_peer(other, addr));
+
+        // Bans run out
+        manager.ban_peer(banned, Duration::ZERO);
+        assert!(!manager.is_banned(&banned));
+        assert!(manager.add_peer(banned, addr));
+    }
+
+    #[test]
+    fn test_best_peer_follows_announced_heads() {
+        let manager = PeerManager::new(10);
+        let addr: SocketAddr = "127.0.0.1:30303".parse().unwrap();
+        let (first, second) = (PeerId::repeat_byte(1), PeerId::repeat_byte(2));
+        for id in [first, second] {
+            manager.add_peer(id, addr);
+            manager.update_peer_state(&id, PeerState::Connected);
+        }
+        assert_eq!(manager.best_peer(), None);
+
+        manager.record_announced_head(&first, B256::repeat_byte(0xa1), 5);
+        manager.record_announced_head(&second, B256::repeat_byte(0xb1), 3);
+        assert_eq!(manager.best_peer(), Some((first, 5)));
+
+        // Older announcements don't move a head back
+        manager.record_announced_head(&first, B256::repeat_byte(0xa0), 4);
+        assert_eq!(manager.peer_head(&first), Some(5));
+
+        // Ties go to the lower peer ID, disconnected peers are skipped
+        manager.record_announced_head(&second, B256::repeat_byte(0xb2), 5);
+        assert_eq!(manager.best_peer(), Some((first, 5)));
+        manager.update_peer_state(&first, PeerState::Disconnected);
+        assert_eq!(manager.best_peer(), Some((second, 5)));
+        assert_eq!(manager.peer_heads(), vec![(second, 5)]);
+    }
+
+    #[test]
+    fn test_dexvm_peers() {
+        let manager = PeerManager::new(10);
+        let addr: SocketAddr = "127.0.0.1:30303".parse().unwrap();
+        let ids = [PeerId::repeat_byte(1), PeerId::repeat_byte(2), PeerId::repeat_byte(3)];
+        for id in ids {
+            manager.add_peer(id, addr);
+            manager.update_peer_state(&id, PeerState::Connected);
+        }
+        manager.set_dexvm(&ids[0], true);
+        manager.set_dexvm(&ids[2], true);
+        manager.record_announced_head(&ids[2], B256::repeat_byte(3), 7);
+        assert_eq!(manager.dexvm_peers(), vec![ids[2], ids[0]]);
+
+        manager.update_peer_state(&ids[2], PeerState::Disconnected);
+        assert_eq!(manager.dexvm_peers(), vec![ids[0]]);
+    }
 }
@@ -0,0 +1,156 @@
+//! Encrypted transaction gossip between private peers
+//!
+//! Consortium deployments can keep pending transactions off the open network:
+//! with private peers configured, transactions are sent only to those peers
+//! (typically the validators) instead of being flooded, sealed under a key
+//! shared by the two ends of the link on top of the RLPx encryption.
+//!
+//! reth's ECIES stream does not expose its session secrets, so the link key is
+//! derived from the ECDH of the two node keys the ECIES handshake has just
+//! authenticated. Each message is AES-256-CTR under a fresh random IV, followed
+//! by an HMAC-SHA256 tag over IV and ciphertext.
+
+use aes::Aes256;
+use alloy_primitives::Bytes;
+use alloy_rlp::Decodable;
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher},
+    Ctr64BE,
+};
+use hmac::{Hmac, Mac};
+use reth_network_peers::{id2pk, PeerId};
+use secp256k1::{ecdh::SharedSecret, SecretKey};
+use sha2::Sha256;
+use std::fmt;
+
+/// Message id of private transactions, outside the eth68 message range
+pub const PRIVATE_TRANSACTIONS_ID: u8 = 0x20;
+
+const IV_LEN: usize = 16;
+const TAG_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Symmetric keys of one peer link
+#[derive(Clone, PartialEq, Eq)]
+pub struct LinkKey {
+    cipher_key: [u8; 32],
+    mac_key: [u8; 32],
+}
+
+impl fmt::Debug for LinkKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LinkKey(..)")
+    }
+}
+
+impl LinkKey {
+    /// Key shared between the node with `secret_key` and the peer `remote_id`
+    pub fn derive(secret_key: &SecretKey, remote_id: &PeerId) -> eyre::Result<Self> {
+        let remote = id2pk(*remote_id)
+            .map_err(|e| eyre::eyre!("Invalid peer ID {}: {}", remote_id, e))?;
+        let shared = SharedSecret::new(&remote, secret_key).secret_bytes();
+        Ok(Self {
+            cipher_key: hmac_sha256(&shared, &[b"dex-reth private tx cipher".as_slice()]),
+            mac_key: hmac_sha256(&shared, &[b"dex-reth private tx mac".as_slice()]),
+        })
+    }
+
+    /// Encrypt RLP-encoded `transactions` into a private transactions message
+    pub fn seal(&self, transactions: &[Vec<u8>]) -> Vec<u8> {
+        let transactions: Vec<Bytes> = transactions.iter().cloned().map(Bytes::from).collect();
+        let mut ciphertext = alloy_rlp::encode(&transactions);
+        let iv: [u8; IV_LEN] = rand::random();
+        self.cipher(&iv).apply_keystream(&mut ciphertext);
+        let tag = hmac_sha256(&self.mac_key, &[iv.as_slice(), ciphertext.as_slice()]);
+
+        let mut message = Vec::with_capacity(1 + IV_LEN + ciphertext.len() + TAG_LEN);
+        message.push(PRIVATE_TRANSACTIONS_ID);
+        message.extend_from_slice(&iv);
+        message.extend_from_slice(&ciphertext);
+        message.extend_from_slice(&tag);
+        message
+    }
+
+    /// Authenticate and decrypt a private transactions message
+    pub fn open(&self, message: &[u8]) -> eyre::Result<Vec<Vec<u8>>> {
+        let body = match message.split_first() {
+            Some((&PRIVATE_TRANSACTIONS_ID, body)) if body.len() >= IV_LEN + TAG_LEN => body,
+            _ => return Err(eyre::eyre!("Malformed {} byte private message", message.len())),
+        };
+        let (iv, rest) = body.split_at(IV_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+        let mut mac = HmacSha256::new_from_slice(&self.mac_key).expect("HMAC takes any key");
+        mac.update(iv);
+        mac.update(ciphertext);
+        mac.verify_slice(tag).map_err(|_| eyre::eyre!("Private message failed authentication"))?;
+
+        let mut plaintext = ciphertext.to_vec();
+        self.cipher(iv.try_into().expect("IV length checked")).apply_keystream(&mut plaintext);
+        let mut buf = plaintext.as_slice();
+        let transactions = Vec::<Bytes>::decode(&mut buf)
+            .map_err(|e| eyre::eyre!("Malformed private transactions: {}", e))?;
+        if !buf.is_empty() {
+            return Err(eyre::eyre!("{} trailing bytes after private transactions", buf.len()));
+        }
+        Ok(transactions.iter().map(|tx| tx.to_vec()).collect())
+    }
+
+    fn cipher(&self, iv: &[u8; IV_LEN]) -> Ctr64BE<Aes256> {
+        Ctr64BE::<Aes256>::new(&self.cipher_key.into(), &(*iv).into())
+    }
+}
+
+/// HMAC-SHA256 of the concatenated `parts` under `key`
+fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes any key");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_network_peers::pk2id;
+    use secp256k1::SECP256K1;
+
+    fn key_pair() -> (SecretKey, PeerId) {
+        let secret_key = SecretKey::new(&mut rand::thread_rng());
+        (secret_key, pk2id(&secret_key.public_key(SECP256K1)))
+    }
+
+    #[test]
+    fn test_link_key_seals_for_the_other_end_only() {
+        let (alice_key, alice_id) = key_pair();
+        let (bob_key, bob_id) = key_pair();
+        let (carol_key, _) = key_pair();
+
+        let alice_to_bob = LinkKey::derive(&alice_key, &bob_id).unwrap();
+        let bob_to_alice = LinkKey::derive(&bob_key, &alice_id).unwrap();
+        assert_eq!(alice_to_bob, bob_to_alice);
+
+        let transactions = vec![vec![0xf8, 0x01, 0x02], vec![0x02; 120]];
+        let sealed = alice_to_bob.seal(&transactions);
+        assert_eq!(sealed[0], PRIVATE_TRANSACTIONS_ID);
+        assert_eq!(bob_to_alice.open(&sealed).unwrap(), transactions);
+        // Fresh IVs: the same transactions never seal to the same bytes
+        assert_ne!(alice_to_bob.seal(&transactions), sealed);
+
+        // A third node's key cannot open the link's messages
+        let carol_to_bob = LinkKey::derive(&carol_key, &bob_id).unwrap();
+        assert!(carol_to_bob.open(&sealed).is_err());
+
+        // Any flipped byte fails authentication, any truncation fails too
+        for index in 1..sealed.len() {
+            let mut tampered = sealed.clone();
+            tampered[index] ^= 0x01;
+            assert!(bob_to_alice.open(&tampered).is_err());
+        }
+        for len in 0..sealed.len() {
+            assert!(bob_to_alice.open(&sealed[..len]).is_err());
+        }
+    }
+}
@@ -2,27 +2,62 @@
 
 use crate::{
     config::P2pConfig,
+    dexvm_wire::{dexvm_capability, DexVmMessage},
     eth_handler::{run_eth_handler, EthHandlerCommand, EthHandlerEvent},
     peer::{PeerManager, PeerState, SharedPeerManager},
-    session::{accept_inbound, connect_outbound, SessionConfig},
+    session::{accept_inbound, connect_outbound, GenesisPolicy, SessionConfig},
+    transport::{bind_tcp, BoxedTransport, Dialer, Listener, TcpDialer},
 };
 use alloy_consensus::Header as ConsensusHeader;
-use alloy_primitives::B256;
+use alloy_primitives::{keccak256, B256};
+use reth_eth_wire::DisconnectReason;
 use reth_network_peers::{pk2id, PeerId, TrustedPeer};
 use secp256k1::{PublicKey, SECP256K1};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     net::SocketAddr,
     sync::Arc,
     time::Duration,
 };
 use tokio::{
-    net::{TcpListener, TcpStream},
-    sync::{broadcast, mpsc, RwLock},
+    sync::{broadcast, mpsc, oneshot, RwLock},
     time::interval,
 };
 use tracing::{debug, error, info, warn};
 
+/// How long a block announcement waits for each session to write it
+pub const ANNOUNCE_ACK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Transactions remembered per peer as already sent to or received from it
+pub const KNOWN_TXS_PER_PEER: usize = 4096;
+
+/// Transactions a peer already has, keyed by the hash of their encoding
+///
+/// Lets pending transactions be broadcast again and again while only
+/// reaching each peer once. The oldest entries are forgotten first.
+#[derive(Debug, Default)]
+struct KnownTransactions {
+    hashes: HashSet<B256>,
+    order: VecDeque<B256>,
+}
+
+impl KnownTransactions {
+    /// Remember a transaction; returns false if the peer already had it
+    fn insert(&mut self, encoded: &[u8]) -> bool {
+        let hash = keccak256(encoded);
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > KNOWN_TXS_PER_PEER {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
 /// P2P network events
 #[derive(Debug, Clone)]
 pub enum P2pEvent {
@@ -54,6 +89,9 @@ pub enum P2pEvent {
         request_id: u64,
         start: reth_eth_wire_types::HashOrNumber,
         limit: u64,
+        /// Blocks skipped between consecutive headers
+        skip: u64,
+        direction: reth_eth_wire_types::HeadersDirection,
     },
     /// Peer requesting block bodies (validator should respond)
     GetBlockBodiesRequest {
@@ -66,6 +104,21 @@ pub enum P2pEvent {
         peer_id: PeerId,
         transactions: Vec<Vec<u8>>, // RLP-encoded transactions
     },
+    /// A block announcement reached no peer; the node may be isolated
+    AnnouncementUndelivered { hash: B256, number: u64, peers: usize },
+    /// Received a `dexvm/1` request or response
+    DexVm { peer_id: PeerId, message: DexVmMessage },
+}
+
+/// Outcome of a block announcement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastReport {
+    /// Sessions open when the announcement was sent
+    pub peers: usize,
+    /// Sessions that accepted the announcement into their queue
+    pub queued: usize,
+    /// Sessions that wrote it to the wire within [`ANNOUNCE_ACK_TIMEOUT`]
+    pub delivered: usize,
 }
 
 /// P2P service handle
@@ -86,18 +139,30 @@ pub struct P2pHandle {
 /// Commands to send to active sessions
 #[derive(Debug)]
 pub enum SessionCommand {
-    /// Broadcast a new block to all peers
-    BroadcastBlock { hash: B256, number: u64 },
-    /// Request block headers from a peer
-    GetBlockHeaders { peer_id: PeerId, start: u64, count: u64 },
-    /// Request block bodies from a peer
-    GetBlockBodies { peer_id: PeerId, hashes: Vec<B256> },
+    /// Broadcast a new block to all peers, optionally reporting delivery
+    BroadcastBlock { hash: B256, number: u64, report: Option<oneshot::Sender<BroadcastReport>> },
+    /// Request `count` block headers from a peer, `skip` blocks apart, from `start` in `direction`
+    GetBlockHeaders {
+        peer_id: PeerId,
+        start: u64,
+        count: u64,
+        skip: u64,
+        direction: reth_eth_wire_types::HeadersDirection,
+    },
+    /// Request block bodies from a peer; the response carries `request_id` back
+    GetBlockBodies { peer_id: PeerId, request_id: u64, hashes: Vec<B256> },
     /// Send block headers response to a peer
     SendBlockHeaders { peer_id: PeerId, request_id: u64, headers: Vec<ConsensusHeader> },
     /// Send block bodies response to a peer
     SendBlockBodies { peer_id: PeerId, request_id: u64, bodies: Vec<reth_ethereum_primitives::BlockBody> },
     /// Broadcast transactions to all peers
     BroadcastTransactions { transactions: Vec<Vec<u8>> },
+    /// Send a `dexvm/1` request or response to a peer
+    SendDexVm { peer_id: PeerId, message: DexVmMessage },
+    /// End the session with a misbehaving peer
+    DisconnectPeer { peer_id: PeerId, reason: DisconnectReason },
+    /// End the session with a peer that sent invalid data and refuse it for `duration`
+    BanPeer { peer_id: PeerId, duration: Duration },
 }
 
 impl P2pHandle {
@@ -121,6 +186,31 @@ impl P2pHandle {
         self.event_tx.subscribe()
     }
 
+    /// Whether a peer is banned now
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.peers.is_banned(peer_id)
+    }
+
+    /// Highest block number a peer announced
+    pub fn peer_head(&self, peer_id: &PeerId) -> Option<u64> {
+        self.peers.peer_head(peer_id)
+    }
+
+    /// Announced heads of connected peers
+    pub fn peer_heads(&self) -> Vec<(PeerId, u64)> {
+        self.peers.peer_heads()
+    }
+
+    /// Connected peer with the highest announced head, to sync from
+    pub fn best_peer(&self) -> Option<(PeerId, u64)> {
+        self.peers.best_peer()
+    }
+
+    /// Connected peers serving DexVM state, highest announced head first
+    pub fn dexvm_peers(&self) -> Vec<PeerId> {
+        self.peers.dexvm_peers()
+    }
+
     /// Get all connected peer IDs
     pub fn connected_peers(&self) -> Vec<PeerId> {
         self.peers
@@ -134,6 +224,43 @@ impl P2pHandle {
     pub async fn send_command(&self, cmd: SessionCommand) -> Result<(), mpsc::error::SendError<SessionCommand>> {
         self.session_tx.send(cmd).await
     }
+
+    /// Announce a block to every connected peer and wait for the outcome
+    ///
+    /// Resolves once every session has written the announcement, failed, or
+    /// run out of [`ANNOUNCE_ACK_TIMEOUT`]. An announcement that reaches no
+    /// peer also emits [`P2pEvent::AnnouncementUndelivered`].
+    pub async fn broadcast_block(&self, hash: B256, number: u64) -> eyre::Result<BroadcastReport> {
+        let (report_tx, report_rx) = oneshot::channel();
+        let cmd = SessionCommand::BroadcastBlock { hash, number, report: Some(report_tx) };
+        self.session_tx.send(cmd).await.map_err(|_| eyre::eyre!("P2P service stopped"))?;
+        report_rx.await.map_err(|_| eyre::eyre!("P2P service stopped"))
+    }
+}
+
+/// Wait for the sessions' acknowledgements of a block announcement
+async fn collect_announcement_acks(
+    hash: B256,
+    number: u64,
+    peers: usize,
+    acks: Vec<oneshot::Receiver<()>>,
+    event_tx: broadcast::Sender<P2pEvent>,
+    report: Option<oneshot::Sender<BroadcastReport>>,
+) {
+    let queued = acks.len();
+    let results = futures::future::join_all(
+        acks.into_iter().map(|ack| tokio::time::timeout(ANNOUNCE_ACK_TIMEOUT, ack)),
+    )
+    .await;
+    let delivered = results.iter().filter(|result| matches!(result, Ok(Ok(())))).count();
+
+    if delivered == 0 {
+        warn!("Block {} announcement reached none of {} peers", number, peers);
+        let _ = event_tx.send(P2pEvent::AnnouncementUndelivered { hash, number, peers });
+    }
+    if let Some(report) = report {
+        let _ = report.send(BroadcastReport { peers, queued, delivered });
+    }
 }
 
 /// P2P network service
@@ -154,6 +281,10 @@ pub struct P2pService {
     session_tx: mpsc::Sender<SessionCommand>,
     /// Session command receiver
     session_rx: Option<mpsc::Receiver<SessionCommand>>,
+    /// Inbound connection source; a TCP listener on `listen_addr` is bound if unset
+    listener: Option<Box<dyn Listener>>,
+    /// Outbound connection factory
+    dialer: Arc<dyn Dialer>,
 }
 
 impl P2pService {
@@ -177,6 +308,8 @@ impl P2pService {
             shutdown_tx: Arc::new(shutdown_tx),
             session_tx,
             session_rx: Some(session_rx),
+            listener: None,
+            dialer: Arc::new(TcpDialer),
         }
     }
 
@@ -192,7 +325,17 @@ impl P2pService {
     }
 
     /// Start the P2P service
+    ///
+    /// Fails if no listener was given and `listen_addr` cannot be bound.
     pub async fn start(mut self) -> eyre::Result<P2pHandle> {
+        let listener = match self.listener.take() {
+            Some(listener) => listener,
+            None => {
+                self.config.validate_listen_addr().map_err(|e| eyre::eyre!(e))?;
+                Box::new(bind_tcp(self.config.listen_addr, self.config.dual_stack)?)
+            }
+        };
+        let dialer = Arc::clone(&self.dialer);
         let handle = self.handle();
         let config = self.config.clone();
         let peers = Arc::clone(&self.peers);
@@ -205,6 +348,8 @@ impl P2pService {
         tokio::spawn(async move {
             if let Err(e) = Self::run_service(
                 config,
+                listener,
+                dialer,
                 peers,
                 event_tx,
                 local_id,
@@ -220,25 +365,37 @@ impl P2pService {
         Ok(handle)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn run_service(
         config: P2pConfig,
+        mut listener: Box<dyn Listener>,
+        dialer: Arc<dyn Dialer>,
         peers: SharedPeerManager,
         event_tx: broadcast::Sender<P2pEvent>,
         local_id: PeerId,
         shutdown_rx: &mut mpsc::Receiver<()>,
         session_rx: &mut mpsc::Receiver<SessionCommand>,
     ) -> eyre::Result<()> {
-        info!(
-            "Starting P2P service on {}, local_id={:?}",
-            config.listen_addr, local_id
-        );
+        let listen_addr = listener.local_addr()?;
+        info!("Starting P2P service on {}, local_id={:?}", listen_addr, local_id);
 
         // Create session config
-        let session_config = SessionConfig::new(config.secret_key, config.chain_id, config.genesis_hash);
+        let mut session_config =
+            SessionConfig::new(config.secret_key, config.chain_id, config.genesis_hash)
+                .with_genesis_policy(config.genesis_policy);
+        if config.genesis_policy == GenesisPolicy::Relaxed {
+            warn!("Relaxed genesis policy: accepting peers with a different genesis or fork ID");
+        }
+        if let Some(allowlist) = config.peer_allowlist() {
+            info!("Trusted-only mode: accepting {} trusted peers", allowlist.len());
+            session_config = session_config.with_trusted_peers(allowlist);
+        }
+        let private_tx_peers = config.private_tx_peers.clone();
+        if config.private_tx_gossip() {
+            info!("Private transaction gossip to {} peers", private_tx_peers.len());
+        }
 
-        // Bind TCP listener
-        let listener = TcpListener::bind(config.listen_addr).await?;
-        info!("P2P listening on {}", config.listen_addr);
+        info!("P2P listening on {}", listen_addr);
 
         // Active sessions storage - now stores command sender per peer
         let peer_commands: Arc<RwLock<HashMap<PeerId, mpsc::Sender<EthHandlerCommand>>>> =
@@ -258,6 +415,7 @@ impl P2pService {
         tokio::spawn(async move {
             for boot_node in boot_nodes {
                 Self::connect_to_peer(
+                    dialer.as_ref(),
                     boot_node,
                     Arc::clone(&peers_clone),
                     event_tx_clone.clone(),
@@ -271,6 +429,7 @@ impl P2pService {
 
         // Periodic peer maintenance
         let mut maintenance_interval = interval(Duration::from_secs(30));
+        let mut known_txs: HashMap<PeerId, KnownTransactions> = HashMap::new();
 
         loop {
             tokio::select! {
@@ -306,24 +465,48 @@ impl P2pService {
                 // Handle session commands from external callers
                 Some(cmd) = session_rx.recv() => {
                     match cmd {
-                        SessionCommand::BroadcastBlock { hash, number } => {
+                        SessionCommand::BroadcastBlock { hash, number, report } => {
                             debug!("Broadcasting block {} to all peers", number);
                             let commands = peer_commands.read().await;
+                            let mut acks = Vec::with_capacity(commands.len());
                             for (peer_id, sender) in commands.iter() {
+                                let (ack_tx, ack_rx) = oneshot::channel();
                                 let cmd = EthHandlerCommand::AnnounceBlocks {
                                     blocks: vec![(hash, number)],
+                                    ack: Some(ack_tx),
                                 };
-                                if let Err(e) = sender.send(cmd).await {
-                                    warn!("Failed to send block announcement to peer {}: {}", peer_id, e);
+                                match sender.send(cmd).await {
+                                    Ok(()) => acks.push(ack_rx),
+                                    Err(e) => warn!("Failed to send block announcement to peer {}: {}", peer_id, e),
                                 }
                             }
+                            let peer_count = commands.len();
+                            drop(commands);
+
+                            // Acks arrive as sessions write; don't hold up other commands
+                            tokio::spawn(collect_announcement_acks(
+                                hash,
+                                number,
+                                peer_count,
+                                acks,
+                                event_tx.clone(),
+                                report,
+                            ));
                         }
-                        SessionCommand::GetBlockHeaders { peer_id, start, count } => {
+                        SessionCommand::GetBlockHeaders {
+                            peer_id,
+                            start,
+                            count,
+                            skip,
+                            direction,
+                        } => {
                             let commands = peer_commands.read().await;
                             if let Some(sender) = commands.get(&peer_id) {
                                 let cmd = EthHandlerCommand::GetBlockHeaders {
                                     start: crate::BlockHashOrNumber::Number(start),
                                     limit: count,
+                                    skip,
+                                    direction,
                                     request_id: rand::random(),
                                 };
                                 if let Err(e) = sender.send(cmd).await {
@@ -331,13 +514,10 @@ impl P2pService {
                                 }
                             }
                         }
-                        SessionCommand::GetBlockBodies { peer_id, hashes } => {
+                        SessionCommand::GetBlockBodies { peer_id, request_id, hashes } => {
                             let commands = peer_commands.read().await;
                             if let Some(sender) = commands.get(&peer_id) {
-                                let cmd = EthHandlerCommand::GetBlockBodies {
-                                    hashes,
-                                    request_id: rand::random(),
-                                };
+                                let cmd = EthHandlerCommand::GetBlockBodies { hashes, request_id };
                                 if let Err(e) = sender.send(cmd).await {
                                     warn!("Failed to send GetBlockBodies to peer {}: {}", peer_id, e);
                                 }
@@ -367,12 +547,52 @@ impl P2pService {
                                 }
                             }
                         }
+                        SessionCommand::SendDexVm { peer_id, message } => {
+                            let commands = peer_commands.read().await;
+                            if let Some(sender) = commands.get(&peer_id) {
+                                let cmd = EthHandlerCommand::SendDexVm { message };
+                                if let Err(e) = sender.send(cmd).await {
+                                    warn!("Failed to send dexvm message to peer {}: {}", peer_id, e);
+                                }
+                            }
+                        }
+                        SessionCommand::DisconnectPeer { peer_id, reason } => {
+                            let commands = peer_commands.read().await;
+                            if let Some(sender) = commands.get(&peer_id) {
+                                let _ = sender.send(EthHandlerCommand::Disconnect { reason }).await;
+                            }
+                        }
+                        SessionCommand::BanPeer { peer_id, duration } => {
+                            info!("Banning peer {} for {:?}", peer_id, duration);
+                            peers.ban_peer(peer_id, duration);
+                            let commands = peer_commands.read().await;
+                            if let Some(sender) = commands.get(&peer_id) {
+                                let reason = DisconnectReason::ProtocolBreach;
+                                let _ = sender.send(EthHandlerCommand::Disconnect { reason }).await;
+                            }
+                        }
                         SessionCommand::BroadcastTransactions { transactions } => {
-                            debug!("Broadcasting {} transactions to all peers", transactions.len());
+                            debug!("Broadcasting {} transactions", transactions.len());
+                            let private = !private_tx_peers.is_empty();
                             let commands = peer_commands.read().await;
                             for (peer_id, sender) in commands.iter() {
-                                let cmd = EthHandlerCommand::BroadcastTransactions {
-                                    transactions: transactions.clone(),
+                                // In private mode only the configured peers see transactions
+                                if private && !private_tx_peers.contains(peer_id) {
+                                    continue;
+                                }
+                                let known = known_txs.entry(*peer_id).or_default();
+                                let transactions: Vec<_> = transactions
+                                    .iter()
+                                    .filter(|tx| known.insert(tx))
+                                    .cloned()
+                                    .collect();
+                                if transactions.is_empty() {
+                                    continue;
+                                }
+                                let cmd = if private {
+                                    EthHandlerCommand::BroadcastPrivateTransactions { transactions }
+                                } else {
+                                    EthHandlerCommand::BroadcastTransactions { transactions }
                                 };
                                 if let Err(e) = sender.send(cmd).await {
                                     warn!("Failed to send transactions to peer {}: {}", peer_id, e);
@@ -388,6 +608,8 @@ impl P2pService {
                         EthHandlerEvent::NewBlockHashes { peer_id, hashes } => {
                             for (hash, number) in hashes {
                                 debug!("Received NewBlockHash from peer {}: {} at {}", peer_id, hash, number);
+                                // Heads are recorded before the event goes out, so sync sees them
+                                peers.record_announced_head(&peer_id, hash, number);
                                 let _ = event_tx.send(P2pEvent::NewBlockHash { peer_id, hash, number });
                             }
                         }
@@ -403,11 +625,12 @@ impl P2pService {
                             info!("Peer {} disconnected", peer_id);
                             peers.update_peer_state(&peer_id, PeerState::Disconnected);
                             peer_commands.write().await.remove(&peer_id);
+                            known_txs.remove(&peer_id);
                             let _ = event_tx.send(P2pEvent::PeerDisconnected { peer_id });
                         }
-                        EthHandlerEvent::GetBlockHeadersRequest { peer_id, request_id, start, limit } => {
+                        EthHandlerEvent::GetBlockHeadersRequest { peer_id, request_id, start, limit, skip, direction } => {
                             debug!("Peer {} requesting {} headers starting from {:?}", peer_id, limit, start);
-                            let _ = event_tx.send(P2pEvent::GetBlockHeadersRequest { peer_id, request_id, start, limit });
+                            let _ = event_tx.send(P2pEvent::GetBlockHeadersRequest { peer_id, request_id, start, limit, skip, direction });
                         }
                         EthHandlerEvent::GetBlockBodiesRequest { peer_id, request_id, hashes } => {
                             debug!("Peer {} requesting {} block bodies", peer_id, hashes.len());
@@ -415,8 +638,15 @@ impl P2pService {
                         }
                         EthHandlerEvent::Transactions { peer_id, transactions } => {
                             debug!("Received {} transactions from peer {}", transactions.len(), peer_id);
+                            let known = known_txs.entry(peer_id).or_default();
+                            transactions.iter().for_each(|tx| {
+                                known.insert(tx);
+                            });
                             let _ = event_tx.send(P2pEvent::Transactions { peer_id, transactions });
                         }
+                        EthHandlerEvent::DexVm { peer_id, message } => {
+                            let _ = event_tx.send(P2pEvent::DexVm { peer_id, message });
+                        }
                     }
                 }
 
@@ -444,6 +674,7 @@ impl P2pService {
     }
 
     async fn connect_to_peer(
+        dialer: &dyn Dialer,
         peer: TrustedPeer,
         peers: SharedPeerManager,
         event_tx: broadcast::Sender<P2pEvent>,
@@ -465,22 +696,37 @@ impl P2pService {
         info!("Connecting to boot node: {} at {}", remote_id, addr);
 
         // Establish session with ECIES + P2P + ETH Status handshake
-        match connect_outbound(addr, remote_id, &session_config).await {
+        match connect_outbound(dialer, addr, remote_id, &session_config).await {
             Ok(session) => {
                 let peer_id = session.peer_id;
 
                 if peers.add_peer(peer_id, addr) {
                     peers.update_peer_state(&peer_id, PeerState::Connected);
-                    let _ = event_tx.send(P2pEvent::PeerConnected { peer_id, addr });
-                    info!("Connected to peer {} at {}", peer_id, addr);
+                    peers.set_dexvm(&peer_id, session.capabilities.contains(&dexvm_capability()));
+                    let status = &session.their_status;
+                    peers.update_peer_head(
+                        &peer_id,
+                        status.blockhash,
+                        status.total_difficulty.saturating_to(),
+                    );
 
-                    // Create command channel for this peer
+                    // Register the command channel before announcing the peer, so
+                    // event handlers can send it requests right away
                     let (cmd_tx, cmd_rx) = mpsc::channel(256);
                     peer_commands.write().await.insert(peer_id, cmd_tx);
+                    let _ = event_tx.send(P2pEvent::PeerConnected { peer_id, addr });
+                    info!("Connected to peer {} at {}", peer_id, addr);
 
                     // Spawn ETH handler for this session
                     tokio::spawn(async move {
-                        run_eth_handler(peer_id, session.stream, cmd_rx, eth_event_tx).await;
+                        run_eth_handler(
+                            peer_id,
+                            session.stream,
+                            session.link_key,
+                            cmd_rx,
+                            eth_event_tx,
+                        )
+                        .await;
                     });
                 }
             }
@@ -491,7 +737,7 @@ impl P2pService {
     }
 
     async fn handle_incoming(
-        stream: TcpStream,
+        stream: BoxedTransport,
         addr: SocketAddr,
         peers: SharedPeerManager,
         event_tx: broadcast::Sender<P2pEvent>,
@@ -511,16 +757,31 @@ impl P2pService {
 
                 if peers.add_peer(peer_id, addr) {
                     peers.update_peer_state(&peer_id, PeerState::Connected);
-                    let _ = event_tx.send(P2pEvent::PeerConnected { peer_id, addr });
-                    info!("Accepted peer {} from {}", peer_id, addr);
+                    peers.set_dexvm(&peer_id, session.capabilities.contains(&dexvm_capability()));
+                    let status = &session.their_status;
+                    peers.update_peer_head(
+                        &peer_id,
+                        status.blockhash,
+                        status.total_difficulty.saturating_to(),
+                    );
 
-                    // Create command channel for this peer
+                    // Register the command channel before announcing the peer, so
+                    // event handlers can send it requests right away
                     let (cmd_tx, cmd_rx) = mpsc::channel(256);
                     peer_commands.write().await.insert(peer_id, cmd_tx);
+                    let _ = event_tx.send(P2pEvent::PeerConnected { peer_id, addr });
+                    info!("Accepted peer {} from {}", peer_id, addr);
 
                     // Spawn ETH handler for this session
                     tokio::spawn(async move {
-                        run_eth_handler(peer_id, session.stream, cmd_rx, eth_event_tx).await;
+                        run_eth_handler(
+                            peer_id,
+                            session.stream,
+                            session.link_key,
+                            cmd_rx,
+                            eth_event_tx,
+                        )
+                        .await;
                     });
                 }
             }
@@ -534,12 +795,14 @@ impl P2pService {
 /// Builder for P2P service
 pub struct P2pServiceBuilder {
     config: P2pConfig,
+    listener: Option<Box<dyn Listener>>,
+    dialer: Option<Arc<dyn Dialer>>,
 }
 
 impl P2pServiceBuilder {
     /// Create new builder
     pub fn new(config: P2pConfig) -> Self {
-        Self { config }
+        Self { config, listener: None, dialer: None }
     }
 
     /// Set listen port
@@ -560,9 +823,50 @@ impl P2pServiceBuilder {
         self
     }
 
+    /// Add a peer allowed to connect in trusted-only mode
+    pub fn trusted_peer(mut self, peer_id: PeerId) -> Self {
+        self.config = self.config.with_trusted_peer(peer_id);
+        self
+    }
+
+    /// Only allow sessions with trusted peers
+    pub fn trusted_only(mut self, trusted_only: bool) -> Self {
+        self.config = self.config.with_trusted_only(trusted_only);
+        self
+    }
+
+    /// Gossip transactions encrypted to this peer instead of flooding them
+    pub fn private_tx_peer(mut self, peer_id: PeerId) -> Self {
+        self.config = self.config.with_private_tx_peer(peer_id);
+        self
+    }
+
+    /// Set the genesis matching policy
+    pub fn genesis_policy(mut self, policy: GenesisPolicy) -> Self {
+        self.config = self.config.with_genesis_policy(policy);
+        self
+    }
+
+    /// Accept peers from `listener` instead of binding the configured TCP port
+    pub fn listener(mut self, listener: impl Listener) -> Self {
+        self.listener = Some(Box::new(listener));
+        self
+    }
+
+    /// Dial peers through `dialer` instead of TCP
+    pub fn dialer(mut self, dialer: impl Dialer) -> Self {
+        self.dialer = Some(Arc::new(dialer));
+        self
+    }
+
     /// Build the service
     pub fn build(self) -> P2pService {
-        P2pService::new(self.config)
+        let mut service = P2pService::new(self.config);
+        service.listener = self.listener;
+        if let Some(dialer) = self.dialer {
+            service.dialer = dialer;
+        }
+        service
     }
 }
 
@@ -591,4 +895,92 @@ mod tests {
 
         assert_eq!(handle.peer_count(), 0);
     }
+
+    #[test]
+    fn test_known_transactions_reach_each_peer_once() {
+        let mut known = KnownTransactions::default();
+        assert!(known.insert(&[0x01]));
+        assert!(!known.insert(&[0x01]));
+
+        // The oldest transaction is forgotten once the set is full
+        for i in 0..KNOWN_TXS_PER_PEER as u64 {
+            known.insert(&i.to_be_bytes());
+        }
+        assert!(known.insert(&[0x01]));
+        assert_eq!(known.order.len(), KNOWN_TXS_PER_PEER);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_without_peers_reports_isolation() {
+        let config = P2pConfig::default().with_port(0);
+        let handle = P2pService::new(config).start().await.unwrap();
+        let mut events = handle.subscribe();
+
+        let hash = B256::repeat_byte(0x11);
+        let report = handle.broadcast_block(hash, 7).await.unwrap();
+        assert_eq!(report, BroadcastReport { peers: 0, queued: 0, delivered: 0 });
+
+        match events.recv().await.unwrap() {
+            P2pEvent::AnnouncementUndelivered { hash: announced, number, peers } => {
+                assert_eq!((announced, number, peers), (hash, 7, 0));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+    #[tokio::test]
+    async fn test_in_memory_peers_exchange_announcements() {
+        use crate::transport::MemoryNetwork;
+
+        let network = MemoryNetwork::new();
+        let validator_addr: SocketAddr = "10.0.0.1:30303".parse().unwrap();
+        let fullnode_addr: SocketAddr = "10.0.0.2:30303".parse().unwrap();
+
+        let validator = P2pServiceBuilder::new(P2pConfig::default())
+            .listener(network.listen(validator_addr))
+            .dialer(network.dialer(validator_addr))
+            .build();
+        let enode = format!(
+            "enode://{}@{}",
+            hex::encode(validator.handle().local_id().as_slice()),
+            validator_addr
+        );
+        let validator = validator.start().await.unwrap();
+        let mut validator_events = validator.subscribe();
+
+        let fullnode = P2pServiceBuilder::new(P2pConfig::default())
+            .boot_node(enode.parse().unwrap())
+            .listener(network.listen(fullnode_addr))
+            .dialer(network.dialer(fullnode_addr))
+            .build()
+            .start()
+            .await
+            .unwrap();
+        let mut fullnode_events = fullnode.subscribe();
+
+        let connected = tokio::time::timeout(Duration::from_secs(5), validator_events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match connected {
+            P2pEvent::PeerConnected { peer_id, addr } => {
+                assert_eq!((peer_id, addr), (fullnode.local_id(), fullnode_addr));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let hash = B256::repeat_byte(0x22);
+        let report = validator.broadcast_block(hash, 3).await.unwrap();
+        assert_eq!(report, BroadcastReport { peers: 1, queued: 1, delivered: 1 });
+
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), fullnode_events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let P2pEvent::NewBlockHash { peer_id, hash: announced, number } = event {
+                assert_eq!((peer_id, announced, number), (validator.local_id(), hash, 3));
+                break;
+            }
+        }
+    }
 }
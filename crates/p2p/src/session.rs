@@ -6,19 +6,54 @@ use alloy_primitives::{B256, U256};
 use futures::{SinkExt, StreamExt};
 use reth_ecies::stream::ECIESStream;
 use reth_eth_wire::{
-    Capability, EthVersion, HelloMessageWithProtocols, P2PStream, ProtocolVersion,
-    UnauthedP2PStream,
+    protocol::Protocol, Capability, EthVersion, HelloMessageWithProtocols, P2PStream,
+    ProtocolVersion, UnauthedP2PStream,
 };
 use reth_eth_wire_types::{EthMessage, EthNetworkPrimitives, ProtocolMessage, Status, StatusMessage};
 use reth_network_peers::PeerId;
 use secp256k1::SecretKey;
-use std::net::SocketAddr;
-use tokio::net::TcpStream;
-use tracing::{debug, info, trace};
+use crate::{
+    dexvm_wire::{dexvm_capability, DEXVM_MESSAGE_COUNT},
+    private::LinkKey,
+    transport::{BoxedTransport, Dialer, Transport},
+};
+use std::{collections::HashSet, fmt, net::SocketAddr, str::FromStr};
+use tracing::{debug, info, trace, warn};
 
 /// Client version string
 pub const CLIENT_VERSION: &str = "dex-reth/0.1.0";
 
+/// How strictly a peer's genesis hash and fork ID must match ours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GenesisPolicy {
+    /// Refuse peers whose genesis hash or fork ID differs
+    #[default]
+    Strict,
+    /// Accept such peers anyway, logging a warning for each session
+    Relaxed,
+}
+
+impl FromStr for GenesisPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(Self::Strict),
+            "relaxed" => Ok(Self::Relaxed),
+            _ => Err(format!("unknown genesis policy '{}' (expected strict or relaxed)", s)),
+        }
+    }
+}
+
+impl fmt::Display for GenesisPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Strict => write!(f, "strict"),
+            Self::Relaxed => write!(f, "relaxed"),
+        }
+    }
+}
+
 /// Session configuration
 #[derive(Debug, Clone)]
 pub struct SessionConfig {
@@ -30,6 +65,10 @@ pub struct SessionConfig {
     pub genesis_hash: B256,
     /// Client version
     pub client_version: String,
+    /// Peer allowlist; when set, sessions with any other peer are refused
+    pub trusted_peers: Option<HashSet<PeerId>>,
+    /// Genesis hash and fork ID matching policy
+    pub genesis_policy: GenesisPolicy,
 }
 
 impl SessionConfig {
@@ -40,20 +79,50 @@ impl SessionConfig {
             chain_id,
             genesis_hash,
             client_version: CLIENT_VERSION.to_string(),
+            trusted_peers: None,
+            genesis_policy: GenesisPolicy::Strict,
         }
     }
+
+    /// Set the genesis matching policy
+    pub fn with_genesis_policy(mut self, policy: GenesisPolicy) -> Self {
+        self.genesis_policy = policy;
+        self
+    }
+
+    /// Restrict sessions to the given peer IDs
+    pub fn with_trusted_peers(mut self, peers: HashSet<PeerId>) -> Self {
+        self.trusted_peers = Some(peers);
+        self
+    }
+
+    /// Check whether a session with this peer is allowed
+    pub fn is_peer_allowed(&self, peer_id: &PeerId) -> bool {
+        self.trusted_peers.as_ref().is_none_or(|peers| peers.contains(peer_id))
+    }
+}
+
+/// Refuse the session if the remote peer is not on the allowlist
+fn ensure_peer_allowed(config: &SessionConfig, peer_id: &PeerId) -> eyre::Result<()> {
+    if !config.is_peer_allowed(peer_id) {
+        warn!("Refusing session with untrusted peer {}", peer_id);
+        return Err(eyre::eyre!("Peer {} is not in the trusted peer list", peer_id));
+    }
+    Ok(())
 }
 
 /// Result of establishing a peer session
-pub struct EstablishedSession {
+pub struct EstablishedSession<Io = BoxedTransport> {
     /// Remote peer ID
     pub peer_id: PeerId,
     /// P2P stream for communication
-    pub stream: P2PStream<ECIESStream<TcpStream>>,
-    /// Shared capabilities
+    pub stream: P2PStream<ECIESStream<Io>>,
+    /// Capabilities the peer advertised
     pub capabilities: Vec<Capability>,
     /// Remote peer's status
     pub their_status: Status,
+    /// Key for private transaction messages on this link
+    pub link_key: LinkKey,
 }
 
 /// Create a Status message for ETH protocol handshake
@@ -72,10 +141,42 @@ fn create_status_message(config: &SessionConfig) -> Status {
     }
 }
 
+/// Check a peer's status against ours
+///
+/// The chain ID must always match. Genesis hash and fork ID mismatches are
+/// fatal under [`GenesisPolicy::Strict`] and only logged under `Relaxed`.
+fn validate_status(ours: &Status, theirs: &Status, policy: GenesisPolicy) -> eyre::Result<()> {
+    if theirs.chain.id() != ours.chain.id() {
+        return Err(eyre::eyre!(
+            "Chain ID mismatch: expected {:?}, got {:?}",
+            ours.chain,
+            theirs.chain
+        ));
+    }
+
+    let mismatch = if theirs.genesis != ours.genesis {
+        Some(format!("Genesis hash mismatch: expected {:?}, got {:?}", ours.genesis, theirs.genesis))
+    } else if theirs.forkid != ours.forkid {
+        Some(format!("Fork ID mismatch: expected {:?}, got {:?}", ours.forkid, theirs.forkid))
+    } else {
+        None
+    };
+
+    match (mismatch, policy) {
+        (None, _) => Ok(()),
+        (Some(mismatch), GenesisPolicy::Strict) => Err(eyre::eyre!(mismatch)),
+        (Some(mismatch), GenesisPolicy::Relaxed) => {
+            warn!("{} - accepting peer anyway (relaxed genesis policy)", mismatch);
+            Ok(())
+        }
+    }
+}
+
 /// Perform ETH Status handshake
-async fn eth_status_handshake(
-    stream: &mut P2PStream<ECIESStream<TcpStream>>,
+async fn eth_status_handshake<Io: Transport>(
+    stream: &mut P2PStream<ECIESStream<Io>>,
     our_status: Status,
+    policy: GenesisPolicy,
 ) -> eyre::Result<Status> {
     // Send our status
     let status_msg = ProtocolMessage::<EthNetworkPrimitives>::from(
@@ -89,34 +190,24 @@ async fn eth_status_handshake(
     let their_msg = stream.next().await
         .ok_or_else(|| eyre::eyre!("Connection closed during status handshake"))??;
 
-    // Decode the status message
-    let protocol_msg = ProtocolMessage::<EthNetworkPrimitives>::decode_message(
-        EthVersion::Eth68,
-        &mut their_msg.as_ref(),
-    ).map_err(|e| eyre::eyre!("Failed to decode status message: {}", e))?;
+    let status = decode_status(&their_msg, &our_status, policy)?;
+    trace!("Received ETH Status: {:?}", status);
+    Ok(status)
+}
+
+/// Decode and validate the first message of a peer, which must be its Status
+fn decode_status(bytes: &[u8], ours: &Status, policy: GenesisPolicy) -> eyre::Result<Status> {
+    let mut buf = bytes;
+    let protocol_msg =
+        ProtocolMessage::<EthNetworkPrimitives>::decode_message(EthVersion::Eth68, &mut buf)
+            .map_err(|e| eyre::eyre!("Failed to decode status message: {}", e))?;
+    if !buf.is_empty() {
+        return Err(eyre::eyre!("{} trailing bytes after status message", buf.len()));
+    }
 
     match protocol_msg.message {
         EthMessage::Status(StatusMessage::Legacy(status)) => {
-            trace!("Received ETH Status: {:?}", status);
-
-            // Validate genesis hash matches
-            if status.genesis != our_status.genesis {
-                return Err(eyre::eyre!(
-                    "Genesis hash mismatch: expected {:?}, got {:?}",
-                    our_status.genesis,
-                    status.genesis
-                ));
-            }
-
-            // Validate chain ID matches
-            if status.chain.id() != our_status.chain.id() {
-                return Err(eyre::eyre!(
-                    "Chain ID mismatch: expected {:?}, got {:?}",
-                    our_status.chain,
-                    status.chain
-                ));
-            }
-
+            validate_status(ours, &status, policy)?;
             Ok(status)
         }
         EthMessage::Status(StatusMessage::Eth69(_)) => {
@@ -126,22 +217,24 @@ async fn eth_status_handshake(
     }
 }
 
-/// Establish an outbound session to a peer
+/// Establish an outbound session to a peer, connecting through `dialer`
 pub async fn connect_outbound(
+    dialer: &dyn Dialer,
     addr: SocketAddr,
     remote_id: PeerId,
     config: &SessionConfig,
 ) -> eyre::Result<EstablishedSession> {
     info!("Connecting to peer {} at {}", remote_id, addr);
 
-    // Connect TCP
-    let tcp_stream = TcpStream::connect(addr).await?;
+    let transport = dialer.dial(addr).await?;
 
     // ECIES handshake
     trace!("Starting ECIES handshake with {}", remote_id);
-    let ecies_stream = ECIESStream::connect(tcp_stream, config.secret_key, remote_id).await?;
+    let ecies_stream = ECIESStream::connect(transport, config.secret_key, remote_id).await?;
     let actual_remote_id = ecies_stream.remote_id();
     debug!("ECIES handshake completed with peer {}", actual_remote_id);
+    ensure_peer_allowed(config, &actual_remote_id)?;
+    let link_key = LinkKey::derive(&config.secret_key, &actual_remote_id)?;
 
     // P2P handshake
     let hello = create_hello_message(config);
@@ -157,7 +250,7 @@ pub async fn connect_outbound(
     // ETH Status handshake
     let our_status = create_status_message(config);
     trace!("Starting ETH Status handshake with {}", actual_remote_id);
-    let their_status = eth_status_handshake(&mut p2p_stream, our_status).await?;
+    let their_status = eth_status_handshake(&mut p2p_stream, our_status, config.genesis_policy).await?;
     info!(
         "ETH Status handshake completed with {}, chain: {}, genesis: {:?}",
         actual_remote_id, their_status.chain, their_status.genesis
@@ -168,22 +261,25 @@ pub async fn connect_outbound(
         stream: p2p_stream,
         capabilities: their_hello.capabilities,
         their_status,
+        link_key,
     })
 }
 
 /// Accept an inbound session from a peer
-pub async fn accept_inbound(
-    tcp_stream: TcpStream,
+pub async fn accept_inbound<Io: Transport>(
+    transport: Io,
     addr: SocketAddr,
     config: &SessionConfig,
-) -> eyre::Result<EstablishedSession> {
+) -> eyre::Result<EstablishedSession<Io>> {
     info!("Accepting connection from {}", addr);
 
     // ECIES handshake (server side)
     trace!("Starting ECIES handshake (inbound) from {}", addr);
-    let ecies_stream = ECIESStream::incoming(tcp_stream, config.secret_key).await?;
+    let ecies_stream = ECIESStream::incoming(transport, config.secret_key).await?;
     let remote_id = ecies_stream.remote_id();
     debug!("ECIES handshake completed with peer {}", remote_id);
+    ensure_peer_allowed(config, &remote_id)?;
+    let link_key = LinkKey::derive(&config.secret_key, &remote_id)?;
 
     // P2P handshake
     let hello = create_hello_message(config);
@@ -199,7 +295,7 @@ pub async fn accept_inbound(
     // ETH Status handshake
     let our_status = create_status_message(config);
     trace!("Starting ETH Status handshake with {}", remote_id);
-    let their_status = eth_status_handshake(&mut p2p_stream, our_status).await?;
+    let their_status = eth_status_handshake(&mut p2p_stream, our_status, config.genesis_policy).await?;
     info!(
         "ETH Status handshake completed with {}, chain: {}, genesis: {:?}",
         remote_id, their_status.chain, their_status.genesis
@@ -210,6 +306,7 @@ pub async fn accept_inbound(
         stream: p2p_stream,
         capabilities: their_hello.capabilities,
         their_status,
+        link_key,
     })
 }
 
@@ -222,14 +319,17 @@ fn create_hello_message(config: &SessionConfig) -> HelloMessageWithProtocols {
         .protocol_version(ProtocolVersion::V5)
         // Add eth68 capability (we're compatible with standard eth protocol for block sync)
         .protocol(EthVersion::Eth68)
+        // DexVM state sync, see `dexvm_wire`
+        .protocol(Protocol::new(dexvm_capability(), DEXVM_MESSAGE_COUNT))
         .build()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transport::TcpDialer;
     use secp256k1::SECP256K1;
-    use tokio::net::TcpListener;
+    use tokio::net::{TcpListener, TcpStream};
 
     #[tokio::test]
     async fn test_session_handshake() {
@@ -253,10 +353,128 @@ mod tests {
         });
 
         // Client connect
-        let client_result = connect_outbound(addr, server_id, &client_config).await;
+        let client_result = connect_outbound(&TcpDialer, addr, server_id, &client_config).await;
         assert!(client_result.is_ok(), "Client connection failed: {:?}", client_result.err());
 
         let server_result = server_handle.await.unwrap();
         assert!(server_result.is_ok(), "Server accept failed: {:?}", server_result.err());
     }
+
+    #[test]
+    fn test_validate_status_policy() {
+        let key = SecretKey::new(&mut rand::thread_rng());
+        let ours = create_status_message(&SessionConfig::new(key, 1, B256::ZERO));
+        let other_genesis =
+            create_status_message(&SessionConfig::new(key, 1, B256::repeat_byte(0x01)));
+        let other_chain = create_status_message(&SessionConfig::new(key, 2, B256::ZERO));
+
+        assert!(validate_status(&ours, &ours, GenesisPolicy::Strict).is_ok());
+        assert!(validate_status(&ours, &other_genesis, GenesisPolicy::Strict).is_err());
+        assert!(validate_status(&ours, &other_genesis, GenesisPolicy::Relaxed).is_ok());
+        // Chain ID is enforced regardless of policy
+        assert!(validate_status(&ours, &other_chain, GenesisPolicy::Relaxed).is_err());
+
+        assert_eq!("relaxed".parse::<GenesisPolicy>(), Ok(GenesisPolicy::Relaxed));
+        assert!("loose".parse::<GenesisPolicy>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_peer_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_key = SecretKey::new(&mut rand::thread_rng());
+        let client_key = SecretKey::new(&mut rand::thread_rng());
+        let server_id = reth_network_peers::pk2id(&server_key.public_key(SECP256K1));
+        let client_id = reth_network_peers::pk2id(&client_key.public_key(SECP256K1));
+
+        // Server only trusts some other peer
+        let other_id = reth_network_peers::pk2id(
+            &SecretKey::new(&mut rand::thread_rng()).public_key(SECP256K1),
+        );
+        let server_config = SessionConfig::new(server_key, 1, B256::ZERO)
+            .with_trusted_peers(HashSet::from([other_id]));
+        let client_config = SessionConfig::new(client_key, 1, B256::ZERO)
+            .with_trusted_peers(HashSet::from([server_id]));
+
+        assert!(!server_config.is_peer_allowed(&client_id));
+        assert!(client_config.is_peer_allowed(&server_id));
+
+        let server_handle = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            accept_inbound(stream, peer_addr, &server_config).await
+        });
+
+        let client_result = connect_outbound(&TcpDialer, addr, server_id, &client_config).await;
+        let server_result = server_handle.await.unwrap();
+
+        assert!(server_result.is_err());
+        assert!(client_result.is_err());
+    }
+
+    #[test]
+    fn test_decode_malformed_status() {
+        let key = SecretKey::new(&mut rand::thread_rng());
+        let ours = create_status_message(&SessionConfig::new(key, 1, B256::ZERO));
+        let encoded = alloy_rlp::encode(ProtocolMessage::<EthNetworkPrimitives>::from(
+            EthMessage::Status(StatusMessage::Legacy(ours)),
+        ));
+        assert_eq!(decode_status(&encoded, &ours, GenesisPolicy::Strict).unwrap(), ours);
+
+        for len in 0..encoded.len() {
+            assert!(decode_status(&encoded[..len], &ours, GenesisPolicy::Strict).is_err());
+        }
+
+        // Every single-byte corruption either fails or still decodes to a status
+        for index in 0..encoded.len() {
+            for flip in [0x01, 0x80, 0xff] {
+                let mut bytes = encoded.clone();
+                bytes[index] ^= flip;
+                let _ = decode_status(&bytes, &ours, GenesisPolicy::Relaxed);
+            }
+        }
+
+        // Any other first message fails the handshake
+        let other = alloy_rlp::encode(ProtocolMessage::<EthNetworkPrimitives>::from(
+            EthMessage::GetBlockBodies(reth_eth_wire::message::RequestPair {
+                request_id: 1,
+                message: reth_eth_wire_types::GetBlockBodies(vec![B256::ZERO]),
+            }),
+        ));
+        assert!(decode_status(&other, &ours, GenesisPolicy::Relaxed).is_err());
+        let mut trailing = encoded.clone();
+        trailing.push(0x80);
+        assert!(decode_status(&trailing, &ours, GenesisPolicy::Strict).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_rejects_malformed_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_key = SecretKey::new(&mut rand::thread_rng());
+        let server_id = reth_network_peers::pk2id(&server_key.public_key(SECP256K1));
+        let server_config = SessionConfig::new(server_key, 1, B256::ZERO);
+        let server_handle = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            accept_inbound(stream, peer_addr, &server_config).await
+        });
+
+        // A peer that completes the P2P handshake, then sends a truncated Status
+        let client_config =
+            SessionConfig::new(SecretKey::new(&mut rand::thread_rng()), 1, B256::ZERO);
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let ecies = ECIESStream::connect(tcp, client_config.secret_key, server_id).await.unwrap();
+        let (mut stream, _) = UnauthedP2PStream::new(ecies)
+            .handshake(create_hello_message(&client_config))
+            .await
+            .unwrap();
+        let status = alloy_rlp::encode(ProtocolMessage::<EthNetworkPrimitives>::from(
+            EthMessage::Status(StatusMessage::Legacy(create_status_message(&client_config))),
+        ));
+        stream.send(status[..status.len() - 3].to_vec().into()).await.unwrap();
+
+        let server_result = server_handle.await.unwrap();
+        assert!(server_result.is_err());
+    }
 }
@@ -0,0 +1,186 @@
+//! Connection transports
+//!
+//! Sessions run over any byte stream. The service accepts peers from a
+//! [`Listener`] and dials them through a [`Dialer`]: TCP by default, or a
+//! [`MemoryNetwork`] that connects services in-process, for tests.
+//!
+//! [`bind_tcp`] sets up the TCP listener, including dual-stack IPv6 sockets
+//! that take IPv4 peers too. Their IPv4-mapped remote addresses are reported
+//! as plain IPv4, so a peer has one address whichever socket it came in on.
+
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+
+/// Buffer size of each direction of an in-memory connection
+pub const MEMORY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Byte stream a peer session runs over
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+impl<T> Transport for T where T: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+/// Type-erased transport, as produced by listeners and dialers
+pub type BoxedTransport = Box<dyn Transport>;
+
+/// Source of inbound connections
+pub trait Listener: Send + 'static {
+    /// Wait for the next connection and the remote address it came from
+    fn accept(&mut self) -> BoxFuture<'_, io::Result<(BoxedTransport, SocketAddr)>>;
+
+    /// Address peers reach this listener at
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+}
+
+/// Opens outbound connections
+pub trait Dialer: Send + Sync + 'static {
+    /// Connect to `addr`
+    fn dial(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<BoxedTransport>>;
+}
+
+/// Connections waiting to be accepted by a TCP listener
+const TCP_BACKLOG: i32 = 1024;
+
+/// Bind a TCP listener on `addr`
+///
+/// With `dual_stack`, an IPv6 listener also accepts IPv4 connections; without
+/// it, an IPv6 listener is IPv6 only whatever the OS default is.
+pub fn bind_tcp(addr: SocketAddr, dual_stack: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(!dual_stack)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(TCP_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+impl Listener for TcpListener {
+    fn accept(&mut self) -> BoxFuture<'_, io::Result<(BoxedTransport, SocketAddr)>> {
+        Box::pin(async move {
+            let (stream, addr) = TcpListener::accept(self).await?;
+            let addr = SocketAddr::new(addr.ip().to_canonical(), addr.port());
+            Ok((Box::new(stream) as BoxedTransport, addr))
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpListener::local_addr(self)
+    }
+}
+
+/// Dials peers over TCP
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpDialer;
+
+impl Dialer for TcpDialer {
+    fn dial(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<BoxedTransport>> {
+        Box::pin(async move { Ok(Box::new(TcpStream::connect(addr).await?) as BoxedTransport) })
+    }
+}
+
+type Incoming = (DuplexStream, SocketAddr);
+
+/// In-process network routing dialed addresses to listeners
+#[derive(Debug, Clone, Default)]
+pub struct MemoryNetwork {
+    listeners: Arc<Mutex<HashMap<SocketAddr, mpsc::UnboundedSender<Incoming>>>>,
+}
+
+impl MemoryNetwork {
+    /// Create an empty network
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listen on `addr`, replacing any earlier listener there
+    pub fn listen(&self, addr: SocketAddr) -> MemoryListener {
+        let (tx, incoming) = mpsc::unbounded_channel();
+        self.listeners.lock().insert(addr, tx);
+        MemoryListener { addr, incoming }
+    }
+
+    /// Dialer whose connections appear to come from `local_addr`
+    pub fn dialer(&self, local_addr: SocketAddr) -> MemoryDialer {
+        MemoryDialer { network: self.clone(), local_addr }
+    }
+}
+
+/// Accepts connections dialed through a [`MemoryNetwork`]
+#[derive(Debug)]
+pub struct MemoryListener {
+    addr: SocketAddr,
+    incoming: mpsc::UnboundedReceiver<Incoming>,
+}
+
+impl Listener for MemoryListener {
+    fn accept(&mut self) -> BoxFuture<'_, io::Result<(BoxedTransport, SocketAddr)>> {
+        Box::pin(async move {
+            let (stream, addr) = self.incoming.recv().await.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::ConnectionAborted, "memory listener replaced")
+            })?;
+            Ok((Box::new(stream) as BoxedTransport, addr))
+        })
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+}
+
+/// Dials listeners of a [`MemoryNetwork`]
+#[derive(Debug, Clone)]
+pub struct MemoryDialer {
+    network: MemoryNetwork,
+    local_addr: SocketAddr,
+}
+
+impl Dialer for MemoryDialer {
+    fn dial(&self, addr: SocketAddr) -> BoxFuture<'_, io::Result<BoxedTransport>> {
+        Box::pin(async move {
+            let listener = self.network.listeners.lock().get(&addr).cloned();
+            let refused = || io::Error::new(io::ErrorKind::ConnectionRefused, addr.to_string());
+            let listener = listener.ok_or_else(refused)?;
+
+            let (local, remote) = tokio::io::duplex(MEMORY_BUFFER_SIZE);
+            listener.send((remote, self.local_addr)).map_err(|_| refused())?;
+            Ok(Box::new(local) as BoxedTransport)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_memory_network_connects_by_address() {
+        let network = MemoryNetwork::new();
+        let server_addr: SocketAddr = "10.0.0.1:30303".parse().unwrap();
+        let client_addr: SocketAddr = "10.0.0.2:30303".parse().unwrap();
+        let mut listener = network.listen(server_addr);
+        let dialer = network.dialer(client_addr);
+
+        let mut client = dialer.dial(server_addr).await.unwrap();
+        let (mut server, from) = listener.accept().await.unwrap();
+        assert_eq!(from, client_addr);
+
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        let unknown: SocketAddr = "10.0.0.3:30303".parse().unwrap();
+        let err = dialer.dial(unknown).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+    }
+}
@@ -0,0 +1,158 @@
+//! Per-transaction state access sets
+//!
+//! An [`AccessSet`] records the accounts (balance and nonce) and storage slots
+//! a transaction read or wrote. Two transactions whose sets do not conflict
+//! can run in parallel. DexVM counters are recorded as slots of the counter
+//! precompile account, keyed by [`counter_slot`] and [`labeled_counter_slot`].
+
+use alloy_primitives::{keccak256, Address, B256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Slot key of an account's DexVM counter
+pub fn counter_slot(owner: Address) -> B256 {
+    owner.into_word()
+}
+
+/// Slot key of one of an account's labeled DexVM counters
+///
+/// Label zero is the main counter and maps to [`counter_slot`].
+pub fn labeled_counter_slot(owner: Address, label: B256) -> B256 {
+    if label.is_zero() {
+        return counter_slot(owner);
+    }
+    let mut key = [0u8; 52];
+    key[..20].copy_from_slice(owner.as_slice());
+    key[20..].copy_from_slice(label.as_slice());
+    keccak256(key)
+}
+
+/// Accounts and storage slots touched by one transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessSet {
+    /// Accounts whose balance or nonce was read
+    pub accounts_read: BTreeSet<Address>,
+    /// Accounts whose balance or nonce was written
+    pub accounts_written: BTreeSet<Address>,
+    /// Storage slots read
+    pub slots_read: BTreeSet<(Address, B256)>,
+    /// Storage slots written
+    pub slots_written: BTreeSet<(Address, B256)>,
+}
+
+impl AccessSet {
+    /// Create an empty access set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an account read
+    pub fn read_account(&mut self, address: Address) {
+        self.accounts_read.insert(address);
+    }
+
+    /// Record an account write
+    pub fn write_account(&mut self, address: Address) {
+        self.accounts_written.insert(address);
+    }
+
+    /// Record a storage slot read
+    pub fn read_slot(&mut self, address: Address, slot: B256) {
+        self.slots_read.insert((address, slot));
+    }
+
+    /// Record a storage slot write
+    pub fn write_slot(&mut self, address: Address, slot: B256) {
+        self.slots_written.insert((address, slot));
+    }
+
+    /// Whether nothing was recorded
+    pub fn is_empty(&self) -> bool {
+        self.accounts_read.is_empty() &&
+            self.accounts_written.is_empty() &&
+            self.slots_read.is_empty() &&
+            self.slots_written.is_empty()
+    }
+
+    /// Whether the two transactions must run in order
+    ///
+    /// They conflict when either one writes an account or slot the other
+    /// reads or writes.
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        fn writes_touch(a: &AccessSet, b: &AccessSet) -> bool {
+            a.accounts_written
+                .iter()
+                .any(|addr| b.accounts_read.contains(addr) || b.accounts_written.contains(addr)) ||
+                a.slots_written
+                    .iter()
+                    .any(|slot| b.slots_read.contains(slot) || b.slots_written.contains(slot))
+        }
+        writes_touch(self, other) || writes_touch(other, self)
+    }
+
+    /// EIP-2930 style list: every touched account with its touched slots
+    pub fn access_list(&self) -> Vec<(Address, Vec<B256>)> {
+        let mut list: BTreeMap<Address, BTreeSet<B256>> = BTreeMap::new();
+        for address in self.accounts_read.iter().chain(&self.accounts_written) {
+            list.entry(*address).or_default();
+        }
+        for (address, slot) in self.slots_read.iter().chain(&self.slots_written) {
+            list.entry(*address).or_default().insert(*slot);
+        }
+        list.into_iter().map(|(address, slots)| (address, slots.into_iter().collect())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    const COUNTERS: Address = address!("0000000000000000000000000000000000000100");
+
+    #[test]
+    fn test_conflicts() {
+        let alice = address!("1111111111111111111111111111111111111111");
+        let bob = address!("2222222222222222222222222222222222222222");
+
+        let mut a = AccessSet::new();
+        a.read_account(alice);
+        a.write_account(alice);
+        a.read_slot(COUNTERS, counter_slot(alice));
+        a.write_slot(COUNTERS, counter_slot(alice));
+
+        let mut b = AccessSet::new();
+        b.read_account(bob);
+        b.write_account(bob);
+        b.read_slot(COUNTERS, counter_slot(bob));
+        assert!(!a.conflicts_with(&b));
+
+        // Reading a slot the other writes is a conflict, in either direction
+        b.read_slot(COUNTERS, counter_slot(alice));
+        assert!(a.conflicts_with(&b));
+        assert!(b.conflicts_with(&a));
+
+        // Shared reads alone are not
+        let mut c = AccessSet::new();
+        c.read_account(bob);
+        let mut d = AccessSet::new();
+        d.read_account(bob);
+        assert!(!c.conflicts_with(&d));
+    }
+
+    #[test]
+    fn test_access_list() {
+        let alice = address!("1111111111111111111111111111111111111111");
+
+        let mut set = AccessSet::new();
+        assert!(set.is_empty());
+        set.write_account(alice);
+        set.read_slot(COUNTERS, counter_slot(alice));
+        set.write_slot(COUNTERS, counter_slot(alice));
+
+        assert_eq!(
+            set.access_list(),
+            vec![(alice, vec![]), (COUNTERS, vec![counter_slot(alice)])]
+        );
+    }
+}
@@ -3,10 +3,22 @@
 //! Core primitive types for the dual VM system:
 //! - Transaction types and routing logic
 //! - DexVM receipt types
+//! - Per-transaction state access sets
+//! - Reserved system addresses
 //! - Constants
 
+pub mod access;
 pub mod receipt;
+pub mod registry;
 pub mod transaction;
 
-pub use receipt::{DexVmExecutionResult, DexVmReceipt};
-pub use transaction::{DexVmOperation, DexVmTransaction, DualVmTransaction, DEXVM_ROUTER_ADDRESS};
+pub use access::{counter_slot, labeled_counter_slot, AccessSet};
+pub use receipt::{CounterChange, DexVmExecutionResult, DexVmReceipt};
+pub use registry::{
+    system_handler, system_registry, AddressRange, PrecompileRegistry, SystemHandler,
+    COUNTER_PRECOMPILE_ADDRESS, DEXVM_PRECOMPILE_RANGE, ETHEREUM_PRECOMPILE_RANGE,
+};
+pub use transaction::{
+    split_body_transactions, DexVmOperation, DexVmTransaction, DualVmTransaction,
+    DEXVM_ROUTER_ADDRESS,
+};
@@ -1,4 +1,5 @@
-use alloy_primitives::Address;
+use crate::DexVmOperation;
+use alloy_primitives::{Address, B256, U256};
 use serde::{Deserialize, Serialize};
 
 /// DexVM execution result
@@ -14,6 +15,8 @@ pub struct DexVmExecutionResult {
     pub gas_used: u64,
     /// Error message
     pub error: Option<String>,
+    /// Value read by an EVM read operation
+    pub evm_value: Option<U256>,
 }
 
 /// DexVM transaction receipt
@@ -31,6 +34,15 @@ pub struct DexVmReceipt {
     pub gas_used: u64,
     /// Error message (if any)
     pub error: Option<String>,
+    /// Value read by an EVM read operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evm_value: Option<U256>,
+    /// Hash of the transaction, zero until [`DexVmReceipt::with_transaction`] sets it
+    #[serde(default)]
+    pub tx_hash: B256,
+    /// Operation the transaction performed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation: Option<DexVmOperation>,
 }
 
 impl From<DexVmExecutionResult> for DexVmReceipt {
@@ -42,6 +54,9 @@ impl From<DexVmExecutionResult> for DexVmReceipt {
             new_counter: result.new_counter,
             gas_used: result.gas_used,
             error: result.error,
+            evm_value: result.evm_value,
+            tx_hash: B256::ZERO,
+            operation: None,
         }
     }
 }
@@ -56,7 +71,17 @@ impl DexVmReceipt {
         gas_used: u64,
         error: Option<String>,
     ) -> Self {
-        Self { from, success, old_counter, new_counter, gas_used, error }
+        Self {
+            from,
+            success,
+            old_counter,
+            new_counter,
+            gas_used,
+            error,
+            evm_value: None,
+            tx_hash: B256::ZERO,
+            operation: None,
+        }
     }
 
     /// Create receipt from execution result and sender address
@@ -68,8 +93,31 @@ impl DexVmReceipt {
             new_counter: result.new_counter,
             gas_used: result.gas_used,
             error: result.error,
+            evm_value: result.evm_value,
+            tx_hash: B256::ZERO,
+            operation: None,
         }
     }
+
+    /// Record the transaction the receipt belongs to and its operation
+    pub fn with_transaction(mut self, tx_hash: B256, operation: DexVmOperation) -> Self {
+        self.tx_hash = tx_hash;
+        self.operation = Some(operation);
+        self
+    }
+}
+
+/// A counter value changed by one transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CounterChange {
+    /// Counter owner
+    pub address: Address,
+    /// Value before the transaction
+    pub old_value: u64,
+    /// Value after the transaction
+    pub new_value: u64,
+    /// Transaction that made the change
+    pub tx_hash: B256,
 }
 
 #[cfg(test)]
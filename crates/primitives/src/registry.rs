@@ -0,0 +1,180 @@
+//! Reserved system addresses
+//!
+//! Ethereum's precompiles sit at the lowest addresses (0x01-0x0a, with later
+//! forks adding more right above them), so [`ETHEREUM_PRECOMPILE_RANGE`]
+//! keeps 0x00-0xff free of anything this chain installs. DexVM precompiles go
+//! in [`DEXVM_PRECOMPILE_RANGE`] (0x100-0x1ff); the DexVM router keeps its
+//! address outside both. Executors and the transaction router look call
+//! targets up in the [`PrecompileRegistry`] rather than comparing addresses.
+
+use crate::transaction::DEXVM_ROUTER_ADDRESS;
+use alloy_primitives::{address, Address};
+use std::{collections::BTreeMap, sync::OnceLock};
+
+/// Counter precompile address (for EVM → DexVM cross-VM calls)
+pub const COUNTER_PRECOMPILE_ADDRESS: Address =
+    address!("0000000000000000000000000000000000000100");
+
+/// An inclusive range of reserved addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressRange {
+    pub name: &'static str,
+    pub first: Address,
+    pub last: Address,
+}
+
+impl AddressRange {
+    /// Whether `address` is in the range
+    pub fn contains(&self, address: Address) -> bool {
+        self.first <= address && address <= self.last
+    }
+}
+
+/// Addresses left to Ethereum's precompiles
+pub const ETHEREUM_PRECOMPILE_RANGE: AddressRange = AddressRange {
+    name: "Ethereum precompiles",
+    first: Address::ZERO,
+    last: address!("00000000000000000000000000000000000000ff"),
+};
+
+/// Addresses of DexVM precompiles
+pub const DEXVM_PRECOMPILE_RANGE: AddressRange = AddressRange {
+    name: "DexVM precompiles",
+    first: address!("0000000000000000000000000000000000000100"),
+    last: address!("00000000000000000000000000000000000001ff"),
+};
+
+/// What handles calls to a system address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemHandler {
+    /// Counter operations called from EVM transactions
+    CounterPrecompile,
+    /// Native DexVM transactions carried as calldata
+    DexVmRouter,
+}
+
+impl SystemHandler {
+    /// Range the handler's address must be in, if any
+    pub fn range(self) -> Option<AddressRange> {
+        match self {
+            Self::CounterPrecompile => Some(DEXVM_PRECOMPILE_RANGE),
+            Self::DexVmRouter => None,
+        }
+    }
+}
+
+/// System addresses and their handlers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecompileRegistry {
+    handlers: BTreeMap<Address, SystemHandler>,
+}
+
+impl PrecompileRegistry {
+    /// A registry with no handlers
+    pub fn empty() -> Self {
+        Self { handlers: BTreeMap::new() }
+    }
+
+    /// Install `handler` at `address`, which must not have one yet
+    pub fn register(&mut self, address: Address, handler: SystemHandler) -> Result<(), String> {
+        if let Some(existing) = self.handlers.get(&address) {
+            return Err(format!("{} is already the address of {:?}", address, existing));
+        }
+        self.handlers.insert(address, handler);
+        Ok(())
+    }
+
+    /// Check that no handler shadows an Ethereum precompile or leaves its range
+    pub fn validate(&self) -> Result<(), String> {
+        for (&address, &handler) in &self.handlers {
+            if ETHEREUM_PRECOMPILE_RANGE.contains(address) {
+                return Err(format!(
+                    "{:?} at {} is in the range of {}",
+                    handler, address, ETHEREUM_PRECOMPILE_RANGE.name
+                ));
+            }
+            if let Some(range) = handler.range().filter(|range| !range.contains(address)) {
+                return Err(format!("{:?} at {} is outside the {}", handler, address, range.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Handler of a call target
+    pub fn handler(&self, to: Option<Address>) -> Option<SystemHandler> {
+        self.handlers.get(&to?).copied()
+    }
+
+    /// Registered addresses and their handlers, in address order
+    pub fn handlers(&self) -> impl Iterator<Item = (Address, SystemHandler)> + '_ {
+        self.handlers.iter().map(|(&address, &handler)| (address, handler))
+    }
+
+    /// Whether `address` is registered or lies in a reserved range
+    pub fn is_reserved(&self, address: Address) -> bool {
+        self.handlers.contains_key(&address) ||
+            ETHEREUM_PRECOMPILE_RANGE.contains(address) ||
+            DEXVM_PRECOMPILE_RANGE.contains(address)
+    }
+}
+
+impl Default for PrecompileRegistry {
+    /// The counter precompile and the DexVM router
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for (address, handler) in [
+            (COUNTER_PRECOMPILE_ADDRESS, SystemHandler::CounterPrecompile),
+            (DEXVM_ROUTER_ADDRESS, SystemHandler::DexVmRouter),
+        ] {
+            registry.register(address, handler).expect("system addresses are distinct");
+        }
+        registry
+    }
+}
+
+/// The registry of this chain's system addresses
+pub fn system_registry() -> &'static PrecompileRegistry {
+    static REGISTRY: OnceLock<PrecompileRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(PrecompileRegistry::default)
+}
+
+/// Handler of a call target in the [`system_registry`]
+pub fn system_handler(to: Option<Address>) -> Option<SystemHandler> {
+    system_registry().handler(to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_keeps_handlers_in_their_ranges() {
+        let registry = system_registry();
+        assert!(registry.validate().is_ok());
+        assert_eq!(
+            system_handler(Some(COUNTER_PRECOMPILE_ADDRESS)),
+            Some(SystemHandler::CounterPrecompile)
+        );
+        assert_eq!(system_handler(Some(DEXVM_ROUTER_ADDRESS)), Some(SystemHandler::DexVmRouter));
+        assert_eq!(system_handler(Some(Address::repeat_byte(1))), None);
+        assert_eq!(system_handler(None), None);
+
+        // Collisions are refused
+        let mut registry = registry.clone();
+        let taken = registry.register(COUNTER_PRECOMPILE_ADDRESS, SystemHandler::DexVmRouter);
+        assert!(taken.is_err());
+
+        // A handler on an Ethereum precompile, or outside its range, fails validation
+        let ecrecover = address!("0000000000000000000000000000000000000001");
+        let mut shadowing = PrecompileRegistry::empty();
+        shadowing.register(ecrecover, SystemHandler::DexVmRouter).unwrap();
+        assert!(shadowing.validate().is_err());
+        let mut outside = PrecompileRegistry::empty();
+        outside.register(Address::repeat_byte(0x22), SystemHandler::CounterPrecompile).unwrap();
+        assert!(outside.validate().is_err());
+
+        assert!(registry.is_reserved(ecrecover));
+        assert!(registry.is_reserved(address!("00000000000000000000000000000000000001ff")));
+        assert!(!registry.is_reserved(address!("0000000000000000000000000000000000000200")));
+    }
+}
@@ -1,14 +1,16 @@
-use alloy_consensus::Transaction;
-use alloy_primitives::{Address, B256};
+use alloy_consensus::{Transaction, TxLegacy};
+use crate::registry::{system_handler, SystemHandler};
+use alloy_primitives::{Address, Signature, TxKind, B256, U256};
 use reth_ethereum_primitives::TransactionSigned;
 use reth_primitives_traits::SignerRecoverable;
+use serde::{Deserialize, Serialize};
 
 /// DexVM router address - transactions sent to this address are routed to DexVM
 pub const DEXVM_ROUTER_ADDRESS: Address =
     alloy_primitives::address!("ddddddddddddddddddddddddddddddddddddddd1");
 
 /// DexVM operation type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DexVmOperation {
     /// Increment counter
     Increment(u64),
@@ -16,6 +18,48 @@ pub enum DexVmOperation {
     Decrement(u64),
     /// Query counter
     Query,
+    /// Read an EVM account's balance, without changing any state
+    EvmBalance(Address),
+    /// Read an EVM account's storage slot, without changing any state
+    EvmStorage(Address, U256),
+}
+
+impl DexVmOperation {
+    /// Operation type byte, as in router calldata
+    pub fn code(&self) -> u8 {
+        match self {
+            Self::Increment(_) => 0,
+            Self::Decrement(_) => 1,
+            Self::Query => 2,
+            Self::EvmBalance(_) => 3,
+            Self::EvmStorage(..) => 4,
+        }
+    }
+
+    /// Amount of an increment or decrement, zero for reads
+    pub fn amount(&self) -> u64 {
+        match self {
+            Self::Increment(amount) | Self::Decrement(amount) => *amount,
+            _ => 0,
+        }
+    }
+
+    /// Router calldata of the operation, as [`DexVmTransaction::decode_calldata`] reads it
+    pub fn calldata(&self) -> Vec<u8> {
+        let mut data = vec![self.code()];
+        match self {
+            Self::Increment(amount) | Self::Decrement(amount) => {
+                data.extend_from_slice(&amount.to_be_bytes());
+            }
+            Self::Query => {}
+            Self::EvmBalance(address) => data.extend_from_slice(address.as_slice()),
+            Self::EvmStorage(address, slot) => {
+                data.extend_from_slice(address.as_slice());
+                data.extend_from_slice(&slot.to_be_bytes::<32>());
+            }
+        }
+        data
+    }
 }
 
 /// DexVM transaction
@@ -25,7 +69,12 @@ pub struct DexVmTransaction {
     pub from: Address,
     /// Operation type
     pub operation: DexVmOperation,
-    /// Signature (simplified)
+    /// Must exceed the nonce of the sender's last native transaction
+    pub nonce: u64,
+    /// EIP-191 signature over the operation, nonce and chain id
+    ///
+    /// Empty for operations decoded from router calldata, which the signed
+    /// Ethereum transaction carrying them authorizes.
     pub signature: Vec<u8>,
 }
 
@@ -33,6 +82,9 @@ impl DexVmTransaction {
     /// Decode DexVM transaction from calldata
     /// Format: [op_type: u8][amount: u64]
     /// op_type: 0 = Increment, 1 = Decrement, 2 = Query
+    ///
+    /// EVM reads carry the account, and the slot for storage reads, instead:
+    /// [3][address: 20 bytes] or [4][address: 20 bytes][slot: 32 bytes]
     pub fn decode_calldata(from: Address, calldata: &[u8]) -> Result<Self, String> {
         if calldata.is_empty() {
             return Err("Empty calldata".to_string());
@@ -59,34 +111,89 @@ impl DexVmTransaction {
                 DexVmOperation::Decrement(amount)
             }
             2 => DexVmOperation::Query,
+            3 => {
+                if calldata.len() < 21 {
+                    return Err("Invalid EVM balance read calldata length".to_string());
+                }
+                DexVmOperation::EvmBalance(Address::from_slice(&calldata[1..21]))
+            }
+            4 => {
+                if calldata.len() < 53 {
+                    return Err("Invalid EVM storage read calldata length".to_string());
+                }
+                let slot = U256::from_be_slice(&calldata[21..53]);
+                DexVmOperation::EvmStorage(Address::from_slice(&calldata[1..21]), slot)
+            }
             _ => return Err(format!("Unknown operation type: {}", op_type)),
         };
 
-        Ok(Self { from, operation, signature: vec![] })
+        Ok(Self { from, operation, nonce: 0, signature: vec![] })
     }
 
-    /// Calculate transaction hash (simplified)
+    /// Transaction hash: the hash of its block body transaction
     pub fn hash(&self) -> B256 {
-        use alloy_primitives::keccak256;
-        let mut data = Vec::new();
-        data.extend_from_slice(self.from.as_slice());
-        match self.operation {
-            DexVmOperation::Increment(amount) => {
-                data.push(0);
-                data.extend_from_slice(&amount.to_be_bytes());
-            }
-            DexVmOperation::Decrement(amount) => {
-                data.push(1);
-                data.extend_from_slice(&amount.to_be_bytes());
-            }
-            DexVmOperation::Query => {
-                data.push(2);
-            }
+        *self.to_body_transaction().tx_hash()
+    }
+
+    /// Encode as a transaction of a block body
+    ///
+    /// Block bodies hold Ethereum transactions, so a native transaction travels
+    /// as a legacy router call with an all-zero signature, which no signed
+    /// transaction can have. It carries the native nonce, and its input is
+    /// `[from: 20 bytes][operation calldata][signature]`.
+    pub fn to_body_transaction(&self) -> TransactionSigned {
+        let mut input = self.from.to_vec();
+        input.extend_from_slice(&self.operation.calldata());
+        input.extend_from_slice(&self.signature);
+        let tx = TxLegacy {
+            nonce: self.nonce,
+            to: TxKind::Call(DEXVM_ROUTER_ADDRESS),
+            input: input.into(),
+            ..Default::default()
+        };
+        TransactionSigned::new_unhashed(tx.into(), Signature::new(U256::ZERO, U256::ZERO, false))
+    }
+
+    /// Decode a native transaction from a block body transaction
+    ///
+    /// Returns `None` for signed transactions.
+    pub fn from_body_transaction(tx: &TransactionSigned) -> Option<Result<Self, String>> {
+        let signature = tx.signature();
+        if !signature.r().is_zero() || !signature.s().is_zero() {
+            return None;
+        }
+        if tx.to() != Some(DEXVM_ROUTER_ADDRESS) {
+            return Some(Err("Native DexVM transaction not sent to the router".to_string()));
+        }
+
+        let input = tx.input();
+        if input.len() < 20 {
+            return Some(Err("Native DexVM transaction input too short".to_string()));
         }
-        keccak256(&data)
+        let from = Address::from_slice(&input[..20]);
+        Some(Self::decode_calldata(from, &input[20..]).map(|decoded| {
+            let signature = input[20 + decoded.operation.calldata().len()..].to_vec();
+            Self { nonce: tx.nonce(), signature, ..decoded }
+        }))
     }
 }
 
+/// Split block body transactions into signed ones and the native DexVM ones after them
+pub fn split_body_transactions(
+    transactions: Vec<TransactionSigned>,
+) -> Result<(Vec<TransactionSigned>, Vec<DexVmTransaction>), String> {
+    let mut signed = Vec::new();
+    let mut native = Vec::new();
+    for tx in transactions {
+        match DexVmTransaction::from_body_transaction(&tx) {
+            Some(decoded) => native.push(decoded?),
+            None if native.is_empty() => signed.push(tx),
+            None => return Err(format!("Signed transaction {} follows native ones", tx.tx_hash())),
+        }
+    }
+    Ok((signed, native))
+}
+
 /// Dual VM transaction enum
 #[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
@@ -101,8 +208,11 @@ impl DualVmTransaction {
     /// Parse from Ethereum transaction
     /// Rule: if to address is the special DexVM contract address, route to DexVM
     pub fn from_ethereum_tx(tx: TransactionSigned) -> Self {
+        if let Some(Ok(native)) = DexVmTransaction::from_body_transaction(&tx) {
+            return Self::DexVm(native);
+        }
         if let Some(to) = tx.to() {
-            if to == DEXVM_ROUTER_ADDRESS {
+            if system_handler(Some(to)) == Some(SystemHandler::DexVmRouter) {
                 // Try to recover signer address
                 if let Ok(from) = tx.recover_signer() {
                     // Parse calldata as DexVM operation
@@ -131,7 +241,6 @@ impl DualVmTransaction {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use alloy_consensus::TxLegacy;
     use alloy_primitives::address;
 
     #[test]
@@ -172,6 +281,54 @@ mod tests {
         assert!(dual_tx.is_evm());
     }
 
+    #[test]
+    fn test_decode_evm_reads() {
+        let from = address!("1111111111111111111111111111111111111111");
+        let account = address!("2222222222222222222222222222222222222222");
+
+        let mut calldata = vec![3u8];
+        calldata.extend_from_slice(account.as_slice());
+        let tx = DexVmTransaction::decode_calldata(from, &calldata).unwrap();
+        assert_eq!(tx.operation, DexVmOperation::EvmBalance(account));
+
+        calldata[0] = 4;
+        assert!(DexVmTransaction::decode_calldata(from, &calldata).is_err());
+        calldata.extend_from_slice(&U256::from(7).to_be_bytes::<32>());
+        let tx = DexVmTransaction::decode_calldata(from, &calldata).unwrap();
+        assert_eq!(tx.operation, DexVmOperation::EvmStorage(account, U256::from(7)));
+    }
+
+    #[test]
+    fn test_native_transactions_travel_in_block_bodies() {
+        let native = DexVmTransaction {
+            from: address!("1111111111111111111111111111111111111111"),
+            operation: DexVmOperation::EvmStorage(Address::repeat_byte(2), U256::from(7)),
+            nonce: 3,
+            signature: vec![0xab; 65],
+        };
+        let body_tx = native.to_body_transaction();
+        assert_eq!(*body_tx.tx_hash(), native.hash());
+
+        let decoded = DexVmTransaction::from_body_transaction(&body_tx).unwrap().unwrap();
+        assert_eq!(decoded.from, native.from);
+        assert_eq!(decoded.operation, native.operation);
+        assert_eq!(decoded.nonce, 3);
+        assert_eq!(decoded.signature, native.signature);
+        assert!(DualVmTransaction::from_ethereum_tx(body_tx.clone()).is_dexvm());
+
+        // Signed transactions come first; native ones may not be followed by signed ones
+        let signed = TransactionSigned::new_unhashed(
+            TxLegacy { to: TxKind::Call(DEXVM_ROUTER_ADDRESS), ..Default::default() }.into(),
+            Signature::test_signature(),
+        );
+        assert!(DexVmTransaction::from_body_transaction(&signed).is_none());
+        let (signed_txs, native_txs) =
+            split_body_transactions(vec![signed.clone(), body_tx.clone()]).unwrap();
+        assert_eq!(signed_txs.len(), 1);
+        assert_eq!(native_txs[0].hash(), native.hash());
+        assert!(split_body_transactions(vec![body_tx, signed]).is_err());
+    }
+
     #[test]
     fn test_contract_creation_routes_to_evm() {
         // Contract creation transactions should route to EVM
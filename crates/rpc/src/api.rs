@@ -1,28 +1,103 @@
 //! DexVM REST API
+//!
+//! Counter increments and decrements must be signed by the counter owner (see
+//! [`crate::signed`]) and are queued for block inclusion like any other DexVM
+//! transaction.
 
-use alloy_primitives::{Address, B256};
+use crate::{
+    evm_rpc::{BlockRoots, EvmRpcServer},
+    signed::recover_operation_signer,
+    status::{
+        HeadStatus, MempoolStatus, NodeStatus, StartupCheck, StatusResponse, SyncStatus,
+    },
+    webhooks::{Webhook, WebhookDispatcher},
+};
+use alloy_primitives::{Address, Bytes, B256, U256};
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
-use dex_dexvm::{DexVmExecutor, DexVmOperation, DexVmTransaction};
+use dex_dexvm::{DexVmExecutor, DexVmOperation, DexVmPool, DexVmSnapshot, DexVmTransaction};
+use dex_storage::{
+    account_consistency, AccountConsistency, BlockReader, DualvmStorage, StateReader, StateWriter,
+    StoredDexVmOperation,
+};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, RwLock},
+};
 use tracing::{debug, info, warn};
 
 /// DexVM REST API service
 #[derive(Clone)]
 pub struct DexVmApi {
     executor: Arc<RwLock<DexVmExecutor>>,
+    pool: Option<Arc<DexVmPool>>,
+    chain_id: u64,
+    /// Highest queued signed-operation nonce per sender
+    ///
+    /// Nonces of executed operations are in the DexVM state, which is stored
+    /// with each block, so they stay replay-protected across restarts.
+    nonces: Arc<Mutex<HashMap<Address, u64>>>,
+    status: Arc<NodeStatus>,
+    storage: Option<Arc<DualvmStorage>>,
+    evm_rpc: Option<Arc<EvmRpcServer>>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
 }
 
 impl DexVmApi {
     /// Create new API service
     pub fn new(executor: Arc<RwLock<DexVmExecutor>>) -> Self {
-        Self { executor }
+        Self {
+            executor,
+            pool: None,
+            chain_id: 1,
+            nonces: Arc::default(),
+            status: Arc::default(),
+            storage: None,
+            evm_rpc: None,
+            webhooks: None,
+        }
+    }
+
+    /// Chain id signed operations must commit to
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Queue submitted transactions into the given pool for block inclusion
+    pub fn with_pool(mut self, pool: Arc<DexVmPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Report uptime, peers and validator from the given node status
+    pub fn with_status(mut self, status: Arc<NodeStatus>) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Read head block and database size for `/status` from storage
+    pub fn with_storage(mut self, storage: Arc<DualvmStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Count pending EVM transactions for `/status`
+    pub fn with_evm_rpc(mut self, evm_rpc: Arc<EvmRpcServer>) -> Self {
+        self.evm_rpc = Some(evm_rpc);
+        self
+    }
+
+    /// Manage counter change webhooks through `/api/v1/webhooks`
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
     }
 
     /// Create routes
@@ -30,10 +105,22 @@ impl DexVmApi {
         Router::new()
             .route("/", get(health_check))
             .route("/health", get(health_check))
+            .route("/status", get(get_status))
             .route("/api/v1/counter/:address", get(get_counter))
             .route("/api/v1/counter/:address/increment", post(increment_counter))
             .route("/api/v1/counter/:address/decrement", post(decrement_counter))
+            .route("/api/v1/counter/:address/labels", get(get_counter_labels))
+            .route("/api/v1/counter/:address/labels/:label", get(get_labeled_counter))
+            .route("/api/v1/counters/top", get(get_top_counters))
             .route("/api/v1/state-root", get(get_state_root))
+            .route("/api/v1/blocks/:number", get(get_block))
+            .route("/api/v1/blocks/:number/roots", get(get_block_roots))
+            .route("/api/v1/transactions", post(submit_transaction))
+            .route("/api/v1/dexvm/export", get(export_dexvm))
+            .route("/api/v1/dexvm/import", post(import_dexvm))
+            .route("/api/v1/dexvm/consistency", get(get_account_consistency))
+            .route("/api/v1/webhooks", get(list_webhooks).post(register_webhook))
+            .route("/api/v1/webhooks/:id", delete(unregister_webhook))
             .with_state(self)
     }
 }
@@ -45,27 +132,125 @@ pub struct CounterResponse {
     pub counter: u64,
 }
 
-/// Increment counter request
+/// Labeled counter query response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LabeledCounterResponse {
+    pub address: Address,
+    pub label: B256,
+    pub counter: u64,
+}
+
+/// One labeled counter of an address
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CounterLabel {
+    pub label: B256,
+    pub counter: u64,
+}
+
+/// Labeled counters of an address, by label
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CounterLabelsResponse {
+    pub address: Address,
+    pub labels: Vec<CounterLabel>,
+}
+
+/// Counters returned by `/api/v1/counters/top` when no limit is given
+const DEFAULT_TOP_COUNTERS: usize = 10;
+
+/// Most counters `/api/v1/counters/top` returns
+const MAX_TOP_COUNTERS: usize = 100;
+
+/// Top counters query parameters
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopCountersQuery {
+    /// Number of counters, capped at 100
+    pub limit: Option<usize>,
+}
+
+/// Addresses of each kind `/api/v1/dexvm/consistency` lists when no limit is given
+const DEFAULT_CONSISTENCY_LIMIT: usize = 100;
+
+/// Most addresses of each kind `/api/v1/dexvm/consistency` lists
+const MAX_CONSISTENCY_LIMIT: usize = 1000;
+
+/// Account consistency query parameters
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsistencyQuery {
+    /// Addresses listed of each kind, capped at 1000
+    pub limit: Option<usize>,
+}
+
+/// Signed increment counter request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IncrementRequest {
     pub amount: u64,
+    /// Must exceed the sender's last accepted nonce
+    pub nonce: u64,
+    /// EIP-191 signature over the operation message
+    pub signature: Bytes,
 }
 
-/// Decrement counter request
+/// Signed decrement counter request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DecrementRequest {
     pub amount: u64,
+    /// Must exceed the sender's last accepted nonce
+    pub nonce: u64,
+    /// EIP-191 signature over the operation message
+    pub signature: Bytes,
 }
 
-/// Operation response
+/// Signed native DexVM transaction submission
 #[derive(Debug, Serialize, Deserialize)]
-pub struct OperationResponse {
-    pub success: bool,
+pub struct SubmitTransactionRequest {
+    pub from: Address,
+    #[serde(flatten)]
+    pub operation: SubmitOperation,
+    /// Must exceed the sender's last accepted nonce
+    pub nonce: u64,
+    /// EIP-191 signature over the operation message
+    pub signature: Bytes,
+}
+
+/// Operation of a submitted transaction
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum SubmitOperation {
+    Increment { amount: u64 },
+    Decrement { amount: u64 },
+    Query,
+    /// Read an EVM account's balance
+    #[serde(rename = "evm_balance")]
+    EvmBalance { address: Address },
+    /// Read an EVM account's storage slot
+    #[serde(rename = "evm_storage")]
+    EvmStorage { address: Address, slot: U256 },
+}
+
+impl From<SubmitOperation> for DexVmOperation {
+    fn from(op: SubmitOperation) -> Self {
+        match op {
+            SubmitOperation::Increment { amount } => Self::Increment(amount),
+            SubmitOperation::Decrement { amount } => Self::Decrement(amount),
+            SubmitOperation::Query => Self::Query,
+            SubmitOperation::EvmBalance { address } => Self::EvmBalance(address),
+            SubmitOperation::EvmStorage { address, slot } => Self::EvmStorage(address, slot),
+        }
+    }
+}
+
+/// Submission response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitTransactionResponse {
     pub tx_hash: B256,
-    pub old_counter: u64,
-    pub new_counter: u64,
-    pub gas_used: u64,
-    pub error: Option<String>,
+    pub queue_position: usize,
+}
+
+/// Webhook registration request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterWebhookRequest {
+    /// `http://` URL that receives counter change events
+    pub url: String,
 }
 
 /// State root response
@@ -74,6 +259,58 @@ pub struct StateRootResponse {
     pub state_root: B256,
 }
 
+/// Stored block with the DexVM operations it performed
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockResponse {
+    pub number: u64,
+    pub hash: B256,
+    pub parent_hash: B256,
+    pub timestamp: u64,
+    pub transaction_hashes: Vec<B256>,
+    /// Operations of the block's DexVM transactions, in execution order
+    ///
+    /// Empty for blocks a fullnode imported without executing them.
+    pub dexvm_operations: Vec<BlockDexVmOperation>,
+}
+
+/// A DexVM operation executed in a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDexVmOperation {
+    pub tx_hash: B256,
+    /// Counter owner
+    pub address: Address,
+    /// `increment`, `decrement`, `query`, `evm_balance` or `evm_storage`
+    pub op: String,
+    /// Amount of an increment or decrement, zero for reads
+    pub amount: u64,
+    pub success: bool,
+    /// Counter value after the operation
+    pub new_value: u64,
+}
+
+impl From<&StoredDexVmOperation> for BlockDexVmOperation {
+    fn from(operation: &StoredDexVmOperation) -> Self {
+        let op = match operation.op {
+            0 => "increment",
+            1 => "decrement",
+            2 => "query",
+            3 => "evm_balance",
+            4 => "evm_storage",
+            _ => "unknown",
+        };
+        Self {
+            tx_hash: operation.tx_hash,
+            address: operation.address,
+            op: op.to_string(),
+            amount: operation.amount,
+            success: operation.success,
+            new_value: operation.new_value,
+        }
+    }
+}
+
 /// API error type
 #[derive(Debug)]
 pub struct ApiError {
@@ -107,19 +344,74 @@ impl IntoResponse for ApiError {
 /// Health check response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HealthResponse {
+    /// `ok`, or `degraded` if the startup check found an inconsistency or block
+    /// production stalled
     pub status: String,
     pub service: String,
     pub version: String,
+    /// Startup consistency check, run after an unclean shutdown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_check: Option<StartupCheck>,
+    /// Set while a validator has not produced a block for too long
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub production_stalled: bool,
 }
 
-async fn health_check() -> Json<HealthResponse> {
+async fn health_check(State(api): State<DexVmApi>) -> Json<HealthResponse> {
+    let startup_check = api.status.startup_check();
+    let production_stalled = api.status.production_stalled();
+    let status = match &startup_check {
+        Some(check) if !check.is_ok() => "degraded",
+        _ if production_stalled => "degraded",
+        _ => "ok",
+    };
     Json(HealthResponse {
-        status: "ok".to_string(),
+        status: status.to_string(),
         service: "dexvm-api".to_string(),
-        version: "0.1.0".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        startup_check,
+        production_stalled,
     })
 }
 
+async fn get_status(State(api): State<DexVmApi>) -> Result<Json<StatusResponse>, ApiError> {
+    let storage = api.storage.as_ref().ok_or_else(|| {
+        ApiError::new("Node status unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+
+    let head = storage
+        .blocks
+        .get_latest_block()
+        .map(|block| HeadStatus {
+            number: block.number,
+            hash: block.hash,
+            timestamp: block.timestamp,
+        })
+        .ok_or_else(|| ApiError::internal_error("No blocks stored"))?;
+    let db_size_bytes = storage.file_size();
+
+    Ok(Json(StatusResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        chain_id: api.chain_id,
+        uptime_secs: api.status.uptime_secs(),
+        sync: SyncStatus::new(head.number, api.status.highest_peer_block()),
+        head,
+        peers: api.status.peer_count(),
+        mempool: MempoolStatus {
+            evm_pending: api.evm_rpc.as_ref().map_or(0, |rpc| rpc.pending_count()),
+            dexvm_pending: api.pool.as_ref().map_or(0, |pool| pool.len()),
+            quarantined: api.evm_rpc.as_ref().map_or(0, |rpc| rpc.quarantined_count()),
+            dropped: api.evm_rpc.as_ref().map(|rpc| rpc.pool_drops()).unwrap_or_default(),
+        },
+        validator: api.status.validator(),
+        db_size_bytes,
+        db_growth_bytes_per_day: api.status.storage_growth().bytes_per_day(),
+        db_days_until_budget: api.status.storage_growth().days_until_budget(db_size_bytes),
+        tx_execution: api.status.tx_execution().summary(),
+        production: api.status.production(),
+    }))
+}
+
 async fn get_counter(
     Path(address): Path<Address>,
     State(api): State<DexVmApi>,
@@ -133,108 +425,222 @@ async fn get_counter(
     Ok(Json(CounterResponse { address, counter }))
 }
 
+async fn get_labeled_counter(
+    Path((address, label)): Path<(Address, B256)>,
+    State(api): State<DexVmApi>,
+) -> Result<Json<LabeledCounterResponse>, ApiError> {
+    let executor = api.executor.read().map_err(|e| ApiError::internal_error(e.to_string()))?;
+
+    let counter = executor.state().get_labeled_counter(&address, label);
+
+    debug!(address = %address, label = %label, counter = counter, "DexVM labeled counter queried");
+
+    Ok(Json(LabeledCounterResponse { address, label, counter }))
+}
+
+/// Non-zero labeled counters of an address
+async fn get_counter_labels(
+    Path(address): Path<Address>,
+    State(api): State<DexVmApi>,
+) -> Result<Json<CounterLabelsResponse>, ApiError> {
+    let executor = api.executor.read().map_err(|e| ApiError::internal_error(e.to_string()))?;
+
+    let labels = executor
+        .state()
+        .counter_labels(&address)
+        .into_iter()
+        .map(|(label, counter)| CounterLabel { label, counter })
+        .collect();
+
+    Ok(Json(CounterLabelsResponse { address, labels }))
+}
+
+/// Highest stored counters, largest first
+async fn get_top_counters(
+    Query(query): Query<TopCountersQuery>,
+    State(api): State<DexVmApi>,
+) -> Result<Json<Vec<CounterResponse>>, ApiError> {
+    let storage = api.storage.as_ref().ok_or_else(|| {
+        ApiError::new("Storage unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_TOP_COUNTERS).min(MAX_TOP_COUNTERS);
+    let top = storage
+        .state
+        .top_counters(limit)
+        .into_iter()
+        .map(|(address, counter)| CounterResponse { address, counter })
+        .collect();
+
+    Ok(Json(top))
+}
+
 async fn increment_counter(
     Path(address): Path<Address>,
     State(api): State<DexVmApi>,
     Json(req): Json<IncrementRequest>,
-) -> Result<Json<OperationResponse>, ApiError> {
-    if req.amount == 0 {
-        warn!(address = %address, "DexVM increment rejected: amount is 0");
+) -> Result<Json<SubmitTransactionResponse>, ApiError> {
+    let operation = DexVmOperation::Increment(req.amount);
+    submit_signed(&api, address, operation, req.nonce, &req.signature).map(Json)
+}
+
+async fn decrement_counter(
+    Path(address): Path<Address>,
+    State(api): State<DexVmApi>,
+    Json(req): Json<DecrementRequest>,
+) -> Result<Json<SubmitTransactionResponse>, ApiError> {
+    let operation = DexVmOperation::Decrement(req.amount);
+    submit_signed(&api, address, operation, req.nonce, &req.signature).map(Json)
+}
+
+/// Verify a signed counter operation and queue it for block inclusion
+fn submit_signed(
+    api: &DexVmApi,
+    address: Address,
+    operation: DexVmOperation,
+    nonce: u64,
+    signature: &[u8],
+) -> Result<SubmitTransactionResponse, ApiError> {
+    if matches!(operation, DexVmOperation::Increment(0) | DexVmOperation::Decrement(0)) {
+        warn!(address = %address, "DexVM operation rejected: amount is 0");
         return Err(ApiError::bad_request("Amount must be greater than 0"));
     }
 
-    let mut executor = api.executor.write().map_err(|e| ApiError::internal_error(e.to_string()))?;
+    let pool = api.pool.as_ref().ok_or_else(|| {
+        ApiError::new("Transaction pool unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
 
-    let tx = DexVmTransaction {
-        from: address,
-        operation: DexVmOperation::Increment(req.amount),
-        signature: vec![],
-    };
+    let signer = recover_operation_signer(operation, nonce, api.chain_id, signature)
+        .map_err(ApiError::bad_request)?;
+    if signer != address {
+        warn!(address = %address, signer = %signer, "DexVM operation rejected: signer mismatch");
+        return Err(ApiError::new(
+            format!("Signature is from {}, not {}", signer, address),
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
 
-    let tx_hash = tx.hash();
+    let executed = api
+        .executor
+        .read()
+        .map_err(|e| ApiError::internal_error(e.to_string()))?
+        .state()
+        .get_nonce(&address);
 
-    let result =
-        executor.execute_transaction(&tx).map_err(|e| ApiError::internal_error(e.to_string()))?;
+    // Hold the nonce lock until queued so concurrent replays cannot both pass
+    let mut nonces = api.nonces.lock().unwrap_or_else(|e| e.into_inner());
+    let last = nonces.get(&address).copied().unwrap_or(0).max(executed);
+    if nonce <= last {
+        return Err(ApiError::bad_request(format!(
+            "Nonce {} already used, must be greater than {}",
+            nonce, last
+        )));
+    }
 
-    executor.commit();
+    let tx = DexVmTransaction { from: address, operation, nonce, signature: signature.to_vec() };
+    let (tx_hash, queue_position) =
+        pool.submit(tx).map_err(|e| ApiError::new(e, StatusCode::SERVICE_UNAVAILABLE))?;
+    nonces.insert(address, nonce);
 
     info!(
         address = %address,
-        operation = "increment",
-        amount = req.amount,
-        old_counter = result.old_counter,
-        new_counter = result.new_counter,
+        operation = ?operation,
+        nonce = nonce,
         tx_hash = %tx_hash,
-        gas_used = result.gas_used,
-        "DexVM counter incremented"
+        queue_position = queue_position,
+        "Signed DexVM operation queued"
     );
 
-    Ok(Json(OperationResponse {
-        success: result.success,
-        tx_hash,
-        old_counter: result.old_counter,
-        new_counter: result.new_counter,
-        gas_used: result.gas_used,
-        error: result.error,
-    }))
+    Ok(SubmitTransactionResponse { tx_hash, queue_position })
 }
 
-async fn decrement_counter(
-    Path(address): Path<Address>,
+async fn submit_transaction(
     State(api): State<DexVmApi>,
-    Json(req): Json<DecrementRequest>,
-) -> Result<Json<OperationResponse>, ApiError> {
-    if req.amount == 0 {
-        warn!(address = %address, "DexVM decrement rejected: amount is 0");
-        return Err(ApiError::bad_request("Amount must be greater than 0"));
-    }
+    Json(req): Json<SubmitTransactionRequest>,
+) -> Result<Json<SubmitTransactionResponse>, ApiError> {
+    submit_signed(&api, req.from, req.operation.into(), req.nonce, &req.signature).map(Json)
+}
+
+async fn export_dexvm(State(api): State<DexVmApi>) -> Result<Json<DexVmSnapshot>, ApiError> {
+    let executor = api.executor.read().map_err(|e| ApiError::internal_error(e.to_string()))?;
+    Ok(Json(DexVmSnapshot::from_state(executor.state())))
+}
+
+/// Addresses with a counter but no EVM account, and EVM accounts without a counter
+async fn get_account_consistency(
+    Query(query): Query<ConsistencyQuery>,
+    State(api): State<DexVmApi>,
+) -> Result<Json<AccountConsistency>, ApiError> {
+    let storage = api.storage.as_ref().ok_or_else(|| {
+        ApiError::new("Storage unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_CONSISTENCY_LIMIT).min(MAX_CONSISTENCY_LIMIT);
+    Ok(Json(account_consistency(storage.state.as_ref(), limit)))
+}
+
+/// Load a snapshot into a node that has not produced or imported any block yet
+async fn import_dexvm(
+    State(api): State<DexVmApi>,
+    Json(snapshot): Json<DexVmSnapshot>,
+) -> Result<Json<StateRootResponse>, ApiError> {
+    let storage = api.storage.as_ref().ok_or_else(|| {
+        ApiError::new("Storage unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+    let state = snapshot.to_state().map_err(ApiError::bad_request)?;
 
     let mut executor = api.executor.write().map_err(|e| ApiError::internal_error(e.to_string()))?;
+    let head = storage.blocks.get_latest_block().map_or(0, |block| block.number);
+    if head > 0 || !executor.state().is_empty() {
+        return Err(ApiError::new(
+            "DexVM state can only be imported into a fresh node",
+            StatusCode::CONFLICT,
+        ));
+    }
 
-    let tx = DexVmTransaction {
-        from: address,
-        operation: DexVmOperation::Decrement(req.amount),
-        signature: vec![],
-    };
+    let counters: Vec<_> =
+        snapshot.counters.iter().map(|(address, value)| (*address, *value)).collect();
+    storage.state.set_counters(&counters).map_err(|e| ApiError::internal_error(e.to_string()))?;
+    storage
+        .state
+        .set_labeled_counters(&snapshot.labeled_entries())
+        .map_err(|e| ApiError::internal_error(e.to_string()))?;
+    *executor = DexVmExecutor::new(state);
+    let state_root = executor.state_root();
 
-    let tx_hash = tx.hash();
+    info!(counters = snapshot.counters.len(), state_root = %state_root, "DexVM state imported");
 
-    let result =
-        executor.execute_transaction(&tx).map_err(|e| ApiError::internal_error(e.to_string()))?;
+    Ok(Json(StateRootResponse { state_root }))
+}
 
-    executor.commit();
+fn webhook_dispatcher(api: &DexVmApi) -> Result<&Arc<WebhookDispatcher>, ApiError> {
+    api.webhooks
+        .as_ref()
+        .ok_or_else(|| ApiError::new("Webhooks unavailable", StatusCode::SERVICE_UNAVAILABLE))
+}
 
-    if result.success {
-        info!(
-            address = %address,
-            operation = "decrement",
-            amount = req.amount,
-            old_counter = result.old_counter,
-            new_counter = result.new_counter,
-            tx_hash = %tx_hash,
-            gas_used = result.gas_used,
-            "DexVM counter decremented"
-        );
-    } else {
-        warn!(
-            address = %address,
-            operation = "decrement",
-            amount = req.amount,
-            old_counter = result.old_counter,
-            tx_hash = %tx_hash,
-            error = ?result.error,
-            "DexVM decrement failed"
-        );
-    }
+async fn list_webhooks(State(api): State<DexVmApi>) -> Result<Json<Vec<Webhook>>, ApiError> {
+    Ok(Json(webhook_dispatcher(&api)?.list()))
+}
 
-    Ok(Json(OperationResponse {
-        success: result.success,
-        tx_hash,
-        old_counter: result.old_counter,
-        new_counter: result.new_counter,
-        gas_used: result.gas_used,
-        error: result.error,
-    }))
+async fn register_webhook(
+    State(api): State<DexVmApi>,
+    Json(req): Json<RegisterWebhookRequest>,
+) -> Result<Json<Webhook>, ApiError> {
+    let webhook = webhook_dispatcher(&api)?.register(&req.url).map_err(ApiError::bad_request)?;
+    info!(id = webhook.id, url = %webhook.url, "Webhook registered");
+    Ok(Json(webhook))
+}
+
+async fn unregister_webhook(
+    Path(id): Path<u64>,
+    State(api): State<DexVmApi>,
+) -> Result<StatusCode, ApiError> {
+    if !webhook_dispatcher(&api)?.unregister(id) {
+        return Err(ApiError::new(format!("Webhook {} not found", id), StatusCode::NOT_FOUND));
+    }
+    info!(id = id, "Webhook removed");
+    Ok(StatusCode::NO_CONTENT)
 }
 
 async fn get_state_root(State(api): State<DexVmApi>) -> Result<Json<StateRootResponse>, ApiError> {
@@ -245,12 +651,55 @@ async fn get_state_root(State(api): State<DexVmApi>) -> Result<Json<StateRootRes
     Ok(Json(StateRootResponse { state_root }))
 }
 
+/// A stored block with the DexVM operations of its transactions
+async fn get_block(
+    Path(number): Path<u64>,
+    State(api): State<DexVmApi>,
+) -> Result<Json<BlockResponse>, ApiError> {
+    let storage = api.storage.as_ref().ok_or_else(|| {
+        ApiError::new("Block storage unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+    let block = storage.blocks.get_block_by_number(number).ok_or_else(|| {
+        ApiError::new(format!("Block {} not found", number), StatusCode::NOT_FOUND)
+    })?;
+    let dexvm_operations = storage
+        .blocks
+        .get_dexvm_receipts(number)
+        .map(|receipts| receipts.operations.iter().map(BlockDexVmOperation::from).collect())
+        .unwrap_or_default();
+    Ok(Json(BlockResponse {
+        number: block.number,
+        hash: block.hash,
+        parent_hash: block.parent_hash,
+        timestamp: block.timestamp,
+        transaction_hashes: block.transaction_hashes,
+        dexvm_operations,
+    }))
+}
+
+/// State roots and header hash of a stored block, as `dex_getBlockRoots` returns them
+async fn get_block_roots(
+    Path(number): Path<u64>,
+    State(api): State<DexVmApi>,
+) -> Result<Json<BlockRoots>, ApiError> {
+    let storage = api.storage.as_ref().ok_or_else(|| {
+        ApiError::new("Block storage unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    })?;
+    let block = storage.blocks.get_block_by_number(number).ok_or_else(|| {
+        ApiError::new(format!("Block {} not found", number), StatusCode::NOT_FOUND)
+    })?;
+    Ok(Json(BlockRoots::from(&block)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use alloy_primitives::address;
     use axum::{body::Body, http::Request};
+    use crate::signed::sign_operation;
     use dex_dexvm::DexVmState;
+    use dex_storage::BlockWriter;
+    use secp256k1::SecretKey;
     use tower::ServiceExt;
 
     #[tokio::test]
@@ -273,30 +722,407 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    fn signed_increment(key: &SecretKey, amount: u64, nonce: u64) -> Request<Body> {
+        let signer = crate::signed::tests::key_address(key);
+        let signature = sign_operation(key, DexVmOperation::Increment(amount), nonce, 13337);
+        let req_body = serde_json::to_string(&IncrementRequest {
+            amount,
+            nonce,
+            signature: Bytes::copy_from_slice(&signature),
+        })
+        .unwrap();
+
+        Request::builder()
+            .method("POST")
+            .uri(format!("/api/v1/counter/{}/increment", signer))
+            .header("content-type", "application/json")
+            .body(Body::from(req_body))
+            .unwrap()
+    }
+
     #[tokio::test]
-    async fn test_increment_counter() {
+    async fn test_signed_increment_is_queued() {
         let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
-        let api = DexVmApi::new(executor.clone());
-        let app = api.routes();
+        let pool = Arc::new(DexVmPool::default());
+        let app =
+            DexVmApi::new(executor.clone()).with_pool(pool.clone()).with_chain_id(13337).routes();
+
+        let key = SecretKey::from_slice(&[0x22; 32]).unwrap();
+        let response = app.clone().oneshot(signed_increment(&key, 10, 1)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
 
-        let addr = address!("2222222222222222222222222222222222222222");
-        let req_body = serde_json::to_string(&IncrementRequest { amount: 10 }).unwrap();
+        // Queued for the block builder, not executed directly
+        assert_eq!(pool.len(), 1);
+        let signer = crate::signed::tests::key_address(&key);
+        assert_eq!(executor.read().unwrap().state().get_counter(&signer), 0);
 
+        // Replaying the same nonce is rejected
+        let response = app.clone().oneshot(signed_increment(&key, 10, 1)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // A signature from another key does not authorize this counter
+        let other = SecretKey::from_slice(&[0x33; 32]).unwrap();
+        let signature = sign_operation(&other, DexVmOperation::Increment(5), 2, 13337);
+        let req_body = serde_json::json!({
+            "amount": 5,
+            "nonce": 2,
+            "signature": Bytes::copy_from_slice(&signature),
+        });
         let response = app
             .oneshot(
                 Request::builder()
                     .method("POST")
-                    .uri(format!("/api/v1/counter/{}/increment", addr))
+                    .uri(format!("/api/v1/counter/{}/increment", signer))
+                    .header("content-type", "application/json")
+                    .body(Body::from(req_body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_submit_evm_reads() {
+        let account = address!("4444444444444444444444444444444444444444");
+        let request: SubmitTransactionRequest = serde_json::from_value(serde_json::json!({
+            "from": account,
+            "operation": "evm_storage",
+            "address": account,
+            "slot": "0x2",
+            "nonce": 1,
+            "signature": "0x",
+        }))
+        .unwrap();
+        assert_eq!(
+            DexVmOperation::from(request.operation),
+            DexVmOperation::EvmStorage(account, U256::from(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_submit_transaction_requires_signature() {
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let pool = Arc::new(DexVmPool::default());
+        let app =
+            DexVmApi::new(executor.clone()).with_pool(pool.clone()).with_chain_id(13337).routes();
+        let key = SecretKey::from_slice(&[0x33; 32]).unwrap();
+        let addr = crate::signed::tests::key_address(&key);
+        let submit = |nonce: u64, signature: &[u8]| {
+            let req_body = serde_json::json!({
+                "from": addr,
+                "operation": "increment",
+                "amount": 7,
+                "nonce": nonce,
+                "signature": Bytes::copy_from_slice(signature),
+            });
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/transactions")
                     .header("content-type", "application/json")
-                    .body(Body::from(req_body))
+                    .body(Body::from(req_body.to_string()))
                     .unwrap(),
             )
+        };
+
+        let response = submit(1, &[]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let signature = sign_operation(&key, DexVmOperation::Increment(7), 1, 13337);
+        let response = submit(1, &signature).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Queued, not executed
+        assert_eq!(pool.len(), 1);
+        assert_eq!(executor.read().unwrap().state().get_counter(&addr), 0);
+
+        // Nonces the DexVM state already executed are rejected, e.g. after a restart
+        executor.write().unwrap().pending_state_mut().set_nonce(addr, 5);
+        executor.write().unwrap().sync_pending_to_state();
+        let signature = sign_operation(&key, DexVmOperation::Increment(7), 3, 13337);
+        let response = submit(3, &signature).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_status() {
+        let storage = Arc::new(DualvmStorage::in_memory().unwrap());
+        storage.blocks.init_genesis(13337).unwrap();
+
+        let status = Arc::new(NodeStatus::new());
+        status.set_peer_count(2);
+        status.set_highest_peer_block(5);
+        status.set_validator(address!("4444444444444444444444444444444444444444"));
+
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let app = DexVmApi::new(executor)
+            .with_chain_id(13337)
+            .with_pool(Arc::new(DexVmPool::default()))
+            .with_status(status)
+            .with_storage(storage)
+            .routes();
+
+        let response = app
+            .oneshot(Request::builder().uri("/status").body(Body::empty()).unwrap())
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let status: StatusResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status.chain_id, 13337);
+        assert_eq!(status.head.number, 0);
+        assert_eq!(status.peers, 2);
+        assert!(status.sync.syncing);
+        assert_eq!(status.sync.highest_block, 5);
+        assert_eq!(status.mempool.dexvm_pending, 0);
+        assert!(status.validator.is_some());
+        assert!(status.db_size_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_roots() {
+        let storage = Arc::new(DualvmStorage::in_memory().unwrap());
+        storage.blocks.init_genesis(13337).unwrap();
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let app = DexVmApi::new(executor).with_storage(Arc::clone(&storage)).routes();
+        let get = |uri: &str| {
+            app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
 
+        let response = get("/api/v1/blocks/0/roots").await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let roots: BlockRoots = serde_json::from_slice(&body).unwrap();
+        let genesis = storage.blocks.get_block_by_number(0).unwrap();
+        assert_eq!(roots, BlockRoots::from(&genesis));
+
+        let response = get("/api/v1/blocks/1/roots").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_block_lists_dexvm_operations() {
+        let storage = Arc::new(DualvmStorage::in_memory().unwrap());
+        storage.blocks.init_genesis(13337).unwrap();
+        let mut block = storage.blocks.get_block_by_number(0).unwrap();
+        block.number = 1;
+        block.parent_hash = block.hash;
+        block.hash = B256::repeat_byte(1);
+        let tx_hash = B256::repeat_byte(2);
+        block.transaction_hashes = vec![tx_hash];
+        let operation = StoredDexVmOperation {
+            tx_hash,
+            address: Address::repeat_byte(3),
+            op: 1,
+            amount: 4,
+            success: true,
+            new_value: 6,
+        };
+        let receipts = dex_storage::StoredDexVmReceipts { operations: vec![operation] };
+        storage.blocks.store_dexvm_receipts(1, receipts).unwrap();
+        storage.blocks.store_block(block).unwrap();
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let app = DexVmApi::new(executor).with_storage(Arc::clone(&storage)).routes();
+        let get = |uri: &str| {
+            app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let response = get("/api/v1/blocks/1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["dexvmOperations"][0]["op"], "decrement");
+        let block: BlockResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(block.transaction_hashes, vec![tx_hash]);
+        assert_eq!(
+            block.dexvm_operations,
+            vec![BlockDexVmOperation {
+                tx_hash,
+                address: Address::repeat_byte(3),
+                op: "decrement".to_string(),
+                amount: 4,
+                success: true,
+                new_value: 6,
+            }]
+        );
+
+        // Genesis ran no DexVM transactions
+        let response = get("/api/v1/blocks/0").await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let genesis: BlockResponse = serde_json::from_slice(&body).unwrap();
+        assert!(genesis.dexvm_operations.is_empty());
+
+        let response = get("/api/v1/blocks/2").await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_startup_check() {
+        let status = Arc::new(NodeStatus::new());
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let app = DexVmApi::new(executor).with_status(Arc::clone(&status)).routes();
+        let health = |app: Router| async move {
+            let response = app
+                .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<HealthResponse>(&body).unwrap()
+        };
+
+        let response = health(app.clone()).await;
+        assert_eq!(response.status, "ok");
+        assert!(response.startup_check.is_none());
+
+        status.set_production_stalled();
+        let response = health(app.clone()).await;
+        assert_eq!(response.status, "degraded");
+        assert!(response.production_stalled);
+        status.record_block_produced();
+        assert_eq!(health(app.clone()).await.status, "ok");
+
+        let check = StartupCheck {
+            clean_shutdown: false,
+            blocks_checked: 3,
+            error: Some("block 3: block missing".to_string()),
+        };
+        status.set_startup_check(check.clone());
+        let response = health(app).await;
+        assert_eq!(response.status, "degraded");
+        assert_eq!(response.startup_check, Some(check));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_registration() {
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let webhooks = Arc::new(WebhookDispatcher::new(Default::default()));
+        let app = DexVmApi::new(executor).with_webhooks(Arc::clone(&webhooks)).routes();
+
+        let register = |url: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/webhooks")
+                .header("content-type", "application/json")
+                .body(Body::from(serde_json::json!({ "url": url }).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(register("http://127.0.0.1:9000/hook")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let webhook: Webhook = serde_json::from_slice(&body).unwrap();
+        assert_eq!(webhooks.list(), vec![webhook.clone()]);
+
+        let response = app.clone().oneshot(register("ftp://example.com")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let delete = |id: u64| {
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/api/v1/webhooks/{}", id))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(delete(webhook.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = app.oneshot(delete(webhook.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(webhooks.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dexvm_export_import() {
+        let storage = Arc::new(DualvmStorage::in_memory().unwrap());
+        storage.blocks.init_genesis(13337).unwrap();
+
+        let mut source = DexVmState::default();
+        source.set_counter(address!("1111111111111111111111111111111111111111"), 42);
+        source.set_labeled_counter(Address::repeat_byte(2), B256::repeat_byte(1), 7);
+        let source_app =
+            DexVmApi::new(Arc::new(RwLock::new(DexVmExecutor::new(source.clone())))).routes();
+        let response = source_app
+            .oneshot(Request::builder().uri("/api/v1/dexvm/export").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let snapshot = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let app = DexVmApi::new(Arc::clone(&executor)).with_storage(Arc::clone(&storage)).routes();
+        let import = || {
+            Request::builder()
+                .method("POST")
+                .uri("/api/v1/dexvm/import")
+                .header("content-type", "application/json")
+                .body(Body::from(snapshot.clone()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(import()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(executor.read().unwrap().state_root(), source.state_root());
+        assert_eq!(storage.state.all_counters().len(), 1);
+        assert_eq!(storage.state.all_labeled_counters().len(), 1);
+
+        // The node is no longer fresh
+        let response = app.oneshot(import()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_top_counters() {
+        let storage = Arc::new(DualvmStorage::in_memory().unwrap());
+        let leader = address!("1111111111111111111111111111111111111111");
+        let runner_up = address!("2222222222222222222222222222222222222222");
+        storage.state.set_counters(&[(runner_up, 3), (leader, 8)]).unwrap();
+
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(DexVmState::default())));
+        let app = DexVmApi::new(executor).with_storage(storage).routes();
+        let request =
+            Request::builder().uri("/api/v1/counters/top?limit=1").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let top: Vec<CounterResponse> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(top.len(), 1);
+        assert_eq!((top[0].address, top[0].counter), (leader, 8));
+    }
+
+    #[tokio::test]
+    async fn test_labeled_counters() {
+        let owner = address!("1111111111111111111111111111111111111111");
+        let (score, visits) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        let mut state = DexVmState::default();
+        state.set_counter(owner, 5);
+        state.set_labeled_counter(owner, visits, 3);
+        state.set_labeled_counter(owner, score, 9);
+        let app = DexVmApi::new(Arc::new(RwLock::new(DexVmExecutor::new(state)))).routes();
+
+        let get = |uri: String| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response =
+            app.clone().oneshot(get(format!("/api/v1/counter/{}/labels", owner))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let labels: CounterLabelsResponse = serde_json::from_slice(&body).unwrap();
+        let labels: Vec<_> = labels.labels.iter().map(|l| (l.label, l.counter)).collect();
+        assert_eq!(labels, vec![(score, 9), (visits, 3)]);
+
+        let uri = format!("/api/v1/counter/{}/labels/{}", owner, score);
+        let response = app.clone().oneshot(get(uri)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let counter: LabeledCounterResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(counter.counter, 9);
 
-        let exec = executor.read().unwrap();
-        assert_eq!(exec.state().get_counter(&addr), 10);
+        // Unused labels read as zero
+        let uri = format!("/api/v1/counter/{}/labels/{}", owner, B256::repeat_byte(3));
+        let response = app.oneshot(get(uri)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let counter: LabeledCounterResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(counter.counter, 0);
     }
 }
@@ -0,0 +1,353 @@
+//! JSON-RPC audit log
+//!
+//! Optional middleware that appends one JSON line per audited call to a
+//! dedicated file: method, request id, params, outcome and latency. Raw
+//! transactions additionally record their hash and recovered sender, so the
+//! origin of every submitted transaction can be traced even with params
+//! redacted. Calls can be sampled per method to bound the log's growth.
+
+use alloy_consensus::transaction::SignerRecoverable;
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_rlp::Decodable;
+use jsonrpsee::{server::middleware::rpc::RpcServiceT, types::Request, MethodResponse};
+use reth_ethereum_primitives::TransactionSigned;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    future::Future,
+    io::Write,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Methods audited by default: everything that changes node or chain state
+pub const DEFAULT_AUDIT_METHODS: &[&str] =
+    &["eth_sendRawTransaction", "admin_", "dex_resetAccount"];
+
+/// Placeholder for redacted params and results
+const REDACTED: &str = "[redacted]";
+
+/// Audit log settings
+#[derive(Debug, Clone)]
+pub struct RpcAuditConfig {
+    /// File the JSON lines are appended to
+    pub path: PathBuf,
+    /// Audited methods; an entry ending in `_` matches the whole namespace
+    pub methods: Vec<String>,
+    /// Fraction of calls of each method that are logged, in `0.0..=1.0`
+    pub sample_rate: f64,
+    /// Leave params and results out of the log
+    pub redact: bool,
+}
+
+impl RpcAuditConfig {
+    /// Audit the default methods into `path`, logging every call
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            methods: DEFAULT_AUDIT_METHODS.iter().map(|m| m.to_string()).collect(),
+            sample_rate: 1.0,
+            redact: false,
+        }
+    }
+
+    /// Whether calls of `method` are audited
+    pub fn matches(&self, method: &str) -> bool {
+        self.methods.iter().any(|m| {
+            if m.ends_with('_') {
+                method.starts_with(m.as_str())
+            } else {
+                method == m
+            }
+        })
+    }
+}
+
+/// One audited call
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// When the call arrived (unix milliseconds)
+    pub timestamp_ms: u64,
+    /// JSON-RPC method
+    pub method: String,
+    /// Request id
+    pub id: Value,
+    /// Request params, or `"[redacted]"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    /// Hash of a submitted raw transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<B256>,
+    /// Recovered sender of a submitted raw transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sender: Option<Address>,
+    /// Whether the call succeeded
+    pub success: bool,
+    /// JSON-RPC error code of a failed call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<i32>,
+    /// Result or error object, or `"[redacted]"` for results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    /// Time spent serving the call (microseconds)
+    pub duration_us: u64,
+}
+
+impl AuditRecord {
+    /// Record a call before it is served
+    fn new(method: &str, id: Value, params: Option<&str>, redact: bool) -> Self {
+        let params = params.map(|raw| serde_json::from_str(raw).unwrap_or(Value::Null));
+        let (tx_hash, sender) = match (method, &params) {
+            ("eth_sendRawTransaction", Some(params)) => raw_transaction_origin(params),
+            _ => (None, None),
+        };
+
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            method: method.to_string(),
+            id,
+            params: if redact { params.map(|_| Value::from(REDACTED)) } else { params },
+            tx_hash,
+            sender,
+            success: false,
+            error_code: None,
+            response: None,
+            duration_us: 0,
+        }
+    }
+
+    /// Fill in the outcome from the serialized JSON-RPC response
+    fn finish(&mut self, response: &str, error_code: Option<i32>, redact: bool, started: Instant) {
+        let mut response: Value = serde_json::from_str(response).unwrap_or(Value::Null);
+        self.success = error_code.is_none();
+        self.error_code = error_code;
+        // Errors are kept even when redacting, they only describe the failure
+        self.response = match response.get_mut("error") {
+            Some(error) => Some(error.take()),
+            None if redact => Some(Value::from(REDACTED)),
+            None => response.get_mut("result").map(Value::take),
+        };
+        self.duration_us = started.elapsed().as_micros() as u64;
+    }
+}
+
+/// Hash and sender of the transaction in `eth_sendRawTransaction` params
+fn raw_transaction_origin(params: &Value) -> (Option<B256>, Option<Address>) {
+    let Ok((data,)) = serde_json::from_value::<(Bytes,)>(params.clone()) else {
+        return (None, None);
+    };
+    let Ok(tx) = TransactionSigned::decode(&mut data.as_ref()) else {
+        return (None, None);
+    };
+    (Some(*tx.tx_hash()), tx.recover_signer().ok())
+}
+
+/// Open audit log shared by all RPC connections
+#[derive(Debug, Clone)]
+pub struct RpcAuditLog {
+    inner: Arc<AuditLogInner>,
+}
+
+#[derive(Debug)]
+struct AuditLogInner {
+    config: RpcAuditConfig,
+    file: Mutex<File>,
+    /// Calls seen per audited method, for sampling
+    calls: Mutex<HashMap<String, u64>>,
+}
+
+impl RpcAuditLog {
+    /// Open (or create) the log file for appending
+    pub fn open(config: RpcAuditConfig) -> eyre::Result<Self> {
+        if !(0.0..=1.0).contains(&config.sample_rate) {
+            return Err(eyre::eyre!(
+                "Audit sample rate {} is not between 0 and 1",
+                config.sample_rate
+            ));
+        }
+        if let Some(dir) = config.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&config.path).map_err(
+            |e| eyre::eyre!("Failed to open audit log {}: {}", config.path.display(), e),
+        )?;
+
+        Ok(Self {
+            inner: Arc::new(AuditLogInner {
+                config,
+                file: Mutex::new(file),
+                calls: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+
+    /// Log file path
+    pub fn path(&self) -> &Path {
+        &self.inner.config.path
+    }
+
+    /// Whether this call of `method` should be logged
+    ///
+    /// Sampling is deterministic: at rate `r` the `n`th call is logged when
+    /// `floor(n * r)` advances, so e.g. every fourth call is logged at 0.25.
+    fn sample(&self, method: &str) -> bool {
+        let config = &self.inner.config;
+        if !config.matches(method) {
+            return false;
+        }
+        let mut calls = self.inner.calls.lock().unwrap();
+        let n = calls.entry(method.to_string()).or_default();
+        *n += 1;
+        let rate = config.sample_rate;
+        ((*n as f64) * rate).floor() > ((*n - 1) as f64 * rate).floor()
+    }
+
+    /// Append `record` as one JSON line
+    fn write(&self, record: &AuditRecord) {
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Failed to serialize audit record: {}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(e) = self.inner.file.lock().unwrap().write_all(&line) {
+            tracing::warn!("Failed to write audit log {}: {}", self.path().display(), e);
+        }
+    }
+
+    /// Wrap an RPC service, auditing its calls if `log` is set
+    pub fn layer<S>(log: Option<Self>, service: S) -> AuditService<S> {
+        AuditService { service, log }
+    }
+}
+
+/// RPC middleware that writes sampled calls to the audit log
+#[derive(Debug, Clone)]
+pub struct AuditService<S> {
+    service: S,
+    log: Option<RpcAuditLog>,
+}
+
+impl<'a, S> RpcServiceT<'a> for AuditService<S>
+where
+    S: RpcServiceT<'a> + Clone + Send + Sync + 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let service = self.service.clone();
+        let Some(log) = self.log.clone().filter(|log| log.sample(request.method_name())) else {
+            return Box::pin(async move { service.call(request).await });
+        };
+
+        let redact = log.inner.config.redact;
+        let id = serde_json::to_value(&request.id).unwrap_or(Value::Null);
+        let mut record =
+            AuditRecord::new(request.method_name(), id, request.params().as_str(), redact);
+        let started = Instant::now();
+
+        Box::pin(async move {
+            let response = service.call(request).await;
+            record.finish(response.as_result(), response.as_error_code(), redact, started);
+            log.write(&record);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::TxLegacy;
+    use alloy_primitives::{hex, Signature, TxKind};
+
+    fn open_log(dir: &Path, sample_rate: f64, redact: bool) -> RpcAuditLog {
+        let mut config = RpcAuditConfig::new(dir.join("audit").join("rpc.jsonl"));
+        config.sample_rate = sample_rate;
+        config.redact = redact;
+        RpcAuditLog::open(config).unwrap()
+    }
+
+    #[test]
+    fn test_method_matching() {
+        let config = RpcAuditConfig::new("audit.jsonl");
+        assert!(config.matches("eth_sendRawTransaction"));
+        assert!(config.matches("admin_dbStats"));
+        assert!(config.matches("dex_resetAccount"));
+        assert!(!config.matches("eth_call"));
+        assert!(!config.matches("dex_waitForTransaction"));
+    }
+
+    #[test]
+    fn test_sampling_is_per_method() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open_log(dir.path(), 0.25, false);
+
+        let logged = (0..100).filter(|_| log.sample("eth_sendRawTransaction")).count();
+        assert_eq!(logged, 25);
+        // Other methods keep their own count
+        assert!(!log.sample("admin_dbStats"));
+        assert!(!log.sample("eth_blockNumber"));
+
+        let none = open_log(dir.path(), 0.0, false);
+        assert!(!(0..10).any(|_| none.sample("admin_dbStats")));
+        assert!(RpcAuditLog::open(RpcAuditConfig {
+            sample_rate: 1.5,
+            ..RpcAuditConfig::new(dir.path().join("x.jsonl"))
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_records_transaction_origin() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = open_log(dir.path(), 1.0, true);
+
+        let tx = TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(Address::repeat_byte(0x11)),
+                gas_limit: 21000,
+                chain_id: Some(1),
+                ..Default::default()
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+        let params = format!("[\"0x{}\"]", hex::encode(alloy_rlp::encode(&tx)));
+        let mut record =
+            AuditRecord::new("eth_sendRawTransaction", Value::from(1), Some(&params), true);
+        record.finish(r#"{"jsonrpc":"2.0","id":1,"result":"0x01"}"#, None, true, Instant::now());
+        log.write(&record);
+
+        let mut failed = AuditRecord::new("admin_dbStats", Value::from(2), None, true);
+        let error = r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32000,"message":"no"}}"#;
+        failed.finish(error, Some(-32000), true, Instant::now());
+        log.write(&failed);
+
+        let contents = std::fs::read_to_string(log.path()).unwrap();
+        let lines: Vec<Value> =
+            contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["params"], REDACTED);
+        assert_eq!(lines[0]["response"], REDACTED);
+        assert_eq!(lines[0]["txHash"], serde_json::to_value(tx.tx_hash()).unwrap());
+        assert_eq!(
+            lines[0]["sender"],
+            serde_json::to_value(tx.recover_signer().unwrap()).unwrap()
+        );
+        assert_eq!(lines[0]["success"], true);
+        assert_eq!(lines[1]["errorCode"], -32000);
+        assert_eq!(lines[1]["response"]["message"], "no");
+        assert!(lines[1].get("params").is_none());
+    }
+}
@@ -0,0 +1,302 @@
+//! Contract calls for `eth_call` and `eth_estimateGas`
+//!
+//! Calls run in revm against the state store, in the context of the latest
+//! block. Nothing they change is kept, and gas is free, so a caller only needs
+//! the balance for the value it sends. Block execution moves value but does
+//! not run contract code; this only answers reads of deployed contracts.
+//!
+//! Gas estimation runs a call several times. The runs share one executor, so
+//! state is loaded from the store once, and most estimates take two runs.
+
+use crate::mempool::{decode_revert_reason, CallOutcome, SimulationError};
+use alloy_primitives::{keccak256, Address, Bytes, TxKind, B256, U256};
+use dex_storage::StateReader;
+use revm::{
+    context::TxEnv,
+    context_interface::result::ExecutionResult,
+    database::CacheDB,
+    primitives::KECCAK_EMPTY,
+    state::{AccountInfo, Bytecode},
+    Context, DatabaseRef, ExecuteEvm, MainBuilder, MainContext,
+};
+use std::convert::Infallible;
+
+/// Gas limit of a call that sets none, and the most a call may set
+pub const CALL_GAS_CAP: u64 = 30_000_000;
+
+/// Gas a `CALL` with value adds for the callee on top of what it passes
+const CALL_STIPEND: u64 = 2_300;
+
+/// A gas estimate may exceed the lowest working limit by 1/64 of itself
+const ESTIMATE_TOLERANCE_DIVISOR: u64 = 64;
+
+/// A call to run: sender, target (`None` creates a contract), value and input
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EvmCall {
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+}
+
+/// The state store as a revm database
+///
+/// Block hashes are not part of the state, so `BLOCKHASH` reads zero.
+pub struct StoreDatabase<'a>(pub &'a dyn StateReader);
+
+impl DatabaseRef for StoreDatabase<'_> {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        Ok(self.0.get_account(&address).map(|account| {
+            let (code_hash, code) = match account.code.filter(|code| !code.is_empty()) {
+                Some(code) => (keccak256(&code), Bytecode::new_raw(code)),
+                None => (KECCAK_EMPTY, Bytecode::default()),
+            };
+            AccountInfo::new(account.balance, account.nonce, code_hash, code)
+        }))
+    }
+
+    fn code_by_hash_ref(&self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is loaded with its account
+        Ok(Bytecode::default())
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self.0.get_storage(&address, index))
+    }
+
+    fn block_hash_ref(&self, _number: u64) -> Result<B256, Self::Error> {
+        Ok(B256::ZERO)
+    }
+}
+
+/// Runs calls in revm against the state store
+///
+/// Accounts and slots one call reads stay cached for the next.
+pub struct EvmCallExecutor<'a> {
+    chain_id: u64,
+    block_number: u64,
+    timestamp: u64,
+    db: CacheDB<StoreDatabase<'a>>,
+}
+
+impl<'a> EvmCallExecutor<'a> {
+    /// Create an executor reading `state`
+    pub fn new(chain_id: u64, state: &'a dyn StateReader) -> Self {
+        Self { chain_id, block_number: 0, timestamp: 0, db: CacheDB::new(StoreDatabase(state)) }
+    }
+
+    /// Run calls in the block with this number and timestamp
+    pub fn with_block(mut self, number: u64, timestamp: u64) -> Self {
+        self.block_number = number;
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Run a call with `gas_limit`, discarding what it changes
+    ///
+    /// A revert fails with its revert data, and a halt such as running out of
+    /// gas with [`SimulationError::Halted`].
+    pub fn execute_call(
+        &mut self,
+        call: &EvmCall,
+        gas_limit: u64,
+    ) -> Result<CallOutcome, SimulationError> {
+        let tx = TxEnv {
+            caller: call.from,
+            gas_limit,
+            gas_price: 0,
+            kind: call.to.map_or(TxKind::Create, TxKind::Call),
+            value: call.value,
+            data: call.input.clone(),
+            chain_id: Some(self.chain_id),
+            ..Default::default()
+        };
+        let (chain_id, number, timestamp) = (self.chain_id, self.block_number, self.timestamp);
+        let mut evm = Context::mainnet()
+            .with_db(&mut self.db)
+            .modify_cfg_chained(|cfg| {
+                cfg.chain_id = chain_id;
+                // A call is not a transaction, so it does not need the sender's next nonce
+                cfg.disable_nonce_check = true;
+            })
+            .modify_block_chained(|block| {
+                block.number = number.into();
+                block.timestamp = timestamp.into();
+            })
+            .build_mainnet();
+        let result = evm
+            .transact(tx)
+            .map_err(|e| SimulationError::Internal(e.to_string()))?
+            .result;
+
+        match result {
+            ExecutionResult::Success { gas_used, output, .. } => Ok(CallOutcome {
+                success: true,
+                return_data: output.into_data(),
+                gas_used,
+                error: None,
+            }),
+            ExecutionResult::Revert { output, .. } => Err(SimulationError::Reverted {
+                reason: decode_revert_reason(&output).unwrap_or_else(|| output.to_string()),
+                data: output,
+            }),
+            ExecutionResult::Halt { reason, .. } => {
+                Err(SimulationError::Halted(format!("{:?}", reason)))
+            }
+        }
+    }
+
+    /// Lowest gas limit up to `cap` the call succeeds with
+    ///
+    /// A run at `cap` gives the gas used. Most calls succeed with that plus a
+    /// margin for refunds, stipends and the 1/64 of gas kept from subcalls, which
+    /// ends the estimate. Otherwise the limit is binary searched between the two.
+    pub fn estimate_gas(&mut self, call: &EvmCall, cap: u64) -> Result<u64, SimulationError> {
+        let used = self.execute_call(call, cap)?.gas_used;
+        // A call to an account without code runs nothing, so it needs exactly what it used
+        if call.to.is_some_and(|to| !self.has_code(to)) {
+            return Ok(used);
+        }
+        let optimistic = (used + CALL_STIPEND) * 64 / 63;
+        let (mut low, mut high) = (used.saturating_sub(1), cap);
+        if optimistic < cap {
+            if self.succeeds(call, optimistic)? {
+                return Ok(optimistic);
+            }
+            low = optimistic;
+        }
+
+        while high - low > (high / ESTIMATE_TOLERANCE_DIVISOR).max(1) {
+            let mid = low + (high - low) / 2;
+            if self.succeeds(call, mid)? {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        Ok(high)
+    }
+
+    /// Whether an account has code
+    fn has_code(&self, address: Address) -> bool {
+        let account = self.db.basic_ref(address).ok().flatten();
+        account.is_some_and(|account| !account.is_empty_code_hash())
+    }
+
+    /// Whether the call succeeds with `gas_limit`
+    fn succeeds(&mut self, call: &EvmCall, gas_limit: u64) -> Result<bool, SimulationError> {
+        match self.execute_call(call, gas_limit) {
+            Ok(_) => Ok(true),
+            Err(SimulationError::Internal(e)) => Err(SimulationError::Internal(e)),
+            Err(_) => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::mempool::encode_revert_reason;
+    use dex_storage::{DualvmStorage, StateWriter};
+
+    /// Runtime code returning storage slot 0
+    pub(crate) const RETURN_SLOT_0: [u8; 11] =
+        [0x60, 0x00, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+
+    /// Runtime code reverting with `Error(reason)`
+    fn revert_with(reason: &str) -> Vec<u8> {
+        let data = encode_revert_reason(reason);
+        let mut code = Vec::new();
+        for (offset, word) in data.chunks(32).enumerate() {
+            // PUSH32 word, PUSH1 offset, MSTORE
+            code.push(0x7f);
+            code.extend_from_slice(word);
+            code.resize(code.len() + 32 - word.len(), 0);
+            code.extend_from_slice(&[0x60, (offset * 32) as u8, 0x52]);
+        }
+        // PUSH1 len, PUSH1 0, REVERT
+        code.extend_from_slice(&[0x60, data.len() as u8, 0x60, 0x00, 0xfd]);
+        code
+    }
+
+    #[test]
+    fn test_call_reads_contract_storage() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let contract = Address::repeat_byte(0xcc);
+        storage.state.set_code(contract, RETURN_SLOT_0.to_vec().into()).unwrap();
+        storage.state.set_storage(contract, U256::ZERO, U256::from(42)).unwrap();
+
+        let mut executor = EvmCallExecutor::new(1, storage.state.as_ref());
+        let call = EvmCall { to: Some(contract), ..Default::default() };
+        let outcome = executor.execute_call(&call, CALL_GAS_CAP).unwrap();
+        assert!(outcome.success);
+        assert_eq!(outcome.return_data, Bytes::from(U256::from(42).to_be_bytes::<32>().to_vec()));
+        assert!(outcome.gas_used > 21_000);
+
+        // Accounts without code return nothing
+        let call = EvmCall { to: Some(Address::repeat_byte(0xdd)), ..Default::default() };
+        let outcome = executor.execute_call(&call, CALL_GAS_CAP).unwrap();
+        assert_eq!((outcome.return_data, outcome.gas_used), (Bytes::new(), 21_000));
+    }
+
+    #[test]
+    fn test_call_failures() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let contract = Address::repeat_byte(0xcc);
+        storage.state.set_code(contract, revert_with("nope").into()).unwrap();
+        let mut executor = EvmCallExecutor::new(1, storage.state.as_ref());
+
+        let call = EvmCall { to: Some(contract), ..Default::default() };
+        match executor.execute_call(&call, CALL_GAS_CAP) {
+            Err(SimulationError::Reverted { reason, data }) => {
+                assert_eq!(reason, "nope");
+                assert_eq!(data, encode_revert_reason("nope"));
+            }
+            other => panic!("unexpected outcome {:?}", other),
+        }
+
+        // Not enough gas to finish
+        let err = executor.execute_call(&call, 21_010).unwrap_err();
+        assert!(matches!(err, SimulationError::Halted(_)));
+        assert!(executor.estimate_gas(&call, CALL_GAS_CAP).is_err());
+    }
+
+    #[test]
+    fn test_estimate_gas() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let contract = Address::repeat_byte(0xcc);
+        storage.state.set_code(contract, RETURN_SLOT_0.to_vec().into()).unwrap();
+        let mut executor = EvmCallExecutor::new(1, storage.state.as_ref());
+
+        // The gas used plus the margin is enough
+        let call = EvmCall { to: Some(contract), ..Default::default() };
+        let used = executor.execute_call(&call, CALL_GAS_CAP).unwrap().gas_used;
+        let estimate = executor.estimate_gas(&call, CALL_GAS_CAP).unwrap();
+        assert_eq!(estimate, (used + CALL_STIPEND) * 64 / 63);
+
+        // Calls to accounts without code need exactly what they use
+        let transfer = EvmCall { to: Some(Address::repeat_byte(0xee)), ..Default::default() };
+        assert_eq!(executor.estimate_gas(&transfer, CALL_GAS_CAP).unwrap(), 21_000);
+
+        // Runs share the state they loaded
+        storage.state.set_storage(contract, U256::ZERO, U256::from(9)).unwrap();
+        let outcome = executor.execute_call(&call, CALL_GAS_CAP).unwrap();
+        assert_eq!(U256::from_be_slice(&outcome.return_data), U256::ZERO);
+
+        // Reverts unless a million gas is left: far more than it uses, so the limit is searched
+        let needy = Address::repeat_byte(0xdd);
+        let code = [
+            0x62, 0x0f, 0x42, 0x40, 0x5a, 0x10, 0x60, 0x0a, 0x57, 0x00, 0x5b, 0x60, 0x00, 0x60,
+            0x00, 0xfd,
+        ];
+        storage.state.set_code(needy, code.to_vec().into()).unwrap();
+        let call = EvmCall { to: Some(needy), ..Default::default() };
+        let needed = 21_000 + 3 + 2 + 1_000_000;
+        let estimate = executor.estimate_gas(&call, CALL_GAS_CAP).unwrap();
+        assert!(estimate >= needed && estimate <= needed + needed / 63 + 1);
+        assert!(executor.execute_call(&call, estimate).is_ok());
+        assert!(executor.execute_call(&call, needed - 1).is_err());
+    }
+}
@@ -1,23 +1,79 @@
 //! EVM JSON-RPC service
 
-use alloy_consensus::{transaction::SignerRecoverable, Transaction};
-use alloy_primitives::{Address, Bytes, B256, B64, U256, U64};
+use alloy_consensus::{transaction::SignerRecoverable, Transaction, Typed2718};
+use alloy_primitives::{keccak256, Address, Bytes, B256, B64, U256, U64};
 use alloy_rlp::Decodable;
-use dex_storage::{BlockStore, StateStore, StoredBlock};
+use crate::{
+    audit::RpcAuditLog,
+    evm_call::{EvmCall, EvmCallExecutor, CALL_GAS_CAP},
+    mempool::{
+        call_access_set, simulate_call, simulate_transaction, CallOutcome, MempoolConfig,
+        PendingPool, PoolDrops, Quarantine, SimulationError, StoreEvmState,
+    },
+    priority::{RpcPriorityConfig, RpcPriorityLimits},
+    status::{NodeStatus, SyncStatus},
+    subscriptions::{
+        counter_updates, ChainEvent, ChainEvents, CounterUpdate, LogFilter, SubscriptionKind,
+        MAX_COUNTER_CATCH_UP_BLOCKS, MAX_COUNTER_SUBSCRIPTION_ADDRESSES,
+    },
+    sync_gate::{SyncGate, SyncGateMode, SyncProgress, Syncing, SYNCING_HEADER},
+};
+use axum::http::{HeaderName, HeaderValue};
+use dex_dexvm::{
+    sends_value_to_dexvm, DexVmExecutor, DexVmPool, PrecompileExecutor, DEXVM_VALUE_ERROR,
+};
+use dex_primitives::{system_handler, SystemHandler};
+use dex_storage::{
+    BackupReport, Backups, BlockReader, DatabaseStats, DualvmStorage, StateReader, StateWriter,
+    StorageGrowth, StorageInfo, StoredBlock, StoredBlockStats, StoredSiblingBlock, UnwoundBlock,
+};
 use jsonrpsee::{
-    core::RpcResult,
+    core::{RpcResult, SubscriptionResult},
+    types::ErrorObjectOwned,
     proc_macros::rpc,
-    server::{ServerBuilder, ServerHandle},
+    server::{middleware::rpc::RpcServiceBuilder, HttpResponse, ServerBuilder, ServerHandle},
+    PendingSubscriptionSink, SubscriptionMessage, SubscriptionSink,
+};
+use tower_http::{
+    cors::{Any, CorsLayer},
+    set_header::SetResponseHeaderLayer,
 };
-use tower_http::cors::{Any, CorsLayer};
 use reth_ethereum_primitives::TransactionSigned;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     net::SocketAddr,
     sync::{Arc, RwLock},
+    time::{Duration, Instant},
 };
-use tokio::sync::mpsc;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc, Notify,
+};
+
+/// Version of the JSON-RPC API served by this node
+///
+/// The minor version goes up when methods or response fields are added, the
+/// major version when any are removed or change meaning.
+pub const RPC_API_VERSION: &str = "1.4.0";
+
+/// JSON-RPC namespaces served by [`rpc_module`]
+pub const RPC_NAMESPACES: &[&str] = &["eth", "web3", "net", "admin", "dex"];
+
+/// `web3_clientVersion` of this node
+pub const CLIENT_VERSION: &str = "DualVM/v0.1.0";
+
+/// Default `dex_waitForTransaction` timeout
+pub const DEFAULT_WAIT_TIMEOUT_MS: u64 = 30_000;
+
+/// Maximum `dex_waitForTransaction` timeout
+pub const MAX_WAIT_TIMEOUT_MS: u64 = 120_000;
+
+/// Ethereum wire protocol version reported by `eth_protocolVersion` (eth/68)
+pub const ETH_PROTOCOL_VERSION: u64 = 68;
+
+/// Maximum number of blocks in one `dex_getBlockStats` or `admin_siblingBlocks` range
+pub const MAX_BLOCK_STATS_RANGE: u64 = 1_024;
 
 /// Transaction request
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +88,23 @@ pub struct TransactionRequest {
     pub nonce: Option<U64>,
 }
 
+impl TransactionRequest {
+    /// The call this request makes
+    fn evm_call(&self) -> EvmCall {
+        EvmCall {
+            from: self.from.unwrap_or_default(),
+            to: self.to,
+            value: self.value.unwrap_or_default(),
+            input: self.data.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Requested gas limit, at most [`CALL_GAS_CAP`]
+    fn gas_limit(&self) -> u64 {
+        self.gas.map_or(CALL_GAS_CAP, |gas| gas.to::<u64>().min(CALL_GAS_CAP))
+    }
+}
+
 /// Transaction receipt
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -44,6 +117,7 @@ pub struct TransactionReceipt {
     pub to: Option<Address>,
     pub cumulative_gas_used: U64,
     pub gas_used: U64,
+    pub effective_gas_price: U256,
     pub contract_address: Option<Address>,
     pub logs: Vec<Log>,
     pub logs_bloom: Bytes,
@@ -52,6 +126,71 @@ pub struct TransactionReceipt {
     pub tx_type: U64,
 }
 
+/// Transaction object returned by `eth_getTransactionByHash`
+///
+/// The block fields are null while the transaction is pending.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInfo {
+    pub hash: B256,
+    pub nonce: U64,
+    pub block_hash: Option<B256>,
+    pub block_number: Option<U64>,
+    pub transaction_index: Option<U64>,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas: U64,
+    pub gas_price: U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<U256>,
+    pub input: Bytes,
+    #[serde(rename = "type")]
+    pub tx_type: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<U64>,
+    pub v: U64,
+    pub r: U256,
+    pub s: U256,
+}
+
+impl TransactionInfo {
+    /// Describe `tx`, mined at `(block hash, block number, index)` if given
+    pub fn new(tx: &TransactionSigned, from: Address, mined: Option<(B256, u64, u64)>) -> Self {
+        let signature = tx.signature();
+        let parity = signature.v() as u64;
+        // Legacy transactions fold the chain id into v (EIP-155)
+        let v = match (tx.is_legacy(), tx.chain_id()) {
+            (true, Some(chain_id)) => chain_id * 2 + 35 + parity,
+            (true, None) => 27 + parity,
+            (false, _) => parity,
+        };
+        let dynamic_fee = tx.is_dynamic_fee();
+        Self {
+            hash: *tx.tx_hash(),
+            nonce: U64::from(tx.nonce()),
+            block_hash: mined.map(|(hash, _, _)| hash),
+            block_number: mined.map(|(_, number, _)| U64::from(number)),
+            transaction_index: mined.map(|(_, _, index)| U64::from(index)),
+            from,
+            to: tx.to(),
+            value: tx.value(),
+            gas: U64::from(tx.gas_limit()),
+            gas_price: U256::from(tx.effective_gas_price(Some(BASE_FEE_PER_GAS))),
+            max_fee_per_gas: dynamic_fee.then(|| U256::from(tx.max_fee_per_gas())),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas().map(U256::from),
+            input: tx.input().clone(),
+            tx_type: U64::from(tx.ty()),
+            chain_id: tx.chain_id().map(U64::from),
+            v: U64::from(v),
+            r: signature.r(),
+            s: signature.s(),
+        }
+    }
+}
+
 /// Log entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -64,6 +203,9 @@ pub struct Log {
     pub transaction_hash: B256,
     pub transaction_index: U64,
     pub log_index: U64,
+    /// Set on logs of a block unwound in a reorg
+    #[serde(default)]
+    pub removed: bool,
 }
 
 /// Block info - compatible with Ethereum RPC format
@@ -93,6 +235,189 @@ pub struct BlockInfo {
     pub base_fee_per_gas: Option<U256>,
 }
 
+/// `eth_createAccessList` response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListResult {
+    pub access_list: Vec<AccessListItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub gas_used: U64,
+}
+
+/// One account of an access list with the storage slots it touches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<B256>,
+}
+
+/// Per-block execution summary returned by `dex_getBlockStats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStats {
+    pub number: U64,
+    pub tx_count: U64,
+    pub evm_tx_count: U64,
+    pub cross_vm_tx_count: U64,
+    pub dexvm_tx_count: U64,
+    pub native_dexvm_tx_count: U64,
+    pub failed_tx_count: U64,
+    pub dexvm_ops_count: U64,
+    pub gas_used: U64,
+    pub execution_micros: U64,
+}
+
+impl BlockStats {
+    fn new(number: u64, stats: StoredBlockStats) -> Self {
+        Self {
+            number: U64::from(number),
+            tx_count: U64::from(stats.tx_count()),
+            evm_tx_count: U64::from(stats.evm_tx_count),
+            cross_vm_tx_count: U64::from(stats.cross_vm_tx_count),
+            dexvm_tx_count: U64::from(stats.dexvm_tx_count),
+            native_dexvm_tx_count: U64::from(stats.native_dexvm_tx_count),
+            failed_tx_count: U64::from(stats.failed_tx_count),
+            dexvm_ops_count: U64::from(stats.dexvm_ops_count),
+            gas_used: U64::from(stats.gas_used),
+            execution_micros: U64::from(stats.execution_micros),
+        }
+    }
+}
+
+/// Account to reset with `dex_resetAccount`; omitted fields reset to zero
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountReset {
+    pub address: Address,
+    #[serde(default)]
+    pub balance: Option<U256>,
+    #[serde(default)]
+    pub nonce: Option<U64>,
+    #[serde(default)]
+    pub counter: Option<U64>,
+}
+
+/// Account state after `dex_resetAccount`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountResetResult {
+    pub address: Address,
+    pub balance: U256,
+    pub nonce: U64,
+    pub counter: U64,
+    /// Pending EVM and native DexVM transactions from the account that were dropped
+    pub removed_pending: U64,
+}
+
+/// State roots of a block returned by `dex_getBlockRoots`
+///
+/// Indexers check cross-VM state against the component roots; the combined
+/// root is the `stateRoot` of the block header `hash` is computed from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRoots {
+    pub number: U64,
+    /// Hash of the block's Ethereum header
+    pub hash: B256,
+    pub evm_state_root: B256,
+    pub dexvm_state_root: B256,
+    pub combined_state_root: B256,
+}
+
+impl From<&StoredBlock> for BlockRoots {
+    fn from(block: &StoredBlock) -> Self {
+        Self {
+            number: U64::from(block.number),
+            hash: block.hash,
+            evm_state_root: block.evm_state_root,
+            dexvm_state_root: block.dexvm_state_root,
+            combined_state_root: block.combined_state_root,
+        }
+    }
+}
+
+/// Node features that depend on how it was started, reported by `dex_clientCapabilities`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientFeatures {
+    /// Receipts of stored blocks survive a restart
+    pub receipts_persisted: bool,
+    /// A JSON trace is written for every produced block
+    pub traces_available: bool,
+    /// Blocks below the head whose state can be queried; 0 serves the latest state only
+    pub archive_depth: U64,
+    /// Dev-only methods such as `dex_resetAccount` are served
+    pub dev_methods: bool,
+    /// Transactions are refused
+    pub read_only: bool,
+}
+
+/// `dex_clientCapabilities` response
+///
+/// Lets clients adapt to the node instead of probing it with trial calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientCapabilities {
+    /// [`RPC_API_VERSION`]
+    pub api_version: String,
+    pub client_version: String,
+    pub namespaces: Vec<String>,
+    pub features: ClientFeatures,
+}
+
+/// Competing block returned by `admin_siblingBlocks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiblingBlock {
+    pub number: U64,
+    pub hash: B256,
+    /// Hash of the local block at the same height
+    pub canonical_hash: Option<B256>,
+    /// Signer recovered from the sibling's header, if it was fetched
+    pub signer: Option<Address>,
+    /// Peer the sibling was first seen from
+    pub peer: String,
+    /// When the sibling was first seen (unix milliseconds)
+    pub received_at: U64,
+}
+
+impl SiblingBlock {
+    fn new(number: u64, canonical_hash: Option<B256>, sibling: StoredSiblingBlock) -> Self {
+        Self {
+            number: U64::from(number),
+            hash: sibling.hash,
+            canonical_hash,
+            signer: sibling.signer,
+            peer: format!("{:x}", sibling.peer),
+            received_at: U64::from(sibling.received_at_ms),
+        }
+    }
+}
+
+/// Check a `from..=to` block range against [`MAX_BLOCK_STATS_RANGE`]
+fn check_block_range(from: u64, to: u64) -> RpcResult<()> {
+    if from > to {
+        return Err(ErrorObjectOwned::owned(
+            -32000,
+            format!("invalid block range {}..={}", from, to),
+            None::<()>,
+        ));
+    }
+    if to - from >= MAX_BLOCK_STATS_RANGE {
+        return Err(ErrorObjectOwned::owned(
+            -32000,
+            format!("block range exceeds {} blocks", MAX_BLOCK_STATS_RANGE),
+            None::<()>,
+        ));
+    }
+    Ok(())
+}
+
+/// Base fee per gas reported for every block (1 gwei)
+pub const BASE_FEE_PER_GAS: u64 = 1_000_000_000;
+
 /// Empty uncles hash (keccak256 of RLP empty list)
 const EMPTY_OMMER_ROOT: B256 = B256::new([
     0x1d, 0xcc, 0x4d, 0xe8, 0xde, 0xc7, 0x5d, 0x7a, 0xab, 0x85, 0xb5, 0x67, 0xb6, 0xcc, 0xd4, 0x1a,
@@ -138,11 +463,113 @@ impl From<StoredBlock> for BlockInfo {
             transactions: block.transaction_hashes,
             uncles: vec![],
             nonce: B64::ZERO,
-            base_fee_per_gas: Some(U256::from(1_000_000_000u64)), // 1 gwei
+            base_fee_per_gas: Some(U256::from(BASE_FEE_PER_GAS)),
         }
     }
 }
 
+/// Build RPC receipts for a block from its transactions and execution receipts
+///
+/// `receipts` must be in block order with block-cumulative gas. Per-transaction gas is
+/// derived from consecutive cumulative values, and log indexes run across the whole block.
+pub fn build_block_receipts(
+    block_hash: B256,
+    block_number: u64,
+    base_fee_per_gas: u64,
+    transactions: &[TransactionSigned],
+    receipts: &[alloy_consensus::Receipt],
+) -> Vec<TransactionReceipt> {
+    let mut previous_cumulative = 0u64;
+    let mut log_index = 0u64;
+
+    transactions
+        .iter()
+        .zip(receipts.iter())
+        .enumerate()
+        .map(|(idx, (tx, receipt))| {
+            let position = ReceiptPosition {
+                block_hash,
+                block_number,
+                transaction_index: idx as u64,
+                gas_used: receipt.cumulative_gas_used.saturating_sub(previous_cumulative),
+                first_log_index: log_index,
+            };
+            previous_cumulative = receipt.cumulative_gas_used;
+            log_index += receipt.logs.len() as u64;
+            build_receipt(position, base_fee_per_gas, tx, receipt)
+        })
+        .collect()
+}
+
+/// Where a receipt sits in its block
+struct ReceiptPosition {
+    block_hash: B256,
+    block_number: u64,
+    transaction_index: u64,
+    /// Gas used by this transaction alone
+    gas_used: u64,
+    /// Block-wide index of the receipt's first log
+    first_log_index: u64,
+}
+
+/// Build the RPC receipt of one transaction
+fn build_receipt(
+    position: ReceiptPosition,
+    base_fee_per_gas: u64,
+    tx: &TransactionSigned,
+    receipt: &alloy_consensus::Receipt,
+) -> TransactionReceipt {
+    let ReceiptPosition { block_hash, block_number, transaction_index, .. } = position;
+    let tx_hash = *tx.tx_hash();
+    let from = tx.recover_signer().unwrap_or_default();
+    let to = tx.to();
+    let success = receipt.status.coerce_status();
+
+    // Contract creation: address = keccak256(sender || nonce)[12:]
+    let contract_address = if to.is_none() && success {
+        let mut data = Vec::new();
+        data.extend_from_slice(from.as_slice());
+        data.extend_from_slice(&tx.nonce().to_be_bytes());
+        Some(Address::from_slice(&keccak256(&data)[12..]))
+    } else {
+        None
+    };
+
+    let logs = receipt
+        .logs
+        .iter()
+        .zip(position.first_log_index..)
+        .map(|(log, log_index)| Log {
+            address: log.address,
+            topics: log.data.topics().to_vec(),
+            data: log.data.data.clone(),
+            block_hash,
+            block_number: U64::from(block_number),
+            transaction_hash: tx_hash,
+            transaction_index: U64::from(transaction_index),
+            log_index: U64::from(log_index),
+            removed: false,
+        })
+        .collect();
+
+    TransactionReceipt {
+        transaction_hash: tx_hash,
+        transaction_index: U64::from(transaction_index),
+        block_hash,
+        block_number: U64::from(block_number),
+        from,
+        to,
+        cumulative_gas_used: U64::from(receipt.cumulative_gas_used),
+        gas_used: U64::from(position.gas_used),
+        effective_gas_price: U256::from(tx.effective_gas_price(Some(base_fee_per_gas))),
+        contract_address,
+        logs,
+        logs_bloom: Bytes::from(vec![0u8; 256]),
+        status: U64::from(success as u64),
+        tx_type: U64::from(0u64), // Legacy tx
+    }
+}
+
 /// EVM JSON-RPC interface
 #[rpc(server, namespace = "eth")]
 pub trait EthApi {
@@ -152,6 +579,10 @@ pub trait EthApi {
     #[method(name = "blockNumber")]
     async fn block_number(&self) -> RpcResult<U64>;
 
+    /// `false`, or the progress while the head is behind the highest block peers announced
+    #[method(name = "syncing")]
+    async fn syncing(&self) -> RpcResult<Syncing>;
+
     #[method(name = "getBalance")]
     async fn get_balance(&self, address: Address, block: Option<String>) -> RpcResult<U256>;
 
@@ -176,9 +607,17 @@ pub trait EthApi {
     #[method(name = "sendRawTransaction")]
     async fn send_raw_transaction(&self, data: Bytes) -> RpcResult<B256>;
 
+    /// Return data of a simulated call
+    ///
+    /// DexVM targets are simulated against pending DexVM state; other calls run
+    /// their contract code in revm against the state store (see [`EvmCallExecutor`]).
     #[method(name = "call")]
     async fn call(&self, request: TransactionRequest, block: Option<String>) -> RpcResult<Bytes>;
 
+    /// Gas a call needs
+    ///
+    /// DexVM targets take one simulated run, whose gas does not depend on the
+    /// limit. Other calls are estimated with [`EvmCallExecutor::estimate_gas`].
     #[method(name = "estimateGas")]
     async fn estimate_gas(
         &self,
@@ -186,6 +625,16 @@ pub trait EthApi {
         block: Option<String>,
     ) -> RpcResult<U64>;
 
+    /// Accounts and slots the call would touch, excluding the sender
+    ///
+    /// A failing call still returns its access list, with the reason in `error`.
+    #[method(name = "createAccessList")]
+    async fn create_access_list(
+        &self,
+        request: TransactionRequest,
+        block: Option<String>,
+    ) -> RpcResult<AccessListResult>;
+
     #[method(name = "gasPrice")]
     async fn gas_price(&self) -> RpcResult<U256>;
 
@@ -202,11 +651,41 @@ pub trait EthApi {
     #[method(name = "getTransactionReceipt")]
     async fn get_transaction_receipt(&self, hash: B256) -> RpcResult<Option<TransactionReceipt>>;
 
+    /// A mined transaction with its block position, or a pending one without
+    #[method(name = "getTransactionByHash")]
+    async fn get_transaction_by_hash(&self, hash: B256) -> RpcResult<Option<TransactionInfo>>;
+
     #[method(name = "accounts")]
     async fn accounts(&self) -> RpcResult<Vec<Address>>;
 
-    #[method(name = "net_version")]
-    async fn net_version(&self) -> RpcResult<String>;
+    #[method(name = "protocolVersion")]
+    async fn protocol_version(&self) -> RpcResult<U64>;
+
+    /// Whether this node produces blocks
+    #[method(name = "mining")]
+    async fn mining(&self) -> RpcResult<bool>;
+
+    /// Always zero: blocks are produced by POA, not proof of work
+    #[method(name = "hashrate")]
+    async fn hashrate(&self) -> RpcResult<U64>;
+
+    /// Validator address, or the zero address on non-validator nodes
+    #[method(name = "coinbase")]
+    async fn coinbase(&self) -> RpcResult<Address>;
+
+    /// Subscribe to `newHeads`, or to `logs` matching an optional filter
+    ///
+    /// Logs of blocks unwound in a reorg are sent again with `removed: true`.
+    #[subscription(
+        name = "subscribe" => "subscription",
+        unsubscribe = "unsubscribe",
+        item = serde_json::Value
+    )]
+    async fn subscribe(
+        &self,
+        kind: SubscriptionKind,
+        filter: Option<LogFilter>,
+    ) -> SubscriptionResult;
 }
 
 /// Web3 JSON-RPC interface
@@ -229,37 +708,543 @@ pub trait NetApi {
     async fn peer_count(&self) -> RpcResult<U64>;
 }
 
+/// Admin JSON-RPC interface
+#[rpc(server, namespace = "admin")]
+pub trait AdminApi {
+    #[method(name = "dbStats")]
+    async fn db_stats(&self) -> RpcResult<DatabaseStats>;
+
+    /// Competing blocks peers announced at heights in `from_block..=to_block`
+    ///
+    /// Points at duplicate validators or forks caused by latency.
+    #[method(name = "siblingBlocks")]
+    async fn sibling_blocks(&self, from_block: U64, to_block: U64) -> RpcResult<Vec<SiblingBlock>>;
+
+    /// Back the database up now into the configured backup directory
+    #[method(name = "backup")]
+    async fn backup(&self) -> RpcResult<BackupReport>;
+}
+
+/// DexVM node JSON-RPC interface
+#[rpc(server, namespace = "dex")]
+pub trait DexApi {
+    /// Wait until a transaction is included, returning its receipt
+    ///
+    /// Resolves to `null` if the timeout passes first.
+    #[method(name = "waitForTransaction")]
+    async fn wait_for_transaction(
+        &self,
+        hash: B256,
+        timeout_ms: Option<u64>,
+    ) -> RpcResult<Option<TransactionReceipt>>;
+
+    /// Execution summaries for blocks in `from_block..=to_block`
+    ///
+    /// Blocks without a recorded summary (e.g. imported by sync) are omitted.
+    #[method(name = "getBlockStats")]
+    async fn get_block_stats(&self, from_block: U64, to_block: U64) -> RpcResult<Vec<BlockStats>>;
+
+    /// EVM, DexVM and combined state roots and header hash of a stored block, or `null`
+    #[method(name = "getBlockRoots")]
+    async fn get_block_roots(&self, number: U64) -> RpcResult<Option<BlockRoots>>;
+
+    /// API version, namespaces and features of this node
+    #[method(name = "clientCapabilities")]
+    async fn client_capabilities(&self) -> RpcResult<ClientCapabilities>;
+
+    /// Size, per-table usage and growth of the database
+    ///
+    /// Growth is estimated from samples the node takes while running, so it is
+    /// `null` for the first hour after a start.
+    #[method(name = "storageInfo")]
+    async fn storage_info(&self) -> RpcResult<StorageInfo>;
+
+    /// Set accounts' balance, nonce and DexVM counter and drop their pending transactions
+    ///
+    /// Only served by nodes started with `--dev`. Code and storage are kept.
+    #[method(name = "resetAccount")]
+    async fn reset_account(
+        &self,
+        accounts: Vec<AccountReset>,
+    ) -> RpcResult<Vec<AccountResetResult>>;
+
+    /// Subscribe to changes of the counters of `addresses` in stored blocks
+    ///
+    /// With `from_block`, the changes made by stored blocks from that one on
+    /// are sent before the live ones.
+    #[subscription(
+        name = "subscribeCounters" => "counterUpdate",
+        unsubscribe = "unsubscribeCounters",
+        item = CounterUpdate
+    )]
+    async fn subscribe_counters(
+        &self,
+        addresses: Vec<Address>,
+        from_block: Option<U64>,
+    ) -> SubscriptionResult;
+}
+
+/// Check the parameters of a `dex_subscribeCounters` call made at block `head`
+fn check_counter_subscription(
+    addresses: &[Address],
+    from_block: Option<U64>,
+    head: u64,
+) -> Result<(), String> {
+    if addresses.is_empty() {
+        return Err("No addresses to follow".to_string());
+    }
+    if addresses.len() > MAX_COUNTER_SUBSCRIPTION_ADDRESSES {
+        return Err(format!(
+            "{} addresses exceed the limit of {}",
+            addresses.len(),
+            MAX_COUNTER_SUBSCRIPTION_ADDRESSES
+        ));
+    }
+    if let Some(from) = from_block.map(|from| from.to::<u64>()) {
+        if from < head.saturating_sub(MAX_COUNTER_CATCH_UP_BLOCKS) {
+            return Err(format!(
+                "Catch-up from block {} starts more than {} blocks below the head {}",
+                from, MAX_COUNTER_CATCH_UP_BLOCKS, head
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Send counter updates to a subscription
+async fn send_counter_updates(
+    sink: &SubscriptionSink,
+    updates: Vec<CounterUpdate>,
+) -> SubscriptionResult {
+    for update in updates {
+        sink.send(SubscriptionMessage::from_json(&update)?).await?;
+    }
+    Ok(())
+}
+
+/// JSON-RPC error code for reverted execution
+pub const EXECUTION_REVERTED_CODE: i32 = 3;
+
+/// Map a simulation failure to a JSON-RPC error
+///
+/// Reverts use code 3 with the ABI-encoded revert data, as clients expect.
+fn simulation_error(error: SimulationError) -> ErrorObjectOwned {
+    match &error {
+        SimulationError::Reverted { data, .. } => {
+            ErrorObjectOwned::owned(EXECUTION_REVERTED_CODE, error.to_string(), Some(data.clone()))
+        }
+        _ => ErrorObjectOwned::owned(-32000, error.to_string(), None::<()>),
+    }
+}
+
+/// JSON-RPC error code for malformed parameters
+pub const INVALID_PARAMS_CODE: i32 = -32602;
+
+/// Resolve a block number or tag
+///
+/// Blocks are final once produced, so `safe` and `finalized` are the latest
+/// block, like `pending`. Anything else must be a `0x`-prefixed hex quantity.
+fn parse_block_tag(tag: &str, latest: u64) -> RpcResult<u64> {
+    match tag {
+        "latest" | "pending" | "safe" | "finalized" => Ok(latest),
+        "earliest" => Ok(0),
+        _ => tag
+            .strip_prefix("0x")
+            .filter(|digits| !digits.is_empty())
+            .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| {
+                ErrorObjectOwned::owned(
+                    INVALID_PARAMS_CODE,
+                    format!("Invalid block number or tag: {}", tag),
+                    None::<()>,
+                )
+            }),
+    }
+}
+
 /// Pending transaction
 #[derive(Debug, Clone)]
 pub struct PendingTransaction {
     pub tx: TransactionSigned,
     pub hash: B256,
     pub from: Address,
+    /// When the pool first saw it; kept when it is requeued
+    pub received_at: Instant,
+    /// RLP-encoded size, counted against the pool byte limit
+    pub size: usize,
+}
+
+impl PendingTransaction {
+    /// Wrap a transaction received now
+    pub fn new(tx: TransactionSigned, from: Address) -> Self {
+        Self {
+            hash: *tx.tx_hash(),
+            size: alloy_rlp::Encodable::length(&tx),
+            tx,
+            from,
+            received_at: Instant::now(),
+        }
+    }
+
+    /// Gas price paid at the base fee, which ranks transactions for eviction
+    pub fn gas_price(&self) -> u128 {
+        self.tx.effective_gas_price(Some(BASE_FEE_PER_GAS))
+    }
 }
 
 /// EVM RPC server implementation
 pub struct EvmRpcServer {
     chain_id: u64,
-    state_store: Arc<StateStore>,
-    block_store: Arc<BlockStore>,
-    pending_txs: Arc<RwLock<Vec<PendingTransaction>>>,
+    state_store: Arc<dyn StateReader>,
+    block_store: Arc<dyn BlockReader>,
+    pending_txs: Arc<RwLock<PendingPool>>,
     receipts: Arc<RwLock<HashMap<B256, TransactionReceipt>>>,
+    /// Wakes `dex_waitForTransaction` callers when receipts are added
+    receipt_notify: Arc<Notify>,
     /// Optional channel for broadcasting transactions via P2P
     tx_broadcast_sender: Arc<RwLock<Option<mpsc::Sender<Vec<u8>>>>>,
+    /// Optional database handle for admin statistics
+    storage: Arc<RwLock<Option<Arc<DualvmStorage>>>>,
+    /// Mempool admission limits
+    mempool_config: Arc<RwLock<MempoolConfig>>,
+    /// Optional DexVM executor used to simulate transactions on admission
+    dexvm_executor: Arc<RwLock<Option<Arc<RwLock<DexVmExecutor>>>>>,
+    /// Address this node produces blocks as, if it is a validator
+    validator: Arc<RwLock<Option<Address>>>,
+    /// Transactions the block builder dropped; refused on admission
+    quarantine: Arc<RwLock<Quarantine>>,
+    /// Pending transactions dropped by the pool limits
+    pool_drops: Arc<RwLock<PoolDrops>>,
+    /// Serving a read-only database; transactions are refused
+    read_only: Arc<RwLock<bool>>,
+    /// Stored and unwound blocks, fanned out to `eth_subscribe` subscriptions
+    chain_events: ChainEvents,
+    /// Optional DexVM pool, cleared of reset accounts
+    dexvm_pool: Arc<RwLock<Option<Arc<DexVmPool>>>>,
+    /// Serve dev-only methods such as `dex_resetAccount`
+    dev_mode: Arc<RwLock<bool>>,
+    /// Optional node status, for the connected peer count
+    node_status: Arc<RwLock<Option<Arc<NodeStatus>>>>,
+    /// Features reported by `dex_clientCapabilities`
+    features: Arc<RwLock<ClientFeatures>>,
+    /// Optional backup directory for `admin_backup`
+    backups: Arc<RwLock<Option<Arc<Backups>>>>,
+    /// Head when the server started, reported by `eth_syncing`
+    starting_block: u64,
+    /// How state reads are answered while the node is behind its peers
+    sync_gate: Arc<RwLock<SyncGate>>,
 }
 
 impl EvmRpcServer {
-    pub fn new(chain_id: u64, state_store: Arc<StateStore>, block_store: Arc<BlockStore>) -> Self {
+    pub fn new(
+        chain_id: u64,
+        state_store: Arc<dyn StateReader>,
+        block_store: Arc<dyn BlockReader>,
+    ) -> Self {
+        let starting_block = block_store.latest_block_number();
         Self {
             chain_id,
             state_store,
             block_store,
-            pending_txs: Arc::new(RwLock::new(Vec::new())),
+            pending_txs: Arc::new(RwLock::new(PendingPool::default())),
             receipts: Arc::new(RwLock::new(HashMap::new())),
+            receipt_notify: Arc::new(Notify::new()),
             tx_broadcast_sender: Arc::new(RwLock::new(None)),
+            storage: Arc::new(RwLock::new(None)),
+            mempool_config: Arc::new(RwLock::new(MempoolConfig::default())),
+            dexvm_executor: Arc::new(RwLock::new(None)),
+            validator: Arc::new(RwLock::new(None)),
+            quarantine: Arc::new(RwLock::new(Quarantine::default())),
+            pool_drops: Arc::new(RwLock::new(PoolDrops::default())),
+            read_only: Arc::new(RwLock::new(false)),
+            chain_events: ChainEvents::default(),
+            dexvm_pool: Arc::new(RwLock::new(None)),
+            dev_mode: Arc::new(RwLock::new(false)),
+            node_status: Arc::new(RwLock::new(None)),
+            features: Arc::new(RwLock::new(ClientFeatures::default())),
+            backups: Arc::new(RwLock::new(None)),
+            starting_block,
+            sync_gate: Arc::new(RwLock::new(SyncGate::default())),
         }
     }
 
+    /// Set mempool admission limits
+    pub fn set_mempool_config(&self, config: MempoolConfig) {
+        *self.mempool_config.write().unwrap() = config;
+    }
+
+    /// Mempool admission limits
+    pub fn mempool_config(&self) -> MempoolConfig {
+        *self.mempool_config.read().unwrap()
+    }
+
+    /// Set the DexVM executor whose pending state admission simulation runs against
+    pub fn set_dexvm_executor(&self, executor: Arc<RwLock<DexVmExecutor>>) {
+        *self.dexvm_executor.write().unwrap() = Some(executor);
+    }
+
+    /// Set the node status `net_peerCount` reads the connected peer count from
+    pub fn set_node_status(&self, status: Arc<NodeStatus>) {
+        *self.node_status.write().unwrap() = Some(status);
+    }
+
+    /// Set the validator address reported by `eth_coinbase` and `eth_mining`
+    pub fn set_validator(&self, validator: Address) {
+        *self.validator.write().unwrap() = Some(validator);
+    }
+
+    /// Refuse a transaction from now on, e.g. after it stalled block building
+    pub fn quarantine_transaction(&self, hash: B256) {
+        self.quarantine.write().unwrap().insert(hash);
+    }
+
+    /// Whether a transaction has been quarantined
+    pub fn is_quarantined(&self, hash: &B256) -> bool {
+        self.quarantine.read().unwrap().contains(hash)
+    }
+
+    /// Number of quarantined transactions
+    pub fn quarantined_count(&self) -> usize {
+        self.quarantine.read().unwrap().len()
+    }
+
+    /// Check fee floor and per-sender limit for a new pending transaction
+    ///
+    /// With `reject_failing` set, the transaction is also simulated.
+    fn check_admission(
+        &self,
+        pending: &PendingPool,
+        tx: &TransactionSigned,
+        from: Address,
+    ) -> Result<(), ErrorObjectOwned> {
+        if self.is_quarantined(tx.tx_hash()) {
+            return Err(ErrorObjectOwned::owned(
+                -32000,
+                format!("Transaction {} is quarantined", tx.tx_hash()),
+                None::<()>,
+            ));
+        }
+
+        if sends_value_to_dexvm(tx.to(), tx.value()) {
+            return Err(ErrorObjectOwned::owned(-32000, DEXVM_VALUE_ERROR, None::<()>));
+        }
+
+        let config = *self.mempool_config.read().unwrap();
+        config
+            .check_input_size(tx)
+            .and_then(|_| config.check_fees(tx))
+            .and_then(|_| {
+                // A replacement takes the place of the sender's transaction with its nonce
+                let replaces = pending.get_by_nonce(from, tx.nonce()).is_some();
+                config.check_sender_pending(pending.sender_count(from) - usize::from(replaces))
+            })
+            .map_err(|e| ErrorObjectOwned::owned(-32000, e, None::<()>))?;
+
+        if config.reject_failing {
+            if let Some(executor) = self.dexvm_executor.read().unwrap().as_ref() {
+                let executor = executor.read().map_err(|e| {
+                    ErrorObjectOwned::owned(-32000, format!("DexVM lock error: {}", e), None::<()>)
+                })?;
+                let evm_state = StoreEvmState(self.state_store.as_ref());
+                simulate_transaction(tx, from, executor.pending_state(), &evm_state)
+                    .map_err(simulation_error)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add an admitted transaction to the pool, enforcing the pool limits
+    ///
+    /// A transaction with the nonce of one of its sender's pending transactions
+    /// replaces it if it pays the price bump. Expired transactions are dropped
+    /// first; if the pool is still full, cheaper transactions are evicted or the
+    /// newcomer is refused.
+    fn insert_pending(
+        &self,
+        pending: &mut PendingPool,
+        incoming: PendingTransaction,
+    ) -> Result<(), ErrorObjectOwned> {
+        let config = *self.mempool_config.read().unwrap();
+        self.drop_expired(&config, pending);
+
+        let replaced = config
+            .check_replacement(pending, &incoming)
+            .map_err(|e| ErrorObjectOwned::owned(-32000, e, None::<()>))?;
+        // The replaced transaction stays pending if the pool refuses its replacement
+        let evicted = config
+            .make_room(pending, &incoming, replaced)
+            .map_err(|e| ErrorObjectOwned::owned(-32000, e, None::<()>))?;
+        if let Some(old) = replaced.and_then(|hash| pending.remove(&hash)) {
+            tracing::info!(
+                "Replaced pending transaction {} (gas price {}) with {} (gas price {})",
+                old.hash,
+                old.gas_price(),
+                incoming.hash,
+                incoming.gas_price()
+            );
+            self.pool_drops.write().unwrap().replaced += 1;
+        }
+        for tx in &evicted {
+            tracing::info!(
+                "Evicted pending transaction {} (gas price {}) for {} (gas price {})",
+                tx.hash,
+                tx.gas_price(),
+                incoming.hash,
+                incoming.gas_price()
+            );
+        }
+        self.pool_drops.write().unwrap().evicted += evicted.len() as u64;
+
+        pending.insert(incoming);
+        Ok(())
+    }
+
+    /// Drop pending transactions older than the configured maximum age
+    fn drop_expired(&self, config: &MempoolConfig, pending: &mut PendingPool) {
+        let expired = config.expire(pending, Instant::now());
+        if expired.is_empty() {
+            return;
+        }
+        for tx in &expired {
+            tracing::debug!("Pending transaction {} from {} expired", tx.hash, tx.from);
+        }
+        tracing::info!(
+            "Dropped {} pending transactions older than {}s",
+            expired.len(),
+            config.max_tx_age.as_secs()
+        );
+        self.pool_drops.write().unwrap().expired += expired.len() as u64;
+    }
+
+    /// Drop expired pending transactions, for pools no block is draining
+    pub fn prune_pending_transactions(&self) {
+        let config = *self.mempool_config.read().unwrap();
+        self.drop_expired(&config, &mut self.pending_txs.write().unwrap());
+    }
+
+    /// Pending transactions dropped by the pool limits since startup
+    pub fn pool_drops(&self) -> PoolDrops {
+        *self.pool_drops.read().unwrap()
+    }
+
+    /// Simulate a call request against pending DexVM state
+    ///
+    /// Returns `None` when the target has no DexVM side effects. Refused like
+    /// other state reads while the node is too far behind its peers.
+    fn simulate_request(&self, request: &TransactionRequest) -> RpcResult<Option<CallOutcome>> {
+        self.check_synced()?;
+        let Some(executor) = self.dexvm_executor.read().unwrap().clone() else {
+            return Ok(None);
+        };
+        let executor = executor.read().map_err(|e| {
+            ErrorObjectOwned::owned(-32000, format!("DexVM lock error: {}", e), None::<()>)
+        })?;
+
+        simulate_call(
+            request.from.unwrap_or_default(),
+            request.to,
+            request.value.unwrap_or_default(),
+            request.data.as_ref().map(|data| data.as_ref()).unwrap_or_default(),
+            executor.pending_state(),
+            &StoreEvmState(self.state_store.as_ref()),
+        )
+        .map_err(simulation_error)
+    }
+
+    /// Contract call executor reading the state store, in the latest block
+    fn call_executor(&self) -> EvmCallExecutor<'_> {
+        let latest = self.block_store.latest_block_number();
+        let timestamp = self.block_store.get_block_by_number(latest).map_or(0, |b| b.timestamp);
+        EvmCallExecutor::new(self.chain_id, self.state_store.as_ref()).with_block(latest, timestamp)
+    }
+
+    /// Set the database handle used by admin methods
+    pub fn set_storage(&self, storage: Arc<DualvmStorage>) {
+        *self.storage.write().unwrap() = Some(storage);
+    }
+
+    /// Set the DexVM pool `dex_resetAccount` drops reset accounts' transactions from
+    pub fn set_dexvm_pool(&self, pool: Arc<DexVmPool>) {
+        *self.dexvm_pool.write().unwrap() = Some(pool);
+    }
+
+    /// Serve dev-only methods such as `dex_resetAccount`
+    pub fn set_dev_mode(&self, dev_mode: bool) {
+        *self.dev_mode.write().unwrap() = dev_mode;
+    }
+
+    /// Set the backup directory `admin_backup` writes into
+    pub fn set_backups(&self, backups: Arc<Backups>) {
+        *self.backups.write().unwrap() = Some(backups);
+    }
+
+    /// Set the features reported by `dex_clientCapabilities`
+    ///
+    /// `dev_methods` and `read_only` follow [`Self::set_dev_mode`] and
+    /// [`Self::set_read_only`] instead.
+    pub fn set_client_features(&self, features: ClientFeatures) {
+        *self.features.write().unwrap() = features;
+    }
+
+    /// Set how state reads are answered while the node is behind its peers
+    pub fn set_sync_gate(&self, gate: SyncGate) {
+        *self.sync_gate.write().unwrap() = gate;
+    }
+
+    /// Stored head against the highest block peers announced
+    ///
+    /// Without a node status no peers are known, so the node counts as synced.
+    pub fn sync_status(&self) -> SyncStatus {
+        let highest = self.node_status.read().unwrap().as_ref().map_or(0, |status| {
+            status.highest_peer_block()
+        });
+        SyncStatus::new(self.block_store.latest_block_number(), highest)
+    }
+
+    /// Sync status, if the gate is in `mode` and the node is too far behind
+    fn lagging(&self, mode: SyncGateMode) -> Option<SyncStatus> {
+        let gate = *self.sync_gate.read().unwrap();
+        if gate.mode != mode {
+            return None;
+        }
+        Some(self.sync_status()).filter(|sync| gate.is_behind(sync))
+    }
+
+    fn sync_progress(&self, sync: &SyncStatus) -> SyncProgress {
+        SyncProgress {
+            starting_block: U64::from(self.starting_block.min(sync.current_block)),
+            current_block: U64::from(sync.current_block),
+            highest_block: U64::from(sync.highest_block),
+        }
+    }
+
+    /// Refuse a state read if the gate rejects them and the node is too far behind
+    fn check_synced(&self) -> RpcResult<()> {
+        match self.lagging(SyncGateMode::Reject) {
+            Some(sync) => Err(ErrorObjectOwned::owned(
+                -32000,
+                format!(
+                    "Node is syncing (block {} of {}); state is not current",
+                    sync.current_block, sync.highest_block
+                ),
+                Some(self.sync_progress(&sync)),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Value of [`SYNCING_HEADER`], if the gate warns and the node is too far behind
+    pub fn syncing_header(&self) -> Option<HeaderValue> {
+        let sync = self.lagging(SyncGateMode::Warn)?;
+        HeaderValue::from_str(&format!("{}/{}", sync.current_block, sync.highest_block)).ok()
+    }
+
+    /// Refuse `eth_sendRawTransaction`, for replicas over a read-only database
+    pub fn set_read_only(&self, read_only: bool) {
+        *self.read_only.write().unwrap() = read_only;
+    }
+
     /// Set the transaction broadcast channel for P2P propagation
     pub fn set_tx_broadcast_sender(&self, sender: mpsc::Sender<Vec<u8>>) {
         *self.tx_broadcast_sender.write().unwrap() = Some(sender);
@@ -273,37 +1258,174 @@ impl EvmRpcServer {
         }
     }
 
+    /// Broadcast every pending transaction again, returning how many were queued
+    ///
+    /// Reaches peers that were not connected when a transaction was first
+    /// broadcast; the P2P service sends each transaction to a peer only once.
+    pub fn rebroadcast_pending_transactions(&self) -> usize {
+        let Some(sender) = self.tx_broadcast_sender.read().unwrap().clone() else {
+            return 0;
+        };
+        let pending = self.pending_txs.read().unwrap();
+        pending
+            .iter()
+            .take_while(|pending| sender.try_send(alloy_rlp::encode(&pending.tx)).is_ok())
+            .count()
+    }
+
     pub fn get_pending_transactions(&self) -> Vec<PendingTransaction> {
-        self.pending_txs.read().unwrap().clone()
+        self.pending_txs.read().unwrap().to_vec()
+    }
+
+    /// Number of pending EVM transactions
+    pub fn pending_count(&self) -> usize {
+        self.pending_txs.read().unwrap().len()
     }
 
     pub fn clear_pending_transactions(&self) {
         self.pending_txs.write().unwrap().clear();
     }
 
+    /// Put transactions that missed a block back at the front of the pool
+    ///
+    /// Transactions replaced in the meantime are not requeued.
+    pub fn requeue_pending_transactions(&self, txs: Vec<PendingTransaction>) {
+        self.pending_txs.write().unwrap().requeue(txs);
+    }
+
+    /// Notify subscriptions of a block just stored on top of the chain
+    ///
+    /// Its receipts must already be stored for `logs` subscriptions to see them.
+    pub fn notify_block(&self, number: u64) {
+        let Some(block) = self.block_store.get_block_by_number(number) else {
+            return;
+        };
+        let receipts: Vec<_> = block
+            .transaction_hashes
+            .iter()
+            .filter_map(|hash| Some((*hash, self.block_store.get_receipt(*hash)?)))
+            .collect();
+        self.chain_events.publish(ChainEvent::committed(&block, &receipts));
+    }
+
+    /// Counter updates for `addresses` made by stored blocks `from..=to`
+    fn stored_counter_updates(
+        &self,
+        addresses: &HashSet<Address>,
+        from: u64,
+        to: u64,
+    ) -> Vec<CounterUpdate> {
+        (from..=to)
+            .filter_map(|number| {
+                let block = self.block_store.get_block_by_number(number)?;
+                let receipts = self.block_store.get_dexvm_receipts(number)?;
+                Some(counter_updates(&block, &receipts, addresses))
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Receive the chain events `eth_subscribe` subscriptions are fed from now on
+    pub fn subscribe_chain_events(&self) -> broadcast::Receiver<ChainEvent> {
+        self.chain_events.subscribe()
+    }
+
+    /// Notify subscriptions of blocks removed by `BlockWriter::unwind_to`
+    ///
+    /// Drops receipts cached for their transactions, which are no longer included.
+    pub fn notify_unwind(&self, unwound: &[UnwoundBlock]) {
+        let mut receipts = self.receipts.write().unwrap();
+        for removed in unwound {
+            for hash in &removed.block.transaction_hashes {
+                receipts.remove(hash);
+            }
+            self.chain_events.publish(ChainEvent::reverted(removed));
+        }
+    }
+
     pub fn add_receipt(&self, hash: B256, receipt: TransactionReceipt) {
         self.receipts.write().unwrap().insert(hash, receipt);
+        self.receipt_notify.notify_waiters();
+    }
+
+    /// Rebuild a receipt from the database, for blocks this process did not produce
+    fn stored_receipt(&self, hash: B256) -> Option<TransactionReceipt> {
+        let stored = self.block_store.get_receipt(hash)?;
+        let block_number = self.block_store.get_tx_block_number(hash)?;
+        let block = self.block_store.get_block_by_number(block_number)?;
+        let rlp = self.block_store.get_transaction(hash)?;
+        let tx = TransactionSigned::decode(&mut rlp.as_slice()).ok()?;
+
+        // Per-transaction gas needs the previous receipt's cumulative total
+        let previous_cumulative = stored
+            .transaction_index
+            .checked_sub(1)
+            .and_then(|index| block.transaction_hashes.get(index as usize))
+            .and_then(|previous| self.block_store.get_receipt(*previous))
+            .map_or(0, |previous| previous.cumulative_gas_used);
+
+        let position = ReceiptPosition {
+            block_hash: block.hash,
+            block_number,
+            transaction_index: stored.transaction_index,
+            gas_used: stored.cumulative_gas_used.saturating_sub(previous_cumulative),
+            first_log_index: stored.first_log_index,
+        };
+        let receipt = alloy_consensus::Receipt {
+            status: stored.success.into(),
+            cumulative_gas_used: stored.cumulative_gas_used,
+            logs: stored
+                .logs
+                .into_iter()
+                .map(|log| {
+                    alloy_primitives::Log::new_unchecked(log.address, log.topics, log.data.into())
+                })
+                .collect(),
+        };
+        Some(build_receipt(position, BASE_FEE_PER_GAS, &tx, &receipt))
+    }
+
+    /// A stored transaction with the position of its block
+    fn stored_transaction(&self, hash: B256) -> Option<TransactionInfo> {
+        let info = self.block_store.get_tx_info(hash)?;
+        let block = self.block_store.get_block_by_number(info.block_number)?;
+        let rlp = self.block_store.get_transaction(hash)?;
+        let tx = TransactionSigned::decode(&mut rlp.as_slice()).ok()?;
+        let from = tx.recover_signer().ok()?;
+        Some(TransactionInfo::new(&tx, from, Some((block.hash, info.block_number, info.tx_index))))
     }
 
     /// Add a pending transaction from P2P (without validation)
     /// Returns true if the transaction was added, false if it already exists
     pub fn add_pending_transaction_from_p2p(&self, tx: TransactionSigned) -> bool {
-        let hash = *tx.tx_hash();
-        let mut pending = self.pending_txs.write().unwrap();
-
-        // Check if transaction already exists
-        if pending.iter().any(|p| p.hash == hash) {
-            return false;
-        }
-
         // Recover sender address
         let from = match tx.recover_signer() {
             Ok(addr) => addr,
             Err(_) => return false,
         };
+        self.add_recovered_transactions_from_p2p(vec![PendingTransaction::new(tx, from)]) == 1
+    }
 
-        pending.push(PendingTransaction { tx, hash, from });
-        true
+    /// Add P2P transactions whose senders are already recovered, under one pool lock
+    ///
+    /// Returns how many were admitted; known and rejected transactions are skipped.
+    pub fn add_recovered_transactions_from_p2p(&self, txs: Vec<PendingTransaction>) -> usize {
+        let mut pending = self.pending_txs.write().unwrap();
+        let mut added = 0;
+        for tx in txs {
+            if pending.contains(&tx.hash) {
+                continue;
+            }
+            let hash = tx.hash;
+            let admitted = self
+                .check_admission(&pending, &tx.tx, tx.from)
+                .and_then(|_| self.insert_pending(&mut pending, tx));
+            match admitted {
+                Ok(_) => added += 1,
+                Err(e) => tracing::debug!("Rejected P2P transaction {}: {}", hash, e.message()),
+            }
+        }
+        added
     }
 }
 
@@ -317,7 +1439,16 @@ impl EthApiServer for EvmRpcServer {
         Ok(U64::from(self.block_store.latest_block_number()))
     }
 
+    async fn syncing(&self) -> RpcResult<Syncing> {
+        let sync = self.sync_status();
+        if !sync.syncing {
+            return Ok(Syncing::NotSyncing(false));
+        }
+        Ok(Syncing::Progress(self.sync_progress(&sync)))
+    }
+
     async fn get_balance(&self, address: Address, _block: Option<String>) -> RpcResult<U256> {
+        self.check_synced()?;
         Ok(self.state_store.get_balance(&address))
     }
 
@@ -326,10 +1457,12 @@ impl EthApiServer for EvmRpcServer {
         address: Address,
         _block: Option<String>,
     ) -> RpcResult<U64> {
+        self.check_synced()?;
         Ok(U64::from(self.state_store.get_nonce(&address)))
     }
 
     async fn get_code(&self, address: Address, _block: Option<String>) -> RpcResult<Bytes> {
+        self.check_synced()?;
         Ok(self.state_store.get_code(&address).unwrap_or_default())
     }
 
@@ -339,11 +1472,26 @@ impl EthApiServer for EvmRpcServer {
         slot: U256,
         _block: Option<String>,
     ) -> RpcResult<B256> {
+        self.check_synced()?;
         let value = self.state_store.get_storage(&address, slot);
         Ok(B256::from(value.to_be_bytes()))
     }
 
     async fn send_raw_transaction(&self, data: Bytes) -> RpcResult<B256> {
+        if *self.read_only.read().unwrap() {
+            return Err(ErrorObjectOwned::owned(
+                -32000,
+                "Node is a read-only replica and does not accept transactions",
+                None::<()>,
+            ));
+        }
+
+        self.mempool_config
+            .read()
+            .unwrap()
+            .check_encoded_size(data.len())
+            .map_err(|e| ErrorObjectOwned::owned(-32000, e, None::<()>))?;
+
         let tx = TransactionSigned::decode(&mut data.as_ref()).map_err(|e| {
             jsonrpsee::types::ErrorObjectOwned::owned(
                 -32000,
@@ -396,7 +1544,11 @@ impl EthApiServer for EvmRpcServer {
         }
 
         // Add to pending transactions (will be executed during block production)
-        self.pending_txs.write().unwrap().push(PendingTransaction { tx, hash: tx_hash, from: caller });
+        {
+            let mut pending = self.pending_txs.write().unwrap();
+            self.check_admission(&pending, &tx, caller)?;
+            self.insert_pending(&mut pending, PendingTransaction::new(tx, caller))?;
+        }
 
         // Broadcast transaction to P2P network (for fullnode mode)
         self.broadcast_transaction(data.to_vec());
@@ -410,8 +1562,16 @@ impl EthApiServer for EvmRpcServer {
         Ok(tx_hash)
     }
 
-    async fn call(&self, _request: TransactionRequest, _block: Option<String>) -> RpcResult<Bytes> {
-        Ok(Bytes::default())
+    async fn call(&self, request: TransactionRequest, _block: Option<String>) -> RpcResult<Bytes> {
+        if let Some(outcome) = self.simulate_request(&request)? {
+            return Ok(outcome.into_result().map_err(simulation_error)?.return_data);
+        }
+
+        let outcome = self
+            .call_executor()
+            .execute_call(&request.evm_call(), request.gas_limit())
+            .map_err(simulation_error)?;
+        Ok(outcome.return_data)
     }
 
     async fn estimate_gas(
@@ -419,21 +1579,51 @@ impl EthApiServer for EvmRpcServer {
         request: TransactionRequest,
         _block: Option<String>,
     ) -> RpcResult<U64> {
-        let mut gas = 21000u64;
-        if let Some(data) = &request.data {
-            gas += data.len() as u64 * 16;
-        }
-        if request.to.is_none() {
-            gas += 32000;
-            if let Some(data) = &request.data {
-                gas += data.len() as u64 * 200;
-            }
+        if let Some(outcome) = self.simulate_request(&request)? {
+            let outcome = outcome.into_result().map_err(simulation_error)?;
+            return Ok(U64::from(outcome.gas_used));
         }
-        Ok(U64::from((gas as f64 * 1.2) as u64))
+
+        // Without DexVM state to simulate against, a counter precompile call costs what the
+        // precompile charges up front; a limit below that fails the call out of gas
+        if system_handler(request.to) == Some(SystemHandler::CounterPrecompile) {
+            let input = request.data.as_ref().map(|data| data.as_ref()).unwrap_or_default();
+            return Ok(U64::from(PrecompileExecutor::required_gas(input)));
+        }
+
+        self.call_executor()
+            .estimate_gas(&request.evm_call(), request.gas_limit())
+            .map(U64::from)
+            .map_err(simulation_error)
+    }
+
+    async fn create_access_list(
+        &self,
+        request: TransactionRequest,
+        block: Option<String>,
+    ) -> RpcResult<AccessListResult> {
+        let outcome = self.simulate_request(&request)?;
+        let succeeded = outcome.as_ref().is_none_or(|outcome| outcome.success);
+        let from = request.from.unwrap_or_default();
+        let input = request.data.as_ref().map(|data| data.as_ref()).unwrap_or_default();
+
+        let access_list = call_access_set(from, request.to, input, succeeded)
+            .access_list()
+            .into_iter()
+            .filter(|(address, _)| *address != from)
+            .map(|(address, storage_keys)| AccessListItem { address, storage_keys })
+            .collect();
+
+        let (gas_used, error) = match outcome {
+            Some(outcome) => (U64::from(outcome.gas_used), outcome.error),
+            None => (self.estimate_gas(request, block).await?, None),
+        };
+
+        Ok(AccessListResult { access_list, error, gas_used })
     }
 
     async fn gas_price(&self) -> RpcResult<U256> {
-        Ok(U256::from(1_000_000_000u64))
+        Ok(U256::from(BASE_FEE_PER_GAS))
     }
 
     async fn get_block_by_number(
@@ -441,15 +1631,7 @@ impl EthApiServer for EvmRpcServer {
         number: String,
         _full_tx: bool,
     ) -> RpcResult<Option<BlockInfo>> {
-        let block_num = if number == "latest" || number == "pending" {
-            self.block_store.latest_block_number()
-        } else if number == "earliest" {
-            0
-        } else {
-            let num_str = number.strip_prefix("0x").unwrap_or(&number);
-            u64::from_str_radix(num_str, 16).unwrap_or(0)
-        };
-
+        let block_num = parse_block_tag(&number, self.block_store.latest_block_number())?;
         Ok(self.block_store.get_block_by_number(block_num).map(BlockInfo::from))
     }
 
@@ -458,7 +1640,16 @@ impl EthApiServer for EvmRpcServer {
     }
 
     async fn get_transaction_receipt(&self, hash: B256) -> RpcResult<Option<TransactionReceipt>> {
-        Ok(self.receipts.read().unwrap().get(&hash).cloned())
+        let cached = self.receipts.read().unwrap().get(&hash).cloned();
+        Ok(cached.or_else(|| self.stored_receipt(hash)))
+    }
+
+    async fn get_transaction_by_hash(&self, hash: B256) -> RpcResult<Option<TransactionInfo>> {
+        if let Some(mined) = self.stored_transaction(hash) {
+            return Ok(Some(mined));
+        }
+        let pending = self.pending_txs.read().unwrap();
+        Ok(pending.get(&hash).map(|pending| TransactionInfo::new(&pending.tx, pending.from, None)))
     }
 
     async fn accounts(&self) -> RpcResult<Vec<Address>> {
@@ -466,20 +1657,307 @@ impl EthApiServer for EvmRpcServer {
         Ok(accounts.keys().cloned().collect())
     }
 
-    async fn net_version(&self) -> RpcResult<String> {
-        Ok(self.chain_id.to_string())
+    async fn protocol_version(&self) -> RpcResult<U64> {
+        Ok(U64::from(ETH_PROTOCOL_VERSION))
+    }
+
+    async fn mining(&self) -> RpcResult<bool> {
+        Ok(self.validator.read().unwrap().is_some())
+    }
+
+    async fn hashrate(&self) -> RpcResult<U64> {
+        Ok(U64::ZERO)
+    }
+
+    async fn coinbase(&self) -> RpcResult<Address> {
+        Ok(self.validator.read().unwrap().unwrap_or_default())
+    }
+
+    async fn subscribe(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: SubscriptionKind,
+        filter: Option<LogFilter>,
+    ) -> SubscriptionResult {
+        let mut events = self.chain_events.subscribe();
+        let sink = pending.accept().await?;
+
+        loop {
+            let event = tokio::select! {
+                _ = sink.closed() => return Ok(()),
+                event = events.recv() => event,
+            };
+            match event {
+                Ok(event) => {
+                    for item in event.notifications(kind, filter.as_ref()) {
+                        sink.send(SubscriptionMessage::from_json(&item)?).await?;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!("Subscription {:?} missed {} chain events", kind, missed);
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl Web3ApiServer for EvmRpcServer {
     async fn client_version(&self) -> RpcResult<String> {
-        Ok("DualVM/v0.1.0".to_string())
+        Ok(CLIENT_VERSION.to_string())
+    }
+}
+
+#[async_trait::async_trait]
+impl AdminApiServer for EvmRpcServer {
+    async fn db_stats(&self) -> RpcResult<DatabaseStats> {
+        let storage = self.storage.read().unwrap().clone().ok_or_else(|| {
+            jsonrpsee::types::ErrorObjectOwned::owned(
+                -32000,
+                "Database statistics not available",
+                None::<()>,
+            )
+        })?;
+
+        storage.stats().map_err(|e| {
+            jsonrpsee::types::ErrorObjectOwned::owned(
+                -32000,
+                format!("Failed to read database statistics: {}", e),
+                None::<()>,
+            )
+        })
+    }
+
+    async fn sibling_blocks(
+        &self,
+        from_block: U64,
+        to_block: U64,
+    ) -> RpcResult<Vec<SiblingBlock>> {
+        let (from, to) = (from_block.to::<u64>(), to_block.to::<u64>());
+        check_block_range(from, to)?;
+
+        Ok(self
+            .block_store
+            .get_siblings_range(from, to)
+            .into_iter()
+            .flat_map(|(number, siblings)| {
+                let canonical_hash =
+                    self.block_store.get_block_by_number(number).map(|block| block.hash);
+                siblings
+                    .blocks
+                    .into_iter()
+                    .map(move |sibling| SiblingBlock::new(number, canonical_hash, sibling))
+            })
+            .collect())
+    }
+
+    async fn backup(&self) -> RpcResult<BackupReport> {
+        let storage = self.storage.read().unwrap().clone();
+        let backups = self.backups.read().unwrap().clone();
+        let (Some(storage), Some(backups)) = (storage, backups) else {
+            return Err(ErrorObjectOwned::owned(
+                -32000,
+                "Backups are not configured (start the node with --backup-dir)",
+                None::<()>,
+            ));
+        };
+
+        // Copying the database blocks for as long as it takes
+        tokio::task::spawn_blocking(move || backups.backup(&storage))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result.map_err(|e| format!("{:#}", e)))
+            .map_err(|e| ErrorObjectOwned::owned(-32000, e, None::<()>))
+    }
+}
+
+#[async_trait::async_trait]
+impl DexApiServer for EvmRpcServer {
+    async fn wait_for_transaction(
+        &self,
+        hash: B256,
+        timeout_ms: Option<u64>,
+    ) -> RpcResult<Option<TransactionReceipt>> {
+        let timeout = timeout_ms.unwrap_or(DEFAULT_WAIT_TIMEOUT_MS).min(MAX_WAIT_TIMEOUT_MS);
+
+        let wait = async {
+            loop {
+                // Register before checking so a receipt added in between still wakes us
+                let notified = self.receipt_notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                let receipt = self.receipts.read().unwrap().get(&hash).cloned();
+                if let Some(receipt) = receipt {
+                    return receipt;
+                }
+                notified.await;
+            }
+        };
+
+        Ok(tokio::time::timeout(Duration::from_millis(timeout), wait).await.ok())
+    }
+
+    async fn get_block_stats(&self, from_block: U64, to_block: U64) -> RpcResult<Vec<BlockStats>> {
+        let (from, to) = (from_block.to::<u64>(), to_block.to::<u64>());
+        check_block_range(from, to)?;
+
+        Ok(self
+            .block_store
+            .get_block_stats_range(from, to)
+            .into_iter()
+            .map(|(number, stats)| BlockStats::new(number, stats))
+            .collect())
+    }
+
+    async fn get_block_roots(&self, number: U64) -> RpcResult<Option<BlockRoots>> {
+        Ok(self.block_store.get_block_by_number(number.to()).as_ref().map(BlockRoots::from))
+    }
+
+    async fn client_capabilities(&self) -> RpcResult<ClientCapabilities> {
+        let mut features = self.features.read().unwrap().clone();
+        features.dev_methods = *self.dev_mode.read().unwrap();
+        features.read_only = *self.read_only.read().unwrap();
+        Ok(ClientCapabilities {
+            api_version: RPC_API_VERSION.to_string(),
+            client_version: CLIENT_VERSION.to_string(),
+            namespaces: RPC_NAMESPACES.iter().map(|namespace| namespace.to_string()).collect(),
+            features,
+        })
+    }
+
+    async fn storage_info(&self) -> RpcResult<StorageInfo> {
+        let storage = self.storage.read().unwrap().clone().ok_or_else(|| {
+            jsonrpsee::types::ErrorObjectOwned::owned(
+                -32000,
+                "Database not available",
+                None::<()>,
+            )
+        })?;
+        let growth = match self.node_status.read().unwrap().as_ref() {
+            Some(status) => Arc::clone(status.storage_growth()),
+            None => Arc::new(StorageGrowth::default()),
+        };
+
+        growth.info(&storage).map_err(|e| {
+            jsonrpsee::types::ErrorObjectOwned::owned(
+                -32000,
+                format!("Failed to read database size: {}", e),
+                None::<()>,
+            )
+        })
+    }
+
+    async fn reset_account(
+        &self,
+        accounts: Vec<AccountReset>,
+    ) -> RpcResult<Vec<AccountResetResult>> {
+        if !*self.dev_mode.read().unwrap() {
+            return Err(ErrorObjectOwned::owned(
+                -32000,
+                "dex_resetAccount is only available on nodes started with --dev",
+                None::<()>,
+            ));
+        }
+        let storage = self.storage.read().unwrap().clone().ok_or_else(|| {
+            ErrorObjectOwned::owned(-32000, "Database not available", None::<()>)
+        })?;
+        let dexvm_executor = self.dexvm_executor.read().unwrap().clone();
+        let dexvm_pool = self.dexvm_pool.read().unwrap().clone();
+        let storage_error = |e: eyre::Report| {
+            ErrorObjectOwned::owned(-32000, format!("Failed to reset account: {}", e), None::<()>)
+        };
+
+        // Hold the DexVM executor so no block executes against a half-reset account
+        let mut executor = dexvm_executor.as_ref().map(|executor| executor.write().unwrap());
+        let mut results = Vec::with_capacity(accounts.len());
+        for reset in accounts {
+            let address = reset.address;
+            let balance = reset.balance.unwrap_or_default();
+            let nonce = reset.nonce.unwrap_or_default().to::<u64>();
+            let counter = reset.counter.unwrap_or_default().to::<u64>();
+
+            storage.state.set_balance(address, balance).map_err(storage_error)?;
+            storage.state.set_nonce(address, nonce).map_err(storage_error)?;
+            storage.state.set_counter(address, counter).map_err(storage_error)?;
+            if let Some(executor) = executor.as_mut() {
+                executor.set_counter(address, counter);
+            }
+
+            let removed_evm = self.pending_txs.write().unwrap().remove_sender(address);
+            let removed_dexvm = dexvm_pool.as_ref().map_or(0, |pool| pool.remove_sender(&address));
+
+            tracing::info!(
+                "Reset {}: balance {}, nonce {}, counter {}, dropped {} pending transactions",
+                address,
+                balance,
+                nonce,
+                counter,
+                removed_evm + removed_dexvm
+            );
+            results.push(AccountResetResult {
+                address,
+                balance,
+                nonce: U64::from(nonce),
+                counter: U64::from(counter),
+                removed_pending: U64::from(removed_evm + removed_dexvm),
+            });
+        }
+
+        Ok(results)
+    }
+
+    async fn subscribe_counters(
+        &self,
+        pending: PendingSubscriptionSink,
+        addresses: Vec<Address>,
+        from_block: Option<U64>,
+    ) -> SubscriptionResult {
+        // Subscribe before reading the head, so no stored block falls in between
+        let mut events = self.chain_events.subscribe();
+        let head = self.block_store.latest_block_number();
+        if let Err(message) = check_counter_subscription(&addresses, from_block, head) {
+            pending.reject(ErrorObjectOwned::owned(INVALID_PARAMS_CODE, message, None::<()>)).await;
+            return Ok(());
+        }
+        let sink = pending.accept().await?;
+        let addresses: HashSet<Address> = addresses.into_iter().collect();
+
+        // Next block whose updates are due
+        let mut next = from_block.map_or(head + 1, |from| from.to());
+        send_counter_updates(&sink, self.stored_counter_updates(&addresses, next, head)).await?;
+        next = next.max(head + 1);
+
+        loop {
+            let event = tokio::select! {
+                _ = sink.closed() => return Ok(()),
+                event = events.recv() => event,
+            };
+            match event {
+                // Blocks missed while lagging are read back along with this one
+                Ok(ChainEvent::Committed { block, .. }) => {
+                    let number = block.number.to();
+                    let updates = self.stored_counter_updates(&addresses, next, number);
+                    send_counter_updates(&sink, updates).await?;
+                    next = next.max(number + 1);
+                }
+                // The blocks replacing it are sent when they are stored
+                Ok(reverted @ ChainEvent::Reverted { .. }) => {
+                    next = next.min(reverted.block_number());
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::debug!("Counter subscription missed {} chain events", missed);
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl NetApiServer for EvmRpcServer {
+    /// The chain ID in decimal, the same value `eth_chainId` returns in hex (EIP-695)
     async fn version(&self) -> RpcResult<String> {
         Ok(self.chain_id.to_string())
     }
@@ -488,18 +1966,23 @@ impl NetApiServer for EvmRpcServer {
         Ok(true)
     }
 
+    /// Connected peers, or zero without a node status (e.g. P2P disabled)
     async fn peer_count(&self) -> RpcResult<U64> {
-        Ok(U64::from(0))
+        let status = self.node_status.read().unwrap();
+        Ok(U64::from(status.as_ref().map_or(0, |status| status.peer_count())))
     }
 }
 
-/// Start EVM RPC server
+/// Start EVM RPC server, writing audited calls to `audit` if set
+///
+/// Returns the address it listens on, which has the port picked when `port` is 0.
 pub async fn start_evm_rpc_server(
     chain_id: u64,
-    state_store: Arc<StateStore>,
-    block_store: Arc<BlockStore>,
+    state_store: Arc<dyn StateReader>,
+    block_store: Arc<dyn BlockReader>,
     port: u16,
-) -> eyre::Result<(ServerHandle, Arc<EvmRpcServer>)> {
+    audit: Option<RpcAuditLog>,
+) -> eyre::Result<(ServerHandle, Arc<EvmRpcServer>, SocketAddr)> {
     let server = EvmRpcServer::new(chain_id, state_store, block_store);
     let server = Arc::new(server);
 
@@ -509,27 +1992,50 @@ pub async fn start_evm_rpc_server(
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([HeaderName::from_static(SYNCING_HEADER)]);
+
+    // While the node is far behind its peers, responses say so if the sync gate warns
+    let syncing = Arc::clone(&server);
+    let sync_warning = SetResponseHeaderLayer::overriding(
+        HeaderName::from_static(SYNCING_HEADER),
+        move |_: &HttpResponse| syncing.syncing_header(),
+    );
+
+    // Cheap and expensive methods get separate concurrency limits
+    // Calls are audited before queueing, so the logged latency is what the client saw
+    let limits = RpcPriorityLimits::new(RpcPriorityConfig::default());
+    let rpc_middleware = RpcServiceBuilder::new()
+        .layer_fn(move |service| RpcAuditLog::layer(audit.clone(), service))
+        .layer_fn(move |service| limits.layer(service));
 
     let server_builder = ServerBuilder::default()
-        .set_http_middleware(tower::ServiceBuilder::new().layer(cors))
+        .set_http_middleware(tower::ServiceBuilder::new().layer(cors).layer(sync_warning))
+        .set_rpc_middleware(rpc_middleware)
         .build(addr)
         .await?;
+    let addr = server_builder.local_addr()?;
 
-    let server_clone = Arc::clone(&server);
-    let rpc_module = {
-        let mut module = jsonrpsee::RpcModule::new(());
-        module.merge(EthApiServer::into_rpc(server_clone.as_ref().clone()))?;
-        module.merge(Web3ApiServer::into_rpc(server_clone.as_ref().clone()))?;
-        module.merge(NetApiServer::into_rpc(server_clone.as_ref().clone()))?;
-        module
-    };
-
-    let handle = server_builder.start(rpc_module);
+    let handle = server_builder.start(rpc_module(&server)?);
 
     tracing::info!("EVM JSON-RPC server listening on {}", addr);
 
-    Ok((handle, server))
+    Ok((handle, server, addr))
+}
+
+/// All JSON-RPC namespaces served by `server`
+///
+/// Tests can call it in-process with `RpcModule::raw_json_request`.
+pub fn rpc_module(
+    server: &EvmRpcServer,
+) -> Result<jsonrpsee::RpcModule<()>, jsonrpsee::core::RegisterMethodError> {
+    let mut module = jsonrpsee::RpcModule::new(());
+    module.merge(EthApiServer::into_rpc(server.clone()))?;
+    module.merge(Web3ApiServer::into_rpc(server.clone()))?;
+    module.merge(NetApiServer::into_rpc(server.clone()))?;
+    module.merge(AdminApiServer::into_rpc(server.clone()))?;
+    module.merge(DexApiServer::into_rpc(server.clone()))?;
+    Ok(module)
 }
 
 impl Clone for EvmRpcServer {
@@ -540,7 +2046,822 @@ impl Clone for EvmRpcServer {
             block_store: Arc::clone(&self.block_store),
             pending_txs: Arc::clone(&self.pending_txs),
             receipts: Arc::clone(&self.receipts),
+            receipt_notify: Arc::clone(&self.receipt_notify),
             tx_broadcast_sender: Arc::clone(&self.tx_broadcast_sender),
+            storage: Arc::clone(&self.storage),
+            mempool_config: Arc::clone(&self.mempool_config),
+            dexvm_executor: Arc::clone(&self.dexvm_executor),
+            validator: Arc::clone(&self.validator),
+            quarantine: Arc::clone(&self.quarantine),
+            pool_drops: Arc::clone(&self.pool_drops),
+            read_only: Arc::clone(&self.read_only),
+            chain_events: self.chain_events.clone(),
+            dexvm_pool: Arc::clone(&self.dexvm_pool),
+            dev_mode: Arc::clone(&self.dev_mode),
+            node_status: Arc::clone(&self.node_status),
+            features: Arc::clone(&self.features),
+            backups: Arc::clone(&self.backups),
+            starting_block: self.starting_block,
+            sync_gate: Arc::clone(&self.sync_gate),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{Receipt, TxLegacy};
+    use dex_dexvm::COUNTER_PRECOMPILE_ADDRESS;
+    use alloy_primitives::{Log as PrimitiveLog, LogData, Signature, TxKind};
+    use crate::{evm_call::tests::RETURN_SLOT_0, sync_gate::DEFAULT_MAX_SYNC_LAG};
+    use dex_storage::BlockWriter;
+
+    fn test_tx(nonce: u64) -> TransactionSigned {
+        priced_tx(nonce, 2_000_000_000)
+    }
+
+    fn priced_tx(nonce: u64, gas_price: u128) -> TransactionSigned {
+        TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(Address::repeat_byte(0x11)),
+                nonce,
+                gas_price,
+                gas_limit: 21000,
+                chain_id: Some(1),
+                ..Default::default()
+            }
+            .into(),
+            Signature::test_signature(),
+        )
+    }
+
+    fn test_log() -> PrimitiveLog {
+        PrimitiveLog {
+            address: Address::repeat_byte(0x22),
+            data: LogData::new_unchecked(vec![B256::repeat_byte(0x33)], Bytes::default()),
+        }
+    }
+
+    #[test]
+    fn test_parse_block_tag() {
+        assert_eq!(parse_block_tag("latest", 9).unwrap(), 9);
+        assert_eq!(parse_block_tag("finalized", 9).unwrap(), 9);
+        assert_eq!(parse_block_tag("earliest", 9).unwrap(), 0);
+        assert_eq!(parse_block_tag("0x1f", 9).unwrap(), 31);
+        for tag in ["", "0x", "12", "0xzz", "newest"] {
+            let err = parse_block_tag(tag, 9).unwrap_err();
+            assert_eq!(err.code(), INVALID_PARAMS_CODE, "{:?}", tag);
+        }
+    }
+
+    #[test]
+    fn test_build_block_receipts() {
+        let txs = vec![test_tx(0), test_tx(1)];
+        let receipts = vec![
+            Receipt { status: true.into(), cumulative_gas_used: 21000, logs: vec![test_log()] },
+            Receipt {
+                status: true.into(),
+                cumulative_gas_used: 47000,
+                logs: vec![test_log(), test_log()],
+            },
+        ];
+
+        let rpc_receipts =
+            build_block_receipts(B256::ZERO, 7, BASE_FEE_PER_GAS, &txs, &receipts);
+
+        assert_eq!(rpc_receipts.len(), 2);
+        assert_eq!(rpc_receipts[0].gas_used, U64::from(21000));
+        assert_eq!(rpc_receipts[1].gas_used, U64::from(26000));
+        assert_eq!(rpc_receipts[1].cumulative_gas_used, U64::from(47000));
+        assert_eq!(rpc_receipts[0].effective_gas_price, U256::from(2_000_000_000u64));
+
+        // Log indexes continue across receipts in the same block
+        let log_indexes: Vec<_> = rpc_receipts
+            .iter()
+            .flat_map(|r| r.logs.iter().map(|l| l.log_index.to::<u64>()))
+            .collect();
+        assert_eq!(log_indexes, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_quarantined_transaction_refused() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        let tx = test_tx(0);
+        server.quarantine_transaction(*tx.tx_hash());
+        assert!(!server.add_pending_transaction_from_p2p(tx));
+        assert_eq!(server.pending_count(), 0);
+        assert_eq!(server.quarantined_count(), 1);
+
+        assert!(server.add_pending_transaction_from_p2p(test_tx(1)));
+    }
+
+    #[test]
+    fn test_value_to_dexvm_refused() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        let tx = TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(dex_dexvm::COUNTER_PRECOMPILE_ADDRESS),
+                value: U256::from(1),
+                gas_price: 2_000_000_000,
+                gas_limit: 100_000,
+                chain_id: Some(1),
+                ..Default::default()
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+        assert!(!server.add_pending_transaction_from_p2p(tx));
+        assert_eq!(server.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_covers_precompile_cost() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let mut data = vec![dex_dexvm::OP_INCREMENT];
+        data.extend_from_slice(&5u64.to_be_bytes());
+        let request = TransactionRequest {
+            from: Some(Address::repeat_byte(0x44)),
+            to: Some(COUNTER_PRECOMPILE_ADDRESS),
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(data.clone().into()),
+            nonce: None,
+        };
+        let required = PrecompileExecutor::required_gas(&data);
+        assert_eq!(server.estimate_gas(request.clone(), None).await.unwrap(), U64::from(required));
+
+        // Simulating against DexVM state gives the same cost
+        server.set_dexvm_executor(Arc::new(RwLock::new(DexVmExecutor::new(Default::default()))));
+        assert_eq!(server.estimate_gas(request, None).await.unwrap(), U64::from(required));
+    }
+
+    #[tokio::test]
+    async fn test_call_runs_contract_code() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let contract = Address::repeat_byte(0xcc);
+        storage.state.set_code(contract, RETURN_SLOT_0.to_vec().into()).unwrap();
+        storage.state.set_storage(contract, U256::ZERO, U256::from(7)).unwrap();
+        let request = TransactionRequest {
+            from: None,
+            to: Some(contract),
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: None,
+            nonce: None,
+        };
+
+        let data = server.call(request.clone(), None).await.unwrap();
+        assert_eq!(U256::from_be_slice(&data), U256::from(7));
+
+        // Too little gas to finish
+        let starved = TransactionRequest { gas: Some(U64::from(21_005)), ..request.clone() };
+        assert!(server.call(starved.clone(), None).await.is_err());
+
+        // The estimate is enough to run it, and a limit below that is refused
+        let estimate = server.estimate_gas(request.clone(), None).await.unwrap();
+        let enough = TransactionRequest { gas: Some(estimate), ..request };
+        assert!(server.call(enough, None).await.is_ok());
+        assert!(server.estimate_gas(starved, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call_revert_returns_code_3() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        server.set_dexvm_executor(Arc::new(RwLock::new(DexVmExecutor::new(Default::default()))));
+
+        // Decrement below zero
+        let mut data = vec![dex_dexvm::OP_DECREMENT];
+        data.extend_from_slice(&1u64.to_be_bytes());
+        let request = TransactionRequest {
+            from: Some(Address::repeat_byte(0x44)),
+            to: Some(dex_dexvm::COUNTER_PRECOMPILE_ADDRESS),
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(data.into()),
+            nonce: None,
+        };
+
+        let err = server.call(request.clone(), None).await.unwrap_err();
+        assert_eq!(err.code(), EXECUTION_REVERTED_CODE);
+        let revert_data: Bytes = serde_json::from_str(err.data().unwrap().get()).unwrap();
+        assert_eq!(revert_data[..4], [0x08, 0xc3, 0x79, 0xa0]);
+
+        let err = server.estimate_gas(request, None).await.unwrap_err();
+        assert_eq!(err.code(), EXECUTION_REVERTED_CODE);
+    }
+
+    #[tokio::test]
+    async fn test_compatibility_endpoints() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        assert_eq!(server.protocol_version().await.unwrap(), U64::from(68));
+        assert_eq!(server.hashrate().await.unwrap(), U64::ZERO);
+        assert!(!server.mining().await.unwrap());
+        assert_eq!(server.coinbase().await.unwrap(), Address::ZERO);
+
+        let validator = Address::repeat_byte(0x66);
+        server.set_validator(validator);
+        assert!(server.mining().await.unwrap());
+        assert_eq!(server.coinbase().await.unwrap(), validator);
+    }
+
+    #[tokio::test]
+    async fn test_method_namespaces() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(13337, storage.state.clone(), storage.blocks.clone());
+        let module = rpc_module(&server).unwrap();
+        let names: Vec<&str> = module.method_names().collect();
+
+        for name in ["eth_chainId", "net_version", "net_listening", "net_peerCount"] {
+            assert!(names.contains(&name), "{} not registered", name);
+        }
+        for name in &names {
+            let (namespace, method) = name.split_once('_').unwrap();
+            assert!(RPC_NAMESPACES.contains(&namespace), "{}", name);
+            // A namespace inside a method name means it was declared in the wrong trait
+            assert!(!method.contains('_'), "{}", name);
+        }
+
+        let peers: U64 = module.call("net_peerCount", [(); 0]).await.unwrap();
+        assert_eq!(peers, U64::ZERO);
+        let status = Arc::new(NodeStatus::new());
+        status.set_peer_count(3);
+        server.set_node_status(status);
+        let peers: U64 = module.call("net_peerCount", [(); 0]).await.unwrap();
+        assert_eq!(peers, U64::from(3));
+
+        let chain_id: U64 = module.call("eth_chainId", [(); 0]).await.unwrap();
+        let version: String = module.call("net_version", [(); 0]).await.unwrap();
+        assert_eq!(chain_id, U64::from(13337));
+        assert_eq!(version, "13337");
+        let (response, _) = module
+            .raw_json_request(r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#, 1)
+            .await
+            .unwrap();
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["result"], "0x3419");
+    }
+
+    #[tokio::test]
+    async fn test_client_capabilities() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let module = rpc_module(&server).unwrap();
+
+        // Every reported namespace serves methods
+        let capabilities: ClientCapabilities =
+            module.call("dex_clientCapabilities", [(); 0]).await.unwrap();
+        assert_eq!(capabilities.api_version, RPC_API_VERSION);
+        for namespace in &capabilities.namespaces {
+            let prefix = format!("{}_", namespace);
+            assert!(module.method_names().any(|name| name.starts_with(&prefix)), "{}", namespace);
+        }
+        assert_eq!(capabilities.features, ClientFeatures::default());
+
+        server.set_client_features(ClientFeatures {
+            receipts_persisted: true,
+            traces_available: true,
+            ..Default::default()
+        });
+        server.set_dev_mode(true);
+        let capabilities: ClientCapabilities =
+            module.call("dex_clientCapabilities", [(); 0]).await.unwrap();
+        assert!(capabilities.features.receipts_persisted);
+        assert!(capabilities.features.traces_available);
+        assert!(capabilities.features.dev_methods);
+        assert!(!capabilities.features.read_only);
+        assert_eq!(capabilities.features.archive_depth, U64::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_sync_gate() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let module = rpc_module(&server).unwrap();
+        let balance = || module.call::<_, U256>("eth_getBalance", (Address::ZERO,));
+        let syncing: Syncing = module.call("eth_syncing", [(); 0]).await.unwrap();
+        assert_eq!(syncing, Syncing::NotSyncing(false));
+
+        // Within the allowed lag state reads are answered plainly
+        let status = Arc::new(NodeStatus::new());
+        status.set_highest_peer_block(DEFAULT_MAX_SYNC_LAG);
+        server.set_node_status(Arc::clone(&status));
+        server.set_sync_gate(SyncGate { mode: SyncGateMode::Reject, ..Default::default() });
+        assert!(balance().await.is_ok());
+        let syncing: Syncing = module.call("eth_syncing", [(); 0]).await.unwrap();
+        assert!(matches!(syncing, Syncing::Progress(_)));
+
+        status.set_highest_peer_block(DEFAULT_MAX_SYNC_LAG + 1);
+        assert!(server.syncing_header().is_none());
+        let error = balance().await.unwrap_err().to_string();
+        assert!(error.contains("Node is syncing"), "{}", error);
+        assert!(server.get_code(Address::ZERO, None).await.is_err());
+        // Block data is still served
+        let number: U64 = module.call("eth_blockNumber", [(); 0]).await.unwrap();
+        assert_eq!(number, U64::ZERO);
+
+        server.set_sync_gate(SyncGate::default());
+        assert!(balance().await.is_ok());
+        assert_eq!(server.syncing_header().unwrap(), "0/17");
+    }
+
+    #[tokio::test]
+    async fn test_storage_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(DualvmStorage::new(dir.path()).unwrap());
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        assert!(server.storage_info().await.is_err());
+        server.set_storage(Arc::clone(&storage));
+
+        let info = server.storage_info().await.unwrap();
+        assert!(info.file_size_bytes > 0);
+        assert!(!info.tables.is_empty());
+        assert_eq!(info.growth_bytes_per_day, None);
+        assert_eq!(info.disk_budget_bytes, None);
+
+        // Budget and growth come from the node status
+        let status = Arc::new(NodeStatus::new());
+        status.storage_growth().set_budget(Some(info.file_size_bytes * 2));
+        status.storage_growth().record(0, info.file_size_bytes / 2);
+        status.storage_growth().record(86_400, info.file_size_bytes);
+        server.set_node_status(status);
+        let info = server.storage_info().await.unwrap();
+        assert_eq!(info.growth_bytes_per_day, Some((info.file_size_bytes / 2) as i64));
+        assert_eq!(info.days_until_budget, Some(2.0));
+    }
+
+    #[tokio::test]
+    async fn test_create_access_list() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        server.set_dexvm_executor(Arc::new(RwLock::new(DexVmExecutor::new(Default::default()))));
+
+        let from = Address::repeat_byte(0x44);
+        let mut data = vec![dex_dexvm::OP_INCREMENT];
+        data.extend_from_slice(&3u64.to_be_bytes());
+        let mut request = TransactionRequest {
+            from: Some(from),
+            to: Some(dex_dexvm::COUNTER_PRECOMPILE_ADDRESS),
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(data.into()),
+            nonce: None,
+        };
+
+        let result = server.create_access_list(request.clone(), None).await.unwrap();
+        assert!(result.error.is_none());
+        assert_eq!(
+            result.access_list,
+            vec![AccessListItem {
+                address: dex_dexvm::COUNTER_PRECOMPILE_ADDRESS,
+                storage_keys: vec![dex_primitives::counter_slot(from)],
+            }]
+        );
+
+        // A plain transfer touches only the recipient
+        let to = Address::repeat_byte(0x55);
+        request.to = Some(to);
+        request.data = None;
+        let result = server.create_access_list(request, None).await.unwrap();
+        assert_eq!(result.access_list, vec![AccessListItem { address: to, storage_keys: vec![] }]);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_transaction() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        let tx = test_tx(0);
+        let hash = *tx.tx_hash();
+
+        // Times out while the transaction is pending
+        assert!(server.wait_for_transaction(hash, Some(10)).await.unwrap().is_none());
+
+        let waiter = {
+            let server = server.clone();
+            tokio::spawn(async move { server.wait_for_transaction(hash, Some(5_000)).await })
+        };
+        tokio::task::yield_now().await;
+
+        let receipt = build_block_receipts(
+            B256::repeat_byte(0x01),
+            7,
+            BASE_FEE_PER_GAS,
+            &[tx],
+            &[Receipt { status: true.into(), cumulative_gas_used: 21000, logs: vec![] }],
+        )
+        .remove(0);
+        server.add_receipt(hash, receipt);
+
+        let included = waiter.await.unwrap().unwrap().expect("receipt");
+        assert_eq!(included.block_number, U64::from(7));
+        assert_eq!(included.status, U64::from(1));
+    }
+
+    #[tokio::test]
+    async fn test_get_block_stats() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        let stats = StoredBlockStats { evm_tx_count: 2, failed_tx_count: 1, ..Default::default() };
+        storage.blocks.store_block_stats(3, stats).unwrap();
+
+        let range = server.get_block_stats(U64::from(1), U64::from(5)).await.unwrap();
+        assert_eq!(range.len(), 1);
+        assert_eq!(range[0].number, U64::from(3));
+        assert_eq!(range[0].tx_count, U64::from(2));
+        assert_eq!(range[0].failed_tx_count, U64::from(1));
+
+        assert!(server.get_block_stats(U64::from(5), U64::from(1)).await.is_err());
+        let too_wide = U64::from(MAX_BLOCK_STATS_RANGE);
+        assert!(server.get_block_stats(U64::ZERO, too_wide).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_block_roots() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.hash = B256::repeat_byte(0x01);
+        block.evm_state_root = B256::repeat_byte(0x02);
+        block.dexvm_state_root = B256::repeat_byte(0x03);
+        block.combined_state_root = B256::repeat_byte(0x04);
+        storage.blocks.store_block(block.clone()).unwrap();
+
+        let roots = server.get_block_roots(U64::from(1)).await.unwrap().unwrap();
+        assert_eq!(roots, BlockRoots::from(&block));
+        assert_eq!(roots.dexvm_state_root, B256::repeat_byte(0x03));
+        assert!(server.get_block_roots(U64::from(2)).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_pool_limits() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        server.set_mempool_config(MempoolConfig { max_pool_count: 1, ..Default::default() });
+
+        assert!(server.add_pending_transaction_from_p2p(test_tx(0)));
+        // A full pool refuses a transaction paying no more, and evicts for one paying more
+        assert!(!server.add_pending_transaction_from_p2p(test_tx(1)));
+        assert!(server.add_pending_transaction_from_p2p(priced_tx(2, 3_000_000_000)));
+        assert_eq!(server.pending_count(), 1);
+        assert_eq!(server.pool_drops(), PoolDrops { expired: 0, evicted: 1, replaced: 0 });
+
+        // Everything has expired once the maximum age is zero
+        let config = MempoolConfig { max_tx_age: Duration::ZERO, ..Default::default() };
+        server.set_mempool_config(config);
+        std::thread::sleep(Duration::from_millis(5));
+        server.prune_pending_transactions();
+        assert_eq!(server.pending_count(), 0);
+        assert_eq!(server.pool_drops().expired, 1);
+    }
+
+    #[test]
+    fn test_replace_pending_transaction() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        assert!(server.add_pending_transaction_from_p2p(priced_tx(0, 2_000_000_000)));
+        // Known again, or underpriced for a replacement
+        assert!(!server.add_pending_transaction_from_p2p(priced_tx(0, 2_000_000_000)));
+        assert!(!server.add_pending_transaction_from_p2p(priced_tx(0, 2_100_000_000)));
+
+        let replacement = priced_tx(0, 2_200_000_000);
+        assert!(server.add_pending_transaction_from_p2p(replacement.clone()));
+        let pending = server.get_pending_transactions();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].hash, *replacement.tx_hash());
+        assert_eq!(server.pool_drops().replaced, 1);
+
+        // The replaced transaction does not come back when a block leaves it out
+        let original = PendingTransaction::new(priced_tx(0, 2_000_000_000), pending[0].from);
+        server.requeue_pending_transactions(vec![original]);
+        assert_eq!(server.pending_count(), 1);
+    }
+
+    #[test]
+    fn test_refused_replacement_keeps_original() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let (first, second) = (priced_tx(0, 3_000_000_000), test_tx(1));
+        let size = |tx: &TransactionSigned| alloy_rlp::Encodable::length(tx);
+        let max_pool_bytes = size(&first) + size(&second);
+        server.set_mempool_config(MempoolConfig { max_pool_bytes, ..Default::default() });
+        assert!(server.add_pending_transaction_from_p2p(first));
+        assert!(server.add_pending_transaction_from_p2p(second.clone()));
+
+        // A larger replacement needs room that only a better-paying transaction holds
+        let larger = TransactionSigned::new_unhashed(
+            TxLegacy {
+                to: TxKind::Call(Address::repeat_byte(0x11)),
+                nonce: 1,
+                gas_price: 2_200_000_000,
+                gas_limit: 30000,
+                chain_id: Some(1),
+                input: vec![0xff; 64].into(),
+                ..Default::default()
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+        assert!(!server.add_pending_transaction_from_p2p(larger));
+        let pending = server.get_pending_transactions();
+        assert_eq!(pending.len(), 2);
+        assert!(pending.iter().any(|tx| tx.hash == *second.tx_hash()));
+        assert_eq!(server.pool_drops(), PoolDrops::default());
+
+        // The replaced transaction's space counts as free for one of the same size
+        assert!(server.add_pending_transaction_from_p2p(priced_tx(1, 2_200_000_000)));
+        assert_eq!(server.pending_count(), 2);
+        assert_eq!(server.pool_drops(), PoolDrops { expired: 0, evicted: 0, replaced: 1 });
+    }
+
+    #[test]
+    fn test_rebroadcast_pending_transactions() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        assert!(server.add_pending_transaction_from_p2p(test_tx(0)));
+        assert!(server.add_pending_transaction_from_p2p(test_tx(1)));
+        assert_eq!(server.rebroadcast_pending_transactions(), 0);
+
+        // A full channel stops the round; the rest go out next time
+        let (sender, mut receiver) = mpsc::channel(1);
+        server.set_tx_broadcast_sender(sender);
+        assert_eq!(server.rebroadcast_pending_transactions(), 1);
+        assert_eq!(receiver.try_recv().unwrap(), alloy_rlp::encode(test_tx(0)));
+        assert_eq!(server.rebroadcast_pending_transactions(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reset_account() {
+        let storage = Arc::new(DualvmStorage::in_memory().unwrap());
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        server.set_storage(Arc::clone(&storage));
+        let executor = Arc::new(RwLock::new(DexVmExecutor::new(Default::default())));
+        server.set_dexvm_executor(Arc::clone(&executor));
+        let pool = Arc::new(DexVmPool::default());
+        server.set_dexvm_pool(Arc::clone(&pool));
+
+        assert!(server.add_pending_transaction_from_p2p(test_tx(0)));
+        let sender = server.get_pending_transactions()[0].from;
+        storage.state.set_nonce(sender, 5).unwrap();
+        storage.state.set_counter(sender, 9).unwrap();
+        executor.write().unwrap().set_counter(sender, 9);
+        let increment = dex_dexvm::DexVmTransaction {
+            from: sender,
+            operation: dex_dexvm::DexVmOperation::Increment(1),
+            nonce: 0,
+            signature: vec![],
+        };
+        pool.submit(increment).unwrap();
+
+        let reset = vec![AccountReset {
+            address: sender,
+            balance: Some(U256::from(100)),
+            ..Default::default()
+        }];
+        assert!(server.reset_account(reset.clone()).await.is_err());
+
+        server.set_dev_mode(true);
+        let results = server.reset_account(reset).await.unwrap();
+        assert_eq!(results[0].removed_pending, U64::from(2));
+        assert_eq!(storage.state.get_balance(&sender), U256::from(100));
+        assert_eq!(storage.state.get_nonce(&sender), 0);
+        assert_eq!(storage.state.get_counter(&sender), 0);
+        assert_eq!(executor.read().unwrap().pending_state().get_counter(&sender), 0);
+        assert_eq!(server.pending_count(), 0);
+        assert!(pool.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_by_hash() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        // Pending transactions have no block position
+        let pending = test_tx(1);
+        assert!(server.add_pending_transaction_from_p2p(pending.clone()));
+        let info = server.get_transaction_by_hash(*pending.tx_hash()).await.unwrap().unwrap();
+        assert_eq!((info.block_hash, info.block_number), (None, None));
+        assert_eq!(info.nonce, U64::from(1));
+        // EIP-155 folds chain id 1 into v
+        assert!(info.v == U64::from(37) || info.v == U64::from(38));
+
+        let mined = test_tx(0);
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.hash = B256::repeat_byte(0x01);
+        block.transaction_count = 1;
+        block.transaction_hashes = vec![*mined.tx_hash()];
+        let encoded = alloy_rlp::encode(&mined);
+        storage.blocks.store_transactions(&[(*mined.tx_hash(), encoded)]).unwrap();
+        storage.blocks.store_block(block.clone()).unwrap();
+
+        let info = server.get_transaction_by_hash(*mined.tx_hash()).await.unwrap().unwrap();
+        assert_eq!(info.block_hash, Some(block.hash));
+        assert_eq!(info.block_number, Some(U64::from(1)));
+        assert_eq!(info.transaction_index, Some(U64::from(0)));
+        assert_eq!(info.from, mined.recover_signer().unwrap());
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["type"], "0x0");
+        assert!(json.get("maxFeePerGas").is_none());
+
+        let unknown = server.get_transaction_by_hash(B256::repeat_byte(0x99)).await.unwrap();
+        assert!(unknown.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stored_receipt_matches_built_receipt() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+
+        let txs = vec![test_tx(0), test_tx(1)];
+        let receipts = vec![
+            Receipt { status: true.into(), cumulative_gas_used: 21000, logs: vec![test_log()] },
+            Receipt { status: false.into(), cumulative_gas_used: 47000, logs: vec![test_log()] },
+        ];
+        let hashes: Vec<B256> = txs.iter().map(|tx| *tx.tx_hash()).collect();
+
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.hash = B256::repeat_byte(0x01);
+        block.transaction_count = 2;
+        block.transaction_hashes = hashes.clone();
+        let encoded: Vec<_> =
+            txs.iter().map(|tx| (*tx.tx_hash(), alloy_rlp::encode(tx))).collect();
+        storage.blocks.store_transactions(&encoded).unwrap();
+        let stored: Vec<_> = hashes
+            .iter()
+            .zip(&receipts)
+            .enumerate()
+            .map(|(index, (hash, receipt))| {
+                let logs = receipt
+                    .logs
+                    .iter()
+                    .map(|log| dex_storage::StoredLog {
+                        address: log.address,
+                        topics: log.data.topics().to_vec(),
+                        data: log.data.data.to_vec(),
+                    })
+                    .collect();
+                let receipt = dex_storage::StoredReceipt {
+                    success: receipt.status.coerce_status(),
+                    cumulative_gas_used: receipt.cumulative_gas_used,
+                    transaction_index: index as u64,
+                    first_log_index: index as u64,
+                    logs,
+                };
+                (*hash, receipt)
+            })
+            .collect();
+        storage.blocks.store_receipts(&stored).unwrap();
+        storage.blocks.store_block(block.clone()).unwrap();
+
+        let built = build_block_receipts(block.hash, 1, BASE_FEE_PER_GAS, &txs, &receipts);
+        for (hash, expected) in hashes.iter().zip(built) {
+            let receipt = server.get_transaction_receipt(*hash).await.unwrap().unwrap();
+            assert_eq!(
+                serde_json::to_value(receipt).unwrap(),
+                serde_json::to_value(expected).unwrap()
+            );
+        }
+        let unknown = server.get_transaction_receipt(B256::repeat_byte(0x99)).await.unwrap();
+        assert!(unknown.is_none());
+
+        // Replicas refuse new transactions
+        server.set_read_only(true);
+        let raw = Bytes::from(alloy_rlp::encode(test_tx(2)));
+        assert!(server.send_raw_transaction(raw).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_size_limits() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        server.set_mempool_config(MempoolConfig {
+            max_tx_bytes: 1024,
+            max_input_bytes: 16,
+            ..Default::default()
+        });
+
+        // Refused by size alone: this is not even a transaction
+        let error = server.send_raw_transaction(Bytes::from(vec![0; 1025])).await.unwrap_err();
+        assert!(error.message().starts_with("Transaction too large"), "{}", error.message());
+
+        let with_input = |len: usize| {
+            TransactionSigned::new_unhashed(
+                TxLegacy {
+                    to: TxKind::Call(Address::repeat_byte(0x11)),
+                    gas_price: 2_000_000_000,
+                    gas_limit: 100_000,
+                    chain_id: Some(1),
+                    input: vec![1; len].into(),
+                    ..Default::default()
+                }
+                .into(),
+                Signature::test_signature(),
+            )
+        };
+        let sender = with_input(0).recover_signer().unwrap();
+        storage.state.set_balance(sender, U256::from(10).pow(U256::from(18))).unwrap();
+        let raw = Bytes::from(alloy_rlp::encode(with_input(17)));
+        let error = server.send_raw_transaction(raw).await.unwrap_err();
+        assert!(error.message().starts_with("Transaction input too large"), "{}", error.message());
+        assert!(!server.add_pending_transaction_from_p2p(with_input(17)));
+        assert!(server.add_pending_transaction_from_p2p(with_input(16)));
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_see_unwound_logs() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let module = rpc_module(&server).unwrap();
+        let mut heads = module.subscribe_unbounded("eth_subscribe", ["newHeads"]).await.unwrap();
+        let mut logs = module.subscribe_unbounded("eth_subscribe", ["logs"]).await.unwrap();
+
+        let hash = *test_tx(0).tx_hash();
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.hash = B256::repeat_byte(0x01);
+        block.transaction_count = 1;
+        block.transaction_hashes = vec![hash];
+        let log = dex_storage::StoredLog {
+            address: Address::repeat_byte(0x22),
+            topics: vec![B256::repeat_byte(0x33)],
+            data: vec![],
+        };
+        let receipt =
+            dex_storage::StoredReceipt { success: true, logs: vec![log], ..Default::default() };
+        storage.blocks.store_receipts(&[(hash, receipt)]).unwrap();
+        storage.blocks.store_block(block.clone()).unwrap();
+        server.notify_block(1);
+
+        let (head, _) = heads.next::<serde_json::Value>().await.unwrap().unwrap();
+        assert_eq!(head["hash"], serde_json::json!(block.hash));
+        let (log, _) = logs.next::<Log>().await.unwrap().unwrap();
+        assert_eq!(log.transaction_hash, hash);
+        assert!(!log.removed);
+
+        // Unwinding the block sends its logs again, marked removed
+        let unwound = storage.blocks.unwind_to(0).unwrap();
+        server.notify_unwind(&unwound);
+        let (log, _) = logs.next::<Log>().await.unwrap().unwrap();
+        assert_eq!(log.block_hash, block.hash);
+        assert!(log.removed);
+    }
+
+    #[tokio::test]
+    async fn test_counter_subscription_catches_up() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let server = EvmRpcServer::new(1, storage.state.clone(), storage.blocks.clone());
+        let module = rpc_module(&server).unwrap();
+        let followed = Address::repeat_byte(0x44);
+        let store_block = |number: u64, new_value: u64| {
+            let mut block = StoredBlock::genesis(1);
+            block.number = number;
+            block.hash = B256::repeat_byte(number as u8);
+            let operation = dex_storage::StoredDexVmOperation {
+                tx_hash: B256::repeat_byte(0x50 + number as u8),
+                address: followed,
+                op: 0,
+                amount: 1,
+                success: true,
+                new_value,
+            };
+            let receipts = dex_storage::StoredDexVmReceipts { operations: vec![operation] };
+            storage.blocks.store_dexvm_receipts(number, receipts).unwrap();
+            storage.blocks.store_block(block).unwrap();
+        };
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        store_block(1, 1);
+
+        // An empty address list is refused
+        let params = (Vec::<Address>::new(), None::<U64>);
+        assert!(module.subscribe_unbounded("dex_subscribeCounters", params).await.is_err());
+
+        // Block 1 is caught up on, block 2 arrives live
+        let params = (vec![followed], Some(U64::from(1)));
+        let mut updates =
+            module.subscribe_unbounded("dex_subscribeCounters", params).await.unwrap();
+        let (update, _) = updates.next::<CounterUpdate>().await.unwrap().unwrap();
+        assert_eq!((update.block_number, update.old_value, update.new_value), (U64::from(1), 0, 1));
+
+        store_block(2, 2);
+        server.notify_block(2);
+        let (update, _) = updates.next::<CounterUpdate>().await.unwrap().unwrap();
+        assert_eq!((update.block_number, update.old_value, update.new_value), (U64::from(2), 1, 2));
+    }
+}
@@ -3,16 +3,70 @@
 //! This crate provides RPC interfaces:
 //! - DexVM REST API (port 9845): Counter operations
 //! - EVM JSON-RPC (port 8545): Ethereum-compatible RPC
+//! - Counter change webhooks, registered through the REST API
+//! - `eth_subscribe` notifications of new heads and logs, including reorgs
 
 pub mod api;
+pub mod audit;
+pub mod evm_call;
 pub mod evm_rpc;
+pub mod mempool;
+pub mod priority;
+pub mod signed;
+pub mod status;
+pub mod subscriptions;
+pub mod sync_gate;
+pub mod webhooks;
 
 pub use api::{
-    CounterResponse, DecrementRequest, DexVmApi, HealthResponse, IncrementRequest,
-    OperationResponse, StateRootResponse,
+    BlockDexVmOperation, BlockResponse, CounterLabel, CounterLabelsResponse, CounterResponse,
+    DecrementRequest, DexVmApi, HealthResponse, IncrementRequest, LabeledCounterResponse,
+    RegisterWebhookRequest, StateRootResponse, SubmitOperation, SubmitTransactionRequest,
+    SubmitTransactionResponse,
 };
 
+pub use audit::{AuditRecord, RpcAuditConfig, RpcAuditLog, DEFAULT_AUDIT_METHODS};
+
+pub use evm_call::{EvmCall, EvmCallExecutor, StoreDatabase, CALL_GAS_CAP};
+
 pub use evm_rpc::{
-    start_evm_rpc_server, BlockInfo, EvmRpcServer, Log, PendingTransaction, TransactionReceipt,
-    TransactionRequest,
+    build_block_receipts, rpc_module, start_evm_rpc_server, AccessListItem, AccessListResult,
+    AccountReset, AccountResetResult, BlockInfo, BlockRoots, BlockStats, ClientCapabilities,
+    ClientFeatures, EvmRpcServer, Log, PendingTransaction, SiblingBlock, TransactionInfo,
+    TransactionReceipt, TransactionRequest, BASE_FEE_PER_GAS, CLIENT_VERSION,
+    DEFAULT_WAIT_TIMEOUT_MS, ETH_PROTOCOL_VERSION, EXECUTION_REVERTED_CODE, INVALID_PARAMS_CODE,
+    MAX_BLOCK_STATS_RANGE, MAX_WAIT_TIMEOUT_MS, RPC_API_VERSION, RPC_NAMESPACES,
+};
+
+pub use mempool::{
+    call_access_set, decode_revert_reason, encode_revert_reason, simulate_call,
+    simulate_transaction, split_by_nonce, CallOutcome, MempoolConfig, NonceSplit, PendingPool,
+    PoolDrops, Quarantine, SimulationError, StoreEvmState, DEFAULT_QUARANTINE_CAPACITY,
+};
+
+pub use priority::{MethodClass, RpcPriorityConfig, RpcPriorityLimits};
+
+pub use signed::{
+    operation_message, public_key_to_address, recover_operation_signer, sign_operation,
+};
+
+pub use status::{
+    HeadStatus, MempoolStatus, NodeStatus, ProductionStatus, StartupCheck, StatusResponse,
+    SyncStatus,
+    TxTimeBucket, TxTimeHistogram, TxTimeSummary, TX_TIME_BUCKETS_MICROS,
+};
+
+pub use subscriptions::{
+    counter_updates, ChainEvent, ChainEvents, CounterUpdate, FilterSet, LogFilter,
+    SubscriptionKind, CHAIN_EVENT_CAPACITY, MAX_COUNTER_CATCH_UP_BLOCKS,
+    MAX_COUNTER_SUBSCRIPTION_ADDRESSES,
+};
+
+pub use sync_gate::{
+    SyncGate, SyncGateMode, SyncProgress, Syncing, DEFAULT_MAX_SYNC_LAG, SYNCING_HEADER,
+};
+
+pub use webhooks::{
+    CounterChangeEvent, Webhook, WebhookConfig, WebhookDispatcher, DEFAULT_WEBHOOK_BACKOFF,
+    DEFAULT_WEBHOOK_RETRIES,
 };
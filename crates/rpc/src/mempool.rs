@@ -0,0 +1,1107 @@
+//! Mempool admission policy
+//!
+//! Transactions below the gas price floor, or from senders that already have
+//! too many pending transactions, are neither accepted nor relayed. Nor are
+//! transactions over the size limits: the encoded size is checked before a
+//! transaction is decoded, so oversized ones cost no decoding.
+//!
+//! Optionally, transactions are simulated against pending DexVM state, and
+//! EVM state for DexVM reads of it, and rejected if they would revert or run
+//! out of gas.
+//!
+//! Calls that send ETH into DexVM would fail on-chain, so they are refused too.
+//!
+//! Transactions the block builder had to drop (see [`Quarantine`]) are
+//! refused until they age out.
+//!
+//! The pool itself is bounded by count and encoded size. Transactions older
+//! than the configured age expire, and a full pool evicts its lowest-paying
+//! transactions for a better-paying newcomer, or refuses it.
+//!
+//! A sender has one pending transaction per nonce: a new one with the same
+//! nonce replaces it only if it raises the fees by the price bump. The
+//! [`PendingPool`] indexes transactions by sender and nonce and by hash, so
+//! admission looks them up without scanning the pool. Blocks take
+//! each sender's transactions that continue from its account nonce without a
+//! gap (see [`split_by_nonce`]); later ones wait in the pool for the gap to fill.
+
+use crate::evm_rpc::{PendingTransaction, BASE_FEE_PER_GAS};
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, Bytes, B256, U256};
+use dex_dexvm::{
+    record_labeled_counter_access, record_operation_access, sends_value_to_dexvm, DexVmExecutor,
+    DexVmState, EvmStateReader, PrecompileExecutor, COUNTER_PRECOMPILE_ADDRESS, DEXVM_VALUE_ERROR,
+    OP_TOP,
+};
+use dex_primitives::{system_handler, AccessSet, DexVmTransaction, SystemHandler};
+use dex_storage::StateReader;
+use reth_ethereum_primitives::TransactionSigned;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Default minimum gas price (matches the advertised `eth_gasPrice`)
+pub const DEFAULT_MIN_GAS_PRICE: u128 = BASE_FEE_PER_GAS as u128;
+
+/// Default minimum priority fee
+pub const DEFAULT_MIN_PRIORITY_FEE: u128 = 0;
+
+/// Default maximum pending transactions per sender
+pub const DEFAULT_MAX_PENDING_PER_SENDER: usize = 64;
+
+/// Default maximum number of pending transactions
+pub const DEFAULT_MAX_POOL_COUNT: usize = 10_000;
+
+/// Default maximum encoded size of all pending transactions
+pub const DEFAULT_MAX_POOL_BYTES: usize = 32 * 1024 * 1024;
+
+/// Default maximum encoded size of one transaction (geth's limit)
+pub const DEFAULT_MAX_TX_BYTES: usize = 128 * 1024;
+
+/// Default maximum input (calldata or init code) of one transaction
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 64 * 1024;
+
+/// Default fee increase a replacement transaction must pay, in percent
+pub const DEFAULT_PRICE_BUMP_PERCENT: u64 = 10;
+
+/// Default age after which a pending transaction expires, in minutes
+pub const DEFAULT_MAX_TX_AGE_MINS: u64 = 180;
+
+/// Default number of quarantined transaction hashes remembered
+pub const DEFAULT_QUARANTINE_CAPACITY: usize = 4_096;
+
+/// Gas charged for every transaction before execution
+const INTRINSIC_GAS: u64 = 21_000;
+
+/// Mempool admission limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MempoolConfig {
+    /// Minimum gas price (max fee per gas for EIP-1559 transactions)
+    pub min_gas_price: u128,
+    /// Minimum priority fee (the gas price for legacy transactions)
+    pub min_priority_fee: u128,
+    /// Maximum pending transactions from a single sender
+    pub max_pending_per_sender: usize,
+    /// Simulate transactions on admission and reject those that would fail
+    pub reject_failing: bool,
+    /// Maximum number of pending transactions
+    pub max_pool_count: usize,
+    /// Maximum encoded size of all pending transactions
+    pub max_pool_bytes: usize,
+    /// Age after which a pending transaction is dropped
+    pub max_tx_age: Duration,
+    /// Maximum encoded size of one transaction, checked before it is decoded
+    pub max_tx_bytes: usize,
+    /// Maximum input (calldata or init code) of one transaction
+    pub max_input_bytes: usize,
+    /// Fee increase, in percent, that replacing a pending transaction takes
+    pub price_bump_percent: u64,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            min_gas_price: DEFAULT_MIN_GAS_PRICE,
+            min_priority_fee: DEFAULT_MIN_PRIORITY_FEE,
+            max_pending_per_sender: DEFAULT_MAX_PENDING_PER_SENDER,
+            reject_failing: false,
+            max_pool_count: DEFAULT_MAX_POOL_COUNT,
+            max_pool_bytes: DEFAULT_MAX_POOL_BYTES,
+            max_tx_age: Duration::from_secs(DEFAULT_MAX_TX_AGE_MINS * 60),
+            max_tx_bytes: DEFAULT_MAX_TX_BYTES,
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            price_bump_percent: DEFAULT_PRICE_BUMP_PERCENT,
+        }
+    }
+}
+
+impl MempoolConfig {
+    /// Check an encoded transaction's size against the limit, before decoding it
+    pub fn check_encoded_size(&self, len: usize) -> Result<(), String> {
+        if len > self.max_tx_bytes {
+            return Err(format!(
+                "Transaction too large: {} bytes, maximum {}",
+                len, self.max_tx_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check a transaction's input against the limit
+    pub fn check_input_size(&self, tx: &TransactionSigned) -> Result<(), String> {
+        let len = tx.input().len();
+        if len > self.max_input_bytes {
+            return Err(format!(
+                "Transaction input too large: {} bytes, maximum {}",
+                len, self.max_input_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check a transaction's fees against the floor
+    pub fn check_fees(&self, tx: &TransactionSigned) -> Result<(), String> {
+        let gas_price = tx.max_fee_per_gas();
+        if gas_price < self.min_gas_price {
+            return Err(format!(
+                "Gas price too low: got {}, minimum {}",
+                gas_price, self.min_gas_price
+            ));
+        }
+
+        let priority_fee = tx.priority_fee_or_price();
+        if priority_fee < self.min_priority_fee {
+            return Err(format!(
+                "Priority fee too low: got {}, minimum {}",
+                priority_fee, self.min_priority_fee
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Check a sender's pending transaction count against the limit
+    pub fn check_sender_pending(&self, pending: usize) -> Result<(), String> {
+        if pending >= self.max_pending_per_sender {
+            return Err(format!(
+                "Too many pending transactions from sender (limit {})",
+                self.max_pending_per_sender
+            ));
+        }
+        Ok(())
+    }
+
+    /// Find the pending transaction `incoming` replaces, the sender's one with the same nonce
+    ///
+    /// Returns its hash. The replacement must raise both the max fee and the
+    /// priority fee by at least `price_bump_percent`. Transactions already
+    /// pending are refused.
+    pub fn check_replacement(
+        &self,
+        pending: &PendingPool,
+        incoming: &PendingTransaction,
+    ) -> Result<Option<B256>, String> {
+        if pending.contains(&incoming.hash) {
+            return Err(format!("Transaction {} already known", incoming.hash));
+        }
+        let Some(existing) = pending.get_by_nonce(incoming.from, incoming.tx.nonce()) else {
+            return Ok(None);
+        };
+
+        let (hash, existing) = (existing.hash, &existing.tx);
+        let bumped = |fee: u128| fee.saturating_mul(100 + self.price_bump_percent as u128) / 100;
+        let max_fee = bumped(existing.max_fee_per_gas());
+        let priority_fee = bumped(existing.priority_fee_or_price());
+        if incoming.tx.max_fee_per_gas() < max_fee ||
+            incoming.tx.priority_fee_or_price() < priority_fee
+        {
+            return Err(format!(
+                "Replacement transaction underpriced: fees must be at least {} (priority {})",
+                max_fee, priority_fee
+            ));
+        }
+        Ok(Some(hash))
+    }
+
+    /// Remove transactions received more than `max_tx_age` before `now`
+    pub fn expire(&self, pending: &mut PendingPool, now: Instant) -> Vec<PendingTransaction> {
+        pending.remove_where(|tx| now.saturating_duration_since(tx.received_at) > self.max_tx_age)
+    }
+
+    /// Make room for `incoming` within the pool limits
+    ///
+    /// Evicts the lowest-paying transactions, the most recent first among equal
+    /// fees, and returns them. The pool is left untouched and the newcomer
+    /// refused if it does not pay more than every transaction it would evict.
+    /// `replacing` is the transaction the newcomer replaces: its space counts as
+    /// free and it is never evicted, so the caller removes it only on success.
+    pub fn make_room(
+        &self,
+        pending: &mut PendingPool,
+        incoming: &PendingTransaction,
+        replacing: Option<B256>,
+    ) -> Result<Vec<PendingTransaction>, String> {
+        if incoming.size > self.max_pool_bytes {
+            return Err(format!(
+                "Transaction too large for the pool: {} bytes, limit {}",
+                incoming.size, self.max_pool_bytes
+            ));
+        }
+
+        let replaced = replacing.and_then(|hash| pending.get(&hash));
+        let mut count = pending.len() + 1 - replaced.map_or(0, |_| 1);
+        let mut bytes = pending.bytes() + incoming.size - replaced.map_or(0, |tx| tx.size);
+        if count <= self.max_pool_count && bytes <= self.max_pool_bytes {
+            return Ok(Vec::new());
+        }
+        let mut candidates: Vec<&PendingTransaction> =
+            pending.iter().filter(|tx| Some(tx.hash) != replacing).collect();
+        candidates.sort_by_key(|tx| (tx.gas_price(), Reverse(tx.received_at)));
+
+        let mut evict = Vec::new();
+        for tx in candidates {
+            if count <= self.max_pool_count && bytes <= self.max_pool_bytes {
+                break;
+            }
+            if tx.gas_price() >= incoming.gas_price() {
+                return Err(format!(
+                    "Transaction pool is full: gas price must exceed {}",
+                    tx.gas_price()
+                ));
+            }
+            count -= 1;
+            bytes -= tx.size;
+            evict.push(tx.hash);
+        }
+        if count > self.max_pool_count || bytes > self.max_pool_bytes {
+            return Err("Transaction pool is full".to_string());
+        }
+
+        Ok(evict.iter().filter_map(|hash| pending.remove(hash)).collect())
+    }
+}
+
+/// Pending EVM transactions, indexed by sender and nonce and by hash
+///
+/// A sender has at most one transaction per nonce. Iteration follows arrival
+/// order, with requeued transactions ahead of the rest.
+#[derive(Debug, Clone, Default)]
+pub struct PendingPool {
+    /// Transactions with their arrival position, by sender and nonce
+    by_nonce: BTreeMap<(Address, u64), (i64, PendingTransaction)>,
+    /// Sender and nonce of each transaction, by hash
+    by_hash: HashMap<B256, (Address, u64)>,
+    /// Sender and nonce of each transaction, by arrival position
+    arrivals: BTreeMap<i64, (Address, u64)>,
+    /// Encoded size of all transactions
+    bytes: usize,
+    /// Arrival positions of the next transaction added and requeued
+    next_back: i64,
+    next_front: i64,
+}
+
+impl PendingPool {
+    /// Number of transactions
+    pub fn len(&self) -> usize {
+        self.by_nonce.len()
+    }
+
+    /// Whether the pool is empty
+    pub fn is_empty(&self) -> bool {
+        self.by_nonce.is_empty()
+    }
+
+    /// Encoded size of all transactions, counted against the pool byte limit
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Whether a transaction is pending
+    pub fn contains(&self, hash: &B256) -> bool {
+        self.by_hash.contains_key(hash)
+    }
+
+    /// Pending transaction by hash
+    pub fn get(&self, hash: &B256) -> Option<&PendingTransaction> {
+        self.by_hash.get(hash).and_then(|key| self.by_nonce.get(key)).map(|(_, tx)| tx)
+    }
+
+    /// The sender's pending transaction with `nonce`
+    pub fn get_by_nonce(&self, sender: Address, nonce: u64) -> Option<&PendingTransaction> {
+        self.by_nonce.get(&(sender, nonce)).map(|(_, tx)| tx)
+    }
+
+    /// Number of the sender's pending transactions
+    pub fn sender_count(&self, sender: Address) -> usize {
+        self.by_nonce.range((sender, 0)..=(sender, u64::MAX)).count()
+    }
+
+    /// Add a transaction behind the others
+    ///
+    /// Returns the sender's transaction with the same nonce it replaced.
+    pub fn insert(&mut self, tx: PendingTransaction) -> Option<PendingTransaction> {
+        let replaced = self.remove_key((tx.from, tx.tx.nonce()));
+        let position = self.next_back;
+        self.next_back += 1;
+        self.insert_at(position, tx);
+        replaced
+    }
+
+    /// Put transactions back ahead of the others, keeping their order
+    ///
+    /// Transactions whose sender and nonce is pending again are dropped.
+    pub fn requeue(&mut self, txs: Vec<PendingTransaction>) {
+        for tx in txs.into_iter().rev() {
+            if self.by_nonce.contains_key(&(tx.from, tx.tx.nonce())) {
+                continue;
+            }
+            self.next_front -= 1;
+            self.insert_at(self.next_front, tx);
+        }
+    }
+
+    /// Remove a transaction by hash
+    pub fn remove(&mut self, hash: &B256) -> Option<PendingTransaction> {
+        let key = *self.by_hash.get(hash)?;
+        self.remove_key(key)
+    }
+
+    /// Remove every transaction of a sender, returning how many there were
+    pub fn remove_sender(&mut self, sender: Address) -> usize {
+        let keys: Vec<_> =
+            self.by_nonce.range((sender, 0)..=(sender, u64::MAX)).map(|(key, _)| *key).collect();
+        keys.iter().filter_map(|key| self.remove_key(*key)).count()
+    }
+
+    /// Remove and return the transactions matching `predicate`, in arrival order
+    pub fn remove_where(
+        &mut self,
+        mut predicate: impl FnMut(&PendingTransaction) -> bool,
+    ) -> Vec<PendingTransaction> {
+        let keys: Vec<_> = self
+            .arrivals
+            .values()
+            .filter(|key| self.by_nonce.get(key).is_some_and(|(_, tx)| predicate(tx)))
+            .copied()
+            .collect();
+        keys.into_iter().filter_map(|key| self.remove_key(key)).collect()
+    }
+
+    /// Pending transactions in arrival order
+    pub fn iter(&self) -> impl Iterator<Item = &PendingTransaction> + '_ {
+        self.arrivals.values().filter_map(|key| self.by_nonce.get(key)).map(|(_, tx)| tx)
+    }
+
+    /// Copy of the pending transactions in arrival order
+    pub fn to_vec(&self) -> Vec<PendingTransaction> {
+        self.iter().cloned().collect()
+    }
+
+    /// Remove every transaction
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn insert_at(&mut self, position: i64, tx: PendingTransaction) {
+        let key = (tx.from, tx.tx.nonce());
+        self.bytes += tx.size;
+        self.by_hash.insert(tx.hash, key);
+        self.arrivals.insert(position, key);
+        self.by_nonce.insert(key, (position, tx));
+    }
+
+    fn remove_key(&mut self, key: (Address, u64)) -> Option<PendingTransaction> {
+        let (position, tx) = self.by_nonce.remove(&key)?;
+        self.bytes -= tx.size;
+        self.by_hash.remove(&tx.hash);
+        self.arrivals.remove(&position);
+        Some(tx)
+    }
+}
+
+impl FromIterator<PendingTransaction> for PendingPool {
+    fn from_iter<I: IntoIterator<Item = PendingTransaction>>(txs: I) -> Self {
+        let mut pool = Self::default();
+        for tx in txs {
+            pool.insert(tx);
+        }
+        pool
+    }
+}
+
+/// Pending transactions dropped by the pool limits since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolDrops {
+    /// Dropped for exceeding the maximum age
+    pub expired: u64,
+    /// Evicted from a full pool by a better-paying transaction
+    pub evicted: u64,
+    /// Replaced by a transaction with the same sender and nonce and higher fees
+    pub replaced: u64,
+}
+
+/// Pending transactions split by whether their nonces let them run now
+#[derive(Debug, Default)]
+pub struct NonceSplit {
+    /// Each sender's transactions continuing from its account nonce, in nonce order
+    pub ready: Vec<PendingTransaction>,
+    /// Transactions behind a nonce gap
+    pub queued: Vec<PendingTransaction>,
+    /// Transactions whose nonce the account already used
+    pub stale: Vec<PendingTransaction>,
+}
+
+/// Split pending transactions against the senders' account nonces in `state`
+pub fn split_by_nonce(pending: Vec<PendingTransaction>, state: &dyn StateReader) -> NonceSplit {
+    let mut by_sender: BTreeMap<Address, Vec<PendingTransaction>> = BTreeMap::new();
+    for tx in pending {
+        by_sender.entry(tx.from).or_default().push(tx);
+    }
+
+    let mut split = NonceSplit::default();
+    for (sender, mut txs) in by_sender {
+        txs.sort_by_key(|tx| tx.tx.nonce());
+        let mut next = state.get_nonce(&sender);
+        for tx in txs {
+            let nonce = tx.tx.nonce();
+            if nonce < next {
+                split.stale.push(tx);
+            } else if nonce == next {
+                next += 1;
+                split.ready.push(tx);
+            } else {
+                split.queued.push(tx);
+            }
+        }
+    }
+    split
+}
+
+/// Hashes of transactions dropped by the block builder
+///
+/// Bounded; the oldest hash is forgotten first.
+#[derive(Debug, Clone)]
+pub struct Quarantine {
+    capacity: usize,
+    hashes: HashSet<B256>,
+    order: VecDeque<B256>,
+}
+
+impl Quarantine {
+    /// Create a quarantine remembering at most `capacity` hashes
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, hashes: HashSet::new(), order: VecDeque::new() }
+    }
+
+    /// Quarantine a transaction; returns false if it already was
+    pub fn insert(&mut self, hash: B256) -> bool {
+        if self.capacity == 0 || !self.hashes.insert(hash) {
+            return false;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Whether a transaction is quarantined
+    pub fn contains(&self, hash: &B256) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    /// Number of quarantined transactions
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether no transaction is quarantined
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+impl Default for Quarantine {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUARANTINE_CAPACITY)
+    }
+}
+
+/// Selector of the Solidity `Error(string)` revert payload
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Result of simulating a DexVM-touching call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallOutcome {
+    /// Whether execution succeeded
+    pub success: bool,
+    /// Return data (the resulting counter value)
+    pub return_data: Bytes,
+    /// Gas consumed, including the intrinsic cost
+    pub gas_used: u64,
+    /// Failure reason
+    pub error: Option<String>,
+}
+
+/// Why a simulated transaction would fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationError {
+    /// Gas limit is below what execution needs
+    OutOfGas { needed: u64, limit: u64 },
+    /// Execution reverted; `data` is the ABI-encoded revert reason
+    Reverted { reason: String, data: Bytes },
+    /// Contract execution stopped exceptionally, such as out of gas or on an invalid opcode
+    Halted(String),
+    /// The simulation itself failed
+    Internal(String),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfGas { needed, limit } => {
+                write!(f, "Out of gas: needs {}, limit {}", needed, limit)
+            }
+            Self::Reverted { reason, .. } => write!(f, "execution reverted: {}", reason),
+            Self::Halted(reason) => write!(f, "execution halted: {}", reason),
+            Self::Internal(e) => write!(f, "Simulation failed: {}", e),
+        }
+    }
+}
+
+/// ABI-encode a revert reason as Solidity's `Error(string)`
+pub fn encode_revert_reason(reason: &str) -> Bytes {
+    let padded_len = reason.len().div_ceil(32) * 32;
+    let mut data = Vec::with_capacity(4 + 64 + padded_len);
+    data.extend_from_slice(&ERROR_STRING_SELECTOR);
+    data.extend_from_slice(&U256::from(32).to_be_bytes::<32>());
+    data.extend_from_slice(&U256::from(reason.len()).to_be_bytes::<32>());
+    data.extend_from_slice(reason.as_bytes());
+    data.resize(4 + 64 + padded_len, 0);
+    data.into()
+}
+
+/// Decode a Solidity `Error(string)` revert payload
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    let payload = data.strip_prefix(&ERROR_STRING_SELECTOR)?;
+    let len: usize = U256::try_from_be_slice(payload.get(32..64)?)?.try_into().ok()?;
+    let reason = payload.get(64..64usize.checked_add(len)?)?;
+    String::from_utf8(reason.to_vec()).ok()
+}
+
+/// EVM state read by simulated DexVM operations, from the state store
+pub struct StoreEvmState<'a>(pub &'a dyn StateReader);
+
+impl EvmStateReader for StoreEvmState<'_> {
+    fn balance(&self, address: &Address) -> U256 {
+        self.0.get_balance(address)
+    }
+
+    fn storage(&self, address: &Address, slot: U256) -> U256 {
+        self.0.get_storage(address, slot)
+    }
+}
+
+/// Simulate a call to the DexVM router or counter precompile without applying it
+///
+/// Returns `None` for other targets, which have no DexVM side effects. Calls
+/// carrying value fail, as they do in a block. Counter
+/// operations only touch one of the caller's counters, so the simulation runs
+/// on a copy of that one entry; top-counter queries read every counter and run on a
+/// copy of the whole state.
+pub fn simulate_call(
+    from: Address,
+    to: Option<Address>,
+    value: U256,
+    input: &[u8],
+    dexvm_state: &DexVmState,
+    evm_state: &dyn EvmStateReader,
+) -> Result<Option<CallOutcome>, SimulationError> {
+    if sends_value_to_dexvm(to, value) {
+        return Ok(Some(CallOutcome {
+            success: false,
+            return_data: Bytes::new(),
+            gas_used: INTRINSIC_GAS,
+            error: Some(DEXVM_VALUE_ERROR.to_string()),
+        }));
+    }
+
+    let handler = system_handler(to);
+    let precompile = handler == Some(SystemHandler::CounterPrecompile);
+    let reads_all = precompile && input.first() == Some(&OP_TOP);
+    let mut scratch = if reads_all {
+        dexvm_state.clone()
+    } else {
+        let mut scratch = DexVmState::new();
+        // Router transactions only use the main counter
+        let label = if precompile {
+            PrecompileExecutor::counter_label(input)
+        } else {
+            B256::ZERO
+        };
+        scratch.set_labeled_counter(from, label, dexvm_state.get_labeled_counter(&from, label));
+        scratch
+    };
+
+    match handler {
+        Some(SystemHandler::DexVmRouter) => {
+            let Ok(dexvm_tx) = DexVmTransaction::decode_calldata(from, input) else {
+                    // Undecodable router calls are executed as plain EVM calls
+                return Ok(None);
+            };
+            let result = DexVmExecutor::new(scratch)
+                .execute_transaction_with_evm(&dexvm_tx, Some(evm_state))
+                .map_err(|e| SimulationError::Internal(e.to_string()))?;
+            let return_data = match result.evm_value {
+                Some(value) => value.to_be_bytes::<32>().to_vec(),
+                None => result.new_counter.to_be_bytes().to_vec(),
+            };
+            Ok(Some(CallOutcome {
+                success: result.success,
+                return_data: return_data.into(),
+                gas_used: result.gas_used,
+                error: result.error,
+            }))
+        }
+        Some(SystemHandler::CounterPrecompile) => {
+            let result = PrecompileExecutor::new()
+                .execute_with_dexvm(from, COUNTER_PRECOMPILE_ADDRESS, input, Some(&mut scratch))
+                .map_err(|e| SimulationError::Internal(e.to_string()))?;
+            Ok(Some(CallOutcome {
+                success: result.success,
+                return_data: result.return_data.into(),
+                gas_used: result.gas_used,
+                error: result.error,
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Accounts and slots a call touches, following the block executor's rules
+///
+/// `succeeded` is the simulated outcome; failed counter operations read the
+/// counter without writing it.
+pub fn call_access_set(
+    from: Address,
+    to: Option<Address>,
+    input: &[u8],
+    succeeded: bool,
+) -> AccessSet {
+    let mut access_set = AccessSet::new();
+
+    let handler = system_handler(to);
+    if handler == Some(SystemHandler::DexVmRouter) {
+        if let Ok(dexvm_tx) = DexVmTransaction::decode_calldata(from, input) {
+            record_operation_access(&mut access_set, from, dexvm_tx.operation, succeeded);
+            return access_set;
+        }
+    }
+
+    access_set.read_account(from);
+    access_set.write_account(from);
+    if handler == Some(SystemHandler::CounterPrecompile) {
+        let written = succeeded && PrecompileExecutor::writes_counter(input);
+        let label = PrecompileExecutor::counter_label(input);
+        record_labeled_counter_access(&mut access_set, from, label, written);
+    } else if let Some(to) = to {
+        access_set.read_account(to);
+        access_set.write_account(to);
+    }
+
+    access_set
+}
+
+impl CallOutcome {
+    /// Convert a failed outcome into a revert error
+    pub fn into_result(self) -> Result<Self, SimulationError> {
+        if self.success {
+            return Ok(self);
+        }
+        let reason = self.error.unwrap_or_else(|| "execution failed".to_string());
+        let data = encode_revert_reason(&reason);
+        Err(SimulationError::Reverted { reason, data })
+    }
+}
+
+/// Simulate a transaction against pending DexVM state without applying it
+///
+/// Only DexVM-touching transactions can revert here; plain transfers are
+/// covered by the nonce and balance checks.
+pub fn simulate_transaction(
+    tx: &TransactionSigned,
+    from: Address,
+    dexvm_state: &DexVmState,
+    evm_state: &dyn EvmStateReader,
+) -> Result<(), SimulationError> {
+    let limit = tx.gas_limit();
+    if limit < INTRINSIC_GAS {
+        return Err(SimulationError::OutOfGas { needed: INTRINSIC_GAS, limit });
+    }
+
+    let Some(outcome) =
+        simulate_call(from, tx.to(), tx.value(), tx.input(), dexvm_state, evm_state)?
+    else {
+        return Ok(());
+    };
+    if outcome.gas_used > limit {
+        return Err(SimulationError::OutOfGas { needed: outcome.gas_used, limit });
+    }
+    outcome.into_result().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{TxEip1559, TxLegacy};
+    use alloy_primitives::Signature;
+    use dex_primitives::DEXVM_ROUTER_ADDRESS;
+
+    /// EVM state with no accounts
+    struct NoEvm;
+
+    impl EvmStateReader for NoEvm {
+        fn balance(&self, _address: &Address) -> U256 {
+            U256::ZERO
+        }
+
+        fn storage(&self, _address: &Address, _slot: U256) -> U256 {
+            U256::ZERO
+        }
+    }
+
+    #[test]
+    fn test_quarantine_evicts_oldest() {
+        let mut quarantine = Quarantine::new(2);
+        assert!(quarantine.insert(B256::repeat_byte(1)));
+        assert!(!quarantine.insert(B256::repeat_byte(1)));
+        assert!(quarantine.insert(B256::repeat_byte(2)));
+        assert!(quarantine.insert(B256::repeat_byte(3)));
+
+        assert_eq!(quarantine.len(), 2);
+        assert!(!quarantine.contains(&B256::repeat_byte(1)));
+        assert!(quarantine.contains(&B256::repeat_byte(3)));
+    }
+
+    fn legacy_tx(gas_price: u128) -> TransactionSigned {
+        TransactionSigned::new_unhashed(
+            TxLegacy { gas_price, gas_limit: 21000, ..Default::default() }.into(),
+            Signature::test_signature(),
+        )
+    }
+
+    #[test]
+    fn test_gas_price_floor() {
+        let config = MempoolConfig::default();
+        assert!(config.check_fees(&legacy_tx(0)).is_err());
+        assert!(config.check_fees(&legacy_tx(DEFAULT_MIN_GAS_PRICE)).is_ok());
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let config = MempoolConfig { max_tx_bytes: 100, max_input_bytes: 10, ..Default::default() };
+        assert!(config.check_encoded_size(100).is_ok());
+        assert!(config.check_encoded_size(101).is_err());
+
+        let with_input = |len: usize| {
+            TransactionSigned::new_unhashed(
+                TxLegacy { input: vec![0; len].into(), ..Default::default() }.into(),
+                Signature::test_signature(),
+            )
+        };
+        assert!(config.check_input_size(&with_input(10)).is_ok());
+        let error = config.check_input_size(&with_input(11)).unwrap_err();
+        assert_eq!(error, "Transaction input too large: 11 bytes, maximum 10");
+    }
+
+    #[test]
+    fn test_priority_fee_floor() {
+        let config = MempoolConfig { min_gas_price: 0, min_priority_fee: 10, ..Default::default() };
+
+        let tx = TransactionSigned::new_unhashed(
+            TxEip1559 {
+                max_fee_per_gas: 1_000,
+                max_priority_fee_per_gas: 5,
+                gas_limit: 21000,
+                ..Default::default()
+            }
+            .into(),
+            Signature::test_signature(),
+        );
+        assert!(config.check_fees(&tx).is_err());
+        assert!(config.check_fees(&legacy_tx(10)).is_ok());
+    }
+
+    fn pending_at(gas_price: u128, received_at: Instant) -> PendingTransaction {
+        pending_nonce(0, gas_price, received_at)
+    }
+
+    /// A transaction of sender `0x42…` with `nonce`
+    fn pending_nonce(nonce: u64, gas_price: u128, received_at: Instant) -> PendingTransaction {
+        let tx = TransactionSigned::new_unhashed(
+            TxLegacy { nonce, gas_price, gas_limit: 21000, ..Default::default() }.into(),
+            Signature::test_signature(),
+        );
+        let tx = PendingTransaction::new(tx, Address::repeat_byte(0x42));
+        PendingTransaction { received_at, ..tx }
+    }
+
+    #[test]
+    fn test_expire_by_age() {
+        let config =
+            MempoolConfig { max_tx_age: Duration::from_secs(60), ..Default::default() };
+        let now = Instant::now();
+        let mut pending: PendingPool = [
+            pending_nonce(0, 1, now - Duration::from_secs(120)),
+            pending_nonce(1, 2, now - Duration::from_secs(30)),
+        ]
+        .into_iter()
+        .collect();
+
+        let expired = config.expire(&mut pending, now);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].gas_price(), 1);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending.to_vec()[0].gas_price(), 2);
+    }
+
+    #[test]
+    fn test_make_room_evicts_lowest_fee() {
+        let config = MempoolConfig { max_pool_count: 3, ..Default::default() };
+        let now = Instant::now();
+        let mut pending: PendingPool = [
+            pending_nonce(0, 5, now),
+            pending_nonce(1, 2, now - Duration::from_secs(2)),
+            pending_nonce(2, 2, now - Duration::from_secs(1)),
+        ]
+        .into_iter()
+        .collect();
+
+        // Not paying more than the cheapest transaction: refused, nothing evicted
+        assert!(config.make_room(&mut pending, &pending_nonce(3, 2, now), None).is_err());
+        assert_eq!(pending.len(), 3);
+
+        // The most recent of the two cheapest goes
+        let evicted = config.make_room(&mut pending, &pending_nonce(3, 3, now), None).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].received_at, now - Duration::from_secs(1));
+        assert_eq!(pending.len(), 2);
+        assert!(!pending.contains(&evicted[0].hash));
+
+        // Room left: nothing to evict
+        let evicted = config.make_room(&mut pending, &pending_nonce(3, 1, now), None).unwrap();
+        assert!(evicted.is_empty());
+    }
+
+    #[test]
+    fn test_replacement_needs_price_bump() {
+        let config = MempoolConfig::default();
+        let now = Instant::now();
+        let original = pending_at(100, now);
+        let pending: PendingPool = [original.clone()].into_iter().collect();
+
+        assert!(config.check_replacement(&pending, &pending_at(100, now)).is_err());
+        let error = config.check_replacement(&pending, &pending_at(109, now)).unwrap_err();
+        assert!(error.starts_with("Replacement transaction underpriced"));
+        let replacement = pending_at(110, now);
+        assert_eq!(config.check_replacement(&pending, &replacement), Ok(Some(original.hash)));
+
+        // Other senders are not replaced
+        let mut other = pending_at(1, now);
+        other.from = Address::repeat_byte(0x43);
+        assert_eq!(config.check_replacement(&pending, &other), Ok(None));
+    }
+
+    #[test]
+    fn test_split_by_nonce() {
+        use dex_storage::StateWriter;
+        let storage = dex_storage::DualvmStorage::in_memory().unwrap();
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        storage.state.set_nonce(alice, 1).unwrap();
+        let tx = |from, nonce| {
+            let tx = TransactionSigned::new_unhashed(
+                TxLegacy { nonce, gas_limit: 21000, ..Default::default() }.into(),
+                Signature::test_signature(),
+            );
+            PendingTransaction::new(tx, from)
+        };
+        let nonces = |txs: &[PendingTransaction]| -> Vec<(Address, u64)> {
+            txs.iter().map(|tx| (tx.from, tx.tx.nonce())).collect()
+        };
+
+        let split = split_by_nonce(
+            vec![tx(alice, 2), tx(alice, 0), tx(alice, 1), tx(alice, 4), tx(bob, 1)],
+            storage.state.as_ref(),
+        );
+        assert_eq!(nonces(&split.ready), vec![(alice, 1), (alice, 2)]);
+        assert_eq!(nonces(&split.queued), vec![(alice, 4), (bob, 1)]);
+        assert_eq!(nonces(&split.stale), vec![(alice, 0)]);
+    }
+
+    #[test]
+    fn test_make_room_by_size() {
+        let now = Instant::now();
+        let size = pending_at(1, now).size;
+        let config = MempoolConfig { max_pool_bytes: size * 2, ..Default::default() };
+        let mut pending: PendingPool =
+            [pending_nonce(0, 1, now), pending_nonce(1, 2, now)].into_iter().collect();
+
+        let evicted = config.make_room(&mut pending, &pending_nonce(2, 3, now), None).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].gas_price(), 1);
+        assert_eq!(pending.bytes(), size);
+
+        let tiny = MempoolConfig { max_pool_bytes: size - 1, ..Default::default() };
+        assert!(tiny.make_room(&mut PendingPool::default(), &pending_at(9, now), None).is_err());
+    }
+
+    #[test]
+    fn test_pending_pool_indexes_sender_and_nonce() {
+        let now = Instant::now();
+        let other = Address::repeat_byte(0x43);
+        let mut pool: PendingPool =
+            [pending_nonce(0, 1, now), pending_nonce(1, 1, now)].into_iter().collect();
+        pool.insert(PendingTransaction { from: other, ..pending_nonce(0, 2, now) });
+        assert_eq!((pool.len(), pool.sender_count(Address::repeat_byte(0x42))), (3, 2));
+
+        // Same sender and nonce: the newcomer takes the old one's place
+        let replacement = pending_nonce(1, 5, now);
+        let replaced = pool.insert(replacement.clone()).unwrap();
+        assert_eq!(replaced.gas_price(), 1);
+        assert!(!pool.contains(&replaced.hash));
+        assert_eq!(pool.get(&replacement.hash).unwrap().gas_price(), 5);
+        assert_eq!(pool.len(), 3);
+
+        // Requeued transactions go first, unless their nonce is taken again
+        let requeued = pending_nonce(2, 1, now);
+        pool.requeue(vec![requeued.clone(), pending_nonce(1, 1, now)]);
+        assert_eq!(pool.iter().next().unwrap().hash, requeued.hash);
+        assert_eq!(pool.get_by_nonce(Address::repeat_byte(0x42), 1).unwrap().gas_price(), 5);
+
+        assert_eq!(pool.remove_sender(Address::repeat_byte(0x42)), 3);
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.bytes(), pool.iter().map(|tx| tx.size).sum::<usize>());
+    }
+
+    #[test]
+    fn test_sender_pending_limit() {
+        let config = MempoolConfig { max_pending_per_sender: 2, ..Default::default() };
+        assert!(config.check_sender_pending(1).is_ok());
+        assert!(config.check_sender_pending(2).is_err());
+    }
+
+    #[test]
+    fn test_simulate_rejects_failing_decrement() {
+        let from = Address::repeat_byte(0x42);
+        let mut state = DexVmState::new();
+        state.set_counter(from, 5);
+
+        let counter_tx = |op: u8, amount: u64, gas_limit: u64| {
+            let mut input = vec![op];
+            input.extend_from_slice(&amount.to_be_bytes());
+            TransactionSigned::new_unhashed(
+                TxLegacy {
+                    to: COUNTER_PRECOMPILE_ADDRESS.into(),
+                    input: input.into(),
+                    gas_limit,
+                    ..Default::default()
+                }
+                .into(),
+                Signature::test_signature(),
+            )
+        };
+
+        assert!(simulate_transaction(&counter_tx(0x01, 5, 100_000), from, &state, &NoEvm).is_ok());
+        assert!(matches!(
+            simulate_transaction(&counter_tx(0x01, 6, 100_000), from, &state, &NoEvm),
+            Err(SimulationError::Reverted { .. })
+        ));
+        // Out of gas below the precompile cost
+        assert!(matches!(
+            simulate_transaction(&counter_tx(0x00, 1, 21_000), from, &state, &NoEvm),
+            Err(SimulationError::OutOfGas { .. })
+        ));
+        // Simulation leaves the pending state untouched
+        assert_eq!(state.get_counter(&from), 5);
+
+        assert!(simulate_transaction(&legacy_tx(0), from, &state, &NoEvm).is_ok());
+
+        // Top-counter queries see every counter, not just the caller's
+        let other = Address::repeat_byte(0x43);
+        state.set_counter(other, 9);
+        let top = counter_tx(OP_TOP, 2, 100_000);
+        let outcome = simulate_call(from, top.to(), U256::ZERO, top.input(), &state, &NoEvm)
+            .unwrap()
+            .unwrap();
+        assert_eq!(&outcome.return_data[..20], other.as_slice());
+
+        // Labeled operations see the caller's counter under that label
+        let label = B256::repeat_byte(7);
+        state.set_labeled_counter(from, label, 2);
+        let mut input = vec![dex_dexvm::OP_LABELED_DECREMENT];
+        input.extend_from_slice(label.as_slice());
+        input.extend_from_slice(&2u64.to_be_bytes());
+        let to = Some(COUNTER_PRECOMPILE_ADDRESS);
+        assert!(simulate_call(from, to, U256::ZERO, &input, &state, &NoEvm)
+            .unwrap()
+            .unwrap()
+            .success);
+        input[40] = 3;
+        assert!(!simulate_call(from, to, U256::ZERO, &input, &state, &NoEvm)
+            .unwrap()
+            .unwrap()
+            .success);
+
+        // Router EVM reads return the value read
+        let mut input = vec![3u8];
+        input.extend_from_slice(other.as_slice());
+        let to = Some(DEXVM_ROUTER_ADDRESS);
+        let outcome = simulate_call(from, to, U256::ZERO, &input, &state, &NoEvm).unwrap().unwrap();
+        assert!(outcome.success);
+        assert_eq!(outcome.return_data, Bytes::from(vec![0u8; 32]));
+    }
+
+    #[test]
+    fn test_call_access_set() {
+        let from = Address::repeat_byte(0x11);
+        let slot = (COUNTER_PRECOMPILE_ADDRESS, dex_primitives::counter_slot(from));
+
+        let mut increment = vec![dex_dexvm::OP_INCREMENT];
+        increment.extend_from_slice(&5u64.to_be_bytes());
+        let access_set =
+            call_access_set(from, Some(COUNTER_PRECOMPILE_ADDRESS), &increment, true);
+        assert!(access_set.accounts_written.contains(&from));
+        assert!(access_set.slots_written.contains(&slot));
+
+        // A failed call only reads the counter
+        let access_set =
+            call_access_set(from, Some(COUNTER_PRECOMPILE_ADDRESS), &increment, false);
+        assert!(access_set.slots_read.contains(&slot));
+        assert!(access_set.slots_written.is_empty());
+
+        // Labeled operations use the slot of their label
+        let label = B256::repeat_byte(7);
+        let mut labeled = vec![dex_dexvm::OP_LABELED_INCREMENT];
+        labeled.extend_from_slice(label.as_slice());
+        labeled.extend_from_slice(&5u64.to_be_bytes());
+        let access_set = call_access_set(from, Some(COUNTER_PRECOMPILE_ADDRESS), &labeled, true);
+        let labeled_slot =
+            (COUNTER_PRECOMPILE_ADDRESS, dex_primitives::labeled_counter_slot(from, label));
+        assert!(access_set.slots_written.contains(&labeled_slot));
+        assert!(!access_set.slots_read.contains(&slot));
+
+        // Router EVM reads only read the EVM account
+        let account = Address::repeat_byte(0x33);
+        let mut read = vec![3u8];
+        read.extend_from_slice(account.as_slice());
+        let access_set = call_access_set(from, Some(DEXVM_ROUTER_ADDRESS), &read, true);
+        assert!(access_set.accounts_read.contains(&account));
+        assert!(access_set.slots_read.is_empty() && access_set.accounts_written.is_empty());
+
+        let to = Address::repeat_byte(0x22);
+        let access_set = call_access_set(from, Some(to), &[], true);
+        assert!(access_set.accounts_written.contains(&to));
+        assert!(access_set.slots_read.is_empty());
+    }
+
+    #[test]
+    fn test_encode_revert_reason() {
+        let data = encode_revert_reason("nope");
+        assert_eq!(data.len(), 4 + 32 * 3);
+        assert_eq!(data[..4], ERROR_STRING_SELECTOR);
+        assert_eq!(data[4 + 31], 0x20);
+        assert_eq!(data[4 + 63], 4);
+        assert_eq!(&data[68..72], b"nope");
+
+        assert_eq!(decode_revert_reason(&data), Some("nope".to_string()));
+        assert_eq!(decode_revert_reason(&data[..70]), None);
+        assert_eq!(decode_revert_reason(&[0xde, 0xad]), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! JSON-RPC request prioritization
+//!
+//! Classifies incoming methods as cheap or expensive and gives each class its own
+//! concurrency limit, so a burst of heavy calls (eth_getLogs, eth_call, debug/trace)
+//! cannot starve lightweight calls like eth_blockNumber.
+
+use jsonrpsee::{server::middleware::rpc::RpcServiceT, types::Request, MethodResponse};
+use std::{future::Future, pin::Pin, sync::Arc};
+use tokio::sync::Semaphore;
+
+/// Default number of cheap calls served concurrently
+pub const DEFAULT_MAX_CHEAP_CALLS: usize = 256;
+
+/// Default number of expensive calls served concurrently
+pub const DEFAULT_MAX_EXPENSIVE_CALLS: usize = 8;
+
+/// Default number of long-poll calls waiting concurrently
+pub const DEFAULT_MAX_WAITING_CALLS: usize = 1024;
+
+/// Method cost class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodClass {
+    /// Constant-time lookups (block number, balance, chain id, ...)
+    Cheap,
+    /// Methods that execute code or scan ranges
+    Expensive,
+    /// Long-polls that mostly sleep until an event
+    Waiting,
+}
+
+impl MethodClass {
+    /// Classify a JSON-RPC method by name
+    pub fn classify(method: &str) -> Self {
+        if method.starts_with("debug_") || method.starts_with("trace_") {
+            return Self::Expensive;
+        }
+
+        match method {
+            "dex_waitForTransaction" => Self::Waiting,
+            "eth_getLogs" | "eth_call" | "eth_estimateGas" | "eth_createAccessList"
+            | "eth_feeHistory" | "dex_getBlockStats" | "admin_siblingBlocks"
+            | "admin_backup" => Self::Expensive,
+            _ => Self::Cheap,
+        }
+    }
+}
+
+/// Concurrency limits per method class
+#[derive(Debug, Clone, Copy)]
+pub struct RpcPriorityConfig {
+    /// Maximum cheap calls in flight
+    pub max_cheap_calls: usize,
+    /// Maximum expensive calls in flight
+    pub max_expensive_calls: usize,
+    /// Maximum long-poll calls in flight
+    pub max_waiting_calls: usize,
+}
+
+impl Default for RpcPriorityConfig {
+    fn default() -> Self {
+        Self {
+            max_cheap_calls: DEFAULT_MAX_CHEAP_CALLS,
+            max_expensive_calls: DEFAULT_MAX_EXPENSIVE_CALLS,
+            max_waiting_calls: DEFAULT_MAX_WAITING_CALLS,
+        }
+    }
+}
+
+/// Shared permit pools, one per method class
+#[derive(Debug, Clone)]
+pub struct RpcPriorityLimits {
+    cheap: Arc<Semaphore>,
+    expensive: Arc<Semaphore>,
+    waiting: Arc<Semaphore>,
+}
+
+impl RpcPriorityLimits {
+    /// Create permit pools from config
+    pub fn new(config: RpcPriorityConfig) -> Self {
+        Self {
+            cheap: Arc::new(Semaphore::new(config.max_cheap_calls.max(1))),
+            expensive: Arc::new(Semaphore::new(config.max_expensive_calls.max(1))),
+            waiting: Arc::new(Semaphore::new(config.max_waiting_calls.max(1))),
+        }
+    }
+
+    fn semaphore(&self, class: MethodClass) -> Arc<Semaphore> {
+        match class {
+            MethodClass::Cheap => Arc::clone(&self.cheap),
+            MethodClass::Expensive => Arc::clone(&self.expensive),
+            MethodClass::Waiting => Arc::clone(&self.waiting),
+        }
+    }
+
+    /// Wrap an RPC service with these limits
+    pub fn layer<S>(&self, service: S) -> PriorityService<S> {
+        PriorityService { service, limits: self.clone() }
+    }
+}
+
+/// RPC middleware that queues each call on its class's permit pool
+#[derive(Debug, Clone)]
+pub struct PriorityService<S> {
+    service: S,
+    limits: RpcPriorityLimits,
+}
+
+impl<'a, S> RpcServiceT<'a> for PriorityService<S>
+where
+    S: RpcServiceT<'a> + Clone + Send + Sync + 'a,
+{
+    type Future = Pin<Box<dyn Future<Output = MethodResponse> + Send + 'a>>;
+
+    fn call(&self, request: Request<'a>) -> Self::Future {
+        let class = MethodClass::classify(request.method_name());
+        let semaphore = self.limits.semaphore(class);
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // The semaphore is never closed, so acquiring can only fail if it were
+            let _permit = semaphore.acquire_owned().await.ok();
+            if class == MethodClass::Expensive {
+                tracing::trace!("Serving expensive RPC method {}", request.method_name());
+            }
+            service.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_methods() {
+        assert_eq!(MethodClass::classify("eth_blockNumber"), MethodClass::Cheap);
+        assert_eq!(MethodClass::classify("eth_getBalance"), MethodClass::Cheap);
+        assert_eq!(MethodClass::classify("eth_getLogs"), MethodClass::Expensive);
+        assert_eq!(MethodClass::classify("eth_call"), MethodClass::Expensive);
+        assert_eq!(MethodClass::classify("debug_traceTransaction"), MethodClass::Expensive);
+        assert_eq!(MethodClass::classify("trace_block"), MethodClass::Expensive);
+        assert_eq!(MethodClass::classify("dex_waitForTransaction"), MethodClass::Waiting);
+        assert_eq!(MethodClass::classify("dex_getBlockStats"), MethodClass::Expensive);
+        assert_eq!(MethodClass::classify("admin_siblingBlocks"), MethodClass::Expensive);
+        assert_eq!(MethodClass::classify("admin_backup"), MethodClass::Expensive);
+    }
+
+    #[test]
+    fn test_limits_are_independent() {
+        let limits = RpcPriorityLimits::new(RpcPriorityConfig {
+            max_cheap_calls: 4,
+            max_expensive_calls: 1,
+            max_waiting_calls: 1,
+        });
+
+        let expensive = limits.semaphore(MethodClass::Expensive);
+        let _held = expensive.clone().try_acquire_owned().unwrap();
+        assert!(expensive.try_acquire().is_err());
+
+        // Cheap pool is unaffected by a saturated expensive pool
+        assert_eq!(limits.semaphore(MethodClass::Cheap).available_permits(), 4);
+    }
+}
@@ -0,0 +1,370 @@
+//! Node status for the `GET /status` dashboard endpoint
+
+use crate::mempool::PoolDrops;
+use alloy_primitives::{Address, B256};
+use dex_storage::StorageGrowth;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// Upper bounds (microseconds) of the transaction execution time buckets
+pub const TX_TIME_BUCKETS_MICROS: [u64; 8] =
+    [100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 1_000_000];
+
+/// Node facts that are not stored anywhere else
+///
+/// Shared between the node, the P2P status tracker and the REST API.
+#[derive(Debug)]
+pub struct NodeStatus {
+    started_at: Instant,
+    validator: RwLock<Option<Address>>,
+    peer_count: AtomicUsize,
+    highest_peer_block: AtomicU64,
+    startup_check: RwLock<Option<StartupCheck>>,
+    tx_execution: Arc<TxTimeHistogram>,
+    storage_growth: Arc<StorageGrowth>,
+    /// Milliseconds after `started_at` the last produced block was finalized
+    last_block_ms: AtomicU64,
+    production_stalled: AtomicBool,
+    production_stalls: AtomicU64,
+}
+
+impl NodeStatus {
+    /// Start tracking status from now
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            validator: RwLock::new(None),
+            peer_count: AtomicUsize::new(0),
+            highest_peer_block: AtomicU64::new(0),
+            startup_check: RwLock::new(None),
+            tx_execution: Arc::new(TxTimeHistogram::default()),
+            storage_growth: Arc::new(StorageGrowth::default()),
+            last_block_ms: AtomicU64::new(0),
+            production_stalled: AtomicBool::new(false),
+            production_stalls: AtomicU64::new(0),
+        }
+    }
+
+    /// Seconds since the node started
+    pub fn uptime_secs(&self) -> u64 {
+        self.started_at.elapsed().as_secs()
+    }
+
+    /// Record the address this node produces blocks as
+    pub fn set_validator(&self, validator: Address) {
+        *self.validator.write().unwrap_or_else(|e| e.into_inner()) = Some(validator);
+    }
+
+    /// Validator address, if this node produces blocks
+    pub fn validator(&self) -> Option<Address> {
+        *self.validator.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record the number of connected peers
+    pub fn set_peer_count(&self, count: usize) {
+        self.peer_count.store(count, Ordering::Relaxed);
+    }
+
+    /// Number of connected peers
+    pub fn peer_count(&self) -> usize {
+        self.peer_count.load(Ordering::Relaxed)
+    }
+
+    /// Record the highest block number announced by a connected peer
+    ///
+    /// Set from the current peers' heads, so it drops when the peer that
+    /// announced the highest block disconnects.
+    pub fn set_highest_peer_block(&self, number: u64) {
+        self.highest_peer_block.store(number, Ordering::Relaxed);
+    }
+
+    /// Highest block number announced by a connected peer
+    pub fn highest_peer_block(&self) -> u64 {
+        self.highest_peer_block.load(Ordering::Relaxed)
+    }
+
+    /// Record the outcome of the startup consistency check
+    pub fn set_startup_check(&self, check: StartupCheck) {
+        *self.startup_check.write().unwrap_or_else(|e| e.into_inner()) = Some(check);
+    }
+
+    /// Outcome of the startup consistency check, if one ran
+    pub fn startup_check(&self) -> Option<StartupCheck> {
+        self.startup_check.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Record that this node finalized a block it produced, ending any stall
+    pub fn record_block_produced(&self) {
+        let elapsed = self.started_at.elapsed().as_millis() as u64;
+        self.last_block_ms.store(elapsed, Ordering::Relaxed);
+        self.production_stalled.store(false, Ordering::Relaxed);
+    }
+
+    /// Time since this node last finalized a block, or since it started
+    pub fn since_last_block(&self) -> Duration {
+        let last_block = Duration::from_millis(self.last_block_ms.load(Ordering::Relaxed));
+        self.started_at.elapsed().saturating_sub(last_block)
+    }
+
+    /// Mark block production as stalled; returns true if it was not already
+    pub fn set_production_stalled(&self) -> bool {
+        let newly = !self.production_stalled.swap(true, Ordering::Relaxed);
+        if newly {
+            self.production_stalls.fetch_add(1, Ordering::Relaxed);
+        }
+        newly
+    }
+
+    /// Whether block production is stalled, as detected by the watchdog
+    pub fn production_stalled(&self) -> bool {
+        self.production_stalled.load(Ordering::Relaxed)
+    }
+
+    /// Block production status, if this node produces blocks
+    pub fn production(&self) -> Option<ProductionStatus> {
+        self.validator().map(|_| ProductionStatus {
+            stalled: self.production_stalled(),
+            secs_since_last_block: self.since_last_block().as_secs(),
+            stalls: self.production_stalls.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Execution times of transactions in produced and synced blocks
+    pub fn tx_execution(&self) -> &Arc<TxTimeHistogram> {
+        &self.tx_execution
+    }
+
+    /// Database size samples and disk budget
+    pub fn storage_growth(&self) -> &Arc<StorageGrowth> {
+        &self.storage_growth
+    }
+}
+
+/// Block production health of a validator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductionStatus {
+    /// No block was finalized for longer than the watchdog allows
+    pub stalled: bool,
+    /// Seconds since the last finalized block, or since startup
+    pub secs_since_last_block: u64,
+    /// Stalls detected since startup
+    pub stalls: u64,
+}
+
+/// Histogram of per-transaction execution times
+#[derive(Debug, Default)]
+pub struct TxTimeHistogram {
+    /// Counts per [`TX_TIME_BUCKETS_MICROS`] bucket, plus one for slower transactions
+    buckets: [AtomicU64; TX_TIME_BUCKETS_MICROS.len() + 1],
+    total_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl TxTimeHistogram {
+    /// Record one transaction that took `elapsed` to execute
+    pub fn record(&self, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let bucket = TX_TIME_BUCKETS_MICROS.partition_point(|&bound| bound < micros);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.max_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Current counts
+    pub fn summary(&self) -> TxTimeSummary {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let buckets = counts
+            .iter()
+            .enumerate()
+            .map(|(i, &count)| TxTimeBucket {
+                le_micros: TX_TIME_BUCKETS_MICROS.get(i).copied(),
+                count,
+            })
+            .collect();
+        TxTimeSummary {
+            count: counts.iter().sum(),
+            total_micros: self.total_micros.load(Ordering::Relaxed),
+            max_micros: self.max_micros.load(Ordering::Relaxed),
+            buckets,
+        }
+    }
+}
+
+/// Snapshot of a [`TxTimeHistogram`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTimeSummary {
+    pub count: u64,
+    pub total_micros: u64,
+    pub max_micros: u64,
+    pub buckets: Vec<TxTimeBucket>,
+}
+
+/// Transactions that took at most `le_micros` and more than the previous bound
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTimeBucket {
+    /// Upper bound; `None` for the slowest bucket
+    pub le_micros: Option<u64>,
+    pub count: u64,
+}
+
+/// Whether the previous run shut down cleanly, and what startup checked if not
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupCheck {
+    pub clean_shutdown: bool,
+    /// Most recent blocks that passed the consistency check
+    pub blocks_checked: u64,
+    /// First inconsistency found
+    pub error: Option<String>,
+}
+
+impl StartupCheck {
+    /// Whether no inconsistency was found
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+impl Default for NodeStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `GET /status` response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusResponse {
+    pub version: String,
+    pub chain_id: u64,
+    pub uptime_secs: u64,
+    pub head: HeadStatus,
+    pub peers: usize,
+    pub sync: SyncStatus,
+    pub mempool: MempoolStatus,
+    pub validator: Option<Address>,
+    pub db_size_bytes: u64,
+    /// Growth of the database file, once the node has sampled it for an hour
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_growth_bytes_per_day: Option<i64>,
+    /// Days until the database fills `--disk-budget-gb` at its current growth
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_days_until_budget: Option<f64>,
+    /// Execution times of the transactions this node ran
+    pub tx_execution: TxTimeSummary,
+    /// Block production health, on validators
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub production: Option<ProductionStatus>,
+}
+
+/// Latest stored block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadStatus {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+}
+
+/// Sync progress against announced peer heads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub syncing: bool,
+    pub current_block: u64,
+    pub highest_block: u64,
+}
+
+impl SyncStatus {
+    /// Compare our head against the highest block peers announced
+    pub fn new(current_block: u64, highest_peer_block: u64) -> Self {
+        Self {
+            syncing: highest_peer_block > current_block,
+            current_block,
+            highest_block: current_block.max(highest_peer_block),
+        }
+    }
+}
+
+/// Pending transaction counts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MempoolStatus {
+    pub evm_pending: usize,
+    pub dexvm_pending: usize,
+    /// Transactions dropped by the block builder and refused since
+    pub quarantined: usize,
+    /// Pending transactions dropped by the pool's age and size limits
+    pub dropped: PoolDrops,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_status() {
+        let status = NodeStatus::new();
+        status.set_highest_peer_block(12);
+        assert_eq!(status.highest_peer_block(), 12);
+
+        let sync = SyncStatus::new(10, status.highest_peer_block());
+        assert!(sync.syncing);
+        assert_eq!(sync.highest_block, 12);
+
+        let sync = SyncStatus::new(15, status.highest_peer_block());
+        assert!(!sync.syncing);
+        assert_eq!(sync.highest_block, 15);
+
+        // The peer ahead of us left
+        status.set_highest_peer_block(7);
+        let sync = SyncStatus::new(10, status.highest_peer_block());
+        assert!(!sync.syncing);
+        assert_eq!(sync.highest_block, 10);
+    }
+
+    #[test]
+    fn test_production_stalls() {
+        let status = NodeStatus::new();
+        assert_eq!(status.production(), None);
+
+        status.set_validator(Address::repeat_byte(0x11));
+        assert!(status.set_production_stalled());
+        assert!(!status.set_production_stalled());
+        let production = status.production().unwrap();
+        assert!(production.stalled);
+        assert_eq!(production.stalls, 1);
+
+        status.record_block_produced();
+        assert!(!status.production_stalled());
+        assert!(status.since_last_block() < Duration::from_secs(1));
+        assert!(status.set_production_stalled());
+        assert_eq!(status.production().unwrap().stalls, 2);
+    }
+
+    #[test]
+    fn test_tx_time_histogram() {
+        let histogram = TxTimeHistogram::default();
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_micros(101));
+        histogram.record(Duration::from_secs(3));
+
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.max_micros, 3_000_000);
+        assert_eq!(summary.total_micros, 3_000_201);
+        assert_eq!(summary.buckets.len(), TX_TIME_BUCKETS_MICROS.len() + 1);
+        assert_eq!(summary.buckets[0], TxTimeBucket { le_micros: Some(100), count: 1 });
+        assert_eq!(summary.buckets[1], TxTimeBucket { le_micros: Some(500), count: 1 });
+        assert_eq!(summary.buckets.last(), Some(&TxTimeBucket { le_micros: None, count: 1 }));
+    }
+}
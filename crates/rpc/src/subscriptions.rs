@@ -0,0 +1,334 @@
+//! `eth_subscribe` chain notifications
+//!
+//! Stored blocks and unwound blocks are published to a [`ChainEvents`] channel
+//! as [`ChainEvent`]s. Each WebSocket subscription turns them into
+//! notifications: `newHeads` gets the header of every new canonical block,
+//! `logs` gets the matching logs of new blocks and, when blocks are unwound in
+//! a reorg, the same logs again with `removed: true`. The new canonical blocks
+//! that follow a reorg are published like any other, so their heads and logs
+//! are re-emitted.
+//!
+//! `dex_subscribeCounters` follows the counters of a list of addresses. Its
+//! updates come from the DexVM receipts stored with each block, so a client
+//! can start from an earlier block and get the updates it missed before the
+//! live ones, and a subscription that falls behind the events reads the
+//! blocks it skipped back from storage. Only nodes that execute blocks store
+//! DexVM receipts; synced blocks produce no updates.
+
+use crate::evm_rpc::{BlockInfo, Log};
+use alloy_primitives::{Address, B256, U64};
+use dex_primitives::DexVmOperation;
+use dex_storage::{StoredBlock, StoredDexVmReceipts, StoredReceipt, UnwoundBlock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+
+/// Chain events buffered per subscriber before it starts lagging
+pub const CHAIN_EVENT_CAPACITY: usize = 256;
+
+/// Most addresses one `dex_subscribeCounters` subscription may follow
+pub const MAX_COUNTER_SUBSCRIPTION_ADDRESSES: usize = 1_000;
+
+/// Most blocks below the head a `dex_subscribeCounters` catch-up may start at
+pub const MAX_COUNTER_CATCH_UP_BLOCKS: u64 = 10_000;
+
+/// What an `eth_subscribe` call listens for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionKind {
+    /// Headers of new canonical blocks
+    NewHeads,
+    /// Logs of new canonical blocks, and removed logs of unwound blocks
+    Logs,
+}
+
+/// One value or a list of values to match, as `eth_subscribe("logs")` takes them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FilterSet<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T: PartialEq> FilterSet<T> {
+    /// Whether `value` is in the set
+    pub fn contains(&self, value: &T) -> bool {
+        match self {
+            Self::One(one) => one == value,
+            Self::Many(many) => many.contains(value),
+        }
+    }
+}
+
+/// Log filter of a `logs` subscription
+///
+/// A log matches if it was emitted by one of `address` and, for each position
+/// in `topics`, its topic there is one of the listed values. `null` positions
+/// and missing fields match anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFilter {
+    #[serde(default)]
+    pub address: Option<FilterSet<Address>>,
+    #[serde(default)]
+    pub topics: Vec<Option<FilterSet<B256>>>,
+}
+
+impl LogFilter {
+    /// Whether `log` passes the filter
+    pub fn matches(&self, log: &Log) -> bool {
+        if self.address.as_ref().is_some_and(|address| !address.contains(&log.address)) {
+            return false;
+        }
+
+        self.topics.iter().enumerate().all(|(position, topics)| match topics {
+            Some(topics) => log.topics.get(position).is_some_and(|topic| topics.contains(topic)),
+            None => true,
+        })
+    }
+}
+
+/// A counter changed by a transaction of a stored block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterUpdate {
+    pub address: Address,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub block_number: U64,
+    pub block_hash: B256,
+    pub transaction_hash: B256,
+}
+
+/// Updates of the counters of `addresses` made by a block's DexVM operations
+///
+/// Failed operations and reads change nothing and produce no update.
+pub fn counter_updates(
+    block: &StoredBlock,
+    receipts: &StoredDexVmReceipts,
+    addresses: &HashSet<Address>,
+) -> Vec<CounterUpdate> {
+    receipts
+        .operations
+        .iter()
+        .filter(|operation| operation.success && operation.amount > 0)
+        .filter(|operation| addresses.contains(&operation.address))
+        .filter_map(|operation| {
+            let old_value = match operation.op {
+                op if op == DexVmOperation::Increment(0).code() => {
+                    operation.new_value.checked_sub(operation.amount)?
+                }
+                op if op == DexVmOperation::Decrement(0).code() => {
+                    operation.new_value.checked_add(operation.amount)?
+                }
+                _ => return None,
+            };
+            Some(CounterUpdate {
+                address: operation.address,
+                old_value,
+                new_value: operation.new_value,
+                block_number: U64::from(block.number),
+                block_hash: block.hash,
+                transaction_hash: operation.tx_hash,
+            })
+        })
+        .collect()
+}
+
+/// A change to the canonical chain
+#[derive(Debug, Clone)]
+pub enum ChainEvent {
+    /// A block was stored on top of the chain
+    Committed { block: BlockInfo, logs: Vec<Log> },
+    /// A block was unwound; its logs are marked removed
+    Reverted { block: BlockInfo, logs: Vec<Log> },
+}
+
+impl ChainEvent {
+    /// Number of the block stored or unwound
+    pub fn block_number(&self) -> u64 {
+        match self {
+            Self::Committed { block, .. } | Self::Reverted { block, .. } => block.number.to(),
+        }
+    }
+
+    /// Event for a stored block and the receipts of its transactions
+    pub fn committed(block: &StoredBlock, receipts: &[(B256, StoredReceipt)]) -> Self {
+        Self::Committed { block: block.clone().into(), logs: block_logs(block, receipts, false) }
+    }
+
+    /// Event for a block removed from the chain
+    pub fn reverted(unwound: &UnwoundBlock) -> Self {
+        Self::Reverted {
+            block: unwound.block.clone().into(),
+            logs: block_logs(&unwound.block, &unwound.receipts, true),
+        }
+    }
+
+    /// Notifications this event produces for a subscription
+    pub fn notifications(
+        &self,
+        kind: SubscriptionKind,
+        filter: Option<&LogFilter>,
+    ) -> Vec<serde_json::Value> {
+        let logs = match (self, kind) {
+            (Self::Committed { block, .. }, SubscriptionKind::NewHeads) => {
+                return serde_json::to_value(block).into_iter().collect();
+            }
+            // Heads are never retracted; the new canonical heads follow instead
+            (Self::Reverted { .. }, SubscriptionKind::NewHeads) => return vec![],
+            (Self::Committed { logs, .. }, SubscriptionKind::Logs) => logs,
+            (Self::Reverted { logs, .. }, SubscriptionKind::Logs) => logs,
+        };
+
+        logs.iter()
+            .filter(|log| filter.is_none_or(|filter| filter.matches(log)))
+            .filter_map(|log| serde_json::to_value(log).ok())
+            .collect()
+    }
+}
+
+/// RPC logs of a block, from the receipts of its transactions
+fn block_logs(block: &StoredBlock, receipts: &[(B256, StoredReceipt)], removed: bool) -> Vec<Log> {
+    receipts
+        .iter()
+        .flat_map(|(hash, receipt)| {
+            receipt.logs.iter().enumerate().map(move |(n, log)| Log {
+                address: log.address,
+                topics: log.topics.clone(),
+                data: log.data.clone().into(),
+                block_hash: block.hash,
+                block_number: U64::from(block.number),
+                transaction_hash: *hash,
+                transaction_index: U64::from(receipt.transaction_index),
+                log_index: U64::from(receipt.log_index(n)),
+                removed,
+            })
+        })
+        .collect()
+}
+
+/// Broadcast channel of chain events, shared by all subscriptions
+#[derive(Debug, Clone)]
+pub struct ChainEvents {
+    sender: broadcast::Sender<ChainEvent>,
+}
+
+impl Default for ChainEvents {
+    fn default() -> Self {
+        Self { sender: broadcast::channel(CHAIN_EVENT_CAPACITY).0 }
+    }
+}
+
+impl ChainEvents {
+    /// Publish an event to current subscribers; without any it is dropped
+    pub fn publish(&self, event: ChainEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+    use dex_storage::{StoredDexVmOperation, StoredLog};
+
+    const EMITTER: Address = address!("1111111111111111111111111111111111111111");
+
+    fn unwound_block() -> UnwoundBlock {
+        let tx_hash = B256::repeat_byte(0xaa);
+        let receipt = StoredReceipt {
+            success: true,
+            transaction_index: 0,
+            first_log_index: 0,
+            logs: vec![
+                StoredLog { address: EMITTER, topics: vec![B256::repeat_byte(1)], data: vec![] },
+                StoredLog { address: EMITTER, topics: vec![B256::repeat_byte(2)], data: vec![] },
+            ],
+            ..Default::default()
+        };
+        UnwoundBlock {
+            block: StoredBlock {
+                number: 7,
+                transaction_hashes: vec![tx_hash],
+                transaction_count: 1,
+                ..StoredBlock::genesis(1)
+            },
+            receipts: vec![(tx_hash, receipt)],
+        }
+    }
+
+    #[test]
+    fn test_log_filter() {
+        let logs = block_logs(&unwound_block().block, &unwound_block().receipts, false);
+
+        let filter: LogFilter = serde_json::from_value(serde_json::json!({
+            "address": EMITTER,
+            "topics": [[B256::repeat_byte(2), B256::repeat_byte(3)]],
+        }))
+        .unwrap();
+        let matched: Vec<_> = logs.iter().filter(|log| filter.matches(log)).collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].log_index, U64::from(1));
+
+        let other_emitter = LogFilter {
+            address: Some(FilterSet::Many(vec![Address::ZERO])),
+            ..Default::default()
+        };
+        assert!(!logs.iter().any(|log| other_emitter.matches(log)));
+        assert!(logs.iter().all(|log| LogFilter::default().matches(log)));
+    }
+
+    #[test]
+    fn test_reorg_notifications() {
+        let unwound = unwound_block();
+        let reverted = ChainEvent::reverted(&unwound);
+
+        // Unwound logs come back marked removed; heads are not retracted
+        let removed = reverted.notifications(SubscriptionKind::Logs, None);
+        assert_eq!(removed.len(), 2);
+        assert!(removed.iter().all(|log| log["removed"] == true));
+        assert!(reverted.notifications(SubscriptionKind::NewHeads, None).is_empty());
+
+        // The replacing block re-emits its head and logs
+        let committed = ChainEvent::committed(&unwound.block, &unwound.receipts);
+        let heads = committed.notifications(SubscriptionKind::NewHeads, None);
+        assert_eq!(heads.len(), 1);
+        assert_eq!(heads[0]["number"], "0x7");
+        let logs = committed.notifications(SubscriptionKind::Logs, None);
+        assert!(logs.iter().all(|log| log["removed"] == false));
+    }
+
+    #[test]
+    fn test_counter_updates() {
+        let block = unwound_block().block;
+        let (followed, other) = (EMITTER, Address::ZERO);
+        let operation = |address, op: DexVmOperation, success, new_value| StoredDexVmOperation {
+            tx_hash: B256::repeat_byte(0xaa),
+            address,
+            op: op.code(),
+            amount: op.amount(),
+            success,
+            new_value,
+        };
+        let receipts = StoredDexVmReceipts {
+            operations: vec![
+                operation(followed, DexVmOperation::Increment(5), true, 5),
+                operation(other, DexVmOperation::Increment(1), true, 1),
+                operation(followed, DexVmOperation::Decrement(9), false, 5),
+                operation(followed, DexVmOperation::Query, true, 5),
+                operation(followed, DexVmOperation::Decrement(2), true, 3),
+            ],
+        };
+
+        let updates = counter_updates(&block, &receipts, &HashSet::from([followed]));
+        let values: Vec<_> = updates.iter().map(|u| (u.old_value, u.new_value)).collect();
+        assert_eq!(values, vec![(0, 5), (5, 3)]);
+        assert_eq!(updates[0].block_number, U64::from(7));
+        assert!(counter_updates(&block, &receipts, &HashSet::new()).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+//! State reads while the node syncs
+//!
+//! A fullnode that just started answers from the state of its last stored
+//! block, which can be far behind its peers. While it is more than
+//! [`SyncGate::max_lag`] blocks behind the highest block peers announced,
+//! the EVM RPC methods that read the current state (`eth_getBalance`,
+//! `eth_getTransactionCount`, `eth_getCode`, `eth_getStorageAt`, `eth_call`,
+//! `eth_estimateGas`, `eth_createAccessList`) are answered as [`SyncGateMode`]
+//! says.
+
+use crate::status::SyncStatus;
+use alloy_primitives::U64;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Default blocks a node may be behind its peers and still answer state reads
+pub const DEFAULT_MAX_SYNC_LAG: u64 = 16;
+
+/// HTTP header set to `<current block>/<highest block>` in [`SyncGateMode::Warn`]
+pub const SYNCING_HEADER: &str = "x-dex-syncing";
+
+/// How state reads are answered while the node is behind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncGateMode {
+    /// Answer as if synced
+    Serve,
+    /// Answer, marking HTTP responses with [`SYNCING_HEADER`]
+    #[default]
+    Warn,
+    /// Fail with the `eth_syncing` progress as error data
+    Reject,
+}
+
+impl fmt::Display for SyncGateMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Serve => "serve",
+            Self::Warn => "warn",
+            Self::Reject => "reject",
+        })
+    }
+}
+
+impl FromStr for SyncGateMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "serve" => Ok(Self::Serve),
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("unknown sync gate mode {:?} (serve, warn or reject)", other)),
+        }
+    }
+}
+
+/// When and how state reads are gated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncGate {
+    pub mode: SyncGateMode,
+    /// Blocks the node may be behind the highest peer block before the gate applies
+    pub max_lag: u64,
+}
+
+impl Default for SyncGate {
+    fn default() -> Self {
+        Self { mode: SyncGateMode::default(), max_lag: DEFAULT_MAX_SYNC_LAG }
+    }
+}
+
+impl SyncGate {
+    /// Whether a node at `sync` is too far behind to answer state reads plainly
+    pub fn is_behind(&self, sync: &SyncStatus) -> bool {
+        sync.highest_block.saturating_sub(sync.current_block) > self.max_lag
+    }
+}
+
+/// `eth_syncing` result: `false`, or the progress while behind peers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Syncing {
+    Progress(SyncProgress),
+    NotSyncing(bool),
+}
+
+/// Sync progress in the `eth_syncing` format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncProgress {
+    /// Head when the node started
+    pub starting_block: U64,
+    pub current_block: U64,
+    pub highest_block: U64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gate_applies_beyond_max_lag() {
+        let gate = SyncGate { mode: SyncGateMode::Reject, max_lag: 4 };
+        assert!(!gate.is_behind(&SyncStatus::new(10, 14)));
+        assert!(gate.is_behind(&SyncStatus::new(10, 15)));
+        // Without peers the node is never behind
+        assert!(!gate.is_behind(&SyncStatus::new(10, 0)));
+
+        for mode in [SyncGateMode::Serve, SyncGateMode::Warn, SyncGateMode::Reject] {
+            assert_eq!(mode.to_string().parse::<SyncGateMode>(), Ok(mode));
+        }
+        assert!("block".parse::<SyncGateMode>().is_err());
+    }
+
+    #[test]
+    fn test_syncing_serializes_like_eth_syncing() {
+        assert_eq!(serde_json::to_value(Syncing::NotSyncing(false)).unwrap(), false);
+        let progress = Syncing::Progress(SyncProgress {
+            starting_block: U64::from(1),
+            current_block: U64::from(2),
+            highest_block: U64::from(16),
+        });
+        let json = serde_json::to_value(&progress).unwrap();
+        assert_eq!(json["highestBlock"], "0x10");
+        assert_eq!(serde_json::from_value::<Syncing>(json).unwrap(), progress);
+    }
+}
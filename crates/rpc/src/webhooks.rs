@@ -0,0 +1,298 @@
+//! Counter change webhooks
+//!
+//! After a block is stored, every registered URL receives one `POST` with a
+//! JSON array of the block's [`CounterChangeEvent`]s. Failed deliveries are
+//! retried with exponential backoff. Only plain `http://` URLs are supported.
+
+use alloy_primitives::{Address, B256};
+use dex_primitives::CounterChange;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Default number of retries after a failed delivery
+pub const DEFAULT_WEBHOOK_RETRIES: u32 = 5;
+
+/// Default delay before the first retry; doubled on each further retry
+pub const DEFAULT_WEBHOOK_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest delay between retries
+const MAX_WEBHOOK_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Timeout for one delivery attempt
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Webhook delivery settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// Retries after the first failed attempt
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self { max_retries: DEFAULT_WEBHOOK_RETRIES, initial_backoff: DEFAULT_WEBHOOK_BACKOFF }
+    }
+}
+
+impl WebhookConfig {
+    /// Delay before retry number `retry` (starting at 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff.saturating_mul(1 << retry.min(16)).min(MAX_WEBHOOK_BACKOFF)
+    }
+}
+
+/// A registered webhook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: u64,
+    pub url: String,
+}
+
+/// Counter change delivered to webhooks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CounterChangeEvent {
+    pub address: Address,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub block_number: u64,
+    pub tx_hash: B256,
+}
+
+impl CounterChangeEvent {
+    /// Event for a change included in `block_number`
+    pub fn new(change: &CounterChange, block_number: u64) -> Self {
+        Self {
+            address: change.address,
+            old_value: change.old_value,
+            new_value: change.new_value,
+            block_number,
+            tx_hash: change.tx_hash,
+        }
+    }
+}
+
+/// Parsed `http://host[:port]/path` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WebhookUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl WebhookUrl {
+    fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Unsupported webhook URL {}: only http:// is supported", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port.parse().map_err(|_| format!("Invalid webhook port in {}", url))?;
+                (host, port)
+            }
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("Missing webhook host in {}", url));
+        }
+
+        Ok(Self { host: host.to_string(), port, path: path.to_string() })
+    }
+}
+
+/// Sends counter change events to registered URLs
+#[derive(Debug)]
+pub struct WebhookDispatcher {
+    config: WebhookConfig,
+    hooks: RwLock<BTreeMap<u64, String>>,
+    next_id: AtomicU64,
+}
+
+impl WebhookDispatcher {
+    /// Create a dispatcher with no registered webhooks
+    pub fn new(config: WebhookConfig) -> Self {
+        Self { config, hooks: RwLock::new(BTreeMap::new()), next_id: AtomicU64::new(1) }
+    }
+
+    /// Register a URL, returning its webhook id
+    pub fn register(&self, url: &str) -> Result<Webhook, String> {
+        WebhookUrl::parse(url)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.hooks.write().unwrap_or_else(|e| e.into_inner()).insert(id, url.to_string());
+        Ok(Webhook { id, url: url.to_string() })
+    }
+
+    /// Remove a webhook; returns whether it existed
+    pub fn unregister(&self, id: u64) -> bool {
+        self.hooks.write().unwrap_or_else(|e| e.into_inner()).remove(&id).is_some()
+    }
+
+    /// Registered webhooks in id order
+    pub fn list(&self) -> Vec<Webhook> {
+        self.hooks
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|(id, url)| Webhook { id: *id, url: url.clone() })
+            .collect()
+    }
+
+    /// Deliver a stored block's counter changes to every webhook
+    ///
+    /// Deliveries run in background tasks, so block production never waits on them.
+    pub fn dispatch(&self, block_number: u64, changes: &[CounterChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        let hooks: Vec<String> =
+            self.hooks.read().unwrap_or_else(|e| e.into_inner()).values().cloned().collect();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let events: Vec<_> =
+            changes.iter().map(|change| CounterChangeEvent::new(change, block_number)).collect();
+        let body: Arc<str> = match serde_json::to_string(&events) {
+            Ok(body) => body.into(),
+            Err(e) => {
+                tracing::warn!("Failed to encode webhook events: {}", e);
+                return;
+            }
+        };
+
+        for url in hooks {
+            tokio::spawn(deliver(self.config, url, Arc::clone(&body)));
+        }
+    }
+}
+
+/// POST `body` to `url`, retrying with backoff until it succeeds or retries run out
+async fn deliver(config: WebhookConfig, url: String, body: Arc<str>) {
+    // Validated on registration
+    let Ok(target) = WebhookUrl::parse(&url) else { return };
+
+    for attempt in 0..=config.max_retries {
+        if attempt > 0 {
+            tokio::time::sleep(config.backoff(attempt - 1)).await;
+        }
+        match tokio::time::timeout(WEBHOOK_TIMEOUT, post_json(&target, &body)).await {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => tracing::debug!("Webhook {} attempt {} failed: {}", url, attempt + 1, e),
+            Err(_) => tracing::debug!("Webhook {} attempt {} timed out", url, attempt + 1),
+        }
+    }
+    tracing::warn!("Giving up on webhook {} after {} retries", url, config.max_retries);
+}
+
+/// Send one HTTP/1.1 POST and require a 2xx status
+async fn post_json(url: &WebhookUrl, body: &str) -> eyre::Result<()> {
+    let mut stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        url.path,
+        url.host,
+        url.port,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let status_line = response.split(|&b| b == b'\n').next().unwrap_or_default();
+    let status = String::from_utf8_lossy(status_line)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| eyre::eyre!("malformed HTTP response"))?;
+
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(eyre::eyre!("HTTP status {}", status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            WebhookUrl::parse("http://localhost:8080/hooks/counter").unwrap(),
+            WebhookUrl { host: "localhost".into(), port: 8080, path: "/hooks/counter".into() }
+        );
+        assert_eq!(
+            WebhookUrl::parse("http://example.com").unwrap(),
+            WebhookUrl { host: "example.com".into(), port: 80, path: "/".into() }
+        );
+        assert!(WebhookUrl::parse("https://example.com").is_err());
+        assert!(WebhookUrl::parse("http://:80/").is_err());
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let config = WebhookConfig { max_retries: 3, initial_backoff: Duration::from_millis(100) };
+        assert_eq!(config.backoff(0), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(400));
+        assert_eq!(config.backoff(40), MAX_WEBHOOK_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_retries_until_success() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let dispatcher = WebhookDispatcher::new(WebhookConfig {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(10),
+        });
+        let hook = dispatcher.register(&format!("http://127.0.0.1:{}/events", port)).unwrap();
+        assert_eq!(dispatcher.list(), vec![hook]);
+
+        let change = CounterChange {
+            address: Address::repeat_byte(0x11),
+            old_value: 1,
+            new_value: 4,
+            tx_hash: B256::repeat_byte(0x22),
+        };
+        dispatcher.dispatch(7, &[change]);
+
+        // Fail the first attempt, accept the retry
+        let mut bodies = Vec::new();
+        for status in ["500 Internal Server Error", "200 OK"] {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 4096];
+            let n = socket.read(&mut buf).await.unwrap();
+            bodies.push(String::from_utf8_lossy(&buf[..n]).to_string());
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+
+        let request = &bodies[1];
+        assert!(request.starts_with("POST /events HTTP/1.1"));
+        let body = request.split("\r\n\r\n").nth(1).unwrap();
+        let events: Vec<CounterChangeEvent> = serde_json::from_str(body).unwrap();
+        assert_eq!(events, vec![CounterChangeEvent::new(&change, 7)]);
+    }
+}
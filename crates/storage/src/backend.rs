@@ -0,0 +1,542 @@
+//! Storage backends
+//!
+//! [`StateStore`](crate::StateStore) and [`BlockStore`](crate::BlockStore)
+//! reach their tables through a [`Backend`]: MDBX for nodes with a datadir, or
+//! [`MemoryDb`] for unit tests and ephemeral dev nodes. The in-memory backend
+//! keeps one `BTreeMap` per table from encoded keys to compressed values, so
+//! entries are ordered and encoded exactly as MDBX stores them.
+
+use reth_db::DatabaseEnv;
+use reth_db_api::{
+    cursor::DbCursorRO,
+    database::Database,
+    table::{Compress, Decode, Decompress, Encode, Table},
+    transaction::{DbTx, DbTxMut},
+    DatabaseError,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Condvar, Mutex, RwLock},
+};
+
+/// Entries an in-memory walk copies out per table lock
+const WALK_CHUNK: usize = 256;
+
+/// Encoded key -> compressed value
+type RawTable = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Uncommitted writes to one table; `None` deletes the key
+type RawOverlay = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Where the DualVM tables are kept
+#[derive(Debug, Clone)]
+pub enum Backend {
+    /// MDBX environment on disk
+    Mdbx(Arc<DatabaseEnv>),
+    /// Tables held in memory, gone once the last handle is dropped
+    Memory(Arc<MemoryDb>),
+}
+
+impl Backend {
+    /// Empty in-memory backend
+    pub fn memory() -> Self {
+        Self::Memory(Arc::new(MemoryDb::default()))
+    }
+
+    /// The MDBX environment, unless the tables are in memory
+    pub fn mdbx(&self) -> Option<&Arc<DatabaseEnv>> {
+        match self {
+            Self::Mdbx(db) => Some(db),
+            Self::Memory(_) => None,
+        }
+    }
+
+    /// Begin a read transaction
+    pub fn tx(&self) -> Result<Tx, DatabaseError> {
+        Ok(match self {
+            Self::Mdbx(db) => Tx::Mdbx(db.tx()?),
+            Self::Memory(db) => Tx::Memory(Arc::clone(db)),
+        })
+    }
+
+    /// Begin a write transaction, waiting for an open one to finish
+    pub fn tx_mut(&self) -> Result<TxMut, DatabaseError> {
+        Ok(match self {
+            Self::Mdbx(db) => TxMut::Mdbx(db.tx_mut()?),
+            Self::Memory(db) => TxMut::Memory(MemoryTxMut::begin(Arc::clone(db))),
+        })
+    }
+}
+
+impl From<Arc<DatabaseEnv>> for Backend {
+    fn from(db: Arc<DatabaseEnv>) -> Self {
+        Self::Mdbx(db)
+    }
+}
+
+/// Typed table reads, shared by read and write transactions
+pub trait TableReader {
+    /// Value stored under `key`
+    fn get<T: Table>(&self, key: T::Key) -> Result<Option<T::Value>, DatabaseError>;
+
+    /// Visit entries in key order from `start` until `visit` returns false
+    fn walk<T: Table>(
+        &self,
+        start: Option<T::Key>,
+        visit: impl FnMut(T::Key, T::Value) -> bool,
+    ) -> Result<(), DatabaseError>;
+
+    /// Entry with the highest key
+    fn last<T: Table>(&self) -> Result<Option<(T::Key, T::Value)>, DatabaseError>;
+
+    /// Entries with keys in `range`, in key order
+    fn range<T: Table>(
+        &self,
+        range: impl RangeBounds<T::Key>,
+    ) -> Result<Vec<(T::Key, T::Value)>, DatabaseError> {
+        let start = match range.start_bound() {
+            Bound::Included(key) | Bound::Excluded(key) => Some(key.clone()),
+            Bound::Unbounded => None,
+        };
+        let mut entries = Vec::new();
+        self.walk::<T>(start, |key, value| {
+            let past_end = match range.end_bound() {
+                Bound::Included(end) => key > *end,
+                Bound::Excluded(end) => key >= *end,
+                Bound::Unbounded => false,
+            };
+            if !past_end && range.contains(&key) {
+                entries.push((key, value));
+            }
+            !past_end
+        })?;
+        Ok(entries)
+    }
+
+    /// Number of entries
+    fn entries<T: Table>(&self) -> Result<usize, DatabaseError> {
+        let mut count = 0;
+        self.walk::<T>(None, |_, _| {
+            count += 1;
+            true
+        })?;
+        Ok(count)
+    }
+}
+
+/// Read transaction
+pub enum Tx {
+    Mdbx(<DatabaseEnv as Database>::TX),
+    Memory(Arc<MemoryDb>),
+}
+
+impl TableReader for Tx {
+    fn get<T: Table>(&self, key: T::Key) -> Result<Option<T::Value>, DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.get::<T>(key),
+            Self::Memory(db) => db.get::<T>(&encode_key::<T>(key), None),
+        }
+    }
+
+    fn walk<T: Table>(
+        &self,
+        start: Option<T::Key>,
+        visit: impl FnMut(T::Key, T::Value) -> bool,
+    ) -> Result<(), DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => mdbx_walk::<T>(tx, start, visit),
+            Self::Memory(db) => db.walk::<T>(start, None, visit),
+        }
+    }
+
+    fn last<T: Table>(&self) -> Result<Option<(T::Key, T::Value)>, DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.cursor_read::<T>()?.last(),
+            Self::Memory(db) => db.last::<T>(None),
+        }
+    }
+}
+
+/// Write transaction; dropped without [`TxMut::commit`], its writes are discarded
+pub enum TxMut {
+    Mdbx(<DatabaseEnv as Database>::TXMut),
+    Memory(MemoryTxMut),
+}
+
+impl TxMut {
+    /// Store `value` under `key`
+    pub fn put<T: Table>(&self, key: T::Key, value: T::Value) -> Result<(), DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.put::<T>(key, value),
+            Self::Memory(tx) => {
+                tx.write::<T>(encode_key::<T>(key), Some(value.compress().into()));
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove `key`, returning whether it was present
+    pub fn delete<T: Table>(&self, key: T::Key) -> Result<bool, DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.delete::<T>(key, None),
+            Self::Memory(tx) => {
+                let key = encode_key::<T>(key);
+                let present = tx.with_overlay::<T, _>(|overlay| tx.db.get::<T>(&key, overlay))?;
+                tx.write::<T>(key, None);
+                Ok(present.is_some())
+            }
+        }
+    }
+
+    /// Make the transaction's writes visible
+    pub fn commit(self) -> Result<(), DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.commit().map(|_| ()),
+            Self::Memory(tx) => {
+                tx.commit();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl TableReader for TxMut {
+    fn get<T: Table>(&self, key: T::Key) -> Result<Option<T::Value>, DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.get::<T>(key),
+            Self::Memory(tx) => {
+                let key = encode_key::<T>(key);
+                tx.with_overlay::<T, _>(|overlay| tx.db.get::<T>(&key, overlay))
+            }
+        }
+    }
+
+    fn walk<T: Table>(
+        &self,
+        start: Option<T::Key>,
+        visit: impl FnMut(T::Key, T::Value) -> bool,
+    ) -> Result<(), DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => mdbx_walk::<T>(tx, start, visit),
+            Self::Memory(tx) => tx.db.walk::<T>(start, tx.overlay::<T>().as_ref(), visit),
+        }
+    }
+
+    fn last<T: Table>(&self) -> Result<Option<(T::Key, T::Value)>, DatabaseError> {
+        match self {
+            Self::Mdbx(tx) => tx.cursor_read::<T>()?.last(),
+            Self::Memory(tx) => tx.with_overlay::<T, _>(|overlay| tx.db.last::<T>(overlay)),
+        }
+    }
+}
+
+fn encode_key<T: Table>(key: T::Key) -> Vec<u8> {
+    key.encode().into()
+}
+
+fn mdbx_walk<T: Table>(
+    tx: &impl DbTx,
+    start: Option<T::Key>,
+    mut visit: impl FnMut(T::Key, T::Value) -> bool,
+) -> Result<(), DatabaseError> {
+    let mut cursor = tx.cursor_read::<T>()?;
+    for entry in cursor.walk(start)? {
+        let (key, value) = entry?;
+        if !visit(key, value) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Tables kept in memory
+///
+/// Like MDBX, there is one writer at a time and readers never see
+/// uncommitted writes. Unlike MDBX, a read transaction is not a snapshot: it
+/// sees writes committed after it began.
+#[derive(Debug, Default)]
+pub struct MemoryDb {
+    tables: RwLock<HashMap<&'static str, RawTable>>,
+    /// Whether a write transaction is open
+    writing: Mutex<bool>,
+    writer_done: Condvar,
+}
+
+impl MemoryDb {
+    fn get<T: Table>(
+        &self,
+        key: &[u8],
+        overlay: Option<&RawOverlay>,
+    ) -> Result<Option<T::Value>, DatabaseError> {
+        let pending = overlay.and_then(|overlay| overlay.get(key));
+        let tables = self.tables.read().unwrap();
+        let raw = match pending {
+            Some(pending) => pending.as_deref(),
+            None => tables.get(T::NAME).and_then(|table| table.get(key)).map(Vec::as_slice),
+        };
+        raw.map(T::Value::decompress).transpose()
+    }
+
+    /// Walk a table in chunks, so `visit` runs without the table lock held
+    fn walk<T: Table>(
+        &self,
+        start: Option<T::Key>,
+        overlay: Option<&RawOverlay>,
+        mut visit: impl FnMut(T::Key, T::Value) -> bool,
+    ) -> Result<(), DatabaseError> {
+        let mut from = match start {
+            Some(key) => Bound::Included(encode_key::<T>(key)),
+            None => Bound::Unbounded,
+        };
+        loop {
+            let (entries, next) = self.chunk(T::NAME, from, overlay);
+            for (key, value) in entries {
+                if !visit(T::Key::decode(&key)?, T::Value::decompress(&value)?) {
+                    return Ok(());
+                }
+            }
+            match next {
+                Some(next) => from = next,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Up to [`WALK_CHUNK`] committed entries from `from` with `overlay` applied,
+    /// and where the next chunk starts if the table goes on
+    #[allow(clippy::type_complexity)]
+    fn chunk(
+        &self,
+        table: &str,
+        from: Bound<Vec<u8>>,
+        overlay: Option<&RawOverlay>,
+    ) -> (Vec<(Vec<u8>, Vec<u8>)>, Option<Bound<Vec<u8>>>) {
+        let tables = self.tables.read().unwrap();
+        let mut merged: RawOverlay = tables
+            .get(table)
+            .map(|table| {
+                table
+                    .range((from.clone(), Bound::Unbounded))
+                    .take(WALK_CHUNK)
+                    .map(|(key, value)| (key.clone(), Some(value.clone())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        drop(tables);
+
+        // A full chunk may be followed by more committed entries; pending
+        // writes are only merged up to its last key
+        let end =
+            if merged.len() == WALK_CHUNK { merged.keys().next_back().cloned() } else { None };
+        if let Some(overlay) = overlay {
+            let until = end.clone().map_or(Bound::Unbounded, Bound::Included);
+            for (key, value) in overlay.range((from, until)) {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+
+        let entries =
+            merged.into_iter().filter_map(|(key, value)| value.map(|value| (key, value))).collect();
+        (entries, end.map(Bound::Excluded))
+    }
+
+    fn last<T: Table>(
+        &self,
+        overlay: Option<&RawOverlay>,
+    ) -> Result<Option<(T::Key, T::Value)>, DatabaseError> {
+        let mut last = None;
+        match overlay {
+            // Deletions may remove the committed last entry, so walk everything
+            Some(overlay) if !overlay.is_empty() => {
+                self.walk::<T>(None, Some(overlay), |key, value| {
+                    last = Some((key, value));
+                    true
+                })?;
+            }
+            _ => {
+                let tables = self.tables.read().unwrap();
+                if let Some((key, value)) =
+                    tables.get(T::NAME).and_then(|table| table.iter().next_back())
+                {
+                    last = Some((T::Key::decode(key)?, T::Value::decompress(value)?));
+                }
+            }
+        }
+        Ok(last)
+    }
+}
+
+/// In-memory write transaction
+///
+/// Writes are buffered per table and applied together on commit.
+pub struct MemoryTxMut {
+    db: Arc<MemoryDb>,
+    overlay: RefCell<HashMap<&'static str, RawOverlay>>,
+}
+
+impl MemoryTxMut {
+    fn begin(db: Arc<MemoryDb>) -> Self {
+        let mut writing = db.writing.lock().unwrap();
+        while *writing {
+            writing = db.writer_done.wait(writing).unwrap();
+        }
+        *writing = true;
+        drop(writing);
+        Self { db, overlay: RefCell::new(HashMap::new()) }
+    }
+
+    fn write<T: Table>(&self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        self.overlay.borrow_mut().entry(T::NAME).or_default().insert(key, value);
+    }
+
+    /// Run `f` on the pending writes to `T`
+    fn with_overlay<T: Table, R>(&self, f: impl FnOnce(Option<&RawOverlay>) -> R) -> R {
+        f(self.overlay.borrow().get(T::NAME))
+    }
+
+    /// Copy of the pending writes to `T`, so a walk's visitor may write
+    fn overlay<T: Table>(&self) -> Option<RawOverlay> {
+        self.overlay.borrow().get(T::NAME).cloned()
+    }
+
+    fn commit(self) {
+        let mut tables = self.db.tables.write().unwrap();
+        for (name, writes) in self.overlay.take() {
+            let table = tables.entry(name).or_default();
+            for (key, value) in writes {
+                match value {
+                    Some(value) => table.insert(key, value),
+                    None => table.remove(&key),
+                };
+            }
+        }
+    }
+}
+
+impl Drop for MemoryTxMut {
+    fn drop(&mut self) {
+        *self.db.writing.lock().unwrap() = false;
+        self.db.writer_done.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::{DualvmBlockStats, DualvmCounters, StoredBlockStats, StoredCounter};
+    use alloy_primitives::Address;
+    use reth_db::mdbx::{init_db_for, DatabaseArguments};
+    use reth_db::models::ClientVersion;
+
+    fn mdbx() -> Backend {
+        let dir = tempfile::tempdir().unwrap();
+        let db = init_db_for::<_, crate::tables::DualvmTableSet>(
+            dir.path(),
+            DatabaseArguments::new(ClientVersion::default()),
+        )
+        .unwrap();
+        Backend::from(Arc::new(db))
+    }
+
+    fn stats(gas_used: u64) -> StoredBlockStats {
+        StoredBlockStats { gas_used, ..Default::default() }
+    }
+
+    fn gas(entries: Vec<(u64, StoredBlockStats)>) -> Vec<(u64, u64)> {
+        entries.into_iter().map(|(number, stats)| (number, stats.gas_used)).collect()
+    }
+
+    /// Same operations against both backends, which must agree
+    fn exercise(backend: Backend) {
+        let tx = backend.tx_mut().unwrap();
+        for number in [5u64, 1, 300, 3] {
+            tx.put::<DualvmBlockStats>(number, stats(number * 10)).unwrap();
+        }
+        // Uncommitted writes are visible to their own transaction only
+        assert_eq!(tx.last::<DualvmBlockStats>().unwrap().unwrap().0, 300);
+        assert!(backend.tx().unwrap().get::<DualvmBlockStats>(5).unwrap().is_none());
+        tx.commit().unwrap();
+
+        let tx = backend.tx().unwrap();
+        assert_eq!(tx.get::<DualvmBlockStats>(5).unwrap(), Some(stats(50)));
+        assert_eq!(tx.entries::<DualvmBlockStats>().unwrap(), 4);
+        assert_eq!(gas(tx.range::<DualvmBlockStats>(2..=5).unwrap()), vec![(3, 30), (5, 50)]);
+        assert_eq!(gas(tx.range::<DualvmBlockStats>(4..).unwrap()), vec![(5, 50), (300, 3000)]);
+        drop(tx);
+
+        // Dropped transactions leave nothing behind
+        let tx = backend.tx_mut().unwrap();
+        tx.put::<DualvmBlockStats>(7, stats(70)).unwrap();
+        drop(tx);
+        assert!(backend.tx().unwrap().get::<DualvmBlockStats>(7).unwrap().is_none());
+
+        // Deletes and overwrites are merged into walks before commit
+        let tx = backend.tx_mut().unwrap();
+        assert!(tx.delete::<DualvmBlockStats>(300).unwrap());
+        assert!(!tx.delete::<DualvmBlockStats>(301).unwrap());
+        tx.put::<DualvmBlockStats>(1, stats(11)).unwrap();
+        assert_eq!(gas(tx.range::<DualvmBlockStats>(..).unwrap()), vec![(1, 11), (3, 30), (5, 50)]);
+        assert_eq!(tx.last::<DualvmBlockStats>().unwrap().unwrap().0, 5);
+        tx.commit().unwrap();
+
+        // Walks longer than a chunk, stopping early
+        let tx = backend.tx_mut().unwrap();
+        for byte in 0..=255u8 {
+            for low in [0u8, 1, 2] {
+                let mut address = [0u8; 20];
+                address[0] = byte;
+                address[19] = low;
+                tx.put::<DualvmCounters>(Address::from(address), StoredCounter { value: 1 })
+                    .unwrap();
+            }
+        }
+        tx.commit().unwrap();
+        let tx = backend.tx().unwrap();
+        let mut seen = Vec::new();
+        tx.walk::<DualvmCounters>(Some(Address::repeat_byte(0x80)), |address, _| {
+            seen.push(address);
+            seen.len() < 300
+        })
+        .unwrap();
+        assert_eq!(seen.len(), 300);
+        assert_eq!(seen[0][0], 0x81);
+        assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(tx.entries::<DualvmCounters>().unwrap(), 768);
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        exercise(mdbx());
+        exercise(Backend::memory());
+    }
+
+    #[test]
+    fn test_memory_writers_are_serialized() {
+        let backend = Backend::memory();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        let tx = backend.tx_mut().unwrap();
+                        let value = tx
+                            .get::<DualvmCounters>(Address::ZERO)
+                            .unwrap()
+                            .map_or(0, |counter| counter.value);
+                        tx.put::<DualvmCounters>(Address::ZERO, StoredCounter { value: value + 1 })
+                            .unwrap();
+                        tx.commit().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let counter = backend.tx().unwrap().get::<DualvmCounters>(Address::ZERO).unwrap();
+        assert_eq!(counter, Some(StoredCounter { value: 200 }));
+    }
+}
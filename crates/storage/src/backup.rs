@@ -0,0 +1,195 @@
+//! Database backups
+//!
+//! [`Backups`] copies a live database into `backup-<unix ms>` directories and
+//! keeps only the newest. The copy reads one MDBX read transaction, which is a
+//! consistent snapshot, so the node keeps producing or syncing blocks while it
+//! runs. Each backup is a complete datadir: restore one by copying its
+//! `mdbx.dat` over the node's while the node is stopped.
+
+use crate::{storage::DualvmStorage, traits::BlockReader};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Backups kept by default
+pub const DEFAULT_BACKUPS_KEPT: usize = 7;
+
+/// Suffix of a backup still being written
+const PARTIAL_SUFFIX: &str = ".tmp";
+
+/// A finished backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReport {
+    /// Backup directory
+    pub path: PathBuf,
+    /// Latest block when the backup started
+    pub block_number: u64,
+    pub entries_copied: u64,
+    pub size_bytes: u64,
+    pub duration_ms: u64,
+    /// Older backups removed to stay within the retention limit
+    pub removed: usize,
+}
+
+/// Writes database backups into a directory, keeping the newest
+#[derive(Debug)]
+pub struct Backups {
+    dir: PathBuf,
+    /// Newest backups kept; 0 keeps every backup
+    keep: usize,
+    /// Held while a backup runs, so scheduled and on-demand backups never overlap
+    running: Mutex<()>,
+}
+
+impl Backups {
+    /// Back up into `dir`, creating it if needed, keeping the newest `keep` backups
+    ///
+    /// Partial backups left by an interrupted run are removed.
+    pub fn new(dir: impl Into<PathBuf>, keep: usize) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| {
+            eyre::eyre!("Failed to create backup directory {}: {}", dir.display(), e)
+        })?;
+        for path in backup_dirs(&dir)? {
+            if is_partial(&path) {
+                fs::remove_dir_all(&path)?;
+            }
+        }
+        Ok(Self { dir, keep, running: Mutex::new(()) })
+    }
+
+    /// Directory the backups are written to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Copy `storage` into a new backup, then remove the oldest beyond the limit
+    ///
+    /// Fails without waiting if another backup is running.
+    pub fn backup(&self, storage: &DualvmStorage) -> Result<BackupReport> {
+        let _running =
+            self.running.try_lock().map_err(|_| eyre::eyre!("A backup is already running"))?;
+        let started = Instant::now();
+        let block_number = storage.blocks.latest_block_number();
+
+        let timestamp_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let name = format!("backup-{:013}", timestamp_ms);
+        let path = self.dir.join(&name);
+        if path.exists() {
+            return Err(eyre::eyre!("Backup {} already exists", path.display()));
+        }
+        let partial = self.dir.join(name + PARTIAL_SUFFIX);
+
+        let report = match storage.compact_into(&partial) {
+            Ok(report) => report,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&partial);
+                return Err(e.wrap_err("Backup failed"));
+            }
+        };
+        // The lock file only matters to an open environment
+        let _ = fs::remove_file(partial.join("mdbx.lck"));
+        fs::rename(&partial, &path)?;
+        let removed = self.rotate()?;
+
+        Ok(BackupReport {
+            path,
+            block_number,
+            entries_copied: report.entries_copied,
+            size_bytes: report.size_after,
+            duration_ms: started.elapsed().as_millis() as u64,
+            removed,
+        })
+    }
+
+    /// Finished backups, oldest first
+    pub fn list(&self) -> Result<Vec<PathBuf>> {
+        let mut backups: Vec<_> =
+            backup_dirs(&self.dir)?.into_iter().filter(|path| !is_partial(path)).collect();
+        // Timestamps are zero-padded, so names sort oldest first
+        backups.sort();
+        Ok(backups)
+    }
+
+    fn rotate(&self) -> Result<usize> {
+        let backups = self.list()?;
+        if self.keep == 0 || backups.len() <= self.keep {
+            return Ok(0);
+        }
+        let stale = &backups[..backups.len() - self.keep];
+        for path in stale {
+            fs::remove_dir_all(path)?;
+        }
+        Ok(stale.len())
+    }
+}
+
+/// Directories in `dir` named like backups, finished or not
+fn backup_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("backup-"))
+        })
+        .collect())
+}
+
+fn is_partial(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| path.ends_with(PARTIAL_SUFFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{block_store::StoredBlock, traits::BlockWriter, MDBX_DATA_FILE};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_backups_restore_and_rotate() {
+        let dir = tempdir().unwrap();
+        let storage = DualvmStorage::new(&dir.path().join("db")).unwrap();
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+
+        // Leftovers of an interrupted backup are cleaned up
+        let backup_dir = dir.path().join("backups");
+        fs::create_dir_all(backup_dir.join("backup-0000000000001.tmp")).unwrap();
+        let backups = Backups::new(&backup_dir, 2).unwrap();
+        assert!(backups.list().unwrap().is_empty());
+
+        let report = backups.backup(&storage).unwrap();
+        assert_eq!(report.block_number, 0);
+        assert_eq!(report.removed, 0);
+        assert!(report.path.join(MDBX_DATA_FILE).exists());
+
+        // A backup opens as a database with the same blocks
+        let restored = DualvmStorage::open_read_only(&report.path).unwrap();
+        assert_eq!(restored.blocks.refresh_latest_block_number().unwrap(), 0);
+        assert_eq!(
+            restored.blocks.get_block_by_number(0).map(|block| block.hash),
+            Some(StoredBlock::genesis(1).hash)
+        );
+        drop(restored);
+
+        for _ in 0..2 {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            backups.backup(&storage).unwrap();
+        }
+        let kept = backups.list().unwrap();
+        assert_eq!(kept.len(), 2);
+        assert!(!kept.contains(&report.path));
+
+        // Nothing to back up without a datadir
+        let memory = DualvmStorage::in_memory().unwrap();
+        assert!(backups.backup(&memory).is_err());
+        assert_eq!(backups.list().unwrap(), kept);
+    }
+}
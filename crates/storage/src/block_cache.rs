@@ -0,0 +1,220 @@
+//! LRU cache of recently accessed blocks
+//!
+//! Serves repeated lookups (latest block polling, P2P header requests) without
+//! decoding the block from MDBX each time. Blocks are indexed by number and hash.
+
+use crate::block_store::StoredBlock;
+use alloy_primitives::B256;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+/// Default number of cached blocks
+pub const DEFAULT_BLOCK_CACHE_SIZE: usize = 256;
+
+/// Cache hit/miss counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockCacheStats {
+    /// Lookups served from the cache
+    pub hits: u64,
+    /// Lookups that fell through to the database
+    pub misses: u64,
+    /// Blocks currently cached
+    pub len: usize,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    /// Block number -> (block, last access tick)
+    blocks: HashMap<u64, (StoredBlock, u64)>,
+    /// Block hash -> block number
+    by_hash: HashMap<B256, u64>,
+    /// Last access tick -> block number, oldest first
+    recency: BTreeMap<u64, u64>,
+    tick: u64,
+}
+
+impl CacheInner {
+    fn touch(&mut self, number: u64) -> Option<StoredBlock> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (block, last_access) = self.blocks.get_mut(&number)?;
+        self.recency.remove(last_access);
+        self.recency.insert(tick, number);
+        *last_access = tick;
+        Some(block.clone())
+    }
+
+    fn remove(&mut self, number: u64) {
+        if let Some((block, last_access)) = self.blocks.remove(&number) {
+            self.recency.remove(&last_access);
+            self.by_hash.remove(&block.hash);
+        }
+    }
+}
+
+/// Fixed-capacity LRU cache of blocks
+#[derive(Debug)]
+pub struct BlockCache {
+    inner: Mutex<CacheInner>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BlockCache {
+    /// Create a cache holding at most `capacity` blocks (0 disables caching)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner::default()),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn inner(&self) -> MutexGuard<'_, CacheInner> {
+        // The cache holds plain data, so a poisoned lock is still usable
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record(&self, block: Option<StoredBlock>) -> Option<StoredBlock> {
+        let counter = if block.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        block
+    }
+
+    /// Look up a block by number
+    pub fn get_by_number(&self, number: u64) -> Option<StoredBlock> {
+        let block = self.inner().touch(number);
+        self.record(block)
+    }
+
+    /// Look up a block by hash
+    pub fn get_by_hash(&self, hash: &B256) -> Option<StoredBlock> {
+        let block = {
+            let mut inner = self.inner();
+            inner.by_hash.get(hash).copied().and_then(|number| inner.touch(number))
+        };
+        self.record(block)
+    }
+
+    /// Cache a block, replacing any cached block with the same number
+    pub fn insert(&self, block: StoredBlock) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut inner = self.inner();
+        inner.remove(block.number);
+
+        while inner.blocks.len() >= self.capacity {
+            let Some((_, oldest)) = inner.recency.pop_first() else { break };
+            if let Some((evicted, _)) = inner.blocks.remove(&oldest) {
+                inner.by_hash.remove(&evicted.hash);
+            }
+        }
+
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.by_hash.insert(block.hash, block.number);
+        inner.recency.insert(tick, block.number);
+        inner.blocks.insert(block.number, (block, tick));
+    }
+
+    /// Drop a cached block
+    pub fn invalidate(&self, number: u64) {
+        self.inner().remove(number);
+    }
+
+    /// Drop every cached block above `number`
+    pub fn invalidate_above(&self, number: u64) {
+        let mut inner = self.inner();
+        let above: Vec<u64> = inner.blocks.keys().copied().filter(|&n| n > number).collect();
+        for number in above {
+            inner.remove(number);
+        }
+    }
+
+    /// Hash of a cached block, without counting a lookup or touching its recency
+    pub fn cached_hash(&self, number: u64) -> Option<B256> {
+        self.inner().blocks.get(&number).map(|(block, _)| block.hash)
+    }
+
+    /// Hit/miss counters and current size
+    pub fn stats(&self) -> BlockCacheStats {
+        BlockCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self.inner().blocks.len(),
+        }
+    }
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_BLOCK_CACHE_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, hash_byte: u8) -> StoredBlock {
+        StoredBlock { number, hash: B256::repeat_byte(hash_byte), ..StoredBlock::genesis(1) }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = BlockCache::new(2);
+        cache.insert(block(1, 0x01));
+        cache.insert(block(2, 0x02));
+
+        // Touch block 1 so block 2 becomes the eviction candidate
+        assert!(cache.get_by_number(1).is_some());
+        cache.insert(block(3, 0x03));
+
+        assert!(cache.get_by_number(2).is_none());
+        assert!(cache.get_by_hash(&B256::repeat_byte(0x02)).is_none());
+        assert_eq!(cache.get_by_hash(&B256::repeat_byte(0x01)).unwrap().number, 1);
+        assert_eq!(cache.get_by_number(3).unwrap().hash, B256::repeat_byte(0x03));
+
+        let stats = cache.stats();
+        assert_eq!(stats.len, 2);
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn test_replace_drops_old_hash() {
+        let cache = BlockCache::new(4);
+        cache.insert(block(5, 0x05));
+        cache.insert(block(5, 0x55));
+
+        assert!(cache.get_by_hash(&B256::repeat_byte(0x05)).is_none());
+        assert_eq!(cache.get_by_number(5).unwrap().hash, B256::repeat_byte(0x55));
+
+        cache.invalidate(5);
+        assert!(cache.get_by_number(5).is_none());
+        assert_eq!(cache.stats().len, 0);
+    }
+
+    #[test]
+    fn test_invalidate_above() {
+        let cache = BlockCache::new(4);
+        for number in 1..=4 {
+            cache.insert(block(number, number as u8));
+        }
+
+        cache.invalidate_above(2);
+        assert_eq!(cache.cached_hash(2), Some(B256::repeat_byte(2)));
+        assert_eq!(cache.cached_hash(3), None);
+        assert!(cache.get_by_hash(&B256::repeat_byte(4)).is_none());
+        assert_eq!(cache.stats().len, 2);
+    }
+}
@@ -1,18 +1,23 @@
-//! Block storage module using MDBX database
-
-use crate::tables::{DualvmBlocks, DualvmTransactions, DualvmTxHashes, StoredDualvmBlock, StoredTransaction, StoredTxInfo};
+//! Block storage module
+
+use crate::{
+    backend::{Backend, TableReader},
+    block_cache::{BlockCache, BlockCacheStats},
+    tables::{
+        DualvmAccessSets, DualvmBlockStats, DualvmBlocks, DualvmDexVmReceipts, DualvmReceipts,
+        DualvmSiblingBlocks, DualvmTransactions, DualvmTxHashes, StoredAccessSet,
+        StoredBlockStats, StoredDexVmReceipts, StoredDualvmBlock, StoredReceipt,
+        StoredSiblingBlock, StoredSiblingBlocks, StoredTransaction, StoredTxInfo,
+    },
+    traits::{BlockReader, BlockWriter},
+};
 use alloy_primitives::{keccak256, Address, B256};
 use eyre::Result;
-use reth_db::DatabaseEnv;
-use reth_db_api::{
-    cursor::DbCursorRO,
-    database::Database,
-    transaction::{DbTx, DbTxMut},
-};
-use std::sync::{
-    atomic::{AtomicU64, Ordering},
-    Arc,
-};
+use reth_db_api::table::Table;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Most competing blocks recorded per height; further ones are dropped
+pub const MAX_SIBLINGS_PER_HEIGHT: usize = 16;
 
 /// Stored block data with transaction hashes
 #[derive(Debug, Clone)]
@@ -94,25 +99,90 @@ impl From<&StoredBlock> for StoredDualvmBlock {
     }
 }
 
-/// Block store using MDBX database
+/// Order of the blocks returned by [`BlockReader::get_headers_range`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeDirection {
+    /// Increasing block numbers from the start block
+    Ascending,
+    /// Decreasing block numbers from the start block, towards genesis
+    Descending,
+}
+
+/// A block removed by [`BlockWriter::unwind_to`], with its receipts
+#[derive(Debug, Clone)]
+pub struct UnwoundBlock {
+    pub block: StoredBlock,
+    /// Receipts of the block's transactions, in block order
+    pub receipts: Vec<(B256, StoredReceipt)>,
+}
+
+/// What [`BlockStore::recover`] found and removed on startup
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Highest stored block, where block production resumes
+    pub head: u64,
+    /// Hash of the head block, the parent of the next block
+    pub head_hash: B256,
+    /// Block stats removed for blocks that were never stored
+    pub removed_stats: u64,
+    /// Transactions removed because no stored block includes them
+    pub removed_transactions: u64,
+    /// Access sets removed because no stored block includes their transaction
+    pub removed_access_sets: u64,
+    /// Receipts removed because no stored block includes their transaction
+    pub removed_receipts: u64,
+    /// DexVM receipts removed for blocks that were never stored
+    pub removed_dexvm_receipts: u64,
+}
+
+impl RecoveryReport {
+    /// Whether a block was left half-written
+    pub fn found_partial_block(&self) -> bool {
+        self.removed_stats +
+            self.removed_transactions +
+            self.removed_access_sets +
+            self.removed_receipts +
+            self.removed_dexvm_receipts >
+            0
+    }
+}
+
+/// Transaction hashes in `T` that no stored block includes
+fn unindexed_hashes<T: Table<Key = B256>>(tx: &impl TableReader) -> Result<Vec<B256>> {
+    let mut hashes = Vec::new();
+    tx.walk::<T>(None, |hash, _| {
+        hashes.push(hash);
+        true
+    })?;
+
+    let mut unindexed = Vec::new();
+    for hash in hashes {
+        if tx.get::<DualvmTxHashes>(hash)?.is_none() {
+            unindexed.push(hash);
+        }
+    }
+    Ok(unindexed)
+}
+
+/// Block store over a storage [`Backend`]
 pub struct BlockStore {
-    db: Arc<DatabaseEnv>,
+    db: Backend,
     latest_block: AtomicU64,
+    cache: BlockCache,
 }
 
 impl BlockStore {
     /// Create new block store with database
-    pub fn new(db: Arc<DatabaseEnv>) -> Result<Self> {
-        let store = Self { db, latest_block: AtomicU64::new(0) };
+    pub fn new(db: impl Into<Backend>) -> Result<Self> {
+        let store =
+            Self { db: db.into(), latest_block: AtomicU64::new(0), cache: BlockCache::default() };
         store.load_latest_block_number()?;
         Ok(store)
     }
 
     fn load_latest_block_number(&self) -> Result<()> {
         let tx = self.db.tx()?;
-        let mut cursor = tx.cursor_read::<DualvmBlocks>()?;
-
-        if let Some((block_number, _)) = cursor.last()? {
+        if let Some((block_number, _)) = tx.last::<DualvmBlocks>()? {
             self.latest_block.store(block_number, Ordering::SeqCst);
             tracing::info!("Loaded latest block number: {}", block_number);
         }
@@ -120,189 +190,376 @@ impl BlockStore {
         Ok(())
     }
 
-    /// Store a block
-    pub fn store_block(&self, block: StoredBlock) -> Result<()> {
-        let tx = self.db.tx_mut()?;
+    /// Block cache hit/miss counters
+    pub fn cache_stats(&self) -> BlockCacheStats {
+        self.cache.stats()
+    }
+}
 
-        let stored: StoredDualvmBlock = (&block).into();
-        tx.put::<DualvmBlocks>(block.number, stored)?;
+impl BlockReader for BlockStore {
+    fn latest_block_number(&self) -> u64 {
+        self.latest_block.load(Ordering::SeqCst)
+    }
 
-        for (idx, tx_hash) in block.transaction_hashes.iter().enumerate() {
-            tx.put::<DualvmTxHashes>(
-                *tx_hash,
-                StoredTxInfo { block_number: block.number, tx_index: idx as u64 },
-            )?;
+    fn refresh_latest_block_number(&self) -> Result<u64> {
+        let tx = self.db.tx()?;
+        let head = tx.last::<DualvmBlocks>()?.map_or(0, |(number, _)| number);
+
+        // The writer may have unwound, and replaced the blocks it removed
+        self.cache.invalidate_above(head);
+        let mut number = head;
+        while let Some(cached) = self.cache.cached_hash(number) {
+            if tx.get::<DualvmBlocks>(number)?.is_some_and(|stored| stored.hash == cached) {
+                break;
+            }
+            self.cache.invalidate(number);
+            let Some(parent) = number.checked_sub(1) else { break };
+            number = parent;
         }
 
-        tx.commit()?;
+        self.latest_block.store(head, Ordering::SeqCst);
+        Ok(head)
+    }
 
-        let current_latest = self.latest_block.load(Ordering::SeqCst);
-        if block.number > current_latest {
-            self.latest_block.store(block.number, Ordering::SeqCst);
+    fn get_block_by_number(&self, number: u64) -> Option<StoredBlock> {
+        if let Some(block) = self.cache.get_by_number(number) {
+            return Some(block);
         }
 
-        tracing::debug!("Stored block {} with hash {:?}", block.number, block.hash);
-        Ok(())
-    }
-
-    /// Get block by number
-    pub fn get_block_by_number(&self, number: u64) -> Option<StoredBlock> {
         let tx = self.db.tx().ok()?;
         let stored = tx.get::<DualvmBlocks>(number).ok()??;
 
         let mut block: StoredBlock = stored.into();
         block.number = number;
 
+        self.cache.insert(block.clone());
         Some(block)
     }
 
-    /// Get block by hash
-    pub fn get_block_by_hash(&self, hash: B256) -> Option<StoredBlock> {
+    fn get_block_by_hash(&self, hash: B256) -> Option<StoredBlock> {
+        if let Some(block) = self.cache.get_by_hash(&hash) {
+            return Some(block);
+        }
+
         let tx = self.db.tx().ok()?;
-        let mut cursor = tx.cursor_read::<DualvmBlocks>().ok()?;
-        let walker = cursor.walk(None).ok()?;
+        let mut found = None;
+        tx.walk::<DualvmBlocks>(None, |number, stored| {
+            if stored.hash != hash {
+                return true;
+            }
+            let mut block: StoredBlock = stored.into();
+            block.number = number;
+            found = Some(block);
+            false
+        })
+        .ok()?;
+
+        if let Some(block) = &found {
+            self.cache.insert(block.clone());
+        }
+        found
+    }
 
-        for (number, stored) in walker.flatten() {
-            if stored.hash == hash {
+    fn block_count(&self) -> usize {
+        self.db.tx().and_then(|tx| tx.entries::<DualvmBlocks>()).unwrap_or(0)
+    }
+
+    fn get_headers_range(
+        &self,
+        start: u64,
+        count: u64,
+        direction: RangeDirection,
+    ) -> Vec<StoredBlock> {
+        let Some(last) = count.checked_sub(1) else {
+            return vec![];
+        };
+        let range = match direction {
+            RangeDirection::Ascending => start..=start.saturating_add(last),
+            RangeDirection::Descending => start.saturating_sub(last)..=start,
+        };
+
+        let stored = self.db.tx().and_then(|tx| tx.range::<DualvmBlocks>(range));
+        let mut blocks: Vec<StoredBlock> = stored
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(number, stored)| {
                 let mut block: StoredBlock = stored.into();
                 block.number = number;
-                return Some(block);
-            }
+                block
+            })
+            .collect();
+        if direction == RangeDirection::Descending {
+            blocks.reverse();
         }
 
-        None
+        // Stop at the first missing block, so the result is contiguous from `start`
+        let contiguous = blocks
+            .iter()
+            .zip(0u64..)
+            .take_while(|(block, offset)| match direction {
+                RangeDirection::Ascending => block.number == start + offset,
+                RangeDirection::Descending => block.number == start - offset,
+            })
+            .count();
+        blocks.truncate(contiguous);
+        blocks
+    }
+
+    fn check_contiguous(&self) -> Result<()> {
+        let tx = self.db.tx()?;
+
+        let mut expected = 0u64;
+        let mut gap = None;
+        tx.walk::<DualvmBlocks>(None, |number, _| {
+            if number != expected {
+                gap = Some((expected, number - 1));
+                return false;
+            }
+            expected += 1;
+            true
+        })?;
+
+        match gap {
+            Some((first, last)) => Err(eyre::eyre!(
+                "Blocks {}..{} are missing below stored head {}",
+                first,
+                last,
+                self.latest_block_number()
+            )),
+            None => Ok(()),
+        }
     }
 
-    /// Get latest block
-    pub fn get_latest_block(&self) -> Option<StoredBlock> {
-        let latest = self.latest_block.load(Ordering::SeqCst);
-        self.get_block_by_number(latest)
+    fn get_tx_info(&self, tx_hash: B256) -> Option<StoredTxInfo> {
+        let tx = self.db.tx().ok()?;
+        tx.get::<DualvmTxHashes>(tx_hash).ok()?
     }
 
-    /// Get latest block number
-    pub fn latest_block_number(&self) -> u64 {
-        self.latest_block.load(Ordering::SeqCst)
+    fn get_transaction(&self, tx_hash: B256) -> Option<Vec<u8>> {
+        let tx = self.db.tx().ok()?;
+        tx.get::<DualvmTransactions>(tx_hash).ok()?.map(|t| t.rlp_bytes)
     }
 
-    /// Get block count
-    pub fn block_count(&self) -> usize {
-        let tx = match self.db.tx() {
-            Ok(tx) => tx,
-            Err(_) => return 0,
-        };
+    fn get_block_stats_range(&self, from: u64, to: u64) -> Vec<(u64, StoredBlockStats)> {
+        self.db.tx().and_then(|tx| tx.range::<DualvmBlockStats>(from..=to)).unwrap_or_default()
+    }
 
-        let mut cursor = match tx.cursor_read::<DualvmBlocks>() {
-            Ok(cursor) => cursor,
-            Err(_) => return 0,
-        };
+    fn get_access_set(&self, tx_hash: B256) -> Option<StoredAccessSet> {
+        let tx = self.db.tx().ok()?;
+        tx.get::<DualvmAccessSets>(tx_hash).ok()?
+    }
 
-        match cursor.walk(None) {
-            Ok(walker) => walker.count(),
-            Err(_) => 0,
-        }
+    fn get_receipt(&self, tx_hash: B256) -> Option<StoredReceipt> {
+        let tx = self.db.tx().ok()?;
+        tx.get::<DualvmReceipts>(tx_hash).ok()?
     }
 
-    /// Get transaction info by hash
-    pub fn get_tx_info(&self, tx_hash: B256) -> Option<StoredTxInfo> {
+    fn get_dexvm_receipts(&self, number: u64) -> Option<StoredDexVmReceipts> {
         let tx = self.db.tx().ok()?;
-        tx.get::<DualvmTxHashes>(tx_hash).ok()?
+        tx.get::<DualvmDexVmReceipts>(number).ok()?
     }
 
-    /// Get block number containing a transaction
-    pub fn get_tx_block_number(&self, tx_hash: B256) -> Option<u64> {
-        self.get_tx_info(tx_hash).map(|info| info.block_number)
+    fn get_siblings_range(&self, from: u64, to: u64) -> Vec<(u64, StoredSiblingBlocks)> {
+        self.db.tx().and_then(|tx| tx.range::<DualvmSiblingBlocks>(from..=to)).unwrap_or_default()
     }
+}
+
+impl BlockWriter for BlockStore {
+    fn store_block(&self, block: StoredBlock) -> Result<()> {
+        let tx = self.db.tx_mut()?;
 
-    /// Check if genesis block exists
-    pub fn has_genesis(&self) -> bool {
-        self.get_block_by_number(0).is_some()
+        let stored: StoredDualvmBlock = (&block).into();
+        tx.put::<DualvmBlocks>(block.number, stored)?;
+
+        for (idx, tx_hash) in block.transaction_hashes.iter().enumerate() {
+            tx.put::<DualvmTxHashes>(
+                *tx_hash,
+                StoredTxInfo { block_number: block.number, tx_index: idx as u64 },
+            )?;
+        }
+
+        tx.commit()?;
+
+        // Overwrites replace the cached entry and drop its old hash mapping
+        self.cache.insert(block.clone());
+
+        let current_latest = self.latest_block.load(Ordering::SeqCst);
+        if block.number > current_latest {
+            self.latest_block.store(block.number, Ordering::SeqCst);
+        }
+
+        tracing::debug!("Stored block {} with hash {:?}", block.number, block.hash);
+        Ok(())
     }
 
-    /// Initialize with genesis block
-    pub fn init_genesis(&self, chain_id: u64) -> Result<()> {
-        if self.has_genesis() {
-            tracing::info!("Genesis block already exists");
+    fn store_transactions(&self, transactions: &[(B256, Vec<u8>)]) -> Result<()> {
+        if transactions.is_empty() {
             return Ok(());
         }
+        let tx = self.db.tx_mut()?;
+        for (tx_hash, rlp_bytes) in transactions {
+            tx.put::<DualvmTransactions>(*tx_hash, StoredTransaction { rlp_bytes: rlp_bytes.clone() })?;
+        }
+        tx.commit()?;
+        tracing::debug!("Stored {} transactions", transactions.len());
+        Ok(())
+    }
 
-        let genesis = StoredBlock::genesis(chain_id);
-        self.store_block(genesis)?;
-        tracing::info!("Initialized genesis block for chain {}", chain_id);
+    fn store_block_stats(&self, number: u64, stats: StoredBlockStats) -> Result<()> {
+        let tx = self.db.tx_mut()?;
+        tx.put::<DualvmBlockStats>(number, stats)?;
+        tx.commit()?;
         Ok(())
     }
 
-    /// Store a full transaction by its hash
-    pub fn store_transaction(&self, tx_hash: B256, rlp_bytes: Vec<u8>) -> Result<()> {
+    fn store_access_sets(&self, access_sets: &[(B256, StoredAccessSet)]) -> Result<()> {
+        if access_sets.is_empty() {
+            return Ok(());
+        }
         let tx = self.db.tx_mut()?;
-        tx.put::<DualvmTransactions>(tx_hash, StoredTransaction { rlp_bytes })?;
+        for (tx_hash, access_set) in access_sets {
+            tx.put::<DualvmAccessSets>(*tx_hash, access_set.clone())?;
+        }
         tx.commit()?;
-        tracing::debug!("Stored transaction {:?}", tx_hash);
         Ok(())
     }
 
-    /// Store multiple transactions in a single batch
-    pub fn store_transactions(&self, transactions: &[(B256, Vec<u8>)]) -> Result<()> {
-        if transactions.is_empty() {
+    fn store_receipts(&self, receipts: &[(B256, StoredReceipt)]) -> Result<()> {
+        if receipts.is_empty() {
             return Ok(());
         }
         let tx = self.db.tx_mut()?;
-        for (tx_hash, rlp_bytes) in transactions {
-            tx.put::<DualvmTransactions>(*tx_hash, StoredTransaction { rlp_bytes: rlp_bytes.clone() })?;
+        for (tx_hash, receipt) in receipts {
+            tx.put::<DualvmReceipts>(*tx_hash, receipt.clone())?;
         }
         tx.commit()?;
-        tracing::debug!("Stored {} transactions", transactions.len());
         Ok(())
     }
 
-    /// Get a transaction by its hash
-    pub fn get_transaction(&self, tx_hash: B256) -> Option<Vec<u8>> {
-        let tx = self.db.tx().ok()?;
-        tx.get::<DualvmTransactions>(tx_hash).ok()?.map(|t| t.rlp_bytes)
+    fn store_dexvm_receipts(&self, number: u64, receipts: StoredDexVmReceipts) -> Result<()> {
+        if receipts.operations.is_empty() {
+            return Ok(());
+        }
+        let tx = self.db.tx_mut()?;
+        tx.put::<DualvmDexVmReceipts>(number, receipts)?;
+        tx.commit()?;
+        Ok(())
     }
 
-    /// Get all transactions for a block by block number
-    pub fn get_block_transactions(&self, block_number: u64) -> Option<Vec<Vec<u8>>> {
-        let block = self.get_block_by_number(block_number)?;
-        let mut txs = Vec::with_capacity(block.transaction_hashes.len());
+    fn record_sibling(&self, number: u64, sibling: StoredSiblingBlock) -> Result<bool> {
+        if self.get_block_by_number(number).is_some_and(|block| block.hash == sibling.hash) {
+            return Ok(false);
+        }
 
-        let tx = self.db.tx().ok()?;
-        for tx_hash in &block.transaction_hashes {
-            if let Ok(Some(stored_tx)) = tx.get::<DualvmTransactions>(*tx_hash) {
-                txs.push(stored_tx.rlp_bytes);
+        let tx = self.db.tx_mut()?;
+        let mut siblings = tx.get::<DualvmSiblingBlocks>(number)?.unwrap_or_default();
+        match siblings.blocks.iter_mut().find(|known| known.hash == sibling.hash) {
+            Some(known) if known.signer.is_none() && sibling.signer.is_some() => {
+                known.signer = sibling.signer;
             }
+            Some(_) => return Ok(false),
+            None if siblings.blocks.len() >= MAX_SIBLINGS_PER_HEIGHT => return Ok(false),
+            None => siblings.blocks.push(sibling),
         }
-
-        Some(txs)
+        tx.put::<DualvmSiblingBlocks>(number, siblings)?;
+        tx.commit()?;
+        Ok(true)
     }
 
-    /// Get transactions by their hashes
-    pub fn get_transactions_by_hashes(&self, hashes: &[B256]) -> Vec<Option<Vec<u8>>> {
-        let tx = match self.db.tx() {
-            Ok(tx) => tx,
-            Err(_) => return hashes.iter().map(|_| None).collect(),
+    fn recover(&self) -> Result<RecoveryReport> {
+        let head = self.latest_block_number();
+        let mut report = RecoveryReport {
+            head,
+            head_hash: self.get_block_by_number(head).map(|b| b.hash).unwrap_or_default(),
+            ..Default::default()
         };
 
-        hashes.iter().map(|hash| {
-            tx.get::<DualvmTransactions>(*hash).ok().flatten().map(|t| t.rlp_bytes)
-        }).collect()
+        let tx = self.db.tx_mut()?;
+
+        let orphan_stats = tx.range::<DualvmBlockStats>(head.saturating_add(1)..)?;
+        for (number, _) in orphan_stats {
+            tx.delete::<DualvmBlockStats>(number)?;
+            report.removed_stats += 1;
+        }
+        let orphan_dexvm_receipts = tx.range::<DualvmDexVmReceipts>(head.saturating_add(1)..)?;
+        for (number, _) in orphan_dexvm_receipts {
+            tx.delete::<DualvmDexVmReceipts>(number)?;
+            report.removed_dexvm_receipts += 1;
+        }
+
+        for hash in unindexed_hashes::<DualvmTransactions>(&tx)? {
+            tx.delete::<DualvmTransactions>(hash)?;
+            report.removed_transactions += 1;
+        }
+        for hash in unindexed_hashes::<DualvmAccessSets>(&tx)? {
+            tx.delete::<DualvmAccessSets>(hash)?;
+            report.removed_access_sets += 1;
+        }
+        for hash in unindexed_hashes::<DualvmReceipts>(&tx)? {
+            tx.delete::<DualvmReceipts>(hash)?;
+            report.removed_receipts += 1;
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+
+    fn unwind_to(&self, number: u64) -> Result<Vec<UnwoundBlock>> {
+        let tx = self.db.tx_mut()?;
+        let removed = tx.range::<DualvmBlocks>(number.saturating_add(1)..)?;
+
+        let mut unwound = Vec::new();
+        for (block_number, stored) in removed.into_iter().rev() {
+            let mut block: StoredBlock = stored.into();
+            block.number = block_number;
+
+            let mut receipts = Vec::new();
+            for hash in &block.transaction_hashes {
+                if let Some(receipt) = tx.get::<DualvmReceipts>(*hash)? {
+                    receipts.push((*hash, receipt));
+                }
+                tx.delete::<DualvmReceipts>(*hash)?;
+                tx.delete::<DualvmTxHashes>(*hash)?;
+                tx.delete::<DualvmTransactions>(*hash)?;
+                tx.delete::<DualvmAccessSets>(*hash)?;
+            }
+            tx.delete::<DualvmBlockStats>(block_number)?;
+            tx.delete::<DualvmDexVmReceipts>(block_number)?;
+            tx.delete::<DualvmBlocks>(block_number)?;
+            unwound.push(UnwoundBlock { block, receipts });
+        }
+
+        tx.commit()?;
+
+        for removed in &unwound {
+            self.cache.invalidate(removed.block.number);
+        }
+        self.latest_block.fetch_min(number, Ordering::SeqCst);
+
+        if !unwound.is_empty() {
+            tracing::info!("Unwound {} blocks to block {}", unwound.len(), number);
+        }
+        Ok(unwound)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tables::StoredDexVmOperation;
     use alloy_primitives::address;
     use reth_db::{mdbx::DatabaseArguments, mdbx::init_db_for, models::ClientVersion};
+    use std::sync::Arc;
     use tempfile::tempdir;
 
-    fn create_test_db() -> Arc<DatabaseEnv> {
+    fn create_test_db() -> Backend {
         let dir = tempdir().unwrap();
         let db = init_db_for::<_, crate::tables::DualvmTableSet>(
             dir.path(),
             DatabaseArguments::new(ClientVersion::default()),
         )
         .unwrap();
-        Arc::new(db)
+        Backend::from(Arc::new(db))
     }
 
     #[test]
@@ -345,5 +602,305 @@ mod tests {
         assert!(store.has_genesis());
         let genesis = store.get_block_by_number(0).unwrap();
         assert_eq!(genesis.number, 0);
+
+        let derived = B256::repeat_byte(0x11);
+        assert!(store.check_genesis(13337, derived).is_ok());
+        assert!(store.check_genesis(1, derived).is_err());
+        assert!(store.check_genesis(1, genesis.hash).is_ok());
+    }
+
+    #[test]
+    fn test_check_contiguous() {
+        for db in [create_test_db(), Backend::memory()] {
+            let store = BlockStore::new(db).unwrap();
+            assert!(store.check_contiguous().is_ok());
+
+            let genesis = StoredBlock::genesis(1);
+            store.store_block(genesis.clone()).unwrap();
+            store.store_block(StoredBlock { number: 1, ..genesis.clone() }).unwrap();
+            assert!(store.check_contiguous().is_ok());
+
+            store.store_block(StoredBlock { number: 4, ..genesis }).unwrap();
+            let err = store.check_contiguous().unwrap_err().to_string();
+            assert!(err.contains("2..3"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_cache_invalidated_on_store() {
+        let db = create_test_db();
+        let store = BlockStore::new(db).unwrap();
+
+        let genesis = StoredBlock::genesis(1);
+        store.store_block(genesis.clone()).unwrap();
+        assert_eq!(store.get_block_by_hash(genesis.hash).unwrap().number, 0);
+        assert!(store.cache_stats().hits > 0);
+
+        // Overwriting a block must not serve the old hash from the cache
+        let replacement = StoredBlock { hash: B256::repeat_byte(0x99), ..genesis.clone() };
+        store.store_block(replacement.clone()).unwrap();
+        assert!(store.get_block_by_hash(genesis.hash).is_none());
+        assert_eq!(store.get_block_by_number(0).unwrap().hash, replacement.hash);
+    }
+
+    #[test]
+    fn test_access_set_roundtrip() {
+        let db = create_test_db();
+        let store = BlockStore::new(db).unwrap();
+
+        let sender = address!("1111111111111111111111111111111111111111");
+        let access_set = StoredAccessSet {
+            accounts_read: vec![sender],
+            accounts_written: vec![sender],
+            slots_read: vec![(Address::repeat_byte(0x01), B256::repeat_byte(0x02))],
+            slots_written: vec![],
+        };
+        let tx_hash = B256::repeat_byte(0xaa);
+        store.store_access_sets(&[(tx_hash, access_set.clone())]).unwrap();
+
+        assert_eq!(store.get_access_set(tx_hash), Some(access_set));
+        assert_eq!(store.get_access_set(B256::ZERO), None);
+    }
+
+    #[test]
+    fn test_recover_after_crash() {
+        recover_after_crash(create_test_db());
+        recover_after_crash(Backend::memory());
+    }
+
+    fn recover_after_crash(db: Backend) {
+        let store = BlockStore::new(db).unwrap();
+        let genesis = StoredBlock::genesis(1);
+        store.store_block(genesis.clone()).unwrap();
+
+        // Clean database: resume after genesis
+        let report = store.recover().unwrap();
+        assert_eq!(report.head, 0);
+        assert_eq!(report.head_hash, genesis.hash);
+        assert!(!report.found_partial_block());
+
+        // Block 1 is fully stored
+        let included = B256::repeat_byte(0x01);
+        let block1 = StoredBlock {
+            number: 1,
+            hash: B256::repeat_byte(0x11),
+            parent_hash: genesis.hash,
+            transaction_hashes: vec![included],
+            transaction_count: 1,
+            ..genesis
+        };
+        store.store_transactions(&[(included, vec![1])]).unwrap();
+        store.store_access_sets(&[(included, StoredAccessSet::default())]).unwrap();
+        store.store_receipts(&[(included, StoredReceipt::default())]).unwrap();
+        store.store_block_stats(1, StoredBlockStats::default()).unwrap();
+        store.store_block(block1.clone()).unwrap();
+
+        // Crash while writing block 2, before the block itself
+        let lost = B256::repeat_byte(0x02);
+        store.store_transactions(&[(lost, vec![2])]).unwrap();
+        store.store_access_sets(&[(lost, StoredAccessSet::default())]).unwrap();
+        store.store_receipts(&[(lost, StoredReceipt::default())]).unwrap();
+        store.store_block_stats(2, StoredBlockStats::default()).unwrap();
+        let operation = StoredDexVmOperation { tx_hash: lost, ..Default::default() };
+        let dexvm_receipts = StoredDexVmReceipts { operations: vec![operation] };
+        store.store_dexvm_receipts(2, dexvm_receipts).unwrap();
+
+        let report = store.recover().unwrap();
+        assert_eq!(
+            report,
+            RecoveryReport {
+                head: 1,
+                head_hash: block1.hash,
+                removed_stats: 1,
+                removed_transactions: 1,
+                removed_access_sets: 1,
+                removed_receipts: 1,
+                removed_dexvm_receipts: 1,
+            }
+        );
+        assert!(store.get_transaction(lost).is_none());
+        assert!(store.get_access_set(lost).is_none());
+        assert!(store.get_receipt(lost).is_none());
+        assert!(store.get_block_stats_range(2, 2).is_empty());
+        assert!(store.get_dexvm_receipts(2).is_none());
+
+        // Block 1's data survives and a second pass finds nothing
+        assert!(store.get_transaction(included).is_some());
+        assert!(store.get_access_set(included).is_some());
+        assert!(store.get_receipt(included).is_some());
+        assert_eq!(store.get_block_stats_range(1, 1).len(), 1);
+        assert!(!store.recover().unwrap().found_partial_block());
+    }
+
+    #[test]
+    fn test_unwind_to() {
+        unwind_to(create_test_db());
+        unwind_to(Backend::memory());
+    }
+
+    fn unwind_to(db: Backend) {
+        let store = BlockStore::new(db).unwrap();
+        let genesis = StoredBlock::genesis(1);
+        store.store_block(genesis.clone()).unwrap();
+
+        let receipt = StoredReceipt { success: true, ..Default::default() };
+        let mut parent_hash = genesis.hash;
+        for number in 1..=3u8 {
+            let hash = B256::repeat_byte(number);
+            let block = StoredBlock {
+                number: number as u64,
+                hash: B256::repeat_byte(0x10 + number),
+                parent_hash,
+                transaction_hashes: vec![hash],
+                transaction_count: 1,
+                ..genesis.clone()
+            };
+            parent_hash = block.hash;
+            store.store_transactions(&[(hash, vec![number])]).unwrap();
+            store.store_access_sets(&[(hash, StoredAccessSet::default())]).unwrap();
+            store.store_receipts(&[(hash, receipt.clone())]).unwrap();
+            store.store_block_stats(number as u64, StoredBlockStats::default()).unwrap();
+            store.store_block(block).unwrap();
+        }
+        // Warm the cache so removed blocks must be evicted
+        assert!(store.get_block_by_number(3).is_some());
+
+        let unwound = store.unwind_to(1).unwrap();
+        let numbers: Vec<_> = unwound.iter().map(|removed| removed.block.number).collect();
+        assert_eq!(numbers, vec![3, 2]);
+        assert_eq!(unwound[0].receipts, vec![(B256::repeat_byte(3), receipt)]);
+
+        assert_eq!(store.latest_block_number(), 1);
+        assert!(store.get_block_by_number(3).is_none());
+        assert!(store.get_block_by_hash(B256::repeat_byte(0x13)).is_none());
+        for byte in [2, 3] {
+            let hash = B256::repeat_byte(byte);
+            assert!(store.get_tx_info(hash).is_none());
+            assert!(store.get_transaction(hash).is_none());
+            assert!(store.get_access_set(hash).is_none());
+            assert!(store.get_receipt(hash).is_none());
+        }
+        assert!(store.get_block_stats_range(2, 3).is_empty());
+
+        // Block 1 is untouched and unwinding above the head removes nothing
+        assert!(store.get_receipt(B256::repeat_byte(1)).is_some());
+        assert!(store.unwind_to(5).unwrap().is_empty());
+        assert_eq!(store.latest_block_number(), 1);
+        store.check_contiguous().unwrap();
+    }
+
+    #[test]
+    fn test_refresh_sees_unwind() {
+        let db = Backend::memory();
+        let writer = BlockStore::new(db.clone()).unwrap();
+        let genesis = StoredBlock::genesis(1);
+        let block = |number: u64, byte| StoredBlock {
+            number,
+            hash: B256::repeat_byte(byte),
+            ..genesis.clone()
+        };
+        writer.store_block(genesis.clone()).unwrap();
+        for number in 1..=3 {
+            writer.store_block(block(number, number as u8)).unwrap();
+        }
+
+        let replica = BlockStore::new(db).unwrap();
+        assert_eq!(replica.refresh_latest_block_number().unwrap(), 3);
+        for number in 1..=3 {
+            assert!(replica.get_block_by_number(number).is_some());
+        }
+
+        // The writer unwinds to 1 and stores a different block 2
+        writer.unwind_to(1).unwrap();
+        writer.store_block(block(2, 0x22)).unwrap();
+
+        assert_eq!(replica.refresh_latest_block_number().unwrap(), 2);
+        assert_eq!(replica.latest_block_number(), 2);
+        assert!(replica.get_block_by_number(3).is_none());
+        assert_eq!(replica.get_block_by_number(2).unwrap().hash, B256::repeat_byte(0x22));
+        assert!(replica.get_block_by_hash(B256::repeat_byte(2)).is_none());
+        assert_eq!(replica.get_block_by_number(1).unwrap().hash, B256::repeat_byte(1));
+    }
+
+    #[test]
+    fn test_headers_range() {
+        let store = BlockStore::new(Backend::memory()).unwrap();
+        for number in [0, 1, 2, 3, 5] {
+            let block = StoredBlock { number, ..StoredBlock::genesis(1) };
+            store.store_block(block).unwrap();
+        }
+        let numbers = |start, count, direction| -> Vec<u64> {
+            let blocks = store.get_headers_range(start, count, direction);
+            blocks.iter().map(|block| block.number).collect()
+        };
+
+        assert_eq!(numbers(3, 512, RangeDirection::Descending), vec![3, 2, 1, 0]);
+        assert_eq!(numbers(1, 2, RangeDirection::Descending), vec![1, 0]);
+        assert_eq!(numbers(1, 2, RangeDirection::Ascending), vec![1, 2]);
+        // The missing block 4 ends the range in either direction
+        assert_eq!(numbers(2, 10, RangeDirection::Ascending), vec![2, 3]);
+        assert_eq!(numbers(5, 3, RangeDirection::Descending), vec![5]);
+        assert!(numbers(4, 3, RangeDirection::Descending).is_empty());
+        assert!(numbers(0, 0, RangeDirection::Ascending).is_empty());
+    }
+
+    #[test]
+    fn test_block_stats_range() {
+        let db = create_test_db();
+        let store = BlockStore::new(db).unwrap();
+
+        for number in [1, 2, 4] {
+            let stats = StoredBlockStats {
+                evm_tx_count: number,
+                gas_used: 21_000 * number,
+                ..Default::default()
+            };
+            store.store_block_stats(number, stats).unwrap();
+        }
+
+        let range = store.get_block_stats_range(2, 4);
+        let numbers: Vec<_> = range.iter().map(|(number, _)| *number).collect();
+        assert_eq!(numbers, vec![2, 4]);
+        assert_eq!(range[1].1.gas_used, 84_000);
+        assert_eq!(range[1].1.tx_count(), 4);
+    }
+    #[test]
+    fn test_record_sibling() {
+        let db = create_test_db();
+        let store = BlockStore::new(db).unwrap();
+        let genesis = StoredBlock::genesis(1);
+        store.store_block(genesis.clone()).unwrap();
+        store.store_block(StoredBlock { number: 1, ..genesis.clone() }).unwrap();
+
+        let sibling = |byte, signer| StoredSiblingBlock {
+            hash: B256::repeat_byte(byte),
+            signer,
+            peer: Default::default(),
+            received_at_ms: byte as u64,
+        };
+
+        // The canonical block is not its own sibling
+        let canonical = StoredSiblingBlock { hash: genesis.hash, ..sibling(0, None) };
+        assert!(!store.record_sibling(1, canonical).unwrap());
+
+        assert!(store.record_sibling(1, sibling(1, None)).unwrap());
+        assert!(!store.record_sibling(1, sibling(1, None)).unwrap());
+        // A later sighting adds the signer but keeps the first time
+        let signer = address!("2222222222222222222222222222222222222222");
+        let mut resent = sibling(1, Some(signer));
+        resent.received_at_ms = 99;
+        assert!(store.record_sibling(1, resent).unwrap());
+
+        for byte in 2..=MAX_SIBLINGS_PER_HEIGHT as u8 + 1 {
+            store.record_sibling(1, sibling(byte, None)).unwrap();
+        }
+
+        let range = store.get_siblings_range(0, 5);
+        assert_eq!(range.len(), 1);
+        let (number, siblings) = &range[0];
+        assert_eq!(*number, 1);
+        assert_eq!(siblings.blocks.len(), MAX_SIBLINGS_PER_HEIGHT);
+        assert_eq!(siblings.blocks[0].signer, Some(signer));
+        assert_eq!(siblings.blocks[0].received_at_ms, 1);
     }
 }
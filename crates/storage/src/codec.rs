@@ -0,0 +1,366 @@
+//! Versioned binary codec for table values
+//!
+//! Every value is written as a one-byte codec version followed by its fields.
+//! Integers are big-endian; byte strings and lists carry a `u32` length
+//! prefix. Decoding is strict: an unknown version, a short buffer or trailing
+//! bytes fail with [`DatabaseError::Decode`] instead of panicking or silently
+//! truncating. Values written before the codec existed are rewritten by
+//! [`crate::migrations`].
+//!
+//! Large byte strings can be written with [`Writer::compressed_bytes`], which
+//! prefixes a format flag and stores them zstd-compressed when that saves
+//! space. Version 2 introduced the flag; values written by version 1 are still
+//! read.
+
+use alloy_primitives::{Address, B256, U256};
+use bytes::BufMut;
+use reth_db_api::DatabaseError;
+
+/// Codec version written in front of every value
+pub const CODEC_VERSION: u8 = 2;
+
+/// Byte strings shorter than this are never compressed
+pub const COMPRESSION_THRESHOLD: usize = 128;
+
+/// zstd level of compressed byte strings
+const ZSTD_LEVEL: i32 = 3;
+
+/// Largest decompressed length accepted, so a corrupt length cannot exhaust memory
+const MAX_DECOMPRESSED_LEN: usize = 64 * 1024 * 1024;
+
+/// Format flag of byte strings stored as is
+const FORMAT_RAW: u8 = 0;
+
+/// Format flag of zstd-compressed byte strings
+const FORMAT_ZSTD: u8 = 1;
+
+/// Table value with a versioned binary encoding
+pub trait TableValue: Sized {
+    /// Write the value's fields
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>);
+
+    /// Read fields written by codec `version`
+    fn decode_fields(r: &mut Reader<'_>, version: u8) -> Result<Self, DatabaseError>;
+}
+
+/// Encode a value with the current codec version
+pub fn encode<T: TableValue, B: BufMut>(value: &T, buf: &mut B) {
+    buf.put_u8(CODEC_VERSION);
+    value.encode_fields(&mut Writer { buf });
+}
+
+/// Decode a value, rejecting unknown versions and trailing bytes
+pub fn decode<T: TableValue>(bytes: &[u8]) -> Result<T, DatabaseError> {
+    let mut r = Reader::new(bytes);
+    let version = r.u8()?;
+    if version == 0 || version > CODEC_VERSION {
+        return Err(DatabaseError::Decode);
+    }
+    let value = T::decode_fields(&mut r, version)?;
+    r.finish()?;
+    Ok(value)
+}
+
+/// Implement `Compress` and `Decompress` through the versioned codec
+macro_rules! impl_compression_for_table_value {
+    ($($ty:ty),+ $(,)?) => {$(
+        impl reth_db_api::table::Compress for $ty {
+            type Compressed = Vec<u8>;
+
+            fn compress_to_buf<B: bytes::BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
+                $crate::codec::encode(self, buf);
+            }
+        }
+
+        impl reth_db_api::table::Decompress for $ty {
+            fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
+                $crate::codec::decode(value)
+            }
+        }
+    )+};
+}
+pub(crate) use impl_compression_for_table_value;
+
+/// Writes value fields
+pub struct Writer<'a, B> {
+    buf: &'a mut B,
+}
+
+impl<B: BufMut> Writer<'_, B> {
+    pub fn u8(&mut self, value: u8) {
+        self.buf.put_u8(value);
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.buf.put_u64(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.buf.put_u8(value as u8);
+    }
+
+    /// Fixed-size bytes, written without a length
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.buf.put_slice(bytes);
+    }
+
+    pub fn address(&mut self, value: &Address) {
+        self.raw(value.as_slice());
+    }
+
+    pub fn b256(&mut self, value: &B256) {
+        self.raw(value.as_slice());
+    }
+
+    pub fn u256(&mut self, value: &U256) {
+        self.raw(&value.to_be_bytes::<32>());
+    }
+
+    /// Length-prefixed bytes
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.raw(bytes);
+    }
+
+    /// Format flag and bytes, zstd-compressed if they are large and compress well
+    ///
+    /// Compressed strings are written as the uncompressed length followed by
+    /// the length-prefixed zstd frame.
+    pub fn compressed_bytes(&mut self, bytes: &[u8]) {
+        if bytes.len() >= COMPRESSION_THRESHOLD {
+            if let Ok(compressed) = zstd::bulk::compress(bytes, ZSTD_LEVEL) {
+                // The extra length field has to pay for itself
+                if compressed.len() + 4 < bytes.len() {
+                    self.u8(FORMAT_ZSTD);
+                    self.len(bytes.len());
+                    self.bytes(&compressed);
+                    return;
+                }
+            }
+        }
+        self.u8(FORMAT_RAW);
+        self.bytes(bytes);
+    }
+
+    /// Length-prefixed list
+    pub fn list<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.len(items.len());
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    fn len(&mut self, len: usize) {
+        let len = u32::try_from(len).expect("table value longer than u32::MAX");
+        self.buf.put_u32(len);
+    }
+}
+
+/// Reads value fields, failing on short input
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    /// Bytes not read yet
+    pub fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Fail unless every byte was read
+    pub fn finish(&self) -> Result<(), DatabaseError> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(DatabaseError::Decode)
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DatabaseError> {
+        if self.buf.len() < len {
+            return Err(DatabaseError::Decode);
+        }
+        let (head, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    /// Fixed-size bytes
+    pub fn raw<const N: usize>(&mut self) -> Result<[u8; N], DatabaseError> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, DatabaseError> {
+        Ok(self.raw::<1>()?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, DatabaseError> {
+        Ok(u32::from_be_bytes(self.raw()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, DatabaseError> {
+        Ok(u64::from_be_bytes(self.raw()?))
+    }
+
+    pub fn bool(&mut self) -> Result<bool, DatabaseError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DatabaseError::Decode),
+        }
+    }
+
+    pub fn address(&mut self) -> Result<Address, DatabaseError> {
+        Ok(Address::from(self.raw::<20>()?))
+    }
+
+    pub fn b256(&mut self) -> Result<B256, DatabaseError> {
+        Ok(B256::from(self.raw::<32>()?))
+    }
+
+    pub fn u256(&mut self) -> Result<U256, DatabaseError> {
+        Ok(U256::from_be_bytes(self.raw::<32>()?))
+    }
+
+    /// Length-prefixed bytes
+    pub fn bytes(&mut self) -> Result<Vec<u8>, DatabaseError> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    /// Bytes written by [`Writer::compressed_bytes`]
+    pub fn compressed_bytes(&mut self) -> Result<Vec<u8>, DatabaseError> {
+        match self.u8()? {
+            FORMAT_RAW => self.bytes(),
+            FORMAT_ZSTD => {
+                let len = self.u32()? as usize;
+                let compressed_len = self.u32()? as usize;
+                let compressed = self.take(compressed_len)?;
+                if len > MAX_DECOMPRESSED_LEN {
+                    return Err(DatabaseError::Decode);
+                }
+                let bytes = zstd::bulk::decompress(compressed, len)
+                    .map_err(|_| DatabaseError::Decode)?;
+                if bytes.len() != len {
+                    return Err(DatabaseError::Decode);
+                }
+                Ok(bytes)
+            }
+            _ => Err(DatabaseError::Decode),
+        }
+    }
+
+    /// Length-prefixed list of items at least `min_size` bytes each
+    pub fn list<T>(
+        &mut self,
+        min_size: usize,
+        mut read: impl FnMut(&mut Self) -> Result<T, DatabaseError>,
+    ) -> Result<Vec<T>, DatabaseError> {
+        let count = self.u32()? as usize;
+        // Reject counts the remaining bytes cannot hold before allocating
+        if count.saturating_mul(min_size.max(1)) > self.buf.len() {
+            return Err(DatabaseError::Decode);
+        }
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            items.push(read(self)?);
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Sample {
+        flag: bool,
+        data: Vec<u8>,
+        hashes: Vec<B256>,
+    }
+
+    impl TableValue for Sample {
+        fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+            w.bool(self.flag);
+            w.bytes(&self.data);
+            w.list(&self.hashes, |w, hash| w.b256(hash));
+        }
+
+        fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+            Ok(Self { flag: r.bool()?, data: r.bytes()?, hashes: r.list(32, |r| r.b256())? })
+        }
+    }
+
+    #[test]
+    fn test_decode_is_strict() {
+        let sample =
+            Sample { flag: true, data: vec![1, 2, 3], hashes: vec![B256::repeat_byte(0xaa)] };
+        let mut encoded = Vec::new();
+        encode(&sample, &mut encoded);
+        assert_eq!(encoded[0], CODEC_VERSION);
+        assert_eq!(decode::<Sample>(&encoded).unwrap(), sample);
+
+        // Unknown versions
+        let mut future = encoded.clone();
+        future[0] = CODEC_VERSION + 1;
+        assert!(decode::<Sample>(&future).is_err());
+        future[0] = 0;
+        assert!(decode::<Sample>(&future).is_err());
+
+        // Trailing bytes
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(decode::<Sample>(&trailing).is_err());
+
+        // Bools other than 0 and 1
+        let mut bad_bool = encoded.clone();
+        bad_bool[1] = 2;
+        assert!(decode::<Sample>(&bad_bool).is_err());
+
+        // A list count larger than the input fails before allocating
+        let mut huge = vec![CODEC_VERSION, 0, 0, 0, 0, 0];
+        huge.extend_from_slice(&u32::MAX.to_be_bytes());
+        assert!(decode::<Sample>(&huge).is_err());
+    }
+
+    fn write_compressed(bytes: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        Writer { buf: &mut buf }.compressed_bytes(bytes);
+        buf
+    }
+
+    #[test]
+    fn test_compressed_bytes() {
+        // Repetitive input is compressed and read back unchanged
+        let code: Vec<u8> = (0..4096).map(|i| (i % 7) as u8).collect();
+        let encoded = write_compressed(&code);
+        assert_eq!(encoded[0], FORMAT_ZSTD);
+        assert!(encoded.len() < code.len() / 3);
+        let mut r = Reader::new(&encoded);
+        assert_eq!(r.compressed_bytes().unwrap(), code);
+        r.finish().unwrap();
+
+        // Short input is stored as is
+        let short = vec![0u8; COMPRESSION_THRESHOLD - 1];
+        let encoded = write_compressed(&short);
+        assert_eq!(encoded[0], FORMAT_RAW);
+        assert_eq!(Reader::new(&encoded).compressed_bytes().unwrap(), short);
+
+        // A wrong uncompressed length, a corrupt frame or an unknown flag fail
+        let encoded = write_compressed(&code);
+        let mut wrong_len = encoded.clone();
+        wrong_len[4] ^= 1;
+        assert!(Reader::new(&wrong_len).compressed_bytes().is_err());
+        let mut corrupt = encoded.clone();
+        corrupt[9] ^= 0xff;
+        assert!(Reader::new(&corrupt).compressed_bytes().is_err());
+        let mut unknown = encoded;
+        unknown[0] = 2;
+        assert!(Reader::new(&unknown).compressed_bytes().is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! Accounts known to only one VM
+//!
+//! DexVM counters are keyed by address like EVM accounts, but neither VM
+//! creates the other's account: a native DexVM transaction gives its sender a
+//! counter without an EVM account, and most EVM accounts never get a counter.
+//! [`account_consistency`] lists both kinds, for `GET /api/v1/dexvm/consistency`.
+
+use crate::traits::StateReader;
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Addresses present in only one VM's state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountConsistency {
+    pub evm_accounts: usize,
+    /// Addresses with a main or labeled counter
+    pub dexvm_accounts: usize,
+    /// Number of addresses with a counter but no EVM account
+    pub counter_only_count: usize,
+    /// Number of EVM accounts without any counter
+    pub evm_only_count: usize,
+    /// Lowest counter-only addresses, up to the requested limit
+    pub counter_only: Vec<Address>,
+    /// Lowest EVM-only addresses, up to the requested limit
+    pub evm_only: Vec<Address>,
+}
+
+/// Compare the EVM accounts and DexVM counters of `state`, listing up to `limit` of each kind
+pub fn account_consistency(state: &dyn StateReader, limit: usize) -> AccountConsistency {
+    let evm: BTreeSet<Address> = state.all_accounts().into_keys().collect();
+    let dexvm: BTreeSet<Address> = state
+        .all_counters()
+        .into_iter()
+        .filter(|&(_, value)| value > 0)
+        .map(|(address, _)| address)
+        .chain(state.all_labeled_counters().into_keys().map(|(address, _)| address))
+        .collect();
+
+    let counter_only: Vec<Address> = dexvm.difference(&evm).copied().collect();
+    let evm_only: Vec<Address> = evm.difference(&dexvm).copied().collect();
+    AccountConsistency {
+        evm_accounts: evm.len(),
+        dexvm_accounts: dexvm.len(),
+        counter_only_count: counter_only.len(),
+        evm_only_count: evm_only.len(),
+        counter_only: counter_only.into_iter().take(limit).collect(),
+        evm_only: evm_only.into_iter().take(limit).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{storage::DualvmStorage, traits::StateWriter};
+    use alloy_primitives::{B256, U256};
+
+    #[test]
+    fn test_lists_accounts_known_to_one_vm() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let state = storage.state.as_ref();
+        let (both, evm_only, counter_only, labeled_only) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+            Address::repeat_byte(4),
+        );
+        state.set_balance(both, U256::from(1)).unwrap();
+        state.set_counter(both, 1).unwrap();
+        state.set_balance(evm_only, U256::from(1)).unwrap();
+        state.set_counter(counter_only, 5).unwrap();
+        state.set_labeled_counters(&[(labeled_only, B256::repeat_byte(9), 2)]).unwrap();
+
+        let report = account_consistency(state, 10);
+        assert_eq!((report.evm_accounts, report.dexvm_accounts), (2, 3));
+        assert_eq!(report.counter_only, vec![counter_only, labeled_only]);
+        assert_eq!(report.evm_only, vec![evm_only]);
+
+        let report = account_consistency(state, 1);
+        assert_eq!(report.counter_only_count, 2);
+        assert_eq!(report.counter_only, vec![counter_only]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Database size tracking
+//!
+//! [`StorageGrowth`] keeps periodic samples of the MDBX data file size and
+//! estimates how fast it grows, and when a configured disk budget runs out at
+//! that rate. Samples are kept in memory for [`GROWTH_WINDOW`], so the estimate
+//! starts over after a restart.
+
+use crate::{
+    storage::{DualvmStorage, TableStats},
+    traits::BlockReader,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How far back samples are kept for the growth estimate
+pub const GROWTH_WINDOW: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Shortest span of samples a growth estimate is made from
+///
+/// MDBX grows its file in steps, so shorter spans mostly measure one step.
+pub const MIN_GROWTH_SPAN: Duration = Duration::from_secs(60 * 60);
+
+const SECS_PER_DAY: f64 = 24.0 * 60.0 * 60.0;
+
+/// Database size at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SizeSample {
+    /// Unix seconds
+    timestamp: u64,
+    file_size: u64,
+}
+
+/// Size samples of one database and the disk budget it must fit in
+#[derive(Debug, Default)]
+pub struct StorageGrowth {
+    samples: Mutex<VecDeque<SizeSample>>,
+    /// Bytes the data file may use; 0 when there is no budget
+    budget: AtomicU64,
+}
+
+impl StorageGrowth {
+    /// Set the bytes the data file may use, or remove the budget with `None`
+    pub fn set_budget(&self, budget: Option<u64>) {
+        self.budget.store(budget.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Bytes the data file may use, if there is a budget
+    pub fn budget(&self) -> Option<u64> {
+        Some(self.budget.load(Ordering::Relaxed)).filter(|&budget| budget > 0)
+    }
+
+    /// Record the current size of `storage`; in-memory storage is not sampled
+    pub fn sample(&self, storage: &DualvmStorage) {
+        if !storage.is_in_memory() {
+            self.record(unix_now(), storage.file_size());
+        }
+    }
+
+    /// Record a `file_size` seen at `timestamp` (unix seconds)
+    pub fn record(&self, timestamp: u64, file_size: u64) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples.push_back(SizeSample { timestamp, file_size });
+        let oldest = timestamp.saturating_sub(GROWTH_WINDOW.as_secs());
+        while samples.front().is_some_and(|sample| sample.timestamp < oldest) {
+            samples.pop_front();
+        }
+    }
+
+    /// Growth of the data file in bytes per day over the kept samples
+    ///
+    /// `None` until the samples span [`MIN_GROWTH_SPAN`]. Negative after a
+    /// compaction shrank the file.
+    pub fn bytes_per_day(&self) -> Option<i64> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let (first, last) = (samples.front()?, samples.back()?);
+        let span = last.timestamp.saturating_sub(first.timestamp);
+        if span < MIN_GROWTH_SPAN.as_secs() {
+            return None;
+        }
+        let grown = last.file_size as f64 - first.file_size as f64;
+        Some((grown * SECS_PER_DAY / span as f64) as i64)
+    }
+
+    /// Days until a data file of `file_size` bytes fills the budget at the current growth
+    ///
+    /// `None` without a budget or while the file is not growing; 0 once it is over budget.
+    pub fn days_until_budget(&self, file_size: u64) -> Option<f64> {
+        let budget = self.budget()?;
+        if file_size >= budget {
+            return Some(0.0);
+        }
+        let per_day = self.bytes_per_day().filter(|&per_day| per_day > 0)?;
+        Some((budget - file_size) as f64 / per_day as f64)
+    }
+
+    /// Size, per-table usage and growth of `storage`
+    pub fn info(&self, storage: &DualvmStorage) -> Result<StorageInfo> {
+        let stats = storage.stats()?;
+        let file_size = stats.file_size_bytes;
+        Ok(StorageInfo {
+            file_size_bytes: file_size,
+            freelist_bytes: stats.freelist_pages * stats.page_size,
+            head_block: storage.blocks.latest_block_number(),
+            tables: stats.tables,
+            growth_bytes_per_day: self.bytes_per_day(),
+            disk_budget_bytes: self.budget(),
+            days_until_budget: self.days_until_budget(file_size),
+        })
+    }
+}
+
+/// Size and growth of the database, as served by `dex_storageInfo`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageInfo {
+    pub file_size_bytes: u64,
+    /// Free pages inside the file, reused before it grows; compaction returns them
+    pub freelist_bytes: u64,
+    pub head_block: u64,
+    pub tables: Vec<TableStats>,
+    /// `None` until the node has sampled its size for an hour
+    pub growth_bytes_per_day: Option<i64>,
+    pub disk_budget_bytes: Option<u64>,
+    /// `None` without a budget or while the file is not growing
+    pub days_until_budget: Option<f64>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60;
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn test_growth_and_budget_estimate() {
+        let growth = StorageGrowth::default();
+        assert_eq!(growth.bytes_per_day(), None);
+
+        // Too short a span for an estimate
+        growth.record(1_000_000, GIB);
+        growth.record(1_000_000 + HOUR / 2, GIB + 1);
+        assert_eq!(growth.bytes_per_day(), None);
+
+        // 1 GiB in 12 hours
+        growth.record(1_000_000 + 12 * HOUR, 2 * GIB);
+        assert_eq!(growth.bytes_per_day(), Some(2 * GIB as i64));
+
+        assert_eq!(growth.days_until_budget(2 * GIB), None);
+        growth.set_budget(Some(10 * GIB));
+        assert_eq!(growth.budget(), Some(10 * GIB));
+        assert_eq!(growth.days_until_budget(2 * GIB), Some(4.0));
+        assert_eq!(growth.days_until_budget(11 * GIB), Some(0.0));
+
+        // Samples older than the window are dropped, so the estimate follows recent growth
+        let later = 1_000_000 + 12 * HOUR + GROWTH_WINDOW.as_secs();
+        growth.record(later, 2 * GIB);
+        assert_eq!(growth.bytes_per_day(), Some(0));
+        assert_eq!(growth.days_until_budget(2 * GIB), None);
+
+        growth.set_budget(None);
+        assert_eq!(growth.budget(), None);
+    }
+
+    #[test]
+    fn test_in_memory_storage_is_not_sampled() {
+        let growth = StorageGrowth::default();
+        let storage = DualvmStorage::in_memory().unwrap();
+        growth.sample(&storage);
+        assert!(growth.samples.lock().unwrap().is_empty());
+        assert!(growth.info(&storage).is_err());
+    }
+}
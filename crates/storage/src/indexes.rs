@@ -0,0 +1,134 @@
+//! Rebuilding derived tables
+//!
+//! Some tables only index data held elsewhere: [`DualvmTxHashes`] locates
+//! the transactions listed in [`DualvmBlocks`], and [`DualvmCounterRanks`]
+//! orders the non-zero values of [`DualvmCounters`]. [`rebuild_indexes`]
+//! recomputes them from those tables and rewrites only the entries that
+//! differ, so running it on a healthy database changes nothing.
+//!
+//! Receipts, block stats and access sets come from executing blocks and are
+//! not covered; `dex-reth replay` re-executes blocks to check them.
+
+use crate::{
+    backend::{TableReader, TxMut},
+    storage::DualvmStorage,
+    tables::{
+        CounterRankKey, DualvmBlocks, DualvmCounterRanks, DualvmCounters, DualvmTxHashes,
+        StoredTxInfo,
+    },
+};
+use eyre::Result;
+use reth_db_api::table::Table;
+use std::collections::BTreeMap;
+
+/// Outcome of rebuilding one derived table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebuiltIndex {
+    pub table: &'static str,
+    /// Entries the table holds after the rebuild
+    pub entries: u64,
+    /// Entries that were missing or held a wrong value
+    pub written: u64,
+    /// Entries that no source data backs
+    pub removed: u64,
+}
+
+/// Recompute every derived table from canonical data, in one write transaction
+pub fn rebuild_indexes(storage: &DualvmStorage) -> Result<Vec<RebuiltIndex>> {
+    let tx = storage.db.tx_mut()?;
+
+    let mut tx_hashes = BTreeMap::new();
+    tx.walk::<DualvmBlocks>(None, |block_number, block| {
+        for (tx_index, hash) in block.transaction_hashes.into_iter().enumerate() {
+            tx_hashes.insert(hash, StoredTxInfo { block_number, tx_index: tx_index as u64 });
+        }
+        true
+    })?;
+
+    let counter_ranks = tx
+        .range::<DualvmCounters>(..)?
+        .into_iter()
+        .filter(|(_, counter)| counter.value != 0)
+        .map(|(address, counter)| (CounterRankKey { value: counter.value, address }, counter))
+        .collect();
+
+    let rebuilt = vec![
+        rebuild::<DualvmTxHashes>(&tx, tx_hashes)?,
+        rebuild::<DualvmCounterRanks>(&tx, counter_ranks)?,
+    ];
+    tx.commit()?;
+    Ok(rebuilt)
+}
+
+/// Make `T` hold exactly `expected`
+fn rebuild<T: Table>(tx: &TxMut, expected: BTreeMap<T::Key, T::Value>) -> Result<RebuiltIndex>
+where
+    T::Value: Clone + PartialEq,
+{
+    let mut existing = BTreeMap::new();
+    tx.walk::<T>(None, |key, value| {
+        existing.insert(key, value);
+        true
+    })?;
+
+    let mut removed = 0;
+    for key in existing.keys().filter(|key| !expected.contains_key(*key)) {
+        tx.delete::<T>(key.clone())?;
+        removed += 1;
+    }
+    let mut written = 0;
+    for (key, value) in &expected {
+        if existing.get(key) != Some(value) {
+            tx.put::<T>(key.clone(), value.clone())?;
+            written += 1;
+        }
+    }
+    Ok(RebuiltIndex { table: T::NAME, entries: expected.len() as u64, written, removed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        block_store::StoredBlock,
+        tables::StoredCounter,
+        traits::{BlockReader, BlockWriter, StateReader, StateWriter},
+    };
+    use alloy_primitives::{Address, B256};
+
+    #[test]
+    fn test_rebuild_restores_derived_tables() {
+        let storage = DualvmStorage::in_memory().unwrap();
+        let hashes = [B256::repeat_byte(1), B256::repeat_byte(2)];
+        let mut block = StoredBlock::genesis(1);
+        block.number = 1;
+        block.transaction_hashes = hashes.to_vec();
+        block.transaction_count = 2;
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        storage.blocks.store_block(block).unwrap();
+        let (low, high) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        storage.state.set_counters(&[(low, 3), (high, 9)]).unwrap();
+
+        // A healthy database is left alone
+        let rebuilt = rebuild_indexes(&storage).unwrap();
+        assert!(rebuilt.iter().all(|index| index.written == 0 && index.removed == 0));
+        assert_eq!(rebuilt[0].entries, 2);
+
+        // Lose an index entry, add a stale one and a rank that no counter backs
+        let tx = storage.db.tx_mut().unwrap();
+        tx.delete::<DualvmTxHashes>(hashes[1]).unwrap();
+        tx.put::<DualvmTxHashes>(B256::repeat_byte(9), StoredTxInfo::default()).unwrap();
+        let stale = CounterRankKey { value: 100, address: low };
+        tx.put::<DualvmCounterRanks>(stale, StoredCounter { value: 100 }).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(storage.state.top_counters(1), vec![(low, 100)]);
+
+        let rebuilt = rebuild_indexes(&storage).unwrap();
+        assert_eq!((rebuilt[0].written, rebuilt[0].removed), (1, 1));
+        assert_eq!((rebuilt[1].written, rebuilt[1].removed), (0, 1));
+        let info = storage.blocks.get_tx_info(hashes[1]).unwrap();
+        assert_eq!((info.block_number, info.tx_index), (1, 1));
+        assert!(storage.blocks.get_tx_info(B256::repeat_byte(9)).is_none());
+        assert_eq!(storage.state.top_counters(2), vec![(high, 9), (low, 3)]);
+    }
+}
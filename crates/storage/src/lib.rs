@@ -1,16 +1,40 @@
 //! DualVM storage
 //!
-//! MDBX-based storage for the dual VM system
+//! MDBX-based storage for the dual VM system, with an in-memory backend for
+//! tests and ephemeral nodes
 
+pub mod backend;
+pub mod backup;
+pub mod block_cache;
 pub mod block_store;
+pub mod codec;
+pub mod consistency;
+pub mod growth;
+pub mod indexes;
+pub mod migrations;
 pub mod state_store;
 pub mod storage;
 pub mod tables;
+pub mod traits;
 
-pub use block_store::{BlockStore, StoredBlock};
-pub use state_store::{AccountState, StateStore};
-pub use storage::DualvmStorage;
+pub use backend::{Backend, MemoryDb, TableReader};
+pub use backup::{BackupReport, Backups, DEFAULT_BACKUPS_KEPT};
+pub use block_cache::{BlockCache, BlockCacheStats, DEFAULT_BLOCK_CACHE_SIZE};
+pub use block_store::{BlockStore, RangeDirection, RecoveryReport, StoredBlock, UnwoundBlock};
+pub use codec::CODEC_VERSION;
+pub use consistency::{account_consistency, AccountConsistency};
+pub use growth::{StorageGrowth, StorageInfo, GROWTH_WINDOW, MIN_GROWTH_SPAN};
+pub use indexes::{rebuild_indexes, RebuiltIndex};
+pub use migrations::SCHEMA_VERSION;
+pub use state_store::{AccountState, FrozenState, StateStore};
+pub use storage::{CompactionReport, DatabaseStats, DualvmStorage, TableStats, MDBX_DATA_FILE};
 pub use tables::{
-    DualvmAccounts, DualvmBlocks, DualvmCounters, DualvmStorage as DualvmStorageTable,
-    DualvmTableSet, DualvmTransactions, DualvmTxHashes, StoredTransaction,
+    CounterRankKey, DualvmAccessSets, DualvmAccounts, DualvmBlockStats, DualvmBlocks,
+    DualvmBytecodes, DualvmCounterRanks, DualvmCounters, DualvmDexVmNonces, DualvmDexVmReceipts,
+    DualvmLabeledCounters, DualvmMetadata, DualvmReceipts, DualvmSiblingBlocks,
+    LabeledCounterKey, DualvmStorage as DualvmStorageTable, DualvmTableSet, DualvmTransactions,
+    DualvmTxHashes, StoredAccessSet, StoredBlockStats, StoredBytecode, StoredDexVmOperation,
+    StoredDexVmReceipts, StoredLog, StoredReceipt, StoredSiblingBlock, StoredSiblingBlocks,
+    StoredTransaction,
 };
+pub use traits::{BlockReader, BlockWriter, StateReader, StateWriter};
@@ -0,0 +1,278 @@
+//! Database schema migrations
+//!
+//! The schema version is kept in [`DualvmMetadata`] under
+//! [`SCHEMA_VERSION_KEY`]. Databases created before it existed count as
+//! version 0. [`migrate`] runs every pending migration in a single write
+//! transaction, so an interrupted upgrade leaves the old schema untouched.
+
+use crate::{
+    backend::{Backend, TableReader, TxMut},
+    codec::Reader,
+    tables::{
+        CounterRankKey, DualvmAccessSets, DualvmAccounts, DualvmBlockStats, DualvmBlocks,
+        DualvmBytecodes, DualvmCounterRanks, DualvmCounters, DualvmMetadata,
+        DualvmStorage as StorageTable, DualvmTransactions, DualvmTxHashes, StoredMetadata,
+        SCHEMA_VERSION_KEY,
+    },
+};
+use eyre::Result;
+use reth_db_api::{
+    table::Table,
+    tables::{RawTable, RawValue},
+};
+
+/// Schema version written by this build
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// Upgrade from the version at its index to the next one, returning the entries rewritten
+type Migration = fn(&TxMut) -> Result<u64>;
+
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] =
+    [reencode_legacy_values, build_counter_ranks];
+
+/// Bring the database to [`SCHEMA_VERSION`], returning the version it was at
+///
+/// A database without a recorded version is taken as version 0 unless
+/// `is_new` says it was just created.
+pub fn migrate(db: &Backend, is_new: bool) -> Result<u64> {
+    let tx = db.tx_mut()?;
+    let stored = tx.get::<DualvmMetadata>(SCHEMA_VERSION_KEY)?.map(|meta| meta.value);
+    let from = match stored {
+        Some(version) => version,
+        None if is_new => SCHEMA_VERSION,
+        None => 0,
+    };
+    if from > SCHEMA_VERSION {
+        return Err(eyre::eyre!(
+            "Database schema version {} is newer than this node supports ({})",
+            from,
+            SCHEMA_VERSION
+        ));
+    }
+
+    for version in from..SCHEMA_VERSION {
+        let rewritten = MIGRATIONS[version as usize](&tx)?;
+        tracing::info!(
+            "Migrated database schema {} -> {} ({} entries rewritten)",
+            version,
+            version + 1,
+            rewritten
+        );
+    }
+
+    if stored != Some(SCHEMA_VERSION) {
+        tx.put::<DualvmMetadata>(SCHEMA_VERSION_KEY, StoredMetadata { value: SCHEMA_VERSION })?;
+    }
+    tx.commit()?;
+    Ok(from)
+}
+
+/// Fail unless the database is at [`SCHEMA_VERSION`], without migrating
+///
+/// Used for read-only access, where pending migrations cannot run.
+pub fn check_current(db: &Backend) -> Result<()> {
+    let tx = db.tx()?;
+    let version = tx.get::<DualvmMetadata>(SCHEMA_VERSION_KEY)?.map(|meta| meta.value);
+    if version != Some(SCHEMA_VERSION) {
+        return Err(eyre::eyre!(
+            "Database schema version {} does not match this node ({}); \
+             start the node with write access once to migrate it",
+            version.map_or_else(|| "0".to_string(), |v| v.to_string()),
+            SCHEMA_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// 0 -> 1: rewrite fixed-layout values in the versioned codec
+fn reencode_legacy_values(tx: &TxMut) -> Result<u64> {
+    Ok(reencode::<DualvmBlocks>(tx, legacy::block)? +
+        reencode::<DualvmAccounts>(tx, legacy::account)? +
+        reencode::<DualvmCounters>(tx, legacy::counter)? +
+        reencode::<StorageTable>(tx, legacy::storage_value)? +
+        reencode::<DualvmTxHashes>(tx, legacy::tx_info)? +
+        reencode::<DualvmTransactions>(tx, legacy::transaction)? +
+        reencode::<DualvmBytecodes>(tx, legacy::bytecode)? +
+        reencode::<DualvmBlockStats>(tx, legacy::block_stats)? +
+        reencode::<DualvmAccessSets>(tx, legacy::access_set)?)
+}
+
+/// 1 -> 2: index existing non-zero counters in the ranking table
+fn build_counter_ranks(tx: &TxMut) -> Result<u64> {
+    let counters = tx.range::<DualvmCounters>(..)?;
+
+    let mut count = 0;
+    for (address, counter) in counters.into_iter().filter(|(_, counter)| counter.value != 0) {
+        tx.put::<DualvmCounterRanks>(CounterRankKey { value: counter.value, address }, counter)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Decode every raw value of `T` with `decode` and store it again
+fn reencode<T: Table>(
+    tx: &TxMut,
+    decode: fn(&mut Reader<'_>) -> Option<T::Value>,
+) -> Result<u64> {
+    let entries = tx.range::<RawTable<T>>(..)?;
+
+    let count = entries.len() as u64;
+    for (key, value) in entries {
+        let decoded = decode(&mut Reader::new(value.raw_value()))
+            .ok_or_else(|| eyre::eyre!("Corrupt {} entry during migration", T::NAME))?;
+        tx.put::<RawTable<T>>(key, RawValue::new(decoded))?;
+    }
+    Ok(count)
+}
+
+/// Decoders for the unversioned layouts written before schema version 1
+mod legacy {
+    use crate::{
+        codec::Reader,
+        tables::{
+            StoredAccessSet, StoredBlockStats, StoredBytecode, StoredCounter,
+            StoredDualvmAccount, StoredDualvmBlock, StoredStorageValue, StoredTransaction,
+            StoredTxInfo,
+        },
+    };
+
+    pub(super) fn block(r: &mut Reader<'_>) -> Option<StoredDualvmBlock> {
+        let mut block = StoredDualvmBlock {
+            hash: r.b256().ok()?,
+            parent_hash: r.b256().ok()?,
+            timestamp: r.u64().ok()?,
+            gas_limit: r.u64().ok()?,
+            gas_used: r.u64().ok()?,
+            miner: r.address().ok()?,
+            evm_state_root: r.b256().ok()?,
+            dexvm_state_root: r.b256().ok()?,
+            combined_state_root: r.b256().ok()?,
+            transaction_count: r.u64().ok()?,
+            ..Default::default()
+        };
+        // The oldest blocks end here; the signature and then the
+        // transaction hashes were appended later
+        if r.remaining() >= 65 {
+            block.signature = r.raw().ok()?;
+            if r.remaining() >= 4 {
+                block.transaction_hashes = r.list(32, |r| r.b256()).ok()?;
+            }
+        }
+        Some(block)
+    }
+
+    pub(super) fn account(r: &mut Reader<'_>) -> Option<StoredDualvmAccount> {
+        Some(StoredDualvmAccount {
+            balance: r.u256().ok()?,
+            nonce: r.u64().ok()?,
+            code_hash: r.b256().ok()?,
+            is_contract: r.u8().ok()? != 0,
+        })
+    }
+
+    pub(super) fn counter(r: &mut Reader<'_>) -> Option<StoredCounter> {
+        Some(StoredCounter { value: r.u64().ok()? })
+    }
+
+    pub(super) fn storage_value(r: &mut Reader<'_>) -> Option<StoredStorageValue> {
+        Some(StoredStorageValue { value: r.u256().ok()? })
+    }
+
+    pub(super) fn tx_info(r: &mut Reader<'_>) -> Option<StoredTxInfo> {
+        Some(StoredTxInfo { block_number: r.u64().ok()?, tx_index: r.u64().ok()? })
+    }
+
+    pub(super) fn transaction(r: &mut Reader<'_>) -> Option<StoredTransaction> {
+        Some(StoredTransaction { rlp_bytes: r.bytes().ok()? })
+    }
+
+    pub(super) fn bytecode(r: &mut Reader<'_>) -> Option<StoredBytecode> {
+        Some(StoredBytecode { code: r.bytes().ok()? })
+    }
+
+    pub(super) fn block_stats(r: &mut Reader<'_>) -> Option<StoredBlockStats> {
+        Some(StoredBlockStats {
+            evm_tx_count: r.u64().ok()?,
+            cross_vm_tx_count: r.u64().ok()?,
+            dexvm_tx_count: r.u64().ok()?,
+            native_dexvm_tx_count: r.u64().ok()?,
+            failed_tx_count: r.u64().ok()?,
+            dexvm_ops_count: r.u64().ok()?,
+            gas_used: r.u64().ok()?,
+            execution_micros: r.u64().ok()?,
+        })
+    }
+
+    pub(super) fn access_set(r: &mut Reader<'_>) -> Option<StoredAccessSet> {
+        Some(StoredAccessSet {
+            accounts_read: r.list(20, |r| r.address()).ok()?,
+            accounts_written: r.list(20, |r| r.address()).ok()?,
+            slots_read: r.list(52, |r| Ok((r.address()?, r.b256()?))).ok()?,
+            slots_written: r.list(52, |r| Ok((r.address()?, r.b256()?))).ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        storage::DualvmStorage,
+        traits::{BlockReader, StateReader},
+    };
+    use alloy_primitives::{address, B256, U256};
+    use reth_db_api::tables::RawKey;
+    use tempfile::tempdir;
+
+    fn put_raw<T: Table>(storage: &DualvmStorage, key: T::Key, bytes: Vec<u8>) {
+        let tx = storage.db.tx_mut().unwrap();
+        tx.put::<RawTable<T>>(RawKey::new(key), RawValue::from_vec(bytes)).unwrap();
+        tx.commit().unwrap();
+    }
+
+    fn schema_version(storage: &DualvmStorage) -> Option<u64> {
+        let tx = storage.db.tx().unwrap();
+        tx.get::<DualvmMetadata>(SCHEMA_VERSION_KEY).unwrap().map(|meta| meta.value)
+    }
+
+    #[test]
+    fn test_migrate_legacy_values() {
+        let dir = tempdir().unwrap();
+        let addr = address!("1111111111111111111111111111111111111111");
+        let block_hash = B256::repeat_byte(0xab);
+
+        let storage = DualvmStorage::new(dir.path()).unwrap();
+        assert_eq!(schema_version(&storage), Some(SCHEMA_VERSION));
+
+        // Write values in the pre-versioning layouts and forget the schema version
+        put_raw::<DualvmCounters>(&storage, addr, 7u64.to_be_bytes().to_vec());
+        let mut account = U256::from(1000).to_be_bytes::<32>().to_vec();
+        account.extend_from_slice(&3u64.to_be_bytes());
+        account.extend_from_slice(&[0u8; 33]);
+        put_raw::<DualvmAccounts>(&storage, addr, account);
+        // A 212-byte block from before signatures were stored
+        let mut block = block_hash.to_vec();
+        block.resize(212, 0);
+        put_raw::<DualvmBlocks>(&storage, 1, block);
+        let tx = storage.db.tx_mut().unwrap();
+        tx.delete::<DualvmMetadata>(SCHEMA_VERSION_KEY).unwrap();
+        tx.commit().unwrap();
+        drop(storage);
+
+        let storage = DualvmStorage::new(dir.path()).unwrap();
+        assert_eq!(schema_version(&storage), Some(SCHEMA_VERSION));
+        assert_eq!(storage.state.get_counter(&addr), 7);
+        assert_eq!(storage.state.top_counters(10), vec![(addr, 7)]);
+        assert_eq!(storage.state.get_balance(&addr), U256::from(1000));
+        assert_eq!(storage.state.get_nonce(&addr), 3);
+        let block = storage.blocks.get_block_by_number(1).unwrap();
+        assert_eq!(block.hash, block_hash);
+
+        // A database from a newer node is refused
+        let tx = storage.db.tx_mut().unwrap();
+        tx.put::<DualvmMetadata>(SCHEMA_VERSION_KEY, StoredMetadata { value: 99 }).unwrap();
+        tx.commit().unwrap();
+        drop(storage);
+        assert!(DualvmStorage::new(dir.path()).is_err());
+    }
+}
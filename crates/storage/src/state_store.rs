@@ -1,18 +1,29 @@
-//! State storage module using MDBX database
-
-use crate::tables::{
-    DualvmAccounts, DualvmCounters, DualvmStorage, StorageKey, StoredCounter, StoredDualvmAccount,
-    StoredStorageValue,
+//! State storage module
+//!
+//! The account root of a block can be computed in the background while the
+//! next block already writes: [`StateStore::freeze`] tracks the accounts
+//! written after it (the dirty set) with their values at the time of the
+//! freeze, and [`FrozenState::state_root`] puts those values back in place of
+//! whatever it reads for them.
+
+use crate::{
+    backend::{Backend, TableReader, TxMut},
+    tables::{
+        CounterRankKey, DualvmAccounts, DualvmBytecodes, DualvmCounterRanks, DualvmCounters,
+        DualvmDexVmNonces, DualvmLabeledCounters, DualvmStorage, LabeledCounterKey, StorageKey,
+        StoredBytecode, StoredCounter, StoredDualvmAccount, StoredStorageValue,
+    },
+    traits::{StateReader, StateWriter},
 };
 use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
 use eyre::Result;
-use reth_db::DatabaseEnv;
-use reth_db_api::{
-    cursor::{DbCursorRO, DbCursorRW},
-    database::Database,
-    transaction::{DbTx, DbTxMut},
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
 };
-use std::{collections::HashMap, sync::Arc};
+
+/// Values at freeze time of the accounts written since, `None` if absent then
+type DirtySet = HashMap<Address, Option<StoredDualvmAccount>>;
 
 /// Account state representation
 #[derive(Debug, Clone, Default)]
@@ -60,57 +71,323 @@ impl From<&AccountState> for StoredDualvmAccount {
     }
 }
 
-/// State store using MDBX database
+/// State store over a storage [`Backend`]
 pub struct StateStore {
-    db: Arc<DatabaseEnv>,
+    db: Backend,
+    /// Dirty set of the pending [`FrozenState`], if there is one
+    frozen: Mutex<Option<DirtySet>>,
 }
 
 impl StateStore {
     /// Create new state store with database
-    pub fn new(db: Arc<DatabaseEnv>) -> Self {
-        Self { db }
+    pub fn new(db: impl Into<Backend>) -> Self {
+        Self { db: db.into(), frozen: Mutex::new(None) }
+    }
+
+    /// Freeze the account state for computing its root while writes go on
+    ///
+    /// Only one freeze can be pending at a time; it ends when the returned
+    /// [`FrozenState`] is dropped.
+    pub fn freeze(self: &Arc<Self>) -> Result<FrozenState> {
+        let mut frozen = self.frozen.lock().unwrap_or_else(|e| e.into_inner());
+        if frozen.is_some() {
+            return Err(eyre::eyre!("Account state is already frozen"));
+        }
+        *frozen = Some(DirtySet::new());
+        Ok(FrozenState { store: Arc::clone(self) })
+    }
+
+    /// Remember an account's value before its first write since the freeze
+    ///
+    /// Called inside the write transaction, before the write, so the value is
+    /// recorded before the write can be seen.
+    fn record_dirty(&self, tx: &TxMut, address: Address) -> Result<()> {
+        let mut frozen = self.frozen.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dirty) = frozen.as_mut() {
+            if !dirty.contains_key(&address) {
+                dirty.insert(address, tx.get::<DualvmAccounts>(address)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Account state as it was at [`StateStore::freeze`]
+pub struct FrozenState {
+    store: Arc<StateStore>,
+}
+
+impl FrozenState {
+    /// Account root of the frozen state
+    pub fn state_root(&self) -> B256 {
+        let Ok(tx) = self.store.db.tx() else { return B256::ZERO };
+
+        let mut accounts = BTreeMap::new();
+        let walked = tx.walk::<DualvmAccounts>(None, |addr, account| {
+            accounts.insert(addr, account);
+            true
+        });
+        if walked.is_err() {
+            return B256::ZERO;
+        }
+        drop(tx);
+
+        // Every write the walk saw was recorded before it was made
+        let frozen = self.store.frozen.lock().unwrap_or_else(|e| e.into_inner());
+        for (address, account) in frozen.iter().flatten() {
+            match account {
+                Some(account) => accounts.insert(*address, account.clone()),
+                None => accounts.remove(address),
+            };
+        }
+        drop(frozen);
+
+        accounts_root(&accounts)
+    }
+}
+
+impl Drop for FrozenState {
+    fn drop(&mut self) {
+        *self.store.frozen.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Root over accounts in address order
+fn accounts_root(accounts: &BTreeMap<Address, StoredDualvmAccount>) -> B256 {
+    if accounts.is_empty() {
+        return B256::ZERO;
     }
 
-    /// Get account state
-    pub fn get_account(&self, address: &Address) -> Option<AccountState> {
+    let mut data = Vec::with_capacity(accounts.len() * 92);
+    for (addr, account) in accounts {
+        data.extend_from_slice(addr.as_slice());
+        data.extend_from_slice(&account.balance.to_be_bytes::<32>());
+        data.extend_from_slice(&account.nonce.to_be_bytes());
+        data.extend_from_slice(account.code_hash.as_slice());
+    }
+    keccak256(&data)
+}
+
+impl StateReader for StateStore {
+    fn get_account(&self, address: &Address) -> Option<AccountState> {
         let tx = self.db.tx().ok()?;
         let stored = tx.get::<DualvmAccounts>(*address).ok()??;
 
+        let is_contract = stored.is_contract;
         let mut account: AccountState = stored.into();
+        if is_contract {
+            account.code =
+                tx.get::<DualvmBytecodes>(account.code_hash).ok()?.map(|b| b.code.into());
+        }
 
         // Load storage for this account
-        let mut cursor = tx.cursor_read::<DualvmStorage>().ok()?;
         let start_key = StorageKey { address: *address, slot: U256::ZERO };
-        let walker = cursor.walk(Some(start_key)).ok()?;
-
-        for result in walker {
-            if let Ok((key, value)) = result {
-                if key.address != *address {
-                    break;
-                }
-                account.storage.insert(key.slot, value.value);
-            } else {
-                break;
+        let _ = tx.walk::<DualvmStorage>(Some(start_key), |key, value| {
+            if key.address != *address {
+                return false;
             }
-        }
+            account.storage.insert(key.slot, value.value);
+            true
+        });
 
         Some(account)
     }
 
-    /// Set account state
-    pub fn set_account(&self, address: Address, state: AccountState) -> Result<()> {
+    fn get_balance(&self, address: &Address) -> U256 {
+        self.db
+            .tx()
+            .ok()
+            .and_then(|tx| tx.get::<DualvmAccounts>(*address).ok())
+            .flatten()
+            .map(|a| a.balance)
+            .unwrap_or(U256::ZERO)
+    }
+
+    fn get_nonce(&self, address: &Address) -> u64 {
+        self.db
+            .tx()
+            .ok()
+            .and_then(|tx| tx.get::<DualvmAccounts>(*address).ok())
+            .flatten()
+            .map(|a| a.nonce)
+            .unwrap_or(0)
+    }
+
+    fn get_code(&self, address: &Address) -> Option<Bytes> {
+        let tx = self.db.tx().ok()?;
+        let account = tx.get::<DualvmAccounts>(*address).ok()??;
+        if !account.is_contract {
+            return None;
+        }
+        tx.get::<DualvmBytecodes>(account.code_hash).ok()?.map(|b| b.code.into())
+    }
+
+    fn get_storage(&self, address: &Address, slot: U256) -> U256 {
+        let key = StorageKey { address: *address, slot };
+        self.db
+            .tx()
+            .ok()
+            .and_then(|tx| tx.get::<DualvmStorage>(key).ok())
+            .flatten()
+            .map(|v| v.value)
+            .unwrap_or(U256::ZERO)
+    }
+
+    fn get_counter(&self, address: &Address) -> u64 {
+        self.db
+            .tx()
+            .ok()
+            .and_then(|tx| tx.get::<DualvmCounters>(*address).ok())
+            .flatten()
+            .map(|c| c.value)
+            .unwrap_or(0)
+    }
+
+    fn state_root(&self) -> B256 {
+        let tx = match self.db.tx() {
+            Ok(tx) => tx,
+            Err(_) => return B256::ZERO,
+        };
+
+        let mut accounts = BTreeMap::new();
+        let walked = tx.walk::<DualvmAccounts>(None, |addr, account| {
+            accounts.insert(addr, account);
+            true
+        });
+        if walked.is_err() {
+            return B256::ZERO;
+        }
+
+        accounts_root(&accounts)
+    }
+
+    fn all_accounts(&self) -> HashMap<Address, AccountState> {
+        let mut result = HashMap::new();
+
+        let tx = match self.db.tx() {
+            Ok(tx) => tx,
+            Err(_) => return result,
+        };
+
+        let _ = tx.walk::<DualvmAccounts>(None, |addr, stored| {
+            result.insert(addr, stored.into());
+            true
+        });
+
+        result
+    }
+
+    fn all_counters(&self) -> HashMap<Address, u64> {
+        let mut result = HashMap::new();
+
+        let tx = match self.db.tx() {
+            Ok(tx) => tx,
+            Err(_) => return result,
+        };
+
+        let _ = tx.walk::<DualvmCounters>(None, |addr, stored| {
+            result.insert(addr, stored.value);
+            true
+        });
+
+        result
+    }
+
+    fn top_counters(&self, limit: usize) -> Vec<(Address, u64)> {
+        let mut result = Vec::new();
+        if limit == 0 {
+            return result;
+        }
+
+        let tx = match self.db.tx() {
+            Ok(tx) => tx,
+            Err(_) => return result,
+        };
+
+        let _ = tx.walk::<DualvmCounterRanks>(None, |key, _| {
+            result.push((key.address, key.value));
+            result.len() < limit
+        });
+
+        result
+    }
+
+    fn get_labeled_counter(&self, address: &Address, label: B256) -> u64 {
+        if label.is_zero() {
+            return self.get_counter(address);
+        }
+        let key = LabeledCounterKey { address: *address, label };
+        self.db
+            .tx()
+            .ok()
+            .and_then(|tx| tx.get::<DualvmLabeledCounters>(key).ok())
+            .flatten()
+            .map(|c| c.value)
+            .unwrap_or(0)
+    }
+
+    fn counter_labels(&self, address: &Address) -> Vec<(B256, u64)> {
+        let Ok(tx) = self.db.tx() else {
+            return Vec::new();
+        };
+        let first = LabeledCounterKey { address: *address, label: B256::ZERO };
+        let last = LabeledCounterKey { address: *address, label: B256::repeat_byte(0xff) };
+        tx.range::<DualvmLabeledCounters>(first..=last)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, counter)| (key.label, counter.value))
+            .collect()
+    }
+
+    fn all_labeled_counters(&self) -> HashMap<(Address, B256), u64> {
+        let mut result = HashMap::new();
+
+        let tx = match self.db.tx() {
+            Ok(tx) => tx,
+            Err(_) => return result,
+        };
+
+        let _ = tx.walk::<DualvmLabeledCounters>(None, |key, stored| {
+            result.insert((key.address, key.label), stored.value);
+            true
+        });
+
+        result
+    }
+
+    fn all_dexvm_nonces(&self) -> HashMap<Address, u64> {
+        let mut result = HashMap::new();
+
+        let tx = match self.db.tx() {
+            Ok(tx) => tx,
+            Err(_) => return result,
+        };
+
+        let _ = tx.walk::<DualvmDexVmNonces>(None, |addr, stored| {
+            result.insert(addr, stored.value);
+            true
+        });
+
+        result
+    }
+}
+
+impl StateWriter for StateStore {
+    fn set_account(&self, address: Address, state: AccountState) -> Result<()> {
         let tx = self.db.tx_mut()?;
+        self.record_dirty(&tx, address)?;
 
         let stored: StoredDualvmAccount = (&state).into();
         tx.put::<DualvmAccounts>(address, stored)?;
 
+        if let Some(code) = &state.code {
+            tx.put::<DualvmBytecodes>(state.code_hash, StoredBytecode { code: code.to_vec() })?;
+        }
+
         for (slot, value) in &state.storage {
             let key = StorageKey { address, slot: *slot };
             if *value == U256::ZERO {
-                let mut cursor = tx.cursor_write::<DualvmStorage>()?;
-                if cursor.seek_exact(key.clone())?.is_some() {
-                    cursor.delete_current()?;
-                }
+                tx.delete::<DualvmStorage>(key)?;
             } else {
                 tx.put::<DualvmStorage>(key, StoredStorageValue { value: *value })?;
             }
@@ -120,20 +397,9 @@ impl StateStore {
         Ok(())
     }
 
-    /// Get account balance
-    pub fn get_balance(&self, address: &Address) -> U256 {
-        self.db
-            .tx()
-            .ok()
-            .and_then(|tx| tx.get::<DualvmAccounts>(*address).ok())
-            .flatten()
-            .map(|a| a.balance)
-            .unwrap_or(U256::ZERO)
-    }
-
-    /// Set account balance
-    pub fn set_balance(&self, address: Address, balance: U256) -> Result<()> {
+    fn set_balance(&self, address: Address, balance: U256) -> Result<()> {
         let tx = self.db.tx_mut()?;
+        self.record_dirty(&tx, address)?;
 
         let mut account =
             tx.get::<DualvmAccounts>(address)?.unwrap_or_else(StoredDualvmAccount::default);
@@ -144,20 +410,9 @@ impl StateStore {
         Ok(())
     }
 
-    /// Get account nonce
-    pub fn get_nonce(&self, address: &Address) -> u64 {
-        self.db
-            .tx()
-            .ok()
-            .and_then(|tx| tx.get::<DualvmAccounts>(*address).ok())
-            .flatten()
-            .map(|a| a.nonce)
-            .unwrap_or(0)
-    }
-
-    /// Set account nonce
-    pub fn set_nonce(&self, address: Address, nonce: u64) -> Result<()> {
+    fn set_nonce(&self, address: Address, nonce: u64) -> Result<()> {
         let tx = self.db.tx_mut()?;
+        self.record_dirty(&tx, address)?;
 
         let mut account =
             tx.get::<DualvmAccounts>(address)?.unwrap_or_else(StoredDualvmAccount::default);
@@ -168,9 +423,45 @@ impl StateStore {
         Ok(())
     }
 
-    /// Increment nonce and return new value
-    pub fn increment_nonce(&self, address: Address) -> Result<u64> {
+    fn remove_account(&self, address: Address) -> Result<()> {
+        let tx = self.db.tx_mut()?;
+        self.record_dirty(&tx, address)?;
+        tx.delete::<DualvmAccounts>(address)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn restore_accounts(&self, accounts: &[(Address, Option<AccountState>)]) -> Result<()> {
+        let tx = self.db.tx_mut()?;
+        for (address, state) in accounts {
+            self.record_dirty(&tx, *address)?;
+
+            let first = StorageKey { address: *address, slot: U256::ZERO };
+            let last = StorageKey { address: *address, slot: U256::MAX };
+            for (key, _) in tx.range::<DualvmStorage>(first..=last)? {
+                tx.delete::<DualvmStorage>(key)?;
+            }
+
+            let Some(state) = state else {
+                tx.delete::<DualvmAccounts>(*address)?;
+                continue;
+            };
+            tx.put::<DualvmAccounts>(*address, state.into())?;
+            if let Some(code) = &state.code {
+                tx.put::<DualvmBytecodes>(state.code_hash, StoredBytecode { code: code.to_vec() })?;
+            }
+            for (slot, value) in state.storage.iter().filter(|(_, value)| !value.is_zero()) {
+                let key = StorageKey { address: *address, slot: *slot };
+                tx.put::<DualvmStorage>(key, StoredStorageValue { value: *value })?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn increment_nonce(&self, address: Address) -> Result<u64> {
         let tx = self.db.tx_mut()?;
+        self.record_dirty(&tx, address)?;
 
         let mut account =
             tx.get::<DualvmAccounts>(address)?.unwrap_or_else(StoredDualvmAccount::default);
@@ -182,14 +473,9 @@ impl StateStore {
         Ok(new_nonce)
     }
 
-    /// Get contract code
-    pub fn get_code(&self, _address: &Address) -> Option<Bytes> {
-        None // Simplified: code storage not implemented
-    }
-
-    /// Set contract code
-    pub fn set_code(&self, address: Address, code: Bytes) -> Result<()> {
+    fn set_code(&self, address: Address, code: Bytes) -> Result<()> {
         let tx = self.db.tx_mut()?;
+        self.record_dirty(&tx, address)?;
 
         let code_hash = keccak256(&code);
         let mut account =
@@ -198,32 +484,17 @@ impl StateStore {
         account.code_hash = code_hash;
         account.is_contract = true;
         tx.put::<DualvmAccounts>(address, account)?;
+        tx.put::<DualvmBytecodes>(code_hash, StoredBytecode { code: code.to_vec() })?;
         tx.commit()?;
         Ok(())
     }
 
-    /// Get storage value
-    pub fn get_storage(&self, address: &Address, slot: U256) -> U256 {
-        let key = StorageKey { address: *address, slot };
-        self.db
-            .tx()
-            .ok()
-            .and_then(|tx| tx.get::<DualvmStorage>(key).ok())
-            .flatten()
-            .map(|v| v.value)
-            .unwrap_or(U256::ZERO)
-    }
-
-    /// Set storage value
-    pub fn set_storage(&self, address: Address, slot: U256, value: U256) -> Result<()> {
+    fn set_storage(&self, address: Address, slot: U256, value: U256) -> Result<()> {
         let tx = self.db.tx_mut()?;
         let key = StorageKey { address, slot };
 
         if value == U256::ZERO {
-            let mut cursor = tx.cursor_write::<DualvmStorage>()?;
-            if cursor.seek_exact(key)?.is_some() {
-                cursor.delete_current()?;
-            }
+            tx.delete::<DualvmStorage>(key)?;
         } else {
             tx.put::<DualvmStorage>(key, StoredStorageValue { value })?;
         }
@@ -232,39 +503,61 @@ impl StateStore {
         Ok(())
     }
 
-    /// Get counter value (for DexVM)
-    pub fn get_counter(&self, address: &Address) -> u64 {
-        self.db
-            .tx()
-            .ok()
-            .and_then(|tx| tx.get::<DualvmCounters>(*address).ok())
-            .flatten()
-            .map(|c| c.value)
-            .unwrap_or(0)
+    fn set_counter(&self, address: Address, value: u64) -> Result<()> {
+        let tx = self.db.tx_mut()?;
+        write_counter(&tx, address, value)?;
+        tx.commit()?;
+        Ok(())
     }
 
-    /// Set counter value (for DexVM)
-    pub fn set_counter(&self, address: Address, value: u64) -> Result<()> {
+    fn set_counters(&self, counters: &[(Address, u64)]) -> Result<()> {
         let tx = self.db.tx_mut()?;
-        tx.put::<DualvmCounters>(address, StoredCounter { value })?;
+        for &(address, value) in counters {
+            write_counter(&tx, address, value)?;
+        }
         tx.commit()?;
         Ok(())
     }
 
-    /// Increment counter and return new value
-    pub fn increment_counter(&self, address: Address, amount: u64) -> Result<u64> {
+    fn set_labeled_counters(&self, counters: &[(Address, B256, u64)]) -> Result<()> {
+        let tx = self.db.tx_mut()?;
+        for &(address, label, value) in counters {
+            if label.is_zero() {
+                write_counter(&tx, address, value)?;
+            } else if value == 0 {
+                tx.delete::<DualvmLabeledCounters>(LabeledCounterKey { address, label })?;
+            } else {
+                tx.put::<DualvmLabeledCounters>(
+                    LabeledCounterKey { address, label },
+                    StoredCounter { value },
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_dexvm_nonces(&self, nonces: &[(Address, u64)]) -> Result<()> {
+        let tx = self.db.tx_mut()?;
+        for &(address, value) in nonces {
+            tx.put::<DualvmDexVmNonces>(address, StoredCounter { value })?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn increment_counter(&self, address: Address, amount: u64) -> Result<u64> {
         let tx = self.db.tx_mut()?;
 
         let current = tx.get::<DualvmCounters>(address)?.map(|c| c.value).unwrap_or(0);
 
         let new_value = current.saturating_add(amount);
-        tx.put::<DualvmCounters>(address, StoredCounter { value: new_value })?;
+        write_counter(&tx, address, new_value)?;
         tx.commit()?;
         Ok(new_value)
     }
 
-    /// Decrement counter and return new value
-    pub fn decrement_counter(&self, address: Address, amount: u64) -> Result<u64> {
+    fn decrement_counter(&self, address: Address, amount: u64) -> Result<u64> {
         let tx = self.db.tx_mut()?;
 
         let current = tx.get::<DualvmCounters>(address)?.map(|c| c.value).unwrap_or(0);
@@ -274,16 +567,16 @@ impl StateStore {
         }
 
         let new_value = current - amount;
-        tx.put::<DualvmCounters>(address, StoredCounter { value: new_value })?;
+        write_counter(&tx, address, new_value)?;
         tx.commit()?;
         Ok(new_value)
     }
 
-    /// Initialize from genesis allocation
-    pub fn init_genesis(&self, alloc: HashMap<Address, U256>) -> Result<()> {
+    fn init_genesis(&self, alloc: HashMap<Address, U256>) -> Result<()> {
         let tx = self.db.tx_mut()?;
 
         for (address, balance) in alloc {
+            self.record_dirty(&tx, address)?;
             let account = StoredDualvmAccount {
                 balance,
                 nonce: 0,
@@ -296,90 +589,23 @@ impl StateStore {
         tx.commit()?;
         Ok(())
     }
+}
 
-    /// Calculate state root
-    pub fn state_root(&self) -> B256 {
-        let tx = match self.db.tx() {
-            Ok(tx) => tx,
-            Err(_) => return B256::ZERO,
-        };
-
-        let mut cursor = match tx.cursor_read::<DualvmAccounts>() {
-            Ok(cursor) => cursor,
-            Err(_) => return B256::ZERO,
-        };
-
-        let mut data = Vec::new();
-        let walker = match cursor.walk(None) {
-            Ok(walker) => walker,
-            Err(_) => return B256::ZERO,
-        };
-
-        for (addr, account) in walker.flatten() {
-            data.extend_from_slice(addr.as_slice());
-            data.extend_from_slice(&account.balance.to_be_bytes::<32>());
-            data.extend_from_slice(&account.nonce.to_be_bytes());
-            data.extend_from_slice(account.code_hash.as_slice());
-        }
-
-        if data.is_empty() {
-            B256::ZERO
-        } else {
-            keccak256(&data)
-        }
-    }
-
-    /// Get all accounts
-    pub fn all_accounts(&self) -> HashMap<Address, AccountState> {
-        let mut result = HashMap::new();
-
-        let tx = match self.db.tx() {
-            Ok(tx) => tx,
-            Err(_) => return result,
-        };
-
-        let mut cursor = match tx.cursor_read::<DualvmAccounts>() {
-            Ok(cursor) => cursor,
-            Err(_) => return result,
-        };
-
-        let walker = match cursor.walk(None) {
-            Ok(walker) => walker,
-            Err(_) => return result,
-        };
-
-        for (addr, stored) in walker.flatten() {
-            result.insert(addr, stored.into());
+/// Store a counter and move its entry in the ranking index
+///
+/// Zero counters are kept in [`DualvmCounters`] but left out of the ranking.
+fn write_counter(tx: &TxMut, address: Address, value: u64) -> Result<()> {
+    if let Some(old) = tx.get::<DualvmCounters>(address)? {
+        if old.value == value {
+            return Ok(());
         }
-
-        result
+        tx.delete::<DualvmCounterRanks>(CounterRankKey { value: old.value, address })?;
     }
-
-    /// Get all counters (for DexVM state recovery)
-    pub fn all_counters(&self) -> HashMap<Address, u64> {
-        let mut result = HashMap::new();
-
-        let tx = match self.db.tx() {
-            Ok(tx) => tx,
-            Err(_) => return result,
-        };
-
-        let mut cursor = match tx.cursor_read::<DualvmCounters>() {
-            Ok(cursor) => cursor,
-            Err(_) => return result,
-        };
-
-        let walker = match cursor.walk(None) {
-            Ok(walker) => walker,
-            Err(_) => return result,
-        };
-
-        for (addr, stored) in walker.flatten() {
-            result.insert(addr, stored.value);
-        }
-
-        result
+    tx.put::<DualvmCounters>(address, StoredCounter { value })?;
+    if value != 0 {
+        tx.put::<DualvmCounterRanks>(CounterRankKey { value, address }, StoredCounter { value })?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -387,16 +613,44 @@ mod tests {
     use super::*;
     use alloy_primitives::address;
     use reth_db::{mdbx::DatabaseArguments, mdbx::init_db_for, models::ClientVersion};
+    use std::sync::Arc;
     use tempfile::tempdir;
 
-    fn create_test_db() -> Arc<DatabaseEnv> {
+    fn create_test_db() -> Backend {
         let dir = tempdir().unwrap();
         let db = init_db_for::<_, crate::tables::DualvmTableSet>(
             dir.path(),
             DatabaseArguments::new(ClientVersion::default()),
         )
         .unwrap();
-        Arc::new(db)
+        Backend::from(Arc::new(db))
+    }
+
+    #[test]
+    fn test_frozen_state_root() {
+        let store = Arc::new(StateStore::new(create_test_db()));
+        let kept = address!("1111111111111111111111111111111111111111");
+        let removed = address!("2222222222222222222222222222222222222222");
+        store.set_balance(kept, U256::from(100)).unwrap();
+        store.set_balance(removed, U256::from(200)).unwrap();
+        let root = store.state_root();
+
+        let frozen = store.freeze().unwrap();
+        assert!(store.freeze().is_err());
+
+        // Writes after the freeze change the live root but not the frozen one
+        store.set_balance(kept, U256::from(1)).unwrap();
+        store.increment_nonce(kept).unwrap();
+        store.remove_account(removed).unwrap();
+        let created = address!("3333333333333333333333333333333333333333");
+        store.set_balance(created, U256::from(3)).unwrap();
+        assert_ne!(store.state_root(), root);
+        assert_eq!(frozen.state_root(), root);
+
+        // Dropping it ends the freeze
+        drop(frozen);
+        let live = store.state_root();
+        assert_eq!(store.freeze().unwrap().state_root(), live);
     }
 
     #[test]
@@ -411,6 +665,20 @@ mod tests {
         assert_eq!(store.get_balance(&addr), U256::from(1000));
     }
 
+    #[test]
+    fn test_remove_account() {
+        let db = create_test_db();
+        let store = StateStore::new(db);
+
+        let addr = address!("1111111111111111111111111111111111111111");
+        store.set_balance(addr, U256::from(1000)).unwrap();
+        assert!(store.get_account(&addr).is_some());
+
+        store.remove_account(addr).unwrap();
+        assert!(store.get_account(&addr).is_none());
+        assert!(store.all_accounts().is_empty());
+    }
+
     #[test]
     fn test_counter() {
         let db = create_test_db();
@@ -426,6 +694,71 @@ mod tests {
         let new_val = store.decrement_counter(addr, 3).unwrap();
         assert_eq!(new_val, 7);
         assert_eq!(store.get_counter(&addr), 7);
+
+        let other = address!("3333333333333333333333333333333333333333");
+        store.set_counters(&[(addr, 1), (other, 5)]).unwrap();
+        assert_eq!(store.all_counters(), HashMap::from([(addr, 1), (other, 5)]));
+    }
+
+    #[test]
+    fn test_top_counters() {
+        let store = StateStore::new(create_test_db());
+        let a = address!("1111111111111111111111111111111111111111");
+        let b = address!("2222222222222222222222222222222222222222");
+        let c = address!("3333333333333333333333333333333333333333");
+
+        store.set_counters(&[(a, 5), (b, 9), (c, 5)]).unwrap();
+        assert_eq!(store.top_counters(10), vec![(b, 9), (a, 5), (c, 5)]);
+        assert_eq!(store.top_counters(1), vec![(b, 9)]);
+        assert!(store.top_counters(0).is_empty());
+
+        // Updates move entries and zero counters drop out
+        store.increment_counter(c, 10).unwrap();
+        store.decrement_counter(b, 9).unwrap();
+        assert_eq!(store.top_counters(10), vec![(c, 15), (a, 5)]);
+    }
+
+    #[test]
+    fn test_labeled_counters() {
+        let store = StateStore::new(create_test_db());
+        let a = address!("1111111111111111111111111111111111111111");
+        let b = address!("2222222222222222222222222222222222222222");
+        let (score, visits) = (B256::repeat_byte(1), B256::repeat_byte(2));
+
+        store.set_labeled_counters(&[(a, visits, 4), (a, score, 9), (b, score, 1)]).unwrap();
+        assert_eq!(store.get_labeled_counter(&a, score), 9);
+        assert_eq!(store.counter_labels(&a), vec![(score, 9), (visits, 4)]);
+        assert_eq!(store.counter_labels(&b), vec![(score, 1)]);
+
+        // Label zero is the main counter, which is not listed as a label
+        store.set_labeled_counters(&[(a, B256::ZERO, 3)]).unwrap();
+        assert_eq!(store.get_counter(&a), 3);
+        assert_eq!(store.get_labeled_counter(&a, B256::ZERO), 3);
+        assert_eq!(store.counter_labels(&a).len(), 2);
+
+        // Zero counters are deleted
+        store.set_labeled_counters(&[(a, visits, 0)]).unwrap();
+        assert_eq!(store.counter_labels(&a), vec![(score, 9)]);
+        assert_eq!(store.all_labeled_counters().len(), 2);
+    }
+
+    #[test]
+    fn test_code() {
+        let db = create_test_db();
+        let store = StateStore::new(db);
+
+        let addr = address!("5555555555555555555555555555555555555555");
+        assert_eq!(store.get_code(&addr), None);
+
+        let code = Bytes::from_static(&[0x60, 0x00, 0x60, 0x00, 0xf3]);
+        store.set_code(addr, code.clone()).unwrap();
+        assert_eq!(store.get_code(&addr), Some(code.clone()));
+        assert_eq!(store.get_account(&addr).unwrap().code, Some(code));
+
+        // Accounts without code stay empty
+        let eoa = address!("6666666666666666666666666666666666666666");
+        store.set_balance(eoa, U256::from(1)).unwrap();
+        assert_eq!(store.get_code(&eoa), None);
     }
 
     #[test]
@@ -444,4 +777,59 @@ mod tests {
         assert_eq!(store.get_balance(&addr1), U256::from(1000));
         assert_eq!(store.get_balance(&addr2), U256::from(2000));
     }
+
+    #[test]
+    fn test_account_storage() {
+        for db in [create_test_db(), Backend::memory()] {
+            let store = StateStore::new(db);
+            let addr = address!("7777777777777777777777777777777777777777");
+            let next = address!("7777777777777777777777777777777777777778");
+
+            let mut account = AccountState::new_eoa(U256::from(5));
+            account.storage.insert(U256::from(1), U256::from(10));
+            account.storage.insert(U256::from(2), U256::from(20));
+            store.set_account(addr, account).unwrap();
+            store.set_storage(next, U256::from(1), U256::from(99)).unwrap();
+
+            // Zero values delete the slot
+            store.set_storage(addr, U256::from(2), U256::ZERO).unwrap();
+            let account = store.get_account(&addr).unwrap();
+            assert_eq!(account.balance, U256::from(5));
+            assert_eq!(account.storage, HashMap::from([(U256::from(1), U256::from(10))]));
+            assert_eq!(store.get_storage(&next, U256::from(1)), U256::from(99));
+            assert_ne!(store.state_root(), B256::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_restore_accounts() {
+        for db in [create_test_db(), Backend::memory()] {
+            let store = StateStore::new(db);
+            let contract = address!("8888888888888888888888888888888888888888");
+            let created = address!("9999999999999999999999999999999999999999");
+
+            let mut account = AccountState::new_contract(U256::from(5), vec![0x60, 0x00].into());
+            account.storage.insert(U256::from(1), U256::from(10));
+            store.set_account(contract, account).unwrap();
+            let before = vec![
+                (contract, store.get_account(&contract)),
+                (created, store.get_account(&created)),
+            ];
+            let root = store.state_root();
+
+            store.set_code(contract, vec![0x00].into()).unwrap();
+            store.set_storage(contract, U256::from(1), U256::from(11)).unwrap();
+            store.set_storage(contract, U256::from(2), U256::from(20)).unwrap();
+            store.set_code(created, vec![0x01].into()).unwrap();
+            store.set_storage(created, U256::from(1), U256::from(1)).unwrap();
+
+            store.restore_accounts(&before).unwrap();
+            let account = store.get_account(&contract).unwrap();
+            assert_eq!(account.code, Some(vec![0x60, 0x00].into()));
+            assert_eq!(account.storage, HashMap::from([(U256::from(1), U256::from(10))]));
+            assert!(store.get_account(&created).is_none());
+            assert_eq!(store.get_storage(&created, U256::from(1)), U256::ZERO);
+            assert_eq!(store.state_root(), root);
+        }
+    }
 }
@@ -1,24 +1,98 @@
 //! Combined storage wrapper
 
-use crate::{block_store::BlockStore, state_store::StateStore, tables::DualvmTableSet};
+use crate::{
+    backend::Backend,
+    block_store::BlockStore,
+    migrations,
+    state_store::StateStore,
+    tables::{
+        table_names, DualvmAccessSets, DualvmAccounts, DualvmBlockStats, DualvmBlocks,
+        DualvmBytecodes, DualvmCounterRanks, DualvmCounters, DualvmDexVmNonces,
+        DualvmDexVmReceipts, DualvmLabeledCounters, DualvmMetadata, DualvmReceipts,
+        DualvmSiblingBlocks, DualvmStorage as StorageTable, DualvmTableSet, DualvmTransactions,
+        DualvmTxHashes,
+    },
+};
 use eyre::Result;
-use reth_db::{mdbx::DatabaseArguments, mdbx::init_db_for, models::ClientVersion, DatabaseEnv};
+use reth_db::{
+    mdbx::{init_db_for, open_db_read_only, DatabaseArguments},
+    models::ClientVersion,
+    DatabaseEnv,
+};
+use reth_db_api::{
+    cursor::DbCursorRO,
+    database::Database,
+    table::Table,
+    transaction::{DbTx, DbTxMut},
+};
+use serde::{Deserialize, Serialize};
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
 };
 
+/// MDBX data file name inside the datadir
+pub const MDBX_DATA_FILE: &str = "mdbx.dat";
+
+/// Per-table MDBX statistics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStats {
+    pub name: String,
+    pub entries: u64,
+    pub branch_pages: u64,
+    pub leaf_pages: u64,
+    pub overflow_pages: u64,
+    pub size_bytes: u64,
+}
+
+/// Database-wide MDBX statistics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStats {
+    pub page_size: u64,
+    pub file_size_bytes: u64,
+    pub freelist_pages: u64,
+    pub tables: Vec<TableStats>,
+}
+
+/// Result of an offline copy-compaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionReport {
+    pub entries_copied: u64,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Copy every entry of a table into another database transaction
+fn copy_table<T: Table>(src: &impl DbTx, dst: &impl DbTxMut) -> Result<u64> {
+    let mut cursor = src.cursor_read::<T>()?;
+    let mut count = 0u64;
+    for entry in cursor.walk(None)? {
+        let (key, value) = entry?;
+        dst.put::<T>(key, value)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn data_file_size(path: &Path) -> u64 {
+    std::fs::metadata(path.join(MDBX_DATA_FILE)).map(|m| m.len()).unwrap_or(0)
+}
+
 /// Combined DualVM storage
 pub struct DualvmStorage {
-    /// Database environment
-    pub db: Arc<DatabaseEnv>,
+    /// Database backend
+    pub db: Backend,
     /// Block store
     pub blocks: Arc<BlockStore>,
     /// State store
     pub state: Arc<StateStore>,
+    /// Database directory
+    path: PathBuf,
     /// Whether this is a new database
     is_new: AtomicBool,
 }
@@ -27,7 +101,7 @@ impl DualvmStorage {
     /// Create new storage from path
     pub fn new(path: &Path) -> Result<Self> {
         // Check if database already exists
-        let db_path = path.join("mdbx.dat");
+        let db_path = path.join(MDBX_DATA_FILE);
         let is_new = !db_path.exists();
 
         // Ensure directory exists
@@ -38,12 +112,71 @@ impl DualvmStorage {
             path,
             DatabaseArguments::new(ClientVersion::default()),
         )?;
-        let db = Arc::new(db);
+        let db = Backend::from(Arc::new(db));
+        migrations::migrate(&db, is_new)?;
 
-        let blocks = Arc::new(BlockStore::new(Arc::clone(&db))?);
-        let state = Arc::new(StateStore::new(Arc::clone(&db)));
+        let blocks = Arc::new(BlockStore::new(db.clone())?);
+        let state = Arc::new(StateStore::new(db.clone()));
 
-        Ok(Self { db, blocks, state, is_new: AtomicBool::new(is_new) })
+        Ok(Self { db, blocks, state, path: path.to_path_buf(), is_new: AtomicBool::new(is_new) })
+    }
+
+    /// Create empty storage held in memory
+    ///
+    /// Nothing is written to disk and everything is lost when the storage is
+    /// dropped. Meant for tests and throwaway dev nodes; there is no datadir,
+    /// so stats and compaction are unavailable.
+    pub fn in_memory() -> Result<Self> {
+        let db = Backend::memory();
+        migrations::migrate(&db, true)?;
+
+        let blocks = Arc::new(BlockStore::new(db.clone())?);
+        let state = Arc::new(StateStore::new(db.clone()));
+
+        Ok(Self { db, blocks, state, path: PathBuf::new(), is_new: AtomicBool::new(true) })
+    }
+
+    /// Open an existing database read-only
+    ///
+    /// MDBX allows readers in other processes alongside the writer, so RPC
+    /// replicas can serve a live node's datadir. Writes through the returned
+    /// storage fail. Migrations need write access, so the schema must already
+    /// be current.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        if !path.join(MDBX_DATA_FILE).exists() {
+            return Err(eyre::eyre!("No database found in {}", path.display()));
+        }
+
+        let db = open_db_read_only(path, DatabaseArguments::new(ClientVersion::default()))?;
+        let db = Backend::from(Arc::new(db));
+        migrations::check_current(&db)?;
+
+        let blocks = Arc::new(BlockStore::new(db.clone())?);
+        let state = Arc::new(StateStore::new(db.clone()));
+
+        Ok(Self { db, blocks, state, path: path.to_path_buf(), is_new: AtomicBool::new(false) })
+    }
+
+    /// Database directory, empty for in-memory storage
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the tables are held in memory rather than in a datadir
+    pub fn is_in_memory(&self) -> bool {
+        self.db.mdbx().is_none()
+    }
+
+    fn mdbx(&self) -> Result<&Arc<DatabaseEnv>> {
+        self.db.mdbx().ok_or_else(|| eyre::eyre!("In-memory storage has no MDBX database"))
+    }
+
+    /// Size of the MDBX data file in bytes, 0 for in-memory storage
+    pub fn file_size(&self) -> u64 {
+        if self.is_in_memory() {
+            return 0;
+        }
+        data_file_size(&self.path)
     }
 
     /// Check if this is a new database
@@ -55,11 +188,91 @@ impl DualvmStorage {
     pub fn mark_initialized(&self) {
         self.is_new.store(false, Ordering::SeqCst);
     }
+
+    /// Collect per-table entry counts, page usage and file size
+    pub fn stats(&self) -> Result<DatabaseStats> {
+        let db = self.mdbx()?;
+        let tx = db.tx()?;
+        let mut tables = Vec::new();
+        let mut page_size = 0u64;
+
+        for name in table_names::ALL {
+            let table_db = tx.inner.open_db(Some(name))?;
+            let stat = tx.inner.db_stat(&table_db)?;
+            page_size = stat.page_size() as u64;
+            let branch_pages = stat.branch_pages() as u64;
+            let leaf_pages = stat.leaf_pages() as u64;
+            let overflow_pages = stat.overflow_pages() as u64;
+
+            tables.push(TableStats {
+                name: name.to_string(),
+                entries: stat.entries() as u64,
+                branch_pages,
+                leaf_pages,
+                overflow_pages,
+                size_bytes: (branch_pages + leaf_pages + overflow_pages) * page_size,
+            });
+        }
+
+        Ok(DatabaseStats {
+            page_size,
+            file_size_bytes: data_file_size(&self.path),
+            freelist_pages: db.freelist()? as u64,
+            tables,
+        })
+    }
+
+    /// Copy all tables into a fresh database at `dest`, dropping free pages
+    ///
+    /// The destination must not contain a database yet. Everything is read in
+    /// one read transaction, a consistent snapshot even while the node writes;
+    /// to compact, callers swap the data file in afterwards while the node is
+    /// offline.
+    pub fn compact_into(&self, dest: &Path) -> Result<CompactionReport> {
+        if dest.join(MDBX_DATA_FILE).exists() {
+            return Err(eyre::eyre!("Compaction target {} already has a database", dest.display()));
+        }
+        let src = self.mdbx()?.tx()?;
+        std::fs::create_dir_all(dest)?;
+
+        let target = init_db_for::<_, DualvmTableSet>(
+            dest,
+            DatabaseArguments::new(ClientVersion::default()),
+        )?;
+
+        let dst = target.tx_mut()?;
+        let mut entries_copied = 0u64;
+        entries_copied += copy_table::<DualvmBlocks>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmAccounts>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmCounters>(&src, &dst)?;
+        entries_copied += copy_table::<StorageTable>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmTxHashes>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmTransactions>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmBytecodes>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmBlockStats>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmAccessSets>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmMetadata>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmReceipts>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmSiblingBlocks>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmCounterRanks>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmLabeledCounters>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmDexVmReceipts>(&src, &dst)?;
+        entries_copied += copy_table::<DualvmDexVmNonces>(&src, &dst)?;
+        dst.commit()?;
+        drop(target);
+
+        Ok(CompactionReport {
+            entries_copied,
+            size_before: data_file_size(&self.path),
+            size_after: data_file_size(dest),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::traits::{BlockReader, BlockWriter, StateReader, StateWriter};
     use tempfile::tempdir;
 
     #[test]
@@ -74,4 +287,68 @@ mod tests {
         let storage2 = DualvmStorage::new(dir.path()).unwrap();
         assert!(!storage2.is_new_database());
     }
+
+    #[test]
+    fn test_open_read_only() {
+        use crate::block_store::StoredBlock;
+
+        let dir = tempdir().unwrap();
+        assert!(DualvmStorage::open_read_only(dir.path()).is_err());
+
+        let storage = DualvmStorage::new(dir.path()).unwrap();
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        drop(storage);
+
+        let genesis = StoredBlock::genesis(1);
+        let replica = DualvmStorage::open_read_only(dir.path()).unwrap();
+        assert!(!replica.is_new_database());
+        assert_eq!(replica.blocks.refresh_latest_block_number().unwrap(), 0);
+        assert_eq!(replica.blocks.get_block_by_number(0).unwrap().hash, genesis.hash);
+        assert!(replica.blocks.store_block(StoredBlock { number: 1, ..genesis }).is_err());
+    }
+
+    #[test]
+    fn test_stats_and_compaction() {
+        use alloy_primitives::{address, B256, U256};
+
+        let dir = tempdir().unwrap();
+        let storage = DualvmStorage::new(dir.path()).unwrap();
+        let addr = address!("1111111111111111111111111111111111111111");
+        storage.state.set_balance(addr, U256::from(1000)).unwrap();
+        storage.state.set_counter(addr, 7).unwrap();
+        storage.state.set_labeled_counters(&[(addr, B256::repeat_byte(1), 2)]).unwrap();
+
+        let stats = storage.stats().unwrap();
+        let accounts =
+            stats.tables.iter().find(|t| t.name == table_names::DUALVM_ACCOUNTS).unwrap();
+        assert_eq!(accounts.entries, 1);
+
+        let dest = tempdir().unwrap();
+        let report = storage.compact_into(dest.path()).unwrap();
+        // Account, counter, counter rank, labeled counter and schema version
+        assert_eq!(report.entries_copied, 5);
+
+        let compacted = DualvmStorage::new(dest.path()).unwrap();
+        assert_eq!(compacted.state.get_balance(&addr), U256::from(1000));
+        assert_eq!(compacted.state.get_counter(&addr), 7);
+        assert_eq!(compacted.state.top_counters(1), vec![(addr, 7)]);
+        assert_eq!(compacted.state.counter_labels(&addr), vec![(B256::repeat_byte(1), 2)]);
+    }
+
+    #[test]
+    fn test_in_memory() {
+        use crate::block_store::StoredBlock;
+
+        let storage = DualvmStorage::in_memory().unwrap();
+        assert!(storage.is_in_memory());
+        assert!(storage.is_new_database());
+        storage.blocks.store_block(StoredBlock::genesis(1)).unwrap();
+        assert_eq!(storage.blocks.block_count(), 1);
+        assert_eq!(storage.file_size(), 0);
+        assert!(storage.stats().is_err());
+        assert!(storage.compact_into(tempdir().unwrap().path()).is_err());
+
+        // Every storage starts empty
+        assert_eq!(DualvmStorage::in_memory().unwrap().blocks.block_count(), 0);
+    }
 }
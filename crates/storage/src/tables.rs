@@ -1,9 +1,12 @@
 //! DualVM database tables
 
-use alloy_primitives::{Address, BlockNumber, B256, U256};
+use crate::codec::{impl_compression_for_table_value, Reader, TableValue, Writer};
+use alloy_primitives::{Address, BlockNumber, B256, B512, U256};
 use bytes::BufMut;
-use reth_codecs::Compact;
-use reth_db_api::table::{Compress, Decompress, Decode, Encode, Table, TableInfo};
+use reth_db_api::{
+    table::{Decode, Encode, Table, TableInfo},
+    DatabaseError,
+};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Helper module for serializing [u8; 65] as hex string
@@ -41,6 +44,36 @@ pub mod table_names {
     pub const DUALVM_STORAGE: &str = "DualvmStorage";
     pub const DUALVM_TX_HASHES: &str = "DualvmTxHashes";
     pub const DUALVM_TRANSACTIONS: &str = "DualvmTransactions";
+    pub const DUALVM_BYTECODES: &str = "DualvmBytecodes";
+    pub const DUALVM_BLOCK_STATS: &str = "DualvmBlockStats";
+    pub const DUALVM_ACCESS_SETS: &str = "DualvmAccessSets";
+    pub const DUALVM_METADATA: &str = "DualvmMetadata";
+    pub const DUALVM_RECEIPTS: &str = "DualvmReceipts";
+    pub const DUALVM_SIBLING_BLOCKS: &str = "DualvmSiblingBlocks";
+    pub const DUALVM_COUNTER_RANKS: &str = "DualvmCounterRanks";
+    pub const DUALVM_LABELED_COUNTERS: &str = "DualvmLabeledCounters";
+    pub const DUALVM_DEXVM_RECEIPTS: &str = "DualvmDexVmReceipts";
+    pub const DUALVM_DEXVM_NONCES: &str = "DualvmDexVmNonces";
+
+    /// All table names, in creation order
+    pub const ALL: [&str; 16] = [
+        DUALVM_BLOCKS,
+        DUALVM_ACCOUNTS,
+        DUALVM_COUNTERS,
+        DUALVM_STORAGE,
+        DUALVM_TX_HASHES,
+        DUALVM_TRANSACTIONS,
+        DUALVM_BYTECODES,
+        DUALVM_BLOCK_STATS,
+        DUALVM_ACCESS_SETS,
+        DUALVM_METADATA,
+        DUALVM_RECEIPTS,
+        DUALVM_SIBLING_BLOCKS,
+        DUALVM_COUNTER_RANKS,
+        DUALVM_LABELED_COUNTERS,
+        DUALVM_DEXVM_RECEIPTS,
+        DUALVM_DEXVM_NONCES,
+    ];
 }
 
 /// Storage key combining address and slot
@@ -62,9 +95,9 @@ impl Encode for StorageKey {
 }
 
 impl Decode for StorageKey {
-    fn decode(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
+    fn decode(value: &[u8]) -> Result<Self, DatabaseError> {
         if value.len() < 52 {
-            return Err(reth_db_api::DatabaseError::Decode);
+            return Err(DatabaseError::Decode);
         }
         let address = Address::from_slice(&value[..20]);
         let slot = U256::from_be_slice(&value[20..52]);
@@ -72,6 +105,78 @@ impl Decode for StorageKey {
     }
 }
 
+/// Labeled counter key: owner then label, so an owner's labels are adjacent
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+pub struct LabeledCounterKey {
+    pub address: Address,
+    pub label: B256,
+}
+
+impl Encode for LabeledCounterKey {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        let mut buf = Vec::with_capacity(52);
+        buf.extend_from_slice(self.address.as_slice());
+        buf.extend_from_slice(self.label.as_slice());
+        buf
+    }
+}
+
+impl Decode for LabeledCounterKey {
+    fn decode(value: &[u8]) -> Result<Self, DatabaseError> {
+        if value.len() < 52 {
+            return Err(DatabaseError::Decode);
+        }
+        let address = Address::from_slice(&value[..20]);
+        let label = B256::from_slice(&value[20..52]);
+        Ok(Self { address, label })
+    }
+}
+
+/// Counter ranking key: highest value first, ties broken by lowest address
+///
+/// The value is encoded inverted, so walking the table in key order visits
+/// counters in ranking order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct CounterRankKey {
+    pub value: u64,
+    pub address: Address,
+}
+
+impl Ord for CounterRankKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.value.cmp(&self.value).then_with(|| self.address.cmp(&other.address))
+    }
+}
+
+impl PartialOrd for CounterRankKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Encode for CounterRankKey {
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        let mut buf = Vec::with_capacity(28);
+        buf.extend_from_slice(&(!self.value).to_be_bytes());
+        buf.extend_from_slice(self.address.as_slice());
+        buf
+    }
+}
+
+impl Decode for CounterRankKey {
+    fn decode(value: &[u8]) -> Result<Self, DatabaseError> {
+        if value.len() < 28 {
+            return Err(DatabaseError::Decode);
+        }
+        let inverted = u64::from_be_bytes(value[..8].try_into().unwrap());
+        Ok(Self { value: !inverted, address: Address::from_slice(&value[8..28]) })
+    }
+}
+
 /// DualVM block header stored in database
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StoredDualvmBlock {
@@ -116,100 +221,37 @@ impl Default for StoredDualvmBlock {
     }
 }
 
-impl Compact for StoredDualvmBlock {
-    fn to_compact<B>(&self, buf: &mut B) -> usize
-    where
-        B: BufMut + AsMut<[u8]>,
-    {
-        buf.put_slice(self.hash.as_slice());
-        buf.put_slice(self.parent_hash.as_slice());
-        buf.put_u64(self.timestamp);
-        buf.put_u64(self.gas_limit);
-        buf.put_u64(self.gas_used);
-        buf.put_slice(self.miner.as_slice());
-        buf.put_slice(self.evm_state_root.as_slice());
-        buf.put_slice(self.dexvm_state_root.as_slice());
-        buf.put_slice(self.combined_state_root.as_slice());
-        buf.put_u64(self.transaction_count);
-        buf.put_slice(&self.signature);
-        // Write transaction hashes count and data
-        buf.put_u32(self.transaction_hashes.len() as u32);
-        for tx_hash in &self.transaction_hashes {
-            buf.put_slice(tx_hash.as_slice());
-        }
-        245 + 4 + self.transaction_hashes.len() * 32
-    }
-
-    fn from_compact(buf: &[u8], _len: usize) -> (Self, &[u8]) {
-        let hash = B256::from_slice(&buf[0..32]);
-        let parent_hash = B256::from_slice(&buf[32..64]);
-        let timestamp = u64::from_be_bytes(buf[64..72].try_into().unwrap());
-        let gas_limit = u64::from_be_bytes(buf[72..80].try_into().unwrap());
-        let gas_used = u64::from_be_bytes(buf[80..88].try_into().unwrap());
-        let miner = Address::from_slice(&buf[88..108]);
-        let evm_state_root = B256::from_slice(&buf[108..140]);
-        let dexvm_state_root = B256::from_slice(&buf[140..172]);
-        let combined_state_root = B256::from_slice(&buf[172..204]);
-        let transaction_count = u64::from_be_bytes(buf[204..212].try_into().unwrap());
-        let mut signature = [0u8; 65];
-        let mut transaction_hashes = vec![];
-        let mut remaining = &buf[212..];
-
-        // Handle old blocks without signature (backwards compatibility)
-        if buf.len() >= 277 {
-            signature.copy_from_slice(&buf[212..277]);
-            remaining = &buf[277..];
-
-            // Read transaction hashes if present (new format)
-            if remaining.len() >= 4 {
-                let tx_count = u32::from_be_bytes(remaining[0..4].try_into().unwrap()) as usize;
-                remaining = &remaining[4..];
-
-                for _ in 0..tx_count {
-                    if remaining.len() >= 32 {
-                        transaction_hashes.push(B256::from_slice(&remaining[0..32]));
-                        remaining = &remaining[32..];
-                    }
-                }
-            }
-        }
-
-        (
-            Self {
-                hash,
-                parent_hash,
-                timestamp,
-                gas_limit,
-                gas_used,
-                miner,
-                evm_state_root,
-                dexvm_state_root,
-                combined_state_root,
-                transaction_count,
-                signature,
-                transaction_hashes,
-            },
-            remaining,
-        )
-    }
-}
-
-impl Compress for StoredDualvmBlock {
-    type Compressed = Vec<u8>;
-
-    fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
-        self.to_compact(buf);
+impl TableValue for StoredDualvmBlock {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.b256(&self.hash);
+        w.b256(&self.parent_hash);
+        w.u64(self.timestamp);
+        w.u64(self.gas_limit);
+        w.u64(self.gas_used);
+        w.address(&self.miner);
+        w.b256(&self.evm_state_root);
+        w.b256(&self.dexvm_state_root);
+        w.b256(&self.combined_state_root);
+        w.u64(self.transaction_count);
+        w.raw(&self.signature);
+        w.list(&self.transaction_hashes, |w, hash| w.b256(hash));
     }
-}
 
-impl Decompress for StoredDualvmBlock {
-    fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
-        // Accept both old format (212 bytes) and new format (277 bytes)
-        if value.len() < 212 {
-            return Err(reth_db_api::DatabaseError::Decode);
-        }
-        let (block, _) = Self::from_compact(value, value.len());
-        Ok(block)
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            hash: r.b256()?,
+            parent_hash: r.b256()?,
+            timestamp: r.u64()?,
+            gas_limit: r.u64()?,
+            gas_used: r.u64()?,
+            miner: r.address()?,
+            evm_state_root: r.b256()?,
+            dexvm_state_root: r.b256()?,
+            combined_state_root: r.b256()?,
+            transaction_count: r.u64()?,
+            signature: r.raw()?,
+            transaction_hashes: r.list(32, |r| r.b256())?,
+        })
     }
 }
 
@@ -222,209 +264,393 @@ pub struct StoredDualvmAccount {
     pub is_contract: bool,
 }
 
-impl Compact for StoredDualvmAccount {
-    fn to_compact<B>(&self, buf: &mut B) -> usize
-    where
-        B: BufMut + AsMut<[u8]>,
-    {
-        buf.put_slice(&self.balance.to_be_bytes::<32>());
-        buf.put_u64(self.nonce);
-        buf.put_slice(self.code_hash.as_slice());
-        buf.put_u8(self.is_contract as u8);
-        73
+impl TableValue for StoredDualvmAccount {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.u256(&self.balance);
+        w.u64(self.nonce);
+        w.b256(&self.code_hash);
+        w.bool(self.is_contract);
     }
 
-    fn from_compact(buf: &[u8], _len: usize) -> (Self, &[u8]) {
-        let balance = U256::from_be_slice(&buf[0..32]);
-        let nonce = u64::from_be_bytes(buf[32..40].try_into().unwrap());
-        let code_hash = B256::from_slice(&buf[40..72]);
-        let is_contract = buf[72] != 0;
-
-        (Self { balance, nonce, code_hash, is_contract }, &buf[73..])
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            balance: r.u256()?,
+            nonce: r.u64()?,
+            code_hash: r.b256()?,
+            is_contract: r.bool()?,
+        })
     }
 }
 
-impl Compress for StoredDualvmAccount {
-    type Compressed = Vec<u8>;
+/// DexVM counter value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredCounter {
+    pub value: u64,
+}
 
-    fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
-        self.to_compact(buf);
+impl TableValue for StoredCounter {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.u64(self.value);
     }
-}
 
-impl Decompress for StoredDualvmAccount {
-    fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
-        if value.len() < 73 {
-            return Err(reth_db_api::DatabaseError::Decode);
-        }
-        let (account, _) = Self::from_compact(value, value.len());
-        Ok(account)
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self { value: r.u64()? })
     }
 }
 
-/// DexVM counter value
+/// Storage value wrapper
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct StoredCounter {
-    pub value: u64,
+pub struct StoredStorageValue {
+    pub value: U256,
 }
 
-impl Compact for StoredCounter {
-    fn to_compact<B>(&self, buf: &mut B) -> usize
-    where
-        B: BufMut + AsMut<[u8]>,
-    {
-        buf.put_u64(self.value);
-        8
+impl TableValue for StoredStorageValue {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.u256(&self.value);
     }
 
-    fn from_compact(buf: &[u8], _len: usize) -> (Self, &[u8]) {
-        let value = u64::from_be_bytes(buf[0..8].try_into().unwrap());
-        (Self { value }, &buf[8..])
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self { value: r.u256()? })
     }
 }
 
-impl Compress for StoredCounter {
-    type Compressed = Vec<u8>;
+/// Transaction info stored for lookup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredTxInfo {
+    pub block_number: BlockNumber,
+    pub tx_index: u64,
+}
+
+impl TableValue for StoredTxInfo {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.u64(self.block_number);
+        w.u64(self.tx_index);
+    }
 
-    fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
-        self.to_compact(buf);
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self { block_number: r.u64()?, tx_index: r.u64()? })
     }
 }
 
-impl Decompress for StoredCounter {
-    fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
-        if value.len() < 8 {
-            return Err(reth_db_api::DatabaseError::Decode);
-        }
-        let (counter, _) = Self::from_compact(value, value.len());
-        Ok(counter)
+/// Full transaction data stored for block body retrieval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredTransaction {
+    /// RLP-encoded transaction bytes
+    pub rlp_bytes: Vec<u8>,
+}
+
+impl TableValue for StoredTransaction {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.compressed_bytes(&self.rlp_bytes);
+    }
+
+    fn decode_fields(r: &mut Reader<'_>, version: u8) -> Result<Self, DatabaseError> {
+        // Version 1 stored the RLP uncompressed and without a format flag
+        let rlp_bytes = if version == 1 { r.bytes()? } else { r.compressed_bytes()? };
+        Ok(Self { rlp_bytes })
     }
 }
 
-/// Storage value wrapper
+/// Contract bytecode, keyed by code hash
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct StoredStorageValue {
-    pub value: U256,
+pub struct StoredBytecode {
+    pub code: Vec<u8>,
 }
 
-impl Compact for StoredStorageValue {
-    fn to_compact<B>(&self, buf: &mut B) -> usize
-    where
-        B: BufMut + AsMut<[u8]>,
-    {
-        buf.put_slice(&self.value.to_be_bytes::<32>());
-        32
+impl TableValue for StoredBytecode {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.bytes(&self.code);
     }
 
-    fn from_compact(buf: &[u8], _len: usize) -> (Self, &[u8]) {
-        let value = U256::from_be_slice(&buf[0..32]);
-        (Self { value }, &buf[32..])
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self { code: r.bytes()? })
     }
 }
 
-impl Compress for StoredStorageValue {
-    type Compressed = Vec<u8>;
+/// Per-block execution summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredBlockStats {
+    /// Plain EVM transactions
+    pub evm_tx_count: u64,
+    /// EVM transactions calling the counter precompile
+    pub cross_vm_tx_count: u64,
+    /// Signed transactions routed to DexVM
+    pub dexvm_tx_count: u64,
+    /// Native DexVM transactions from the DexVM pool
+    pub native_dexvm_tx_count: u64,
+    /// Transactions that executed but failed
+    pub failed_tx_count: u64,
+    /// DexVM counter operations applied
+    pub dexvm_ops_count: u64,
+    /// Total gas used
+    pub gas_used: u64,
+    /// Block execution time in microseconds
+    pub execution_micros: u64,
+}
 
-    fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
-        self.to_compact(buf);
+impl StoredBlockStats {
+    /// Total transactions in the block
+    pub fn tx_count(&self) -> u64 {
+        self.evm_tx_count
+            + self.cross_vm_tx_count
+            + self.dexvm_tx_count
+            + self.native_dexvm_tx_count
     }
 }
 
-impl Decompress for StoredStorageValue {
-    fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
-        if value.len() < 32 {
-            return Err(reth_db_api::DatabaseError::Decode);
-        }
-        let (storage, _) = Self::from_compact(value, value.len());
-        Ok(storage)
+impl TableValue for StoredBlockStats {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.u64(self.evm_tx_count);
+        w.u64(self.cross_vm_tx_count);
+        w.u64(self.dexvm_tx_count);
+        w.u64(self.native_dexvm_tx_count);
+        w.u64(self.failed_tx_count);
+        w.u64(self.dexvm_ops_count);
+        w.u64(self.gas_used);
+        w.u64(self.execution_micros);
+    }
+
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            evm_tx_count: r.u64()?,
+            cross_vm_tx_count: r.u64()?,
+            dexvm_tx_count: r.u64()?,
+            native_dexvm_tx_count: r.u64()?,
+            failed_tx_count: r.u64()?,
+            dexvm_ops_count: r.u64()?,
+            gas_used: r.u64()?,
+            execution_micros: r.u64()?,
+        })
     }
 }
 
-/// Transaction info stored for lookup
+/// Accounts and slots a transaction read or wrote
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct StoredTxInfo {
-    pub block_number: BlockNumber,
-    pub tx_index: u64,
+pub struct StoredAccessSet {
+    pub accounts_read: Vec<Address>,
+    pub accounts_written: Vec<Address>,
+    pub slots_read: Vec<(Address, B256)>,
+    pub slots_written: Vec<(Address, B256)>,
 }
 
-/// Full transaction data stored for block body retrieval
+impl TableValue for StoredAccessSet {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.list(&self.accounts_read, |w, address| w.address(address));
+        w.list(&self.accounts_written, |w, address| w.address(address));
+        w.list(&self.slots_read, write_slot);
+        w.list(&self.slots_written, write_slot);
+    }
+
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            accounts_read: r.list(20, |r| r.address())?,
+            accounts_written: r.list(20, |r| r.address())?,
+            slots_read: r.list(52, read_slot)?,
+            slots_written: r.list(52, read_slot)?,
+        })
+    }
+}
+
+fn write_slot<B: BufMut>(w: &mut Writer<'_, B>, (address, slot): &(Address, B256)) {
+    w.address(address);
+    w.b256(slot);
+}
+
+fn read_slot(r: &mut Reader<'_>) -> Result<(Address, B256), DatabaseError> {
+    Ok((r.address()?, r.b256()?))
+}
+
+/// Node metadata value, such as the schema version
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
-pub struct StoredTransaction {
-    /// RLP-encoded transaction bytes
-    pub rlp_bytes: Vec<u8>,
+pub struct StoredMetadata {
+    pub value: u64,
 }
 
-impl Compact for StoredTransaction {
-    fn to_compact<B>(&self, buf: &mut B) -> usize
-    where
-        B: BufMut + AsMut<[u8]>,
-    {
-        let len = self.rlp_bytes.len();
-        buf.put_u32(len as u32);
-        buf.put_slice(&self.rlp_bytes);
-        4 + len
+impl TableValue for StoredMetadata {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.u64(self.value);
     }
 
-    fn from_compact(buf: &[u8], _len: usize) -> (Self, &[u8]) {
-        let data_len = u32::from_be_bytes(buf[0..4].try_into().unwrap()) as usize;
-        let rlp_bytes = buf[4..4 + data_len].to_vec();
-        (Self { rlp_bytes }, &buf[4 + data_len..])
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self { value: r.u64()? })
     }
 }
 
-impl Compress for StoredTransaction {
-    type Compressed = Vec<u8>;
+/// Log emitted by a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Vec<u8>,
+}
+
+/// Execution receipt of a stored transaction
+///
+/// Carries the transaction's position and the block-wide index of its first
+/// log, so log indexes can be derived without reading the rest of the block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredReceipt {
+    pub success: bool,
+    pub cumulative_gas_used: u64,
+    /// Position of the transaction in its block
+    pub transaction_index: u64,
+    /// Block-wide index of the first log in `logs`
+    pub first_log_index: u64,
+    pub logs: Vec<StoredLog>,
+}
 
-    fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
-        self.to_compact(buf);
+impl StoredReceipt {
+    /// Block-wide index of the receipt's `n`th log
+    pub fn log_index(&self, n: usize) -> u64 {
+        self.first_log_index + n as u64
     }
-}
 
-impl Decompress for StoredTransaction {
-    fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
-        if value.len() < 4 {
-            return Err(reth_db_api::DatabaseError::Decode);
-        }
-        let (tx, _) = Self::from_compact(value, value.len());
-        Ok(tx)
+    /// Block-wide index the next receipt's logs start at
+    pub fn next_log_index(&self) -> u64 {
+        self.log_index(self.logs.len())
     }
 }
 
-impl Compact for StoredTxInfo {
-    fn to_compact<B>(&self, buf: &mut B) -> usize
-    where
-        B: BufMut + AsMut<[u8]>,
-    {
-        buf.put_u64(self.block_number);
-        buf.put_u64(self.tx_index);
-        16
+impl TableValue for StoredReceipt {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.bool(self.success);
+        w.u64(self.cumulative_gas_used);
+        w.u64(self.transaction_index);
+        w.u64(self.first_log_index);
+        w.list(&self.logs, |w, log| {
+            w.address(&log.address);
+            w.list(&log.topics, |w, topic| w.b256(topic));
+            w.bytes(&log.data);
+        });
     }
 
-    fn from_compact(buf: &[u8], _len: usize) -> (Self, &[u8]) {
-        let block_number = u64::from_be_bytes(buf[0..8].try_into().unwrap());
-        let tx_index = u64::from_be_bytes(buf[8..16].try_into().unwrap());
-        (Self { block_number, tx_index }, &buf[16..])
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            success: r.bool()?,
+            cumulative_gas_used: r.u64()?,
+            transaction_index: r.u64()?,
+            first_log_index: r.u64()?,
+            logs: r.list(28, |r| {
+                Ok(StoredLog {
+                    address: r.address()?,
+                    topics: r.list(32, |r| r.b256())?,
+                    data: r.bytes()?,
+                })
+            })?,
+        })
     }
 }
 
-impl Compress for StoredTxInfo {
-    type Compressed = Vec<u8>;
+/// A DexVM operation executed in a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredDexVmOperation {
+    pub tx_hash: B256,
+    /// Counter owner
+    pub address: Address,
+    /// Operation type byte, as in router calldata
+    pub op: u8,
+    /// Amount of an increment or decrement, zero for reads
+    pub amount: u64,
+    pub success: bool,
+    /// Counter value after the operation
+    pub new_value: u64,
+}
+
+/// DexVM receipts of a block's transactions, in execution order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredDexVmReceipts {
+    pub operations: Vec<StoredDexVmOperation>,
+}
+
+impl TableValue for StoredDexVmReceipts {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.list(&self.operations, |w, operation| {
+            w.b256(&operation.tx_hash);
+            w.address(&operation.address);
+            w.u8(operation.op);
+            w.u64(operation.amount);
+            w.bool(operation.success);
+            w.u64(operation.new_value);
+        });
+    }
 
-    fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
-        self.to_compact(buf);
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            operations: r.list(70, |r| {
+                Ok(StoredDexVmOperation {
+                    tx_hash: r.b256()?,
+                    address: r.address()?,
+                    op: r.u8()?,
+                    amount: r.u64()?,
+                    success: r.bool()?,
+                    new_value: r.u64()?,
+                })
+            })?,
+        })
     }
 }
 
-impl Decompress for StoredTxInfo {
-    fn decompress(value: &[u8]) -> Result<Self, reth_db_api::DatabaseError> {
-        if value.len() < 16 {
-            return Err(reth_db_api::DatabaseError::Decode);
-        }
-        let (info, _) = Self::from_compact(value, value.len());
-        Ok(info)
+/// A competing block announced at a height the local chain already has
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredSiblingBlock {
+    pub hash: B256,
+    /// Signer recovered from the block's header, once the header was fetched
+    pub signer: Option<Address>,
+    /// Peer the block was first seen from
+    pub peer: B512,
+    /// When the block was first seen (unix milliseconds)
+    pub received_at_ms: u64,
+}
+
+/// Competing blocks seen at one height, in the order they were first seen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct StoredSiblingBlocks {
+    pub blocks: Vec<StoredSiblingBlock>,
+}
+
+impl TableValue for StoredSiblingBlocks {
+    fn encode_fields<B: BufMut>(&self, w: &mut Writer<'_, B>) {
+        w.list(&self.blocks, |w, block| {
+            w.b256(&block.hash);
+            w.bool(block.signer.is_some());
+            if let Some(signer) = &block.signer {
+                w.address(signer);
+            }
+            w.raw(block.peer.as_slice());
+            w.u64(block.received_at_ms);
+        });
+    }
+
+    fn decode_fields(r: &mut Reader<'_>, _version: u8) -> Result<Self, DatabaseError> {
+        Ok(Self {
+            blocks: r.list(105, |r| {
+                Ok(StoredSiblingBlock {
+                    hash: r.b256()?,
+                    signer: if r.bool()? { Some(r.address()?) } else { None },
+                    peer: B512::from(r.raw::<64>()?),
+                    received_at_ms: r.u64()?,
+                })
+            })?,
+        })
     }
 }
 
+impl_compression_for_table_value!(
+    StoredDualvmBlock,
+    StoredDualvmAccount,
+    StoredCounter,
+    StoredStorageValue,
+    StoredTxInfo,
+    StoredTransaction,
+    StoredBytecode,
+    StoredBlockStats,
+    StoredAccessSet,
+    StoredMetadata,
+    StoredReceipt,
+    StoredSiblingBlocks,
+    StoredDexVmReceipts,
+);
+
 // Table definitions
 
 /// DualVM blocks table: BlockNumber -> StoredDualvmBlock
@@ -553,6 +779,227 @@ impl TableInfo for DualvmTransactions {
     }
 }
 
+/// DualVM bytecodes table: B256 (code_hash) -> StoredBytecode
+#[derive(Debug)]
+pub struct DualvmBytecodes;
+
+impl Table for DualvmBytecodes {
+    const NAME: &'static str = table_names::DUALVM_BYTECODES;
+    const DUPSORT: bool = false;
+    type Key = B256;
+    type Value = StoredBytecode;
+}
+
+impl TableInfo for DualvmBytecodes {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM block stats table: BlockNumber -> StoredBlockStats
+#[derive(Debug)]
+pub struct DualvmBlockStats;
+
+impl Table for DualvmBlockStats {
+    const NAME: &'static str = table_names::DUALVM_BLOCK_STATS;
+    const DUPSORT: bool = false;
+    type Key = BlockNumber;
+    type Value = StoredBlockStats;
+}
+
+impl TableInfo for DualvmBlockStats {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM access sets table: B256 (tx_hash) -> StoredAccessSet
+#[derive(Debug)]
+pub struct DualvmAccessSets;
+
+impl Table for DualvmAccessSets {
+    const NAME: &'static str = table_names::DUALVM_ACCESS_SETS;
+    const DUPSORT: bool = false;
+    type Key = B256;
+    type Value = StoredAccessSet;
+}
+
+impl TableInfo for DualvmAccessSets {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM metadata table: u64 key -> StoredMetadata
+#[derive(Debug)]
+pub struct DualvmMetadata;
+
+/// [`DualvmMetadata`] key holding the schema version
+pub const SCHEMA_VERSION_KEY: u64 = 0;
+
+impl Table for DualvmMetadata {
+    const NAME: &'static str = table_names::DUALVM_METADATA;
+    const DUPSORT: bool = false;
+    type Key = u64;
+    type Value = StoredMetadata;
+}
+
+impl TableInfo for DualvmMetadata {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM receipts table: B256 (tx_hash) -> StoredReceipt
+#[derive(Debug)]
+pub struct DualvmReceipts;
+
+impl Table for DualvmReceipts {
+    const NAME: &'static str = table_names::DUALVM_RECEIPTS;
+    const DUPSORT: bool = false;
+    type Key = B256;
+    type Value = StoredReceipt;
+}
+
+impl TableInfo for DualvmReceipts {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM sibling blocks table: BlockNumber -> StoredSiblingBlocks
+#[derive(Debug)]
+pub struct DualvmSiblingBlocks;
+
+impl Table for DualvmSiblingBlocks {
+    const NAME: &'static str = table_names::DUALVM_SIBLING_BLOCKS;
+    const DUPSORT: bool = false;
+    type Key = BlockNumber;
+    type Value = StoredSiblingBlocks;
+}
+
+impl TableInfo for DualvmSiblingBlocks {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM counter ranking index: CounterRankKey -> StoredCounter
+///
+/// Holds one entry per row of [`DualvmCounters`], written in the same
+/// transaction, so the highest counters are read without scanning them all.
+#[derive(Debug)]
+pub struct DualvmCounterRanks;
+
+impl Table for DualvmCounterRanks {
+    const NAME: &'static str = table_names::DUALVM_COUNTER_RANKS;
+    const DUPSORT: bool = false;
+    type Key = CounterRankKey;
+    type Value = StoredCounter;
+}
+
+impl TableInfo for DualvmCounterRanks {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM labeled counters table (for DexVM): LabeledCounterKey -> StoredCounter
+///
+/// Named counters an address owns besides its main counter in
+/// [`DualvmCounters`]. Zero counters are deleted.
+#[derive(Debug)]
+pub struct DualvmLabeledCounters;
+
+impl Table for DualvmLabeledCounters {
+    const NAME: &'static str = table_names::DUALVM_LABELED_COUNTERS;
+    const DUPSORT: bool = false;
+    type Key = LabeledCounterKey;
+    type Value = StoredCounter;
+}
+
+impl TableInfo for DualvmLabeledCounters {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM DexVM receipts table: BlockNumber -> StoredDexVmReceipts
+#[derive(Debug)]
+pub struct DualvmDexVmReceipts;
+
+impl Table for DualvmDexVmReceipts {
+    const NAME: &'static str = table_names::DUALVM_DEXVM_RECEIPTS;
+    const DUPSORT: bool = false;
+    type Key = BlockNumber;
+    type Value = StoredDexVmReceipts;
+}
+
+impl TableInfo for DualvmDexVmReceipts {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
+/// DualVM native DexVM nonces table: Address -> StoredCounter
+///
+/// Nonce of the last native DexVM transaction each sender had executed.
+#[derive(Debug)]
+pub struct DualvmDexVmNonces;
+
+impl Table for DualvmDexVmNonces {
+    const NAME: &'static str = table_names::DUALVM_DEXVM_NONCES;
+    const DUPSORT: bool = false;
+    type Key = Address;
+    type Value = StoredCounter;
+}
+
+impl TableInfo for DualvmDexVmNonces {
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn is_dupsort(&self) -> bool {
+        Self::DUPSORT
+    }
+}
+
 /// TableSet implementation for DualVM tables
 pub struct DualvmTableSet;
 
@@ -566,8 +1013,244 @@ impl reth_db_api::TableSet for DualvmTableSet {
                 Box::new(DualvmStorage) as Box<dyn TableInfo>,
                 Box::new(DualvmTxHashes) as Box<dyn TableInfo>,
                 Box::new(DualvmTransactions) as Box<dyn TableInfo>,
+                Box::new(DualvmBytecodes) as Box<dyn TableInfo>,
+                Box::new(DualvmBlockStats) as Box<dyn TableInfo>,
+                Box::new(DualvmAccessSets) as Box<dyn TableInfo>,
+                Box::new(DualvmMetadata) as Box<dyn TableInfo>,
+                Box::new(DualvmReceipts) as Box<dyn TableInfo>,
+                Box::new(DualvmSiblingBlocks) as Box<dyn TableInfo>,
+                Box::new(DualvmCounterRanks) as Box<dyn TableInfo>,
+                Box::new(DualvmLabeledCounters) as Box<dyn TableInfo>,
+                Box::new(DualvmDexVmReceipts) as Box<dyn TableInfo>,
+                Box::new(DualvmDexVmNonces) as Box<dyn TableInfo>,
             ]
             .into_iter(),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_db_api::table::{Compress, Decompress};
+    use std::fmt::Debug;
+
+    /// Deterministic xorshift generator so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> usize {
+            (self.next() % bound) as usize
+        }
+
+        fn array<const N: usize>(&mut self) -> [u8; N] {
+            std::array::from_fn(|_| self.next() as u8)
+        }
+
+        fn bytes(&mut self, max_len: u64) -> Vec<u8> {
+            (0..self.below(max_len)).map(|_| self.next() as u8).collect()
+        }
+
+        fn address(&mut self) -> Address {
+            Address::from(self.array::<20>())
+        }
+
+        fn b256(&mut self) -> B256 {
+            B256::from(self.array::<32>())
+        }
+
+        fn u256(&mut self) -> U256 {
+            U256::from_be_bytes(self.array::<32>())
+        }
+
+        fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> T) -> Vec<T> {
+            (0..self.below(5)).map(|_| item(self)).collect()
+        }
+    }
+
+    fn random_block(rng: &mut Rng) -> StoredDualvmBlock {
+        StoredDualvmBlock {
+            hash: rng.b256(),
+            parent_hash: rng.b256(),
+            timestamp: rng.next(),
+            gas_limit: rng.next(),
+            gas_used: rng.next(),
+            miner: rng.address(),
+            evm_state_root: rng.b256(),
+            dexvm_state_root: rng.b256(),
+            combined_state_root: rng.b256(),
+            transaction_count: rng.next(),
+            signature: rng.array(),
+            transaction_hashes: rng.list(Rng::b256),
+        }
+    }
+
+    fn random_access_set(rng: &mut Rng) -> StoredAccessSet {
+        StoredAccessSet {
+            accounts_read: rng.list(Rng::address),
+            accounts_written: rng.list(Rng::address),
+            slots_read: rng.list(|rng| (rng.address(), rng.b256())),
+            slots_written: rng.list(|rng| (rng.address(), rng.b256())),
+        }
+    }
+
+    fn random_stats(rng: &mut Rng) -> StoredBlockStats {
+        StoredBlockStats {
+            evm_tx_count: rng.next(),
+            cross_vm_tx_count: rng.next(),
+            dexvm_tx_count: rng.next(),
+            native_dexvm_tx_count: rng.next(),
+            failed_tx_count: rng.next(),
+            dexvm_ops_count: rng.next(),
+            gas_used: rng.next(),
+            execution_micros: rng.next(),
+        }
+    }
+
+    fn random_receipt(rng: &mut Rng) -> StoredReceipt {
+        StoredReceipt {
+            success: rng.next() % 2 == 0,
+            cumulative_gas_used: rng.next(),
+            transaction_index: rng.next(),
+            first_log_index: rng.next(),
+            logs: rng.list(|rng| StoredLog {
+                address: rng.address(),
+                topics: rng.list(Rng::b256),
+                data: rng.bytes(100),
+            }),
+        }
+    }
+
+    fn random_siblings(rng: &mut Rng) -> StoredSiblingBlocks {
+        StoredSiblingBlocks {
+            blocks: rng.list(|rng| StoredSiblingBlock {
+                hash: rng.b256(),
+                signer: (rng.next() % 2 == 0).then(|| rng.address()),
+                peer: B512::from(rng.array::<64>()),
+                received_at_ms: rng.next(),
+            }),
+        }
+    }
+
+    fn random_dexvm_receipts(rng: &mut Rng) -> StoredDexVmReceipts {
+        StoredDexVmReceipts {
+            operations: rng.list(|rng| StoredDexVmOperation {
+                tx_hash: rng.b256(),
+                address: rng.address(),
+                op: rng.next() as u8,
+                amount: rng.next(),
+                success: rng.next() % 2 == 0,
+                new_value: rng.next(),
+            }),
+        }
+    }
+
+    /// Round-trip `value` and check that truncated or extended encodings fail
+    fn assert_roundtrip<T>(value: T)
+    where
+        T: Compress<Compressed = Vec<u8>> + Decompress + Clone + PartialEq + Debug,
+    {
+        let encoded = value.clone().compress();
+        assert_eq!(T::decompress(&encoded).unwrap(), value);
+        for len in 0..encoded.len() {
+            assert!(T::decompress(&encoded[..len]).is_err(), "{:?} truncated to {}", value, len);
+        }
+        let mut extended = encoded;
+        extended.push(0);
+        assert!(T::decompress(&extended).is_err());
+    }
+
+    /// Feed `bytes` to every decoder; only panics fail the test
+    fn decode_all(bytes: &[u8]) {
+        let _ = StoredDualvmBlock::decompress(bytes);
+        let _ = StoredDualvmAccount::decompress(bytes);
+        let _ = StoredCounter::decompress(bytes);
+        let _ = StoredStorageValue::decompress(bytes);
+        let _ = StoredTxInfo::decompress(bytes);
+        let _ = StoredTransaction::decompress(bytes);
+        let _ = StoredBytecode::decompress(bytes);
+        let _ = StoredBlockStats::decompress(bytes);
+        let _ = StoredAccessSet::decompress(bytes);
+        let _ = StoredMetadata::decompress(bytes);
+        let _ = StoredReceipt::decompress(bytes);
+        let _ = StoredSiblingBlocks::decompress(bytes);
+        let _ = StoredDexVmReceipts::decompress(bytes);
+    }
+
+    #[test]
+    fn test_values_roundtrip() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..64 {
+            assert_roundtrip(random_block(&mut rng));
+            assert_roundtrip(StoredDualvmAccount {
+                balance: rng.u256(),
+                nonce: rng.next(),
+                code_hash: rng.b256(),
+                is_contract: rng.next() % 2 == 0,
+            });
+            assert_roundtrip(StoredCounter { value: rng.next() });
+            assert_roundtrip(StoredStorageValue { value: rng.u256() });
+            assert_roundtrip(StoredTxInfo { block_number: rng.next(), tx_index: rng.next() });
+            assert_roundtrip(StoredTransaction { rlp_bytes: rng.bytes(300) });
+            assert_roundtrip(StoredBytecode { code: rng.bytes(300) });
+            assert_roundtrip(random_stats(&mut rng));
+            assert_roundtrip(random_access_set(&mut rng));
+            assert_roundtrip(StoredMetadata { value: rng.next() });
+            assert_roundtrip(random_receipt(&mut rng));
+            assert_roundtrip(random_siblings(&mut rng));
+            assert_roundtrip(random_dexvm_receipts(&mut rng));
+        }
+    }
+
+    #[test]
+    fn test_transaction_compression() {
+        // Deployment-like RLP compresses and round-trips
+        let mut rlp = vec![0xf9, 0x10, 0x00];
+        rlp.extend((0..4096).map(|i| [0x60, 0x80, 0x60, 0x40, 0x52, 0x00][i % 6]));
+        let tx = StoredTransaction { rlp_bytes: rlp.clone() };
+        let encoded = tx.clone().compress();
+        assert!(encoded.len() < rlp.len() / 3);
+        assert_roundtrip(tx);
+
+        // Values written by codec version 1 are still read
+        let mut legacy = vec![1];
+        legacy.extend_from_slice(&(rlp.len() as u32).to_be_bytes());
+        legacy.extend_from_slice(&rlp);
+        assert_eq!(StoredTransaction::decompress(&legacy).unwrap().rlp_bytes, rlp);
+    }
+
+    #[test]
+    fn test_decode_arbitrary_bytes() {
+        let mut rng = Rng(0xf022);
+
+        // Random input, half of it carrying a valid version byte
+        for _ in 0..20_000 {
+            let mut bytes = rng.bytes(400);
+            if rng.next() % 2 == 0 {
+                if let Some(first) = bytes.first_mut() {
+                    *first = crate::codec::CODEC_VERSION;
+                }
+            }
+            decode_all(&bytes);
+        }
+
+        // Valid encodings with one byte flipped
+        for _ in 0..2_000 {
+            let mut bytes = match rng.below(3) {
+                0 => random_block(&mut rng).compress(),
+                1 => random_access_set(&mut rng).compress(),
+                _ => StoredBytecode { code: rng.bytes(64) }.compress(),
+            };
+            let index = rng.below(bytes.len() as u64);
+            bytes[index] ^= rng.next() as u8 | 1;
+            decode_all(&bytes);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Storage traits
+//!
+//! The executor, sync and RPC layers reach state and blocks through these
+//! traits, so a backend other than [`StateStore`](crate::StateStore) and
+//! [`BlockStore`](crate::BlockStore) (another database, a remote state
+//! service) can be plugged in by implementing them. Writers extend readers:
+//! a component that writes also reads what it wrote.
+
+use crate::{
+    block_store::{RangeDirection, RecoveryReport, StoredBlock, UnwoundBlock},
+    state_store::AccountState,
+    tables::{
+        StoredAccessSet, StoredBlockStats, StoredDexVmReceipts, StoredReceipt, StoredSiblingBlock,
+        StoredSiblingBlocks, StoredTxInfo,
+    },
+};
+use alloy_primitives::{Address, Bytes, B256, U256};
+use eyre::Result;
+use std::collections::HashMap;
+
+/// Read access to account state and DexVM counters
+pub trait StateReader: Send + Sync {
+    /// Get account state, including its code and storage
+    fn get_account(&self, address: &Address) -> Option<AccountState>;
+
+    /// Get account balance
+    fn get_balance(&self, address: &Address) -> U256;
+
+    /// Get account nonce
+    fn get_nonce(&self, address: &Address) -> u64;
+
+    /// Get contract code by resolving the account's code hash
+    fn get_code(&self, address: &Address) -> Option<Bytes>;
+
+    /// Get storage value
+    fn get_storage(&self, address: &Address, slot: U256) -> U256;
+
+    /// Get counter value (for DexVM)
+    fn get_counter(&self, address: &Address) -> u64;
+
+    /// Calculate state root
+    fn state_root(&self) -> B256;
+
+    /// Get all accounts, without code or storage
+    fn all_accounts(&self) -> HashMap<Address, AccountState>;
+
+    /// Get all counters (for DexVM state recovery)
+    fn all_counters(&self) -> HashMap<Address, u64>;
+
+    /// Highest non-zero counters, largest first and ties by address
+    fn top_counters(&self, limit: usize) -> Vec<(Address, u64)>;
+
+    /// Get a labeled counter value (for DexVM); label zero is the main counter
+    fn get_labeled_counter(&self, address: &Address, label: B256) -> u64;
+
+    /// Non-zero labeled counters of an address, by label
+    fn counter_labels(&self, address: &Address) -> Vec<(B256, u64)>;
+
+    /// Get all labeled counters (for DexVM state recovery)
+    fn all_labeled_counters(&self) -> HashMap<(Address, B256), u64>;
+
+    /// Last executed native DexVM transaction nonce of every sender
+    fn all_dexvm_nonces(&self) -> HashMap<Address, u64>;
+}
+
+/// Write access to account state and DexVM counters
+pub trait StateWriter: StateReader {
+    /// Set account state
+    fn set_account(&self, address: Address, state: AccountState) -> Result<()>;
+
+    /// Set account balance
+    fn set_balance(&self, address: Address, balance: U256) -> Result<()>;
+
+    /// Set account nonce
+    fn set_nonce(&self, address: Address, nonce: u64) -> Result<()>;
+
+    /// Delete an account's balance and nonce entry
+    ///
+    /// Code and storage are left in place; callers use this to undo the
+    /// creation of a plain account.
+    fn remove_account(&self, address: Address) -> Result<()>;
+
+    /// Put accounts back as they were, code and storage included, in one write
+    ///
+    /// `None` deletes the account and its storage. Slots not in a restored
+    /// account's storage are deleted.
+    fn restore_accounts(&self, accounts: &[(Address, Option<AccountState>)]) -> Result<()>;
+
+    /// Increment nonce and return new value
+    fn increment_nonce(&self, address: Address) -> Result<u64>;
+
+    /// Set contract code
+    fn set_code(&self, address: Address, code: Bytes) -> Result<()>;
+
+    /// Set storage value, deleting the slot when it is zero
+    fn set_storage(&self, address: Address, slot: U256, value: U256) -> Result<()>;
+
+    /// Set counter value (for DexVM)
+    fn set_counter(&self, address: Address, value: u64) -> Result<()>;
+
+    /// Write many counters at once
+    fn set_counters(&self, counters: &[(Address, u64)]) -> Result<()>;
+
+    /// Write many labeled counters at once, deleting zero ones
+    ///
+    /// Label zero writes the main counter, as [`StateWriter::set_counter`] does.
+    fn set_labeled_counters(&self, counters: &[(Address, B256, u64)]) -> Result<()>;
+
+    /// Write the last executed native DexVM transaction nonce of senders
+    fn set_dexvm_nonces(&self, nonces: &[(Address, u64)]) -> Result<()>;
+
+    /// Increment counter and return new value
+    fn increment_counter(&self, address: Address, amount: u64) -> Result<u64>;
+
+    /// Decrement counter and return new value, failing on underflow
+    fn decrement_counter(&self, address: Address, amount: u64) -> Result<u64>;
+
+    /// Initialize from genesis allocation
+    fn init_genesis(&self, alloc: HashMap<Address, U256>) -> Result<()>;
+}
+
+/// Read access to blocks, transactions and their execution records
+pub trait BlockReader: Send + Sync {
+    /// Get latest block number
+    fn latest_block_number(&self) -> u64;
+
+    /// Re-read the highest stored block number, returning it
+    ///
+    /// Read-only replicas call this to pick up blocks another process wrote or
+    /// unwound. Cached blocks above the head, or replaced since they were
+    /// cached, are dropped.
+    fn refresh_latest_block_number(&self) -> Result<u64>;
+
+    /// Get block by number
+    fn get_block_by_number(&self, number: u64) -> Option<StoredBlock>;
+
+    /// Get block by hash
+    fn get_block_by_hash(&self, hash: B256) -> Option<StoredBlock>;
+
+    /// Get block count
+    fn block_count(&self) -> usize;
+
+    /// Get up to `count` consecutive blocks from `start`, in `direction`
+    ///
+    /// Reads them in one cursor walk. The result stops before the first
+    /// missing block, so it is empty if `start` is not stored.
+    fn get_headers_range(
+        &self,
+        start: u64,
+        count: u64,
+        direction: RangeDirection,
+    ) -> Vec<StoredBlock>;
+
+    /// Refuse a database with blocks missing below the stored head
+    ///
+    /// Block production resumes from the highest stored block, so a block
+    /// that failed to persist would otherwise be skipped for good.
+    fn check_contiguous(&self) -> Result<()>;
+
+    /// Get transaction info by hash
+    fn get_tx_info(&self, tx_hash: B256) -> Option<StoredTxInfo>;
+
+    /// Get a transaction by its hash
+    fn get_transaction(&self, tx_hash: B256) -> Option<Vec<u8>>;
+
+    /// Get execution summaries for blocks in `from..=to`
+    ///
+    /// Blocks without a summary (imported by sync or stored before stats existed) are skipped.
+    fn get_block_stats_range(&self, from: u64, to: u64) -> Vec<(u64, StoredBlockStats)>;
+
+    /// Get the recorded access set of an executed transaction
+    fn get_access_set(&self, tx_hash: B256) -> Option<StoredAccessSet>;
+
+    /// Get the execution receipt of a stored transaction
+    fn get_receipt(&self, tx_hash: B256) -> Option<StoredReceipt>;
+
+    /// Get the DexVM receipts of a block's transactions
+    ///
+    /// Blocks without DexVM transactions, or imported by sync, have none.
+    fn get_dexvm_receipts(&self, number: u64) -> Option<StoredDexVmReceipts>;
+
+    /// Competing blocks recorded at heights in `from..=to`
+    fn get_siblings_range(&self, from: u64, to: u64) -> Vec<(u64, StoredSiblingBlocks)>;
+
+    /// Get latest block
+    fn get_latest_block(&self) -> Option<StoredBlock> {
+        self.get_block_by_number(self.latest_block_number())
+    }
+
+    /// Get block number containing a transaction
+    fn get_tx_block_number(&self, tx_hash: B256) -> Option<u64> {
+        self.get_tx_info(tx_hash).map(|info| info.block_number)
+    }
+
+    /// Check if genesis block exists
+    fn has_genesis(&self) -> bool {
+        self.get_block_by_number(0).is_some()
+    }
+
+    /// Refuse a database created from a different genesis
+    ///
+    /// The stored genesis must be `genesis_hash`, or the synthetic genesis of
+    /// `chain_id` that databases created before genesis headers were derived
+    /// from the genesis file hold. Databases without genesis pass.
+    fn check_genesis(&self, chain_id: u64, genesis_hash: B256) -> Result<()> {
+        let Some(genesis) = self.get_block_by_number(0) else {
+            return Ok(());
+        };
+
+        if genesis.hash != genesis_hash && genesis.hash != StoredBlock::genesis(chain_id).hash {
+            return Err(eyre::eyre!(
+                "Database genesis {} does not match genesis {} of chain {}",
+                genesis.hash,
+                genesis_hash,
+                chain_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Get all transactions for a block by block number
+    fn get_block_transactions(&self, block_number: u64) -> Option<Vec<Vec<u8>>> {
+        let block = self.get_block_by_number(block_number)?;
+        let transactions = block.transaction_hashes.iter();
+        Some(transactions.filter_map(|hash| self.get_transaction(*hash)).collect())
+    }
+
+    /// Get transactions by their hashes
+    fn get_transactions_by_hashes(&self, hashes: &[B256]) -> Vec<Option<Vec<u8>>> {
+        hashes.iter().map(|hash| self.get_transaction(*hash)).collect()
+    }
+}
+
+/// Write access to blocks, transactions and their execution records
+pub trait BlockWriter: BlockReader {
+    /// Store a block
+    fn store_block(&self, block: StoredBlock) -> Result<()>;
+
+    /// Store multiple transactions in a single batch
+    fn store_transactions(&self, transactions: &[(B256, Vec<u8>)]) -> Result<()>;
+
+    /// Store the execution summary of a block
+    fn store_block_stats(&self, number: u64, stats: StoredBlockStats) -> Result<()>;
+
+    /// Store the recorded access sets of executed transactions
+    fn store_access_sets(&self, access_sets: &[(B256, StoredAccessSet)]) -> Result<()>;
+
+    /// Store the execution receipts of a block's transactions
+    fn store_receipts(&self, receipts: &[(B256, StoredReceipt)]) -> Result<()>;
+
+    /// Store the DexVM receipts of a block's transactions
+    fn store_dexvm_receipts(&self, number: u64, receipts: StoredDexVmReceipts) -> Result<()>;
+
+    /// Record a competing block seen at `number`, returning whether anything changed
+    ///
+    /// The stored block at that height is not a sibling and is ignored. A block
+    /// seen again keeps its first peer and time, gaining a signer once known.
+    fn record_sibling(&self, number: u64, sibling: StoredSiblingBlock) -> Result<bool>;
+
+    /// Find where block production resumes and drop data of a half-written block
+    ///
+    /// A block's transactions, access sets, receipts and stats are written before the
+    /// block itself, so a crash in between leaves them behind without a
+    /// block. They are removed here, in one write transaction, so the block is
+    /// produced again from scratch.
+    fn recover(&self) -> Result<RecoveryReport>;
+
+    /// Remove every block above `number`, returning them newest first
+    ///
+    /// Their transactions, receipts, access sets and stats go with them, in one
+    /// write transaction. Account and counter state is left to the caller.
+    fn unwind_to(&self, number: u64) -> Result<Vec<UnwoundBlock>>;
+
+    /// Store a full transaction by its hash
+    fn store_transaction(&self, tx_hash: B256, rlp_bytes: Vec<u8>) -> Result<()> {
+        self.store_transactions(&[(tx_hash, rlp_bytes)])
+    }
+
+    /// Initialize with genesis block
+    fn init_genesis(&self, chain_id: u64) -> Result<()> {
+        if self.has_genesis() {
+            tracing::info!("Genesis block already exists");
+            return Ok(());
+        }
+
+        self.store_block(StoredBlock::genesis(chain_id))?;
+        tracing::info!("Initialized genesis block for chain {}", chain_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Multi-node test harness
+//!
+//! Launches a validator and any number of fullnodes in-process, each on random
+//! ports with an in-memory database, connected over P2P. Integration tests use it
+//! to exercise sync and propagation without manual multi-terminal runs.
+
+#[cfg(test)]
+mod rpc_compat;
+
+use alloy_consensus::{SignableTransaction, TxLegacy};
+use alloy_primitives::{Address, Signature, B256, U256};
+use dex_node::{Genesis, NodeConfig, NodeHandle, PoaConfig};
+use dex_p2p::{P2pConfig, P2pHandle};
+use dex_rpc::{evm_rpc::EthApiServer, EvmRpcServer};
+use dex_storage::{BlockReader, DualvmStorage};
+use reth_ethereum_primitives::TransactionSigned;
+use reth_network_peers::{pk2id, TrustedPeer};
+use secp256k1::{Message, SecretKey, SECP256K1};
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Chain ID used by test networks
+pub const TEST_CHAIN_ID: u64 = 1337;
+
+/// Validator and funded dev account key (well-known development key)
+pub const DEV_SECRET_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// Genesis balance of the dev account (1000 ETH)
+pub const DEV_BALANCE: u128 = 1_000_000_000_000_000_000_000;
+
+/// Default block interval for test networks
+pub const DEFAULT_BLOCK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often wait helpers poll node state
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Dev account secret key
+pub fn dev_secret_key() -> SecretKey {
+    SecretKey::from_slice(&hex::decode(DEV_SECRET_KEY).expect("valid hex"))
+        .expect("valid secret key")
+}
+
+/// Dev account address
+pub fn dev_address() -> Address {
+    dex_node::consensus::secret_key_to_address(&dev_secret_key())
+}
+
+/// Sign a legacy transaction
+pub fn sign_transaction(tx: TxLegacy, key: &SecretKey) -> TransactionSigned {
+    let message = Message::from_digest(tx.signature_hash().0);
+    let (recovery_id, signature) =
+        SECP256K1.sign_ecdsa_recoverable(&message, key).serialize_compact();
+    let signature = Signature::new(
+        U256::from_be_slice(&signature[..32]),
+        U256::from_be_slice(&signature[32..]),
+        i32::from(recovery_id) != 0,
+    );
+    TransactionSigned::new_unhashed(tx.into(), signature)
+}
+
+/// Reserve a free local TCP port
+fn free_port() -> eyre::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+fn genesis_alloc() -> HashMap<Address, U256> {
+    HashMap::from([(dev_address(), U256::from(DEV_BALANCE))])
+}
+
+/// Configuration of every test node
+fn node_config() -> NodeConfig {
+    NodeConfig {
+        chain_id: TEST_CHAIN_ID,
+        ephemeral: true,
+        evm_rpc_port: 0,
+        dexvm_rpc_port: 0,
+        genesis: Some(Genesis::with_balances(TEST_CHAIN_ID, genesis_alloc())),
+        validators: vec![dev_address()],
+        ..Default::default()
+    }
+}
+
+/// P2P configuration on a random local port, returning it with its enode URL
+fn p2p_config(boot_node: Option<&str>) -> eyre::Result<(P2pConfig, String)> {
+    let port = free_port()?;
+    let mut config = P2pConfig::new(P2pConfig::random_secret_key(), TEST_CHAIN_ID, B256::ZERO)
+        .with_listen_addr(SocketAddr::from(([127, 0, 0, 1], port)));
+    if let Some(enode) = boot_node {
+        let peer =
+            enode.parse::<TrustedPeer>().map_err(|e| eyre::eyre!("Invalid enode: {}", e))?;
+        config = config.with_boot_node(peer);
+    }
+    let local_id = pk2id(&config.secret_key.public_key(SECP256K1));
+    let enode = format!("enode://{}@127.0.0.1:{}", hex::encode(local_id.as_slice()), port);
+    Ok((config, enode))
+}
+
+/// A node running inside the test process
+pub struct TestNode {
+    /// Node database
+    pub storage: Arc<DualvmStorage>,
+    /// EVM JSON-RPC implementation
+    pub rpc: Arc<EvmRpcServer>,
+    /// P2P handle
+    pub p2p: P2pHandle,
+    /// Enode URL other nodes can dial
+    pub enode: String,
+    handle: NodeHandle,
+}
+
+impl TestNode {
+    /// Start a block-producing validator
+    pub async fn validator(block_interval: Duration) -> eyre::Result<Self> {
+        let poa_config = PoaConfig::from_hex_key(DEV_SECRET_KEY, block_interval)
+            .map_err(|e| eyre::eyre!("Invalid validator key: {}", e))?;
+        Self::launch(None, Some(poa_config)).await
+    }
+
+    /// Start a fullnode that syncs from `boot_node`
+    ///
+    /// It forwards transactions submitted to it to the validator.
+    pub async fn fullnode(boot_node: &str) -> eyre::Result<Self> {
+        Self::launch(Some(boot_node), None).await
+    }
+
+    async fn launch(boot_node: Option<&str>, poa_config: Option<PoaConfig>) -> eyre::Result<Self> {
+        let (p2p_config, enode) = p2p_config(boot_node)?;
+        let handle = dex_node::launch(node_config(), Some(p2p_config), poa_config).await?;
+        let p2p = handle.p2p().cloned().ok_or_else(|| eyre::eyre!("P2P not started"))?;
+        Ok(Self {
+            storage: Arc::clone(handle.storage()),
+            rpc: Arc::clone(handle.rpc_server()),
+            p2p,
+            enode,
+            handle,
+        })
+    }
+
+    /// The launched node, e.g. to subscribe to its chain events
+    pub fn handle(&self) -> &NodeHandle {
+        &self.handle
+    }
+
+    /// Latest stored block number
+    pub fn latest_block(&self) -> u64 {
+        self.storage.blocks.latest_block_number()
+    }
+
+    /// Submit a signed transaction through `eth_sendRawTransaction`
+    pub async fn submit_tx(&self, tx: &TransactionSigned) -> eyre::Result<B256> {
+        let raw = alloy_rlp::encode(tx).into();
+        EthApiServer::send_raw_transaction(self.rpc.as_ref(), raw)
+            .await
+            .map_err(|e| eyre::eyre!("Transaction rejected: {}", e.message()))
+    }
+
+    /// Send a JSON-RPC request to this node in-process, returning the whole response object
+    ///
+    /// The request goes through the same parameter parsing and result
+    /// encoding as one sent over HTTP.
+    pub async fn rpc_request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> eyre::Result<serde_json::Value> {
+        let module = dex_rpc::rpc_module(&self.rpc)?;
+        let request =
+            serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let (response, _) = module.raw_json_request(&request.to_string(), 1).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Wait until this node has stored block `number`
+    pub async fn wait_for_block(&self, number: u64, timeout: Duration) -> eyre::Result<()> {
+        wait_until(timeout, || self.latest_block() >= number).await.map_err(|_| {
+            eyre::eyre!("Timed out waiting for block {} (latest {})", number, self.latest_block())
+        })
+    }
+
+    /// Wait until this node has at least `count` connected peers
+    pub async fn wait_for_peers(&self, count: usize, timeout: Duration) -> eyre::Result<()> {
+        wait_until(timeout, || self.p2p.connected_count() >= count).await.map_err(|_| {
+            let connected = self.p2p.connected_count();
+            eyre::eyre!("Timed out waiting for {} peers ({} connected)", count, connected)
+        })
+    }
+}
+
+/// Poll `condition` until it holds or `timeout` elapses
+async fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> Result<(), ()> {
+    let deadline = Instant::now() + timeout;
+    while !condition() {
+        if Instant::now() >= deadline {
+            return Err(());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Ok(())
+}
+
+/// A validator plus fullnodes connected to it
+pub struct TestNetwork {
+    /// Block-producing validator
+    pub validator: TestNode,
+    /// Fullnodes syncing from the validator
+    pub fullnodes: Vec<TestNode>,
+}
+
+impl TestNetwork {
+    /// Spawn a validator and `fullnodes` fullnodes with the default block interval
+    pub async fn spawn(fullnodes: usize) -> eyre::Result<Self> {
+        Self::spawn_with_interval(fullnodes, DEFAULT_BLOCK_INTERVAL).await
+    }
+
+    /// Spawn a network with a custom block interval
+    pub async fn spawn_with_interval(
+        fullnodes: usize,
+        block_interval: Duration,
+    ) -> eyre::Result<Self> {
+        let validator = TestNode::validator(block_interval).await?;
+        let mut nodes = Vec::with_capacity(fullnodes);
+        for _ in 0..fullnodes {
+            nodes.push(TestNode::fullnode(&validator.enode).await?);
+        }
+        Ok(Self { validator, fullnodes: nodes })
+    }
+
+    /// All nodes, validator first
+    pub fn nodes(&self) -> impl Iterator<Item = &TestNode> {
+        std::iter::once(&self.validator).chain(&self.fullnodes)
+    }
+
+    /// Wait until every fullnode is connected to the validator
+    pub async fn wait_for_peers(&self, timeout: Duration) -> eyre::Result<()> {
+        for node in &self.fullnodes {
+            node.wait_for_peers(1, timeout).await?;
+        }
+        Ok(())
+    }
+
+    /// Wait until every node has stored block `number`
+    pub async fn wait_for_block(&self, number: u64, timeout: Duration) -> eyre::Result<()> {
+        for node in self.nodes() {
+            node.wait_for_block(number, timeout).await?;
+        }
+        Ok(())
+    }
+
+    /// Submit a transaction to the validator
+    pub async fn submit_tx(&self, tx: &TransactionSigned) -> eyre::Result<B256> {
+        self.validator.submit_tx(tx).await
+    }
+
+    /// Assert all nodes agree on every block they have in common
+    pub fn assert_state_equal(&self) {
+        let common = self.nodes().map(TestNode::latest_block).min().unwrap_or_default();
+        for number in 0..=common {
+            let expected = self.validator.storage.blocks.get_block_by_number(number);
+            let expected = expected.unwrap_or_else(|| panic!("validator missing block {number}"));
+            for (i, node) in self.fullnodes.iter().enumerate() {
+                let block = node
+                    .storage
+                    .blocks
+                    .get_block_by_number(number)
+                    .unwrap_or_else(|| panic!("fullnode {i} missing block {number}"));
+                assert_eq!(block.hash, expected.hash, "fullnode {i} block {number} hash");
+                assert_eq!(
+                    block.combined_state_root, expected.combined_state_root,
+                    "fullnode {i} block {number} state root"
+                );
+                assert_eq!(
+                    block.transaction_hashes, expected.transaction_hashes,
+                    "fullnode {i} block {number} transactions"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::TxKind;
+    use dex_storage::StateReader;
+
+    const TIMEOUT: Duration = Duration::from_secs(20);
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_transfer_propagates_to_fullnodes() {
+        let network = TestNetwork::spawn(2).await.unwrap();
+        network.wait_for_peers(TIMEOUT).await.unwrap();
+
+        let recipient = Address::repeat_byte(0x42);
+        let tx = sign_transaction(
+            TxLegacy {
+                chain_id: Some(TEST_CHAIN_ID),
+                nonce: 0,
+                gas_price: dex_rpc::BASE_FEE_PER_GAS as u128,
+                gas_limit: 21_000,
+                to: TxKind::Call(recipient),
+                value: U256::from(1_000),
+                ..Default::default()
+            },
+            &dev_secret_key(),
+        );
+        let hash = network.submit_tx(&tx).await.unwrap();
+
+        // Wait for the transfer to be mined and a few more blocks to sync
+        wait_until(TIMEOUT, || network.validator.storage.blocks.get_transaction(hash).is_some())
+            .await
+            .expect("transaction not mined");
+        let target = network.validator.latest_block() + 2;
+        network.wait_for_block(target, TIMEOUT).await.unwrap();
+
+        assert_eq!(network.validator.storage.state.get_balance(&recipient), U256::from(1_000));
+        network.assert_state_equal();
+    }
+}
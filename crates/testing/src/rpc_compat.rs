@@ -0,0 +1,213 @@
+//! JSON-RPC compatibility suite
+//!
+//! Runs a validator in-process and sends the raw JSON payloads wallets and
+//! libraries like ethers send, checking results against the Ethereum JSON-RPC
+//! encoding rules: quantities are `0x`-prefixed lowercase hex without leading
+//! zeros, data is `0x`-prefixed lowercase hex of the expected length, and
+//! malformed parameters fail with `-32602`.
+
+use super::*;
+use alloy_primitives::TxKind;
+use serde_json::{json, Value};
+
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+const INVALID_PARAMS: i64 = dex_rpc::INVALID_PARAMS_CODE as i64;
+
+/// Call `method` and return its result, failing on an error response
+async fn result(node: &TestNode, method: &str, params: Value) -> Value {
+    let response = node.rpc_request(method, params.clone()).await.unwrap();
+    assert!(response.get("error").is_none(), "{} {}: {}", method, params, response);
+    response["result"].clone()
+}
+
+/// Call `method` and return its error code, failing on a success response
+async fn error_code(node: &TestNode, method: &str, params: Value) -> i64 {
+    let response = node.rpc_request(method, params.clone()).await.unwrap();
+    response["error"]["code"]
+        .as_i64()
+        .unwrap_or_else(|| panic!("{} {} should fail: {}", method, params, response))
+}
+
+/// Assert `value` is a canonical quantity and return it
+fn quantity(value: &Value) -> u128 {
+    let s = value.as_str().unwrap_or_else(|| panic!("quantity is not a string: {}", value));
+    let digits = s.strip_prefix("0x").unwrap_or_else(|| panic!("missing 0x: {}", s));
+    assert!(!digits.is_empty(), "empty quantity");
+    assert!(digits == "0" || !digits.starts_with('0'), "leading zero: {}", s);
+    assert!(
+        digits.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)),
+        "not lowercase hex: {}",
+        s
+    );
+    u128::from_str_radix(digits, 16).unwrap()
+}
+
+/// Assert `value` is unformatted data of `len` bytes
+fn data(value: &Value, len: usize) {
+    let s = value.as_str().unwrap_or_else(|| panic!("data is not a string: {}", value));
+    let digits = s.strip_prefix("0x").unwrap_or_else(|| panic!("missing 0x: {}", s));
+    assert_eq!(digits.len(), len * 2, "wrong length: {}", s);
+    assert!(
+        digits.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)),
+        "not lowercase hex: {}",
+        s
+    );
+}
+
+fn assert_block(block: &Value) {
+    for field in ["number", "gasLimit", "gasUsed", "timestamp", "size", "difficulty"] {
+        quantity(&block[field]);
+    }
+    for field in ["hash", "parentHash", "stateRoot", "transactionsRoot", "receiptsRoot"] {
+        data(&block[field], 32);
+    }
+    data(&block["logsBloom"], 256);
+    data(&block["nonce"], 8);
+    data(&block["miner"], 20);
+    assert!(block["transactions"].is_array(), "{}", block);
+    assert!(block["uncles"].is_array(), "{}", block);
+}
+
+async fn validator() -> TestNode {
+    let node = TestNode::validator(DEFAULT_BLOCK_INTERVAL).await.unwrap();
+    node.wait_for_block(1, TIMEOUT).await.unwrap();
+    node
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_node_info_encodings() {
+    let node = validator().await;
+    let dev = format!("{:#x}", dev_address());
+
+    assert_eq!(quantity(&result(&node, "eth_chainId", json!([])).await), TEST_CHAIN_ID as u128);
+    assert!(quantity(&result(&node, "eth_blockNumber", json!([])).await) >= 1);
+    quantity(&result(&node, "eth_gasPrice", json!([])).await);
+    quantity(&result(&node, "eth_protocolVersion", json!([])).await);
+    assert_eq!(quantity(&result(&node, "eth_hashrate", json!([])).await), 0);
+    assert_eq!(result(&node, "eth_mining", json!([])).await, json!(true));
+    assert_eq!(result(&node, "eth_coinbase", json!([])).await, json!(dev));
+
+    assert_eq!(result(&node, "net_version", json!([])).await, json!(TEST_CHAIN_ID.to_string()));
+    assert_eq!(error_code(&node, "eth_net_version", json!([])).await, -32601);
+    assert!(result(&node, "net_listening", json!([])).await.is_boolean());
+    quantity(&result(&node, "net_peerCount", json!([])).await);
+    assert!(result(&node, "web3_clientVersion", json!([])).await.is_string());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_account_queries() {
+    let node = validator().await;
+    let dev = format!("{:#x}", dev_address());
+    let empty = format!("{:#x}", Address::repeat_byte(0x42));
+
+    let balance = result(&node, "eth_getBalance", json!([dev, "latest"])).await;
+    assert_eq!(quantity(&balance), DEV_BALANCE);
+    assert_eq!(balance, json!(format!("{:#x}", DEV_BALANCE)));
+    // The block parameter is optional and accepts any tag
+    assert_eq!(result(&node, "eth_getBalance", json!([dev])).await, balance);
+    assert_eq!(result(&node, "eth_getBalance", json!([dev, "0x1"])).await, balance);
+    // Address case is not significant
+    let upper = format!("0x{}", dev[2..].to_uppercase());
+    assert_eq!(result(&node, "eth_getBalance", json!([upper, "latest"])).await, balance);
+
+    assert_eq!(result(&node, "eth_getBalance", json!([empty, "latest"])).await, json!("0x0"));
+    let nonce = result(&node, "eth_getTransactionCount", json!([empty, "pending"])).await;
+    assert_eq!(nonce, json!("0x0"));
+    assert_eq!(result(&node, "eth_getCode", json!([empty, "latest"])).await, json!("0x"));
+    let slot = result(&node, "eth_getStorageAt", json!([empty, "0x0", "latest"])).await;
+    data(&slot, 32);
+
+    let transfer = json!({ "from": dev, "to": empty, "value": "0x1" });
+    assert!(quantity(&result(&node, "eth_estimateGas", json!([transfer])).await) >= 21_000);
+
+    // Malformed parameters
+    let short_address = json!(["0x1234", "latest"]);
+    assert_eq!(error_code(&node, "eth_getBalance", short_address).await, INVALID_PARAMS);
+    assert_eq!(error_code(&node, "eth_getBalance", json!([])).await, INVALID_PARAMS);
+    let bad_slot = json!([empty, "zz", "latest"]);
+    assert_eq!(error_code(&node, "eth_getStorageAt", bad_slot).await, INVALID_PARAMS);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_block_tags() {
+    let node = validator().await;
+
+    let genesis = result(&node, "eth_getBlockByNumber", json!(["0x0", false])).await;
+    assert_block(&genesis);
+    assert_eq!(genesis["number"], json!("0x0"));
+    assert_eq!(result(&node, "eth_getBlockByNumber", json!(["earliest", false])).await, genesis);
+    let by_hash = result(&node, "eth_getBlockByHash", json!([genesis["hash"], false])).await;
+    assert_eq!(by_hash, genesis);
+
+    for tag in ["latest", "pending", "safe", "finalized", "0x1"] {
+        let block = result(&node, "eth_getBlockByNumber", json!([tag, false])).await;
+        assert_block(&block);
+        assert!(quantity(&block["number"]) >= 1, "{}: {}", tag, block);
+    }
+
+    // Blocks that do not exist yet are null, not an error
+    let future = result(&node, "eth_getBlockByNumber", json!(["0xffffffff", false])).await;
+    assert_eq!(future, Value::Null);
+    let unknown = format!("{:#x}", B256::repeat_byte(0x99));
+    assert_eq!(result(&node, "eth_getBlockByHash", json!([unknown, false])).await, Value::Null);
+
+    for tag in ["0xzz", "12", "0x", "newest"] {
+        let code = error_code(&node, "eth_getBlockByNumber", json!([tag, false])).await;
+        assert_eq!(code, INVALID_PARAMS, "{}", tag);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_transaction_lifecycle() {
+    let node = validator().await;
+    let recipient = Address::repeat_byte(0x42);
+    let tx = sign_transaction(
+        TxLegacy {
+            chain_id: Some(TEST_CHAIN_ID),
+            nonce: 0,
+            gas_price: dex_rpc::BASE_FEE_PER_GAS as u128,
+            gas_limit: 21_000,
+            to: TxKind::Call(recipient),
+            value: U256::from(1_000),
+            ..Default::default()
+        },
+        &dev_secret_key(),
+    );
+    let raw = format!("0x{}", hex::encode(alloy_rlp::encode(&tx)));
+
+    let hash = result(&node, "eth_sendRawTransaction", json!([raw])).await;
+    data(&hash, 32);
+    assert_eq!(hash, json!(format!("{:#x}", tx.tx_hash())));
+
+    let receipt = result(&node, "dex_waitForTransaction", json!([hash, 10_000])).await;
+    assert_eq!(result(&node, "eth_getTransactionReceipt", json!([hash])).await, receipt);
+    assert_eq!(receipt["transactionHash"], hash);
+    assert_eq!(receipt["status"], json!("0x1"));
+    assert_eq!(receipt["type"], json!("0x0"));
+    assert_eq!(quantity(&receipt["gasUsed"]), 21_000);
+    for field in ["transactionIndex", "blockNumber", "cumulativeGasUsed", "effectiveGasPrice"] {
+        quantity(&receipt[field]);
+    }
+    data(&receipt["blockHash"], 32);
+    data(&receipt["logsBloom"], 256);
+    assert_eq!(receipt["to"], json!(format!("{:#x}", recipient)));
+    assert_eq!(receipt["contractAddress"], Value::Null);
+    assert_eq!(receipt["logs"], json!([]));
+
+    let block_number = receipt["blockNumber"].clone();
+    let block = result(&node, "eth_getBlockByNumber", json!([block_number, false])).await;
+    assert!(block["transactions"].as_array().unwrap().contains(&hash), "{}", block);
+
+    let dev = format!("{:#x}", dev_address());
+    let nonce = result(&node, "eth_getTransactionCount", json!([dev, "latest"])).await;
+    assert_eq!(nonce, json!("0x1"));
+
+    let unknown = format!("{:#x}", B256::repeat_byte(0x99));
+    let missing = result(&node, "eth_getTransactionReceipt", json!([unknown])).await;
+    assert_eq!(missing, Value::Null);
+
+    // Undecodable and replayed transactions are rejected
+    error_code(&node, "eth_sendRawTransaction", json!(["0x1234"])).await;
+    error_code(&node, "eth_sendRawTransaction", json!([raw])).await;
+}
@@ -0,0 +1,23 @@
+#!/bin/bash
+# Signed DexVM REST counter requests
+# DexVM REST 计数器操作签名
+#
+# Counter increments/decrements must carry an EIP-191 signature from the
+# counter owner over "DexVM <op> <amount> nonce <nonce> chain <chain_id>".
+# Requires foundry's `cast`.
+
+# Default: Hardhat test account #0 (0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266)
+SIGNER_KEY="${SIGNER_KEY:-0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80}"
+DEXVM_CHAIN_ID="${DEXVM_CHAIN_ID:-13337}"
+
+# Print a signed JSON request body: signed_counter_body <increment|decrement> <amount>
+# Nonces only need to increase per address, so a nanosecond timestamp is used.
+signed_counter_body() {
+    local op="$1" amount="$2"
+    local nonce
+    nonce=$(date +%s%N)
+    local signature
+    signature=$(cast wallet sign --private-key "$SIGNER_KEY" \
+        "DexVM $op $amount nonce $nonce chain $DEXVM_CHAIN_ID")
+    echo "{\"amount\": $amount, \"nonce\": $nonce, \"signature\": \"$signature\"}"
+}
@@ -15,6 +15,8 @@ PROJECT_DIR="$(dirname "$SCRIPT_DIR")"
 DATADIR="${DATADIR:-$PROJECT_DIR/data}"
 GENESIS="${GENESIS:-$PROJECT_DIR/genesis.json}"
 P2P_KEY_FILE="${P2P_KEY_FILE:-$PROJECT_DIR/validator_p2p.key}"
+# Chain id of $GENESIS; the node keeps its data under $DATADIR/$CHAIN_ID
+CHAIN_ID="${CHAIN_ID:-13337}"
 EVM_RPC_PORT="${EVM_RPC_PORT:-8545}"
 DEXVM_PORT="${DEXVM_PORT:-9845}"
 P2P_PORT="${P2P_PORT:-30303}"
@@ -45,20 +47,21 @@ if [ ! -f "./target/release/dex-reth" ]; then
     cargo build --release
 fi
 
-# Create data directory if it doesn't exist
-mkdir -p "$DATADIR"
+# Create chain data directory if it doesn't exist
+CHAIN_DIR="$DATADIR/$CHAIN_ID"
+mkdir -p "$CHAIN_DIR"
 
 # Copy P2P key to data directory if exists
 if [ -f "$P2P_KEY_FILE" ]; then
-    cp "$P2P_KEY_FILE" "$DATADIR/p2p_key"
-    chmod 600 "$DATADIR/p2p_key"
+    cp "$P2P_KEY_FILE" "$CHAIN_DIR/p2p_key"
+    chmod 600 "$CHAIN_DIR/p2p_key"
     echo "Using fixed P2P key from: $P2P_KEY_FILE"
 else
     echo "Warning: P2P key file not found at $P2P_KEY_FILE"
     echo "A new P2P key will be generated."
     echo ""
     echo "To fix the P2P key, save it after first run:"
-    echo "  cp $DATADIR/p2p_key $P2P_KEY_FILE"
+    echo "  cp $CHAIN_DIR/p2p_key $P2P_KEY_FILE"
 fi
 
 echo ""
@@ -4,6 +4,9 @@
 
 set -e
 
+# Signed counter request helper
+source "$(dirname "${BASH_SOURCE[0]}")/lib/dexvm_sign.sh"
+
 ADDR="0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
 BASE_URL="${DEXVM_URL:-http://localhost:9845}"
 
@@ -34,7 +37,7 @@ echo ""
 echo "3. Incrementing counter by 10..."
 INC_RESULT=$(curl -s -X POST "$BASE_URL/api/v1/counter/$ADDR/increment" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 10}' 2>/dev/null || echo '{"error": "failed"}')
+    -d "$(signed_counter_body increment 10)" 2>/dev/null || echo '{"error": "failed"}')
 echo "   Response: $INC_RESULT"
 echo ""
 
@@ -48,7 +51,7 @@ echo ""
 echo "5. Decrementing counter by 3..."
 DEC_RESULT=$(curl -s -X POST "$BASE_URL/api/v1/counter/$ADDR/decrement" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 3}' 2>/dev/null || echo '{"error": "failed"}')
+    -d "$(signed_counter_body decrement 3)" 2>/dev/null || echo '{"error": "failed"}')
 echo "   Response: $DEC_RESULT"
 echo ""
 
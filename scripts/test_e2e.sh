@@ -4,6 +4,9 @@
 
 set -e
 
+# Signed counter request helper
+source "$(dirname "${BASH_SOURCE[0]}")/lib/dexvm_sign.sh"
+
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 PROJECT_DIR="$(dirname "$SCRIPT_DIR")"
 
@@ -136,7 +139,7 @@ fi
 echo "Testing counter increment..."
 INC_RESULT=$(curl -s -X POST "$DEXVM_URL/api/v1/counter/$ADDR/increment" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 5}' 2>/dev/null)
+    -d "$(signed_counter_body increment 5)" 2>/dev/null)
 if [ -n "$INC_RESULT" ]; then
     echo -e "  ${GREEN}PASS${NC}: Counter increment successful"
     ((pass_count++))
@@ -149,7 +152,7 @@ fi
 echo "Testing counter decrement..."
 DEC_RESULT=$(curl -s -X POST "$DEXVM_URL/api/v1/counter/$ADDR/decrement" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 2}' 2>/dev/null)
+    -d "$(signed_counter_body decrement 2)" 2>/dev/null)
 if [ -n "$DEC_RESULT" ]; then
     echo -e "  ${GREEN}PASS${NC}: Counter decrement successful"
     ((pass_count++))
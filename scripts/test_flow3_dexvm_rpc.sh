@@ -12,16 +12,23 @@
 # DexVM REST API:
 # - GET  /health                              - 健康检查
 # - GET  /api/v1/counter/:address             - 查询计数器
-# - POST /api/v1/counter/:address/increment   - 增加计数器
-# - POST /api/v1/counter/:address/decrement   - 减少计数器
+# - POST /api/v1/counter/:address/increment   - 增加计数器 (签名，进入交易池)
+# - POST /api/v1/counter/:address/decrement   - 减少计数器 (签名，进入交易池)
+#
+# 计数器操作在下一个区块中执行，节点需要开启共识 (--enable-consensus)
 # - GET  /api/v1/state-root                   - 获取状态根
 #
 
 set -e
 
+# Signed counter request helper
+source "$(dirname "${BASH_SOURCE[0]}")/lib/dexvm_sign.sh"
+
 # 配置
 DEXVM_URL="${DEXVM_URL:-http://127.0.0.1:9845}"
 TEST_ADDRESS="${TEST_ADDRESS:-0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266}"
+# 等待操作被打包的时间 (秒)
+BLOCK_WAIT="${BLOCK_WAIT:-3}"
 
 echo "=============================================="
 echo "  流程 3: DexVM RPC 直接操作计数器"
@@ -55,10 +62,11 @@ echo ""
 echo "=== 3. 增加计数器 (+10) ==="
 INCREMENT_RESULT=$(curl -s -X POST "$DEXVM_URL/api/v1/counter/$TEST_ADDRESS/increment" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 10}')
+    -d "$(signed_counter_body increment 10)")
 echo "响应: $INCREMENT_RESULT"
-if echo "$INCREMENT_RESULT" | grep -q '"success":true'; then
-    echo "状态: 成功"
+if echo "$INCREMENT_RESULT" | grep -q '"tx_hash"'; then
+    echo "状态: 已进入交易池"
+    sleep "$BLOCK_WAIT"
 else
     echo "Warning: 操作可能失败"
 fi
@@ -76,7 +84,7 @@ echo ""
 echo "=== 5. 增加计数器 (+5) ==="
 INCREMENT_RESULT2=$(curl -s -X POST "$DEXVM_URL/api/v1/counter/$TEST_ADDRESS/increment" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 5}')
+    -d "$(signed_counter_body increment 5)")
 echo "响应: $INCREMENT_RESULT2"
 
 # 6. 减少计数器 (-3)
@@ -84,10 +92,11 @@ echo ""
 echo "=== 6. 减少计数器 (-3) ==="
 DECREMENT_RESULT=$(curl -s -X POST "$DEXVM_URL/api/v1/counter/$TEST_ADDRESS/decrement" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 3}')
+    -d "$(signed_counter_body decrement 3)")
 echo "响应: $DECREMENT_RESULT"
-if echo "$DECREMENT_RESULT" | grep -q '"success":true'; then
-    echo "状态: 成功"
+if echo "$DECREMENT_RESULT" | grep -q '"tx_hash"'; then
+    echo "状态: 已进入交易池"
+    sleep "$BLOCK_WAIT"
 else
     echo "Warning: 操作可能失败"
 fi
@@ -105,12 +114,16 @@ echo ""
 echo "=== 8. 测试减少溢出 (应该失败) ==="
 OVERFLOW_RESULT=$(curl -s -X POST "$DEXVM_URL/api/v1/counter/$TEST_ADDRESS/decrement" \
     -H "Content-Type: application/json" \
-    -d '{"amount": 999999}')
+    -d "$(signed_counter_body decrement 999999)")
 echo "响应: $OVERFLOW_RESULT"
-if echo "$OVERFLOW_RESULT" | grep -q '"success":false'; then
-    echo "状态: 正确拒绝 (计数器下溢)"
+# 下溢在打包执行时失败，计数器应保持不变
+sleep "$BLOCK_WAIT"
+AFTER_OVERFLOW=$(curl -s "$DEXVM_URL/api/v1/counter/$TEST_ADDRESS")
+AFTER_OVERFLOW_VALUE=$(echo $AFTER_OVERFLOW | grep -oE '"counter":[0-9]+' | cut -d':' -f2)
+if [ "$AFTER_OVERFLOW_VALUE" = "$FINAL_VALUE" ]; then
+    echo "状态: 正确拒绝 (计数器下溢，计数器未变化)"
 else
-    echo "Warning: 预期失败但操作成功?"
+    echo "Warning: 预期失败但计数器已变化?"
 fi
 
 # 9. 获取状态根
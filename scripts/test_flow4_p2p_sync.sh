@@ -12,6 +12,9 @@
 
 set -e
 
+# Signed counter request helper
+source "$(dirname "${BASH_SOURCE[0]}")/lib/dexvm_sign.sh"
+
 # 配置
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 PROJECT_DIR="$(dirname "$SCRIPT_DIR")"
@@ -183,7 +186,7 @@ echo "=== 8. 在验证者上发送 DexVM 交易 ==="
 for i in {1..3}; do
     RESULT=$(curl -s -X POST "http://127.0.0.1:$VALIDATOR_DEXVM_PORT/api/v1/counter/$TEST_ADDRESS/increment" \
         -H "Content-Type: application/json" \
-        -d '{"amount": 10}')
+        -d "$(signed_counter_body increment 10)")
     echo "交易 $i: $RESULT"
     sleep 1
 done